@@ -195,6 +195,10 @@ macro_rules! curve_impl {
                 self.perform_pairing(other)
             }
 
+            fn is_in_subgroup(&self) -> bool {
+                self.is_in_prime_order_subgroup()
+            }
+
             fn into_projective(&self) -> $projective {
                 (*self).into()
             }
@@ -950,6 +954,11 @@ pub mod g1 {
         //     self.into_projective()
         // }
 
+        // The cofactor of G1 is 1, so every point on the curve is in the subgroup
+        fn is_in_prime_order_subgroup(&self) -> bool {
+            true
+        }
+
         fn get_generator() -> Self {
             G1Affine {
                 x: super::super::fq::G1_GENERATOR_X,
@@ -1344,6 +1353,27 @@ pub mod g2 {
     }
 
     impl G2Affine {
+        /// The untwist-Frobenius-twist endomorphism, which acts on G2 as multiplication
+        /// by p, that is by 6u^2 modulo r.
+        fn psi(&self) -> Self {
+            let mut res = *self;
+            if !res.infinity {
+                res.x.frobenius_map(1);
+                res.x.mul_assign(&super::super::fq::FROBENIUS_COEFF_FQ6_C1[1]);
+                res.y.frobenius_map(1);
+                res.y.mul_assign(&super::super::fq::XI_TO_Q_MINUS_1_OVER_2);
+            }
+            res
+        }
+
+        // A point on the twist is in G2 if and only if psi(P) = [6u^2]P, which only takes a
+        // multiplication by a 128-bit scalar instead of one by r.
+        fn is_in_prime_order_subgroup(&self) -> bool {
+            let six_u_squared = 6 * (super::super::BN_U as u128) * (super::super::BN_U as u128);
+            let by = BitIterator::new([six_u_squared as u64, (six_u_squared >> 64) as u64]);
+            self.mul_bits(by).into_affine() == self.psi()
+        }
+
         fn scale_by_cofactor(&self) -> G2 {
             // G2 cofactor = 2p - n = 2q - r
             // 0x30644e72e131a029b85045b68181585e06ceecda572a2489345f2299c0f9fa8d
@@ -1506,6 +1536,43 @@ pub mod g2 {
         }
     }
 
+    #[test]
+    fn test_g2_subgroup_check() {
+        use SqrtField;
+
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        assert!(G2Affine::zero().is_in_subgroup());
+        for _ in 0..10 {
+            let p = G2::rand(&mut rng).into_affine();
+            assert!(p.is_in_subgroup());
+        }
+
+        // Points on the twist are almost never in the subgroup
+        let mut x = Fq2::one();
+        let mut outside = 0;
+        while outside < 10 {
+            let mut rhs = x;
+            rhs.square();
+            rhs.mul_assign(&x);
+            rhs.add_assign(&G2Affine::get_coeff_b());
+
+            if let Some(y) = rhs.sqrt() {
+                let p = G2Affine {
+                    x: x,
+                    y: y,
+                    infinity: false,
+                };
+                let in_subgroup = p.mul(Fr::char()).is_zero();
+                assert_eq!(p.is_in_subgroup(), in_subgroup);
+                if !in_subgroup {
+                    outside += 1;
+                }
+            }
+
+            x.add_assign(&Fq2::one());
+        }
+    }
+
     #[cfg(test)]
     use rand::{SeedableRng, XorShiftRng};
 
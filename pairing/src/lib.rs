@@ -220,6 +220,13 @@ pub trait CurveAffine:
     /// Converts this element into its affine representation.
     fn into_projective(&self) -> Self::Projective;
 
+    /// Determines if this element, which must be on the curve, is in the subgroup of
+    /// prime order. By default this multiplies by the order; curves override it with a
+    /// cheaper test where they have one.
+    fn is_in_subgroup(&self) -> bool {
+        self.mul(Self::Scalar::char()).is_zero()
+    }
+
     /// Converts this element into its compressed encoding, so long as it's not
     /// the point at infinity.
     fn into_compressed(&self) -> Self::Compressed {
@@ -0,0 +1,11 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use phase2::parameters::MPCParameters;
+
+fuzz_target!(|data: &[u8]| {
+    let fast = MPCParameters::read_fast(data, true, true).ok();
+    let slow = MPCParameters::read(data, true, true).ok();
+
+    // Both readers accept exactly the same files
+    assert!(fast == slow);
+});
@@ -0,0 +1,7 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use phase2::keypair::PublicKey;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = PublicKey::read(data);
+});
@@ -0,0 +1,33 @@
+#![no_main]
+use bellman_ce::pairing::bn256::{G1Uncompressed, G2Uncompressed};
+use byteorder::{BigEndian, ReadBytesExt};
+use libfuzzer_sys::fuzz_target;
+use phase2::utils::read_batch_par;
+
+// The first byte selects the group and the flags, the next four bytes are
+// the number of points, as in the length prefixes of the parameters.
+fuzz_target!(|data: &[u8]| {
+    let mut reader = data;
+    let (options, len) = match (reader.read_u8(), reader.read_u32::<BigEndian>()) {
+        (Ok(options), Ok(len)) => (options, len as usize),
+        _ => return,
+    };
+    let disallow_points_at_infinity = options & 1 != 0;
+    let checked = options & 2 != 0;
+
+    if options & 4 == 0 {
+        let _ = read_batch_par::<G1Uncompressed, _>(
+            &mut reader,
+            len,
+            disallow_points_at_infinity,
+            checked,
+        );
+    } else {
+        let _ = read_batch_par::<G2Uncompressed, _>(
+            &mut reader,
+            len,
+            disallow_points_at_infinity,
+            checked,
+        );
+    }
+});
@@ -0,0 +1,72 @@
+//! Writes well-formed inputs for every fuzz target to `corpus/<target>/`, so that
+//! fuzzing starts from the real formats instead of having to discover them.
+use bellman_ce::groth16::{Parameters, VerifyingKey};
+use bellman_ce::pairing::bn256::{Bn256, G1Affine, G2Affine};
+use bellman_ce::pairing::CurveAffine;
+use byteorder::{BigEndian, WriteBytesExt};
+use phase2::keypair::PublicKey;
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::sync::Arc;
+
+fn write_seed(target: &str, name: &str, bytes: &[u8]) {
+    let directory = format!("corpus/{}", target);
+    fs::create_dir_all(&directory).expect("unable to create the corpus directory");
+    File::create(format!("{}/{}", directory, name))
+        .and_then(|mut file| file.write_all(bytes))
+        .expect("unable to write a seed");
+}
+
+fn main() {
+    let g1 = G1Affine::one();
+    let g2 = G2Affine::one();
+
+    // Only the encoding matters here, so every point is a generator
+    let public_key = PublicKey {
+        delta_after: g1,
+        s: g1,
+        s_delta: g1,
+        r_delta: g2,
+        transcript: [7; 64],
+    };
+    let mut key = vec![];
+    public_key.write(&mut key).unwrap();
+    write_seed("public_key", "key", &key);
+
+    let params = Parameters::<Bn256> {
+        vk: VerifyingKey {
+            alpha_g1: g1,
+            beta_g1: g1,
+            beta_g2: g2,
+            gamma_g2: g2,
+            delta_g1: g1,
+            delta_g2: g2,
+            ic: vec![g1; 2],
+        },
+        h: Arc::new(vec![g1; 3]),
+        l: Arc::new(vec![g1; 2]),
+        a: Arc::new(vec![g1; 4]),
+        b_g1: Arc::new(vec![g1; 4]),
+        b_g2: Arc::new(vec![g2; 4]),
+    };
+    let mut bytes = vec![];
+    params.write(&mut bytes).unwrap();
+    bytes.extend_from_slice(&[3; 64]);
+    bytes.write_u32::<BigEndian>(1).unwrap();
+    bytes.extend_from_slice(&key);
+    write_seed("mpc_parameters", "params", &bytes);
+
+    for &(name, options, count) in &[("g1", 3u8, 3u32), ("g2", 7u8, 2u32)] {
+        let mut bytes = vec![options];
+        bytes.write_u32::<BigEndian>(count).unwrap();
+        for _ in 0..count {
+            if options & 4 == 0 {
+                bytes.extend_from_slice(g1.into_uncompressed().as_ref());
+            } else {
+                bytes.extend_from_slice(g2.into_uncompressed().as_ref());
+            }
+        }
+        write_seed("read_batch", name, &bytes);
+    }
+}
@@ -0,0 +1,142 @@
+//! Options of the random beacon that makes the last contribution.
+//!
+//! The beacon value is usually the hash of a block at a height that was
+//! announced before the block was mined. With `--beacon-height <n>
+//! --beacon-source <command>`, the value is checked against the block
+//! before anything is computed with it: the command is run with the
+//! height as its last argument and must print the hash of the block in
+//! hex, like `bitcoin-cli getblockhash` does.
+
+use std::process::Command;
+
+use hex;
+
+use super::errors::{self, Error, ErrorCategory};
+
+/// Bounds of the exponent of the number of hash iterations.
+pub const MIN_ITERATIONS_EXP: usize = 10;
+pub const MAX_ITERATIONS_EXP: usize = 63;
+
+/// Length of the beacon value, which is the hash of a block.
+pub const BEACON_HASH_LENGTH: usize = 32;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BeaconOpts {
+    pub hash: Vec<u8>,
+    pub num_iterations_exp: usize,
+    /// What the hash is checked against, if anything
+    pub source: Option<BeaconSource>,
+}
+
+/// The block the beacon value is the hash of, and the command that
+/// prints the hash of a block. The command is split at whitespace into
+/// the program and its arguments, it is not run by a shell.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BeaconSource {
+    pub height: u64,
+    pub command: String,
+}
+
+fn invalid<S: Into<String>>(message: S) -> Error {
+    Error::new(errors::INVALID_ARGUMENT, ErrorCategory::InvalidInput, message)
+}
+
+impl BeaconOpts {
+    /// Parses the beacon hash, in hex, and the exponent of the number of
+    /// hash iterations, as given on the command line.
+    pub fn parse(hash: &str, num_iterations_exp: &str) -> Result<Self, Error> {
+        let num_iterations_exp: usize = num_iterations_exp.parse()
+            .map_err(|_| invalid("could not parse the number of iterations exponent"))?;
+        if !(MIN_ITERATIONS_EXP..=MAX_ITERATIONS_EXP).contains(&num_iterations_exp) {
+            return Err(invalid(format!("the number of iterations exponent should be in [{}, {}] range", MIN_ITERATIONS_EXP, MAX_ITERATIONS_EXP)));
+        }
+        let hash = hex::decode(hash).map_err(|e| invalid(format!("the beacon hash should be in hexadecimal format: {}", e)))?;
+        if hash.len() != BEACON_HASH_LENGTH {
+            return Err(invalid(format!("the beacon hash should be {} bytes long, it is {}", BEACON_HASH_LENGTH, hash.len())));
+        }
+
+        Ok(BeaconOpts {
+            hash,
+            num_iterations_exp,
+            source: None,
+        })
+    }
+
+    /// Checks the hash against its source, if it has one.
+    pub fn check(&self) -> Result<(), Error> {
+        match self.source {
+            Some(ref source) => self.check_with(source.height, |_| source.lookup()),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that the hash is what `lookup` returns for `height`, for
+    /// library users that get block hashes another way.
+    pub fn check_with<F: FnOnce(u64) -> Result<Vec<u8>, Error>>(&self, height: u64, lookup: F) -> Result<(), Error> {
+        let expected = lookup(height)?;
+        if expected != self.hash {
+            return Err(invalid(format!("the beacon hash {} isn't the hash {} at height {}", hex::encode(&self.hash), hex::encode(&expected), height))
+                .with_hint("check that the beacon hash and --beacon-height are the announced ones"));
+        }
+
+        Ok(())
+    }
+}
+
+impl BeaconSource {
+    /// Runs the command and returns the hash it printed for the height.
+    pub fn lookup(&self) -> Result<Vec<u8>, Error> {
+        let mut words = self.command.split_whitespace();
+        let program = words.next().ok_or_else(|| invalid("the beacon source command is empty"))?;
+        let output = Command::new(program)
+            .args(words)
+            .arg(self.height.to_string())
+            .output()
+            .map_err(|e| Error::from(e).context(format!("unable to run the beacon source {}", program)))?;
+        if !output.status.success() {
+            return Err(invalid(format!("the beacon source {} failed with {}: {}", program, output.status, String::from_utf8_lossy(&output.stderr).trim())));
+        }
+
+        hex::decode(String::from_utf8_lossy(&output.stdout).trim())
+            .map_err(|e| invalid(format!("the beacon source {} didn't print a hash: {}", program, e)))
+    }
+}
+
+/// Removes `flag <value>` from the arguments and returns the value.
+fn take_value(args: &mut Vec<String>, flag: &str) -> Result<Option<String>, Error> {
+    let position = match args.iter().position(|arg| arg == flag) {
+        Some(position) => position,
+        None => return Ok(None),
+    };
+    if position + 1 >= args.len() {
+        return Err(invalid(format!("{} requires a value", flag)));
+    }
+    let value = args[position + 1].clone();
+    args.drain(position..position + 2);
+
+    Ok(Some(value))
+}
+
+/// Removes `--beacon-height <n>` and `--beacon-source <command>` from the
+/// arguments and returns the source they describe. Both are required if
+/// one is given.
+pub fn take_beacon_source(args: &mut Vec<String>) -> Result<Option<BeaconSource>, Error> {
+    match (take_value(args, "--beacon-height")?, take_value(args, "--beacon-source")?) {
+        (Some(height), Some(command)) => {
+            let height = height.parse().map_err(|_| invalid("could not parse the beacon height"))?;
+            Ok(Some(BeaconSource { height, command }))
+        }
+        (None, None) => Ok(None),
+        _ => Err(invalid("--beacon-height and --beacon-source must be given together")),
+    }
+}
+
+/// Fails if the arguments of `command`, which doesn't use a beacon, have
+/// beacon options, so that a contribution isn't mistaken for the beacon.
+pub fn reject_beacon(args: &[String], command: &str) -> Result<(), Error> {
+    match args.iter().find(|arg| arg.starts_with("--beacon-")) {
+        Some(flag) => Err(invalid(format!("{} doesn't take {}", command, flag))
+            .with_hint("the beacon contributes with the beacon command")),
+        None => Ok(()),
+    }
+}
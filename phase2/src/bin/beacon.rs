@@ -15,22 +15,23 @@ use std::fs::File;
 use std::fs::OpenOptions;
 
 use phase2::parameters::MPCParameters;
+use phase2::beacon::{take_beacon_source, BeaconOpts};
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    let source = take_beacon_source(&mut args).unwrap_or_else(|e| e.exit());
     if args.len() != 5 {
-        println!("Usage: \n<in_params.params> <in_beacon_hash> <in_num_iterations_exp> <out_params.params>");
+        println!("Usage: \n<in_params.params> <in_beacon_hash> <in_num_iterations_exp> <out_params.params> [--beacon-height <n> --beacon-source <command>]");
         std::process::exit(exitcode::USAGE);
     }
     let in_params_filename = &args[1];
-    let beacon_hash = &args[2];
-    let num_iterations_exp = &args[3].parse::<usize>().unwrap();
+    let mut beacon = BeaconOpts::parse(&args[2], &args[3]).unwrap_or_else(|e| e.exit());
+    beacon.source = source;
     let out_params_filename = &args[4];
 
-    if *num_iterations_exp < 10 || *num_iterations_exp > 63 {
-        println!("in_num_iterations_exp should be in [10, 63] range");
-        std::process::exit(exitcode::DATAERR);
-    }
+    // A beacon hash that isn't the hash of its block is rejected before
+    // anything is computed
+    beacon.check().unwrap_or_else(|e| e.exit());
 
     let disallow_points_at_infinity = false;
 
@@ -43,18 +44,9 @@ fn main() {
         use crypto::digest::Digest;
 
         // The hash used for the beacon
-        let hash_result = hex::decode(beacon_hash);
-        if hash_result.is_err() {
-            println!("Beacon hash should be in hexadecimal format");
-            std::process::exit(exitcode::DATAERR);
-        }
-        let mut cur_hash = hash_result.unwrap();
-        if cur_hash.len() != 32 {
-            println!("Beacon hash should be 32 bytes long");
-            std::process::exit(exitcode::DATAERR);
-        }
+        let mut cur_hash = beacon.hash.clone();
         // Performs 2^n hash iterations over it
-        let n: usize = *num_iterations_exp;
+        let n: usize = beacon.num_iterations_exp;
 
         for i in 0..(1u64<<n) {
             // Print 1024 of the interstitial states
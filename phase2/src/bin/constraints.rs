@@ -0,0 +1,62 @@
+extern crate bellman_ce;
+extern crate phase2;
+extern crate exitcode;
+#[macro_use]
+extern crate serde_json;
+
+use bellman_ce::pairing::bn256::Bn256;
+use phase2::parameters::{circuit_size, MAX_DOMAIN_EXP};
+use phase2::circom_circuit::circuit_from_file;
+use phase2::errors::{self, Error, ErrorCategory};
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut args: Vec<String> = std::env::args().collect();
+    let json = match args.iter().position(|arg| arg == "--json") {
+        Some(position) => {
+            args.remove(position);
+            true
+        }
+        None => false,
+    };
+    if args.len() != 2 {
+        println!("Usage: \n<in_circuit.json|.r1cs> [--json]");
+        std::process::exit(exitcode::USAGE);
+    }
+    let circuit_filename = &args[1];
+
+    let size = circuit_size(circuit_from_file::<Bn256>(circuit_filename)).map_err(|e| {
+        Error::new(errors::INVALID_PARAMETERS, ErrorCategory::InvalidInput, format!("unable to synthesize the circuit: {}", e))
+    })?;
+
+    if json {
+        let json = json!({
+            "num_inputs": size.num_inputs,
+            "num_aux": size.num_aux,
+            "num_constraints": size.num_constraints,
+            "domain_size": size.domain_size,
+            "domain_exp": size.domain_exp,
+            "fits_phase1": size.fits_phase1(),
+        });
+        println!("{:#}", json);
+        return Ok(());
+    }
+
+    println!("Circuit {}", circuit_filename);
+    println!("Public inputs: {} (with the constant one)", size.num_inputs);
+    println!("Witness variables: {}", size.num_aux);
+    println!("Constraints: {}", size.num_constraints);
+    println!("Domain: 2^{} = {} (the constraints and one per public input)", size.domain_exp, size.domain_size);
+    if size.fits_phase1() {
+        println!("Phase 1: power {} or more, radix file phase1radix2m{}", size.domain_exp, size.domain_exp);
+    } else {
+        println!("Phase 1: none, the Powers of Tau ceremony supports domains of up to 2^{}", MAX_DOMAIN_EXP);
+    }
+
+    Ok(())
+}
@@ -13,11 +13,19 @@ use std::fs::File;
 use std::fs::OpenOptions;
 
 use phase2::parameters::MPCParameters;
+use phase2::radix::DEFAULT_BATCH_SIZE;
+use phase2::beacon::reject_beacon;
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    // A beacon hash would otherwise be taken as the entropy
+    reject_beacon(&args, "contribute").unwrap_or_else(|e| e.exit());
+    // With --in-place the input is copied to the output and only the
+    // parts that change are rewritten, without loading all of it
+    let in_place = args.iter().any(|arg| arg == "--in-place");
+    args.retain(|arg| arg != "--in-place");
     if args.len() != 4 && args.len() != 6 {
-        println!("Usage: \n<in_params.params> <out_params.params> <in_str_entropy>");
+        println!("Usage: \n<in_params.params> <out_params.params> <in_str_entropy> [--in-place]");
         std::process::exit(exitcode::USAGE);
     }
     if args.len() == 6 && args[4] != "-v" {
@@ -67,13 +75,6 @@ fn main() {
         ChaChaRng::from_seed(&seed)
     };
 
-    let reader = OpenOptions::new()
-                            .read(true)
-                            .open(in_params_filename)
-                            .expect("unable to open.");
-    let mut params = MPCParameters::read(reader, disallow_points_at_infinity, true).expect("unable to read params");
-
-    println!("Contributing to {}...", in_params_filename);
     let mut progress_update_interval: u32 = 0;
     if print_progress {
         let parsed = args[5].parse::<u32>();
@@ -81,6 +82,33 @@ fn main() {
             progress_update_interval = parsed.unwrap();
         }
     }
+
+    if in_place {
+        println!("Copying {} to {}...", in_params_filename, out_params_filename);
+        std::fs::copy(in_params_filename, out_params_filename).expect("unable to copy parameters");
+        let mut file = OpenOptions::new()
+                                .read(true)
+                                .write(true)
+                                .open(out_params_filename)
+                                .expect("unable to open.");
+
+        println!("Contributing to {} in place...", out_params_filename);
+        let hash = phase2::in_place::contribute(&mut rng, &mut file, DEFAULT_BATCH_SIZE, &progress_update_interval)
+            .expect("failed to contribute to parameters");
+        println!("Contribution hash: 0x{:02x}", hash.iter().format(""));
+        if print_progress {
+            println!("wrote");
+        }
+        return;
+    }
+
+    let reader = OpenOptions::new()
+                            .read(true)
+                            .open(in_params_filename)
+                            .expect("unable to open.");
+    let mut params = MPCParameters::read(reader, disallow_points_at_infinity, true).expect("unable to read params");
+
+    println!("Contributing to {}...", in_params_filename);
     let hash = params.contribute(&mut rng, &progress_update_interval);
     println!("Contribution hash: 0x{:02x}", hash.iter().format(""));
 
@@ -4,13 +4,14 @@ extern crate exitcode;
 use phase2::circom_circuit::{
     proving_key_json_file,
     verification_key_json_file,
+    verification_key_gnark_json_file,
     load_params_file
 };
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
-    if args.len() != 4 {
-        println!("Usage: \n<in_params.params> <out_vk.json> <out_pk.json>");
+    if args.len() != 4 && args.len() != 5 {
+        println!("Usage: \n<in_params.params> <out_vk.json> <out_pk.json> [<out_vk_gnark.json>]");
         std::process::exit(exitcode::USAGE);
     }
     let params_filename = &args[1];
@@ -21,4 +22,8 @@ fn main() {
     proving_key_json_file(&params, pk_filename).unwrap();
     verification_key_json_file(&params, vk_filename).unwrap();
     println!("Created {} and {}.", pk_filename, vk_filename);
+    if let Some(gnark_vk_filename) = args.get(4) {
+        verification_key_gnark_json_file(&params, gnark_vk_filename).unwrap();
+        println!("Created {}.", gnark_vk_filename);
+    }
 }
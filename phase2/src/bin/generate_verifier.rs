@@ -3,18 +3,22 @@ extern crate exitcode;
 
 use phase2::circom_circuit::{
     load_params_file,
-    create_verifier_sol_file
+    create_verifier_sol_file,
+    create_verifier_sol_file_from_template
 };
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
-    if args.len() != 3 {
-        println!("Usage: \n<params> <out_contract.sol>");
+    if args.len() != 3 && args.len() != 4 {
+        println!("Usage: \n<params> <out_contract.sol> [<template.sol>]");
         std::process::exit(exitcode::USAGE);
     }
     let params_filename = &args[1];
     let verifier_filename = &args[2];
     let params = load_params_file(params_filename);
-    create_verifier_sol_file(&params, verifier_filename).unwrap();
+    match args.get(3) {
+        Some(template_filename) => create_verifier_sol_file_from_template(&params, template_filename, verifier_filename).unwrap(),
+        None => create_verifier_sol_file(&params, verifier_filename).unwrap(),
+    }
     println!("Created {}", verifier_filename);
-}
\ No newline at end of file
+}
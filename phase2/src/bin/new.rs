@@ -1,15 +1,40 @@
 extern crate rand;
+extern crate bellman_ce;
 extern crate phase2;
 extern crate exitcode;
 
 use std::fs::File;
 use phase2::parameters::MPCParameters;
-use phase2::circom_circuit::circuit_from_json_file;
+use phase2::circom_circuit::circuit_from_file;
+use phase2::errors::{self, Error, ErrorCategory};
+use bellman_ce::SynthesisError;
+
+/// Removes `--memory-budget <MiB>` from the arguments and returns the budget in bytes
+fn take_memory_budget(args: &mut Vec<String>) -> Option<usize> {
+    let position = args.iter().position(|arg| arg == "--memory-budget")?;
+    let budget = match args.get(position + 1).and_then(|budget| budget.parse::<usize>().ok()) {
+        Some(budget) => budget,
+        None => {
+            println!("--memory-budget requires a number of MiB");
+            std::process::exit(exitcode::USAGE);
+        }
+    };
+    args.drain(position..position + 2);
+
+    Some(budget << 20)
+}
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut args: Vec<String> = std::env::args().collect();
+    let memory_budget = take_memory_budget(&mut args);
     if args.len() != 4 {
-        println!("Usage: \n<in_circuit.json> <out_params.params> <path/to/phase1radix>");
+        println!("Usage: \n<in_circuit.json|.r1cs> <out_params.params> <path/to/phase1radix> [--memory-budget <MiB>]");
         std::process::exit(exitcode::USAGE);
     }
     let circuit_filename = &args[1];
@@ -21,11 +46,28 @@ fn main() {
     // Import the circuit and create the initial parameters using phase 1
     println!("Creating initial parameters for {}...", circuit_filename);
     let params = {
-        let c = circuit_from_json_file(&circuit_filename);
-        MPCParameters::new(c, should_filter_points_at_infinity, radix_directory).unwrap()
+        let c = circuit_from_file(circuit_filename);
+        match memory_budget {
+            Some(memory_budget) => MPCParameters::new_with_memory_budget(
+                c,
+                should_filter_points_at_infinity,
+                radix_directory,
+                memory_budget,
+                |plan| println!("{}", plan)
+            ),
+            None => MPCParameters::new(c, should_filter_points_at_infinity, radix_directory)
+        }.map_err(|e| match e {
+            SynthesisError::IoError(e) => Error::from(e).context("unable to create initial params")
+                .with_hint("prepare the phase 1 radix file of the domain of the circuit with prepare_phase2"),
+            SynthesisError::PolynomialDegreeTooLarge => Error::new(errors::INVALID_PARAMETERS, ErrorCategory::InvalidInput, "the circuit has more than 2^28 constraints")
+                .with_hint("the powers of tau of phase 1 support circuits of up to 2^28 constraints"),
+            e => Error::new(errors::INVALID_PARAMETERS, ErrorCategory::InvalidInput, format!("unable to synthesize the circuit: {}", e)),
+        })?
     };
 
     println!("Writing initial parameters to {}.", params_filename);
-    let mut f = File::create(params_filename).unwrap();
-    params.write(&mut f).expect("unable to write params");
+    let mut f = File::create(params_filename).map_err(|e| Error::from(e).context("unable to create params"))?;
+    params.write(&mut f).map_err(|e| Error::from(e).context("unable to write params"))?;
+
+    Ok(())
 }
@@ -14,14 +14,14 @@ use phase2::circom_circuit::{
     verify,
     create_rng,
     proof_to_json_file,
-    circuit_from_json_file,
+    circuit_from_file,
     witness_from_json_file
 };
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
     if args.len() != 6 {
-        println!("Usage: \n<circuit.json> <witness.json> <params> <proof.json> <public.json>");
+        println!("Usage: \n<circuit.json|.r1cs> <witness.json> <params> <proof.json> <public.json>");
         std::process::exit(exitcode::USAGE);
     }
     let circuit_filename = &args[1];
@@ -32,7 +32,7 @@ fn main() {
 
     let rng = create_rng();
     let params = load_params_file(params_filename);
-    let mut circuit = circuit_from_json_file(circuit_filename);
+    let mut circuit = circuit_from_file(circuit_filename);
     circuit.witness =  Some(witness_from_json_file::<Bn256>(witness_filename));
 
     println!("Proving...");
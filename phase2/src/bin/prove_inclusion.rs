@@ -0,0 +1,49 @@
+extern crate phase2;
+extern crate exitcode;
+
+use std::fs::{self, OpenOptions};
+
+use phase2::parameters::MPCParameters;
+use phase2::inclusion::parse_hash;
+use phase2::errors::{self, Error, ErrorCategory};
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 3 && args.len() != 4 {
+        println!("Usage: \n<in_params.params> <contribution_hash> [<out_statement>]");
+        std::process::exit(exitcode::USAGE);
+    }
+    let params_filename = &args[1];
+    let hash = parse_hash(&args[2]).ok_or_else(|| {
+        Error::new(errors::INVALID_ARGUMENT, ErrorCategory::InvalidInput, format!("`{}` is not a contribution hash", args[2]))
+            .with_hint("give the 64 bytes hash printed by contribute, in hex")
+    })?;
+
+    let disallow_points_at_infinity = false;
+
+    let reader = OpenOptions::new()
+                            .read(true)
+                            .open(params_filename)
+                            .map_err(|e| Error::from(e).context("unable to open params"))?;
+    let params = MPCParameters::read_fast(reader, disallow_points_at_infinity, true)
+                            .map_err(|e| Error::from(e).context("unable to read params"))?;
+
+    let statement = params.inclusion_statement(&hash).ok_or_else(|| {
+        Error::new(errors::MISSING_CONTRIBUTION, ErrorCategory::Verification, format!("the contribution is not part of the transcript of {}", params_filename))
+            .with_hint("check that the params are the final ones, and the hash the one printed by contribute")
+    })?;
+    print!("{}", statement);
+    if let Some(statement_filename) = args.get(3) {
+        fs::write(statement_filename, statement.to_string())
+            .map_err(|e| Error::from(e).context("unable to write the statement"))?;
+    }
+    println!("The contribution is at round {} of the {} contributions in {}. The params were not verified.", statement.round, params.contribution_count(), params_filename);
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+extern crate phase2;
+extern crate exitcode;
+
+use std::fs::OpenOptions;
+use std::io::{BufReader, BufWriter};
+
+use phase2::parameters::{MPCParameters, Compression};
+use phase2::errors::{self, Error, ErrorCategory};
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn parse_compression(arg: &str) -> Result<Compression, Error> {
+    match arg {
+        "compressed" => Ok(Compression::Compressed),
+        "uncompressed" => Ok(Compression::Uncompressed),
+        _ => Err(Error::new(errors::INVALID_ARGUMENT, ErrorCategory::InvalidInput, format!("unknown encoding {}", arg))
+            .with_hint("the encoding is compressed or uncompressed")),
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut args: Vec<String> = std::env::args().collect();
+    let checked = match args.iter().position(|arg| arg == "--check") {
+        Some(position) => {
+            args.remove(position);
+            true
+        }
+        None => false,
+    };
+    if args.len() != 5 {
+        println!("Usage: \n<in_params.params> <out_params.params> <compressed|uncompressed> <compressed|uncompressed> [--check]");
+        std::process::exit(exitcode::USAGE);
+    }
+    let in_params_filename = &args[1];
+    let out_params_filename = &args[2];
+    let from = parse_compression(&args[3])?;
+    let to = parse_compression(&args[4])?;
+
+    let reader = OpenOptions::new()
+                            .read(true)
+                            .open(in_params_filename)
+                            .map_err(|e| Error::from(e).context("unable to open params"))?;
+    let writer = OpenOptions::new()
+                            .read(false)
+                            .write(true)
+                            .create_new(true)
+                            .open(out_params_filename)
+                            .map_err(|e| Error::from(e).context("unable to create the new params"))?;
+
+    println!("Recompressing {} from {} to {}...", in_params_filename, args[3], args[4]);
+    MPCParameters::recompress(BufReader::new(reader), BufWriter::new(writer), from, to, checked)
+        .map_err(|e| Error::from(e).context("unable to recompress params"))?;
+    println!("Wrote {}.", out_params_filename);
+
+    Ok(())
+}
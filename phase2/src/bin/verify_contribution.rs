@@ -1,15 +1,42 @@
 extern crate phase2;
 extern crate exitcode;
+extern crate bellman_ce;
 
 use std::fs::OpenOptions;
 
+use bellman_ce::pairing::bn256::Bn256;
 use phase2::parameters::*;
-use phase2::circom_circuit::circuit_from_json_file;
+use phase2::circom_circuit::{
+    circuit_from_file,
+    smoke_test_parameters,
+    witness_from_json_file
+};
+use phase2::errors::{self, Error, ErrorCategory};
+
+/// Removes `--smoke-test <witness.json>` from the arguments and returns the witness file
+fn take_smoke_test_witness(args: &mut Vec<String>) -> Option<String> {
+    let position = args.iter().position(|arg| arg == "--smoke-test")?;
+    if position + 1 >= args.len() {
+        println!("--smoke-test requires a witness file");
+        std::process::exit(exitcode::USAGE);
+    }
+    let witness_filename = args[position + 1].clone();
+    args.drain(position..position + 2);
+
+    Some(witness_filename)
+}
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut args: Vec<String> = std::env::args().collect();
+    let smoke_test_witness = take_smoke_test_witness(&mut args);
     if args.len() != 5 {
-        println!("Usage: \n<in_circuit.json> <in_old_params.params> <in_new_params.params> <path/to/phase1radix>");
+        println!("Usage: \n<in_circuit.json|.r1cs> <in_old_params.params> <in_new_params.params> <path/to/phase1radix> [--smoke-test <witness.json>]");
         std::process::exit(exitcode::USAGE);
     }
     let circuit_filename = &args[1];
@@ -22,20 +49,56 @@ fn main() {
     let old_reader = OpenOptions::new()
                                 .read(true)
                                 .open(old_params_filename)
-                                .expect("unable to open old params");
-    let old_params = MPCParameters::read(old_reader, disallow_points_at_infinity, true).expect("unable to read old params");
+                                .map_err(|e| Error::from(e).context("unable to open old params"))?;
+    let old_params = MPCParameters::read_fast(old_reader, disallow_points_at_infinity, true)
+                                .map_err(|e| Error::from(e).context("unable to read old params"))?;
 
     let new_reader = OpenOptions::new()
                                 .read(true)
                                 .open(new_params_filename)
-                                .expect("unable to open new params");
-    let new_params = MPCParameters::read(new_reader, disallow_points_at_infinity, true).expect("unable to read new params");
+                                .map_err(|e| Error::from(e).context("unable to open new params"))?;
+    // A single contribution appends one public key to the old params
+    let expected_length = expected_size(&old_params.query_lengths(), old_params.contribution_count() + 1);
+    let length = new_reader.metadata()
+                                .map_err(|e| Error::from(e).context("unable to get filesystem metadata for new params"))?
+                                .len();
+    if length != expected_length {
+        return Err(Error::new(errors::INVALID_FILE_SIZE, ErrorCategory::InvalidInput, format!("The size of the new params should be {}, but it's {}", expected_length, length))
+            .with_hint("the new params must have exactly one contribution more than the old params, check that the file was fully transferred"));
+    }
+    let limits = ReadLimits::following(&old_params, 1);
+    let new_params = MPCParameters::read_fast_with_limits(new_reader, &limits, disallow_points_at_infinity, true)
+                                .map_err(|e| Error::from(e).context("unable to read new params"))?;
 
     println!("Checking contribution {}...", new_params_filename);
-    let contribution = verify_contribution(&old_params, &new_params).expect("should verify");
+    let contribution = verify_contribution(&old_params, &new_params).map_err(|_| {
+        Error::new(errors::INVALID_CONTRIBUTION, ErrorCategory::Verification, "contribution is not a valid transformation of the old params")
+            .with_hint("the new params must be computed from the old params with a single contribution")
+    })?;
 
     let should_filter_points_at_infinity = false;
-    let verification_result = new_params.verify(circuit_from_json_file(&circuit_filename), should_filter_points_at_infinity, radix_directory).unwrap();
-    assert!(contains_contribution(&verification_result, &contribution));
+    let verification_result = new_params.verify(circuit_from_file(circuit_filename), should_filter_points_at_infinity, radix_directory).map_err(|_| {
+        Error::new(errors::INVALID_CONTRIBUTION, ErrorCategory::Verification, "params are not valid for the circuit")
+            .with_hint("check that the circuit and the phase 1 radix files are the ones of this ceremony")
+    })?;
+    if !contains_contribution(&verification_result, &contribution) {
+        return Err(Error::new(errors::MISSING_CONTRIBUTION, ErrorCategory::Verification, "contribution is not part of the params transcript"));
+    }
     println!("Contribution {} verified.", new_params_filename);
+
+    if let Some(witness_filename) = smoke_test_witness {
+        println!("Proving and verifying the witness {} with the new params...", witness_filename);
+        let mut circuit = circuit_from_file(circuit_filename);
+        circuit.witness = Some(witness_from_json_file::<Bn256>(&witness_filename));
+        let valid = smoke_test_parameters(new_params.get_params(), circuit).map_err(|e| {
+            Error::new(errors::INVALID_PARAMETERS, ErrorCategory::Verification, format!("unable to prove the witness: {}", e))
+                .with_hint("check that the witness satisfies the circuit")
+        })?;
+        if !valid {
+            return Err(Error::new(errors::INVALID_PARAMETERS, ErrorCategory::Verification, "a proof created with the new params does not verify"));
+        }
+        println!("Smoke test passed.");
+    }
+
+    Ok(())
 }
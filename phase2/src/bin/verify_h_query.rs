@@ -0,0 +1,44 @@
+extern crate phase2;
+extern crate exitcode;
+
+use std::fs::OpenOptions;
+
+use phase2::parameters::MPCParameters;
+use phase2::radix::DEFAULT_BATCH_SIZE;
+use phase2::errors::{self, Error, ErrorCategory};
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 3 {
+        println!("Usage: \n<in_params.params> <path/to/phase1radix>");
+        std::process::exit(exitcode::USAGE);
+    }
+    let params_filename = &args[1];
+    let radix_directory = &args[2];
+
+    let disallow_points_at_infinity = false;
+
+    let reader = OpenOptions::new()
+                            .read(true)
+                            .open(params_filename)
+                            .map_err(|e| Error::from(e).context("unable to open params"))?;
+    let params = MPCParameters::read_fast(reader, disallow_points_at_infinity, true)
+                            .map_err(|e| Error::from(e).context("unable to read params"))?;
+
+    println!("Checking the H query of {} against the phase 1 radix files...", params_filename);
+    let consistent = params.verify_h_query(radix_directory, DEFAULT_BATCH_SIZE)
+                            .map_err(|e| Error::from(e).context("unable to read the phase 1 radix file"))?;
+    if !consistent {
+        return Err(Error::new(errors::INVALID_PARAMETERS, ErrorCategory::Verification, "H query is not consistent with the phase 1 radix file")
+            .with_hint("check that the phase 1 radix files are the ones of this ceremony"));
+    }
+    println!("H query of {} is consistent with phase 1.", params_filename);
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+extern crate phase2;
+extern crate exitcode;
+
+use std::fs::{self, OpenOptions};
+
+use phase2::parameters::MPCParameters;
+use phase2::inclusion::InclusionStatement;
+use phase2::errors::{self, Error, ErrorCategory};
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 3 {
+        println!("Usage: \n<in_params.params> <in_statement>");
+        std::process::exit(exitcode::USAGE);
+    }
+    let params_filename = &args[1];
+    let statement_filename = &args[2];
+
+    let text = fs::read_to_string(statement_filename)
+                            .map_err(|e| Error::from(e).context("unable to read the statement"))?;
+    let statement = InclusionStatement::parse(&text)
+                            .map_err(|e| e.context(format!("invalid statement {}", statement_filename)))?;
+
+    let disallow_points_at_infinity = false;
+
+    let reader = OpenOptions::new()
+                            .read(true)
+                            .open(params_filename)
+                            .map_err(|e| Error::from(e).context("unable to open params"))?;
+    let params = MPCParameters::read_fast(reader, disallow_points_at_infinity, true)
+                            .map_err(|e| Error::from(e).context("unable to read params"))?;
+
+    if !statement.holds_in(&params.contribution_hashes()) {
+        return Err(Error::new(errors::MISSING_CONTRIBUTION, ErrorCategory::Verification, format!("the statement doesn't hold for the transcript of {}", params_filename))
+            .with_hint("the contribution must be at the stated round, between the stated previous and next contributions"));
+    }
+    println!("The statement {} holds, the contribution is at round {} in {}.", statement_filename, statement.round, params_filename);
+
+    Ok(())
+}
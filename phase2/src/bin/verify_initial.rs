@@ -0,0 +1,61 @@
+extern crate phase2;
+extern crate exitcode;
+extern crate itertools;
+
+use std::fs::OpenOptions;
+use std::io::{self, BufReader};
+
+use itertools::Itertools;
+use phase2::parameters::MPCParameters;
+use phase2::circom_circuit::circuit_from_file;
+use phase2::hash_writer::HashWriter;
+use phase2::errors::{self, Error, ErrorCategory};
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 4 {
+        println!("Usage: \n<in_circuit.json|.r1cs> <in_initial_params.params> <path/to/phase1radix>");
+        std::process::exit(exitcode::USAGE);
+    }
+    let circuit_filename = &args[1];
+    let params_filename = &args[2];
+    let radix_directory = &args[3];
+
+    // Same as in `new`
+    let should_filter_points_at_infinity = false;
+
+    // The published params are hashed as they are, without decoding them
+    let reader = OpenOptions::new()
+                            .read(true)
+                            .open(params_filename)
+                            .map_err(|e| Error::from(e).context("unable to open initial params"))?;
+    let mut published = HashWriter::new(io::sink());
+    io::copy(&mut BufReader::new(reader), &mut published)
+        .map_err(|e| Error::from(e).context("unable to read initial params"))?;
+    let published = published.into_hash();
+
+    println!("Recreating initial parameters for {}...", circuit_filename);
+    let params = MPCParameters::new(circuit_from_file(circuit_filename), should_filter_points_at_infinity, radix_directory).map_err(|e| {
+        Error::new(errors::INVALID_PARAMETERS, ErrorCategory::InvalidInput, format!("unable to create initial params: {}", e))
+            .with_hint("check that the circuit and the phase 1 radix files are the ones of this ceremony")
+    })?;
+    let mut recreated = HashWriter::new(io::sink());
+    params.write(&mut recreated).map_err(|e| Error::from(e).context("unable to hash recreated params"))?;
+    let recreated = recreated.into_hash();
+
+    println!("Published params hash: 0x{:02x}", published.iter().format(""));
+    println!("Recreated params hash: 0x{:02x}", recreated.iter().format(""));
+    if published != recreated {
+        return Err(Error::new(errors::INVALID_PARAMETERS, ErrorCategory::Verification, "initial params are not the ones of the circuit and phase 1")
+            .with_hint("check that the circuit and the phase 1 radix files are the ones of this ceremony"));
+    }
+    println!("Initial params {} verified.", params_filename);
+
+    Ok(())
+}
@@ -4,12 +4,13 @@ extern crate rand;
 use std::str;
 use std::fs;
 use std::fs::{OpenOptions, File};
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 use std::collections::BTreeMap;
 use std::iter::repeat;
 use std::sync::Arc;
 use itertools::Itertools;
 use rand::{Rng, OsRng};
+use byteorder::{LittleEndian, ReadBytesExt};
 use parameters::MPCParameters;
 
 use bellman_ce::{
@@ -31,9 +32,12 @@ use bellman_ce::{
         CurveAffine,
         ff::{
             PrimeField,
+            PrimeFieldRepr,
         },
         bn256::{
             Bn256,
+            G1Affine,
+            G2Affine,
         }
     }
 };
@@ -98,6 +102,91 @@ struct VerifyingKeyJson {
     pub inputs_count: usize,
 }
 
+/// A point of G1 in the JSON encoding of gnark's `groth16.VerifyingKey` on BN254:
+/// field elements are decimal strings and the point at infinity is (0, 0).
+/// Fields are in the order of gnark's structs.
+#[derive(Serialize, Deserialize)]
+struct GnarkG1 {
+    #[serde(rename = "X")]
+    pub x: String,
+    #[serde(rename = "Y")]
+    pub y: String,
+}
+
+/// An element of the quadratic extension, `A0 + A1 * u`.
+#[derive(Serialize, Deserialize)]
+struct GnarkE2 {
+    #[serde(rename = "A0")]
+    pub a0: String,
+    #[serde(rename = "A1")]
+    pub a1: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GnarkG2 {
+    #[serde(rename = "X")]
+    pub x: GnarkE2,
+    #[serde(rename = "Y")]
+    pub y: GnarkE2,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GnarkVerifyingKeyG1 {
+    #[serde(rename = "Alpha")]
+    pub alpha: GnarkG1,
+    #[serde(rename = "Beta")]
+    pub beta: GnarkG1,
+    #[serde(rename = "Delta")]
+    pub delta: GnarkG1,
+    #[serde(rename = "K")]
+    pub k: Vec<GnarkG1>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GnarkVerifyingKeyG2 {
+    #[serde(rename = "Beta")]
+    pub beta: GnarkG2,
+    #[serde(rename = "Delta")]
+    pub delta: GnarkG2,
+    #[serde(rename = "Gamma")]
+    pub gamma: GnarkG2,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GnarkVerifyingKey {
+    #[serde(rename = "G1")]
+    pub g1: GnarkVerifyingKeyG1,
+    #[serde(rename = "G2")]
+    pub g2: GnarkVerifyingKeyG2,
+}
+
+fn gnark_g1(p: &G1Affine) -> GnarkG1 {
+    if p.is_zero() {
+        return GnarkG1 { x: String::from("0"), y: String::from("0") };
+    }
+    GnarkG1 {
+        x: repr_to_big(p.get_x().into_repr()),
+        y: repr_to_big(p.get_y().into_repr()),
+    }
+}
+
+fn gnark_g2(p: &G2Affine) -> GnarkG2 {
+    let zero = || GnarkE2 { a0: String::from("0"), a1: String::from("0") };
+    if p.is_zero() {
+        return GnarkG2 { x: zero(), y: zero() };
+    }
+    GnarkG2 {
+        x: GnarkE2 {
+            a0: repr_to_big(p.get_x().c0.into_repr()),
+            a1: repr_to_big(p.get_x().c1.into_repr()),
+        },
+        y: GnarkE2 {
+            a0: repr_to_big(p.get_y().c0.into_repr()),
+            a1: repr_to_big(p.get_y().c1.into_repr()),
+        },
+    }
+}
+
 #[derive(Clone)]
 pub struct CircomCircuit<E: Engine> {
     pub num_inputs: usize,
@@ -199,10 +288,29 @@ pub fn verify<E: Engine>(circuit: &CircomCircuit<E>, params: &Parameters<E>, pro
     );
 }
 
+/// End-to-end check of final parameters: proves the circuit with its
+/// witness and verifies the proof. Parameters that are structurally
+/// valid but unusable fail here instead of on the first real proof.
+#[cfg(not(feature = "wasm"))]
+pub fn smoke_test_parameters<E: Engine>(params: &Parameters<E>, circuit: CircomCircuit<E>) -> Result<bool, SynthesisError> {
+    if circuit.witness.is_none() {
+        return Err(SynthesisError::AssignmentMissing);
+    }
+    let proof = prove(circuit.clone(), params, create_rng())?;
+    verify(&circuit, params, &proof)
+}
+
 pub fn create_verifier_sol(params: &Parameters<Bn256>) -> String {
-    // TODO: use a simple template engine
     let bytes = include_bytes!("verifier_groth.sol");
-    let template = String::from_utf8_lossy(bytes);
+    create_verifier_sol_from_template(params, &String::from_utf8_lossy(bytes))
+}
+
+/// Fills a Solidity verifier template with the verifying key of `params`.
+/// The template uses the same placeholders as the built-in one:
+/// `<%vk_alfa1%>`, `<%vk_beta2%>`, `<%vk_gamma2%>`, `<%vk_delta2%>`,
+/// `<%vk_ic_length%>`, `<%vk_input_length%>` and `<%vk_ic_pts%>`.
+pub fn create_verifier_sol_from_template(params: &Parameters<Bn256>, template: &str) -> String {
+    // TODO: use a simple template engine
 
     let p1_to_str = |p: &<Bn256 as Engine>::G1Affine| {
         let x = repr_to_big(p.get_x().into_repr());
@@ -240,6 +348,11 @@ pub fn create_verifier_sol_file(params: &Parameters<Bn256>, filename: &str) -> s
     return fs::write(filename, create_verifier_sol(params).as_bytes());
 }
 
+pub fn create_verifier_sol_file_from_template(params: &Parameters<Bn256>, template_filename: &str, filename: &str) -> std::io::Result<()> {
+    let template = fs::read_to_string(template_filename)?;
+    fs::write(filename, create_verifier_sol_from_template(params, &template).as_bytes())
+}
+
 pub fn proof_to_json(proof: &Proof<Bn256>) -> Result<String, serde_json::error::Error> {
     return serde_json::to_string(&ProofJson {
         protocol: "groth".to_string(),
@@ -316,6 +429,29 @@ pub fn verification_key_json_file(params: &Parameters<Bn256>, filename: &str) ->
     return fs::write(filename, str.as_bytes());
 }
 
+/// Exports the verifying key in the JSON encoding of gnark, whose `K` are the `IC` of snarkjs.
+pub fn verification_key_gnark_json(params: &Parameters<Bn256>) -> Result<String, serde_json::error::Error> {
+    let verification_key = GnarkVerifyingKey {
+        g1: GnarkVerifyingKeyG1 {
+            alpha: gnark_g1(&params.vk.alpha_g1),
+            beta: gnark_g1(&params.vk.beta_g1),
+            delta: gnark_g1(&params.vk.delta_g1),
+            k: params.vk.ic.iter().map(gnark_g1).collect_vec(),
+        },
+        g2: GnarkVerifyingKeyG2 {
+            beta: gnark_g2(&params.vk.beta_g2),
+            delta: gnark_g2(&params.vk.delta_g2),
+            gamma: gnark_g2(&params.vk.gamma_g2),
+        },
+    };
+    serde_json::to_string(&verification_key)
+}
+
+pub fn verification_key_gnark_json_file(params: &Parameters<Bn256>, filename: &str) -> std::io::Result<()> {
+    let str = verification_key_gnark_json(params).map_err(std::io::Error::from)?;
+    fs::write(filename, str.as_bytes())
+}
+
 pub fn witness_from_json_file<E: Engine>(filename: &str) -> Vec<E::Fr> {
     let reader = OpenOptions::new()
         .read(true)
@@ -360,6 +496,272 @@ pub fn circuit_from_json<E: Engine, R: Read>(reader: R) -> CircomCircuit::<E> {
     };
 }
 
+/// Loads a circuit from a `.r1cs` file, the binary format of circom, or from the JSON export
+/// of any other file.
+pub fn circuit_from_file<E: Engine>(filename: &str) -> CircomCircuit::<E> {
+    if filename.ends_with(".r1cs") {
+        return circuit_from_r1cs_file(filename);
+    }
+    circuit_from_json_file(filename)
+}
+
+pub fn circuit_from_r1cs_file<E: Engine>(filename: &str) -> CircomCircuit::<E> {
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(filename)
+        .expect("unable to open.");
+    circuit_from_r1cs(io::BufReader::new(reader)).expect("unable to read the r1cs file")
+}
+
+/// Reads a circuit in the binary `.r1cs` format of circom:
+///
+/// "r1cs" | version (u32) | section count (u32) | for every section: type (u32) | size (u64) | content
+///
+/// with integers in little endian. The header section (type 1) has the size of field elements in
+/// bytes, the prime, the number of wires, of public outputs, public inputs and private inputs, of
+/// labels (u64) and of constraints. The constraint section (type 2) has the linear combinations
+/// A, B and C of every constraint, each as a number of terms followed by the wire (u32) and the
+/// coefficient of every term. Wires are numbered like the variables of the JSON export, and
+/// other sections, such as the labels of the wires, are skipped.
+pub fn circuit_from_r1cs<E: Engine, R: Read>(mut reader: R) -> io::Result<CircomCircuit::<E>> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != b"r1cs" {
+        return Err(invalid("not an r1cs file".to_string()));
+    }
+    let version = reader.read_u32::<LittleEndian>()?;
+    if version != 1 {
+        return Err(invalid(format!("unsupported r1cs version {}", version)));
+    }
+
+    // Sections may come in any order, but constraints can only be read with the header
+    let mut header = None;
+    let mut constraint_section = None;
+    let sections = reader.read_u32::<LittleEndian>()?;
+    for _ in 0..sections {
+        let section_type = reader.read_u32::<LittleEndian>()?;
+        let size = reader.read_u64::<LittleEndian>()?;
+        let mut section = (&mut reader).take(size);
+        match section_type {
+            1 => {
+                let field_size = section.read_u32::<LittleEndian>()? as usize;
+                let modulus = E::Fr::char();
+                if field_size != modulus.as_ref().len() * 8 {
+                    return Err(invalid(format!("field elements of {} bytes are not elements of this curve", field_size)));
+                }
+                let mut prime = E::Fr::char();
+                prime.read_le(&mut section)?;
+                if prime != modulus {
+                    return Err(invalid("the circuit is defined over the field of another curve".to_string()));
+                }
+                let wires = section.read_u32::<LittleEndian>()? as usize;
+                let public_outputs = section.read_u32::<LittleEndian>()? as usize;
+                let public_inputs = section.read_u32::<LittleEndian>()? as usize;
+                let _private_inputs = section.read_u32::<LittleEndian>()?;
+                let _labels = section.read_u64::<LittleEndian>()?;
+                let constraints = section.read_u32::<LittleEndian>()? as usize;
+                header = Some((wires, public_outputs + public_inputs + 1, constraints));
+            },
+            2 => {
+                let mut bytes = vec![];
+                section.read_to_end(&mut bytes)?;
+                constraint_section = Some(bytes);
+            },
+            _ => {},
+        }
+        // Skips what is left of the section
+        io::copy(&mut section, &mut io::sink())?;
+        if section.limit() != 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the r1cs file is truncated"));
+        }
+    }
+
+    let (wires, num_inputs, num_constraints) = header.ok_or_else(|| invalid("the r1cs file has no header".to_string()))?;
+    let constraint_section = constraint_section.ok_or_else(|| invalid("the r1cs file has no constraints".to_string()))?;
+    if num_inputs > wires {
+        return Err(invalid(format!("{} public variables in a circuit of {} wires", num_inputs, wires)));
+    }
+
+    let mut section = &constraint_section[..];
+    let read_lc = |section: &mut &[u8]| -> io::Result<Vec<(usize, E::Fr)>> {
+        let terms = section.read_u32::<LittleEndian>()? as usize;
+        let mut lc = Vec::with_capacity(terms.min(section.len()));
+        for _ in 0..terms {
+            let wire = section.read_u32::<LittleEndian>()? as usize;
+            if wire >= wires {
+                return Err(invalid(format!("wire {} of a circuit of {} wires", wire, wires)));
+            }
+            let mut repr = E::Fr::char();
+            repr.read_le(&mut *section)?;
+            let coeff = E::Fr::from_repr(repr).map_err(|e| invalid(format!("invalid coefficient: {}", e)))?;
+            lc.push((wire, coeff));
+        }
+        Ok(lc)
+    };
+    let mut constraints = Vec::with_capacity(num_constraints.min(constraint_section.len()));
+    for _ in 0..num_constraints {
+        let a = read_lc(&mut section)?;
+        let b = read_lc(&mut section)?;
+        let c = read_lc(&mut section)?;
+        constraints.push((a, b, c));
+    }
+
+    Ok(CircomCircuit {
+        num_inputs,
+        num_aux: wires - num_inputs,
+        num_constraints,
+        witness: None,
+        constraints,
+    })
+}
+
 pub fn create_rng() -> Box<dyn Rng> {
     return Box::new(OsRng::new().unwrap())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bellman_ce::pairing::bn256::Fr;
+    use byteorder::WriteBytesExt;
+
+    /// The terms of the constraints of the test circuit, out * 1 = in * private and
+    /// (5 + private) * 1 = in, over the wires 1, out, in and private.
+    fn constraints() -> Vec<[Vec<(u32, u64)>; 3]> {
+        vec![
+            [vec![(1, 1)], vec![(0, 1)], vec![(2, 1), (3, 1)]],
+            [vec![(0, 5), (3, 1)], vec![(0, 1)], vec![(2, 1)]],
+        ]
+    }
+
+    fn write_element(bytes: &mut Vec<u8>, repr: <Fr as PrimeField>::Repr) {
+        repr.write_le(bytes).unwrap();
+    }
+
+    fn header(wires: u32, prime: <Fr as PrimeField>::Repr) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.write_u32::<LittleEndian>(32).unwrap();
+        write_element(&mut bytes, prime);
+        for &count in &[wires, 1, 1, 1] {
+            bytes.write_u32::<LittleEndian>(count).unwrap();
+        }
+        bytes.write_u64::<LittleEndian>(wires as u64).unwrap();
+        bytes.write_u32::<LittleEndian>(constraints().len() as u32).unwrap();
+        bytes
+    }
+
+    fn constraint_section(constraints: &[[Vec<(u32, u64)>; 3]]) -> Vec<u8> {
+        let mut bytes = vec![];
+        for lc in constraints.iter().flat_map(|constraint| constraint.iter()) {
+            bytes.write_u32::<LittleEndian>(lc.len() as u32).unwrap();
+            for &(wire, coeff) in lc {
+                bytes.write_u32::<LittleEndian>(wire).unwrap();
+                write_element(&mut bytes, Fr::from_str(&coeff.to_string()).unwrap().into_repr());
+            }
+        }
+        bytes
+    }
+
+    fn r1cs(sections: &[(u32, Vec<u8>)]) -> Vec<u8> {
+        let mut bytes = b"r1cs".to_vec();
+        bytes.write_u32::<LittleEndian>(1).unwrap();
+        bytes.write_u32::<LittleEndian>(sections.len() as u32).unwrap();
+        for &(section_type, ref section) in sections {
+            bytes.write_u32::<LittleEndian>(section_type).unwrap();
+            bytes.write_u64::<LittleEndian>(section.len() as u64).unwrap();
+            bytes.extend_from_slice(section);
+        }
+        bytes
+    }
+
+    fn read(bytes: &[u8]) -> io::Result<CircomCircuit<Bn256>> {
+        circuit_from_r1cs::<Bn256, _>(bytes)
+    }
+
+    #[test]
+    fn test_r1cs() {
+        let expected = r1cs(&[(1, header(4, Fr::char())), (2, constraint_section(&constraints()))]);
+        let circuit = read(&expected).unwrap();
+        assert_eq!((circuit.num_inputs, circuit.num_aux, circuit.num_constraints), (3, 1, 2));
+
+        // The JSON export of the same circuit
+        let json = r#"{"nPubInputs": 1, "nOutputs": 1, "nVars": 4, "constraints": [
+            [{"1": "1"}, {"0": "1"}, {"2": "1", "3": "1"}],
+            [{"0": "5", "3": "1"}, {"0": "1"}, {"2": "1"}]
+        ]}"#;
+        let from_json = circuit_from_json::<Bn256, _>(json.as_bytes());
+        assert_eq!((circuit.num_inputs, circuit.num_aux), (from_json.num_inputs, from_json.num_aux));
+        assert!(circuit.constraints == from_json.constraints);
+
+        // Sections come in any order, and others are skipped
+        let reordered = r1cs(&[(3, vec![7; 40]), (2, constraint_section(&constraints())), (1, header(4, Fr::char()))]);
+        assert!(read(&reordered).unwrap().constraints == circuit.constraints);
+
+        // A `.r1cs` file is read as such, anything else as JSON
+        let path = std::env::temp_dir().join(format!("phase2_test_r1cs_{}.r1cs", std::process::id()));
+        fs::write(&path, &expected).unwrap();
+        let from_file = circuit_from_file::<Bn256>(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert!(from_file.constraints == circuit.constraints);
+    }
+
+    #[test]
+    fn test_invalid_r1cs() {
+        let constraints = constraint_section(&constraints());
+        let kind = |bytes: Vec<u8>| read(&bytes).err().expect("must be rejected").kind();
+
+        let mut magic = r1cs(&[(1, header(4, Fr::char())), (2, constraints.clone())]);
+        magic[0] = b'R';
+        assert_eq!(kind(magic), io::ErrorKind::InvalidData);
+
+        // The prime of another field
+        let mut prime = Fr::char();
+        prime.sub_noborrow(&Fr::from_str("2").unwrap().into_repr());
+        assert_eq!(kind(r1cs(&[(1, header(4, prime)), (2, constraints.clone())])), io::ErrorKind::InvalidData);
+
+        // Wire 3 in a circuit of 3 wires, and fewer wires than public variables
+        assert_eq!(kind(r1cs(&[(1, header(3, Fr::char())), (2, constraints.clone())])), io::ErrorKind::InvalidData);
+        assert_eq!(kind(r1cs(&[(1, header(2, Fr::char())), (2, constraints.clone())])), io::ErrorKind::InvalidData);
+
+        // A first coefficient, after the number of terms and the wire, equal to the prime
+        let mut non_canonical = constraints[..8].to_vec();
+        write_element(&mut non_canonical, Fr::char());
+        non_canonical.extend_from_slice(&constraints[8 + 32..]);
+        assert_eq!(kind(r1cs(&[(1, header(4, Fr::char())), (2, non_canonical)])), io::ErrorKind::InvalidData);
+
+        // Missing sections, and sections cut short
+        assert_eq!(kind(r1cs(&[(2, constraints.clone())])), io::ErrorKind::InvalidData);
+        assert_eq!(kind(r1cs(&[(1, header(4, Fr::char()))])), io::ErrorKind::InvalidData);
+        assert_eq!(kind(r1cs(&[(1, header(4, Fr::char())), (2, constraints[..100].to_vec())])), io::ErrorKind::UnexpectedEof);
+        let complete = r1cs(&[(1, header(4, Fr::char())), (2, constraints.clone())]);
+        assert_eq!(kind(complete[..complete.len() - 1].to_vec()), io::ErrorKind::UnexpectedEof);
+    }
+    #[test]
+    fn test_verification_key_gnark_json() {
+        let params = ::parameters::tests::test_params(0);
+        let vk = &params.get_params().vk;
+        let json = verification_key_gnark_json(params.get_params()).unwrap();
+
+        // The names and the order of the fields of gnark's structs
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let keys = |value: &serde_json::Value| value.as_object().unwrap().keys().cloned().collect_vec();
+        assert_eq!(keys(&value), vec!["G1", "G2"]);
+        assert_eq!(keys(&value["G1"]), vec!["Alpha", "Beta", "Delta", "K"]);
+        assert_eq!(keys(&value["G2"]), vec!["Beta", "Delta", "Gamma"]);
+        assert_eq!(keys(&value["G1"]["Alpha"]), vec!["X", "Y"]);
+        assert_eq!(keys(&value["G2"]["Beta"]["X"]), vec!["A0", "A1"]);
+        assert!(json.starts_with(r#"{"G1":{"Alpha":{"X":""#));
+
+        // And back
+        let key: GnarkVerifyingKey = serde_json::from_str(&json).unwrap();
+        assert_eq!(key.g1.k.len(), vk.ic.len());
+        assert_eq!(key.g1.alpha.x, repr_to_big(vk.alpha_g1.get_x().into_repr()));
+        assert_eq!(key.g2.gamma.y.a1, repr_to_big(vk.gamma_g2.get_y().c1.into_repr()));
+        assert_eq!(serde_json::to_string(&key).unwrap(), json);
+
+        let infinity = serde_json::to_string(&gnark_g2(&G2Affine::zero())).unwrap();
+        assert_eq!(infinity, r#"{"X":{"A0":"0","A1":"0"},"Y":{"A0":"0","A1":"0"}}"#);
+    }
+}
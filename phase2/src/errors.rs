@@ -0,0 +1,119 @@
+//! Errors reported by the command line tools, with the same codes, categories and exit
+//! codes as the ones of the powers of tau tools.
+
+use std::fmt;
+use std::io;
+
+pub const IO: u16 = 100;
+pub const FILE_NOT_FOUND: u16 = 101;
+pub const PERMISSION_DENIED: u16 = 102;
+pub const DISK_FULL: u16 = 103;
+pub const INVALID_FILE_SIZE: u16 = 200;
+pub const INVALID_ARGUMENT: u16 = 202;
+pub const INVALID_PARAMETERS: u16 = 203;
+pub const INVALID_CONTRIBUTION: u16 = 301;
+pub const MISSING_CONTRIBUTION: u16 = 302;
+pub const INTERNAL: u16 = 400;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ErrorCategory {
+    Io,
+    InvalidInput,
+    Verification,
+    Internal,
+}
+
+impl ErrorCategory {
+    /// Process exit code used for errors of this category.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorCategory::Io => exitcode::IOERR,
+            ErrorCategory::InvalidInput => exitcode::DATAERR,
+            ErrorCategory::Verification => exitcode::PROTOCOL,
+            ErrorCategory::Internal => exitcode::SOFTWARE,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorCategory::Io => write!(f, "IO"),
+            ErrorCategory::InvalidInput => write!(f, "InvalidInput"),
+            ErrorCategory::Verification => write!(f, "Verification"),
+            ErrorCategory::Internal => write!(f, "Internal"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub code: u16,
+    pub category: ErrorCategory,
+    pub message: String,
+    /// What the user can do about the error, if anything
+    pub hint: Option<&'static str>,
+}
+
+impl Error {
+    pub fn new<S: Into<String>>(code: u16, category: ErrorCategory, message: S) -> Self {
+        Error {
+            code,
+            category,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    pub fn with_hint(mut self, hint: &'static str) -> Self {
+        self.hint = Some(hint);
+        self
+    }
+
+    /// Prefixes the message with what was being done when the error happened.
+    pub fn context<S: AsRef<str>>(mut self, context: S) -> Self {
+        self.message = format!("{}: {}", context.as_ref(), self.message);
+        self
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.category.exit_code()
+    }
+
+    /// Reports the error and terminates the process with the exit code of its category.
+    pub fn exit(&self) -> ! {
+        eprintln!("{}", self);
+        ::std::process::exit(self.exit_code())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "error E{} ({}): {}", self.code, self.category, self.message)?;
+        if let Some(hint) = self.hint {
+            write!(f, "\nhint: {}", hint)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ::std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        let message = err.to_string();
+        match err.kind() {
+            io::ErrorKind::NotFound => Error::new(FILE_NOT_FOUND, ErrorCategory::Io, message)
+                .with_hint("check that the file exists and the path is spelled correctly"),
+            io::ErrorKind::PermissionDenied => Error::new(PERMISSION_DENIED, ErrorCategory::Io, message)
+                .with_hint("check the permissions of the file and directory"),
+            io::ErrorKind::StorageFull => Error::new(DISK_FULL, ErrorCategory::Io, message)
+                .with_hint("free up disk space and run the command again"),
+            // Malformed parameters surface as invalid data while reading them
+            io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => Error::new(INVALID_PARAMETERS, ErrorCategory::InvalidInput, message)
+                .with_hint("the file is corrupted or was not produced by this ceremony"),
+            _ => Error::new(IO, ErrorCategory::Io, message),
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Contributing to a parameters file in place. A contribution only
+//! changes delta, the H and L queries and the list of contributions,
+//! so only those are read and rewritten, in batches; the A and B
+//! queries, most of a typical file, are never touched. Work on a
+//! copy of the previous parameters, as an interrupted contribution
+//! leaves the file unusable.
+
+extern crate bellman_ce;
+extern crate rand;
+extern crate byteorder;
+
+use std::fs::File;
+use std::io::{
+    self,
+    BufReader,
+    BufWriter,
+    Read,
+    Seek,
+    SeekFrom,
+    Write,
+};
+
+use byteorder::{
+    BigEndian,
+    ReadBytesExt,
+    WriteBytesExt,
+};
+
+use bellman_ce::pairing::{
+    ff::Field,
+    EncodedPoint,
+    CurveAffine,
+    CurveProjective,
+    bn256::{
+        G1Affine,
+        G1Uncompressed,
+        G2Affine,
+        G2Uncompressed
+    }
+};
+
+use rand::Rng;
+
+use super::hash_writer::HashWriter;
+use super::keypair::{
+    clear_scalar,
+    PublicKey,
+    SecretGuard,
+};
+use super::parameters::{
+    batch_exp,
+    keypair_from_transcript,
+};
+use super::utils::read_batch_par;
+
+/// Offsets of the parts of a parameters file that a contribution
+/// changes, as written by `MPCParameters::write`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamsLayout {
+    pub delta_g1: u64,
+    pub delta_g2: u64,
+    /// Offset of the first point and number of points of the H query
+    pub h: (u64, usize),
+    /// Offset of the first point and number of points of the L query
+    pub l: (u64, usize),
+    pub cs_hash: u64,
+    /// Offset of the number of contributions, which are followed by
+    /// the public keys
+    pub contributions: u64,
+}
+
+impl ParamsLayout {
+    /// Finds the parts in `file` by reading only the lengths of the
+    /// queries.
+    pub fn read(file: &mut File) -> io::Result<ParamsLayout> {
+        let g1 = G1Uncompressed::size() as u64;
+        let g2 = G2Uncompressed::size() as u64;
+
+        // alpha_g1, beta_g1, beta_g2, gamma_g2
+        let delta_g1 = 2 * g1 + 2 * g2;
+        let delta_g2 = delta_g1 + g1;
+
+        let mut position = delta_g2 + g2;
+        let mut skip_query = |file: &mut File, point_size: u64| -> io::Result<(u64, usize)> {
+            file.seek(SeekFrom::Start(position))?;
+            let len = file.read_u32::<BigEndian>()? as usize;
+            let start = position + 4;
+            position = start + len as u64 * point_size;
+            Ok((start, len))
+        };
+
+        let _ic = skip_query(file, g1)?;
+        let h = skip_query(file, g1)?;
+        let l = skip_query(file, g1)?;
+        let _a = skip_query(file, g1)?;
+        let _b_g1 = skip_query(file, g1)?;
+        let (b_g2_start, b_g2_len) = skip_query(file, g2)?;
+
+        let cs_hash = b_g2_start + b_g2_len as u64 * g2;
+        let contributions = cs_hash + 64;
+        if contributions + 4 > file.metadata()?.len() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "parameters file is truncated"));
+        }
+
+        Ok(ParamsLayout { delta_g1, delta_g2, h, l, cs_hash, contributions })
+    }
+}
+
+fn read_g1(file: &mut File, position: u64) -> io::Result<G1Affine> {
+    file.seek(SeekFrom::Start(position))?;
+    Ok(read_batch_par::<G1Uncompressed, _>(file, 1, true, true)?[0])
+}
+
+fn read_g2(file: &mut File, position: u64) -> io::Result<G2Affine> {
+    file.seek(SeekFrom::Start(position))?;
+    Ok(read_batch_par::<G2Uncompressed, _>(file, 1, true, true)?[0])
+}
+
+fn write_points<C: CurveAffine>(file: &mut File, position: u64, points: &[C]) -> io::Result<()> {
+    file.seek(SeekFrom::Start(position))?;
+    let mut writer = BufWriter::new(file);
+    for point in points {
+        writer.write_all(point.into_uncompressed().as_ref())?;
+    }
+    writer.flush()
+}
+
+/// Same as `MPCParameters::contribute`, on the parameters in `file`,
+/// which must be open for reading and writing. The H and L queries
+/// are processed `batch_size` points at a time. Returns the hash of
+/// the contribution.
+pub fn contribute<R: Rng>(
+    rng: &mut R,
+    file: &mut File,
+    batch_size: usize,
+    progress_update_interval: &u32
+) -> io::Result<[u8; 64]>
+{
+    assert!(batch_size > 0, "batch size must be positive");
+    let layout = ParamsLayout::read(file)?;
+
+    let delta_g1 = read_g1(file, layout.delta_g1)?;
+    let delta_g2 = read_g2(file, layout.delta_g2)?;
+
+    let mut cs_hash = [0u8; 64];
+    file.seek(SeekFrom::Start(layout.cs_hash))?;
+    file.read_exact(&mut cs_hash)?;
+
+    let contributions = {
+        let mut reader = BufReader::new(&mut *file);
+        let contributions_len = reader.read_u32::<BigEndian>()? as usize;
+        let mut contributions = vec![];
+        for _ in 0..contributions_len {
+            contributions.push(PublicKey::read(&mut reader)?);
+        }
+        // The public keys are the end of the file
+        if reader.read(&mut [0u8])? != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unexpected data after the contributions"));
+        }
+        contributions
+    };
+
+    // Generate a keypair
+    let (pubkey, privkey) = keypair_from_transcript(rng, &cs_hash, &contributions, delta_g1);
+    let privkey = SecretGuard::new(privkey);
+
+    let mut delta_inv = privkey.delta.inverse().expect("nonzero");
+    let total_exps = (layout.l.1 + layout.h.1) as u32;
+    for &(start, len) in &[layout.l, layout.h] {
+        let mut done = 0;
+        while done < len {
+            let batch_len = ::std::cmp::min(batch_size, len - done);
+            let position = start + (done * G1Uncompressed::size()) as u64;
+
+            file.seek(SeekFrom::Start(position))?;
+            let mut points = read_batch_par::<G1Uncompressed, _>(&mut BufReader::new(&mut *file), batch_len, true, true)?;
+            batch_exp(&mut points, delta_inv, progress_update_interval, &total_exps);
+            write_points(file, position, &points)?;
+
+            done += batch_len;
+        }
+    }
+    clear_scalar(&mut delta_inv);
+
+    write_points(file, layout.delta_g1, &[delta_g1.mul(privkey.delta).into_affine()])?;
+    write_points(file, layout.delta_g2, &[delta_g2.mul(privkey.delta).into_affine()])?;
+
+    // Append the public key
+    file.seek(SeekFrom::Start(layout.contributions))?;
+    file.write_u32::<BigEndian>((contributions.len() + 1) as u32)?;
+    file.seek(SeekFrom::End(0))?;
+    pubkey.write(&mut *file)?;
+    file.sync_all()?;
+
+    // Calculate the hash of the public key and return it
+    let sink = io::sink();
+    let mut sink = HashWriter::new(sink);
+    pubkey.write(&mut sink)?;
+    let h = sink.into_hash();
+    let mut response = [0u8; 64];
+    response.copy_from_slice(h.as_ref());
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parameters::tests::{rng, test_params};
+    use std::env;
+    use std::fs::{self, OpenOptions};
+    use std::process;
+
+    #[test]
+    fn test_contribute() {
+        let mut params = test_params(1);
+        let path = env::temp_dir().join(format!("phase2_test_in_place_{}", process::id()));
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path).unwrap();
+        params.write(&mut file).unwrap();
+
+        // Batches smaller than the queries, the same output as `contribute` and `write`
+        let hash = contribute(&mut rng(), &mut file, 2, &0).unwrap();
+        let contributed = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(hash[..] == params.contribute(&mut rng(), &0)[..]);
+        let mut expected = vec![];
+        params.write(&mut expected).unwrap();
+        assert!(contributed == expected);
+    }
+}
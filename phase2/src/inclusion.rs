@@ -0,0 +1,120 @@
+//! Statements that a contribution is in the transcript of some params.
+//!
+//! A statement gives the position of the contribution and the hashes of the
+//! contributions around it, so a contributor can publish a few lines instead
+//! of pointing at a list of hashes:
+//!
+//! ```text
+//! round: 2
+//! previous: 5e1c...
+//! contribution: 09ab...
+//! next: none
+//! ```
+//!
+//! Rounds start from 0, and `previous` is `none` for the first contribution,
+//! like `next` for the last one. Anyone with the params can check the
+//! statement with `verify_inclusion`.
+
+use std::fmt;
+
+use hex;
+
+use super::errors::{self, Error, ErrorCategory};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InclusionStatement {
+    /// Position of the contribution in the transcript, starting from 0
+    pub round: usize,
+    pub previous: Option<[u8; 64]>,
+    pub contribution: [u8; 64],
+    pub next: Option<[u8; 64]>,
+}
+
+impl InclusionStatement {
+    /// The statement for the contribution `hash` in the transcript made of
+    /// `hashes`, if it's there.
+    pub fn new(hashes: &[[u8; 64]], hash: &[u8; 64]) -> Option<Self> {
+        let round = hashes.iter().position(|h| h == hash)?;
+
+        Some(InclusionStatement {
+            round,
+            previous: if round > 0 { Some(hashes[round - 1]) } else { None },
+            contribution: *hash,
+            next: hashes.get(round + 1).cloned(),
+        })
+    }
+
+    /// Checks that the statement holds in the transcript made of `hashes`.
+    pub fn holds_in(&self, hashes: &[[u8; 64]]) -> bool {
+        InclusionStatement::new(hashes, &self.contribution).as_ref() == Some(self)
+    }
+
+    /// Parses a statement written by `Display`. Lines may be in any order,
+    /// and hashes may start with `0x`.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let invalid = |message: String| {
+            Error::new(errors::INVALID_ARGUMENT, ErrorCategory::InvalidInput, message)
+                .with_hint("the statement must have the round, previous, contribution and next lines written by prove_inclusion")
+        };
+
+        let (mut round, mut previous, mut contribution, mut next) = (None, None, None, None);
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let mut fields = line.splitn(2, ':');
+            let key = fields.next().unwrap().trim();
+            let value = fields.next().map(str::trim).ok_or_else(|| invalid(format!("expected `key: value`, found `{}`", line)))?;
+            match key {
+                "round" => round = Some(value.parse().map_err(|_| invalid(format!("bad round `{}`", value)))?),
+                "previous" => previous = Some(parse_optional_hash(value).ok_or_else(|| invalid(format!("bad previous hash `{}`", value)))?),
+                "contribution" => contribution = Some(parse_hash(value).ok_or_else(|| invalid(format!("bad contribution hash `{}`", value)))?),
+                "next" => next = Some(parse_optional_hash(value).ok_or_else(|| invalid(format!("bad next hash `{}`", value)))?),
+                _ => return Err(invalid(format!("unknown line `{}`", line))),
+            }
+        }
+
+        match (round, previous, contribution, next) {
+            (Some(round), Some(previous), Some(contribution), Some(next)) => Ok(InclusionStatement {
+                round,
+                previous,
+                contribution,
+                next,
+            }),
+            _ => Err(invalid("the statement is incomplete".to_string())),
+        }
+    }
+}
+
+impl fmt::Display for InclusionStatement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let optional_hash = |hash: &Option<[u8; 64]>| match *hash {
+            Some(ref hash) => hex::encode(&hash[..]),
+            None => "none".to_string(),
+        };
+
+        writeln!(f, "round: {}", self.round)?;
+        writeln!(f, "previous: {}", optional_hash(&self.previous))?;
+        writeln!(f, "contribution: {}", hex::encode(&self.contribution[..]))?;
+        writeln!(f, "next: {}", optional_hash(&self.next))
+    }
+}
+
+/// Parses a hash in hex, with or without `0x`.
+pub fn parse_hash(hex_hash: &str) -> Option<[u8; 64]> {
+    let hex_hash = hex_hash.trim();
+    let hex_hash = hex_hash.strip_prefix("0x").unwrap_or(hex_hash);
+    let bytes = hex::decode(hex_hash).ok()?;
+    if bytes.len() != 64 {
+        return None;
+    }
+    let mut hash = [0; 64];
+    hash.copy_from_slice(&bytes);
+
+    Some(hash)
+}
+
+fn parse_optional_hash(value: &str) -> Option<Option<[u8; 64]>> {
+    if value == "none" {
+        Some(None)
+    } else {
+        parse_hash(value).map(Some)
+    }
+}
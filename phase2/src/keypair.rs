@@ -1,14 +1,24 @@
 extern crate bellman_ce;
 
+use std::fmt;
 use std::io::{
     self,
     Read,
     Write,
 };
+use std::ops::Deref;
+use std::ptr;
+use std::sync::atomic::{
+    self,
+    Ordering
+};
+
+use zeroize::Zeroize;
 
 use bellman_ce::pairing::{
     EncodedPoint,
     CurveAffine,
+    ff::Field,
     bn256::{
         Fr,
         G1Affine,
@@ -19,11 +29,62 @@ use bellman_ce::pairing::{
 };
 
 /// This needs to be destroyed by at least one participant
-/// for the final parameters to be secure.
+/// for the final parameters to be secure. It is overwritten
+/// with zeroes when dropped.
 pub struct PrivateKey {
     pub delta: Fr
 }
 
+impl Zeroize for PrivateKey {
+    fn zeroize(&mut self) {
+        clear_scalar(&mut self.delta);
+    }
+}
+
+impl Drop for PrivateKey {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// Overwrites a secret scalar with zero. The write is volatile
+/// so that the compiler does not remove it.
+pub fn clear_scalar<F: Field>(scalar: &mut F) {
+    unsafe { ptr::write_volatile(scalar, F::zero()) };
+    atomic::compiler_fence(Ordering::SeqCst);
+}
+
+/// Owns a secret and zeroizes it when dropped. The secret can
+/// only be borrowed, and the guard can neither be cloned nor
+/// printed, so that no copies of it are made by accident.
+pub struct SecretGuard<T: Zeroize>(T);
+
+impl<T: Zeroize> SecretGuard<T> {
+    pub fn new(secret: T) -> Self {
+        SecretGuard(secret)
+    }
+}
+
+impl<T: Zeroize> Deref for SecretGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Zeroize> fmt::Debug for SecretGuard<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecretGuard(..)")
+    }
+}
+
+impl<T: Zeroize> Drop for SecretGuard<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
 /// This allows others to verify that you contributed. The hash produced
 /// by `MPCParameters::contribute` is just a BLAKE2b hash of this object.
 #[derive(Clone)]
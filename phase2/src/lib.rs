@@ -13,15 +13,26 @@ extern crate num_traits;
 extern crate cfg_if;
 extern crate itertools;
 extern crate blake2;
+extern crate exitcode;
+extern crate hex;
+extern crate zeroize;
 
 use cfg_if::cfg_if;
 
 pub mod keypair;
 pub mod keypair_assembly;
+pub mod memory_plan;
 pub mod hash_writer;
+pub mod inclusion;
+pub mod beacon;
 pub mod parameters;
+pub mod radix;
+pub mod in_place;
 pub mod utils;
 pub mod circom_circuit;
+pub mod errors;
+#[cfg(feature = "u32-backend")]
+pub mod u32_backend;
 
 cfg_if! {
     if #[cfg(feature = "wasm")] {
@@ -33,7 +44,8 @@ cfg_if! {
 
         use wasm_bindgen::prelude::*;
         use itertools::Itertools;
-        use parameters::MPCParameters;
+        use errors::{Error, ErrorCategory};
+        use parameters::{Compression, MPCParameters};
         use std::io::{
             Read,
             Write,
@@ -43,10 +55,100 @@ cfg_if! {
             ($($t:tt)*) => (web_sys::console::log_1(&format_args!($($t)*).to_string().into()))
         }
 
+        /// How params are read and written. By default, like the
+        /// command line tools, they are uncompressed and their points
+        /// are checked when they are read.
+        #[wasm_bindgen]
+        #[derive(Clone, Copy, Debug)]
+        pub struct ParamsOptions {
+            /// Whether the params given have compressed points
+            pub compressed_input: bool,
+            /// Whether the params returned have compressed points
+            pub compressed_output: bool,
+            /// Whether points are checked to be in their subgroup
+            pub check_points: bool,
+            /// Whether points at infinity are rejected
+            pub disallow_points_at_infinity: bool,
+        }
+
+        impl Default for ParamsOptions {
+            fn default() -> Self {
+                ParamsOptions {
+                    compressed_input: false,
+                    compressed_output: false,
+                    check_points: true,
+                    disallow_points_at_infinity: false,
+                }
+            }
+        }
+
+        #[wasm_bindgen]
+        impl ParamsOptions {
+            #[wasm_bindgen(constructor)]
+            pub fn new() -> ParamsOptions {
+                ParamsOptions::default()
+            }
+        }
+
+        /// Throws a JS `Error` with the message of `error`, and its
+        /// code, category and hint as properties, the way the command
+        /// line tools report it.
+        fn js_error(error: Error) -> JsValue {
+            let js = js_sys::Error::new(&error.message);
+            let _ = js_sys::Reflect::set(&js, &"code".into(), &error.code.into());
+            let _ = js_sys::Reflect::set(&js, &"category".into(), &error.category.to_string().into());
+            if let Some(hint) = error.hint {
+                let _ = js_sys::Reflect::set(&js, &"hint".into(), &hint.into());
+            }
+
+            js.into()
+        }
+
+        fn invalid_params(message: &str, e: std::io::Error) -> JsValue {
+            js_error(Error::new(errors::INVALID_PARAMETERS, ErrorCategory::InvalidInput, format!("{}: {}", message, e))
+                .with_hint("the params are corrupted or the compression options don't match them"))
+        }
+
+        fn read_params(params: &[u8], options: &ParamsOptions) -> Result<MPCParameters, JsValue> {
+            if !options.compressed_input {
+                return MPCParameters::read(params, options.disallow_points_at_infinity, options.check_points)
+                    .map_err(|e| invalid_params("unable to read params", e));
+            }
+
+            // The points are checked while they're decompressed
+            let mut decompressed = vec![];
+            MPCParameters::recompress(params, &mut decompressed, Compression::Compressed, Compression::Uncompressed, options.check_points)
+                .map_err(|e| invalid_params("unable to decompress params", e))?;
+            MPCParameters::read(&*decompressed, options.disallow_points_at_infinity, false)
+                .map_err(|e| invalid_params("unable to read params", e))
+        }
+
+        fn write_params(params: &MPCParameters, options: &ParamsOptions) -> Result<Vec<u8>, JsValue> {
+            let internal = |e: std::io::Error| js_error(Error::new(errors::INTERNAL, ErrorCategory::Internal, format!("unable to write params: {}", e)));
+            let mut output: Vec<u8> = vec![];
+            params.write(&mut output).map_err(internal)?;
+            if !options.compressed_output {
+                return Ok(output);
+            }
+
+            let mut compressed = vec![];
+            MPCParameters::recompress(&*output, &mut compressed, Compression::Uncompressed, Compression::Compressed, false)
+                .map_err(internal)?;
+            Ok(compressed)
+        }
+
+        /// Contributes to uncompressed params, see `contribute_with_options`.
         #[wasm_bindgen]
         pub fn contribute(params: Vec<u8>, entropy: Vec<u8>) -> Result<Vec<u8>, JsValue> {
+            contribute_with_options(params, entropy, &ParamsOptions::new())
+        }
+
+        /// Contributes to `params` with randomness drawn from `entropy`
+        /// and returns the new params. Throws an `Error` with a `code`
+        /// if the params can't be read, rather than aborting.
+        #[wasm_bindgen]
+        pub fn contribute_with_options(params: Vec<u8>, entropy: Vec<u8>, options: &ParamsOptions) -> Result<Vec<u8>, JsValue> {
             console_error_panic_hook::set_once();
-            let disallow_points_at_infinity = false;
 
             log!("Initializing phase2");
             // Create an RNG based on provided randomness
@@ -72,17 +174,89 @@ cfg_if! {
                 ChaChaRng::from_seed(&seed)
             };
         
-            let mut params = MPCParameters::read(&*params, disallow_points_at_infinity, true).expect("unable to read params");
+            let mut params = read_params(&params, options)?;
 
             log!("Contributing...");
             let zero: u32 = 0;
             let hash = params.contribute(&mut rng, &zero);
             log!("Contribution hash: 0x{:02x}", hash.iter().format(""));
 
-            let mut output: Vec<u8> = vec![];
-            params.write(&mut output).expect("failed to write updated parameters");
+            let output = write_params(&params, options)?;
             log!("Returning parameters");
             Ok(output)
         }
+
+        /// Checks that `after` is `before` with one valid contribution,
+        /// both read with `options`, and returns the hash of that
+        /// contribution in hex, so that the last contribution can be
+        /// checked before contributing on top of it.
+        #[wasm_bindgen]
+        pub fn verify(before: Vec<u8>, after: Vec<u8>, options: &ParamsOptions) -> Result<String, JsValue> {
+            console_error_panic_hook::set_once();
+
+            let before = read_params(&before, options)?;
+            let after = read_params(&after, options)?;
+
+            log!("Verifying the contribution...");
+            let hash = parameters::verify_contribution(&before, &after).map_err(|_| {
+                js_error(Error::new(errors::INVALID_CONTRIBUTION, ErrorCategory::Verification, "the contribution is invalid")
+                    .with_hint("don't contribute on top of these params, report them to the coordinator"))
+            })?;
+            log!("Contribution hash: 0x{:02x}", hash.iter().format(""));
+
+            Ok(format!("{:02x}", hash.iter().format("")))
+        }
+
+        /// Times the multiplication of `points` random points of G1
+        /// by a scalar, which is most of the work of a contribution,
+        /// with the generic arithmetic and with the backend of this
+        /// build, and checks that both give the same points. Returns
+        /// the timings in milliseconds as JSON.
+        #[wasm_bindgen]
+        pub fn benchmark(points: u32) -> Result<String, JsValue> {
+            use bellman_ce::pairing::{
+                ff::PrimeField,
+                CurveAffine,
+                CurveProjective,
+                Wnaf,
+                bn256::{
+                    Fr,
+                    G1,
+                },
+            };
+            use rand::{Rand, SeedableRng, XorShiftRng};
+            console_error_panic_hook::set_once();
+
+            let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+            let bases: Vec<_> = (0..points).map(|_| G1::rand(&mut rng).into_affine()).collect();
+            let coeff = Fr::rand(&mut rng);
+
+            log!("Multiplying {} points with the generic arithmetic...", points);
+            let start = js_sys::Date::now();
+            let mut wnaf = Wnaf::new();
+            let mut projective: Vec<_> = bases.iter()
+                .map(|base| wnaf.base(base.into_projective(), 1).scalar(coeff.into_repr()))
+                .collect();
+            G1::batch_normalization(&mut projective);
+            let generic: Vec<_> = projective.iter().map(|point| point.into_affine()).collect();
+            let generic_ms = js_sys::Date::now() - start;
+
+            let backend = if cfg!(feature = "u32-backend") { "u32" } else { "generic" };
+            log!("Multiplying {} points with the {} backend...", points, backend);
+            let start = js_sys::Date::now();
+            let mut multiplied = bases.clone();
+            parameters::batch_exp(&mut multiplied, coeff, &0, &points);
+            let backend_ms = js_sys::Date::now() - start;
+
+            if multiplied != generic {
+                return Err(JsValue::from_str("the backend disagrees with the generic arithmetic"));
+            }
+            log!("Generic: {} ms, {}: {} ms", generic_ms, backend, backend_ms);
+
+            Ok(format!(
+                "{{\"points\":{},\"backend\":\"{}\",\"generic_ms\":{},\"backend_ms\":{}}}",
+                points, backend, generic_ms, backend_ms
+            ))
+        }
     }
 }
@@ -0,0 +1,133 @@
+//! Estimates of the memory that building the initial parameters
+//! takes, stage by stage. The circuit is synthesized first, and
+//! the plan is made from it before any point is read or computed,
+//! so that a circuit that doesn't fit in a memory budget fails
+//! right away instead of after the evaluation, and the radix file
+//! is read in batches small enough to fit when it can.
+
+extern crate bellman_ce;
+
+use std::fmt;
+use std::mem::size_of;
+
+use bellman_ce::pairing::{
+    EncodedPoint,
+    bn256::{
+        Bn256,
+        Fr,
+        G1,
+        G2,
+        G1Affine,
+        G1Uncompressed,
+        G2Affine,
+        G2Uncompressed
+    }
+};
+
+use super::keypair_assembly::KeypairAssembly;
+use super::radix::DEFAULT_BATCH_SIZE;
+
+/// A stage of building the parameters, with the memory of the
+/// large allocations alive at the end of it.
+#[derive(Clone, Debug)]
+pub struct Stage {
+    pub name: &'static str,
+    pub bytes: usize
+}
+
+#[derive(Clone, Debug)]
+pub struct MemoryPlan {
+    pub stages: Vec<Stage>,
+    /// Lagrange coefficients of each radix section read at once
+    pub batch_size: usize
+}
+
+/// Memory for one Lagrange coefficient of each section of the
+/// radix file, decoded, with the buffer of the largest encoding.
+fn coefficient_size() -> usize {
+    3 * size_of::<G1Affine>() + size_of::<G2Affine>() + G2Uncompressed::size()
+}
+
+impl MemoryPlan {
+    /// Plan for building the parameters of `assembly`, whose
+    /// evaluation domain has `m` elements, reading `batch_size`
+    /// coefficients at a time.
+    pub fn new(assembly: &KeypairAssembly<Bn256>, m: usize, batch_size: usize) -> MemoryPlan {
+        let variables = assembly.num_inputs + assembly.num_aux;
+        let terms: usize = assembly.at_inputs.iter()
+            .chain(assembly.bt_inputs.iter())
+            .chain(assembly.ct_inputs.iter())
+            .chain(assembly.at_aux.iter())
+            .chain(assembly.bt_aux.iter())
+            .chain(assembly.ct_aux.iter())
+            .map(|polynomial| polynomial.len())
+            .sum();
+
+        // The QAP polynomials stay alive until the parameters are built
+        let circuit = terms * size_of::<(Fr, usize)>() + 3 * variables * size_of::<Vec<(Fr, usize)>>();
+        // A and B in G1 and G2, and IC and L, which have one element per variable together
+        let evaluated = variables * (3 * size_of::<G1>() + size_of::<G2>());
+        let batch_size = ::std::cmp::min(batch_size, m);
+        let batch = batch_size * coefficient_size();
+        let h = (m - 1) * (size_of::<G1Affine>() + G1Uncompressed::size());
+        let affine = variables * (3 * size_of::<G1Affine>() + size_of::<G2Affine>());
+
+        MemoryPlan {
+            stages: vec![
+                Stage { name: "circuit", bytes: circuit },
+                Stage { name: "evaluation", bytes: circuit + evaluated + batch },
+                Stage { name: "H query", bytes: circuit + evaluated + h },
+                Stage { name: "parameters", bytes: circuit + evaluated + h + affine },
+            ],
+            batch_size
+        }
+    }
+
+    /// Plan with the default batch size if it fits in `budget`
+    /// bytes, or with the largest batch size that does. Fails with
+    /// the plan of the smallest batches if even those don't fit.
+    pub fn within_budget(
+        assembly: &KeypairAssembly<Bn256>,
+        m: usize,
+        budget: usize
+    ) -> Result<MemoryPlan, MemoryPlan>
+    {
+        let plan = MemoryPlan::new(assembly, m, DEFAULT_BATCH_SIZE);
+        if plan.peak() <= budget {
+            return Ok(plan);
+        }
+
+        // Only the evaluation depends on the batch size
+        let smallest = MemoryPlan::new(assembly, m, 1);
+        if smallest.peak() > budget {
+            return Err(smallest);
+        }
+        let without_batch = smallest.stage_bytes("evaluation") - coefficient_size();
+        let batch_size = (budget - without_batch) / coefficient_size();
+
+        Ok(MemoryPlan::new(assembly, m, batch_size))
+    }
+
+    /// Memory of the largest stage.
+    pub fn peak(&self) -> usize {
+        self.stages.iter().map(|stage| stage.bytes).max().unwrap_or(0)
+    }
+
+    fn stage_bytes(&self, name: &str) -> usize {
+        self.stages.iter().find(|stage| stage.name == name).map(|stage| stage.bytes).unwrap_or(0)
+    }
+}
+
+impl fmt::Display for MemoryPlan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for stage in self.stages.iter() {
+            writeln!(f, "{:>12}: {:.1} MiB", stage.name, stage.bytes as f64 / (1 << 20) as f64)?;
+        }
+        write!(
+            f,
+            "Expected peak of {:.1} MiB, reading batches of {} coefficients",
+            self.peak() as f64 / (1 << 20) as f64,
+            self.batch_size
+        )
+    }
+}
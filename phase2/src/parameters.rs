@@ -71,8 +71,16 @@ use rand::{
 };
 
 use super::hash_writer::*;
+use super::inclusion::InclusionStatement;
 use super::keypair_assembly::*;
 use super::keypair::*;
+use super::memory_plan::MemoryPlan;
+use super::radix::{
+    available_exps,
+    RadixFile,
+    Section,
+    DEFAULT_BATCH_SIZE
+};
 use super::utils::*;
 
 /// MPC parameters are just like bellman `Parameters` except, when serialized,
@@ -99,122 +107,68 @@ impl MPCParameters {
     pub fn new<C>(
         circuit: C,
         should_filter_points_at_infinity: bool,
-        radix_directory: &String,
+        radix_directory: &str,
     ) -> Result<MPCParameters, SynthesisError>
         where C: Circuit<Bn256>
     {
-        let mut assembly = KeypairAssembly {
-            num_inputs: 0,
-            num_aux: 0,
-            num_constraints: 0,
-            at_inputs: vec![],
-            bt_inputs: vec![],
-            ct_inputs: vec![],
-            at_aux: vec![],
-            bt_aux: vec![],
-            ct_aux: vec![]
-        };
-
-        // Allocate the "one" input variable
-        assembly.alloc_input(|| "", || Ok(Fr::one()))?;
-
-        // Synthesize the circuit.
-        circuit.synthesize(&mut assembly)?;
-
-        // Input constraints to ensure full density of IC query
-        // x * 0 = 0
-        for i in 0..assembly.num_inputs {
-            assembly.enforce(|| "",
-                             |lc| lc + Variable::new_unchecked(Index::Input(i)),
-                             |lc| lc,
-                             |lc| lc,
-            );
-        }
-
-        // Compute the size of our evaluation domain
-        let mut m = 1;
-        let mut exp = 0;
-        while m < assembly.num_constraints {
-            m *= 2;
-            exp += 1;
+        MPCParameters::new_with_batch_size(circuit, should_filter_points_at_infinity, radix_directory, DEFAULT_BATCH_SIZE)
+    }
 
-            // Powers of Tau ceremony can't support more than 2^28
-            if exp > 28 {
-                return Err(SynthesisError::PolynomialDegreeTooLarge)
-            }
-        }
+    /// Same as `new`, reading at most `batch_size` Lagrange
+    /// coefficients of each section of the phase 1 radix file
+    /// at a time, which bounds the memory used for them.
+    pub fn new_with_batch_size<C>(
+        circuit: C,
+        should_filter_points_at_infinity: bool,
+        radix_directory: &str,
+        batch_size: usize,
+    ) -> Result<MPCParameters, SynthesisError>
+        where C: Circuit<Bn256>
+    {
+        let (assembly, exp) = synthesize(circuit)?;
+        MPCParameters::from_assembly(assembly, exp, should_filter_points_at_infinity, radix_directory, batch_size)
+    }
 
-        // Try to load "radix_directory/phase1radix2m{}"
-        let f = match File::open(format!("{}/phase1radix2m{}", radix_directory, exp)) {
-            Ok(f) => f,
-            Err(e) => {
-                panic!("Couldn't load phase1radix2m{}: {:?}", exp, e);
+    /// Same as `new`, but first plans the memory that building the
+    /// parameters takes, and reads the phase 1 radix file in batches
+    /// small enough to stay within `memory_budget` bytes. `report`
+    /// is given the plan before anything is built. Fails without
+    /// building anything if the parameters can't fit the budget.
+    pub fn new_with_memory_budget<C, F>(
+        circuit: C,
+        should_filter_points_at_infinity: bool,
+        radix_directory: &str,
+        memory_budget: usize,
+        report: F,
+    ) -> Result<MPCParameters, SynthesisError>
+        where C: Circuit<Bn256>, F: FnOnce(&MemoryPlan)
+    {
+        let (assembly, exp) = synthesize(circuit)?;
+        match MemoryPlan::within_budget(&assembly, 1 << exp, memory_budget) {
+            Ok(plan) => {
+                report(&plan);
+                MPCParameters::from_assembly(assembly, exp, should_filter_points_at_infinity, radix_directory, plan.batch_size)
+            },
+            Err(plan) => {
+                report(&plan);
+                Err(SynthesisError::IoError(io::Error::new(
+                    io::ErrorKind::OutOfMemory,
+                    format!("the parameters need at least {} bytes to build, more than the budget of {}", plan.peak(), memory_budget)
+                )))
             }
-        };
-        let f = &mut BufReader::with_capacity(1024 * 1024, f);
-
-        let read_g1 = |reader: &mut BufReader<File>| -> io::Result<G1Affine> {
-            let mut repr = G1Uncompressed::empty();
-            reader.read_exact(repr.as_mut())?;
-
-            repr.into_affine_unchecked()
-                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
-                .and_then(|e| if e.is_zero() {
-                    Err(io::Error::new(io::ErrorKind::InvalidData, "point at infinity"))
-                } else {
-                    Ok(e)
-                })
-        };
-
-        let read_g2 = |reader: &mut BufReader<File>| -> io::Result<G2Affine> {
-            let mut repr = G2Uncompressed::empty();
-            reader.read_exact(repr.as_mut())?;
-
-            repr.into_affine_unchecked()
-                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
-                .and_then(|e| if e.is_zero() {
-                    Err(io::Error::new(io::ErrorKind::InvalidData, "point at infinity"))
-                } else {
-                    Ok(e)
-                })
-        };
-
-        let alpha = read_g1(f)?;
-        let beta_g1 = read_g1(f)?;
-        let beta_g2 = read_g2(f)?;
-
-        let mut coeffs_g1 = Vec::with_capacity(m);
-        for _ in 0..m {
-            coeffs_g1.push(read_g1(f)?);
-        }
-
-        let mut coeffs_g2 = Vec::with_capacity(m);
-        for _ in 0..m {
-            coeffs_g2.push(read_g2(f)?);
         }
+    }
 
-        let mut alpha_coeffs_g1 = Vec::with_capacity(m);
-        for _ in 0..m {
-            alpha_coeffs_g1.push(read_g1(f)?);
-        }
-
-        let mut beta_coeffs_g1 = Vec::with_capacity(m);
-        for _ in 0..m {
-            beta_coeffs_g1.push(read_g1(f)?);
-        }
-
-        // These are `Arc` so that later it'll be easier
-        // to use multiexp during QAP evaluation (which
-        // requires a futures-based API)
-        let coeffs_g1 = Arc::new(coeffs_g1);
-        let coeffs_g2 = Arc::new(coeffs_g2);
-        let alpha_coeffs_g1 = Arc::new(alpha_coeffs_g1);
-        let beta_coeffs_g1 = Arc::new(beta_coeffs_g1);
-
-        let mut h = Vec::with_capacity(m-1);
-        for _ in 0..m-1 {
-            h.push(read_g1(f)?);
-        }
+    fn from_assembly(
+        assembly: KeypairAssembly<Bn256>,
+        exp: u32,
+        should_filter_points_at_infinity: bool,
+        radix_directory: &str,
+        batch_size: usize,
+    ) -> Result<MPCParameters, SynthesisError>
+    {
+        // Load "radix_directory/phase1radix2m{}" before building anything
+        let mut radix = open_radix(radix_directory, exp, assembly.num_constraints)?;
 
         let mut ic = vec![G1::zero(); assembly.num_inputs];
         let mut l = vec![G1::zero(); assembly.num_aux];
@@ -222,12 +176,15 @@ impl MPCParameters {
         let mut b_g1 = vec![G1::zero(); assembly.num_inputs + assembly.num_aux];
         let mut b_g2 = vec![G2::zero(); assembly.num_inputs + assembly.num_aux];
 
+        /// Adds the terms of the QAP polynomials whose Lagrange
+        /// coefficients are in the batch starting at `offset`.
         fn eval(
-            // Lagrange coefficients for tau
-            coeffs_g1: Arc<Vec<G1Affine>>,
-            coeffs_g2: Arc<Vec<G2Affine>>,
-            alpha_coeffs_g1: Arc<Vec<G1Affine>>,
-            beta_coeffs_g1: Arc<Vec<G1Affine>>,
+            // Lagrange coefficients for tau, from `offset` on
+            offset: usize,
+            coeffs_g1: &[G1Affine],
+            coeffs_g2: &[G2Affine],
+            alpha_coeffs_g1: &[G1Affine],
+            beta_coeffs_g1: &[G1Affine],
 
             // QAP polynomials
             at: &[Vec<(Fr, usize)>],
@@ -252,6 +209,8 @@ impl MPCParameters {
             assert_eq!(a_g1.len(), b_g2.len());
             assert_eq!(a_g1.len(), ext.len());
 
+            let batch = offset..offset + coeffs_g1.len();
+
             // Evaluate polynomials in multiple threads
             worker.scope(a_g1.len(), |scope, chunk| {
                 for ((((((a_g1, b_g1), b_g2), ext), at), bt), ct) in
@@ -263,10 +222,7 @@ impl MPCParameters {
                         .zip(bt.chunks(chunk))
                         .zip(ct.chunks(chunk))
                     {
-                        let coeffs_g1 = coeffs_g1.clone();
-                        let coeffs_g2 = coeffs_g2.clone();
-                        let alpha_coeffs_g1 = alpha_coeffs_g1.clone();
-                        let beta_coeffs_g1 = beta_coeffs_g1.clone();
+                        let batch = batch.clone();
 
                         scope.spawn(move |_| {
                             for ((((((a_g1, b_g1), b_g2), ext), at), bt), ct) in
@@ -278,23 +234,42 @@ impl MPCParameters {
                                     .zip(bt.iter())
                                     .zip(ct.iter())
                                 {
-                                    for &(coeff, lag) in at {
-                                        a_g1.add_assign(&coeffs_g1[lag].mul(coeff));
-                                        ext.add_assign(&beta_coeffs_g1[lag].mul(coeff));
+                                    for &(coeff, lag) in at.iter().filter(|&&(_, lag)| batch.contains(&lag)) {
+                                        a_g1.add_assign(&coeffs_g1[lag - offset].mul(coeff));
+                                        ext.add_assign(&beta_coeffs_g1[lag - offset].mul(coeff));
                                     }
 
-                                    for &(coeff, lag) in bt {
-                                        b_g1.add_assign(&coeffs_g1[lag].mul(coeff));
-                                        b_g2.add_assign(&coeffs_g2[lag].mul(coeff));
-                                        ext.add_assign(&alpha_coeffs_g1[lag].mul(coeff));
+                                    for &(coeff, lag) in bt.iter().filter(|&&(_, lag)| batch.contains(&lag)) {
+                                        b_g1.add_assign(&coeffs_g1[lag - offset].mul(coeff));
+                                        b_g2.add_assign(&coeffs_g2[lag - offset].mul(coeff));
+                                        ext.add_assign(&alpha_coeffs_g1[lag - offset].mul(coeff));
                                     }
 
-                                    for &(coeff, lag) in ct {
-                                        ext.add_assign(&coeffs_g1[lag].mul(coeff));
+                                    for &(coeff, lag) in ct.iter().filter(|&&(_, lag)| batch.contains(&lag)) {
+                                        ext.add_assign(&coeffs_g1[lag - offset].mul(coeff));
                                     }
                                 }
+                        });
+                    }
+            });
+        }
 
-                            // Batch normalize
+        fn normalize(
+            a_g1: &mut [G1],
+            b_g1: &mut [G1],
+            b_g2: &mut [G2],
+            ext: &mut [G1],
+            worker: &Worker
+        )
+        {
+            worker.scope(a_g1.len(), |scope, chunk| {
+                for (((a_g1, b_g1), b_g2), ext) in
+                    a_g1.chunks_mut(chunk)
+                        .zip(b_g1.chunks_mut(chunk))
+                        .zip(b_g2.chunks_mut(chunk))
+                        .zip(ext.chunks_mut(chunk))
+                    {
+                        scope.spawn(move |_| {
                             G1::batch_normalization(a_g1);
                             G1::batch_normalization(b_g1);
                             G2::batch_normalization(b_g2);
@@ -306,31 +281,56 @@ impl MPCParameters {
 
         let worker = Worker::new();
 
-        // Evaluate for inputs.
-        eval(
-            coeffs_g1.clone(),
-            coeffs_g2.clone(),
-            alpha_coeffs_g1.clone(),
-            beta_coeffs_g1.clone(),
-            &assembly.at_inputs,
-            &assembly.bt_inputs,
-            &assembly.ct_inputs,
+        // Only one batch of each coefficient section is in memory at a time
+        for (start, len) in radix.batches(Section::CoeffsG1, batch_size) {
+            let coeffs_g1 = radix.read_g1(Section::CoeffsG1, start, len)?;
+            let coeffs_g2 = radix.read_g2(start, len)?;
+            let alpha_coeffs_g1 = radix.read_g1(Section::AlphaCoeffsG1, start, len)?;
+            let beta_coeffs_g1 = radix.read_g1(Section::BetaCoeffsG1, start, len)?;
+
+            // Evaluate for inputs.
+            eval(
+                start,
+                &coeffs_g1,
+                &coeffs_g2,
+                &alpha_coeffs_g1,
+                &beta_coeffs_g1,
+                &assembly.at_inputs,
+                &assembly.bt_inputs,
+                &assembly.ct_inputs,
+                &mut a_g1[0..assembly.num_inputs],
+                &mut b_g1[0..assembly.num_inputs],
+                &mut b_g2[0..assembly.num_inputs],
+                &mut ic,
+                &worker
+            );
+
+            // Evaluate for auxillary variables.
+            eval(
+                start,
+                &coeffs_g1,
+                &coeffs_g2,
+                &alpha_coeffs_g1,
+                &beta_coeffs_g1,
+                &assembly.at_aux,
+                &assembly.bt_aux,
+                &assembly.ct_aux,
+                &mut a_g1[assembly.num_inputs..],
+                &mut b_g1[assembly.num_inputs..],
+                &mut b_g2[assembly.num_inputs..],
+                &mut l,
+                &worker
+            );
+        }
+
+        normalize(
             &mut a_g1[0..assembly.num_inputs],
             &mut b_g1[0..assembly.num_inputs],
             &mut b_g2[0..assembly.num_inputs],
             &mut ic,
             &worker
         );
-
-        // Evaluate for auxillary variables.
-        eval(
-            coeffs_g1.clone(),
-            coeffs_g2.clone(),
-            alpha_coeffs_g1.clone(),
-            beta_coeffs_g1.clone(),
-            &assembly.at_aux,
-            &assembly.bt_aux,
-            &assembly.ct_aux,
+        normalize(
             &mut a_g1[assembly.num_inputs..],
             &mut b_g1[assembly.num_inputs..],
             &mut b_g2[assembly.num_inputs..],
@@ -338,6 +338,11 @@ impl MPCParameters {
             &worker
         );
 
+        let h = radix.read_g1(Section::H, 0, radix.section_len(Section::H))?;
+        let alpha = radix.alpha_g1;
+        let beta_g1 = radix.beta_g1;
+        let beta_g2 = radix.beta_g2;
+
         // Don't allow any elements be unconstrained, so that
         // the L query is always fully dense.
         for e in l.iter() {
@@ -402,6 +407,44 @@ impl MPCParameters {
         &self.params
     }
 
+    /// Get the number of points of each query
+    pub fn query_lengths(&self) -> QueryLengths {
+        QueryLengths {
+            ic: self.params.vk.ic.len(),
+            h: self.params.h.len(),
+            l: self.params.l.len(),
+            a: self.params.a.len(),
+            b_g1: self.params.b_g1.len(),
+            b_g2: self.params.b_g2.len()
+        }
+    }
+
+    /// Get the number of contributions made to the parameters
+    pub fn contribution_count(&self) -> usize {
+        self.contributions.len()
+    }
+
+    /// Get the hashes of the contributions, in the order they
+    /// were made, as `MPCParameters::contribute` returned them.
+    /// Unlike `MPCParameters::verify`, this doesn't check that
+    /// the contributions are valid.
+    pub fn contribution_hashes(&self) -> Vec<[u8; 64]> {
+        self.contributions.iter().map(contribution_hash).collect()
+    }
+
+    /// Get the position of the contribution with the hash `hash`
+    /// in the transcript, starting from 0, if it's there.
+    pub fn contribution_index_of(&self, hash: &[u8; 64]) -> Option<usize> {
+        self.contributions.iter().position(|pubkey| contribution_hash(pubkey) == *hash)
+    }
+
+    /// Get the statement that the contribution with the hash
+    /// `hash` is in the transcript, with its position and the
+    /// hashes of its neighbours, if it's there.
+    pub fn inclusion_statement(&self, hash: &[u8; 64]) -> Option<InclusionStatement> {
+        InclusionStatement::new(&self.contribution_hashes(), hash)
+    }
+
     /// Contributes some randomness to the parameters. Only one
     /// contributor needs to be honest for the parameters to be
     /// secure.
@@ -418,89 +461,15 @@ impl MPCParameters {
     ) -> [u8; 64]
     {
         // Generate a keypair
-        let (pubkey, privkey) = keypair(rng, self);
+        let (pubkey, privkey) = guarded_keypair(rng, self);
 
-        #[cfg(not(feature = "wasm"))]
-        fn batch_exp<C: CurveAffine>(bases: &mut [C], coeff: C::Scalar, progress_update_interval: &u32, total_exps: &u32) {
-            let coeff = coeff.into_repr();
-
-            let mut projective = vec![C::Projective::zero(); bases.len()];
-            let cpus = num_cpus::get();
-            let chunk_size = if bases.len() < cpus {
-                1
-            } else {
-                bases.len() / cpus
-            };
-
-            // Perform wNAF over multiple cores, placing results into `projective`.
-            crossbeam::scope(|scope| {
-                for (bases, projective) in bases.chunks_mut(chunk_size)
-                    .zip(projective.chunks_mut(chunk_size))
-                    {
-                        scope.spawn(move |_| {
-                            let mut wnaf = Wnaf::new();
-                            let mut count = 0;
-                            for (base, projective) in bases.iter_mut()
-                                .zip(projective.iter_mut())
-                                {
-                                    *projective = wnaf.base(base.into_projective(), 1).scalar(coeff);
-                                    count = count + 1;
-                                    if *progress_update_interval > 0 && count % *progress_update_interval == 0 {
-                                        println!("progress {} {}", *progress_update_interval, *total_exps)
-                                    }
-                                }
-                        });
-                    }
-            }).unwrap();
-
-            // Perform batch normalization
-            crossbeam::scope(|scope| {
-                for projective in projective.chunks_mut(chunk_size)
-                    {
-                        scope.spawn(move |_| {
-                            C::Projective::batch_normalization(projective);
-                        });
-                    }
-            }).unwrap();
-
-            // Turn it all back into affine points
-            for (projective, affine) in projective.iter().zip(bases.iter_mut()) {
-                *affine = projective.into_affine();
-            }
-        }
-
-        #[cfg(feature = "wasm")]
-        fn batch_exp<C: CurveAffine>(bases: &mut [C], coeff: C::Scalar, progress_update_interval: &u32, total_exps: &u32) {
-            let coeff = coeff.into_repr();
-
-            let mut projective = vec![C::Projective::zero(); bases.len()];
-
-            // Perform wNAF, placing results into `projective`.
-            let mut wnaf = Wnaf::new();
-            let mut count = 0;
-            for (base, projective) in bases.iter_mut().zip(projective.iter_mut()) {
-                *projective = wnaf.base(base.into_projective(), 1).scalar(coeff);
-                count = count + 1;
-                if *progress_update_interval > 0 && count % *progress_update_interval == 0 {
-                    println!("progress {} {}", *progress_update_interval, *total_exps)
-                }
-            }
-
-            // Perform batch normalization
-            C::Projective::batch_normalization(&mut projective);
-
-            // Turn it all back into affine points
-            for (projective, affine) in projective.iter().zip(bases.iter_mut()) {
-                *affine = projective.into_affine();
-            }
-        }
-
-        let delta_inv = privkey.delta.inverse().expect("nonzero");
+        let mut delta_inv = privkey.delta.inverse().expect("nonzero");
         let mut l = (&self.params.l[..]).to_vec();
         let mut h = (&self.params.h[..]).to_vec();
         let total_exps = (l.len() + h.len()) as u32;
         batch_exp(&mut l, delta_inv, &progress_update_interval, &total_exps);
         batch_exp(&mut h, delta_inv, &progress_update_interval, &total_exps);
+        clear_scalar(&mut delta_inv);
         self.params.l = Arc::new(l);
         self.params.h = Arc::new(h);
 
@@ -510,15 +479,7 @@ impl MPCParameters {
         self.contributions.push(pubkey.clone());
 
         // Calculate the hash of the public key and return it
-        {
-            let sink = io::sink();
-            let mut sink = HashWriter::new(sink);
-            pubkey.write(&mut sink).unwrap();
-            let h = sink.into_hash();
-            let mut response = [0u8; 64];
-            response.copy_from_slice(h.as_ref());
-            response
-        }
+        contribution_hash(&pubkey)
     }
 
     /// Verify the correctness of the parameters, given a circuit
@@ -530,7 +491,7 @@ impl MPCParameters {
         &self,
         circuit: C,
         should_filter_points_at_infinity: bool,
-        radix_directory: &String,
+        radix_directory: &str,
     ) -> Result<Vec<[u8; 64]>, ()>
     {
         let initial_params = MPCParameters::new(circuit, should_filter_points_at_infinity, radix_directory).map_err(|_| ())?;
@@ -616,15 +577,7 @@ impl MPCParameters {
 
             current_delta = pubkey.delta_after;
 
-            {
-                let sink = io::sink();
-                let mut sink = HashWriter::new(sink);
-                pubkey.write(&mut sink).unwrap();
-                let h = sink.into_hash();
-                let mut response = [0u8; 64];
-                response.copy_from_slice(h.as_ref());
-                result.push(response);
-            }
+            result.push(contribution_hash(pubkey));
         }
 
         // Current parameters should have consistent delta in G1
@@ -658,6 +611,48 @@ impl MPCParameters {
         Ok(result)
     }
 
+    /// Checks that the H query is the one of the phase 1 radix
+    /// file, i.e. `(tau^i * t(tau)) / delta`, for the delta of
+    /// these parameters. Unlike `verify`, this needs neither the
+    /// circuit nor the initial parameters, and only `batch_size`
+    /// elements of the radix file are in memory at a time.
+    ///
+    /// Returns an error if the radix file can't be read, and
+    /// `Ok(false)` if the H query is inconsistent with it.
+    pub fn verify_h_query(
+        &self,
+        radix_directory: &str,
+        batch_size: usize,
+    ) -> io::Result<bool>
+    {
+        // The H query has one element less than the domain
+        let m = self.params.h.len() + 1;
+        if !m.is_power_of_two() {
+            return Ok(false);
+        }
+        let exp = m.trailing_zeros();
+
+        let mut radix = RadixFile::open(radix_directory, exp)?;
+
+        // A random linear combination of every batch, summed
+        // over all batches, is a random linear combination of
+        // the whole query
+        let mut initial = G1::zero();
+        let mut current = G1::zero();
+        for (start, len) in radix.batches(Section::H, batch_size) {
+            let h = radix.read_g1(Section::H, start, len)?;
+            let (s, sx) = merge_pairs(&h, &self.params.h[start..start + len]);
+            initial.add_assign_mixed(&s);
+            current.add_assign_mixed(&sx);
+        }
+
+        // H query should be updated with delta^-1
+        Ok(same_ratio(
+            (initial.into_affine(), current.into_affine()),
+            (self.params.vk.delta_g2, G2Affine::one()) // reversed for inverse
+        ))
+    }
+
     /// Serialize these parameters. The serialized parameters
     /// can be read by bellman as Groth16 `Parameters`.
     pub fn write<W: Write>(
@@ -680,17 +675,101 @@ impl MPCParameters {
     /// we won't perform curve validity and group order
     /// checks.
     pub fn read<R: Read>(
-        mut reader: R,
+        reader: R,
         disallow_points_at_infinity: bool,
         checked: bool
     ) -> io::Result<MPCParameters>
     {
-        let params = Parameters::read(&mut reader, disallow_points_at_infinity, checked)?;
+        MPCParameters::read_with_limits(reader, &ReadLimits::default(), disallow_points_at_infinity, checked)
+    }
+
+    /// Same as `read`, but decodes the points of the parameters
+    /// on all available cores.
+    pub fn read_fast<R: Read>(
+        reader: R,
+        disallow_points_at_infinity: bool,
+        checked: bool
+    ) -> io::Result<MPCParameters>
+    {
+        MPCParameters::read_fast_with_limits(reader, &ReadLimits::default(), disallow_points_at_infinity, checked)
+    }
+
+    /// Same as `read`, but fails as soon as the length of a query
+    /// or the number of contributions is above `limits`, before
+    /// reading the points or public keys.
+    pub fn read_with_limits<R: Read>(
+        reader: R,
+        limits: &ReadLimits,
+        disallow_points_at_infinity: bool,
+        checked: bool
+    ) -> io::Result<MPCParameters>
+    {
+        MPCParameters::read_limited(reader, limits, disallow_points_at_infinity, checked, false)
+    }
+
+    /// Same as `read_fast`, with the `limits` of `read_with_limits`.
+    pub fn read_fast_with_limits<R: Read>(
+        reader: R,
+        limits: &ReadLimits,
+        disallow_points_at_infinity: bool,
+        checked: bool
+    ) -> io::Result<MPCParameters>
+    {
+        MPCParameters::read_limited(reader, limits, disallow_points_at_infinity, checked, true)
+    }
+
+    fn read_limited<R: Read>(
+        mut reader: R,
+        limits: &ReadLimits,
+        disallow_points_at_infinity: bool,
+        checked: bool,
+        parallel: bool
+    ) -> io::Result<MPCParameters>
+    {
+        let vk = VerifyingKey::<Bn256>::read(&mut reader)?;
+        // bellman reads the IC query itself, but only grows it as
+        // points are read
+        check_limit("IC query", vk.ic.len(), limits.queries.ic)?;
+
+        fn read_query<ENC: EncodedPoint, R: Read>(
+            reader: &mut R,
+            name: &str,
+            max: usize,
+            disallow_points_at_infinity: bool,
+            checked: bool,
+            parallel: bool
+        ) -> io::Result<Vec<ENC::Affine>>
+        {
+            let len = reader.read_u32::<BigEndian>()? as usize;
+            check_limit(name, len, max)?;
+            if parallel {
+                read_batch_par::<ENC, _>(reader, len, disallow_points_at_infinity, checked)
+            } else {
+                read_batch::<ENC, _>(reader, len, disallow_points_at_infinity, checked)
+            }
+        }
+
+        let queries = &limits.queries;
+        let h = read_query::<G1Uncompressed, _>(&mut reader, "H query", queries.h, disallow_points_at_infinity, checked, parallel)?;
+        let l = read_query::<G1Uncompressed, _>(&mut reader, "L query", queries.l, disallow_points_at_infinity, checked, parallel)?;
+        let a = read_query::<G1Uncompressed, _>(&mut reader, "A query", queries.a, disallow_points_at_infinity, checked, parallel)?;
+        let b_g1 = read_query::<G1Uncompressed, _>(&mut reader, "B G1 query", queries.b_g1, disallow_points_at_infinity, checked, parallel)?;
+        let b_g2 = read_query::<G2Uncompressed, _>(&mut reader, "B G2 query", queries.b_g2, disallow_points_at_infinity, checked, parallel)?;
+
+        let params = Parameters {
+            vk,
+            h: Arc::new(h),
+            l: Arc::new(l),
+            a: Arc::new(a),
+            b_g1: Arc::new(b_g1),
+            b_g2: Arc::new(b_g2)
+        };
 
         let mut cs_hash = [0u8; 64];
         reader.read_exact(&mut cs_hash)?;
 
         let contributions_len = reader.read_u32::<BigEndian>()? as usize;
+        check_limit("transcript", contributions_len, limits.contributions)?;
 
         let mut contributions = vec![];
         for _ in 0..contributions_len {
@@ -701,8 +780,421 @@ impl MPCParameters {
             params, cs_hash, contributions
         })
     }
+
+    /// Copies serialized parameters from `reader` to `writer` with
+    /// their points encoded as `to` instead of `from`, a batch of
+    /// points at a time, so that the parameters are never all in
+    /// memory. If `checked`, every point is checked to be in its
+    /// subgroup while it's decoded, so that the output can then be
+    /// read unchecked. Lengths are checked against the default
+    /// `ReadLimits`.
+    pub fn recompress<R: Read, W: Write>(
+        mut reader: R,
+        mut writer: W,
+        from: Compression,
+        to: Compression,
+        checked: bool
+    ) -> io::Result<()>
+    {
+        // Nothing to decode or check, the parameters are copied as
+        // they are
+        if from == to && !checked {
+            io::copy(&mut reader, &mut writer)?;
+            return Ok(());
+        }
+
+        let limits = ReadLimits::default();
+        let points = |reader: &mut R, writer: &mut W, g2: bool, len: usize| {
+            if g2 {
+                recompress_points::<G2Affine, _, _>(reader, writer, len, from, to, checked)
+            } else {
+                recompress_points::<G1Affine, _, _>(reader, writer, len, from, to, checked)
+            }
+        };
+
+        // alpha_g1, beta_g1, beta_g2, gamma_g2, delta_g1 and delta_g2
+        for &g2 in &[false, false, true, true, false, true] {
+            points(&mut reader, &mut writer, g2, 1)?;
+        }
+        // and then the queries, each prefixed with its length
+        let queries = &limits.queries;
+        for &(name, g2, max) in &[
+            ("IC query", false, queries.ic),
+            ("H query", false, queries.h),
+            ("L query", false, queries.l),
+            ("A query", false, queries.a),
+            ("B G1 query", false, queries.b_g1),
+            ("B G2 query", true, queries.b_g2)
+        ] {
+            let len = reader.read_u32::<BigEndian>()?;
+            check_limit(name, len as usize, max)?;
+            writer.write_u32::<BigEndian>(len)?;
+            points(&mut reader, &mut writer, g2, len as usize)?;
+        }
+
+        let mut cs_hash = [0u8; 64];
+        reader.read_exact(&mut cs_hash)?;
+        writer.write_all(&cs_hash)?;
+
+        let contributions = reader.read_u32::<BigEndian>()?;
+        check_limit("transcript", contributions as usize, limits.contributions)?;
+        writer.write_u32::<BigEndian>(contributions)?;
+        for _ in 0..contributions {
+            // delta_after, s and s_delta, r_delta and the transcript
+            points(&mut reader, &mut writer, false, 3)?;
+            points(&mut reader, &mut writer, true, 1)?;
+            let mut transcript = [0u8; 64];
+            reader.read_exact(&mut transcript)?;
+            writer.write_all(&transcript)?;
+        }
+
+        writer.flush()
+    }
+}
+
+/// How the points of serialized parameters are encoded. `write`
+/// writes them uncompressed, which is what `read` and bellman
+/// read, and compressed parameters are about half the size, to
+/// ship them; see `MPCParameters::recompress`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Uncompressed,
+    Compressed
+}
+
+/// Reads `len` points encoded as `from` and writes them encoded as
+/// `to`, decoding `DEFAULT_BATCH_SIZE` of them at a time.
+fn recompress_points<C: CurveAffine, R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    len: usize,
+    from: Compression,
+    to: Compression,
+    checked: bool
+) -> io::Result<()>
+{
+    let mut remaining = len;
+    while remaining > 0 {
+        let batch = ::std::cmp::min(remaining, DEFAULT_BATCH_SIZE);
+        let points: Vec<C> = match from {
+            Compression::Compressed => read_batch_par::<C::Compressed, _>(reader, batch, false, checked)?,
+            Compression::Uncompressed => read_batch_par::<C::Uncompressed, _>(reader, batch, false, checked)?,
+        };
+        for point in &points {
+            match to {
+                Compression::Compressed => writer.write_all(point.into_compressed().as_ref())?,
+                Compression::Uncompressed => writer.write_all(point.into_uncompressed().as_ref())?,
+            }
+        }
+        remaining -= batch;
+    }
+
+    Ok(())
+}
+
+fn check_limit(name: &str, len: usize, max: usize) -> io::Result<()> {
+    if len > max {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} has {} entries, more than the {} expected", name, len, max)
+        ));
+    }
+    Ok(())
+}
+
+
+/// Synthesizes `circuit` into its QAP polynomials, and returns them
+/// with the exponent of the size of their evaluation domain.
+fn synthesize<C>(circuit: C) -> Result<(KeypairAssembly<Bn256>, u32), SynthesisError>
+    where C: Circuit<Bn256>
+{
+    let assembly = assemble(circuit)?;
+    let exp = domain_exp(assembly.num_constraints);
+
+    // Powers of Tau ceremony can't support more than 2^28
+    if exp > MAX_DOMAIN_EXP {
+        return Err(SynthesisError::PolynomialDegreeTooLarge)
+    }
+
+    Ok((assembly, exp))
+}
+
+fn assemble<C>(circuit: C) -> Result<KeypairAssembly<Bn256>, SynthesisError>
+    where C: Circuit<Bn256>
+{
+    let mut assembly = KeypairAssembly {
+        num_inputs: 0,
+        num_aux: 0,
+        num_constraints: 0,
+        at_inputs: vec![],
+        bt_inputs: vec![],
+        ct_inputs: vec![],
+        at_aux: vec![],
+        bt_aux: vec![],
+        ct_aux: vec![]
+    };
+
+    // Allocate the "one" input variable
+    assembly.alloc_input(|| "", || Ok(Fr::one()))?;
+
+    // Synthesize the circuit.
+    circuit.synthesize(&mut assembly)?;
+
+    // Input constraints to ensure full density of IC query
+    // x * 0 = 0
+    for i in 0..assembly.num_inputs {
+        assembly.enforce(|| "",
+                         |lc| lc + Variable::new_unchecked(Index::Input(i)),
+                         |lc| lc,
+                         |lc| lc,
+        );
+    }
+
+    Ok(assembly)
+}
+
+/// Exponent of the size of our evaluation domain, the smallest
+/// power of two that's at least `num_constraints`
+fn domain_exp(num_constraints: usize) -> u32 {
+    let mut m = 1;
+    let mut exp = 0;
+    while m < num_constraints {
+        m *= 2;
+        exp += 1;
+    }
+
+    exp
+}
+
+/// Largest evaluation domain, 2^28, that the Powers of Tau
+/// ceremony supports.
+pub const MAX_DOMAIN_EXP: u32 = 28;
+
+/// Sizes of a circuit, and the evaluation domain and phase 1
+/// they need, to know which ceremony can set up a circuit before
+/// creating its parameters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircuitSize {
+    /// Public inputs, the constant one included
+    pub num_inputs: usize,
+    /// Private variables of the witness
+    pub num_aux: usize,
+    /// Constraints of the circuit
+    pub num_constraints: usize,
+    /// Size of the evaluation domain, which has room for the
+    /// constraints and one more constraint per input
+    pub domain_size: usize,
+    /// Exponent of the domain size, which is also the smallest
+    /// power of a phase 1 ceremony that can set up the circuit
+    pub domain_exp: u32,
+}
+
+impl CircuitSize {
+    /// Whether the Powers of Tau ceremony supports the domain.
+    pub fn fits_phase1(&self) -> bool {
+        self.domain_exp <= MAX_DOMAIN_EXP
+    }
+}
+
+/// Synthesizes `circuit` the way `MPCParameters::new` does and
+/// returns its sizes, without reading any phase 1 radix file. A
+/// circuit too large for phase 1 has a size all the same.
+pub fn circuit_size<C>(circuit: C) -> Result<CircuitSize, SynthesisError>
+    where C: Circuit<Bn256>
+{
+    let assembly = assemble(circuit)?;
+    let exp = domain_exp(assembly.num_constraints);
+
+    Ok(CircuitSize {
+        num_inputs: assembly.num_inputs,
+        num_aux: assembly.num_aux,
+        num_constraints: assembly.num_constraints - assembly.num_inputs,
+        domain_size: 1 << exp,
+        domain_exp: exp,
+    })
+}
+
+/// Opens the radix file of the domain of a circuit with
+/// `num_constraints` constraints, with an error that says which
+/// domain the circuit needs and which radix files there are if
+/// the file is missing or isn't the radix file of that domain.
+fn open_radix(radix_directory: &str, exp: u32, num_constraints: usize) -> Result<RadixFile, SynthesisError> {
+    RadixFile::open(radix_directory, exp).map_err(|e| {
+        let available = match available_exps(radix_directory).last() {
+            Some(largest) => format!("the radix files in {} go up to 2^{}", radix_directory, largest),
+            None => format!("there are no radix files in {}", radix_directory)
+        };
+        SynthesisError::IoError(io::Error::new(
+            e.kind(),
+            format!("the circuit and its inputs take {} constraints, which need a domain of 2^{}, but phase1radix2m{} can't be loaded ({}); {}", num_constraints, exp, exp, e, available)
+        ))
+    })
+}
+
+/// Multiplies all of `bases` by `coeff`.
+#[cfg(not(feature = "wasm"))]
+pub(crate) fn batch_exp<C: CurveAffine>(bases: &mut [C], coeff: C::Scalar, progress_update_interval: &u32, total_exps: &u32) {
+    let coeff = coeff.into_repr();
+
+    let mut projective = vec![C::Projective::zero(); bases.len()];
+    let cpus = num_cpus::get();
+    let chunk_size = if bases.len() < cpus {
+        1
+    } else {
+        bases.len() / cpus
+    };
+
+    // Perform wNAF over multiple cores, placing results into `projective`.
+    crossbeam::scope(|scope| {
+        for (bases, projective) in bases.chunks_mut(chunk_size)
+            .zip(projective.chunks_mut(chunk_size))
+            {
+                scope.spawn(move |_| {
+                    let mut wnaf = Wnaf::new();
+                    let mut count = 0;
+                    for (base, projective) in bases.iter_mut()
+                        .zip(projective.iter_mut())
+                        {
+                            *projective = wnaf.base(base.into_projective(), 1).scalar(coeff);
+                            count += 1;
+                            if *progress_update_interval > 0 && count % *progress_update_interval == 0 {
+                                println!("progress {} {}", *progress_update_interval, *total_exps)
+                            }
+                        }
+                });
+            }
+    }).unwrap();
+
+    // Perform batch normalization
+    crossbeam::scope(|scope| {
+        for projective in projective.chunks_mut(chunk_size)
+            {
+                scope.spawn(move |_| {
+                    C::Projective::batch_normalization(projective);
+                });
+            }
+    }).unwrap();
+
+    // Turn it all back into affine points
+    for (projective, affine) in projective.iter().zip(bases.iter_mut()) {
+        *affine = projective.into_affine();
+    }
 }
 
+#[cfg(all(feature = "wasm", not(feature = "u32-backend")))]
+pub(crate) fn batch_exp<C: CurveAffine>(bases: &mut [C], coeff: C::Scalar, progress_update_interval: &u32, total_exps: &u32) {
+    let coeff = coeff.into_repr();
+
+    let mut projective = vec![C::Projective::zero(); bases.len()];
+
+    // Perform wNAF, placing results into `projective`.
+    let mut wnaf = Wnaf::new();
+    let mut count = 0;
+    for (base, projective) in bases.iter_mut().zip(projective.iter_mut()) {
+        *projective = wnaf.base(base.into_projective(), 1).scalar(coeff);
+        count = count + 1;
+        if *progress_update_interval > 0 && count % *progress_update_interval == 0 {
+            println!("progress {} {}", *progress_update_interval, *total_exps)
+        }
+    }
+
+    // Perform batch normalization
+    C::Projective::batch_normalization(&mut projective);
+
+    // Turn it all back into affine points
+    for (projective, affine) in projective.iter().zip(bases.iter_mut()) {
+        *affine = projective.into_affine();
+    }
+}
+
+/// Multiplies all of `bases` by `coeff` with 32-bit limbs, which
+/// WebAssembly multiplies natively.
+#[cfg(all(feature = "wasm", feature = "u32-backend"))]
+pub(crate) fn batch_exp(bases: &mut [G1Affine], coeff: Fr, progress_update_interval: &u32, total_exps: &u32) {
+    super::u32_backend::batch_exp(bases, coeff, progress_update_interval, total_exps)
+}
+
+/// The number of points of each query of the parameters. With the
+/// number of contributions, they determine the size of the serialized
+/// parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueryLengths {
+    pub ic: usize,
+    pub h: usize,
+    pub l: usize,
+    pub a: usize,
+    pub b_g1: usize,
+    pub b_g2: usize
+}
+
+/// The largest queries and transcript that reading parameters
+/// accepts. The lengths in the file are checked against these
+/// before anything is read for them, so a malicious file can't
+/// make the reader allocate more than the expected parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReadLimits {
+    pub queries: QueryLengths,
+    pub contributions: usize
+}
+
+/// Powers of Tau ceremony can't support more than 2^28 constraints,
+/// and circuits don't come close to as many variables.
+pub const DEFAULT_MAX_QUERY_LENGTH: usize = 1 << 28;
+pub const DEFAULT_MAX_CONTRIBUTIONS: usize = 1 << 20;
+
+impl Default for ReadLimits {
+    fn default() -> Self {
+        let max = DEFAULT_MAX_QUERY_LENGTH;
+        ReadLimits {
+            queries: QueryLengths { ic: max, h: max, l: max, a: max, b_g1: max, b_g2: max },
+            contributions: DEFAULT_MAX_CONTRIBUTIONS
+        }
+    }
+}
+
+impl ReadLimits {
+    /// Limits for parameters with the queries of `params` and up
+    /// to `contributions` more contributions, such as the result
+    /// of contributing to `params`.
+    pub fn following(params: &MPCParameters, contributions: usize) -> Self {
+        ReadLimits {
+            queries: params.query_lengths(),
+            contributions: params.contribution_count().saturating_add(contributions)
+        }
+    }
+}
+
+/// Returns the size in bytes of parameters with queries of `lengths`
+/// and `contributions` public keys, as written by
+/// `MPCParameters::write`.
+pub fn expected_size(lengths: &QueryLengths, contributions: usize) -> u64 {
+    let g1 = G1Uncompressed::size() as u64;
+    let g2 = G2Uncompressed::size() as u64;
+
+    // alpha_g1, beta_g1, beta_g2, gamma_g2, delta_g1, delta_g2
+    let vk = 3 * g1 + 3 * g2;
+    // Every query is prefixed with its length
+    let g1_points = lengths.ic + lengths.h + lengths.l + lengths.a + lengths.b_g1;
+    let queries = 6 * 4 + g1_points as u64 * g1 + lengths.b_g2 as u64 * g2;
+    // delta_after, s, s_delta, r_delta and the transcript
+    let public_key = 3 * g1 + g2 + 64;
+
+    // The hash of the circuit and the number of contributions follow
+    // the queries
+    vk + queries + 64 + 4 + contributions as u64 * public_key
+}
+
+/// The hash of a contribution, which the contributor is given
+/// to find their contribution in the transcript.
+fn contribution_hash(pubkey: &PublicKey) -> [u8; 64] {
+    let sink = io::sink();
+    let mut sink = HashWriter::new(sink);
+    pubkey.write(&mut sink).unwrap();
+    let h = sink.into_hash();
+    let mut response = [0u8; 64];
+    response.copy_from_slice(h.as_ref());
+    response
+}
 
 /// This is a cheap helper utility that exists purely
 /// because Rust still doesn't have type-level integers
@@ -854,6 +1346,18 @@ pub fn verify_contribution(
 }
 
 
+/// Same as `keypair`, but the private key can only be used
+/// through a `SecretGuard`.
+pub fn guarded_keypair<R: Rng>(
+    rng: &mut R,
+    current: &MPCParameters,
+) -> (PublicKey, SecretGuard<PrivateKey>)
+{
+    let (pubkey, privkey) = keypair(rng, current);
+
+    (pubkey, SecretGuard::new(privkey))
+}
+
 /// Compute a keypair, given the current parameters. Keypairs
 /// cannot be reused for multiple contributions or contributions
 /// in different parameters.
@@ -862,20 +1366,36 @@ pub fn keypair<R: Rng>(
     current: &MPCParameters,
 ) -> (PublicKey, PrivateKey)
 {
-    // Sample random delta
-    let delta: Fr = rng.gen();
+    keypair_from_transcript(rng, &current.cs_hash, &current.contributions, current.params.vk.delta_g1)
+}
+
+/// Same as `keypair`, given only the parts of the parameters that
+/// the keypair depends on: the hash of the circuit, the previous
+/// contributions and delta in G1.
+pub fn keypair_from_transcript<R: Rng>(
+    rng: &mut R,
+    cs_hash: &[u8; 64],
+    contributions: &[PublicKey],
+    delta_g1: G1Affine,
+) -> (PublicKey, PrivateKey)
+{
+    // Sample random delta directly into the key, so that no
+    // copy of it outlives the key
+    let privkey = PrivateKey {
+        delta: rng.gen()
+    };
 
     // Compute delta s-pair in G1
     let s = G1::rand(rng).into_affine();
-    let s_delta = s.mul(delta).into_affine();
+    let s_delta = s.mul(privkey.delta).into_affine();
 
     // H(cs_hash | <previous pubkeys> | s | s_delta)
     let h = {
         let sink = io::sink();
         let mut sink = HashWriter::new(sink);
 
-        sink.write_all(&current.cs_hash[..]).unwrap();
-        for pubkey in &current.contributions {
+        sink.write_all(&cs_hash[..]).unwrap();
+        for pubkey in contributions {
             pubkey.write(&mut sink).unwrap();
         }
         sink.write_all(s.into_uncompressed().as_ref()).unwrap();
@@ -891,18 +1411,175 @@ pub fn keypair<R: Rng>(
 
     // Compute delta s-pair in G2
     let r = hash_to_g2(h.as_ref()).into_affine();
-    let r_delta = r.mul(delta).into_affine();
+    let r_delta = r.mul(privkey.delta).into_affine();
 
     (
         PublicKey {
-            delta_after: current.params.vk.delta_g1.mul(delta).into_affine(),
+            delta_after: delta_g1.mul(privkey.delta).into_affine(),
             s: s,
             s_delta: s_delta,
             r_delta: r_delta,
             transcript: transcript
         },
-        PrivateKey {
-            delta: delta
-        }
+        privkey
     )
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use bellman_ce::pairing::bn256::{G1Compressed, G2Compressed};
+    use rand::XorShiftRng;
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    pub(crate) fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c])
+    }
+
+    /// Knows a square root of its public input.
+    struct Square;
+
+    impl Circuit<Bn256> for Square {
+        fn synthesize<CS: ConstraintSystem<Bn256>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x = cs.alloc(|| "x", || Ok(Fr::from_str("3").unwrap()))?;
+            let y = cs.alloc_input(|| "y", || Ok(Fr::from_str("9").unwrap()))?;
+            cs.enforce(|| "x * x = y", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+            Ok(())
+        }
+    }
+
+    /// Parameters of a small circuit with `contributions`, built from
+    /// a phase 1 radix file of multiples of the generators, which is
+    /// as good as any for tests.
+    pub(crate) fn test_params(contributions: usize) -> MPCParameters {
+        // Every call has its own radix file, as tests run in parallel
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        let directory = env::temp_dir().join(format!("phase2_test_radix_{}_{}", process::id(), CALLS.fetch_add(1, Ordering::SeqCst)));
+        fs::create_dir_all(&directory).unwrap();
+
+        let exp = circuit_size(Square).unwrap().domain_exp;
+        let m = 1 << exp;
+        let g1 = |n: usize| G1Affine::one().mul(Fr::from_str(&(n + 2).to_string()).unwrap()).into_affine().into_uncompressed();
+        let g2 = |n: usize| G2Affine::one().mul(Fr::from_str(&(n + 2).to_string()).unwrap()).into_affine().into_uncompressed();
+        let mut radix = vec![];
+        radix.extend_from_slice(g1(0).as_ref());
+        radix.extend_from_slice(g1(1).as_ref());
+        radix.extend_from_slice(g2(2).as_ref());
+        // Lagrange coefficients in G1 and G2, times alpha and times beta, then H
+        for n in 0..m {
+            radix.extend_from_slice(g1(n).as_ref());
+        }
+        for n in 0..m {
+            radix.extend_from_slice(g2(n).as_ref());
+        }
+        for n in 0..3 * m - 1 {
+            radix.extend_from_slice(g1(m + n).as_ref());
+        }
+        fs::write(directory.join(format!("phase1radix2m{}", exp)), &radix).unwrap();
+
+        let params = MPCParameters::new(Square, false, directory.to_str().unwrap());
+        fs::remove_dir_all(&directory).unwrap();
+        let mut params = params.unwrap();
+        let rng = &mut rng();
+        for _ in 0..contributions {
+            params.contribute(rng, &0);
+        }
+        params
+    }
+
+    #[test]
+    fn test_open_radix() {
+        let directory = env::temp_dir().join(format!("phase2_test_open_radix_{}", process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        let radix_directory = directory.to_str().unwrap();
+        let message = |exp| match open_radix(radix_directory, exp, 5) {
+            Err(SynthesisError::IoError(e)) => e.to_string(),
+            _ => panic!("the radix file must be rejected"),
+        };
+
+        // The error says which domain the circuit needs, and which radix files there are
+        let missing = message(3);
+        assert!(missing.contains("5 constraints, which need a domain of 2^3"));
+        assert!(missing.contains("there are no radix files"));
+        fs::write(directory.join("phase1radix2m2"), vec![0; 10]).unwrap();
+        fs::write(directory.join("phase1radix2m3"), vec![0; 10]).unwrap();
+        let truncated = message(3);
+        assert!(truncated.contains("has 10 bytes"));
+        assert!(truncated.contains("go up to 2^3"));
+        // A circuit too large for any of the radix files
+        assert!(message(4).contains("go up to 2^3"));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_keypair_from_transcript() {
+        let params = test_params(2);
+        let (pubkey, privkey) = keypair(&mut rng(), &params);
+        let (from_transcript, privkey_from_transcript) = keypair_from_transcript(&mut rng(), &params.cs_hash, &params.contributions, params.params.vk.delta_g1);
+        assert!(pubkey == from_transcript);
+        assert!(privkey.delta == privkey_from_transcript.delta);
+        assert!(pubkey.delta_after == params.params.vk.delta_g1.mul(privkey.delta).into_affine());
+
+        // The key is bound to the transcript
+        let (other, _) = keypair_from_transcript(&mut rng(), &params.cs_hash, &params.contributions[1..], params.params.vk.delta_g1);
+        assert!(pubkey.transcript[..] != other.transcript[..]);
+    }
+
+    #[test]
+    fn test_batch_exp() {
+        let rng = &mut rng();
+        // More bases than cores, in chunks of uneven sizes
+        let bases = (0..2 * num_cpus::get() + 3).map(|_| G1::rand(rng).into_affine()).collect::<Vec<_>>();
+        let coeff = Fr::rand(rng);
+
+        let mut exps = bases.clone();
+        batch_exp(&mut exps, coeff, &0, &(bases.len() as u32));
+        for (base, exp) in bases.iter().zip(exps.iter()) {
+            assert!(*exp == base.mul(coeff).into_affine());
+        }
+    }
+
+    #[test]
+    fn test_recompress() {
+        let params = test_params(1);
+        let mut uncompressed = vec![];
+        params.write(&mut uncompressed).unwrap();
+        let recompress = |input: &[u8], from, to, checked| {
+            let mut output = vec![];
+            MPCParameters::recompress(input, &mut output, from, to, checked).map(|_| output)
+        };
+
+        let compressed = recompress(&uncompressed, Compression::Uncompressed, Compression::Compressed, true).unwrap();
+        assert!(compressed.len() < uncompressed.len());
+        for &checked in &[false, true] {
+            let decompressed = recompress(&compressed, Compression::Compressed, Compression::Uncompressed, checked).unwrap();
+            assert!(decompressed == uncompressed);
+            assert!(recompress(&compressed, Compression::Compressed, Compression::Compressed, checked).unwrap() == compressed);
+        }
+        assert!(MPCParameters::read(&uncompressed[..], false, true).unwrap() == params);
+
+        // Points are decoded as they are announced, and lengths are limited
+        let kind = |input: &[u8]| recompress(input, Compression::Compressed, Compression::Uncompressed, true).expect_err("must be rejected").kind();
+        assert_eq!(kind(&compressed[..compressed.len() - 1]), io::ErrorKind::UnexpectedEof);
+        assert_eq!(kind(&uncompressed), io::ErrorKind::InvalidData);
+        let mut too_long = compressed.clone();
+        let ic = 3 * G1Compressed::size() + 3 * G2Compressed::size();
+        too_long[ic..ic + 4].copy_from_slice(&[0xff; 4]);
+        assert_eq!(kind(&too_long), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_contribute() {
+        let before = test_params(1);
+        let mut after = before.clone();
+        let hash = after.contribute(&mut rng(), &0);
+
+        assert_eq!(after.contributions.len(), 2);
+        assert_eq!(verify_contribution(&before, &after).unwrap()[..], hash[..]);
+    }
+}
@@ -0,0 +1,207 @@
+//! Lazy access to the phase 1 radix files (`phase1radix2m{exp}`)
+//! prepared for phase 2. Only the header is read up front; the
+//! coefficient sections are read and decoded in batches on demand,
+//! so that building parameters never needs all of them in memory.
+
+extern crate bellman_ce;
+
+use std::fs::{self, File};
+use std::io::{
+    self,
+    BufReader,
+    Seek,
+    SeekFrom,
+};
+
+use bellman_ce::pairing::{
+    EncodedPoint,
+    CurveAffine,
+    bn256::{
+        G1Affine,
+        G1Uncompressed,
+        G2Affine,
+        G2Uncompressed
+    }
+};
+
+use super::utils::read_batch_par;
+
+/// Coefficients read and decoded at once when building parameters.
+pub const DEFAULT_BATCH_SIZE: usize = 1 << 21;
+
+/// Sections of a radix file, in file order, after the header
+/// (alpha in G1, beta in G1, beta in G2).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Section {
+    /// Lagrange coefficients in G1
+    CoeffsG1,
+    /// Lagrange coefficients in G2
+    CoeffsG2,
+    /// Lagrange coefficients times alpha in G1
+    AlphaCoeffsG1,
+    /// Lagrange coefficients times beta in G1
+    BetaCoeffsG1,
+    /// Powers of tau times the vanishing polynomial, `m - 1` of them
+    H,
+}
+
+pub struct RadixFile {
+    file: File,
+    /// Size of the evaluation domain
+    m: usize,
+    pub alpha_g1: G1Affine,
+    pub beta_g1: G1Affine,
+    pub beta_g2: G2Affine,
+}
+
+impl RadixFile {
+    /// Opens `radix_directory/phase1radix2m{exp}` and reads its header.
+    /// Fails if the file doesn't have the size of the radix file of
+    /// a domain of `2^exp`, rather than reading past its end later.
+    pub fn open(radix_directory: &str, exp: u32) -> io::Result<RadixFile> {
+        let path = format!("{}/phase1radix2m{}", radix_directory, exp);
+        let file = File::open(&path)?;
+        let size = file.metadata()?.len();
+        if size != radix_file_size(exp) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} has {} bytes, the radix file of a domain of 2^{} has {}", path, size, exp, radix_file_size(exp))
+            ));
+        }
+        let mut radix = RadixFile {
+            file,
+            m: 1 << exp,
+            alpha_g1: G1Affine::zero(),
+            beta_g1: G1Affine::zero(),
+            beta_g2: G2Affine::zero(),
+        };
+
+        radix.file.seek(SeekFrom::Start(0))?;
+        let mut reader = BufReader::new(&radix.file);
+        radix.alpha_g1 = read_batch_par::<G1Uncompressed, _>(&mut reader, 1, true, false)?[0];
+        radix.beta_g1 = read_batch_par::<G1Uncompressed, _>(&mut reader, 1, true, false)?[0];
+        radix.beta_g2 = read_batch_par::<G2Uncompressed, _>(&mut reader, 1, true, false)?[0];
+
+        Ok(radix)
+    }
+
+    /// Number of elements in `section`.
+    pub fn section_len(&self, section: Section) -> usize {
+        match section {
+            Section::H => self.m - 1,
+            _ => self.m
+        }
+    }
+
+    fn section_offset(&self, section: Section) -> u64 {
+        let g1 = G1Uncompressed::size() as u64;
+        let g2 = G2Uncompressed::size() as u64;
+        let m = self.m as u64;
+        let header = 2 * g1 + g2;
+
+        match section {
+            Section::CoeffsG1 => header,
+            Section::CoeffsG2 => header + m * g1,
+            Section::AlphaCoeffsG1 => header + m * g1 + m * g2,
+            Section::BetaCoeffsG1 => header + 2 * m * g1 + m * g2,
+            Section::H => header + 3 * m * g1 + m * g2,
+        }
+    }
+
+    /// Reads `len` elements of a G1 section, starting at element `start`.
+    pub fn read_g1(&mut self, section: Section, start: usize, len: usize) -> io::Result<Vec<G1Affine>> {
+        assert!(section != Section::CoeffsG2, "coefficients in G2 are not in G1");
+        assert!(start + len <= self.section_len(section), "range exceeds the section");
+
+        let offset = self.section_offset(section) + (start * G1Uncompressed::size()) as u64;
+        self.file.seek(SeekFrom::Start(offset))?;
+        read_batch_par::<G1Uncompressed, _>(&mut BufReader::new(&self.file), len, true, false)
+    }
+
+    /// Reads `len` Lagrange coefficients in G2, starting at element `start`.
+    pub fn read_g2(&mut self, start: usize, len: usize) -> io::Result<Vec<G2Affine>> {
+        assert!(start + len <= self.m, "range exceeds the section");
+
+        let offset = self.section_offset(Section::CoeffsG2) + (start * G2Uncompressed::size()) as u64;
+        self.file.seek(SeekFrom::Start(offset))?;
+        read_batch_par::<G2Uncompressed, _>(&mut BufReader::new(&self.file), len, true, false)
+    }
+
+    /// Ranges of at most `batch_size` elements covering `section`.
+    pub fn batches(&self, section: Section, batch_size: usize) -> Vec<(usize, usize)> {
+        let len = self.section_len(section);
+
+        (0..len).step_by(batch_size).map(|start| (start, ::std::cmp::min(batch_size, len - start))).collect()
+    }
+}
+
+/// Size of the radix file of a domain of `2^exp`: the header, four
+/// sections of `m` elements and the H section of `m - 1` elements.
+pub fn radix_file_size(exp: u32) -> u64 {
+    let g1 = G1Uncompressed::size() as u64;
+    let g2 = G2Uncompressed::size() as u64;
+    let m = 1u64 << exp;
+
+    g1 + g2 + m * (4 * g1 + g2)
+}
+
+/// Exponents of the domains of the radix files in `radix_directory`,
+/// in increasing order. A directory that can't be read has none.
+pub fn available_exps(radix_directory: &str) -> Vec<u32> {
+    let mut exps: Vec<u32> = match fs::read_dir(radix_directory) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().to_str()
+                .and_then(|name| name.strip_prefix("phase1radix2m"))
+                .and_then(|exp| exp.parse().ok()))
+            .collect(),
+        Err(_) => vec![]
+    };
+    exps.sort_unstable();
+
+    exps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    fn directory(name: &str) -> String {
+        let directory = env::temp_dir().join(format!("phase2_test_{}_{}", name, process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_truncated_radix_file() {
+        let directory = directory("truncated_radix");
+        let size = radix_file_size(3) as usize;
+        for len in &[0, size - 1] {
+            fs::write(format!("{}/phase1radix2m3", directory), vec![0; *len]).unwrap();
+            let e = RadixFile::open(&directory, 3).err().expect("a truncated file must be rejected");
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+            assert!(e.to_string().contains(&format!("has {} bytes", len)));
+        }
+        // Nor is a radix file taken for that of another domain
+        assert_eq!(RadixFile::open(&directory, 2).err().unwrap().kind(), io::ErrorKind::NotFound);
+        fs::rename(format!("{}/phase1radix2m3", directory), format!("{}/phase1radix2m2", directory)).unwrap();
+        assert_eq!(RadixFile::open(&directory, 2).err().unwrap().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(available_exps(&directory), vec![2]);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_missing_radix_file() {
+        let directory = directory("missing_radix");
+        assert_eq!(RadixFile::open(&directory, 3).err().unwrap().kind(), io::ErrorKind::NotFound);
+        assert!(available_exps(&directory).is_empty());
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(RadixFile::open(&directory, 3).err().unwrap().kind(), io::ErrorKind::NotFound);
+        assert!(available_exps(&directory).is_empty());
+    }
+}
@@ -0,0 +1,505 @@
+//! Multiplication of G1 points by a scalar with 32-bit limbs, for
+//! the WebAssembly build. WebAssembly multiplies 64-bit integers
+//! natively but has no widening multiplication, so every product
+//! of the 64-bit limbs of the generic field arithmetic goes through
+//! an emulated 128-bit multiplication, and these dominate the time
+//! of a contribution in the browser. With 32-bit limbs, every limb
+//! product is a single native 64-bit multiplication.
+//!
+//! Points are exchanged with `bellman_ce` through their raw
+//! encoding, which holds the coordinates in Montgomery form. The
+//! Montgomery constant is 2^256 with either limb size, so the
+//! coordinates are used as they are.
+
+extern crate bellman_ce;
+
+use bellman_ce::pairing::{
+    ff::{
+        PrimeField,
+        PrimeFieldRepr,
+    },
+    EncodedPoint,
+    CurveAffine,
+    RawEncodable,
+    bn256::{
+        Fr,
+        G1Affine,
+        G1Uncompressed,
+    }
+};
+
+/// The modulus of the base field, in little-endian limbs.
+const MODULUS: [u32; 8] = [
+    0xd87cfd47, 0x3c208c16, 0x6871ca8d, 0x97816a91,
+    0x8181585d, 0xb85045b6, 0xe131a029, 0x30644e72
+];
+
+/// -MODULUS^-1 mod 2^32
+const INV: u32 = 0xe4866389;
+
+/// 2^256 mod MODULUS, which is one in Montgomery form.
+const ONE: [u32; 8] = [
+    0xc58f0d9d, 0xd35d438d, 0xf5c70b3d, 0x0a78eb28,
+    0x7879462c, 0x666ea36f, 0x9a07df2f, 0x0e0a77c1
+];
+
+/// MODULUS - 2, the exponent that inverts an element.
+const MODULUS_MINUS_TWO: [u32; 8] = [
+    0xd87cfd45, 0x3c208c16, 0x6871ca8d, 0x97816a91,
+    0x8181585d, 0xb85045b6, 0xe131a029, 0x30644e72
+];
+
+/// The window of the signed digits of the scalar, which are odd
+/// and less than 2^WINDOW in absolute value.
+const WINDOW: u32 = 4;
+
+/// An element of the base field in Montgomery form.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Fq([u32; 8]);
+
+impl Fq {
+    fn zero() -> Fq {
+        Fq([0; 8])
+    }
+
+    fn one() -> Fq {
+        Fq(ONE)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|&limb| limb == 0)
+    }
+
+    /// Subtracts the modulus unless the element is already reduced.
+    fn reduce(mut limbs: [u32; 8]) -> Fq {
+        let reduced = limbs.iter().zip(MODULUS.iter()).rev()
+            .find(|(limb, modulus)| limb != modulus)
+            .is_some_and(|(limb, modulus)| limb < modulus);
+        if !reduced {
+            let mut borrow = 0;
+            for (limb, modulus) in limbs.iter_mut().zip(MODULUS.iter()) {
+                let (difference, borrow_1) = limb.overflowing_sub(*modulus);
+                let (difference, borrow_2) = difference.overflowing_sub(borrow);
+                *limb = difference;
+                borrow = (borrow_1 || borrow_2) as u32;
+            }
+        }
+
+        Fq(limbs)
+    }
+
+    fn add(&self, other: &Fq) -> Fq {
+        // The modulus is less than 2^255, so the sum doesn't overflow
+        let mut limbs = [0; 8];
+        let mut carry = 0u64;
+        for (limb, (a, b)) in limbs.iter_mut().zip(self.0.iter().zip(other.0.iter())) {
+            let sum = *a as u64 + *b as u64 + carry;
+            *limb = sum as u32;
+            carry = sum >> 32;
+        }
+
+        Fq::reduce(limbs)
+    }
+
+    fn double(&self) -> Fq {
+        self.add(self)
+    }
+
+    fn sub(&self, other: &Fq) -> Fq {
+        let mut limbs = [0; 8];
+        let mut borrow = 0;
+        for (limb, (a, b)) in limbs.iter_mut().zip(self.0.iter().zip(other.0.iter())) {
+            let (difference, borrow_1) = a.overflowing_sub(*b);
+            let (difference, borrow_2) = difference.overflowing_sub(borrow);
+            *limb = difference;
+            borrow = (borrow_1 || borrow_2) as u32;
+        }
+        if borrow == 1 {
+            let mut carry = 0u64;
+            for (limb, modulus) in limbs.iter_mut().zip(MODULUS.iter()) {
+                let sum = *limb as u64 + *modulus as u64 + carry;
+                *limb = sum as u32;
+                carry = sum >> 32;
+            }
+        }
+
+        Fq(limbs)
+    }
+
+    fn negate(&self) -> Fq {
+        Fq::zero().sub(self)
+    }
+
+    /// Montgomery multiplication, interleaving the product and the
+    /// reduction of every limb of `other`.
+    fn mul(&self, other: &Fq) -> Fq {
+        let mut t = [0u32; 10];
+        for i in 0..8 {
+            let mut carry = 0u64;
+            for (t, limb) in t.iter_mut().zip(self.0.iter()) {
+                let sum = *t as u64 + *limb as u64 * other.0[i] as u64 + carry;
+                *t = sum as u32;
+                carry = sum >> 32;
+            }
+            let sum = t[8] as u64 + carry;
+            t[8] = sum as u32;
+            t[9] = (sum >> 32) as u32;
+
+            let m = t[0].wrapping_mul(INV);
+            let mut carry = (t[0] as u64 + m as u64 * MODULUS[0] as u64) >> 32;
+            for j in 1..8 {
+                let sum = t[j] as u64 + m as u64 * MODULUS[j] as u64 + carry;
+                t[j - 1] = sum as u32;
+                carry = sum >> 32;
+            }
+            let sum = t[8] as u64 + carry;
+            t[7] = sum as u32;
+            t[8] = t[9] + (sum >> 32) as u32;
+        }
+
+        // The modulus is less than 2^254, so the result is less than
+        // twice the modulus and t[8] is zero
+        let mut limbs = [0; 8];
+        limbs.copy_from_slice(&t[..8]);
+        Fq::reduce(limbs)
+    }
+
+    fn square(&self) -> Fq {
+        self.mul(self)
+    }
+
+    /// The inverse of a non-zero element, by Fermat's little theorem.
+    fn inverse(&self) -> Fq {
+        let mut result = Fq::one();
+        for limb in MODULUS_MINUS_TWO.iter().rev() {
+            for bit in (0..32).rev() {
+                result = result.square();
+                if (limb >> bit) & 1 == 1 {
+                    result = result.mul(self);
+                }
+            }
+        }
+
+        result
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Fq {
+        let mut limbs = [0; 8];
+        for (limb, bytes) in limbs.iter_mut().zip(bytes.chunks(4)) {
+            *limb = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+
+        Fq(limbs)
+    }
+
+    fn write_bytes(&self, bytes: &mut [u8]) {
+        for (limb, bytes) in self.0.iter().zip(bytes.chunks_mut(4)) {
+            bytes.copy_from_slice(&limb.to_le_bytes());
+        }
+    }
+}
+
+/// A point of G1 in Jacobian coordinates, which is the point at
+/// infinity when `z` is zero.
+#[derive(Clone, Copy)]
+struct G1 {
+    x: Fq,
+    y: Fq,
+    z: Fq,
+}
+
+impl G1 {
+    fn zero() -> G1 {
+        G1 { x: Fq::zero(), y: Fq::one(), z: Fq::zero() }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.z.is_zero()
+    }
+
+    fn from_affine(point: &G1Affine) -> G1 {
+        if point.is_zero() {
+            return G1::zero();
+        }
+        let encoded = point.into_raw_uncompressed_le();
+        let bytes = encoded.as_ref();
+
+        G1 {
+            x: Fq::from_bytes(&bytes[..32]),
+            y: Fq::from_bytes(&bytes[32..]),
+            z: Fq::one(),
+        }
+    }
+
+    fn negate(&self) -> G1 {
+        G1 { x: self.x, y: self.y.negate(), z: self.z }
+    }
+
+    /// Doubling for curves with a = 0, "dbl-2009-l" in the
+    /// Explicit-Formulas Database.
+    fn double(&self) -> G1 {
+        if self.is_zero() {
+            return *self;
+        }
+        let a = self.x.square();
+        let b = self.y.square();
+        let c = b.square();
+        let d = self.x.add(&b).square().sub(&a).sub(&c).double();
+        let e = a.double().add(&a);
+        let f = e.square();
+        let x = f.sub(&d.double());
+        let y = e.mul(&d.sub(&x)).sub(&c.double().double().double());
+        let z = self.y.mul(&self.z).double();
+
+        G1 { x, y, z }
+    }
+
+    /// Addition, "add-2007-bl" in the Explicit-Formulas Database.
+    fn add(&self, other: &G1) -> G1 {
+        if self.is_zero() {
+            return *other;
+        }
+        if other.is_zero() {
+            return *self;
+        }
+        let z1z1 = self.z.square();
+        let z2z2 = other.z.square();
+        let u1 = self.x.mul(&z2z2);
+        let u2 = other.x.mul(&z1z1);
+        let s1 = self.y.mul(&other.z).mul(&z2z2);
+        let s2 = other.y.mul(&self.z).mul(&z1z1);
+        if u1 == u2 {
+            return if s1 == s2 { self.double() } else { G1::zero() };
+        }
+        let h = u2.sub(&u1);
+        let i = h.double().square();
+        let j = h.mul(&i);
+        let r = s2.sub(&s1).double();
+        let v = u1.mul(&i);
+        let x = r.square().sub(&j).sub(&v.double());
+        let y = r.mul(&v.sub(&x)).sub(&s1.mul(&j).double());
+        let z = self.z.add(&other.z).square().sub(&z1z1).sub(&z2z2).mul(&h);
+
+        G1 { x, y, z }
+    }
+
+    /// Multiplies the point by the scalar with the signed `digits`,
+    /// least significant first.
+    fn mul(&self, digits: &[i8]) -> G1 {
+        // The odd multiples of the point up to 2^WINDOW
+        let double = self.double();
+        let mut table = vec![*self];
+        for i in 1..(1 << (WINDOW - 1)) {
+            let next = table[i - 1].add(&double);
+            table.push(next);
+        }
+
+        let mut result = G1::zero();
+        for &digit in digits.iter().rev() {
+            result = result.double();
+            if digit > 0 {
+                result = result.add(&table[(digit / 2) as usize]);
+            } else if digit < 0 {
+                result = result.add(&table[(-digit / 2) as usize].negate());
+            }
+        }
+
+        result
+    }
+}
+
+/// The digits of `scalar` in the width-(WINDOW + 1) non-adjacent
+/// form, least significant first.
+fn wnaf_digits(scalar: Fr) -> Vec<i8> {
+    let mut scalar = scalar.into_repr();
+    let mut digits = vec![];
+    while !scalar.is_zero() {
+        let mut digit = 0;
+        if scalar.is_odd() {
+            digit = (scalar.as_ref()[0] % (1 << (WINDOW + 1))) as i64;
+            if digit > 1 << WINDOW {
+                digit -= 1 << (WINDOW + 1);
+            }
+            if digit > 0 {
+                scalar.sub_noborrow(&(digit as u64).into());
+            } else {
+                scalar.add_nocarry(&((-digit) as u64).into());
+            }
+        }
+        digits.push(digit as i8);
+        scalar.shr(1);
+    }
+
+    digits
+}
+
+/// Converts `points` to affine coordinates with a single inversion.
+fn batch_normalization(points: &[G1], affine: &mut [G1Affine]) {
+    // The products of the z coordinates of the points before each one
+    let mut products = Vec::with_capacity(points.len());
+    let mut product = Fq::one();
+    for point in points.iter().filter(|point| !point.is_zero()) {
+        products.push(product);
+        product = product.mul(&point.z);
+    }
+
+    let mut inverse = product.inverse();
+    let points = points.iter().zip(affine.iter_mut()).rev()
+        .filter(|(point, _)| !point.is_zero());
+    for ((point, affine), product) in points.zip(products.iter().rev()) {
+        let z_inverse = inverse.mul(product);
+        inverse = inverse.mul(&point.z);
+        let z_inverse_squared = z_inverse.square();
+
+        let mut encoded = G1Uncompressed::empty();
+        point.x.mul(&z_inverse_squared).write_bytes(&mut encoded.as_mut()[..32]);
+        point.y.mul(&z_inverse_squared).mul(&z_inverse).write_bytes(&mut encoded.as_mut()[32..]);
+        *affine = G1Affine::from_raw_uncompressed_le_unchecked(&encoded, false)
+            .expect("coordinates are reduced");
+    }
+}
+
+/// Multiplies all of `bases` by `coeff`, like the generic
+/// `batch_exp` of the parameters.
+pub fn batch_exp(bases: &mut [G1Affine], coeff: Fr, progress_update_interval: &u32, total_exps: &u32) {
+    let digits = wnaf_digits(coeff);
+
+    let mut projective = Vec::with_capacity(bases.len());
+    let mut count = 0;
+    for base in bases.iter() {
+        projective.push(G1::from_affine(base).mul(&digits));
+        count += 1;
+        if *progress_update_interval > 0 && count % *progress_update_interval == 0 {
+            println!("progress {} {}", *progress_update_interval, *total_exps)
+        }
+    }
+
+    for (point, base) in projective.iter().zip(bases.iter_mut()) {
+        if point.is_zero() {
+            *base = G1Affine::zero();
+        }
+    }
+    batch_normalization(&projective, bases);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bellman_ce::pairing::{
+        ff::Field,
+        CurveProjective,
+        bn256,
+    };
+    use rand::{Rand, SeedableRng, XorShiftRng};
+
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654])
+    }
+
+    /// The element of the generic arithmetic, which is in Montgomery form as well.
+    fn fq(element: bn256::Fq) -> Fq {
+        let mut bytes = vec![];
+        element.into_raw_repr().write_le(&mut bytes).unwrap();
+        Fq::from_bytes(&bytes)
+    }
+
+    /// Multiplies `bases` by `coeff` with the generic arithmetic.
+    fn generic_exp(bases: &[G1Affine], coeff: Fr) -> Vec<G1Affine> {
+        bases.iter().map(|base| base.mul(coeff).into_affine()).collect()
+    }
+
+    #[test]
+    fn test_field() {
+        let rng = &mut rng();
+        assert!(Fq::one() == fq(bn256::Fq::one()));
+        assert!(Fq::zero() == fq(bn256::Fq::zero()));
+        for _ in 0..100 {
+            let (a, b) = (bn256::Fq::rand(rng), bn256::Fq::rand(rng));
+
+            let mut sum = a;
+            sum.add_assign(&b);
+            assert!(fq(a).add(&fq(b)) == fq(sum));
+            let mut difference = a;
+            difference.sub_assign(&b);
+            assert!(fq(a).sub(&fq(b)) == fq(difference));
+            let mut negated = a;
+            negated.negate();
+            assert!(fq(a).negate() == fq(negated));
+            let mut product = a;
+            product.mul_assign(&b);
+            assert!(fq(a).mul(&fq(b)) == fq(product));
+            let mut square = a;
+            square.square();
+            assert!(fq(a).square() == fq(square));
+            assert!(fq(a).inverse() == fq(a.inverse().unwrap()));
+            assert!(fq(a).mul(&fq(a).inverse()) == Fq::one());
+        }
+        assert!(Fq::one().inverse() == Fq::one());
+        assert!(Fq::zero().negate() == Fq::zero());
+    }
+
+    #[test]
+    fn test_infinity() {
+        let rng = &mut rng();
+        assert!(G1::zero().double().is_zero());
+        assert!(G1::from_affine(&G1Affine::zero()).is_zero());
+
+        let point = G1::from_affine(&bn256::G1::rand(rng).into_affine());
+        assert!(point.add(&point.negate()).is_zero());
+        assert!(G1::zero().add(&point).add(&G1::zero()).x == point.x);
+
+        let coeff = Fr::rand(rng);
+        let mut bases = vec![G1Affine::zero(), bn256::G1::rand(rng).into_affine(), G1Affine::zero()];
+        let expected = generic_exp(&bases, coeff);
+        batch_exp(&mut bases, coeff, &0, &3);
+        assert_eq!(bases, expected);
+    }
+
+    #[test]
+    fn test_scalars() {
+        let rng = &mut rng();
+        let points: Vec<G1Affine> = (0..10).map(|_| bn256::G1::rand(rng).into_affine()).collect();
+        let mut minus_one = Fr::one();
+        minus_one.negate();
+        for &coeff in [Fr::zero(), Fr::one(), minus_one, Fr::from_str("2").unwrap(), Fr::from_str("17").unwrap()].iter() {
+            let mut bases = points.clone();
+            batch_exp(&mut bases, coeff, &0, &10);
+            assert_eq!(bases, generic_exp(&points, coeff));
+        }
+
+        let mut bases = points.clone();
+        batch_exp(&mut bases, Fr::zero(), &0, &10);
+        assert!(bases.iter().all(|base| base.is_zero()));
+        let mut bases = points.clone();
+        batch_exp(&mut bases, Fr::one(), &0, &10);
+        assert_eq!(bases, points);
+    }
+
+    #[test]
+    fn test_batch_exp() {
+        let rng = &mut rng();
+        let mut bases: Vec<G1Affine> = (0..100).map(|_| bn256::G1::rand(rng).into_affine()).collect();
+        bases.push(G1Affine::one());
+        for _ in 0..5 {
+            let coeff = Fr::rand(rng);
+            let expected = generic_exp(&bases, coeff);
+            batch_exp(&mut bases, coeff, &0, &101);
+            assert_eq!(bases, expected);
+        }
+    }
+    #[test]
+    fn test_multiexp() {
+        let rng = &mut rng();
+        let mut expected = bn256::G1::zero();
+        let mut result = G1::zero();
+        for _ in 0..50 {
+            let (base, scalar) = (bn256::G1::rand(rng).into_affine(), Fr::rand(rng));
+            expected.add_assign(&base.mul(scalar));
+            result = result.add(&G1::from_affine(&base).mul(&wnaf_digits(scalar)));
+        }
+
+        let mut affine = [G1Affine::zero()];
+        batch_normalization(&[result], &mut affine);
+        assert_eq!(affine[0], expected.into_affine());
+    }
+}
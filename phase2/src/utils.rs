@@ -2,17 +2,30 @@ extern crate bellman_ce;
 extern crate rand;
 extern crate byteorder;
 
+#[cfg(feature = "wasm")]
+use bellman_ce::singlecore::Worker;
+#[cfg(not(feature = "wasm"))]
+use bellman_ce::multicore::Worker;
+
 use byteorder::{
     BigEndian,
     ReadBytesExt,
 };
 use num_bigint::BigUint;
 use num_traits::Num;
-use std::sync::Arc;
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use std::io::{
+    self,
+    Read,
+};
 use bellman_ce::pairing::{
     ff::{
         PrimeField,
     },
+    EncodedPoint,
     CurveAffine,
     CurveProjective,
     Wnaf,
@@ -43,6 +56,95 @@ pub fn same_ratio<G1: CurveAffine>(
     g1.0.pairing_with(&g2.1) == g1.1.pairing_with(&g2.0)
 }
 
+/// Reads `len` encoded points and decodes them one by one, as
+/// bellman does when reading parameters.
+pub fn read_batch<ENC: EncodedPoint, R: Read>(
+    reader: &mut R,
+    len: usize,
+    disallow_points_at_infinity: bool,
+    checked: bool
+) -> io::Result<Vec<ENC::Affine>>
+{
+    let mut decoded = vec![];
+    for _ in 0..len {
+        let mut encoded = ENC::empty();
+        reader.read_exact(encoded.as_mut())?;
+        let point = if checked {
+            encoded.into_affine()
+        } else {
+            encoded.into_affine_unchecked()
+        }.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if disallow_points_at_infinity && point.is_zero() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "point at infinity"));
+        }
+        decoded.push(point);
+    }
+
+    Ok(decoded)
+}
+
+/// Reads `len` encoded points and decodes them on all available
+/// cores, since decoding (and the subgroup check in particular)
+/// dominates the time spent reading parameters. Points are decoded
+/// one by one when built for wasm.
+pub fn read_batch_par<ENC: EncodedPoint, R: Read>(
+    reader: &mut R,
+    len: usize,
+    disallow_points_at_infinity: bool,
+    checked: bool
+) -> io::Result<Vec<ENC::Affine>>
+{
+    // `len` usually comes from the file itself, so the buffer only
+    // grows as points are actually read from it.
+    let mut encoded = vec![];
+    for _ in 0..len {
+        let mut point = ENC::empty();
+        reader.read_exact(point.as_mut())?;
+        encoded.push(point);
+    }
+
+    let mut decoded = vec![ENC::Affine::zero(); len];
+
+    // If any of the threads encounters a decoding error, catch it with this.
+    let decoding_error = Arc::new(Mutex::new(None));
+
+    let worker = Worker::new();
+    worker.scope(len, |scope, chunk| {
+        for (encoded, decoded) in encoded.chunks(chunk).zip(decoded.chunks_mut(chunk)) {
+            let decoding_error = decoding_error.clone();
+
+            scope.spawn(move |_| {
+                for (encoded, decoded) in encoded.iter().zip(decoded.iter_mut()) {
+                    let point = if checked {
+                        encoded.into_affine()
+                    } else {
+                        encoded.into_affine_unchecked()
+                    };
+
+                    match point {
+                        Ok(point) => {
+                            if disallow_points_at_infinity && point.is_zero() {
+                                *decoding_error.lock().unwrap() = Some(io::Error::new(io::ErrorKind::InvalidData, "point at infinity"));
+                                return;
+                            }
+                            *decoded = point;
+                        },
+                        Err(e) => {
+                            *decoding_error.lock().unwrap() = Some(io::Error::new(io::ErrorKind::InvalidData, e));
+                            return;
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    match Arc::try_unwrap(decoding_error).unwrap().into_inner().unwrap() {
+        Some(e) => Err(e),
+        None => Ok(decoded)
+    }
+}
+
 /// Computes a random linear combination over v1/v2.
 ///
 /// Checking that many pairs of elements are exponentiated by
@@ -58,7 +160,6 @@ pub fn same_ratio<G1: CurveAffine>(
 /// ... with high probability.
 pub fn merge_pairs<G: CurveAffine>(v1: &[G], v2: &[G]) -> (G, G)
 {
-    use std::sync::Mutex;
     use rand::{thread_rng};
 
     assert_eq!(v1.len(), v2.len());
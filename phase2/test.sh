@@ -18,6 +18,7 @@ npx circom circuit.circom -o circuit.json && npx snarkjs info -c circuit.json
 
 # initialize ceremony
 cargo run --release --bin new circuit.json circom1.params ./
+cargo run --release --bin verify_initial circuit.json circom1.params ./
 
 cargo run --release --bin contribute circom1.params circom2.params asdajdzixcjlzxjczxlkcjzxlkcj
 cargo run --release --bin verify_contribution circuit.json circom1.params circom2.params ./
@@ -27,6 +28,7 @@ cargo run --release --bin verify_contribution circuit.json circom2.params circom
 
 cargo run --release --bin contribute circom3.params circom4.params askldfjklasdf
 cargo run --release --bin verify_contribution circuit.json circom3.params circom4.params ./
+cargo run --release --bin verify_h_query circom4.params ./
 
 # create dummy keys in circom format
 echo "Generating dummy key files..."
@@ -0,0 +1,40 @@
+//! Records the commit and target the crate is built from, see `build_info::BuildInfo`.
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let output = String::from_utf8(output.stdout).ok()?;
+    Some(output.trim().to_string())
+}
+
+fn main() {
+    // Builds from a source archive have no repository, the commit can be given instead
+    println!("cargo:rerun-if-env-changed=POWERSOFTAU_GIT_COMMIT");
+    let commit = env::var("POWERSOFTAU_GIT_COMMIT")
+        .ok()
+        .or_else(|| git(&["rev-parse", "HEAD"]))
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=POWERSOFTAU_GIT_COMMIT={}", commit);
+
+    // Build again when another commit is checked out
+    if let Some(git_dir) = git(&["rev-parse", "--git-dir"]) {
+        let git_dir = Path::new(&git_dir);
+        println!("cargo:rerun-if-changed={}", git_dir.join("HEAD").display());
+        if let Some(reference) = git(&["symbolic-ref", "-q", "HEAD"]) {
+            println!(
+                "cargo:rerun-if-changed={}",
+                git_dir.join(reference).display()
+            );
+        }
+    }
+
+    println!(
+        "cargo:rustc-env=POWERSOFTAU_TARGET={}",
+        env::var("TARGET").unwrap_or_else(|_| "unknown".to_string())
+    );
+}
@@ -0,0 +1,49 @@
+//! Measures how fast the hash of a challenge or response is computed. Verifying a contribution
+//! hashes both files, which is a single stream of BLAKE2b each and can't use more than one core.
+//!
+//! cargo run --release --example hash_throughput -- [size in MiB, 1024 by default]
+//!
+//! It compares `utils::calculate_hash` with the portable BLAKE2b of the `blake2` crate that it
+//! used before, and with the tree hash, which splits the file into leaves hashed on all cores
+//! but is a different hash, only used for transfers.
+
+use blake2::{Blake2b, Digest};
+use powersoftau::tree_hash::{TreeHash, DEFAULT_LEAF_SIZE};
+use powersoftau::utils::calculate_hash;
+
+use std::time::Instant;
+
+fn measure<F: FnMut()>(name: &str, size: usize, mut hash: F) {
+    // The first run brings the buffer into the caches and the CPU out of power saving
+    hash();
+    let start = Instant::now();
+    hash();
+    let seconds = start.elapsed().as_secs_f64();
+    println!(
+        "{:<24} {:>8.3} s {:>10.1} MiB/s",
+        name,
+        seconds,
+        size as f64 / seconds / (1 << 20) as f64
+    );
+}
+
+fn main() {
+    let mebibytes: usize = std::env::args()
+        .nth(1)
+        .map(|size| size.parse().expect("the size must be a number of MiB"))
+        .unwrap_or(1024);
+    let size = mebibytes << 20;
+    let data: Vec<u8> = (0..size).map(|i| (i * 31 + i / 4093) as u8).collect();
+
+    assert_eq!(calculate_hash(&data), Blake2b::digest(&data));
+    println!("Hashing {} MiB", mebibytes);
+    measure("blake2 (portable)", size, || {
+        Blake2b::digest(&data);
+    });
+    measure("calculate_hash", size, || {
+        calculate_hash(&data);
+    });
+    measure("tree hash (all cores)", size, || {
+        TreeHash::compute(&data, DEFAULT_LEAF_SIZE);
+    });
+}
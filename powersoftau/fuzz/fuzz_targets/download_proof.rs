@@ -0,0 +1,12 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use powersoftau::download_proof::{DownloadChallenge, DownloadProof};
+
+fuzz_target!(|data: &[u8]| {
+    let mut reader = &data[..];
+    if let Ok(challenge) = DownloadChallenge::read(&mut reader) {
+        if let Ok(proof) = DownloadProof::read(&mut reader) {
+            let _ = challenge.check(data, &proof);
+        }
+    }
+});
@@ -0,0 +1,21 @@
+#![no_main]
+use bellman_ce::pairing::bn256::Bn256;
+use libfuzzer_sys::fuzz_target;
+use memmap::MmapMut;
+use powersoftau::keypair::PublicKey;
+use powersoftau::parameters::{CeremonyParams, UseCompression};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = PublicKey::<Bn256>::deserialize(&mut &data[..]);
+
+    // The key at the end of a (tiny) compressed response, read from a map that
+    // may well be too short to hold it
+    if data.is_empty() {
+        return;
+    }
+    let parameters = CeremonyParams::<Bn256>::new(2, 2);
+    let mut map = MmapMut::map_anon(data.len()).unwrap();
+    map.copy_from_slice(data);
+    let map = map.make_read_only().unwrap();
+    let _ = PublicKey::<Bn256>::read(&map, UseCompression::Yes, &parameters);
+});
@@ -0,0 +1,10 @@
+#![no_main]
+use bellman_ce::pairing::bn256::Bn256;
+use libfuzzer_sys::fuzz_target;
+use powersoftau::receipt::Receipt;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(receipt) = Receipt::<Bn256>::deserialize(&mut &data[..]) {
+        let _ = receipt.verify();
+    }
+});
@@ -0,0 +1,7 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use powersoftau::round_state::RoundState;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = RoundState::read(&mut &data[..]);
+});
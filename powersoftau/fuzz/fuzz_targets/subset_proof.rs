@@ -0,0 +1,11 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use powersoftau::merkle::SubsetProof;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(proof) = SubsetProof::read(&mut &data[..]) {
+        if proof.verify(&[0; 64]) {
+            let _ = proof.subset();
+        }
+    }
+});
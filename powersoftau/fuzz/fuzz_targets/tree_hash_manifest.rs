@@ -0,0 +1,9 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use powersoftau::tree_hash::TreeHash;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(tree) = TreeHash::read(&mut &data[..]) {
+        let _ = tree.check_download(data);
+    }
+});
@@ -0,0 +1,85 @@
+//! Writes well-formed inputs for every fuzz target to `corpus/<target>/`, so that
+//! fuzzing starts from the real formats instead of having to discover them.
+use bellman_ce::pairing::bn256::Bn256;
+use memmap::MmapOptions;
+use powersoftau::download_proof::DownloadChallenge;
+use powersoftau::keypair::keypair;
+use powersoftau::merkle::MerkleTree;
+use powersoftau::parameters::CeremonyParams;
+use powersoftau::receipt::Receipt;
+use powersoftau::round_state::RoundState;
+use powersoftau::tree_hash::TreeHash;
+use rand::{thread_rng, Rng};
+
+use std::fs::{self, File};
+use std::io::Write;
+
+fn write_seed(target: &str, name: &str, bytes: &[u8]) {
+    let directory = format!("corpus/{}", target);
+    fs::create_dir_all(&directory).expect("unable to create the corpus directory");
+    File::create(format!("{}/{}", directory, name))
+        .and_then(|mut file| file.write_all(bytes))
+        .expect("unable to write a seed");
+}
+
+fn main() {
+    let rng = &mut thread_rng();
+    let parameters = CeremonyParams::<Bn256>::new(2, 2);
+    let digest = (0..64).map(|_| rng.gen()).collect::<Vec<u8>>();
+
+    let (public_key, _) = keypair::<_, Bn256>(rng, &digest);
+    let mut key = vec![];
+    public_key.serialize(&mut key).unwrap();
+    write_seed("public_key", "key", &key);
+    // The key where `PublicKey::read` expects it in a compressed response
+    let mut response = vec![0; parameters.contribution_size - parameters.public_key_size];
+    response.extend_from_slice(&key);
+    write_seed("public_key", "response", &response);
+
+    let mut receipt = vec![];
+    receipt.extend_from_slice(&digest);
+    receipt.extend_from_slice(&[1; 64]);
+    receipt.extend_from_slice(&key);
+    assert!(Receipt::<Bn256>::deserialize(&mut &receipt[..])
+        .unwrap()
+        .verify());
+    write_seed("receipt", "receipt", &receipt);
+
+    let challenge = (0..256).map(|_| rng.gen()).collect::<Vec<u8>>();
+    let mut challenge_map = MmapOptions::new().len(challenge.len()).map_anon().unwrap();
+    challenge_map.copy_from_slice(&challenge);
+    let state = RoundState::new(
+        &challenge_map.make_read_only().unwrap(),
+        &parameters,
+        b"signature".to_vec(),
+    );
+    let mut bytes = vec![];
+    state.write(&mut bytes).unwrap();
+    write_seed("round_state", "state", &bytes);
+
+    let tree = TreeHash::compute(&challenge, 64);
+    let mut manifest = vec![];
+    tree.write(&mut manifest).unwrap();
+    write_seed("tree_hash_manifest", "manifest", &manifest);
+
+    let merkle_tree = MerkleTree::compute(&challenge, 16);
+    let mut proof = vec![];
+    merkle_tree
+        .prove(&challenge, 40..72)
+        .write(&mut proof)
+        .unwrap();
+    write_seed("subset_proof", "proof", &proof);
+
+    // A challenge for a file as long as the seed itself, so that the target hashes the ranges
+    // of its input. The proof can't be valid, as it would have to hash itself.
+    let download_challenge = DownloadChallenge::new(rng, 392, 4, 16);
+    let mut download = vec![];
+    download_challenge.write(&mut download).unwrap();
+    download_challenge
+        .prove(&[0; 392])
+        .unwrap()
+        .write(&mut download)
+        .unwrap();
+    assert_eq!(download.len(), 392);
+    write_seed("download_proof", "proof", &download);
+}
@@ -0,0 +1,155 @@
+/// Reports which implementation of the field and curve arithmetic a contribution runs on, so
+/// that slow contributions can be told apart from slow machines. The pairing crate has a
+/// single portable implementation in Rust, so there is nothing to select yet; the report shows
+/// the CPU features an optimized implementation could use and how the binary was built. It
+/// also measures the subgroup checks, which dominate the verification of uncompressed inputs.
+use bellman_ce::pairing::bn256::G2;
+use bellman_ce::pairing::CurveProjective;
+use rand::{thread_rng, Rand};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use super::build_info::BuildInfo;
+use super::parameters::SubgroupCheckMode;
+
+/// Points of G2 the subgroup checks are timed on.
+const SUBGROUP_CHECK_SAMPLES: usize = 32;
+
+/// The arithmetic backend and the machine it runs on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackendReport {
+    pub arch: &'static str,
+    /// Detected CPU features that are relevant to field arithmetic
+    pub cpu_features: Vec<&'static str>,
+    pub implementation: &'static str,
+    /// Whether the binary was built without optimizations, which is several times slower
+    pub debug_build: bool,
+    pub threads: usize,
+    /// Time to check that a point of G2 is in the subgroup, with each mode
+    pub subgroup_checks: Vec<(SubgroupCheckMode, Duration)>,
+}
+
+impl BackendReport {
+    pub fn detect() -> Self {
+        BackendReport {
+            arch: std::env::consts::ARCH,
+            cpu_features: cpu_features(),
+            implementation: "portable Rust, 64-bit limbs",
+            debug_build: cfg!(debug_assertions),
+            threads: num_cpus::get(),
+            subgroup_checks: time_subgroup_checks(),
+        }
+    }
+}
+
+fn time_subgroup_checks() -> Vec<(SubgroupCheckMode, Duration)> {
+    let rng = &mut thread_rng();
+    let points = (0..SUBGROUP_CHECK_SAMPLES)
+        .map(|_| G2::rand(rng).into_affine())
+        .collect::<Vec<_>>();
+
+    [SubgroupCheckMode::FullOrder, SubgroupCheckMode::CofactorMul]
+        .iter()
+        .map(|&mode| {
+            let start = Instant::now();
+            for point in &points {
+                assert!(mode.is_in_subgroup(point));
+            }
+            (mode, start.elapsed() / SUBGROUP_CHECK_SAMPLES as u32)
+        })
+        .collect()
+}
+
+impl fmt::Display for BackendReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "arithmetic backend: {}", self.implementation)?;
+        writeln!(f, "architecture: {}", self.arch)?;
+        if self.cpu_features.is_empty() {
+            writeln!(f, "cpu features: none detected")?;
+        } else {
+            writeln!(f, "cpu features: {}", self.cpu_features.join(", "))?;
+        }
+        writeln!(f, "threads: {}", self.threads)?;
+        for (mode, duration) in &self.subgroup_checks {
+            writeln!(
+                f,
+                "subgroup check in G2 ({}): {}µs per point",
+                mode,
+                duration.as_micros()
+            )?;
+        }
+        writeln!(f, "binary: {}", BuildInfo::current())?;
+        write!(
+            f,
+            "build: {}",
+            if self.debug_build {
+                "debug, rebuild with --release for a faster contribution"
+            } else {
+                "release"
+            }
+        )
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn cpu_features() -> Vec<&'static str> {
+    let mut features = vec![];
+    if is_x86_feature_detected!("bmi2") {
+        features.push("bmi2");
+    }
+    if is_x86_feature_detected!("adx") {
+        features.push("adx");
+    }
+    if is_x86_feature_detected!("avx2") {
+        features.push("avx2");
+    }
+    features
+}
+
+#[cfg(target_arch = "aarch64")]
+fn cpu_features() -> Vec<&'static str> {
+    let mut features = vec![];
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        features.push("neon");
+    }
+    if std::arch::is_aarch64_feature_detected!("aes") {
+        features.push("aes");
+    }
+    features
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn cpu_features() -> Vec<&'static str> {
+    vec![]
+}
+
+/// Removes `--report-backend` from the arguments and returns whether it was given.
+pub fn take_report_backend(args: &mut Vec<String>) -> bool {
+    match args.iter().position(|arg| arg == "--report-backend") {
+        Some(position) => {
+            args.remove(position);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_report() {
+        let report = BackendReport::detect();
+        assert_eq!(report.arch, std::env::consts::ARCH);
+        assert!(report.threads > 0);
+        let text = report.to_string();
+        assert!(text.contains(report.implementation));
+        assert!(text.contains("subgroup check in G2 (cofactor-mul)"));
+
+        let mut args = vec!["compute".to_string(), "--report-backend".to_string()];
+        assert!(take_report_backend(&mut args));
+        assert_eq!(args, vec!["compute".to_string()]);
+        assert!(!take_report_backend(&mut args));
+    }
+}
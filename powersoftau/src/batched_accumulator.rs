@@ -5,18 +5,29 @@ use bellman_ce::pairing::*;
 use log::{error, info};
 
 use generic_array::GenericArray;
-use itertools::Itertools;
 use memmap::{Mmap, MmapMut};
+use rand::thread_rng;
 
 use std::io::{self, Read, Write};
+use std::ops::Range;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 use typenum::consts::U64;
 
-use super::keypair::{PrivateKey, PublicKey};
+use super::disk::{prefetch, RateLimiter, ReadOptions};
+use super::keypair::{clear_scalar, PrivateKey, PublicKey};
+use super::metrics::{self, Metric};
 use super::parameters::{
-    CeremonyParams, CheckForCorrectness, DeserializationError, ElementType, UseCompression,
+    expected_sizes, CeremonyParams, CheckForCorrectness, DeserializationError, ElementType,
+    TrapdoorMask, UseCompression,
 };
-use super::utils::{blank_hash, compute_g2_s, power_pairs, same_ratio};
+use super::profiling::{self, Operation};
+use super::utils::{
+    blank_hash, check_ratios, compute_g2_s, fill_repeated, iter_chunk, merge_pairs, pipeline_chunk,
+    power_pairs, read_hash_header, reduced_hash, same_ratio, write_hash_header, write_point,
+    RatioBatch,
+};
+use super::verification_cursor::VerificationCursor;
 
 pub enum AccumulatorState {
     Empty,
@@ -24,6 +35,24 @@ pub enum AccumulatorState {
     Transformed,
 }
 
+/// The first two powers of tau of a response in G1 and in G2, see
+/// `BatchedAccumulator::first_powers`.
+pub(crate) type FirstPowers<E> = (
+    (<E as Engine>::G1Affine, <E as Engine>::G1Affine),
+    (<E as Engine>::G2Affine, <E as Engine>::G2Affine),
+);
+
+/// Result of `BatchedAccumulator::audit_boundaries` for the link between the last power of a
+/// chunk and the first power of the next one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundaryCheck {
+    /// Index of the chunk before the boundary, see `BatchedAccumulator::chunk_ranges`
+    pub chunk: usize,
+    /// Last power of the chunk
+    pub index: usize,
+    pub valid: bool,
+}
+
 /// The `Accumulator` is an object that participants of the ceremony contribute
 /// randomness to. This object contains powers of trapdoor `tau` in G1 and in G2 over
 /// fixed generators, and additionally in G1 over two other generators of exponents
@@ -62,17 +91,11 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
     }
 
     fn g1_size(&self, compression: UseCompression) -> usize {
-        match compression {
-            UseCompression::Yes => self.parameters.curve.g1_compressed,
-            UseCompression::No => self.parameters.curve.g1,
-        }
+        self.parameters.curve.g1_size(compression)
     }
 
     fn g2_size(&self, compression: UseCompression) -> usize {
-        match compression {
-            UseCompression::Yes => self.parameters.curve.g2_compressed,
-            UseCompression::No => self.parameters.curve.g2,
-        }
+        self.parameters.curve.g2_size(compression)
     }
 
     fn get_size(&self, element_type: ElementType, compression: UseCompression) -> usize {
@@ -174,10 +197,114 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
             }
         };
 
-        position + self.parameters.hash_size
+        position + self.parameters.hash_size + compression.header().len()
+    }
+
+    /// Byte range of `count` consecutive elements of `element_type` from `index` in an
+    /// accumulator file, or `None` if the elements don't all exist.
+    pub fn element_range(
+        element_type: ElementType,
+        index: usize,
+        count: usize,
+        compression: UseCompression,
+        parameters: &'a CeremonyParams<E>,
+    ) -> Option<Range<usize>> {
+        let length = match element_type {
+            ElementType::TauG1 => parameters.powers_g1_length,
+            ElementType::TauG2 | ElementType::AlphaG1 | ElementType::BetaG1 => {
+                parameters.powers_length
+            }
+            ElementType::BetaG2 => 1,
+        };
+        if count == 0 || index >= length || count > length - index {
+            return None;
+        }
+
+        let accumulator = Self::empty(parameters);
+        let start = accumulator.calculate_mmap_position(index, element_type, compression);
+        let size = accumulator.get_size(element_type, compression);
+        Some(start..start + count * size)
+    }
+
+    /// Byte ranges of the elements that `read_chunk` reads for a chunk of `size` powers from
+    /// `start` in an accumulator file.
+    pub(crate) fn chunk_byte_ranges(
+        start: usize,
+        size: usize,
+        compression: UseCompression,
+        parameters: &'a CeremonyParams<E>,
+    ) -> Vec<Range<usize>> {
+        let mut ranges = vec![];
+        for &element_type in &[
+            ElementType::TauG1,
+            ElementType::TauG2,
+            ElementType::AlphaG1,
+            ElementType::BetaG1,
+        ] {
+            let length = match element_type {
+                ElementType::TauG1 => parameters.powers_g1_length,
+                _ => parameters.powers_length,
+            };
+            if start < length {
+                let count = std::cmp::min(size, length - start);
+                ranges.extend(Self::element_range(
+                    element_type,
+                    start,
+                    count,
+                    compression,
+                    parameters,
+                ));
+            }
+        }
+        if start < parameters.powers_length {
+            ranges.extend(Self::element_range(
+                ElementType::BetaG2,
+                0,
+                1,
+                compression,
+                parameters,
+            ));
+        }
+
+        ranges
     }
 }
 
+/// Describes a failed read of the chunk from `start` to `end` of `source`, keeping the kind of
+/// IO errors such as a truncated file.
+fn chunk_error(e: DeserializationError, start: usize, end: usize, source: &str) -> io::Error {
+    let kind = match e {
+        DeserializationError::IoError(ref e) => e.kind(),
+        _ => io::ErrorKind::InvalidData,
+    };
+    io::Error::new(
+        kind,
+        format!("unable to read a chunk from {} to {} from {}: {}", start, end, source, e),
+    )
+}
+
+/// Writes the declaration of `compression` that follows the hash header, if it has one, see
+/// `UseCompression::header`.
+fn write_policy_header<E: Engine>(
+    output_map: &mut [u8],
+    compression: UseCompression,
+    parameters: &CeremonyParams<E>,
+) -> io::Result<()> {
+    let header = compression.header();
+    let start = parameters.hash_size;
+    output_map
+        .get_mut(start..start + header.len())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the output is too small for an accumulator",
+            )
+        })?
+        .copy_from_slice(header);
+
+    Ok(())
+}
+
 /// Verifies a transformation of the `BatchedAccumulator` with the `PublicKey`, given a 64-byte transcript `digest`.
 pub fn verify_transform<E: Engine>(
     before: &BatchedAccumulator<E>,
@@ -205,10 +332,11 @@ pub fn verify_transform<E: Engine>(
     }
 
     // Check the correctness of the generators for tau powers
-    if after.tau_powers_g1[0] != E::G1Affine::one() {
+    let generators = &after.parameters.generators;
+    if after.tau_powers_g1[0] != generators.g1 {
         return false;
     }
-    if after.tau_powers_g2[0] != E::G2Affine::one() {
+    if after.tau_powers_g2[0] != generators.g2 {
         return false;
     }
 
@@ -273,7 +401,7 @@ pub fn verify_transform<E: Engine>(
 
 impl<'a, E: Engine> BatchedAccumulator<'a, E> {
     /// Verifies a transformation of the `Accumulator` with the `PublicKey`, given a 64-byte transcript `digest`.
-    #[allow(clippy::too_many_arguments, clippy::cognitive_complexity)]
+    #[allow(clippy::too_many_arguments)]
     pub fn verify_transformation(
         input_map: &Mmap,
         output_map: &Mmap,
@@ -285,28 +413,139 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
         check_output_for_correctness: CheckForCorrectness,
         parameters: &'a CeremonyParams<E>,
     ) -> bool {
-        use itertools::MinMaxResult::MinMax;
-        assert_eq!(digest.len(), 64);
+        Self::verify_transformation_with(
+            input_map,
+            output_map,
+            key,
+            digest,
+            input_is_compressed,
+            output_is_compressed,
+            check_input_for_correctness,
+            check_output_for_correctness,
+            &ReadOptions::default(),
+            parameters,
+        )
+    }
 
-        let tau_g2_s = compute_g2_s::<E>(&digest, &key.tau_g1.0, &key.tau_g1.1, 0);
-        let alpha_g2_s = compute_g2_s::<E>(&digest, &key.alpha_g1.0, &key.alpha_g1.1, 1);
-        let beta_g2_s = compute_g2_s::<E>(&digest, &key.beta_g1.0, &key.beta_g1.1, 2);
+    /// Same as `verify_transformation`, but the files are read as selected by `read_options`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_transformation_with(
+        input_map: &Mmap,
+        output_map: &Mmap,
+        key: &PublicKey<E>,
+        digest: &[u8],
+        input_is_compressed: UseCompression,
+        output_is_compressed: UseCompression,
+        check_input_for_correctness: CheckForCorrectness,
+        check_output_for_correctness: CheckForCorrectness,
+        read_options: &ReadOptions,
+        parameters: &'a CeremonyParams<E>,
+    ) -> bool {
+        Self::verify_transformation_writing(
+            input_map,
+            output_map,
+            key,
+            digest,
+            input_is_compressed,
+            output_is_compressed,
+            check_input_for_correctness,
+            check_output_for_correctness,
+            read_options,
+            None,
+            None,
+            parameters,
+        )
+    }
 
-        // Check the proofs-of-knowledge for tau/alpha/beta
+    /// Same as `verify_transformation_with`, but continues from `cursor` and records in it,
+    /// and in the file `cursor_path`, every chunk that is verified, so that an interrupted
+    /// verification can be resumed. See `VerificationCursor`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_transformation_resuming(
+        input_map: &Mmap,
+        output_map: &Mmap,
+        key: &PublicKey<E>,
+        digest: &[u8],
+        input_is_compressed: UseCompression,
+        output_is_compressed: UseCompression,
+        check_input_for_correctness: CheckForCorrectness,
+        check_output_for_correctness: CheckForCorrectness,
+        read_options: &ReadOptions,
+        cursor: &mut VerificationCursor,
+        cursor_path: &Path,
+        parameters: &'a CeremonyParams<E>,
+    ) -> bool {
+        Self::verify_transformation_writing(
+            input_map,
+            output_map,
+            key,
+            digest,
+            input_is_compressed,
+            output_is_compressed,
+            check_input_for_correctness,
+            check_output_for_correctness,
+            read_options,
+            None,
+            Some((cursor, cursor_path)),
+            parameters,
+        )
+    }
 
-        // g1^s / g1^(s*x) = g2^s / g2^(s*x)
-        if !same_ratio(key.tau_g1, (tau_g2_s, key.tau_g2)) {
-            error!("Invalid ratio key.tau_g1, (tau_g2_s, key.tau_g2)");
-            return false;
-        }
-        if !same_ratio(key.alpha_g1, (alpha_g2_s, key.alpha_g2)) {
-            error!("Invalid ratio key.alpha_g1, (alpha_g2_s, key.alpha_g2)");
-            return false;
-        }
-        if !same_ratio(key.beta_g1, (beta_g2_s, key.beta_g2)) {
-            error!("Invalid ratio key.beta_g1, (beta_g2_s, key.beta_g2)");
-            return false;
-        }
+    /// Same as `verify_transformation_with`, but the points of the response are also written
+    /// as they are verified to `new_challenge_map` uncompressed, and to
+    /// `compressed_new_challenge_map` compressed, like `decompress_with_copy` does. The
+    /// response is then read only once. The hashes at the start of the new challenges are left
+    /// alone, and the new challenges are incomplete if the verification fails.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_transformation_and_decompress(
+        input_map: &Mmap,
+        output_map: &Mmap,
+        key: &PublicKey<E>,
+        digest: &[u8],
+        input_is_compressed: UseCompression,
+        output_is_compressed: UseCompression,
+        check_input_for_correctness: CheckForCorrectness,
+        check_output_for_correctness: CheckForCorrectness,
+        read_options: &ReadOptions,
+        new_challenge_map: &mut MmapMut,
+        compressed_new_challenge_map: Option<&mut MmapMut>,
+        parameters: &'a CeremonyParams<E>,
+    ) -> bool {
+        Self::verify_transformation_writing(
+            input_map,
+            output_map,
+            key,
+            digest,
+            input_is_compressed,
+            output_is_compressed,
+            check_input_for_correctness,
+            check_output_for_correctness,
+            read_options,
+            Some((new_challenge_map, compressed_new_challenge_map)),
+            None,
+            parameters,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments, clippy::cognitive_complexity)]
+    fn verify_transformation_writing(
+        input_map: &Mmap,
+        output_map: &Mmap,
+        key: &PublicKey<E>,
+        digest: &[u8],
+        input_is_compressed: UseCompression,
+        output_is_compressed: UseCompression,
+        check_input_for_correctness: CheckForCorrectness,
+        check_output_for_correctness: CheckForCorrectness,
+        read_options: &ReadOptions,
+        mut new_challenge: Option<(&mut MmapMut, Option<&mut MmapMut>)>,
+        mut cursor: Option<(&mut VerificationCursor, &Path)>,
+        parameters: &'a CeremonyParams<E>,
+    ) -> bool {
+        let g2_s = match Self::check_key(key, digest, parameters.trapdoors) {
+            Some(g2_s) => g2_s,
+            None => return false,
+        };
 
         // Load accumulators AND perform computations
 
@@ -317,303 +556,883 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
 
         {
             let chunk_size = 2;
-            before
-                .read_chunk(
-                    0,
-                    chunk_size,
-                    input_is_compressed,
-                    check_input_for_correctness,
-                    &input_map,
-                )
-                .expect("must read a first chunk from `challenge`");
-            after
-                .read_chunk(
-                    0,
-                    chunk_size,
-                    output_is_compressed,
-                    check_output_for_correctness,
-                    &output_map,
-                )
-                .expect("must read a first chunk from `response`");
-
-            // Check the correctness of the generators for tau powers
-            if after.tau_powers_g1[0] != E::G1Affine::one() {
-                error!("tau_powers_g1[0] != 1");
-                return false;
-            }
-            if after.tau_powers_g2[0] != E::G2Affine::one() {
-                error!("tau_powers_g2[0] != 1");
-                return false;
-            }
-
-            // Did the participant multiply the previous tau by the new one?
-            if !same_ratio(
-                (before.tau_powers_g1[1], after.tau_powers_g1[1]),
-                (tau_g2_s, key.tau_g2),
+            if let Err(e) = before.read_chunk(
+                0,
+                chunk_size,
+                input_is_compressed,
+                check_input_for_correctness,
+                input_map,
             ) {
-                error!("Invalid ratio (before.tau_powers_g1[1], after.tau_powers_g1[1]), (tau_g2_s, key.tau_g2)");
+                error!("unable to read the first chunk of `challenge`: {}", e);
                 return false;
             }
-
-            // Did the participant multiply the previous alpha by the new one?
-            if !same_ratio(
-                (before.alpha_tau_powers_g1[0], after.alpha_tau_powers_g1[0]),
-                (alpha_g2_s, key.alpha_g2),
+            if let Err(e) = after.read_chunk(
+                0,
+                chunk_size,
+                output_is_compressed,
+                check_output_for_correctness,
+                output_map,
             ) {
-                error!("Invalid ratio (before.alpha_tau_powers_g1[0], after.alpha_tau_powers_g1[0]), (alpha_g2_s, key.alpha_g2)");
+                error!("unable to read the first chunk of `response`: {}", e);
                 return false;
             }
 
-            // Did the participant multiply the previous beta by the new one?
-            if !same_ratio(
-                (before.beta_tau_powers_g1[0], after.beta_tau_powers_g1[0]),
-                (beta_g2_s, key.beta_g2),
-            ) {
-                error!("Invalid ratio (before.beta_tau_powers_g1[0], after.beta_tau_powers_g1[0]), (beta_g2_s, key.beta_g2)");
-                return false;
-            }
-            if !same_ratio(
-                (before.beta_tau_powers_g1[0], after.beta_tau_powers_g1[0]),
-                (before.beta_g2, after.beta_g2),
-            ) {
-                error!("Invalid ratio (before.beta_tau_powers_g1[0], after.beta_tau_powers_g1[0]), (before.beta_g2, after.beta_g2)");
+            if !Self::check_first_powers(&before, &after, key, &g2_s) {
                 return false;
             }
         }
 
-        let tau_powers_g2_0 = after.tau_powers_g2[0];
-        let tau_powers_g2_1 = after.tau_powers_g2[1];
-        let tau_powers_g1_0 = after.tau_powers_g1[0];
-        let tau_powers_g1_1 = after.tau_powers_g1[1];
+        let first_powers = after.first_powers();
+        // The pairs of powers of all chunks are checked together once they are all read,
+        // unless the verification records its progress: a chunk is only recorded as verified
+        // once its pairs were checked
+        let defer = cursor.is_none();
+        let mut batch = RatioBatch::new(first_powers.0, first_powers.1);
+        let check_batch = |batch: &mut RatioBatch<E>, start: usize, end: usize| {
+            if defer || check_ratios(batch) {
+                return true;
+            }
+            error!(
+                "Invalid ratio of the powers of tau, alpha or beta in the chunk from {} to {}",
+                start, end
+            );
+            false
+        };
 
         // Read by parts and just verify same ratios. Cause of two fixed variables above with tau_powers_g2_1 = tau_powers_g2_0 ^ s
         // one does not need to care about some overlapping
 
         let mut tau_powers_last_first_chunks = vec![E::G1Affine::zero(); 2];
         let tau_powers_length = parameters.powers_length;
-        for chunk in &(0..tau_powers_length).chunks(parameters.batch_size) {
-            if let MinMax(start, end) = chunk.minmax() {
-                // extra 1 to ensure intersection between chunks and ensure we don't overflow
-                let size = end - start + 1 + if end == tau_powers_length - 1 { 0 } else { 1 };
-                before
-                    .read_chunk(
-                        start,
-                        size,
-                        input_is_compressed,
-                        check_input_for_correctness,
-                        &input_map,
+        // Chunks of both files are read and decoded on another thread while the pairings of
+        // the previous chunk are checked. Only the chunks of the response are checked, the
+        // challenge is read to check its points.
+        let mut limiter = read_options.max_rate.map(RateLimiter::new);
+        let mut read_chunks = |start: usize, end: usize, section_end: usize| -> Result<Self, ()> {
+            // extra 1 to ensure intersection between chunks and ensure we don't overflow
+            let size = end - start + 1 + if end == section_end - 1 { 0 } else { 1 };
+            if read_options.prefetch {
+                let mut ranges =
+                    Self::chunk_byte_ranges(start, size, input_is_compressed, parameters);
+                prefetch(input_map, &mut ranges, limiter.as_mut());
+                let mut ranges =
+                    Self::chunk_byte_ranges(start, size, output_is_compressed, parameters);
+                prefetch(output_map, &mut ranges, limiter.as_mut());
+            }
+            let mut before = Self::empty(parameters);
+            before
+                .read_chunk(
+                    start,
+                    size,
+                    input_is_compressed,
+                    check_input_for_correctness,
+                    input_map,
+                )
+                .map_err(|e| {
+                    error!(
+                        "unable to read a chunk from {} to {} from `challenge`: {}",
+                        start, end, e
                     )
-                    .unwrap_or_else(|_| {
-                        panic!(format!(
-                            "must read a chunk from {} to {} from `challenge`",
-                            start, end
-                        ))
-                    });
-                after
-                    .read_chunk(
-                        start,
-                        size,
-                        output_is_compressed,
-                        check_output_for_correctness,
-                        &output_map,
+                })?;
+            let mut after = Self::empty(parameters);
+            after
+                .read_chunk(
+                    start,
+                    size,
+                    output_is_compressed,
+                    check_output_for_correctness,
+                    output_map,
+                )
+                .map_err(|e| {
+                    error!(
+                        "unable to read a chunk from {} to {} from `response`: {}",
+                        start, end, e
                     )
-                    .unwrap_or_else(|_| {
-                        panic!(format!(
-                            "must read a chunk from {} to {} from `response`",
-                            start, end
-                        ))
-                    });
-
-                // Are the powers of tau correct?
-                if !same_ratio(
-                    power_pairs(&after.tau_powers_g1),
-                    (tau_powers_g2_0, tau_powers_g2_1),
-                ) {
-                    error!("Invalid ratio power_pairs(&after.tau_powers_g1), (tau_powers_g2_0, tau_powers_g2_1)");
-                    return false;
-                }
-                if !same_ratio(
-                    power_pairs(&after.tau_powers_g2),
-                    (tau_powers_g1_0, tau_powers_g1_1),
-                ) {
-                    error!("Invalid ratio power_pairs(&after.tau_powers_g2), (tau_powers_g1_0, tau_powers_g1_1)");
-                    return false;
-                }
-                if !same_ratio(
-                    power_pairs(&after.alpha_tau_powers_g1),
-                    (tau_powers_g2_0, tau_powers_g2_1),
-                ) {
-                    error!("Invalid ratio power_pairs(&after.alpha_tau_powers_g1), (tau_powers_g2_0, tau_powers_g2_1)");
-                    return false;
+                })?;
+
+            Ok(after)
+        };
+        // Chunks are written before they are checked, as the new challenge is discarded
+        // anyway if any of them is invalid
+        let mut write_chunk = |start: usize, after: &mut Self| -> Result<(), ()> {
+            if let Some((new_challenge_map, compressed_new_challenge_map)) = new_challenge.as_mut()
+            {
+                after
+                    .write_chunk(start, UseCompression::No, new_challenge_map)
+                    .and_then(|_| match compressed_new_challenge_map.as_mut() {
+                        Some(map) => after.write_chunk(start, UseCompression::Yes, map),
+                        None => Ok(()),
+                    })
+                    .map_err(|e| {
+                        error!(
+                            "unable to write a chunk from {} to the new challenge: {}",
+                            start, e
+                        )
+                    })?;
+            }
+            Ok(())
+        };
+
+        // A resumed verification skips the chunks the cursor has, and records every chunk
+        // once it is verified, which covers the pairs up to the first power of the next chunk
+        let (first_start, extra_start) = match cursor {
+            Some((ref cursor, _)) => (cursor.next_power, cursor.next_extra_power),
+            None => (0, tau_powers_length),
+        };
+        if first_start > 0 || extra_start > tau_powers_length {
+            info!(
+                "Resuming from power {} and extra power {}",
+                first_start, extra_start
+            );
+        }
+        let mut record = |section_end: usize, end: usize| {
+            if let Some((cursor, path)) = cursor.as_mut() {
+                let next = std::cmp::min(end + 1, section_end);
+                if section_end == tau_powers_length {
+                    cursor.next_power = next;
+                } else {
+                    cursor.next_extra_power = next;
                 }
-                if !same_ratio(
-                    power_pairs(&after.beta_tau_powers_g1),
-                    (tau_powers_g2_0, tau_powers_g2_1),
-                ) {
-                    error!("Invalid ratio power_pairs(&after.beta_tau_powers_g1), (tau_powers_g2_0, tau_powers_g2_1)");
-                    return false;
+                if let Err(e) = cursor.write(path) {
+                    error!(
+                        "unable to record the progress of the verification in {}: {}",
+                        path.display(),
+                        e
+                    );
                 }
+            }
+        };
+
+        let valid = pipeline_chunk(
+            first_start..tau_powers_length,
+            parameters.batch_size,
+            read_options.read_ahead,
+            |start, end| read_chunks(start, end, tau_powers_length),
+            |start, end, mut after| -> Result<(), ()> {
+                write_chunk(start, &mut after)?;
+                let size = after.tau_powers_g1.len();
                 if end == tau_powers_length - 1 {
                     tau_powers_last_first_chunks[0] = after.tau_powers_g1[size - 1];
                 }
+                // A chunk of a single power has no pairs, and it was already checked as the
+                // overlap of the previous chunk
+                if size == 1 {
+                    record(tau_powers_length, end);
+                    return Ok(());
+                }
+
+                after.add_chunk_powers(&mut batch);
+                if !check_batch(&mut batch, start, end) {
+                    return Err(());
+                }
+                record(tau_powers_length, end);
                 info!("Done processing {} powers of tau", end);
-            } else {
-                panic!("Chunk does not have a min and max");
-            }
+                Ok(())
+            },
+        );
+        if valid.is_err() {
+            return false;
         }
 
-        for chunk in &(tau_powers_length..parameters.powers_g1_length).chunks(parameters.batch_size)
-        {
-            if let MinMax(start, end) = chunk.minmax() {
-                // extra 1 to ensure intersection between chunks and ensure we don't overflow
-                let size = end - start
-                    + 1
-                    + if end == parameters.powers_g1_length - 1 {
-                        0
-                    } else {
-                        1
-                    };
-                before
-                    .read_chunk(
-                        start,
-                        size,
-                        input_is_compressed,
-                        check_input_for_correctness,
-                        &input_map,
-                    )
-                    .unwrap_or_else(|_| {
-                        panic!(format!(
-                            "must read a chunk from {} to {} from `challenge`",
-                            start, end
-                        ))
-                    });
-                after
-                    .read_chunk(
-                        start,
-                        size,
-                        output_is_compressed,
-                        check_output_for_correctness,
-                        &output_map,
-                    )
-                    .unwrap_or_else(|_| {
-                        panic!(format!(
-                            "must read a chunk from {} to {} from `response`",
-                            start, end
-                        ))
-                    });
-
-                assert_eq!(
-                    before.tau_powers_g2.len(),
-                    0,
-                    "during rest of tau g1 generation tau g2 must be empty"
-                );
+        let valid = pipeline_chunk(
+            extra_start..parameters.powers_g1_length,
+            parameters.batch_size,
+            read_options.read_ahead,
+            |start, end| read_chunks(start, end, parameters.powers_g1_length),
+            |start, end, mut after| -> Result<(), ()> {
+                write_chunk(start, &mut after)?;
                 assert_eq!(
                     after.tau_powers_g2.len(),
                     0,
                     "during rest of tau g1 generation tau g2 must be empty"
                 );
 
-                // Are the powers of tau correct?
-                if !same_ratio(
-                    power_pairs(&after.tau_powers_g1),
-                    (tau_powers_g2_0, tau_powers_g2_1),
-                ) {
-                    error!("Invalid ratio power_pairs(&after.tau_powers_g1), (tau_powers_g2_0, tau_powers_g2_1) in extra TauG1 contribution");
-                    return false;
-                }
                 if start == parameters.powers_length {
                     tau_powers_last_first_chunks[1] = after.tau_powers_g1[0];
                 }
+                // A chunk of a single power has no pairs, and it was already checked as the
+                // overlap of the previous chunk, or against the last power of the first section
+                if after.tau_powers_g1.len() == 1 {
+                    record(parameters.powers_g1_length, end);
+                    return Ok(());
+                }
+
+                after.add_chunk_powers(&mut batch);
+                if !check_batch(&mut batch, start, end) {
+                    return Err(());
+                }
+                record(parameters.powers_g1_length, end);
                 info!("Done processing {} powers of tau", end);
-            } else {
-                panic!("Chunk does not have a min and max");
+                Ok(())
+            },
+        );
+        if valid.is_err() {
+            return false;
+        }
+
+        // The powers on both sides of the link between the sections are only read again if
+        // their chunks were verified before the verification was resumed
+        let read_power = |index: usize| -> Option<E::G1Affine> {
+            let mut after = Self::empty(parameters);
+            match after.read_chunk(
+                index,
+                1,
+                output_is_compressed,
+                check_output_for_correctness,
+                output_map,
+            ) {
+                Ok(()) => Some(after.tau_powers_g1[0]),
+                Err(e) => {
+                    error!("unable to read power {} from `response`: {}", index, e);
+                    None
+                }
+            }
+        };
+        if first_start == tau_powers_length {
+            match read_power(tau_powers_length - 1) {
+                Some(power) => tau_powers_last_first_chunks[0] = power,
+                None => return false,
+            }
+        }
+        if extra_start > tau_powers_length {
+            match read_power(tau_powers_length) {
+                Some(power) => tau_powers_last_first_chunks[1] = power,
+                None => return false,
             }
         }
 
-        if !same_ratio(
+        batch.add_g1(
+            &mut thread_rng(),
             power_pairs(&tau_powers_last_first_chunks),
-            (tau_powers_g2_0, tau_powers_g2_1),
-        ) {
-            error!("Invalid ratio power_pairs(&after.tau_powers_g1), (tau_powers_g2_0, tau_powers_g2_1) in TauG1 contribution intersection");
+        );
+        if !check_ratios(&mut batch) {
+            error!("Invalid ratio of the powers of tau, alpha or beta in the response");
             return false;
         }
         true
     }
 
-    pub fn decompress(
-        input_map: &Mmap,
-        output_map: &mut MmapMut,
-        check_input_for_correctness: CheckForCorrectness,
-        parameters: &'a CeremonyParams<E>,
-    ) -> io::Result<()> {
-        use itertools::MinMaxResult::MinMax;
-
-        let mut accumulator = Self::empty(parameters);
+    /// Checks the proofs of knowledge of tau, alpha and beta in `key` for the 64-byte
+    /// transcript `digest`, and that the key leaves the trapdoors outside `trapdoors` as they
+    /// are. Returns the points of G2 that the proofs were checked against.
+    pub(crate) fn check_key(
+        key: &PublicKey<E>,
+        digest: &[u8],
+        trapdoors: TrapdoorMask,
+    ) -> Option<[E::G2Affine; 3]> {
+        assert_eq!(digest.len(), 64);
 
-        for chunk in &(0..parameters.powers_length).chunks(parameters.batch_size) {
-            if let MinMax(start, end) = chunk.minmax() {
-                let size = end - start + 1;
-                accumulator
-                    .read_chunk(
-                        start,
-                        size,
-                        UseCompression::Yes,
-                        check_input_for_correctness,
-                        &input_map,
-                    )
-                    .unwrap_or_else(|_| {
-                        panic!(format!(
-                            "must read a chunk from {} to {} from source of decompression",
-                            start, end
-                        ))
-                    });
-                accumulator.write_chunk(start, UseCompression::No, output_map)?;
-            } else {
-                panic!("Chunk does not have a min and max");
-            }
+        if !key.respects_mask(trapdoors) {
+            error!("The key updates trapdoors outside of {}", trapdoors);
+            return None;
         }
 
-        for chunk in
-            &(parameters.powers_length..parameters.powers_g1_length).chunks(parameters.batch_size)
-        {
-            if let MinMax(start, end) = chunk.minmax() {
-                let size = end - start + 1;
-                accumulator
-                    .read_chunk(
-                        start,
-                        size,
-                        UseCompression::Yes,
-                        check_input_for_correctness,
-                        &input_map,
-                    )
-                    .unwrap_or_else(|_| {
-                        panic!(format!(
-                            "must read a chunk from {} to {} from source of decompression",
-                            start, end
-                        ))
-                    });
-                assert_eq!(
-                    accumulator.tau_powers_g2.len(),
-                    0,
-                    "during rest of tau g1 generation tau g2 must be empty"
-                );
-                assert_eq!(
-                    accumulator.alpha_tau_powers_g1.len(),
-                    0,
-                    "during rest of tau g1 generation alpha*tau in g1 must be empty"
-                );
-                assert_eq!(
-                    accumulator.beta_tau_powers_g1.len(),
-                    0,
-                    "during rest of tau g1 generation beta*tau in g1 must be empty"
-                );
+        let tau_g2_s = compute_g2_s::<E>(digest, &key.tau_g1.0, &key.tau_g1.1, 0);
+        let alpha_g2_s = compute_g2_s::<E>(digest, &key.alpha_g1.0, &key.alpha_g1.1, 1);
+        let beta_g2_s = compute_g2_s::<E>(digest, &key.beta_g1.0, &key.beta_g1.1, 2);
 
-                accumulator.write_chunk(start, UseCompression::No, output_map)?;
-            } else {
-                panic!("Chunk does not have a min and max");
+        // Check the proofs-of-knowledge for tau/alpha/beta
+
+        // g1^s / g1^(s*x) = g2^s / g2^(s*x)
+        if !same_ratio(key.tau_g1, (tau_g2_s, key.tau_g2)) {
+            error!("Invalid ratio key.tau_g1, (tau_g2_s, key.tau_g2)");
+            return None;
+        }
+        if !same_ratio(key.alpha_g1, (alpha_g2_s, key.alpha_g2)) {
+            error!("Invalid ratio key.alpha_g1, (alpha_g2_s, key.alpha_g2)");
+            return None;
+        }
+        if !same_ratio(key.beta_g1, (beta_g2_s, key.beta_g2)) {
+            error!("Invalid ratio key.beta_g1, (beta_g2_s, key.beta_g2)");
+            return None;
+        }
+
+        Some([tau_g2_s, alpha_g2_s, beta_g2_s])
+    }
+
+    /// Checks that the response `after`, a chunk of at least two powers from the first, is
+    /// the challenge `before` multiplied by the secrets of `key`, with `g2_s` from `check_key`.
+    pub(crate) fn check_first_powers(
+        before: &Self,
+        after: &Self,
+        key: &PublicKey<E>,
+        g2_s: &[E::G2Affine; 3],
+    ) -> bool {
+        let [tau_g2_s, alpha_g2_s, beta_g2_s] = *g2_s;
+
+        // Check the correctness of the generators for tau powers
+        if after.tau_powers_g1[0] != after.parameters.generators.g1 {
+            error!("tau_powers_g1[0] is not the generator of G1");
+            return false;
+        }
+        if after.tau_powers_g2[0] != after.parameters.generators.g2 {
+            error!("tau_powers_g2[0] is not the generator of G2");
+            return false;
+        }
+
+        // Did the participant multiply the previous tau by the new one?
+        if !same_ratio(
+            (before.tau_powers_g1[1], after.tau_powers_g1[1]),
+            (tau_g2_s, key.tau_g2),
+        ) {
+            error!("Invalid ratio (before.tau_powers_g1[1], after.tau_powers_g1[1]), (tau_g2_s, key.tau_g2)");
+            return false;
+        }
+
+        // Did the participant multiply the previous alpha by the new one?
+        if !same_ratio(
+            (before.alpha_tau_powers_g1[0], after.alpha_tau_powers_g1[0]),
+            (alpha_g2_s, key.alpha_g2),
+        ) {
+            error!("Invalid ratio (before.alpha_tau_powers_g1[0], after.alpha_tau_powers_g1[0]), (alpha_g2_s, key.alpha_g2)");
+            return false;
+        }
+
+        // Did the participant multiply the previous beta by the new one?
+        if !same_ratio(
+            (before.beta_tau_powers_g1[0], after.beta_tau_powers_g1[0]),
+            (beta_g2_s, key.beta_g2),
+        ) {
+            error!("Invalid ratio (before.beta_tau_powers_g1[0], after.beta_tau_powers_g1[0]), (beta_g2_s, key.beta_g2)");
+            return false;
+        }
+        if !same_ratio(
+            (before.beta_tau_powers_g1[0], after.beta_tau_powers_g1[0]),
+            (before.beta_g2, after.beta_g2),
+        ) {
+            error!("Invalid ratio (before.beta_tau_powers_g1[0], after.beta_tau_powers_g1[0]), (before.beta_g2, after.beta_g2)");
+            return false;
+        }
+        true
+    }
+
+    /// The first two powers of tau in G1 and in G2 of a chunk that starts from the first
+    /// power, which the pairs of consecutive powers of every chunk are checked against.
+    pub(crate) fn first_powers(&self) -> FirstPowers<E> {
+        (
+            (self.tau_powers_g1[0], self.tau_powers_g1[1]),
+            (self.tau_powers_g2[0], self.tau_powers_g2[1]),
+        )
+    }
+
+    /// Checks that consecutive powers in this chunk of a response have the ratio of the
+    /// `first_powers` of the response, with a single multi-pairing.
+    pub(crate) fn check_chunk_powers(&self, first_powers: FirstPowers<E>) -> bool {
+        let mut batch = RatioBatch::new(first_powers.0, first_powers.1);
+        self.add_chunk_powers(&mut batch);
+        if !check_ratios(&mut batch) {
+            error!("Invalid ratio of the powers of tau, alpha or beta in the chunk");
+            return false;
+        }
+        true
+    }
+
+    /// Adds the pairs of consecutive powers in this chunk of a response to `batch`, to be
+    /// checked later together with those of other chunks. Chunks of the extra powers of tau in
+    /// G1 only have those.
+    pub(crate) fn add_chunk_powers(&self, batch: &mut RatioBatch<E>) {
+        let rng = &mut thread_rng();
+        batch.add_g1(rng, power_pairs(&self.tau_powers_g1));
+        if self.tau_powers_g2.is_empty() {
+            return;
+        }
+        batch.add_g2(rng, power_pairs(&self.tau_powers_g2));
+        batch.add_g1(rng, power_pairs(&self.alpha_tau_powers_g1));
+        batch.add_g1(rng, power_pairs(&self.beta_tau_powers_g1));
+    }
+
+    /// Checks the link between the last power of tau in G1 of the first section and the first
+    /// power of the extra powers, which no chunk holds both of.
+    pub(crate) fn check_sections_link(
+        tau_powers_last_first_chunks: &[E::G1Affine],
+        first_powers: FirstPowers<E>,
+    ) -> bool {
+        let (_, tau_powers_g2) = first_powers;
+        if !same_ratio(power_pairs(tau_powers_last_first_chunks), tau_powers_g2) {
+            error!("Invalid ratio power_pairs(&after.tau_powers_g1), (tau_powers_g2_0, tau_powers_g2_1) in TauG1 contribution intersection");
+            return false;
+        }
+        true
+    }
+
+    /// Ranges of elements, as `(start, end)` inclusive, that make up the chunks of an
+    /// accumulator. Chunks are `batch_size` long and never span both the powers that exist
+    /// for all element types and the extra TauG1 powers.
+    pub fn chunk_ranges(parameters: &CeremonyParams<E>) -> Vec<(usize, usize)> {
+        let sections = [
+            (0, parameters.powers_length),
+            (parameters.powers_length, parameters.powers_g1_length),
+        ];
+        let mut ranges = vec![];
+        for &(section_start, section_end) in sections.iter() {
+            for start in (section_start..section_end).step_by(parameters.batch_size) {
+                let end = std::cmp::min(start + parameters.batch_size, section_end) - 1;
+                ranges.push((start, end));
             }
         }
 
+        ranges
+    }
+
+    /// Verifies that the powers in the chunks with indices `chunk_indices` (see `chunk_ranges`)
+    /// of the accumulator are consistent with each other and with the first power of the next
+    /// chunk. Unlike `verify_transformation` this checks a single accumulator, so it is a cheap
+    /// way to re-verify only the chunks that changed in the latest round.
+    pub fn verify_chunks(
+        input_map: &Mmap,
+        input_is_compressed: UseCompression,
+        check_input_for_correctness: CheckForCorrectness,
+        chunk_indices: &[usize],
+        parameters: &'a CeremonyParams<E>,
+    ) -> Result<bool, DeserializationError> {
+        Self::verify_chunks_and_links(
+            input_map,
+            input_is_compressed,
+            check_input_for_correctness,
+            chunk_indices,
+            false,
+            parameters,
+        )
+    }
+
+    /// Same as `verify_chunks`, but also checks every chunk against the last power of the
+    /// previous chunk. A chunk is then verified on both of its boundaries even if its
+    /// neighbours are not, so a broken link shows up in the verification of either chunk
+    /// instead of only when the whole accumulator is verified.
+    pub fn verify_chunks_with_boundaries(
+        input_map: &Mmap,
+        input_is_compressed: UseCompression,
+        check_input_for_correctness: CheckForCorrectness,
+        chunk_indices: &[usize],
+        parameters: &'a CeremonyParams<E>,
+    ) -> Result<bool, DeserializationError> {
+        Self::verify_chunks_and_links(
+            input_map,
+            input_is_compressed,
+            check_input_for_correctness,
+            chunk_indices,
+            true,
+            parameters,
+        )
+    }
+
+    fn verify_chunks_and_links(
+        input_map: &Mmap,
+        input_is_compressed: UseCompression,
+        check_input_for_correctness: CheckForCorrectness,
+        chunk_indices: &[usize],
+        check_previous_link: bool,
+        parameters: &'a CeremonyParams<E>,
+    ) -> Result<bool, DeserializationError> {
+        let ranges = Self::chunk_ranges(parameters);
+        let mut accumulator = Self::empty(parameters);
+
+        accumulator.read_chunk(
+            0,
+            2,
+            input_is_compressed,
+            check_input_for_correctness,
+            input_map,
+        )?;
+        if accumulator.tau_powers_g1[0] != parameters.generators.g1 {
+            error!("tau_powers_g1[0] is not the generator of G1");
+            return Ok(false);
+        }
+        if accumulator.tau_powers_g2[0] != parameters.generators.g2 {
+            error!("tau_powers_g2[0] is not the generator of G2");
+            return Ok(false);
+        }
+        let tau_g1 = (accumulator.tau_powers_g1[0], accumulator.tau_powers_g1[1]);
+        let tau_g2 = (accumulator.tau_powers_g2[0], accumulator.tau_powers_g2[1]);
+        let mut batch = RatioBatch::<E>::new(tau_g1, tau_g2);
+
+        for &index in chunk_indices {
+            let (start, end) = match ranges.get(index) {
+                Some(range) => *range,
+                None => {
+                    error!(
+                        "Chunk index {} is out of range, there are {} chunks",
+                        index,
+                        ranges.len()
+                    );
+                    return Ok(false);
+                }
+            };
+
+            // The chunk itself, then the link between its last power and the next one, and
+            // optionally the link between the last power of the previous chunk and its first
+            let mut parts = vec![(start, end - start + 1), (end, 2)];
+            if check_previous_link && start > 0 {
+                parts.push((start - 1, 2));
+            }
+            for &(from, size) in parts.iter() {
+                if from + 1 >= parameters.powers_g1_length {
+                    continue;
+                }
+                accumulator.read_chunk(
+                    from,
+                    size,
+                    input_is_compressed,
+                    check_input_for_correctness,
+                    input_map,
+                )?;
+
+                let rng = &mut thread_rng();
+                if accumulator.tau_powers_g1.len() > 1 {
+                    batch.add_g1(rng, power_pairs(&accumulator.tau_powers_g1));
+                }
+                if accumulator.tau_powers_g2.len() > 1 {
+                    batch.add_g2(rng, power_pairs(&accumulator.tau_powers_g2));
+                }
+                if accumulator.alpha_tau_powers_g1.len() > 1 {
+                    batch.add_g1(rng, power_pairs(&accumulator.alpha_tau_powers_g1));
+                }
+                if accumulator.beta_tau_powers_g1.len() > 1 {
+                    batch.add_g1(rng, power_pairs(&accumulator.beta_tau_powers_g1));
+                }
+            }
+            // The chunk and its links are checked together
+            if !batch.is_empty() && !check_ratios(&mut batch) {
+                error!("Invalid ratio of the powers in chunk {}", index);
+                return Ok(false);
+            }
+            info!("Done verifying chunk {} ({} to {})", index, start, end);
+        }
+
+        Ok(true)
+    }
+
+    /// Checks the links between all consecutive chunks of an accumulator, reading only the two
+    /// powers on each side of every boundary. The links of all boundaries and element types
+    /// are merged with a random linear combination into one ratio check in each group, so an
+    /// accumulator whose chunks all come from the same tau is audited with a few pairings;
+    /// the boundaries are only checked one by one if that fails, to tell which of them are
+    /// broken. This catches chunks that are valid on their own but were computed from another
+    /// contribution, which `verify_chunks` only sees for the chunks it is asked to verify.
+    pub fn audit_boundaries(
+        input_map: &Mmap,
+        input_is_compressed: UseCompression,
+        check_input_for_correctness: CheckForCorrectness,
+        parameters: &'a CeremonyParams<E>,
+    ) -> Result<Vec<BoundaryCheck>, DeserializationError> {
+        let view = AccumulatorView::new(
+            input_map,
+            input_is_compressed,
+            check_input_for_correctness,
+            parameters,
+        )?;
+        let tau_powers_g1 = view.tau_powers_g1(0..2)?;
+        let tau_powers_g2 = view.tau_powers_g2(0..2)?;
+        let tau_g1 = (tau_powers_g1[0], tau_powers_g1[1]);
+        let tau_g2 = (tau_powers_g2[0], tau_powers_g2[1]);
+
+        // The pairs of powers across each boundary, in G1 and in G2
+        let mut links = vec![];
+        for (chunk, &(_, end)) in Self::chunk_ranges(parameters).iter().enumerate() {
+            if end + 1 >= parameters.powers_g1_length {
+                continue;
+            }
+            let range = end..end + 2;
+            let mut g1 = vec![view.tau_powers_g1(range.clone())?];
+            let mut g2 = vec![];
+            if end + 1 < parameters.powers_length {
+                g1.push(view.alpha_tau_powers_g1(range.clone())?);
+                g1.push(view.beta_tau_powers_g1(range.clone())?);
+                g2.push(view.tau_powers_g2(range)?);
+            }
+            links.push((chunk, end, g1, g2));
+        }
+
+        let same_ratios = |g1: &[&Vec<E::G1Affine>], g2: &[&Vec<E::G2Affine>]| {
+            let (g1_left, g1_right): (Vec<_>, Vec<_>) =
+                g1.iter().map(|pair| (pair[0], pair[1])).unzip();
+            if !same_ratio(merge_pairs(&g1_left, &g1_right), tau_g2) {
+                return false;
+            }
+            let (g2_left, g2_right): (Vec<_>, Vec<_>) =
+                g2.iter().map(|pair| (pair[0], pair[1])).unzip();
+            g2.is_empty() || same_ratio(merge_pairs(&g2_left, &g2_right), tau_g1)
+        };
+
+        let all_g1 = links.iter().flat_map(|link| &link.2).collect::<Vec<_>>();
+        let all_g2 = links.iter().flat_map(|link| &link.3).collect::<Vec<_>>();
+        let all_valid = links.is_empty() || same_ratios(&all_g1, &all_g2);
+        if !all_valid {
+            error!("Some of the chunk boundaries are broken, checking them one by one");
+        }
+
+        Ok(links
+            .iter()
+            .map(|(chunk, end, g1, g2)| {
+                let valid = all_valid
+                    || same_ratios(
+                        &g1.iter().collect::<Vec<_>>(),
+                        &g2.iter().collect::<Vec<_>>(),
+                    );
+                if !valid {
+                    error!("Invalid link between chunk {} and the next one", chunk);
+                }
+                BoundaryCheck {
+                    chunk: *chunk,
+                    index: *end,
+                    valid,
+                }
+            })
+            .collect())
+    }
+
+    /// Verifies a single element of an accumulator against its neighbours, reading only the
+    /// element, its neighbours and the first powers of tau. Powers of tau, alpha and beta are
+    /// checked to have the ratio of tau with the previous and the next power, and beta in G2
+    /// and the first beta power in G1 are checked against each other. This is meant for spot
+    /// audits and for narrowing down a failed chunk; an accumulator is only valid if all of
+    /// its elements are.
+    pub fn verify_element(
+        input_map: &Mmap,
+        input_is_compressed: UseCompression,
+        check_input_for_correctness: CheckForCorrectness,
+        element_type: ElementType,
+        index: usize,
+        parameters: &'a CeremonyParams<E>,
+    ) -> Result<bool, DeserializationError> {
+        let length = match element_type {
+            ElementType::TauG1 => parameters.powers_g1_length,
+            ElementType::BetaG2 => 1,
+            _ => parameters.powers_length,
+        };
+        if index >= length {
+            error!(
+                "Index {} of {:?} is out of range, there are {} of them",
+                index, element_type, length
+            );
+            return Ok(false);
+        }
+
+        let mut accumulator = Self::empty(parameters);
+        let read_g1 = |accumulator: &mut Self, element_type, from, size| {
+            accumulator.read_g1_points(
+                from,
+                size,
+                element_type,
+                input_is_compressed,
+                check_input_for_correctness,
+                input_map,
+            )
+        };
+        let read_g2 = |accumulator: &mut Self, element_type, from, size| {
+            accumulator.read_g2_points(
+                from,
+                size,
+                element_type,
+                input_is_compressed,
+                check_input_for_correctness,
+                input_map,
+            )
+        };
+
+        let tau_powers_g1 = read_g1(&mut accumulator, ElementType::TauG1, 0, 2)?;
+        let tau_powers_g2 = read_g2(&mut accumulator, ElementType::TauG2, 0, 2)?;
+        if tau_powers_g1[0] != parameters.generators.g1 {
+            error!("tau_powers_g1[0] is not the generator of G1");
+            return Ok(false);
+        }
+        if tau_powers_g2[0] != parameters.generators.g2 {
+            error!("tau_powers_g2[0] is not the generator of G2");
+            return Ok(false);
+        }
+        let tau_g1 = (tau_powers_g1[0], tau_powers_g1[1]);
+        let tau_g2 = (tau_powers_g2[0], tau_powers_g2[1]);
+
+        // The element with the previous and the next power, where they exist
+        let from = index.saturating_sub(1);
+        let size = std::cmp::min(index + 2, length) - from;
+        let neighbours_valid = match element_type {
+            ElementType::TauG1 | ElementType::AlphaG1 | ElementType::BetaG1 => {
+                let powers = read_g1(&mut accumulator, element_type, from, size)?;
+                same_ratio(power_pairs(&powers), tau_g2)
+            }
+            ElementType::TauG2 => {
+                let powers = read_g2(&mut accumulator, element_type, from, size)?;
+                same_ratio(power_pairs(&powers), tau_g1)
+            }
+            ElementType::BetaG2 => true,
+        };
+        if !neighbours_valid {
+            error!(
+                "Invalid ratio of {:?} element {} with its neighbours",
+                element_type, index
+            );
+            return Ok(false);
+        }
+
+        let is_first_beta = match element_type {
+            ElementType::BetaG1 => index == 0,
+            ElementType::BetaG2 => true,
+            _ => false,
+        };
+        if is_first_beta {
+            let beta_g1 = read_g1(&mut accumulator, ElementType::BetaG1, 0, 1)?[0];
+            let beta_g2 = read_g2(&mut accumulator, ElementType::BetaG2, 0, 1)?[0];
+            if !same_ratio(
+                (parameters.generators.g1, beta_g1),
+                (parameters.generators.g2, beta_g2),
+            ) {
+                error!("Invalid ratio of beta_tau_powers_g1[0] and beta_g2");
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    pub fn decompress(
+        input_map: &Mmap,
+        output_map: &mut MmapMut,
+        check_input_for_correctness: CheckForCorrectness,
+        parameters: &'a CeremonyParams<E>,
+    ) -> io::Result<()> {
+        Self::decompress_with_copy(
+            input_map,
+            output_map,
+            None,
+            check_input_for_correctness,
+            parameters,
+        )
+    }
+
+    /// Same as `decompress`, but also writes the accumulator in compressed form to
+    /// `compressed_output_map` from the points read for the uncompressed one, so that both
+    /// outputs take a single pass over the input.
+    pub fn decompress_with_copy(
+        input_map: &Mmap,
+        output_map: &mut MmapMut,
+        mut compressed_output_map: Option<&mut MmapMut>,
+        check_input_for_correctness: CheckForCorrectness,
+        parameters: &'a CeremonyParams<E>,
+    ) -> io::Result<()> {
+        let mut accumulator = Self::empty(parameters);
+
+        iter_chunk(
+            0..parameters.powers_length,
+            parameters.batch_size,
+            |start, end| -> io::Result<()> {
+                let size = end - start + 1;
+                accumulator
+                    .read_chunk(
+                        start,
+                        size,
+                        UseCompression::Yes,
+                        check_input_for_correctness,
+                        &input_map,
+                    )
+                    .map_err(|e| chunk_error(e, start, end, "the source of decompression"))?;
+                accumulator.write_chunk(start, UseCompression::No, output_map)?;
+                if let Some(compressed_output_map) = compressed_output_map.as_mut() {
+                    accumulator.write_chunk(start, UseCompression::Yes, compressed_output_map)?;
+                }
+                Ok(())
+            },
+        )?;
+
+        iter_chunk(
+            parameters.powers_length..parameters.powers_g1_length,
+            parameters.batch_size,
+            |start, end| -> io::Result<()> {
+                let size = end - start + 1;
+                accumulator
+                    .read_chunk(
+                        start,
+                        size,
+                        UseCompression::Yes,
+                        check_input_for_correctness,
+                        &input_map,
+                    )
+                    .map_err(|e| chunk_error(e, start, end, "the source of decompression"))?;
+                assert_eq!(
+                    accumulator.tau_powers_g2.len(),
+                    0,
+                    "during rest of tau g1 generation tau g2 must be empty"
+                );
+                assert_eq!(
+                    accumulator.alpha_tau_powers_g1.len(),
+                    0,
+                    "during rest of tau g1 generation alpha*tau in g1 must be empty"
+                );
+                assert_eq!(
+                    accumulator.beta_tau_powers_g1.len(),
+                    0,
+                    "during rest of tau g1 generation beta*tau in g1 must be empty"
+                );
+
+                accumulator.write_chunk(start, UseCompression::No, output_map)?;
+                if let Some(compressed_output_map) = compressed_output_map.as_mut() {
+                    accumulator.write_chunk(start, UseCompression::Yes, compressed_output_map)?;
+                }
+                Ok(())
+            },
+        )?;
+
+        Ok(())
+    }
+
+    /// Writes the accumulator in `input_map`, encoded with `from` compression, to `output_map`
+    /// with `to` compression, in both directions. The hash that the input starts with is
+    /// copied, and so is the public key of a response, if the input has one and the output is
+    /// the size of a response. The hash of the file itself changes with its encoding, and the
+    /// hash chain is over uncompressed challenges and compressed responses, so converted files
+    /// are for distribution.
+    pub fn convert_compression(
+        input_map: &Mmap,
+        output_map: &mut MmapMut,
+        from: UseCompression,
+        to: UseCompression,
+        check_input_for_correctness: CheckForCorrectness,
+        parameters: &'a CeremonyParams<E>,
+    ) -> io::Result<()> {
+        let input_sizes = expected_sizes(parameters, from);
+        let output_sizes = expected_sizes(parameters, to);
+        let is_response = input_map.len() == input_sizes.response;
+        let expected_output = if is_response {
+            output_sizes.response
+        } else {
+            output_sizes.challenge
+        };
+        if input_map.len() != input_sizes.challenge && !is_response {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "the input is {} bytes, neither the size of a challenge nor of a response",
+                    input_map.len()
+                ),
+            ));
+        }
+        if output_map.len() != expected_output {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "the output is {} bytes, but should be {}",
+                    output_map.len(),
+                    expected_output
+                ),
+            ));
+        }
+
+        write_hash_header(output_map, &read_hash_header(input_map)?)?;
+        Self::transform_with(
+            input_map,
+            output_map,
+            from,
+            to,
+            check_input_for_correctness,
+            &Unchanged,
+            parameters,
+        )?;
+        if is_response {
+            PublicKey::<E>::read(input_map, from, parameters)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?
+                .write(output_map, to, parameters)?;
+        }
+
         Ok(())
     }
 
@@ -623,8 +1442,6 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
         compression: UseCompression,
         parameters: &'a CeremonyParams<E>,
     ) -> io::Result<BatchedAccumulator<'a, E>> {
-        use itertools::MinMaxResult::MinMax;
-
         let mut accumulator = Self::empty(parameters);
 
         let mut tau_powers_g1 = vec![];
@@ -633,8 +1450,10 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
         let mut beta_tau_powers_g1 = vec![];
         let mut beta_g2 = vec![];
 
-        for chunk in &(0..parameters.powers_length).chunks(parameters.batch_size) {
-            if let MinMax(start, end) = chunk.minmax() {
+        iter_chunk(
+            0..parameters.powers_length,
+            parameters.batch_size,
+            |start, end| -> io::Result<()> {
                 let size = end - start + 1;
                 accumulator
                     .read_chunk(
@@ -644,12 +1463,7 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
                         check_input_for_correctness,
                         &input_map,
                     )
-                    .unwrap_or_else(|_| {
-                        panic!(format!(
-                            "must read a chunk from {} to {} from source of decompression",
-                            start, end
-                        ))
-                    });
+                    .map_err(|e| chunk_error(e, start, end, "the source of decompression"))?;
                 tau_powers_g1.extend_from_slice(&accumulator.tau_powers_g1);
                 tau_powers_g2.extend_from_slice(&accumulator.tau_powers_g2);
                 alpha_tau_powers_g1.extend_from_slice(&accumulator.alpha_tau_powers_g1);
@@ -657,15 +1471,14 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
                 if start == 0 {
                     beta_g2.extend_from_slice(&[accumulator.beta_g2]);
                 }
-            } else {
-                panic!("Chunk does not have a min and max");
-            }
-        }
-
-        for chunk in
-            &(parameters.powers_length..parameters.powers_g1_length).chunks(parameters.batch_size)
-        {
-            if let MinMax(start, end) = chunk.minmax() {
+                Ok(())
+            },
+        )?;
+
+        iter_chunk(
+            parameters.powers_length..parameters.powers_g1_length,
+            parameters.batch_size,
+            |start, end| -> io::Result<()> {
                 let size = end - start + 1;
                 accumulator
                     .read_chunk(
@@ -675,12 +1488,7 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
                         check_input_for_correctness,
                         &input_map,
                     )
-                    .unwrap_or_else(|_| {
-                        panic!(format!(
-                            "must read a chunk from {} to {} from source of decompression",
-                            start, end
-                        ))
-                    });
+                    .map_err(|e| chunk_error(e, start, end, "the source of decompression"))?;
                 assert_eq!(
                     accumulator.tau_powers_g2.len(),
                     0,
@@ -701,10 +1509,9 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
                 tau_powers_g2.extend_from_slice(&accumulator.tau_powers_g2);
                 alpha_tau_powers_g1.extend_from_slice(&accumulator.alpha_tau_powers_g1);
                 beta_tau_powers_g1.extend_from_slice(&accumulator.beta_tau_powers_g1);
-            } else {
-                panic!("Chunk does not have a min and max");
-            }
-        }
+                Ok(())
+            },
+        )?;
 
         Ok(BatchedAccumulator {
             tau_powers_g1,
@@ -717,16 +1524,88 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
         })
     }
 
+    /// Copies the leading powers of an accumulator into a smaller, self-contained accumulator
+    /// described by `reduced_parameters`: the first `powers_g1_length` tau powers in G1, the first
+    /// `powers_length` tau powers in G2 and alpha/beta powers in G1, and beta in G2. The output
+    /// starts with `reduced_hash`, so it is a valid challenge for a ceremony of the reduced size.
+    /// Points are processed in batches, so the input is never fully loaded into memory.
+    #[allow(clippy::too_many_arguments)]
+    pub fn extract_subset(
+        input_map: &Mmap,
+        output_map: &mut MmapMut,
+        input_is_compressed: UseCompression,
+        compress_the_output: UseCompression,
+        check_input_for_correctness: CheckForCorrectness,
+        parameters: &'a CeremonyParams<E>,
+        reduced_parameters: &'a CeremonyParams<E>,
+    ) -> io::Result<()> {
+        if reduced_parameters.size > parameters.size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "can not extract 2^{} powers from an accumulator with 2^{} powers",
+                    reduced_parameters.size, parameters.size
+                ),
+            ));
+        }
+
+        let hash = reduced_hash(parameters.size as u8, reduced_parameters.size as u8);
+        write_hash_header(output_map, hash.as_slice())?;
+
+        let mut accumulator = Self::empty(parameters);
+
+        // Chunks must not cross the end of the G2/alpha/beta sections, otherwise `read_chunk`
+        // returns those sections empty for the whole chunk
+        let sections = [
+            0..reduced_parameters.powers_length,
+            reduced_parameters.powers_length..reduced_parameters.powers_g1_length,
+        ];
+        for section in sections.iter() {
+            iter_chunk(
+                section.clone(),
+                reduced_parameters.batch_size,
+                |start, end| -> io::Result<()> {
+                    let size = end - start + 1;
+                    accumulator
+                        .read_chunk(
+                            start,
+                            size,
+                            input_is_compressed,
+                            check_input_for_correctness,
+                            input_map,
+                        )
+                        .map_err(|e| chunk_error(e, start, end, "the input"))?;
+
+                    // Points beyond the reduced lengths are skipped by `write_point`
+                    let mut reduced_accumulator = BatchedAccumulator::<E> {
+                        tau_powers_g1: accumulator.tau_powers_g1.clone(),
+                        tau_powers_g2: accumulator.tau_powers_g2.clone(),
+                        alpha_tau_powers_g1: accumulator.alpha_tau_powers_g1.clone(),
+                        beta_tau_powers_g1: accumulator.beta_tau_powers_g1.clone(),
+                        beta_g2: accumulator.beta_g2,
+                        hash,
+                        parameters: reduced_parameters,
+                    };
+                    reduced_accumulator.write_chunk(start, compress_the_output, output_map)?;
+                    info!("Done extracting {} powers of tau", end);
+                    Ok(())
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+
     pub fn serialize(
         &mut self,
         output_map: &mut MmapMut,
         compression: UseCompression,
         parameters: &CeremonyParams<E>,
     ) -> io::Result<()> {
-        use itertools::MinMaxResult::MinMax;
-
-        for chunk in &(0..parameters.powers_length).chunks(parameters.batch_size) {
-            if let MinMax(start, end) = chunk.minmax() {
+        iter_chunk(
+            0..parameters.powers_length,
+            parameters.batch_size,
+            |start, end| -> io::Result<()> {
                 let mut tmp_acc = BatchedAccumulator::<E> {
                     tau_powers_g1: (&self.tau_powers_g1[start..=end]).to_vec(),
                     tau_powers_g2: (&self.tau_powers_g2[start..=end]).to_vec(),
@@ -737,15 +1616,14 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
                     parameters,
                 };
                 tmp_acc.write_chunk(start, compression, output_map)?;
-            } else {
-                panic!("Chunk does not have a min and max");
-            }
-        }
-
-        for chunk in
-            &(parameters.powers_length..parameters.powers_g1_length).chunks(parameters.batch_size)
-        {
-            if let MinMax(start, end) = chunk.minmax() {
+                Ok(())
+            },
+        )?;
+
+        iter_chunk(
+            parameters.powers_length..parameters.powers_g1_length,
+            parameters.batch_size,
+            |start, end| -> io::Result<()> {
                 let mut tmp_acc = BatchedAccumulator::<E> {
                     tau_powers_g1: (&self.tau_powers_g1[start..=end]).to_vec(),
                     tau_powers_g2: vec![],
@@ -756,10 +1634,9 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
                     parameters,
                 };
                 tmp_acc.write_chunk(start, compression, output_map)?;
-            } else {
-                panic!("Chunk does not have a min and max");
-            }
-        }
+                Ok(())
+            },
+        )?;
 
         Ok(())
     }
@@ -770,9 +1647,11 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
         size: usize,
         compression: UseCompression,
         checked: CheckForCorrectness,
-        input_map: &Mmap,
+        input_map: &[u8],
     ) -> Result<(), DeserializationError> {
-        self.tau_powers_g1 = match compression {
+        let _span = profiling::span(Operation::Serialization, size);
+        metrics::count(Metric::ChunksRead, 1);
+        self.tau_powers_g1 = match compression.g1() {
             UseCompression::Yes => self
                 .read_points_chunk::<<E::G1Affine as CurveAffine>::Compressed>(
                     from,
@@ -782,18 +1661,17 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
                     checked,
                     &input_map,
                 )?,
-            UseCompression::No => self
-                .read_points_chunk::<<E::G1Affine as CurveAffine>::Uncompressed>(
-                    from,
-                    size,
-                    ElementType::TauG1,
-                    compression,
-                    checked,
-                    &input_map,
-                )?,
+            _ => self.read_points_chunk::<<E::G1Affine as CurveAffine>::Uncompressed>(
+                from,
+                size,
+                ElementType::TauG1,
+                compression,
+                checked,
+                input_map,
+            )?,
         };
 
-        self.tau_powers_g2 = match compression {
+        self.tau_powers_g2 = match compression.g2() {
             UseCompression::Yes => self
                 .read_points_chunk::<<E::G2Affine as CurveAffine>::Compressed>(
                     from,
@@ -803,18 +1681,17 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
                     checked,
                     &input_map,
                 )?,
-            UseCompression::No => self
-                .read_points_chunk::<<E::G2Affine as CurveAffine>::Uncompressed>(
-                    from,
-                    size,
-                    ElementType::TauG2,
-                    compression,
-                    checked,
-                    &input_map,
-                )?,
+            _ => self.read_points_chunk::<<E::G2Affine as CurveAffine>::Uncompressed>(
+                from,
+                size,
+                ElementType::TauG2,
+                compression,
+                checked,
+                input_map,
+            )?,
         };
 
-        self.alpha_tau_powers_g1 = match compression {
+        self.alpha_tau_powers_g1 = match compression.g1() {
             UseCompression::Yes => self
                 .read_points_chunk::<<E::G1Affine as CurveAffine>::Compressed>(
                     from,
@@ -824,18 +1701,17 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
                     checked,
                     &input_map,
                 )?,
-            UseCompression::No => self
-                .read_points_chunk::<<E::G1Affine as CurveAffine>::Uncompressed>(
-                    from,
-                    size,
-                    ElementType::AlphaG1,
-                    compression,
-                    checked,
-                    &input_map,
-                )?,
+            _ => self.read_points_chunk::<<E::G1Affine as CurveAffine>::Uncompressed>(
+                from,
+                size,
+                ElementType::AlphaG1,
+                compression,
+                checked,
+                input_map,
+            )?,
         };
 
-        self.beta_tau_powers_g1 = match compression {
+        self.beta_tau_powers_g1 = match compression.g1() {
             UseCompression::Yes => self
                 .read_points_chunk::<<E::G1Affine as CurveAffine>::Compressed>(
                     from,
@@ -845,18 +1721,17 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
                     checked,
                     &input_map,
                 )?,
-            UseCompression::No => self
-                .read_points_chunk::<<E::G1Affine as CurveAffine>::Uncompressed>(
-                    from,
-                    size,
-                    ElementType::BetaG1,
-                    compression,
-                    checked,
-                    &input_map,
-                )?,
+            _ => self.read_points_chunk::<<E::G1Affine as CurveAffine>::Uncompressed>(
+                from,
+                size,
+                ElementType::BetaG1,
+                compression,
+                checked,
+                input_map,
+            )?,
         };
 
-        self.beta_g2 = match compression {
+        self.beta_g2 = match compression.g2() {
             UseCompression::Yes => {
                 let points = self.read_points_chunk::<<E::G2Affine as CurveAffine>::Compressed>(
                     0,
@@ -869,7 +1744,7 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
 
                 points[0]
             }
-            UseCompression::No => {
+            _ => {
                 let points = self.read_points_chunk::<<E::G2Affine as CurveAffine>::Uncompressed>(
                     0,
                     1,
@@ -886,6 +1761,66 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
         Ok(())
     }
 
+    pub(crate) fn read_g1_points(
+        &mut self,
+        from: usize,
+        size: usize,
+        element_type: ElementType,
+        compression: UseCompression,
+        checked: CheckForCorrectness,
+        input_map: &[u8],
+    ) -> Result<Vec<E::G1Affine>, DeserializationError> {
+        match compression.g1() {
+            UseCompression::Yes => self
+                .read_points_chunk::<<E::G1Affine as CurveAffine>::Compressed>(
+                    from,
+                    size,
+                    element_type,
+                    compression,
+                    checked,
+                    input_map,
+                ),
+            _ => self.read_points_chunk::<<E::G1Affine as CurveAffine>::Uncompressed>(
+                from,
+                size,
+                element_type,
+                compression,
+                checked,
+                input_map,
+            ),
+        }
+    }
+
+    pub(crate) fn read_g2_points(
+        &mut self,
+        from: usize,
+        size: usize,
+        element_type: ElementType,
+        compression: UseCompression,
+        checked: CheckForCorrectness,
+        input_map: &[u8],
+    ) -> Result<Vec<E::G2Affine>, DeserializationError> {
+        match compression.g2() {
+            UseCompression::Yes => self
+                .read_points_chunk::<<E::G2Affine as CurveAffine>::Compressed>(
+                    from,
+                    size,
+                    element_type,
+                    compression,
+                    checked,
+                    input_map,
+                ),
+            _ => self.read_points_chunk::<<E::G2Affine as CurveAffine>::Uncompressed>(
+                from,
+                size,
+                element_type,
+                compression,
+                checked,
+                input_map,
+            ),
+        }
+    }
+
     fn read_points_chunk<ENC: EncodedPoint>(
         &mut self,
         from: usize,
@@ -893,7 +1828,7 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
         element_type: ElementType,
         compression: UseCompression,
         checked: CheckForCorrectness,
-        input_map: &Mmap,
+        input_map: &[u8],
     ) -> Result<Vec<ENC::Affine>, DeserializationError> {
         // Read the encoded elements
         let mut res = vec![ENC::empty(); size];
@@ -919,9 +1854,21 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
             let element_size = self.get_size(element_type, compression);
             let mut memory_slice = input_map
                 .get(position..position + element_size)
-                .expect("must read point data from file");
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!(
+                            "the file ends at {} bytes, before {:?} element {} at offset {}",
+                            input_map.len(),
+                            element_type,
+                            index,
+                            position
+                        ),
+                    )
+                })?;
             memory_slice.read_exact(encoded.as_mut())?;
         }
+        metrics::count(Metric::BytesRead, (size * ENC::size()) as u64);
 
         // Allocate space for the deserialized elements
         let mut res_affine = vec![ENC::Affine::zero(); size];
@@ -934,6 +1881,13 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
         // If any of our threads encounter a deserialization/IO error, catch
         // it with this.
         let decoding_error = Arc::new(Mutex::new(None));
+        let subgroup_check = self.parameters.curve.subgroup_check;
+
+        let _span = if checked.checks_subgroup() {
+            Some(profiling::span(Operation::SubgroupCheck, size))
+        } else {
+            None
+        };
 
         crossbeam::scope(|scope| {
             for (source, target) in res
@@ -951,24 +1905,26 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
                             // The hash chain prevents this from being a problem: the
                             // transcript guarantees that the accumulator was properly
                             // formed.
-                            match checked {
-                                CheckForCorrectness::Yes => {
-                                    // Points at infinity are never expected in the accumulator
-                                    source
-                                        .into_affine()
-                                        .map_err(|e| e.into())
-                                        .and_then(|source| {
-                                            if source.is_zero() {
-                                                Err(DeserializationError::PointAtInfinity)
-                                            } else {
-                                                Ok(source)
-                                            }
-                                        })
-                                }
-                                CheckForCorrectness::No => {
-                                    source.into_affine_unchecked().map_err(|e| e.into())
+                            let decoded = if checked.checks_subgroup() {
+                                source.into_affine().and_then(|source| {
+                                    if subgroup_check.is_in_subgroup(&source) {
+                                        Ok(source)
+                                    } else {
+                                        Err(GroupDecodingError::NotInSubgroup)
+                                    }
+                                })
+                            } else {
+                                source.into_affine_unchecked()
+                            };
+                            // Points at infinity are never expected in the accumulator, unless
+                            // the checks are skipped
+                            decoded.map_err(|e| e.into()).and_then(|source| {
+                                if checked.rejects_infinity() && source.is_zero() {
+                                    Err(DeserializationError::PointAtInfinity)
+                                } else {
+                                    Ok(source)
                                 }
-                            }
+                            })
                         } {
                             Ok(source) => {
                                 *target = source;
@@ -982,22 +1938,21 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
             }
         }).unwrap();
 
-        // extra check that during the decompression all the the initially initialized infinitu points
-        // were replaced with something
-        for decoded in res_affine.iter() {
-            if decoded.is_zero() {
-                return Err(DeserializationError::PointAtInfinity);
-            }
-        }
-
-        match Arc::try_unwrap(decoding_error)
+        if let Some(e) = Arc::try_unwrap(decoding_error)
             .unwrap()
             .into_inner()
             .unwrap()
         {
-            Some(e) => Err(e),
-            None => Ok(res_affine),
+            return Err(e);
+        }
+
+        // extra check that during the decompression all the the initially initialized infinitu points
+        // were replaced with something
+        if checked.rejects_infinity() && res_affine.iter().any(|decoded| decoded.is_zero()) {
+            return Err(DeserializationError::PointAtInfinity);
         }
+
+        Ok(res_affine)
     }
 
     fn write_all(
@@ -1076,18 +2031,25 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
             }
         };
 
-        match compression {
-            UseCompression::Yes => {
-                let position = self.calculate_mmap_position(index, element_type, compression);
-                // let size = self.get_size(element_type, compression);
-                (&mut output_map[position..]).write_all(p.into_compressed().as_ref())?;
-            }
-            UseCompression::No => {
-                let position = self.calculate_mmap_position(index, element_type, compression);
-                // let size = self.get_size(element_type, compression);
-                (&mut output_map[position..]).write_all(p.into_uncompressed().as_ref())?;
-            }
+        let position = self.calculate_mmap_position(index, element_type, compression);
+        let size = self.get_size(element_type, compression);
+        let output_len = output_map.len();
+        let mut memory_slice = output_map
+            .get_mut(position..position + size)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!(
+                        "the output ends at {} bytes, before {:?} element {} at offset {}",
+                        output_len, element_type, index, position
+                    ),
+                )
+            })?;
+        match compression.of(element_type) {
+            UseCompression::Yes => memory_slice.write_all(p.into_compressed().as_ref())?,
+            _ => memory_slice.write_all(p.into_uncompressed().as_ref())?,
         };
+        metrics::count(Metric::BytesWritten, size as u64);
 
         Ok(())
     }
@@ -1099,6 +2061,11 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
         compression: UseCompression,
         output_map: &mut MmapMut,
     ) -> io::Result<()> {
+        let _span = profiling::span(Operation::Serialization, self.tau_powers_g1.len());
+        metrics::count(Metric::ChunksWritten, 1);
+        if chunk_start == 0 {
+            write_policy_header(output_map, compression, self.parameters)?;
+        }
         self.write_all(chunk_start, compression, ElementType::TauG1, output_map)?;
         if chunk_start < self.parameters.powers_length {
             self.write_all(chunk_start, compression, ElementType::TauG2, output_map)?;
@@ -1125,67 +2092,34 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
         key: &PrivateKey<E>,
         parameters: &'a CeremonyParams<E>,
     ) -> io::Result<()> {
-        /// Exponentiate a large number of points, with an optional coefficient to be applied to the
-        /// exponent.
-        fn batch_exp<EE: Engine, C: CurveAffine<Engine = EE, Scalar = EE::Fr>>(
-            bases: &mut [C],
-            exp: &[C::Scalar],
-            coeff: Option<&C::Scalar>,
-        ) {
-            assert_eq!(bases.len(), exp.len());
-            let mut projective = vec![C::Projective::zero(); bases.len()];
-            let chunk_size = bases.len() / num_cpus::get();
-
-            // Perform wNAF over multiple cores, placing results into `projective`.
-            crossbeam::scope(|scope| {
-                for ((bases, exp), projective) in bases
-                    .chunks_mut(chunk_size)
-                    .zip(exp.chunks(chunk_size))
-                    .zip(projective.chunks_mut(chunk_size))
-                {
-                    scope.spawn(move |_| {
-                        let mut wnaf = Wnaf::new();
-
-                        for ((base, exp), projective) in
-                            bases.iter_mut().zip(exp.iter()).zip(projective.iter_mut())
-                        {
-                            let mut exp = *exp;
-                            if let Some(coeff) = coeff {
-                                exp.mul_assign(coeff);
-                            }
-
-                            *projective =
-                                wnaf.base(base.into_projective(), 1).scalar(exp.into_repr());
-                        }
-                    });
-                }
-            }).unwrap();
-
-            // Perform batch normalization
-            crossbeam::scope(|scope| {
-                for projective in projective.chunks_mut(chunk_size) {
-                    scope.spawn(move |_| {
-                        C::Projective::batch_normalization(projective);
-                    });
-                }
-            }).unwrap();
-
-            // Turn it all back into affine points
-            for (projective, affine) in projective.iter().zip(bases.iter_mut()) {
-                *affine = projective.into_affine();
-                assert!(
-                    !affine.is_zero(),
-                    "your contribution happened to produce a point at infinity, please re-run"
-                );
-            }
-        }
+        Self::transform_with(
+            input_map,
+            output_map,
+            input_is_compressed,
+            compress_the_output,
+            check_input_for_correctness,
+            key,
+            parameters,
+        )
+    }
 
+    /// Same as `transform`, but the secrets are applied by `transformer`, which may keep them
+    /// in another process, see `signer::ExternalSigner`.
+    pub fn transform_with<T: ChunkTransformer<E>>(
+        input_map: &Mmap,
+        output_map: &mut MmapMut,
+        input_is_compressed: UseCompression,
+        compress_the_output: UseCompression,
+        check_input_for_correctness: CheckForCorrectness,
+        transformer: &T,
+        parameters: &'a CeremonyParams<E>,
+    ) -> io::Result<()> {
         let mut accumulator = Self::empty(parameters);
 
-        use itertools::MinMaxResult::MinMax;
-
-        for chunk in &(0..parameters.powers_length).chunks(parameters.batch_size) {
-            if let MinMax(start, end) = chunk.minmax() {
+        iter_chunk(
+            0..parameters.powers_length,
+            parameters.batch_size,
+            |start, end| -> io::Result<()> {
                 let size = end - start + 1;
                 accumulator
                     .read_chunk(
@@ -1195,54 +2129,19 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
                         check_input_for_correctness,
                         &input_map,
                     )
-                    .expect("must read a first chunk");
-
-                // Construct the powers of tau
-                let mut taupowers = vec![E::Fr::zero(); size];
-                let chunk_size = size / num_cpus::get();
+                    .map_err(|e| chunk_error(e, start, end, "the challenge"))?;
 
-                // Construct exponents in parallel
-                crossbeam::scope(|scope| {
-                    for (i, taupowers) in taupowers.chunks_mut(chunk_size).enumerate() {
-                        scope.spawn(move |_| {
-                            let mut acc = key.tau.pow(&[(start + i * chunk_size) as u64]);
-
-                            for t in taupowers {
-                                *t = acc;
-                                acc.mul_assign(&key.tau);
-                            }
-                        });
-                    }
-                }).unwrap();
-
-                batch_exp::<E, _>(&mut accumulator.tau_powers_g1, &taupowers[0..], None);
-                batch_exp::<E, _>(&mut accumulator.tau_powers_g2, &taupowers[0..], None);
-                batch_exp::<E, _>(
-                    &mut accumulator.alpha_tau_powers_g1,
-                    &taupowers[0..],
-                    Some(&key.alpha),
-                );
-                batch_exp::<E, _>(
-                    &mut accumulator.beta_tau_powers_g1,
-                    &taupowers[0..],
-                    Some(&key.beta),
-                );
-                accumulator.beta_g2 = accumulator.beta_g2.mul(key.beta).into_affine();
-                assert!(
-                    !accumulator.beta_g2.is_zero(),
-                    "your contribution happened to produce a point at infinity, please re-run"
-                );
+                transformer.transform_chunk(start, &mut accumulator)?;
                 accumulator.write_chunk(start, compress_the_output, output_map)?;
                 info!("Done processing {} powers of tau", end);
-            } else {
-                panic!("Chunk does not have a min and max");
-            }
-        }
-
-        for chunk in
-            &(parameters.powers_length..parameters.powers_g1_length).chunks(parameters.batch_size)
-        {
-            if let MinMax(start, end) = chunk.minmax() {
+                Ok(())
+            },
+        )?;
+
+        iter_chunk(
+            parameters.powers_length..parameters.powers_g1_length,
+            parameters.batch_size,
+            |start, end| -> io::Result<()> {
                 let size = end - start + 1;
                 accumulator
                     .read_chunk(
@@ -1252,41 +2151,21 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
                         check_input_for_correctness,
                         &input_map,
                     )
-                    .expect("must read a first chunk");
+                    .map_err(|e| chunk_error(e, start, end, "the challenge"))?;
                 assert_eq!(
                     accumulator.tau_powers_g2.len(),
                     0,
                     "during rest of tau g1 generation tau g2 must be empty"
                 );
 
-                // Construct the powers of tau
-                let mut taupowers = vec![E::Fr::zero(); size];
-                let chunk_size = size / num_cpus::get();
-
-                // Construct exponents in parallel
-                crossbeam::scope(|scope| {
-                    for (i, taupowers) in taupowers.chunks_mut(chunk_size).enumerate() {
-                        scope.spawn(move |_| {
-                            let mut acc = key.tau.pow(&[(start + i * chunk_size) as u64]);
-
-                            for t in taupowers {
-                                *t = acc;
-                                acc.mul_assign(&key.tau);
-                            }
-                        });
-                    }
-                }).unwrap();
-
-                batch_exp::<E, _>(&mut accumulator.tau_powers_g1, &taupowers[0..], None);
-                //accumulator.beta_g2 = accumulator.beta_g2.mul(key.beta).into_affine();
-                //assert!(!accumulator.beta_g2.is_zero(), "your contribution happened to produce a point at infinity, please re-run");
+                // beta_g2 is only transformed along with the first section
+                transformer.transform_chunk(start, &mut accumulator)?;
                 accumulator.write_chunk(start, compress_the_output, output_map)?;
 
                 info!("Done processing {} powers of tau", end);
-            } else {
-                panic!("Chunk does not have a min and max");
-            }
-        }
+                Ok(())
+            },
+        )?;
 
         Ok(())
     }
@@ -1297,52 +2176,1338 @@ impl<'a, E: Engine> BatchedAccumulator<'a, E> {
         compress_the_output: UseCompression,
         parameters: &'a CeremonyParams<E>,
     ) -> io::Result<()> {
-        use itertools::MinMaxResult::MinMax;
+        // Every initial element is a generator in G1 or G2, so each section is a single encoded
+        // point repeated, which is copied instead of written point by point
+        let generators = &parameters.generators;
+        let mut g1_generator = vec![];
+        write_point(&mut g1_generator, &generators.g1, compress_the_output.g1())?;
+        let mut g2_generator = vec![];
+        write_point(&mut g2_generator, &generators.g2, compress_the_output.g2())?;
+        write_policy_header(output_map, compress_the_output, parameters)?;
+
+        for &(element_type, length) in &[
+            (ElementType::TauG1, parameters.powers_g1_length),
+            (ElementType::TauG2, parameters.powers_length),
+            (ElementType::AlphaG1, parameters.powers_length),
+            (ElementType::BetaG1, parameters.powers_length),
+            (ElementType::BetaG2, 1),
+        ] {
+            let range =
+                Self::element_range(element_type, 0, length, compress_the_output, parameters)
+                    .expect("every section has elements");
+            if range.end > output_map.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "the output is too small for an accumulator",
+                ));
+            }
+            let one = match element_type {
+                ElementType::TauG2 | ElementType::BetaG2 => &g2_generator,
+                _ => &g1_generator,
+            };
+            fill_repeated(&mut output_map[range], one);
+            info!("Done writing {} {:?} elements", length, element_type);
+        }
 
-        // Write the first Tau powers in chunks where every initial element is a G1 or G2 `one`
-        for chunk in &(0..parameters.powers_length).chunks(parameters.batch_size) {
-            if let MinMax(start, end) = chunk.minmax() {
-                let size = end - start + 1;
-                let mut accumulator = Self {
-                    tau_powers_g1: vec![E::G1Affine::one(); size],
-                    tau_powers_g2: vec![E::G2Affine::one(); size],
-                    alpha_tau_powers_g1: vec![E::G1Affine::one(); size],
-                    beta_tau_powers_g1: vec![E::G1Affine::one(); size],
-                    beta_g2: E::G2Affine::one(),
-                    hash: blank_hash(),
-                    parameters,
-                };
+        Ok(())
+    }
+}
 
-                accumulator.write_chunk(start, compress_the_output, output_map)?;
-                info!("Done processing {} powers of tau", end);
-            } else {
-                panic!("Chunk does not have a min and max");
-            }
+/// Read access to the sections of an accumulator file that decodes only the elements it's
+/// asked for, where `BatchedAccumulator::deserialize` decodes all of them. Elements are checked
+/// as they are read.
+pub struct AccumulatorView<'a, E: Engine> {
+    input: &'a [u8],
+    compression: UseCompression,
+    check_input_for_correctness: CheckForCorrectness,
+    parameters: &'a CeremonyParams<E>,
+}
+
+impl<'a, E: Engine> AccumulatorView<'a, E> {
+    pub fn new(
+        input: &'a [u8],
+        compression: UseCompression,
+        check_input_for_correctness: CheckForCorrectness,
+        parameters: &'a CeremonyParams<E>,
+    ) -> io::Result<Self> {
+        // Beta in G2 is the last element of an accumulator, a response has the key after it
+        let end = BatchedAccumulator::element_range(
+            ElementType::BetaG2,
+            0,
+            1,
+            compression,
+            parameters,
+        )
+        .expect("there is always beta in G2")
+        .end;
+        if input.len() < end {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "the file is {} bytes, but the accumulator takes {}",
+                    input.len(),
+                    end
+                ),
+            ));
         }
 
-        // Write the next `G1 length` elements
-        for chunk in
-            &(parameters.powers_length..parameters.powers_g1_length).chunks(parameters.batch_size)
+        Ok(AccumulatorView {
+            input,
+            compression,
+            check_input_for_correctness,
+            parameters,
+        })
+    }
+
+    /// Hash of the previous contribution, which the file starts with.
+    pub fn hash(&self) -> &'a [u8] {
+        &self.input[0..self.parameters.hash_size]
+    }
+
+    pub fn tau_powers_g1(
+        &self,
+        range: Range<usize>,
+    ) -> Result<Vec<E::G1Affine>, DeserializationError> {
+        self.read_g1(ElementType::TauG1, range)
+    }
+
+    pub fn tau_powers_g2(
+        &self,
+        range: Range<usize>,
+    ) -> Result<Vec<E::G2Affine>, DeserializationError> {
+        self.read_g2(ElementType::TauG2, range)
+    }
+
+    pub fn alpha_tau_powers_g1(
+        &self,
+        range: Range<usize>,
+    ) -> Result<Vec<E::G1Affine>, DeserializationError> {
+        self.read_g1(ElementType::AlphaG1, range)
+    }
+
+    pub fn beta_tau_powers_g1(
+        &self,
+        range: Range<usize>,
+    ) -> Result<Vec<E::G1Affine>, DeserializationError> {
+        self.read_g1(ElementType::BetaG1, range)
+    }
+
+    pub fn beta_g2(&self) -> Result<E::G2Affine, DeserializationError> {
+        Ok(self.read_g2(ElementType::BetaG2, 0..1)?[0])
+    }
+
+    fn read_g1(
+        &self,
+        element_type: ElementType,
+        range: Range<usize>,
+    ) -> Result<Vec<E::G1Affine>, DeserializationError> {
+        self.check_range(element_type, &range)?;
+        BatchedAccumulator::empty(self.parameters).read_g1_points(
+            range.start,
+            range.len(),
+            element_type,
+            self.compression,
+            self.check_input_for_correctness,
+            self.input,
+        )
+    }
+
+    fn read_g2(
+        &self,
+        element_type: ElementType,
+        range: Range<usize>,
+    ) -> Result<Vec<E::G2Affine>, DeserializationError> {
+        self.check_range(element_type, &range)?;
+        BatchedAccumulator::empty(self.parameters).read_g2_points(
+            range.start,
+            range.len(),
+            element_type,
+            self.compression,
+            self.check_input_for_correctness,
+            self.input,
+        )
+    }
+
+    /// The readers return fewer elements than asked for past the end of a section, so ranges
+    /// are checked up front.
+    fn check_range(&self, element_type: ElementType, range: &Range<usize>) -> io::Result<()> {
+        if range.is_empty()
+            || BatchedAccumulator::element_range(
+                element_type,
+                range.start,
+                range.len(),
+                self.compression,
+                self.parameters,
+            )
+            .is_some()
         {
-            if let MinMax(start, end) = chunk.minmax() {
-                let size = end - start + 1;
-                let mut accumulator = Self {
-                    tau_powers_g1: vec![E::G1Affine::one(); size],
-                    tau_powers_g2: vec![],
-                    alpha_tau_powers_g1: vec![],
-                    beta_tau_powers_g1: vec![],
-                    beta_g2: E::G2Affine::one(),
-                    hash: blank_hash(),
-                    parameters,
-                };
+            return Ok(());
+        }
 
-                accumulator.write_chunk(start, compress_the_output, output_map)?;
-                info!("Done processing {} powers of tau", end);
-            } else {
-                panic!("Chunk does not have a min and max");
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "elements {:?} of {:?} are out of range",
+                range, element_type
+            ),
+        ))
+    }
+}
+
+/// Applies the secrets of a contribution to the chunks of an accumulator.
+pub trait ChunkTransformer<E: Engine> {
+    /// Multiplies the elements of `accumulator`, a chunk that starts at the power `start`, by
+    /// their powers of tau, and by alpha or beta. `beta_g2` is multiplied by beta in chunks of
+    /// the first section only, which are those with powers of tau in G2.
+    fn transform_chunk(
+        &self,
+        start: usize,
+        accumulator: &mut BatchedAccumulator<E>,
+    ) -> io::Result<()>;
+}
+
+/// Leaves the points as they are, to re-encode an accumulator.
+struct Unchanged;
+
+impl<E: Engine> ChunkTransformer<E> for Unchanged {
+    fn transform_chunk(&self, _: usize, _: &mut BatchedAccumulator<E>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Exponentiate a large number of points, with an optional coefficient to be applied to the
+/// exponent.
+fn batch_exp<EE: Engine, C: CurveAffine<Engine = EE, Scalar = EE::Fr>>(
+    bases: &mut [C],
+    exp: &[C::Scalar],
+    coeff: Option<&C::Scalar>,
+) {
+    assert_eq!(bases.len(), exp.len());
+    let _span = profiling::span(Operation::BatchExp, bases.len());
+    let mut projective = vec![C::Projective::zero(); bases.len()];
+    // At least one element per thread, for batches smaller than the number of cores
+    let chunk_size = std::cmp::max(1, bases.len() / num_cpus::get());
+
+    // Perform wNAF over multiple cores, placing results into `projective`.
+    crossbeam::scope(|scope| {
+        for ((bases, exp), projective) in bases
+            .chunks_mut(chunk_size)
+            .zip(exp.chunks(chunk_size))
+            .zip(projective.chunks_mut(chunk_size))
+        {
+            scope.spawn(move |_| {
+                let mut wnaf = Wnaf::new();
+
+                for ((base, exp), projective) in
+                    bases.iter_mut().zip(exp.iter()).zip(projective.iter_mut())
+                {
+                    let mut exp = *exp;
+                    if let Some(coeff) = coeff {
+                        exp.mul_assign(coeff);
+                    }
+
+                    *projective = wnaf.base(base.into_projective(), 1).scalar(exp.into_repr());
+                    clear_scalar(&mut exp);
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    // Perform batch normalization
+    crossbeam::scope(|scope| {
+        for projective in projective.chunks_mut(chunk_size) {
+            scope.spawn(move |_| {
+                C::Projective::batch_normalization(projective);
+            });
+        }
+    })
+    .unwrap();
+
+    // Turn it all back into affine points
+    for (projective, affine) in projective.iter().zip(bases.iter_mut()) {
+        *affine = projective.into_affine();
+        assert!(
+            !affine.is_zero(),
+            "your contribution happened to produce a point at infinity, please re-run"
+        );
+    }
+}
+
+impl<E: Engine> ChunkTransformer<E> for PrivateKey<E> {
+    fn transform_chunk(
+        &self,
+        start: usize,
+        accumulator: &mut BatchedAccumulator<E>,
+    ) -> io::Result<()> {
+        // Construct the powers of tau
+        let size = accumulator.tau_powers_g1.len();
+        let mut taupowers = vec![E::Fr::zero(); size];
+        let chunk_size = std::cmp::max(1, size / num_cpus::get());
+
+        // Construct exponents in parallel
+        crossbeam::scope(|scope| {
+            for (i, taupowers) in taupowers.chunks_mut(chunk_size).enumerate() {
+                scope.spawn(move |_| {
+                    let mut acc = self.tau.pow([(start + i * chunk_size) as u64]);
+
+                    for t in taupowers {
+                        *t = acc;
+                        acc.mul_assign(&self.tau);
+                    }
+                    clear_scalar(&mut acc);
+                });
             }
+        })
+        .unwrap();
+
+        // Chunks of the second section only have powers of tau in G1
+        let length = accumulator.tau_powers_g2.len();
+        batch_exp::<E, _>(&mut accumulator.tau_powers_g1, &taupowers[0..], None);
+        batch_exp::<E, _>(&mut accumulator.tau_powers_g2, &taupowers[0..length], None);
+        batch_exp::<E, _>(
+            &mut accumulator.alpha_tau_powers_g1,
+            &taupowers[0..length],
+            Some(&self.alpha),
+        );
+        batch_exp::<E, _>(
+            &mut accumulator.beta_tau_powers_g1,
+            &taupowers[0..length],
+            Some(&self.beta),
+        );
+        taupowers.iter_mut().for_each(clear_scalar);
+        if length > 0 {
+            accumulator.beta_g2 = accumulator.beta_g2.mul(self.beta).into_affine();
+            assert!(
+                !accumulator.beta_g2.is_zero(),
+                "your contribution happened to produce a point at infinity, please re-run"
+            );
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parameters::arbitrary;
+    use bellman_ce::pairing::bn256::{Bn256, G1Affine as Bn256G1Affine};
+    use memmap::MmapMut;
+    use proptest::prelude::*;
+
+    fn generate_initial_map(parameters: &CeremonyParams<Bn256>) -> MmapMut {
+        let mut map = MmapMut::map_anon(parameters.accumulator_size).unwrap();
+        BatchedAccumulator::generate_initial(&mut map, UseCompression::No, parameters).unwrap();
+        map
+    }
+
+    #[test]
+    fn test_extract_subset() {
+        let parameters = CeremonyParams::<Bn256>::new(4, 4);
+        let reduced_parameters = CeremonyParams::<Bn256>::new(2, 4);
+
+        let input_map = generate_initial_map(&parameters).make_read_only().unwrap();
+        let expected = generate_initial_map(&reduced_parameters);
+
+        let mut output_map = MmapMut::map_anon(reduced_parameters.accumulator_size).unwrap();
+        BatchedAccumulator::extract_subset(
+            &input_map,
+            &mut output_map,
+            UseCompression::No,
+            UseCompression::No,
+            CheckForCorrectness::Yes,
+            &parameters,
+            &reduced_parameters,
+        )
+        .unwrap();
+
+        assert_eq!(&output_map[0..64], reduced_hash(4, 2).as_slice());
+        assert_eq!(&output_map[64..], &expected[64..]);
+    }
+
+    fn transformed_map(parameters: &CeremonyParams<Bn256>) -> MmapMut {
+        use crate::keypair::keypair;
+        use rand::{thread_rng, Rng};
+
+        let initial = generate_initial_map(parameters).make_read_only().unwrap();
+
+        let rng = &mut thread_rng();
+        let digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+        let (_, private_key) = keypair::<_, Bn256>(rng, &digest);
+
+        let mut output = MmapMut::map_anon(parameters.accumulator_size).unwrap();
+        BatchedAccumulator::transform(
+            &initial,
+            &mut output,
+            UseCompression::No,
+            UseCompression::No,
+            CheckForCorrectness::Yes,
+            &private_key,
+            parameters,
+        )
+        .unwrap();
+
+        output
+    }
+
+    #[test]
+    fn test_accumulator_view() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let map = transformed_map(&parameters).make_read_only().unwrap();
+        let accumulator = BatchedAccumulator::deserialize(
+            &map,
+            CheckForCorrectness::Yes,
+            UseCompression::No,
+            &parameters,
+        )
+        .unwrap();
+
+        let view = AccumulatorView::new(
+            &map,
+            UseCompression::No,
+            CheckForCorrectness::Yes,
+            &parameters,
+        )
+        .unwrap();
+        let length = parameters.powers_length;
+        assert_eq!(view.hash(), &map[0..64]);
+        assert_eq!(
+            view.tau_powers_g1(0..parameters.powers_g1_length).unwrap(),
+            accumulator.tau_powers_g1
+        );
+        assert_eq!(
+            view.tau_powers_g2(2..length).unwrap(),
+            &accumulator.tau_powers_g2[2..]
+        );
+        assert_eq!(
+            view.alpha_tau_powers_g1(1..3).unwrap(),
+            &accumulator.alpha_tau_powers_g1[1..3]
+        );
+        assert_eq!(
+            view.beta_tau_powers_g1(0..length).unwrap(),
+            accumulator.beta_tau_powers_g1
+        );
+        assert_eq!(view.beta_g2().unwrap(), accumulator.beta_g2);
+        assert!(view.tau_powers_g1(0..0).unwrap().is_empty());
+
+        // Ranges past the end of a section aren't cut short
+        assert!(view.tau_powers_g2(0..length + 1).is_err());
+        assert!(view.beta_tau_powers_g1(length..length + 1).is_err());
+
+        // Nor are files
+        assert!(AccumulatorView::new(
+            &map[..map.len() - 1],
+            UseCompression::No,
+            CheckForCorrectness::Yes,
+            &parameters
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_tiny_ceremonies() {
+        use crate::keypair::keypair;
+        use rand::thread_rng;
+
+        // Batches of one element, several chunks, a single chunk and a chunk larger than
+        // the accumulator
+        for size in 1..4 {
+            for &batch_size in &[1, 2, 3, 100] {
+                let parameters = CeremonyParams::<Bn256>::new(size, batch_size);
+                let initial = generate_initial_map(&parameters).make_read_only().unwrap();
+                let digest = crate::utils::calculate_hash(&initial);
+                let (public_key, private_key) = keypair::<_, Bn256>(&mut thread_rng(), &digest);
+
+                let mut output = MmapMut::map_anon(parameters.accumulator_size).unwrap();
+                BatchedAccumulator::transform(
+                    &initial,
+                    &mut output,
+                    UseCompression::No,
+                    UseCompression::No,
+                    CheckForCorrectness::Yes,
+                    &private_key,
+                    &parameters,
+                )
+                .unwrap();
+                let output = output.make_read_only().unwrap();
+
+                assert!(
+                    BatchedAccumulator::verify_transformation(
+                        &initial,
+                        &output,
+                        &public_key,
+                        &digest,
+                        UseCompression::No,
+                        UseCompression::No,
+                        CheckForCorrectness::Yes,
+                        CheckForCorrectness::Yes,
+                        &parameters,
+                    ),
+                    "size {}, batch size {}",
+                    size,
+                    batch_size
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_chunk_ranges() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 3);
+        assert_eq!(
+            BatchedAccumulator::chunk_ranges(&parameters),
+            vec![(0, 2), (3, 5), (6, 7), (8, 10), (11, 13), (14, 14)]
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_chunk_ranges(parameters in arbitrary::ceremony_params()) {
+            // Chunks follow each other from the first power to the last, and only the last
+            // chunk before `powers_length` and the last one of all can be shorter than a batch
+            let mut next = 0;
+            for (start, end) in BatchedAccumulator::chunk_ranges(&parameters) {
+                prop_assert_eq!(start, next);
+                prop_assert!(start <= end && end - start < parameters.batch_size);
+                if end + 1 != parameters.powers_length && end + 1 != parameters.powers_g1_length {
+                    prop_assert_eq!(end - start + 1, parameters.batch_size);
+                }
+                next = end + 1;
+            }
+            prop_assert_eq!(next, parameters.powers_g1_length);
+        }
+
+        #[test]
+        fn prop_chunk_byte_ranges(
+            parameters in arbitrary::ceremony_params(),
+            compression in arbitrary::compression(),
+        ) {
+            // Every chunk with powers of all elements reads beta in G2, but apart from it the
+            // chunks read every byte after the hash and the policy header exactly once
+            let beta_g2 = BatchedAccumulator::element_range(
+                ElementType::BetaG2,
+                0,
+                1,
+                compression,
+                &parameters,
+            )
+            .unwrap();
+            let mut ranges = vec![beta_g2.clone()];
+            for (start, end) in BatchedAccumulator::chunk_ranges(&parameters) {
+                let chunk = BatchedAccumulator::chunk_byte_ranges(
+                    start,
+                    end - start + 1,
+                    compression,
+                    &parameters,
+                );
+                ranges.extend(chunk.into_iter().filter(|range| *range != beta_g2));
+            }
+            ranges.sort_by_key(|range| range.start);
+
+            let mut next = parameters.hash_size + compression.header().len();
+            for range in ranges {
+                prop_assert_eq!(range.start, next);
+                next = range.end;
+            }
+            prop_assert_eq!(next, expected_sizes(&parameters, compression).challenge);
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(16))]
+
+        #[test]
+        fn prop_convert_compression_roundtrip(
+            parameters in arbitrary::ceremony_params(),
+            compression in arbitrary::compression(),
+        ) {
+            use crate::keypair::keypair;
+            use rand::thread_rng;
+
+            let (public_key, _) = keypair::<_, Bn256>(&mut thread_rng(), &[1; 64]);
+            let sizes = |compression| expected_sizes(&parameters, compression);
+            let mut response = MmapMut::map_anon(sizes(UseCompression::No).response).unwrap();
+            response[..parameters.accumulator_size].copy_from_slice(&transformed_map(&parameters));
+            write_hash_header(&mut response, &[5; 64]).unwrap();
+            public_key
+                .write(&mut response, UseCompression::No, &parameters)
+                .unwrap();
+            let response = response.make_read_only().unwrap();
+
+            let convert = |input: &Mmap, from, to| {
+                let mut output = MmapMut::map_anon(sizes(to).response).unwrap();
+                BatchedAccumulator::convert_compression(
+                    input,
+                    &mut output,
+                    from,
+                    to,
+                    CheckForCorrectness::Yes,
+                    &parameters,
+                )
+                .unwrap();
+                output.make_read_only().unwrap()
+            };
+            let converted = convert(&response, UseCompression::No, compression);
+            prop_assert!(
+                PublicKey::<Bn256>::read(&converted, compression, &parameters).unwrap()
+                    == public_key
+            );
+            prop_assert_eq!(
+                &convert(&converted, compression, UseCompression::No)[..],
+                &response[..]
+            );
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(8))]
+
+        #[test]
+        fn prop_contribute_and_verify(
+            parameters in arbitrary::ceremony_params(),
+            input_compression in arbitrary::compression(),
+            output_compression in arbitrary::compression(),
+            seed in any::<[u32; 8]>(),
+        ) {
+            use crate::keypair::keypair;
+            use rand::{ChaChaRng, SeedableRng};
+
+            let rng = &mut ChaChaRng::from_seed(&seed);
+            let mut initial =
+                MmapMut::map_anon(expected_sizes(&parameters, input_compression).challenge)
+                    .unwrap();
+            BatchedAccumulator::generate_initial(&mut initial, input_compression, &parameters)
+                .unwrap();
+            let initial = initial.make_read_only().unwrap();
+            let digest = crate::utils::calculate_hash(&initial);
+            let (public_key, private_key) = keypair::<_, Bn256>(rng, &digest);
+
+            let mut output =
+                MmapMut::map_anon(expected_sizes(&parameters, output_compression).challenge)
+                    .unwrap();
+            BatchedAccumulator::transform(
+                &initial,
+                &mut output,
+                input_compression,
+                output_compression,
+                CheckForCorrectness::Yes,
+                &private_key,
+                &parameters,
+            )
+            .unwrap();
+            let output = output.make_read_only().unwrap();
+
+            let verify = |public_key: &PublicKey<Bn256>| {
+                BatchedAccumulator::verify_transformation(
+                    &initial,
+                    &output,
+                    public_key,
+                    &digest,
+                    input_compression,
+                    output_compression,
+                    CheckForCorrectness::Yes,
+                    CheckForCorrectness::Yes,
+                    &parameters,
+                )
+            };
+            prop_assert!(verify(&public_key));
+            let (other_public_key, _) = keypair::<_, Bn256>(rng, &digest);
+            prop_assert!(!verify(&other_public_key));
+        }
+    }
+
+    #[test]
+    fn test_verify_chunks() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let map = transformed_map(&parameters).make_read_only().unwrap();
+        let chunks = BatchedAccumulator::chunk_ranges(&parameters).len();
+        let all_chunks = (0..chunks).collect::<Vec<_>>();
+
+        assert!(BatchedAccumulator::verify_chunks(
+            &map,
+            UseCompression::No,
+            CheckForCorrectness::Yes,
+            &all_chunks,
+            &parameters,
+        )
+        .unwrap());
+
+        // Corrupt a power of tau in G1 in the second chunk
+        let g1_size = parameters.curve.g1;
+        let position = parameters.hash_size + 5 * g1_size;
+        let replacement = Bn256G1Affine::one().into_uncompressed();
+        let mut map = map.make_mut().unwrap();
+        map[position..position + g1_size].copy_from_slice(replacement.as_ref());
+        let map = map.make_read_only().unwrap();
+
+        let verify = |chunk_indices: &[usize]| {
+            BatchedAccumulator::verify_chunks(
+                &map,
+                UseCompression::No,
+                CheckForCorrectness::Yes,
+                chunk_indices,
+                &parameters,
+            )
+            .unwrap()
+        };
+        assert!(verify(&[0, 2, 3]));
+        assert!(!verify(&[1]));
+        assert!(!verify(&all_chunks));
+        assert!(!verify(&[chunks]));
+
+        // Corrupt the last power of tau in G1 of the second chunk: the third chunk only sees it
+        // when its boundary with the previous chunk is checked
+        let position = parameters.hash_size + 7 * g1_size;
+        let mut map = transformed_map(&parameters);
+        map[position..position + g1_size].copy_from_slice(replacement.as_ref());
+        let map = map.make_read_only().unwrap();
+
+        let verify_with_boundaries = |chunk_indices: &[usize]| {
+            BatchedAccumulator::verify_chunks_with_boundaries(
+                &map,
+                UseCompression::No,
+                CheckForCorrectness::Yes,
+                chunk_indices,
+                &parameters,
+            )
+            .unwrap()
+        };
+        assert!(verify_with_boundaries(&[0, 3]));
+        assert!(!verify_with_boundaries(&[2]));
+        assert!(BatchedAccumulator::verify_chunks(
+            &map,
+            UseCompression::No,
+            CheckForCorrectness::Yes,
+            &[2],
+            &parameters,
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_generate_initial() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        for &compression in &[UseCompression::No, UseCompression::Yes] {
+            let size = expected_sizes(&parameters, compression).challenge;
+            let mut map = MmapMut::map_anon(size).unwrap();
+            BatchedAccumulator::generate_initial(&mut map, compression, &parameters).unwrap();
+            let map = map.make_read_only().unwrap();
+
+            let view =
+                AccumulatorView::new(&map, compression, CheckForCorrectness::Yes, &parameters)
+                    .unwrap();
+            let g1_one = Bn256G1Affine::one();
+            let g2_one = <Bn256 as Engine>::G2Affine::one();
+            let all = 0..parameters.powers_length;
+            assert!(view
+                .tau_powers_g1(0..parameters.powers_g1_length)
+                .unwrap()
+                .iter()
+                .all(|p| *p == g1_one));
+            assert!(view.tau_powers_g2(all.clone()).unwrap().iter().all(|p| *p == g2_one));
+            assert!(view.alpha_tau_powers_g1(all.clone()).unwrap().iter().all(|p| *p == g1_one));
+            assert!(view.beta_tau_powers_g1(all).unwrap().iter().all(|p| *p == g1_one));
+            assert_eq!(view.beta_g2().unwrap(), g2_one);
+
+            let mut small = MmapMut::map_anon(size - 1).unwrap();
+            assert!(
+                BatchedAccumulator::generate_initial(&mut small, compression, &parameters)
+                    .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_custom_generators() {
+        use crate::keypair::keypair;
+        use crate::parameters::{detect_compression, Generators};
+        use crate::utils::calculate_hash;
+        use rand::thread_rng;
+        use std::io::Cursor;
+
+        let standard = CeremonyParams::<Bn256>::new(2, 3);
+        let parameters = CeremonyParams::<Bn256>::new(2, 3)
+            .with_generators(Generators::derive(b"another protocol"));
+        let initial = generate_initial_map(&parameters).make_read_only().unwrap();
+        let view = AccumulatorView::new(
+            &initial,
+            UseCompression::No,
+            CheckForCorrectness::Yes,
+            &parameters,
+        )
+        .unwrap();
+        assert_eq!(
+            view.tau_powers_g1(0..1).unwrap()[0],
+            parameters.generators.g1
+        );
+        assert_eq!(view.beta_g2().unwrap(), parameters.generators.g2);
+        assert_eq!(
+            detect_compression(Cursor::new(&initial[..]), &parameters).unwrap(),
+            Some(UseCompression::No)
+        );
+        let standard_detected = detect_compression(Cursor::new(&initial[..]), &standard).unwrap();
+        assert_eq!(standard_detected, None);
+
+        let digest = calculate_hash(&initial);
+        let (public_key, private_key) = keypair::<_, Bn256>(&mut thread_rng(), &digest);
+        let mut output = MmapMut::map_anon(parameters.accumulator_size).unwrap();
+        BatchedAccumulator::transform(
+            &initial,
+            &mut output,
+            UseCompression::No,
+            UseCompression::No,
+            CheckForCorrectness::Yes,
+            &private_key,
+            &parameters,
+        )
+        .unwrap();
+        let output = output.make_read_only().unwrap();
+
+        // The contribution only verifies against the generators of its ceremony
+        let verify = |parameters| {
+            BatchedAccumulator::verify_transformation(
+                &initial,
+                &output,
+                &public_key,
+                &digest,
+                UseCompression::No,
+                UseCompression::No,
+                CheckForCorrectness::Yes,
+                CheckForCorrectness::Yes,
+                parameters,
+            )
+        };
+        assert!(verify(&parameters));
+        assert!(!verify(&standard));
+    }
+
+    #[test]
+    fn test_mixed_compression() {
+        use crate::keypair::keypair;
+        use crate::parameters::MIXED_HEADER;
+        use crate::utils::calculate_hash;
+        use rand::thread_rng;
+
+        let parameters = CeremonyParams::<Bn256>::new(3, 3);
+        let initial = generate_initial_map(&parameters).make_read_only().unwrap();
+        let digest = calculate_hash(&initial);
+        let (public_key, private_key) = keypair::<_, Bn256>(&mut thread_rng(), &digest);
+        let sizes = expected_sizes(&parameters, UseCompression::Mixed);
+        let mut response = MmapMut::map_anon(sizes.response).unwrap();
+        BatchedAccumulator::transform(
+            &initial,
+            &mut response,
+            UseCompression::No,
+            UseCompression::Mixed,
+            CheckForCorrectness::Yes,
+            &private_key,
+            &parameters,
+        )
+        .unwrap();
+        public_key
+            .write(&mut response, UseCompression::Mixed, &parameters)
+            .unwrap();
+        let response = response.make_read_only().unwrap();
+
+        // The first powers are the generators, G1 uncompressed and G2 compressed
+        let (g1_size, g2_size) = (parameters.curve.g1, parameters.curve.g2_compressed);
+        let g1_start = parameters.hash_size + MIXED_HEADER.len();
+        let g2_start = g1_start + parameters.powers_g1_length * g1_size;
+        assert_eq!(&response[64..g1_start], MIXED_HEADER);
+        assert_eq!(
+            &response[g1_start..g1_start + g1_size],
+            parameters.generators.g1.into_uncompressed().as_ref()
+        );
+        assert_eq!(
+            &response[g2_start..g2_start + g2_size],
+            parameters.generators.g2.into_compressed().as_ref()
+        );
+
+        assert!(BatchedAccumulator::verify_transformation(
+            &initial,
+            &response,
+            &public_key,
+            &digest,
+            UseCompression::No,
+            UseCompression::Mixed,
+            CheckForCorrectness::Yes,
+            CheckForCorrectness::Yes,
+            &parameters,
+        ));
+    }
+
+    #[test]
+    fn test_audit_boundaries() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let audit = |map: &Mmap| {
+            BatchedAccumulator::audit_boundaries(
+                map,
+                UseCompression::No,
+                CheckForCorrectness::Yes,
+                &parameters,
+            )
+            .unwrap()
+        };
+
+        let map = transformed_map(&parameters).make_read_only().unwrap();
+        let boundaries = audit(&map);
+        assert_eq!(
+            boundaries.iter().map(|b| b.index).collect::<Vec<_>>(),
+            vec![3, 7, 11]
+        );
+        assert!(boundaries.iter().all(|b| b.valid));
+
+        // The TauG1 powers of the third chunk from another contribution verify on their own,
+        // but break both links of the chunk
+        let other = transformed_map(&parameters);
+        let range = BatchedAccumulator::element_range(
+            ElementType::TauG1,
+            8,
+            4,
+            UseCompression::No,
+            &parameters,
+        )
+        .unwrap();
+        let mut map = map.make_mut().unwrap();
+        map[range.clone()].copy_from_slice(&other[range]);
+        let valid = audit(&map.make_read_only().unwrap()).iter().map(|b| b.valid).collect::<Vec<_>>();
+        assert_eq!(valid, vec![true, false, false]);
+    }
+
+    #[test]
+    fn test_verify_element() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let map = transformed_map(&parameters).make_read_only().unwrap();
+        let elements = [
+            (ElementType::TauG1, parameters.powers_g1_length),
+            (ElementType::TauG2, parameters.powers_length),
+            (ElementType::AlphaG1, parameters.powers_length),
+            (ElementType::BetaG1, parameters.powers_length),
+            (ElementType::BetaG2, 1),
+        ];
+        let verify = |map: &Mmap, element_type, index| {
+            BatchedAccumulator::verify_element(
+                map,
+                UseCompression::No,
+                CheckForCorrectness::Yes,
+                element_type,
+                index,
+                &parameters,
+            )
+            .unwrap()
+        };
+
+        for &(element_type, length) in elements.iter() {
+            for index in 0..length {
+                assert!(verify(&map, element_type, index));
+            }
+            assert!(!verify(&map, element_type, length));
+        }
+
+        // Corrupt a power of tau in G1: it and its neighbours fail, the rest still pass
+        let g1_size = parameters.curve.g1;
+        let position = parameters.hash_size + 5 * g1_size;
+        let replacement = Bn256G1Affine::one().into_uncompressed();
+        let mut map = map.make_mut().unwrap();
+        map[position..position + g1_size].copy_from_slice(replacement.as_ref());
+        let map = map.make_read_only().unwrap();
+
+        for index in 0..parameters.powers_g1_length {
+            let expected = !(4..=6).contains(&index);
+            assert_eq!(verify(&map, ElementType::TauG1, index), expected);
+        }
+        assert!(verify(&map, ElementType::AlphaG1, 5));
+    }
+
+    #[test]
+    fn test_read_chunk_correctness_levels() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let mut map = transformed_map(&parameters);
+
+        // Put the point at infinity in place of a power of tau in G1
+        let g1_size = parameters.curve.g1;
+        let position = parameters.hash_size + 5 * g1_size;
+        let zero = Bn256G1Affine::zero().into_uncompressed();
+        map[position..position + g1_size].copy_from_slice(zero.as_ref());
+        let map = map.make_read_only().unwrap();
+
+        let read = |checked: CheckForCorrectness| {
+            let mut accumulator = BatchedAccumulator::empty(&parameters);
+            accumulator.read_chunk(4, 4, UseCompression::No, checked, &map)
+        };
+        assert!(read(CheckForCorrectness::Yes).is_err());
+        assert!(read(CheckForCorrectness::OnlyNonZero).is_err());
+        assert!(read(CheckForCorrectness::OnlyInGroup).is_ok());
+        assert!(read(CheckForCorrectness::No).is_ok());
+    }
+
+    #[test]
+    fn test_element_range() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let range = |element_type, index, count| {
+            BatchedAccumulator::element_range(
+                element_type,
+                index,
+                count,
+                UseCompression::No,
+                &parameters,
+            )
+        };
+        let g1_size = parameters.curve.g1;
+        let g2_size = parameters.curve.g2;
+
+        let start = parameters.hash_size + 2 * g1_size;
+        assert_eq!(range(ElementType::TauG1, 2, 3), Some(start..start + 3 * g1_size));
+        let start = parameters.hash_size + parameters.powers_g1_length * g1_size;
+        assert_eq!(range(ElementType::TauG2, 0, 1), Some(start..start + g2_size));
+        let end = parameters.accumulator_size;
+        assert_eq!(range(ElementType::BetaG2, 0, 1), Some(end - g2_size..end));
+
+        assert_eq!(range(ElementType::BetaG2, 0, 2), None);
+        assert_eq!(range(ElementType::TauG1, 0, 0), None);
+        assert_eq!(range(ElementType::AlphaG1, parameters.powers_length, 1), None);
+    }
+
+    #[test]
+    fn test_truncated_maps() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let response = transformed_map(&parameters);
+
+        // Cut the file in the middle of the powers of tau in G2
+        let g1_size = parameters.curve.g1;
+        let truncated_size = parameters.hash_size + parameters.powers_g1_length * g1_size + 10;
+        let mut truncated = MmapMut::map_anon(truncated_size).unwrap();
+        truncated.copy_from_slice(&response[..truncated_size]);
+        let truncated = truncated.make_read_only().unwrap();
+
+        let error = BatchedAccumulator::deserialize(
+            &truncated,
+            CheckForCorrectness::Yes,
+            UseCompression::No,
+            &parameters,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(error.to_string().contains("TauG2 element 0"));
+
+        let mut accumulator = BatchedAccumulator::deserialize(
+            &response.make_read_only().unwrap(),
+            CheckForCorrectness::Yes,
+            UseCompression::No,
+            &parameters,
+        )
+        .unwrap();
+        let mut short_output = MmapMut::map_anon(truncated_size).unwrap();
+        let error = accumulator
+            .serialize(&mut short_output, UseCompression::No, &parameters)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_decompress_with_copy() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 2);
+        let uncompressed = transformed_map(&parameters).make_read_only().unwrap();
+
+        let compressed_size = parameters.contribution_size - parameters.public_key_size;
+        let mut compressed = MmapMut::map_anon(compressed_size).unwrap();
+        BatchedAccumulator::deserialize(
+            &uncompressed,
+            CheckForCorrectness::Yes,
+            UseCompression::No,
+            &parameters,
+        )
+        .unwrap()
+        .serialize(&mut compressed, UseCompression::Yes, &parameters)
+        .unwrap();
+        let compressed = compressed.make_read_only().unwrap();
+
+        let mut output = MmapMut::map_anon(parameters.accumulator_size).unwrap();
+        let mut copy = MmapMut::map_anon(compressed_size).unwrap();
+        BatchedAccumulator::decompress_with_copy(
+            &compressed,
+            &mut output,
+            Some(&mut copy),
+            CheckForCorrectness::Yes,
+            &parameters,
+        )
+        .unwrap();
+
+        assert_eq!(&output[64..], &uncompressed[64..]);
+        assert_eq!(&copy[64..], &compressed[64..]);
+    }
+
+    #[test]
+    fn test_convert_compression() {
+        use crate::keypair::keypair;
+        use rand::thread_rng;
+
+        let parameters = CeremonyParams::<Bn256>::new(3, 2);
+        let (public_key, _) = keypair::<_, Bn256>(&mut thread_rng(), &[1; 64]);
+        let sizes = |compression| expected_sizes(&parameters, compression);
+        let mut response = MmapMut::map_anon(sizes(UseCompression::No).response).unwrap();
+        response[..parameters.accumulator_size].copy_from_slice(&transformed_map(&parameters));
+        write_hash_header(&mut response, &[5; 64]).unwrap();
+        public_key
+            .write(&mut response, UseCompression::No, &parameters)
+            .unwrap();
+        let response = response.make_read_only().unwrap();
+
+        let convert = |input: &Mmap, from, to, size| {
+            let mut output = MmapMut::map_anon(size).unwrap();
+            BatchedAccumulator::convert_compression(
+                input,
+                &mut output,
+                from,
+                to,
+                CheckForCorrectness::Yes,
+                &parameters,
+            )
+            .map(|_| output.make_read_only().unwrap())
+        };
+        let compressed = convert(
+            &response,
+            UseCompression::No,
+            UseCompression::Yes,
+            sizes(UseCompression::Yes).response,
+        )
+        .unwrap();
+        assert_eq!(&compressed[..64], &[5; 64][..]);
+        assert!(
+            PublicKey::<Bn256>::read(&compressed, UseCompression::Yes, &parameters).unwrap()
+                == public_key
+        );
+        let uncompressed = convert(
+            &compressed,
+            UseCompression::Yes,
+            UseCompression::No,
+            sizes(UseCompression::No).response,
+        )
+        .unwrap();
+        assert_eq!(&uncompressed[..], &response[..]);
+        let mixed = convert(
+            &compressed,
+            UseCompression::Yes,
+            UseCompression::Mixed,
+            sizes(UseCompression::Mixed).response,
+        )
+        .unwrap();
+        assert!(
+            PublicKey::<Bn256>::read(&mixed, UseCompression::Mixed, &parameters).unwrap()
+                == public_key
+        );
+        let uncompressed = convert(
+            &mixed,
+            UseCompression::Mixed,
+            UseCompression::No,
+            sizes(UseCompression::No).response,
+        )
+        .unwrap();
+        assert_eq!(&uncompressed[..], &response[..]);
+
+        // A response only converts to a response
+        assert!(convert(
+            &response,
+            UseCompression::No,
+            UseCompression::Yes,
+            sizes(UseCompression::Yes).challenge,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_verify_transformation_and_decompress() {
+        use crate::keypair::keypair;
+        use rand::thread_rng;
+
+        for &batch_size in &[1, 2, 3, 100] {
+            let parameters = CeremonyParams::<Bn256>::new(3, batch_size);
+            let initial = generate_initial_map(&parameters).make_read_only().unwrap();
+            let digest = crate::utils::calculate_hash(&initial);
+            let (public_key, private_key) = keypair::<_, Bn256>(&mut thread_rng(), &digest);
+
+            let compressed_size = parameters.contribution_size - parameters.public_key_size;
+            let mut response = MmapMut::map_anon(parameters.contribution_size).unwrap();
+            BatchedAccumulator::transform(
+                &initial,
+                &mut response,
+                UseCompression::No,
+                UseCompression::Yes,
+                CheckForCorrectness::Yes,
+                &private_key,
+                &parameters,
+            )
+            .unwrap();
+            let response = response.make_read_only().unwrap();
+
+            let mut expected = MmapMut::map_anon(parameters.accumulator_size).unwrap();
+            BatchedAccumulator::decompress(
+                &response,
+                &mut expected,
+                CheckForCorrectness::Yes,
+                &parameters,
+            )
+            .unwrap();
+
+            let verify = |response: &Mmap, output: &mut MmapMut, copy: &mut MmapMut| {
+                BatchedAccumulator::verify_transformation_and_decompress(
+                    &initial,
+                    response,
+                    &public_key,
+                    &digest,
+                    UseCompression::No,
+                    UseCompression::Yes,
+                    CheckForCorrectness::Yes,
+                    CheckForCorrectness::Yes,
+                    &ReadOptions::default(),
+                    output,
+                    Some(copy),
+                    &parameters,
+                )
+            };
+            let mut output = MmapMut::map_anon(parameters.accumulator_size).unwrap();
+            let mut copy = MmapMut::map_anon(compressed_size).unwrap();
+            assert!(verify(&response, &mut output, &mut copy));
+            assert_eq!(&output[64..], &expected[64..]);
+            assert_eq!(&copy[64..], &response[64..compressed_size]);
+
+            // A response with a power of tau from another contribution is rejected
+            let mut tampered = MmapMut::map_anon(parameters.contribution_size).unwrap();
+            tampered.copy_from_slice(&response);
+            let g1_size = parameters.curve.g1_compressed;
+            let position = parameters.hash_size + 2 * g1_size;
+            let other = transformed_map(&parameters);
+            let other_point = BatchedAccumulator::deserialize(
+                &other.make_read_only().unwrap(),
+                CheckForCorrectness::Yes,
+                UseCompression::No,
+                &parameters,
+            )
+            .unwrap()
+            .tau_powers_g1[2];
+            tampered[position..position + g1_size]
+                .copy_from_slice(other_point.into_compressed().as_ref());
+            let tampered = tampered.make_read_only().unwrap();
+            assert!(!verify(&tampered, &mut output, &mut copy));
+        }
+    }
+
+    #[test]
+    fn test_verify_transformation_resuming() {
+        use crate::keypair::keypair;
+        use rand::thread_rng;
+
+        let parameters = CeremonyParams::<Bn256>::new(3, 2);
+        let initial = generate_initial_map(&parameters).make_read_only().unwrap();
+        let digest = crate::utils::calculate_hash(&initial);
+        let (public_key, private_key) = keypair::<_, Bn256>(&mut thread_rng(), &digest);
+        let mut response = MmapMut::map_anon(parameters.contribution_size).unwrap();
+        BatchedAccumulator::transform(
+            &initial,
+            &mut response,
+            UseCompression::No,
+            UseCompression::Yes,
+            CheckForCorrectness::Yes,
+            &private_key,
+            &parameters,
+        )
+        .unwrap();
+
+        // Power 2 from another contribution, which only the chunks before it see
+        let mut tampered = MmapMut::map_anon(parameters.contribution_size).unwrap();
+        tampered.copy_from_slice(&response);
+        let g1_size = parameters.curve.g1_compressed;
+        let position = parameters.hash_size + 2 * g1_size;
+        let other_point = BatchedAccumulator::deserialize(
+            &transformed_map(&parameters).make_read_only().unwrap(),
+            CheckForCorrectness::Yes,
+            UseCompression::No,
+            &parameters,
+        )
+        .unwrap()
+        .tau_powers_g1[2];
+        tampered[position..position + g1_size]
+            .copy_from_slice(other_point.into_compressed().as_ref());
+        let response = response.make_read_only().unwrap();
+        let tampered = tampered.make_read_only().unwrap();
+
+        let path = std::env::temp_dir().join(format!(
+            "powersoftau_verification_cursor_{}",
+            std::process::id()
+        ));
+        let verify = |response: &Mmap, next_power: usize, next_extra_power: usize| {
+            let mut cursor = VerificationCursor::new(&digest, &[0; 64], &parameters);
+            cursor.next_power = next_power;
+            cursor.next_extra_power = next_extra_power;
+            let valid = BatchedAccumulator::verify_transformation_resuming(
+                &initial,
+                response,
+                &public_key,
+                &digest,
+                UseCompression::No,
+                UseCompression::Yes,
+                CheckForCorrectness::Yes,
+                CheckForCorrectness::Yes,
+                &ReadOptions::default(),
+                &mut cursor,
+                &path,
+                &parameters,
+            );
+            (valid, cursor)
+        };
+
+        let (valid, cursor) = verify(&response, 0, parameters.powers_length);
+        assert!(valid);
+        assert_eq!(cursor.next_power, parameters.powers_length);
+        assert_eq!(cursor.next_extra_power, parameters.powers_g1_length);
+        let saved = VerificationCursor::resume(&path, &digest, &[0; 64], &parameters).unwrap();
+        assert_eq!(saved, cursor);
+        assert!(saved.has_progress(&parameters));
+        // The cursor is for another response
+        let other = VerificationCursor::resume(&path, &digest, &[1; 64], &parameters).unwrap();
+        assert!(!other.has_progress(&parameters));
+
+        // Everything verified already, only the key, the first powers and the link
+        // between the sections are checked
+        assert!(
+            verify(
+                &response,
+                parameters.powers_length,
+                parameters.powers_g1_length
+            )
+            .0
+        );
+
+        let (valid, cursor) = verify(&tampered, 0, parameters.powers_length);
+        assert!(!valid);
+        assert_eq!(cursor.next_power, 0);
+        let (valid, cursor) = verify(&tampered, 2, parameters.powers_length);
+        assert!(!valid);
+        assert_eq!(cursor.next_power, 2);
+        // The chunks before the cursor aren't verified again
+        assert!(verify(&tampered, 4, parameters.powers_length).0);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_extract_subset_rejects_larger_output() {
+        let parameters = CeremonyParams::<Bn256>::new(2, 4);
+        let larger_parameters = CeremonyParams::<Bn256>::new(3, 4);
+
+        let input_map = generate_initial_map(&parameters).make_read_only().unwrap();
+        let mut output_map = MmapMut::map_anon(larger_parameters.accumulator_size).unwrap();
+        assert!(BatchedAccumulator::extract_subset(
+            &input_map,
+            &mut output_map,
+            UseCompression::No,
+            UseCompression::No,
+            CheckForCorrectness::Yes,
+            &parameters,
+            &larger_parameters,
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,391 @@
+/// The random beacon that makes the last contribution. Its randomness is derived from a public
+/// value, such as a block hash that could not be known in advance, by iterating SHA-256 over it
+/// 2<sup>n</sup> times, so that anyone can recompute the key of the beacon contribution and check
+/// that it was not chosen by the coordinator.
+use bellman_ce::pairing::Engine;
+use byteorder::{BigEndian, ReadBytesExt};
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use rand::chacha::ChaChaRng;
+use rand::SeedableRng;
+
+use std::process::Command;
+
+use super::errors::{self, Error, ErrorCategory};
+use super::keypair::{keypair, PublicKey};
+
+/// Bounds of the exponent of the number of hash iterations.
+pub const MIN_ITERATIONS_EXP: usize = 10;
+pub const MAX_ITERATIONS_EXP: usize = 63;
+
+/// A beacon value and the exponent of the number of hash iterations over it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Beacon {
+    pub hash: Vec<u8>,
+    pub num_iterations_exp: usize,
+}
+
+impl Beacon {
+    pub fn new(hash: Vec<u8>, num_iterations_exp: usize) -> Result<Self, Error> {
+        if !(MIN_ITERATIONS_EXP..=MAX_ITERATIONS_EXP).contains(&num_iterations_exp) {
+            return Err(Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                format!(
+                    "the number of iterations exponent should be in [{}, {}] range",
+                    MIN_ITERATIONS_EXP, MAX_ITERATIONS_EXP
+                ),
+            ));
+        }
+        if hash.len() < 32 {
+            return Err(Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                "the beacon hash must have at least 32 bytes",
+            ));
+        }
+
+        Ok(Beacon {
+            hash,
+            num_iterations_exp,
+        })
+    }
+
+    /// Parses the beacon value from hex.
+    pub fn from_hex(hash: &str, num_iterations_exp: usize) -> Result<Self, Error> {
+        let hash = hex::decode(hash).map_err(|e| {
+            Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                format!("invalid beacon hash: {}", e),
+            )
+        })?;
+
+        Self::new(hash, num_iterations_exp)
+    }
+
+    /// Iterates the hash, and calls `checkpoint` with 1024 of the intermediate states and their
+    /// index, so that the computation can be checked in parallel.
+    pub fn final_hash<F: FnMut(u64, &[u8])>(&self, mut checkpoint: F) -> Vec<u8> {
+        let mut cur_hash = self.hash.clone();
+        let n = self.num_iterations_exp;
+        for i in 0..(1u64 << n) {
+            if i % (1u64 << (n - 10)) == 0 {
+                checkpoint(i, &cur_hash);
+            }
+
+            // The result overwrites the first 32 bytes, longer beacon values keep their tail
+            let mut h = Sha256::new();
+            h.input(&cur_hash);
+            h.result(&mut cur_hash);
+        }
+
+        cur_hash
+    }
+
+    /// The RNG of the beacon contribution, seeded from the first 32 bytes of the final hash.
+    pub fn rng(final_hash: &[u8]) -> ChaChaRng {
+        let mut digest = final_hash;
+        let mut seed = [0u32; 8];
+        for s in &mut seed {
+            *s = digest
+                .read_u32::<BigEndian>()
+                .expect("digest is large enough for this to work");
+        }
+
+        ChaChaRng::from_seed(&seed)
+    }
+
+    /// The public key that the beacon contributes on top of the challenge with the hash
+    /// `challenge_hash`.
+    pub fn public_key<E: Engine>(final_hash: &[u8], challenge_hash: &[u8]) -> PublicKey<E> {
+        keypair(&mut Self::rng(final_hash), challenge_hash).0
+    }
+}
+
+/// A beacon and where its value can be looked up, as given to a command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BeaconOpts {
+    pub beacon: Beacon,
+    /// Checked before the beacon is used, see `BeaconSource`
+    pub source: Option<BeaconSource>,
+}
+
+/// The block a beacon value is the hash of, and a command that prints the hash of a block.
+/// The command is split at whitespace into the program and its arguments, it is not run by a
+/// shell, and the height is passed as its last argument. It must print the hash in hex, such as
+/// `bitcoin-cli getblockhash`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BeaconSource {
+    pub height: u64,
+    pub command: String,
+}
+
+impl BeaconSource {
+    /// Runs the command and returns the value it printed for the height.
+    pub fn lookup(&self) -> Result<Vec<u8>, Error> {
+        let mut words = self.command.split_whitespace();
+        let program = words.next().ok_or_else(|| {
+            Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                "the beacon source command is empty",
+            )
+        })?;
+        let output = Command::new(program)
+            .args(words)
+            .arg(self.height.to_string())
+            .output()
+            .map_err(|e| {
+                Error::from(e).context(format!("unable to run the beacon source {}", program))
+            })?;
+        if !output.status.success() {
+            return Err(Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                format!(
+                    "the beacon source {} failed with {}: {}",
+                    program,
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+
+        hex::decode(String::from_utf8_lossy(&output.stdout).trim()).map_err(|e| {
+            Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                format!("the beacon source {} didn't print a hash: {}", program, e),
+            )
+        })
+    }
+}
+
+impl BeaconOpts {
+    /// Checks the beacon value against its source, if it has one.
+    pub fn check(&self) -> Result<(), Error> {
+        match &self.source {
+            Some(source) => self.check_with(source.height, |_| source.lookup()),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that the beacon value is what `lookup` returns for `height`. Library users with
+    /// another way of getting the block hash, such as a client library, check with it here.
+    pub fn check_with<F: FnOnce(u64) -> Result<Vec<u8>, Error>>(
+        &self,
+        height: u64,
+        lookup: F,
+    ) -> Result<(), Error> {
+        let expected = lookup(height)?;
+        if expected != self.beacon.hash {
+            return Err(Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                format!(
+                    "the beacon hash {} isn't the hash {} at height {}",
+                    hex::encode(&self.beacon.hash),
+                    hex::encode(&expected),
+                    height
+                ),
+            )
+            .with_hint("check that the beacon hash and --beacon-height are the announced ones"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Removes `flag <value>` from the arguments and returns the value.
+fn take_value(args: &mut Vec<String>, flag: &str) -> Result<Option<String>, Error> {
+    let position = match args.iter().position(|arg| arg == flag) {
+        Some(position) => position,
+        None => return Ok(None),
+    };
+    if position + 1 >= args.len() {
+        return Err(Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            format!("{} requires a value", flag),
+        ));
+    }
+    let value = args[position + 1].clone();
+    args.drain(position..position + 2);
+
+    Ok(Some(value))
+}
+
+/// Removes `--beacon-height <n>` and `--beacon-source <command>` from the arguments and
+/// returns the source they describe. Both are required if one is given.
+pub fn take_beacon_source(args: &mut Vec<String>) -> Result<Option<BeaconSource>, Error> {
+    match (
+        take_value(args, "--beacon-height")?,
+        take_value(args, "--beacon-source")?,
+    ) {
+        (Some(height), Some(command)) => {
+            let height = height.parse().map_err(|_| {
+                Error::new(
+                    errors::INVALID_ARGUMENT,
+                    ErrorCategory::InvalidInput,
+                    "could not parse the beacon height",
+                )
+            })?;
+            Ok(Some(BeaconSource { height, command }))
+        }
+        (None, None) => Ok(None),
+        _ => Err(Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            "--beacon-height and --beacon-source must be given together",
+        )),
+    }
+}
+
+/// Removes `--beacon-hash <hex>` and `--beacon-iterations <exp>`, and the source of the beacon,
+/// from the arguments and returns the beacon they describe. The hash and the iterations are
+/// required if one of them or a source is given.
+pub fn take_beacon(args: &mut Vec<String>) -> Result<Option<BeaconOpts>, Error> {
+    let source = take_beacon_source(args)?;
+    match (
+        take_value(args, "--beacon-hash")?,
+        take_value(args, "--beacon-iterations")?,
+    ) {
+        (Some(hash), Some(num_iterations_exp)) => {
+            let num_iterations_exp = num_iterations_exp.parse().map_err(|_| {
+                Error::new(
+                    errors::INVALID_ARGUMENT,
+                    ErrorCategory::InvalidInput,
+                    "could not parse the beacon iterations exponent",
+                )
+            })?;
+            Ok(Some(BeaconOpts {
+                beacon: Beacon::from_hex(&hash, num_iterations_exp)?,
+                source,
+            }))
+        }
+        (None, None) if source.is_none() => Ok(None),
+        (None, None) => Err(Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            "--beacon-height and --beacon-source require --beacon-hash",
+        )),
+        _ => Err(Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            "--beacon-hash and --beacon-iterations must be given together",
+        )),
+    }
+}
+
+/// Fails if the arguments of `command`, which doesn't use a beacon, have beacon options, so
+/// that a contribution isn't mistaken for the beacon.
+pub fn reject_beacon(args: &[String], command: &str) -> Result<(), Error> {
+    match args.iter().find(|arg| arg.starts_with("--beacon-")) {
+        Some(flag) => Err(Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            format!("{} doesn't take {}", command, flag),
+        )
+        .with_hint("the beacon contributes with beacon_constrained")),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bellman_ce::pairing::bn256::Bn256;
+
+    #[test]
+    fn test_beacon_public_key() {
+        let beacon = Beacon::new(vec![1; 32], 10).unwrap();
+        let mut checkpoints = vec![];
+        let final_hash = beacon.final_hash(|i, _| checkpoints.push(i));
+        assert_eq!(checkpoints, (0..1024).collect::<Vec<_>>());
+        assert_eq!(final_hash.len(), 32);
+
+        let challenge_hash = [3; 64];
+        let public_key = Beacon::public_key::<Bn256>(&final_hash, &challenge_hash);
+        assert!(public_key == Beacon::public_key::<Bn256>(&final_hash, &challenge_hash));
+        // The key depends on the beacon and on the challenge
+        let other = Beacon::new(vec![2; 32], 10).unwrap().final_hash(|_, _| {});
+        assert!(public_key != Beacon::public_key::<Bn256>(&other, &challenge_hash));
+        assert!(public_key != Beacon::public_key::<Bn256>(&final_hash, &[4; 64]));
+
+        assert!(Beacon::new(vec![1; 32], 9).is_err());
+        assert!(Beacon::new(vec![1; 31], 10).is_err());
+        assert!(Beacon::from_hex("0g", 10).is_err());
+    }
+
+    #[test]
+    fn test_take_beacon() {
+        let hash = "aa".repeat(32);
+        let strings = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let mut args = strings(&["cmd", "--beacon-iterations", "12", "--beacon-hash", &hash]);
+        assert_eq!(
+            take_beacon(&mut args).unwrap(),
+            Some(BeaconOpts {
+                beacon: Beacon::new(vec![0xaa; 32], 12).unwrap(),
+                source: None,
+            })
+        );
+        assert_eq!(args, strings(&["cmd"]));
+        assert_eq!(take_beacon(&mut args).unwrap(), None);
+        assert!(take_beacon(&mut strings(&["--beacon-hash", &hash])).is_err());
+        let mut iterations = strings(&["--beacon-hash", &hash, "--beacon-iterations", "x"]);
+        assert!(take_beacon(&mut iterations).is_err());
+
+        let source = ["--beacon-height", "5", "--beacon-source", "echo"];
+        let mut args = strings(&["--beacon-hash", &hash, "--beacon-iterations", "12"]);
+        args.extend(strings(&source));
+        assert_eq!(
+            take_beacon(&mut args).unwrap().unwrap().source,
+            Some(BeaconSource {
+                height: 5,
+                command: "echo".to_string()
+            })
+        );
+        assert!(args.is_empty());
+        // A source without a beacon, or a height without a source, is a mistake
+        assert!(take_beacon(&mut strings(&source)).is_err());
+        assert!(take_beacon_source(&mut strings(&["--beacon-height", "5"])).is_err());
+        let mut height = strings(&["--beacon-height", "x", "--beacon-source", "echo"]);
+        assert!(take_beacon_source(&mut height).is_err());
+
+        assert!(reject_beacon(&strings(&["cmd", "challenge"]), "cmd").is_ok());
+        assert!(reject_beacon(&strings(&["cmd", "--beacon-hash", &hash]), "cmd").is_err());
+    }
+
+    #[test]
+    fn test_beacon_source() {
+        let hash = "aa".repeat(32);
+        let opts = |height| BeaconOpts {
+            beacon: Beacon::from_hex(&hash, 10).unwrap(),
+            source: Some(BeaconSource {
+                height,
+                command: format!("echo {}", hash),
+            }),
+        };
+        // echo prints the hash followed by the height
+        assert!(opts(7).check().is_err());
+        assert!(opts(7)
+            .check_with(7, |height| {
+                assert_eq!(height, 7);
+                Ok(vec![0xaa; 32])
+            })
+            .is_ok());
+        assert!(opts(7).check_with(7, |_| Ok(vec![0xbb; 32])).is_err());
+
+        let printed = BeaconSource {
+            height: 7,
+            command: "printf 0%s".to_string(),
+        };
+        assert_eq!(printed.lookup().unwrap(), vec![0x07]);
+        let missing = BeaconSource {
+            height: 7,
+            command: "/nonexistent/beacon-source".to_string(),
+        };
+        assert!(missing.lookup().is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use powersoftau::{
+    batched_accumulator::BatchedAccumulator,
+    beacon::reject_beacon,
+    errors::{self, Error, ErrorCategory},
+    keypair::{entropy_rng, guarded_keypair, take_round, KeyDomain},
+    parameters::{expected_sizes, CeremonyParams, CheckForCorrectness, UseCompression},
+    signer::{take_signer, ExternalSigner},
+    utils::{calculate_hash, write_hash_header},
+    workset::{workset_size, WorksetHeader, HEADER_SIZE},
+};
+
+use bellman_ce::pairing::bn256::Bn256;
+use memmap::*;
+use std::fs::OpenOptions;
+
+const INPUT_IS_COMPRESSED: UseCompression = UseCompression::Yes;
+const COMPRESS_THE_OUTPUT: UseCompression = UseCompression::Yes;
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    reject_beacon(&args, "apply_contribution").unwrap_or_else(|e| e.exit());
+    let signer_command = take_signer(&mut args).unwrap_or_else(|e| e.exit());
+    let round = take_round(&mut args).unwrap_or_else(|e| e.exit());
+    if args.len() != 3 {
+        println!("Usage: \n<workset_file> <response_file> [--signer <command>] [--round <n>]");
+        std::process::exit(exitcode::USAGE);
+    }
+    let workset_filename = &args[1];
+    let response_filename = &args[2];
+
+    let mut reader = OpenOptions::new()
+        .read(true)
+        .open(workset_filename)
+        .expect("unable open workset file");
+    let header = WorksetHeader::read(&mut reader).unwrap_or_else(|e| {
+        Error::from(e)
+            .context("unable to read the workset header")
+            .exit()
+    });
+    let parameters = CeremonyParams::<Bn256>::validated(header.circuit_power, header.batch_size)
+        .unwrap_or_else(|e| e.exit());
+
+    println!(
+        "Will contribute to accumulator for 2^{} powers of tau from a workset",
+        parameters.size
+    );
+
+    let length = reader
+        .metadata()
+        .expect("unable to get filesystem metadata for workset file")
+        .len();
+    if length != workset_size(&parameters) as u64 {
+        Error::new(
+            errors::INVALID_FILE_SIZE,
+            ErrorCategory::InvalidInput,
+            format!(
+                "The size of workset file should be {}, but it's {}, so something isn't right.",
+                workset_size(&parameters),
+                length
+            ),
+        )
+        .with_hint("check that the workset was fully transferred")
+        .exit();
+    }
+
+    let readable_map = unsafe {
+        MmapOptions::new()
+            .offset(HEADER_SIZE as u64)
+            .map(&reader)
+            .expect("unable to create a memory map for input")
+    };
+
+    let writer = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(response_filename)
+        .expect("unable to create response file");
+    writer
+        .set_len(expected_sizes(&parameters, COMPRESS_THE_OUTPUT).response as u64)
+        .expect("must make output file large enough");
+    let mut writable_map = unsafe {
+        MmapOptions::new()
+            .map_mut(&writer)
+            .expect("unable to create a memory map for output")
+    };
+
+    // The response starts with the hash of the uncompressed challenge, not of the workset
+    println!("`challenge` file the workset was prepared from has a hash:");
+    for line in header.challenge_hash.chunks(16) {
+        print!("\t");
+        for section in line.chunks(4) {
+            for b in section {
+                print!("{:02x}", b);
+            }
+            print!(" ");
+        }
+        println!();
+    }
+    write_hash_header(&mut writable_map, &header.challenge_hash)
+        .expect("unable to write a challenge hash to mmap");
+
+    // Keys of a round are bound to the ceremony, see `KeyDomain`
+    let key_digest = match round {
+        Some(round) => KeyDomain::bn256_groth16(parameters.size, round)
+            .digest(&header.challenge_hash)
+            .to_vec(),
+        None => header.challenge_hash.to_vec(),
+    };
+
+    // The points were checked when the workset was prepared
+    let pubkey = match &signer_command {
+        Some(signer_command) => {
+            let signer = ExternalSigner::spawn(signer_command).unwrap_or_else(|e| e.exit());
+            let pubkey = signer.public_key(&key_digest).unwrap_or_else(|e| e.exit());
+
+            println!("Computing and writing your contribution with the signer, this could take a while...");
+            BatchedAccumulator::transform_with(
+                &readable_map,
+                &mut writable_map,
+                INPUT_IS_COMPRESSED,
+                COMPRESS_THE_OUTPUT,
+                CheckForCorrectness::No,
+                &signer,
+                &parameters,
+            )
+            .map_err(Error::from)
+            .unwrap_or_else(|e| e.context("unable to transform with the signer").exit());
+
+            pubkey
+        }
+        None => {
+            let mut rng = entropy_rng();
+            let (pubkey, privkey) = guarded_keypair(&mut rng, &key_digest);
+
+            println!("Computing and writing your contribution, this could take a while...");
+            BatchedAccumulator::transform(
+                &readable_map,
+                &mut writable_map,
+                INPUT_IS_COMPRESSED,
+                COMPRESS_THE_OUTPUT,
+                CheckForCorrectness::No,
+                &privkey,
+                &parameters,
+            )
+            .expect("must transform with the key");
+
+            pubkey
+        }
+    };
+
+    pubkey
+        .write(&mut writable_map, COMPRESS_THE_OUTPUT, &parameters)
+        .expect("unable to write public key");
+    writable_map.flush().expect("must flush a memory map");
+
+    let output_readonly = writable_map
+        .make_read_only()
+        .expect("must make a map readonly");
+    let contribution_hash = calculate_hash(&output_readonly);
+
+    print!(
+        "Done!\n\n\
+              Your contribution has been written to response file\n\n\
+              The BLAKE2b hash of response file is:\n"
+    );
+    for line in contribution_hash.as_slice().chunks(16) {
+        print!("\t");
+        for section in line.chunks(4) {
+            for b in section {
+                print!("{:02x}", b);
+            }
+            print!(" ");
+        }
+        println!();
+    }
+
+    println!("Thank you for your participation, much appreciated! :)");
+}
@@ -1,43 +1,59 @@
 extern crate hex;
 use powersoftau::{
     batched_accumulator::BatchedAccumulator,
-    keypair::keypair,
-    parameters::{CeremonyParams, CheckForCorrectness, UseCompression},
-    utils::calculate_hash,
+    beacon::{take_beacon_source, Beacon, BeaconOpts},
+    errors::Error,
+    keypair::{keypair, take_round, KeyDomain},
+    metrics::take_metrics,
+    parameters::{
+        check_compression, expected_sizes, take_check_input, take_generators, CeremonyParams,
+        CheckForCorrectness, UseCompression,
+    },
+    profiling,
+    utils::{calculate_hash, write_hash_header},
 };
 
 use bellman_ce::pairing::bn256::Bn256;
 use memmap::MmapOptions;
 use std::fs::OpenOptions;
 
-use std::io::Write;
 extern crate hex_literal;
 
 const INPUT_IS_COMPRESSED: UseCompression = UseCompression::No;
 const COMPRESS_THE_OUTPUT: UseCompression = UseCompression::Yes;
-const CHECK_INPUT_CORRECTNESS: CheckForCorrectness = CheckForCorrectness::No;
 
-#[allow(clippy::modulo_one)]
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    let check_input =
+        take_check_input(&mut args, CheckForCorrectness::No).unwrap_or_else(|e| e.exit());
+    let round = take_round(&mut args).unwrap_or_else(|e| e.exit());
+    let generators = take_generators(&mut args).unwrap_or_else(|e| e.exit());
+    let source = take_beacon_source(&mut args).unwrap_or_else(|e| e.exit());
+    let metrics = take_metrics(&mut args).unwrap_or_else(|e| e.exit());
     if args.len() != 7 {
-        println!("Usage: \n<challenge_file> <response_file> <circuit_power> <batch_size> <beacon_hash> <num_iterations_exp>");
+        println!("Usage: \n<challenge_file> <response_file> <circuit_power> <batch_size> <beacon_hash> <num_iterations_exp> [--check-input <full|in-group|non-zero|none>] [--round <n>] [--generators <file>] [--beacon-height <n> --beacon-source <command>] [--metrics-textfile <file>] [--metrics-push <http[s]://host/path>]");
         std::process::exit(exitcode::USAGE);
     }
     let challenge_filename = &args[1];
     let response_filename = &args[2];
     let circuit_power = args[3].parse().expect("could not parse circuit power");
     let batch_size = args[4].parse().expect("could not parse batch size");
-    let beacon_hash = &args[5];
-    let num_iterations_exp = &args[6].parse::<usize>().unwrap();
+    let num_iterations_exp = args[6]
+        .parse()
+        .expect("could not parse the number of iterations exponent");
+    let beacon = BeaconOpts {
+        beacon: Beacon::from_hex(&args[5], num_iterations_exp).unwrap_or_else(|e| e.exit()),
+        source,
+    };
+    beacon.check().unwrap_or_else(|e| e.exit());
+    let beacon = beacon.beacon;
 
-    if *num_iterations_exp < 10 || *num_iterations_exp > 63 {
-        println!("in_num_iterations_exp should be in [10, 63] range");
-        std::process::exit(exitcode::DATAERR);
+    let mut parameters =
+        CeremonyParams::<Bn256>::validated(circuit_power, batch_size).unwrap_or_else(|e| e.exit());
+    if let Some(generators) = generators {
+        parameters.generators = generators;
     }
 
-    let parameters = CeremonyParams::<Bn256>::new(circuit_power, batch_size);
-
     println!(
         "Will contribute a random beacon to accumulator for 2^{} powers of tau",
         parameters.size,
@@ -49,51 +65,22 @@ fn main() {
 
     // Create an RNG based on the outcome of the random beacon
     let mut rng = {
-        use byteorder::{BigEndian, ReadBytesExt};
-        use crypto::digest::Digest;
-        use crypto::sha2::Sha256;
-        use rand::chacha::ChaChaRng;
-        use rand::SeedableRng;
-
-        let mut cur_hash = hex::decode(beacon_hash).unwrap();
-
-        // Performs 2^n hash iterations over it
-        let n: usize = *num_iterations_exp;
-
-        for i in 0..(1u64 << n) {
-            // Print 1024 of the interstitial states
-            // so that verification can be
-            // parallelized
-
-            if i % (1u64 << (n - 10)) == 0 {
-                print!("{}: ", i);
-                for b in cur_hash.iter() {
-                    print!("{:02x}", b);
-                }
-                println!();
+        // Print 1024 of the interstitial states so that verification can be parallelized
+        let final_hash = beacon.final_hash(|i, cur_hash| {
+            print!("{}: ", i);
+            for b in cur_hash.iter() {
+                print!("{:02x}", b);
             }
-
-            let mut h = Sha256::new();
-            h.input(&cur_hash);
-            h.result(&mut cur_hash);
-        }
+            println!();
+        });
 
         print!("Final result of beacon: ");
-        for b in cur_hash.iter() {
+        for b in final_hash.iter() {
             print!("{:02x}", b);
         }
         println!();
 
-        let mut digest = &cur_hash[..];
-
-        let mut seed = [0u32; 8];
-        for s in &mut seed {
-            *s = digest
-                .read_u32::<BigEndian>()
-                .expect("digest is large enough for this to work");
-        }
-
-        ChaChaRng::from_seed(&seed)
+        Beacon::rng(&final_hash)
     };
 
     println!("Done creating a beacon RNG");
@@ -103,15 +90,17 @@ fn main() {
         .read(true)
         .open(challenge_filename)
         .expect("unable open challenge file in this directory");
+    check_compression(&reader, INPUT_IS_COMPRESSED, &parameters).unwrap_or_else(|e| {
+        Error::from(e)
+            .context("unable to use the challenge file")
+            .exit()
+    });
 
     {
         let metadata = reader
             .metadata()
             .expect("unable to get filesystem metadata for challenge file");
-        let expected_challenge_length = match INPUT_IS_COMPRESSED {
-            UseCompression::Yes => parameters.contribution_size,
-            UseCompression::No => parameters.accumulator_size,
-        };
+        let expected_challenge_length = expected_sizes(&parameters, INPUT_IS_COMPRESSED).challenge;
 
         if metadata.len() != (expected_challenge_length as u64) {
             panic!(
@@ -136,10 +125,7 @@ fn main() {
         .open(response_filename)
         .expect("unable to create response file in this directory");
 
-    let required_output_length = match COMPRESS_THE_OUTPUT {
-        UseCompression::Yes => parameters.contribution_size,
-        UseCompression::No => parameters.accumulator_size + parameters.public_key_size,
-    };
+    let required_output_length = expected_sizes(&parameters, COMPRESS_THE_OUTPUT).response;
 
     writer
         .set_len(required_output_length as u64)
@@ -168,8 +154,7 @@ fn main() {
             println!();
         }
 
-        (&mut writable_map[0..])
-            .write_all(current_accumulator_hash.as_slice())
+        write_hash_header(&mut writable_map, current_accumulator_hash.as_slice())
             .expect("unable to write a challenge hash to mmap");
 
         writable_map
@@ -178,7 +163,14 @@ fn main() {
     }
 
     // Construct our keypair using the RNG we created above
-    let (pubkey, privkey) = keypair(&mut rng, current_accumulator_hash.as_ref());
+    // Keys of a round are bound to the ceremony, see `KeyDomain`
+    let key_digest = match round {
+        Some(round) => KeyDomain::bn256_groth16(parameters.size, round)
+            .digest(current_accumulator_hash.as_slice())
+            .to_vec(),
+        None => current_accumulator_hash.to_vec(),
+    };
+    let (pubkey, privkey) = keypair(&mut rng, &key_digest);
 
     // Perform the transformation
     println!("Computing and writing your contribution, this could take a while...");
@@ -189,7 +181,7 @@ fn main() {
         &mut writable_map,
         INPUT_IS_COMPRESSED,
         COMPRESS_THE_OUTPUT,
-        CHECK_INPUT_CORRECTNESS,
+        check_input,
         &privkey,
         &parameters,
     )
@@ -225,4 +217,7 @@ fn main() {
     }
 
     println!("Thank you for your participation, much appreciated! :)");
+
+    profiling::dump_report("beacon_constrained").expect("unable to write the profiling report");
+    metrics.export("beacon_constrained", true);
 }
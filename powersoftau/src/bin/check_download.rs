@@ -0,0 +1,50 @@
+use powersoftau::tree_hash::TreeHash;
+
+use memmap::*;
+use std::fs::OpenOptions;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 3 {
+        println!("Usage: \n<partially_downloaded_file> <manifest_file>");
+        std::process::exit(exitcode::USAGE);
+    }
+    let filename = &args[1];
+    let manifest_filename = &args[2];
+
+    let mut manifest_reader = OpenOptions::new()
+        .read(true)
+        .open(manifest_filename)
+        .expect("unable open manifest file in this directory");
+    let tree = TreeHash::read(&mut manifest_reader).expect("unable to read manifest");
+
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(filename)
+        .expect("unable open file in this directory");
+    let readable_map = unsafe {
+        MmapOptions::new()
+            .map(&reader)
+            .expect("unable to create a memory map for input")
+    };
+
+    let report = tree.check_download(&readable_map);
+    println!(
+        "{} of {} leaves are valid",
+        report.valid_leaves.len(),
+        tree.leaves.len()
+    );
+    if report.is_complete() {
+        println!("The file is complete.");
+        return;
+    }
+
+    println!(
+        "{} bytes have to be downloaded again:",
+        report.missing_bytes()
+    );
+    for range in report.missing.iter() {
+        println!("\t{}-{}", range.start, range.end - 1);
+    }
+    std::process::exit(exitcode::DATAERR);
+}
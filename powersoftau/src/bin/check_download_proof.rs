@@ -0,0 +1,59 @@
+use powersoftau::download_proof::{DownloadChallenge, DownloadProof};
+use powersoftau::errors::{self, Error, ErrorCategory};
+
+use memmap::*;
+use std::fs::OpenOptions;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 4 {
+        println!("Usage: \n<challenge_file> <download_challenge_file> <download_proof_file>");
+        std::process::exit(exitcode::USAGE);
+    }
+    let challenge_filename = &args[1];
+    let download_challenge_filename = &args[2];
+    let download_proof_filename = &args[3];
+
+    let mut download_challenge_reader = OpenOptions::new()
+        .read(true)
+        .open(download_challenge_filename)
+        .expect("unable open download challenge file in this directory");
+    let download_challenge = DownloadChallenge::read(&mut download_challenge_reader)
+        .expect("unable to read download challenge");
+
+    let mut download_proof_reader = OpenOptions::new()
+        .read(true)
+        .open(download_proof_filename)
+        .expect("unable open download proof file in this directory");
+    let proof = DownloadProof::read(&mut download_proof_reader)
+        .unwrap_or_else(|e| Error::from(e).context("invalid download proof").exit());
+
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(challenge_filename)
+        .expect("unable open challenge file in this directory");
+    let challenge_readable_map = unsafe {
+        MmapOptions::new()
+            .map(&reader)
+            .expect("unable to create a memory map for input")
+    };
+
+    let valid = download_challenge
+        .check(&challenge_readable_map, &proof)
+        .unwrap_or_else(|e| {
+            Error::from(e)
+                .context("the download challenge is not for this challenge file")
+                .exit()
+        });
+    if !valid {
+        Error::new(
+            errors::HASH_MISMATCH,
+            ErrorCategory::Verification,
+            "The download proof is invalid.",
+        )
+        .with_hint("the participant has not downloaded the challenge, or answered another download challenge")
+        .exit();
+    }
+
+    println!("The download proof is valid.");
+}
@@ -1,97 +1,134 @@
 use powersoftau::{
-    batched_accumulator::BatchedAccumulator,
-    keypair::keypair,
-    parameters::{CeremonyParams, CheckForCorrectness, UseCompression},
-    utils::calculate_hash,
+    backend::{take_report_backend, BackendReport},
+    beacon::reject_beacon,
+    container::write_accumulator_container,
+    errors::Error,
+    hash_file::{check_expected_hash, take_expected_hash},
+    hooks::{take_hooks, HookContext},
+    in_place::{recover, InPlace, Recovered},
+    keypair::{entropy_rng, guarded_keypair_with_mask, take_round, KeyDomain, PublicKey},
+    metrics::take_metrics,
+    parameters::{
+        check_compression, expected_sizes, take_check_input, take_generators, take_trapdoors,
+        CeremonyParams, CheckForCorrectness, UseCompression,
+    },
+    profiling,
+    receipt::Receipt,
+    round::Round,
+    signer::{take_signer, ExternalSigner},
+    utils::{is_fresh_hash_header, read_hash_header},
 };
 
 use bellman_ce::pairing::bn256::Bn256;
+use generic_array::GenericArray;
 use memmap::*;
-use std::fs::OpenOptions;
+use std::fs::{File, OpenOptions};
+use typenum::consts::U64;
 
-use std::io::{Read, Write};
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
 
 const INPUT_IS_COMPRESSED: UseCompression = UseCompression::No;
 const COMPRESS_THE_OUTPUT: UseCompression = UseCompression::Yes;
-const CHECK_INPUT_CORRECTNESS: CheckForCorrectness = CheckForCorrectness::No;
 
-fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() != 5 {
-        println!("Usage: \n<challenge_file> <response_file> <circuit_power> <batch_size>");
+/// Removes `--container <file>` from the arguments and returns the file
+fn take_container(args: &mut Vec<String>) -> Option<String> {
+    let position = args.iter().position(|arg| arg == "--container")?;
+    if position + 1 >= args.len() {
+        println!("--container requires a file");
         std::process::exit(exitcode::USAGE);
     }
-    let challenge_filename = &args[1];
-    let response_filename = &args[2];
-    let circuit_power = args[3].parse().expect("could not parse circuit power");
-    let batch_size = args[4].parse().expect("could not parse batch size");
+    let container_filename = args[position + 1].clone();
+    args.drain(position..position + 2);
 
-    let parameters = CeremonyParams::<Bn256>::new(circuit_power, batch_size);
+    Some(container_filename)
+}
 
-    println!(
-        "Will contribute to accumulator for 2^{} powers of tau",
-        parameters.size
-    );
-    println!(
-        "In total will generate up to {} powers",
-        parameters.powers_g1_length
-    );
+/// Removes `--in-place` from the arguments and returns whether it was there
+fn take_in_place(args: &mut Vec<String>) -> bool {
+    match args.iter().position(|arg| arg == "--in-place") {
+        Some(position) => {
+            args.remove(position);
+            true
+        }
+        None => false,
+    }
+}
 
-    // Create an RNG based on a mixture of system randomness and user provided randomness
-    let mut rng = {
-        use blake2::{Blake2b, Digest};
-        use byteorder::{BigEndian, ReadBytesExt};
-        use rand::chacha::ChaChaRng;
-        use rand::{OsRng, Rng, SeedableRng};
-
-        let h = {
-            let mut system_rng = OsRng::new().unwrap();
-            let mut h = Blake2b::default();
-
-            // Gather 1024 bytes of entropy from the system
-            for _ in 0..1024 {
-                let r: u8 = system_rng.gen();
-                h.input(&[r]);
-            }
+/// What the receipt, the container and the hooks are made from once a contribution is written
+struct Contributed {
+    challenge_hash: GenericArray<u8, U64>,
+    pubkey: PublicKey<Bn256>,
+    response_hash: GenericArray<u8, U64>,
+    response: Mmap,
+}
 
-            // Ask the user to provide some information for additional entropy
-            let mut user_input = String::new();
-            println!("Type some random text and press [ENTER] to provide additional entropy...");
-            std::io::stdin()
-                .read_line(&mut user_input)
-                .expect("expected to read some random text from the user");
-
-            // Hash it all up to make a seed
-            h.input(&user_input.as_bytes());
-            h.result()
-        };
-
-        let mut digest = &h[..];
-
-        // Interpret the first 32 bytes of the digest as 8 32-bit words
-        let mut seed = [0u32; 8];
-        for s in &mut seed {
-            *s = digest
-                .read_u32::<BigEndian>()
-                .expect("digest is large enough for this to work");
+fn print_hash(hash: &[u8]) {
+    for line in hash.chunks(16) {
+        print!("\t");
+        for section in line.chunks(4) {
+            for b in section {
+                print!("{:02x}", b);
+            }
+            print!(" ");
         }
+        println!();
+    }
+}
 
-        ChaChaRng::from_seed(&seed)
-    };
+/// Prints the hash of the challenge and the hash it claims to be based on, and checks the
+/// former against `--expected-hash`.
+fn print_challenge_hashes(
+    challenge_filename: &str,
+    current_accumulator_hash: &[u8],
+    challenge_hash: &[u8],
+    expected_hash_filename: &Option<String>,
+) {
+    println!("`challenge` file contains decompressed points and has a hash:");
+    print_hash(current_accumulator_hash);
+
+    if let Some(expected_hash_filename) = expected_hash_filename {
+        check_expected_hash(
+            expected_hash_filename,
+            challenge_filename,
+            current_accumulator_hash,
+        )
+        .unwrap_or_else(|e| e.exit());
+        println!("The hash matches the one in {}", expected_hash_filename);
+    }
+
+    println!("`challenge` file claims (!!! Must not be blindly trusted) that it was based on the original contribution with a hash:");
+    print_hash(challenge_hash);
+    if is_fresh_hash_header(challenge_hash) {
+        println!("That is the hash of a fresh challenge, you are the first to contribute.");
+    }
+}
 
+/// Computes the response into a new file, with the challenge memory mapped.
+fn contribute(
+    challenge_filename: &str,
+    response_filename: &str,
+    parameters: &CeremonyParams<Bn256>,
+    check_input: CheckForCorrectness,
+    round: Option<u64>,
+    signer_command: &Option<String>,
+    expected_hash_filename: &Option<String>,
+) -> Contributed {
     // Try to load challenge file from disk.
     let reader = OpenOptions::new()
         .read(true)
         .open(challenge_filename)
         .expect("unable open challenge file");
+    check_compression(&reader, INPUT_IS_COMPRESSED, parameters).unwrap_or_else(|e| {
+        Error::from(e)
+            .context("unable to use the challenge file")
+            .exit()
+    });
     {
         let metadata = reader
             .metadata()
             .expect("unable to get filesystem metadata for challenge file");
-        let expected_challenge_length = match INPUT_IS_COMPRESSED {
-            UseCompression::Yes => parameters.contribution_size,
-            UseCompression::No => parameters.accumulator_size,
-        };
+        let expected_challenge_length = expected_sizes(parameters, INPUT_IS_COMPRESSED).challenge;
 
         if metadata.len() != (expected_challenge_length as u64) {
             panic!(
@@ -116,10 +153,7 @@ fn main() {
         .open(response_filename)
         .expect("unable to create response file");
 
-    let required_output_length = match COMPRESS_THE_OUTPUT {
-        UseCompression::Yes => parameters.contribution_size,
-        UseCompression::No => parameters.accumulator_size + parameters.public_key_size,
-    };
+    let required_output_length = expected_sizes(parameters, COMPRESS_THE_OUTPUT).response;
 
     writer
         .set_len(required_output_length as u64)
@@ -137,84 +171,273 @@ fn main() {
         UseCompression::No == INPUT_IS_COMPRESSED,
         "Hashing the compressed file in not yet defined"
     );
-    let current_accumulator_hash = calculate_hash(&readable_map);
+    let mut contribution =
+        Round::new(&readable_map, parameters).with_checks(check_input, CheckForCorrectness::Yes);
+    let current_accumulator_hash = *contribution.challenge_hash();
+
+    let challenge_hash = read_hash_header(&readable_map).unwrap_or_else(|e| {
+        Error::from(e)
+            .context("unable to read the hash header of the challenge")
+            .exit()
+    });
+    print_challenge_hashes(
+        challenge_filename,
+        current_accumulator_hash.as_slice(),
+        &challenge_hash,
+        expected_hash_filename,
+    );
 
-    {
-        println!("`challenge` file contains decompressed points and has a hash:");
-        for line in current_accumulator_hash.as_slice().chunks(16) {
-            print!("\t");
-            for section in line.chunks(4) {
-                for b in section {
-                    print!("{:02x}", b);
-                }
-                print!(" ");
-            }
-            println!();
+    // Keys of a round are bound to the ceremony, see `KeyDomain`
+    if let Some(round) = round {
+        contribution = contribution.with_domain(&KeyDomain::bn256_groth16(parameters.size, round));
+    }
+    let key_digest = contribution.key_digest().to_vec();
+
+    let pubkey = match signer_command {
+        Some(signer_command) => {
+            // The signer samples the key, so it does not ask for entropy here
+            let signer = ExternalSigner::spawn(signer_command).unwrap_or_else(|e| e.exit());
+            let pubkey = signer.public_key(&key_digest).unwrap_or_else(|e| e.exit());
+
+            println!("Computing and writing your contribution with the signer, this could take a while...");
+            contribution
+                .compute(&mut writable_map, &signer)
+                .map_err(Error::from)
+                .unwrap_or_else(|e| e.context("unable to transform with the signer").exit());
+
+            pubkey
+        }
+        None => {
+            // Construct our keypair with system and user randomness
+            let mut rng = entropy_rng();
+            let (pubkey, privkey) =
+                guarded_keypair_with_mask(&mut rng, &key_digest, parameters.trapdoors);
+
+            // Perform the transformation
+            println!("Computing and writing your contribution, this could take a while...");
+
+            // this computes a transformation and writes it
+            contribution
+                .compute(&mut writable_map, &*privkey)
+                .expect("must transform with the key");
+
+            pubkey
         }
+    };
 
-        (&mut writable_map[0..])
-            .write_all(current_accumulator_hash.as_slice())
-            .expect("unable to write a challenge hash to mmap");
+    println!("Finishing writing your contribution to response file...");
+
+    // Write the public key, and get the hash of the contribution, so the user can compare later
+    let contribution_hash = contribution
+        .finalize(&mut writable_map, &pubkey)
+        .expect("unable to write public key");
+    let response = writable_map
+        .make_read_only()
+        .expect("must make a map readonly");
 
-        writable_map
-            .flush()
-            .expect("unable to write hash to response file");
+    Contributed {
+        challenge_hash: current_accumulator_hash,
+        pubkey,
+        response_hash: contribution_hash,
+        response,
     }
+}
 
+/// Turns the challenge into the response within the same file, for contributors who can't
+/// hold both on disk, or finishes such a contribution if it was interrupted.
+fn contribute_in_place(
+    challenge_filename: &str,
+    response_filename: &str,
+    parameters: &CeremonyParams<Bn256>,
+    check_input: CheckForCorrectness,
+    round: Option<u64>,
+    signer_command: &Option<String>,
+    expected_hash_filename: &Option<String>,
+) -> Contributed {
+    let map_response = || {
+        let reader = File::open(response_filename).expect("unable to open the response file");
+        unsafe {
+            MmapOptions::new()
+                .map(&reader)
+                .expect("unable to create a memory map for the response")
+        }
+    };
+
+    match recover(
+        Path::new(challenge_filename),
+        Path::new(response_filename),
+        parameters,
+    )
+    .unwrap_or_else(|e| e.exit())
     {
-        let mut challenge_hash = [0; 64];
-        let mut memory_slice = readable_map
-            .get(0..64)
-            .expect("must read point data from file");
-        memory_slice
-            .read_exact(&mut challenge_hash)
-            .expect("couldn't read hash of challenge file from response file");
-
-        println!("`challenge` file claims (!!! Must not be blindly trusted) that it was based on the original contribution with a hash:");
-        for line in challenge_hash.chunks(16) {
-            print!("\t");
-            for section in line.chunks(4) {
-                for b in section {
-                    print!("{:02x}", b);
-                }
-                print!(" ");
-            }
-            println!();
+        Some(Recovered::Response {
+            challenge_hash,
+            public_key,
+            response_hash,
+        }) => {
+            println!(
+                "An interrupted contribution was found and has been finished from its journal."
+            );
+            return Contributed {
+                challenge_hash,
+                pubkey: public_key,
+                response_hash,
+                response: map_response(),
+            };
+        }
+        Some(Recovered::Challenge) => {
+            println!("An interrupted contribution was found before it changed the challenge, starting over.")
         }
+        None => {}
     }
 
-    // Construct our keypair using the RNG we created above
-    let (pubkey, privkey) = keypair(&mut rng, current_accumulator_hash.as_ref());
+    let mut contribution = InPlace::open(
+        Path::new(challenge_filename),
+        Path::new(response_filename),
+        parameters,
+    )
+    .unwrap_or_else(|e| e.exit())
+    .with_check(check_input);
+    let current_accumulator_hash = *contribution.challenge_hash();
+
+    let mut challenge_hash = [0; 64];
+    File::open(challenge_filename)
+        .and_then(|mut reader| reader.read_exact(&mut challenge_hash))
+        .unwrap_or_else(|e| {
+            Error::from(e)
+                .context("unable to read the hash header of the challenge")
+                .exit()
+        });
+    print_challenge_hashes(
+        challenge_filename,
+        current_accumulator_hash.as_slice(),
+        &challenge_hash,
+        expected_hash_filename,
+    );
 
-    // Perform the transformation
-    println!("Computing and writing your contribution, this could take a while...");
+    // Keys of a round are bound to the ceremony, see `KeyDomain`
+    if let Some(round) = round {
+        contribution = contribution.with_domain(&KeyDomain::bn256_groth16(parameters.size, round));
+    }
+    let key_digest = contribution.key_digest().to_vec();
 
-    // this computes a transformation and writes it
-    BatchedAccumulator::transform(
-        &readable_map,
-        &mut writable_map,
-        INPUT_IS_COMPRESSED,
-        COMPRESS_THE_OUTPUT,
-        CHECK_INPUT_CORRECTNESS,
-        &privkey,
-        &parameters,
-    )
-    .expect("must transform with the key");
+    println!("The challenge will be turned into the response, so it won't be there anymore.");
+    let (pubkey, response_hash) = match signer_command {
+        Some(signer_command) => {
+            let signer = ExternalSigner::spawn(signer_command).unwrap_or_else(|e| e.exit());
+            let pubkey = signer.public_key(&key_digest).unwrap_or_else(|e| e.exit());
 
-    println!("Finishing writing your contribution to response file...");
+            println!("Computing and writing your contribution with the signer, this could take a while...");
+            let response_hash = contribution
+                .contribute(&signer, &pubkey)
+                .unwrap_or_else(|e| e.context("unable to transform with the signer").exit());
 
-    // Write the public key
-    pubkey
-        .write(&mut writable_map, COMPRESS_THE_OUTPUT, &parameters)
-        .expect("unable to write public key");
+            (pubkey, response_hash)
+        }
+        None => {
+            let mut rng = entropy_rng();
+            let (pubkey, privkey) =
+                guarded_keypair_with_mask(&mut rng, &key_digest, parameters.trapdoors);
 
-    writable_map.flush().expect("must flush a memory map");
+            println!("Computing and writing your contribution, this could take a while...");
+            let response_hash = contribution
+                .contribute(&*privkey, &pubkey)
+                .unwrap_or_else(|e| e.exit());
 
-    // Get the hash of the contribution, so the user can compare later
-    let output_readonly = writable_map
-        .make_read_only()
-        .expect("must make a map readonly");
-    let contribution_hash = calculate_hash(&output_readonly);
+            (pubkey, response_hash)
+        }
+    };
+
+    Contributed {
+        challenge_hash: current_accumulator_hash,
+        pubkey,
+        response_hash,
+        response: map_response(),
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    reject_beacon(&args, "compute_constrained").unwrap_or_else(|e| e.exit());
+    let container_filename = take_container(&mut args);
+    let in_place = take_in_place(&mut args);
+    let report_backend = take_report_backend(&mut args);
+    let signer_command = take_signer(&mut args).unwrap_or_else(|e| e.exit());
+    let round = take_round(&mut args).unwrap_or_else(|e| e.exit());
+    let hooks = take_hooks(&mut args).unwrap_or_else(|e| e.exit());
+    let expected_hash_filename = take_expected_hash(&mut args).unwrap_or_else(|e| e.exit());
+    let check_input =
+        take_check_input(&mut args, CheckForCorrectness::No).unwrap_or_else(|e| e.exit());
+    let generators = take_generators(&mut args).unwrap_or_else(|e| e.exit());
+    let trapdoors = take_trapdoors(&mut args).unwrap_or_else(|e| e.exit());
+    let metrics = take_metrics(&mut args).unwrap_or_else(|e| e.exit());
+    if args.len() != 5 && args.len() != 6 {
+        println!("Usage: \n<challenge_file> <response_file> <circuit_power> <batch_size> [<receipt_file>] [--container <response_container>] [--hash-file <file>] [--attestation <file>] [--webhook <http[s]://host/path>] [--hash-format <hex|b2sum|binary|json>] [--expected-hash <challenge_hash_file>] [--check-input <full|in-group|non-zero|none>] [--report-backend] [--signer <command>] [--round <n>] [--generators <file>] [--trapdoors <tau,alpha,beta>] [--in-place] [--metrics-textfile <file>] [--metrics-push <http[s]://host/path>]");
+        std::process::exit(exitcode::USAGE);
+    }
+    if trapdoors.is_some() && signer_command.is_some() {
+        println!("--trapdoors can't be used with --signer, which samples every trapdoor");
+        std::process::exit(exitcode::USAGE);
+    }
+    let challenge_filename = &args[1];
+    let response_filename = &args[2];
+    let circuit_power = args[3].parse().expect("could not parse circuit power");
+    let batch_size = args[4].parse().expect("could not parse batch size");
+
+    let mut parameters =
+        CeremonyParams::<Bn256>::validated(circuit_power, batch_size).unwrap_or_else(|e| e.exit());
+    if let Some(generators) = generators {
+        parameters.generators = generators;
+    }
+    if let Some(trapdoors) = trapdoors {
+        parameters.trapdoors = trapdoors;
+    }
+
+    if report_backend {
+        println!("{}", BackendReport::detect());
+    }
+
+    println!(
+        "Will contribute to accumulator for 2^{} powers of tau",
+        parameters.size
+    );
+    println!(
+        "In total will generate up to {} powers",
+        parameters.powers_g1_length
+    );
+    if !parameters.trapdoors.is_full() {
+        println!(
+            "Only {} will be updated, the other trapdoors are left as they are",
+            parameters.trapdoors
+        );
+    }
+
+    let Contributed {
+        challenge_hash: current_accumulator_hash,
+        pubkey,
+        response_hash: contribution_hash,
+        response: output_readonly,
+    } = if in_place {
+        contribute_in_place(
+            challenge_filename,
+            response_filename,
+            &parameters,
+            check_input,
+            round,
+            &signer_command,
+            &expected_hash_filename,
+        )
+    } else {
+        contribute(
+            challenge_filename,
+            response_filename,
+            &parameters,
+            check_input,
+            round,
+            &signer_command,
+            &expected_hash_filename,
+        )
+    };
 
     print!(
         "Done!\n\n\
@@ -222,16 +445,57 @@ fn main() {
               The BLAKE2b hash of response file is:\n"
     );
 
-    for line in contribution_hash.as_slice().chunks(16) {
-        print!("\t");
-        for section in line.chunks(4) {
-            for b in section {
-                print!("{:02x}", b);
-            }
-            print!(" ");
+    print_hash(contribution_hash.as_slice());
+
+    if let Some(receipt_filename) = args.get(5) {
+        let mut receipt = Receipt::new(&output_readonly, contribution_hash.as_slice(), pubkey)
+            .with_trapdoors(parameters.trapdoors);
+        if let Some(round) = round {
+            receipt = receipt.with_domain(KeyDomain::bn256_groth16(parameters.size, round));
         }
-        println!();
+        let mut writer = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(receipt_filename)
+            .expect("unable to create receipt file in this directory");
+        receipt
+            .serialize(&mut writer)
+            .expect("unable to write receipt");
+        println!("A receipt of your contribution has been written to receipt file");
+    }
+
+    if let Some(container_filename) = container_filename {
+        let mut writer = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&container_filename)
+            .map(BufWriter::new)
+            .expect("unable to create response container file in this directory");
+        write_accumulator_container(
+            &output_readonly,
+            &parameters,
+            COMPRESS_THE_OUTPUT,
+            true,
+            &mut writer,
+        )
+        .and_then(|_| writer.flush())
+        .expect("unable to write response container");
+        println!(
+            "Your response has also been written to {} as a container",
+            container_filename
+        );
     }
 
+    let context = HookContext::new(
+        "compute_constrained",
+        response_filename,
+        contribution_hash.as_slice(),
+        current_accumulator_hash.as_slice(),
+    );
+    hooks.run(&context).unwrap_or_else(|e| e.exit());
+
     println!("Thank you for your participation, much appreciated! :)");
+
+    profiling::dump_report("compute_constrained").expect("unable to write the profiling report");
+    metrics.export("compute_constrained", true);
 }
@@ -0,0 +1,121 @@
+use powersoftau::{
+    batched_accumulator::BatchedAccumulator,
+    errors::{self, Error, ErrorCategory},
+    parameters::{
+        detect_compression, expected_sizes, take_check_input, take_generators, CeremonyParams,
+        CheckForCorrectness, UseCompression,
+    },
+};
+
+use bellman_ce::pairing::bn256::Bn256;
+use memmap::*;
+use std::fs::OpenOptions;
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut args: Vec<String> = std::env::args().collect();
+    let check_input = take_check_input(&mut args, CheckForCorrectness::Yes)?;
+    let generators = take_generators(&mut args)?;
+    if args.len() != 6 {
+        println!("Usage: \n<input_file> <output_file> <circuit_power> <batch_size> <compressed|uncompressed|mixed> [--check-input <full|in-group|non-zero|none>] [--generators <file>]");
+        std::process::exit(exitcode::USAGE);
+    }
+    let input_filename = &args[1];
+    let output_filename = &args[2];
+    let circuit_power = args[3].parse().expect("could not parse circuit power");
+    let batch_size = args[4].parse().expect("could not parse batch size");
+    let to = args[5].parse::<UseCompression>()?;
+
+    let mut parameters = CeremonyParams::<Bn256>::validated(circuit_power, batch_size)?;
+    if let Some(generators) = generators {
+        parameters.generators = generators;
+    }
+
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(input_filename)
+        .map_err(|e| Error::from(e).context("unable to open the input file"))?;
+    let from = match detect_compression(&reader, &parameters)? {
+        Some(detected) => detected,
+        None => {
+            return Err(Error::new(
+                errors::INVALID_POINT,
+                ErrorCategory::InvalidInput,
+                "The first points of the input aren't the generators of G1 and G2, so it isn't an accumulator.",
+            )
+            .with_hint("check that the file is a challenge or a response"))
+        }
+    };
+    let input_length = reader
+        .metadata()
+        .map_err(|e| Error::from(e).context("unable to get filesystem metadata for input file"))?
+        .len() as usize;
+    let (input_sizes, output_sizes) = (
+        expected_sizes(&parameters, from),
+        expected_sizes(&parameters, to),
+    );
+    let output_length = if input_length == input_sizes.challenge {
+        output_sizes.challenge
+    } else if input_length == input_sizes.response {
+        output_sizes.response
+    } else {
+        return Err(Error::new(
+            errors::INVALID_FILE_SIZE,
+            ErrorCategory::InvalidInput,
+            format!(
+                "The input should be {} bytes for a challenge or {} for a response, but it's {}.",
+                input_sizes.challenge, input_sizes.response, input_length
+            ),
+        )
+        .with_hint(
+            "check that the circuit power matches the ceremony and the file was fully transferred",
+        ));
+    };
+
+    let input_map = unsafe {
+        MmapOptions::new()
+            .map(&reader)
+            .map_err(|e| Error::from(e).context("unable to create a memory map for input"))?
+    };
+
+    let writer = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(output_filename)
+        .map_err(|e| Error::from(e).context("unable to create the output file"))?;
+    writer
+        .set_len(output_length as u64)
+        .map_err(|e| Error::from(e).context("must make output file large enough"))?;
+    let mut output_map = unsafe {
+        MmapOptions::new()
+            .map_mut(&writer)
+            .map_err(|e| Error::from(e).context("unable to create a memory map for output"))?
+    };
+
+    println!(
+        "Converting a {} accumulator for 2^{} powers of tau to {}...",
+        from, parameters.size, to
+    );
+    BatchedAccumulator::convert_compression(
+        &input_map,
+        &mut output_map,
+        from,
+        to,
+        check_input,
+        &parameters,
+    )
+    .map_err(|e| Error::from(e).context("unable to convert the accumulator"))?;
+    output_map
+        .flush()
+        .map_err(|e| Error::from(e).context("unable to write the output file"))?;
+
+    println!("Done! {} has been written.", output_filename);
+
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use powersoftau::parameters::Generators;
+
+use bellman_ce::pairing::bn256::Bn256;
+use bellman_ce::pairing::CurveAffine;
+use std::fs::OpenOptions;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 3 {
+        println!("Usage: \n<domain> <generators_file>");
+        std::process::exit(exitcode::USAGE);
+    }
+    let domain = &args[1];
+    let generators_filename = &args[2];
+
+    let generators = Generators::<Bn256>::derive(domain.as_bytes());
+
+    let mut writer = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(generators_filename)
+        .expect("unable to create generators file in this directory");
+    generators
+        .write(&mut writer)
+        .expect("unable to write generators");
+
+    println!("Generators derived from `{}`:", domain);
+    println!(
+        "\tG1: {}",
+        hex::encode(generators.g1.into_compressed().as_ref())
+    );
+    println!(
+        "\tG2: {}",
+        hex::encode(generators.g2.into_compressed().as_ref())
+    );
+}
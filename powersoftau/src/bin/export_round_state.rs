@@ -0,0 +1,84 @@
+use powersoftau::{
+    errors::Error,
+    parameters::{check_compression, expected_sizes, CeremonyParams, UseCompression},
+    round_state::RoundState,
+};
+
+use bellman_ce::pairing::bn256::Bn256;
+use memmap::*;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 5 && args.len() != 6 {
+        println!("Usage: \n<challenge_file> <round_state_file> <circuit_power> <batch_size> [<manifest_signature_file>]");
+        std::process::exit(exitcode::USAGE);
+    }
+    let challenge_filename = &args[1];
+    let round_state_filename = &args[2];
+    let circuit_power = args[3].parse().expect("could not parse circuit power");
+    let batch_size = args[4].parse().expect("could not parse batch size");
+    let manifest_signature = match args.get(5) {
+        Some(filename) => fs::read(filename).expect("unable to read manifest signature file"),
+        None => vec![],
+    };
+
+    let parameters =
+        CeremonyParams::<Bn256>::validated(circuit_power, batch_size).unwrap_or_else(|e| e.exit());
+
+    // Try to load the last verified challenge from disk.
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(challenge_filename)
+        .expect("unable open challenge file in this directory");
+    check_compression(&reader, UseCompression::No, &parameters).unwrap_or_else(|e| {
+        Error::from(e)
+            .context("unable to use the challenge file")
+            .exit()
+    });
+
+    {
+        let metadata = reader
+            .metadata()
+            .expect("unable to get filesystem metadata for challenge file");
+        let expected_challenge_length = expected_sizes(&parameters, UseCompression::No).challenge;
+        if metadata.len() != (expected_challenge_length as u64) {
+            panic!(
+                "The size of challenge file should be {}, but it's {}, so something isn't right.",
+                expected_challenge_length,
+                metadata.len()
+            );
+        }
+    }
+
+    let challenge_readable_map = unsafe {
+        MmapOptions::new()
+            .map(&reader)
+            .expect("unable to create a memory map for input")
+    };
+
+    let round_state = RoundState::new(&challenge_readable_map, &parameters, manifest_signature);
+
+    let mut writer = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(round_state_filename)
+        .expect("unable to create round state file in this directory");
+    round_state
+        .write(&mut writer)
+        .expect("unable to write round state");
+    writer.flush().expect("unable to flush round state");
+
+    println!("Exported the round state for the challenge with hash:");
+    for line in round_state.challenge_hash.chunks(16) {
+        print!("\t");
+        for section in line.chunks(4) {
+            for b in section {
+                print!("{:02x}", b);
+            }
+            print!(" ");
+        }
+        println!();
+    }
+}
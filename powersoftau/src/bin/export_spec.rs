@@ -0,0 +1,57 @@
+use powersoftau::{
+    parameters::{take_generators, take_subgroup_check, CeremonyParams},
+    spec::export_spec,
+    utils::calculate_hash,
+};
+
+use bellman_ce::pairing::bn256::Bn256;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    let subgroup_check = take_subgroup_check(&mut args).unwrap_or_else(|e| e.exit());
+    let generators = take_generators(&mut args).unwrap_or_else(|e| e.exit());
+    if args.len() != 4 {
+        println!("Usage: \n<spec_file> <circuit_power> <batch_size> [--subgroup-check <full-order|cofactor-mul>] [--generators <file>]");
+        std::process::exit(exitcode::USAGE);
+    }
+    let spec_filename = &args[1];
+    let circuit_power = args[2].parse().expect("could not parse circuit power");
+    let batch_size = args[3].parse().expect("could not parse batch size");
+
+    let mut parameters =
+        CeremonyParams::<Bn256>::validated(circuit_power, batch_size).unwrap_or_else(|e| e.exit());
+    if let Some(subgroup_check) = subgroup_check {
+        parameters.curve.subgroup_check = subgroup_check;
+    }
+    if let Some(generators) = generators {
+        parameters.generators = generators;
+    }
+
+    let spec = export_spec("bn256", &parameters);
+    let mut writer = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(spec_filename)
+        .expect("unable to create spec file in this directory");
+    writer
+        .write_all(spec.as_bytes())
+        .expect("unable to write spec");
+    writer.flush().expect("unable to flush spec");
+
+    println!(
+        "Exported the spec of the ceremony for 2^{} powers of tau, the file has hash:",
+        parameters.size
+    );
+    for line in calculate_hash(spec.as_bytes()).chunks(16) {
+        print!("\t");
+        for section in line.chunks(4) {
+            for b in section {
+                print!("{:02x}", b);
+            }
+            print!(" ");
+        }
+        println!();
+    }
+}
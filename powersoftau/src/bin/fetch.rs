@@ -0,0 +1,62 @@
+use powersoftau::{
+    errors::{self, Error, ErrorCategory},
+    transfer::{fetch, take_transfer_options},
+};
+
+use std::path::Path;
+
+const USAGE: &str = "Usage: \nfetch --url <url> --output <file> [--expected-hash <hash_file>] [--connections <n>] [--part-size <MiB>] [--retries <n>]";
+
+/// Removes `<flag> <value>` from the arguments and returns the value
+fn take_required(args: &mut Vec<String>, flag: &str) -> Result<String, Error> {
+    let position = args
+        .iter()
+        .position(|arg| arg == flag)
+        .filter(|position| position + 1 < args.len())
+        .ok_or_else(|| {
+            Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                format!("{} is required", flag),
+            )
+        })?;
+    let value = args[position + 1].clone();
+    args.drain(position..position + 2);
+
+    Ok(value)
+}
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut args: Vec<String> = std::env::args().collect();
+    let options = take_transfer_options(&mut args)?;
+    let (url, output) = match (
+        take_required(&mut args, "--url"),
+        take_required(&mut args, "--output"),
+    ) {
+        (Ok(url), Ok(output)) if args.len() == 1 => (url, output),
+        _ => {
+            println!("{}", USAGE);
+            std::process::exit(exitcode::USAGE);
+        }
+    };
+
+    println!(
+        "Downloading {} to {} over {} connections...",
+        url, output, options.connections
+    );
+    let hash = fetch(&url, Path::new(&output), &options)?;
+
+    println!("Done! {} has been written.", output);
+    println!("BLAKE2b hash: {}", hex::encode(&hash[..]));
+    if options.expected_hash.is_some() {
+        println!("The hash is the expected one.");
+    }
+
+    Ok(())
+}
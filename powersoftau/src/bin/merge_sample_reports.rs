@@ -0,0 +1,63 @@
+use powersoftau::{
+    errors::{self, Error, ErrorCategory},
+    parameters::CeremonyParams,
+    sampling::{Coverage, SampleReport},
+};
+
+use bellman_ce::pairing::bn256::Bn256;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 4 {
+        println!("Usage: \n<circuit_power> <batch_size> <report_or_attestation>...");
+        std::process::exit(exitcode::USAGE);
+    }
+    let circuit_power = args[1].parse().expect("could not parse circuit power");
+    let batch_size = args[2].parse().expect("could not parse batch size");
+
+    let parameters =
+        CeremonyParams::<Bn256>::validated(circuit_power, batch_size).unwrap_or_else(|e| e.exit());
+
+    let reports = args[3..]
+        .iter()
+        .map(|filename| SampleReport::read(filename).unwrap_or_else(|e| e.exit()))
+        .collect::<Vec<_>>();
+    let coverage = Coverage::merge(&reports, &parameters).unwrap_or_else(|e| e.exit());
+
+    println!(
+        "Merged {} reports of the file with BLAKE2b hash {}",
+        reports.len(),
+        hex::encode(&reports[0].file_hash[..])
+    );
+    let covered = coverage
+        .covered
+        .iter()
+        .map(|range| range.end - range.start)
+        .sum::<usize>();
+    println!(
+        "{} of {} pairs of consecutive powers were checked:",
+        covered, coverage.length
+    );
+    for range in coverage.covered.iter() {
+        println!("\t{}-{}", range.start, range.end - 1);
+    }
+
+    if !coverage.failed.is_empty() {
+        Error::new(
+            errors::INVALID_CONTRIBUTION,
+            ErrorCategory::Verification,
+            format!("Some pairs failed the check: {:?}", coverage.failed),
+        )
+        .exit();
+    }
+
+    if !coverage.is_complete() {
+        println!("Pairs that no report checked:");
+        for range in coverage.uncovered() {
+            println!("\t{}-{}", range.start, range.end - 1);
+        }
+        std::process::exit(exitcode::DATAERR);
+    }
+
+    println!("Every pair was checked by at least one report.");
+}
@@ -0,0 +1,41 @@
+use powersoftau::merkle::{MerkleTree, DEFAULT_LEAF_SIZE};
+
+use memmap::*;
+use std::fs::OpenOptions;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 2 && args.len() != 3 {
+        println!("Usage: \n<challenge_file> [<leaf_size>]");
+        std::process::exit(exitcode::USAGE);
+    }
+    let filename = &args[1];
+    let leaf_size = args
+        .get(2)
+        .map(|size| size.parse().expect("could not parse leaf size"))
+        .unwrap_or(DEFAULT_LEAF_SIZE);
+    if leaf_size == 0 {
+        println!("leaf size must be positive");
+        std::process::exit(exitcode::USAGE);
+    }
+
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(filename)
+        .expect("unable open file in this directory");
+    let readable_map = unsafe {
+        MmapOptions::new()
+            .map(&reader)
+            .expect("unable to create a memory map for input")
+    };
+
+    let tree = MerkleTree::compute(&readable_map, leaf_size);
+
+    println!(
+        "Merkle root of `{}` over {} leaves of {} bytes:",
+        filename,
+        tree.leaf_count(),
+        leaf_size
+    );
+    println!("{}", hex::encode(&tree.root()[..]));
+}
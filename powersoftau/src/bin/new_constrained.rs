@@ -1,36 +1,150 @@
 use powersoftau::batched_accumulator::BatchedAccumulator;
-use powersoftau::parameters::UseCompression;
-use powersoftau::utils::{blank_hash, calculate_hash};
+use powersoftau::errors::{self, Error, ErrorCategory};
+use powersoftau::parameters::{
+    check_compression, expected_sizes, take_generators, CheckForCorrectness, DeserializationError,
+    ElementType, UseCompression,
+};
+use powersoftau::storage::{take_output_policy, LocalFile};
+use powersoftau::utils::{blank_hash, calculate_hash, write_hash_header};
 
 use bellman_ce::pairing::bn256::Bn256;
 use memmap::*;
 use std::fs::OpenOptions;
-use std::io::Write;
 
 use powersoftau::parameters::CeremonyParams;
 
 const COMPRESS_NEW_CHALLENGE: UseCompression = UseCompression::No;
+const EXISTING_IS_COMPRESSED: UseCompression = UseCompression::No;
+
+/// Removes `--from-existing <file>` from the arguments and returns the file
+fn take_from_existing(args: &mut Vec<String>) -> Option<String> {
+    let position = args.iter().position(|arg| arg == "--from-existing")?;
+    if position + 1 >= args.len() {
+        println!("--from-existing requires a file");
+        std::process::exit(exitcode::USAGE);
+    }
+    let existing_filename = args[position + 1].clone();
+    args.drain(position..position + 2);
+
+    Some(existing_filename)
+}
+
+/// Checks that all powers of the accumulator in `existing_map` are consistent, i.e. that it
+/// could be the outcome of a ceremony.
+fn verify_existing(
+    existing_map: &Mmap,
+    parameters: &CeremonyParams<Bn256>,
+) -> Result<bool, DeserializationError> {
+    let chunks = (0..BatchedAccumulator::chunk_ranges(parameters).len()).collect::<Vec<_>>();
+
+    Ok(BatchedAccumulator::verify_chunks(
+        existing_map,
+        EXISTING_IS_COMPRESSED,
+        CheckForCorrectness::Yes,
+        &chunks,
+        parameters,
+    )? && BatchedAccumulator::verify_element(
+        existing_map,
+        EXISTING_IS_COMPRESSED,
+        CheckForCorrectness::Yes,
+        ElementType::BetaG2,
+        0,
+        parameters,
+    )?)
+}
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    let existing_filename = take_from_existing(&mut args);
+    let generators = take_generators(&mut args).unwrap_or_else(|e| e.exit());
+    let output_policy = take_output_policy(&mut args).unwrap_or_else(|e| e.exit());
     if args.len() != 4 {
-        println!("Usage: \n<challenge_file> <ceremony_size> <batch_size>");
+        println!("Usage: \n<challenge_file> <ceremony_size> <batch_size> [--from-existing <accumulator_file>] [--generators <file>] [--no-clobber|--overwrite|--versioned]");
         std::process::exit(exitcode::USAGE);
     }
-    let challenge_filename = &args[1];
+    let challenge_file = LocalFile::output(&args[1], output_policy).unwrap_or_else(|e| e.exit());
+    let challenge_filename = challenge_file.path();
     let circuit_power = args[2].parse().expect("could not parse circuit power");
     let batch_size = args[3].parse().expect("could not parse batch size");
 
-    let parameters = CeremonyParams::<Bn256>::new(circuit_power, batch_size);
+    let mut parameters =
+        CeremonyParams::<Bn256>::validated(circuit_power, batch_size).unwrap_or_else(|e| e.exit());
+    if let Some(generators) = generators {
+        parameters.generators = generators;
+    }
+
+    // An existing accumulator is verified before anything is written, and its hash seeds the
+    // hash chain of the new ceremony
+    let existing = existing_filename.map(|existing_filename| {
+        println!(
+            "Will start from the accumulator for 2^{} powers of tau in {}",
+            parameters.size, existing_filename
+        );
+        // A staged copy is removed at the end of the closure, which the map outlives
+        let existing_file = LocalFile::fetch(&existing_filename).unwrap_or_else(|e| e.exit());
+        let reader = OpenOptions::new()
+            .read(true)
+            .open(existing_file.path())
+            .expect("unable open existing accumulator file");
+        check_compression(&reader, UseCompression::No, &parameters).unwrap_or_else(|e| {
+            Error::from(e)
+                .context("unable to use the existing accumulator")
+                .exit()
+        });
+        let length = reader
+            .metadata()
+            .expect("unable to get filesystem metadata for existing accumulator file")
+            .len();
+        let expected_length = expected_sizes(&parameters, UseCompression::No).challenge;
+        if length != expected_length as u64 {
+            Error::new(
+                errors::INVALID_FILE_SIZE,
+                ErrorCategory::InvalidInput,
+                format!(
+                    "The size of the existing accumulator should be {}, but it's {}",
+                    expected_length, length
+                ),
+            )
+            .with_hint("it must be an uncompressed challenge of the same size; use reduce_powers for a larger one")
+            .exit();
+        }
+        let existing_map = unsafe {
+            MmapOptions::new()
+                .map(&reader)
+                .expect("unable to create a memory map for input")
+        };
+
+        println!("Verifying the existing accumulator...");
+        let valid = verify_existing(&existing_map, &parameters).unwrap_or_else(|e| {
+            Error::from(e)
+                .context("unable to read the existing accumulator")
+                .exit()
+        });
+        if !valid {
+            Error::new(
+                errors::INVALID_CONTRIBUTION,
+                ErrorCategory::Verification,
+                "The existing accumulator is invalid.",
+            )
+            .exit();
+        }
 
-    println!(
-        "Will generate an empty accumulator for 2^{} powers of tau",
-        parameters.size
-    );
-    println!(
-        "In total will generate up to {} powers",
-        parameters.powers_g1_length
-    );
+        existing_map
+    });
+
+    match existing {
+        Some(_) => println!("Will re-brand it as the initial accumulator of a new ceremony"),
+        None => {
+            println!(
+                "Will generate an empty accumulator for 2^{} powers of tau",
+                parameters.size
+            );
+            println!(
+                "In total will generate up to {} powers",
+                parameters.powers_g1_length
+            );
+        }
+    }
 
     let file = OpenOptions::new()
         .read(true)
@@ -39,10 +153,7 @@ fn main() {
         .open(challenge_filename)
         .expect("unable to create challenge file");
 
-    let expected_challenge_length = match COMPRESS_NEW_CHALLENGE {
-        UseCompression::Yes => parameters.contribution_size - parameters.public_key_size,
-        UseCompression::No => parameters.accumulator_size,
-    };
+    let expected_challenge_length = expected_sizes(&parameters, COMPRESS_NEW_CHALLENGE).challenge;
 
     file.set_len(expected_challenge_length as u64)
         .expect("unable to allocate large enough file");
@@ -53,16 +164,21 @@ fn main() {
             .expect("unable to create a memory map")
     };
 
-    // Write a blank BLAKE2b hash:
-    let hash = blank_hash();
-    (&mut writable_map[0..])
-        .write_all(hash.as_slice())
+    // Write a blank BLAKE2b hash, or the one of the existing accumulator:
+    let hash = match existing {
+        Some(ref existing_map) => calculate_hash(existing_map),
+        None => blank_hash(),
+    };
+    write_hash_header(&mut writable_map, hash.as_slice())
         .expect("unable to write a default hash to mmap");
     writable_map
         .flush()
         .expect("unable to write blank hash to challenge file");
 
-    println!("Blank hash for an empty challenge:");
+    match existing {
+        Some(_) => println!("Hash of the existing accumulator:"),
+        None => println!("Blank hash for an empty challenge:"),
+    }
     for line in hash.as_slice().chunks(16) {
         print!("\t");
         for section in line.chunks(4) {
@@ -74,8 +190,16 @@ fn main() {
         println!();
     }
 
-    BatchedAccumulator::generate_initial(&mut writable_map, COMPRESS_NEW_CHALLENGE, &parameters)
-        .expect("generation of initial accumulator is successful");
+    match existing {
+        Some(ref existing_map) => writable_map[parameters.hash_size..]
+            .copy_from_slice(&existing_map[parameters.hash_size..]),
+        None => BatchedAccumulator::generate_initial(
+            &mut writable_map,
+            COMPRESS_NEW_CHALLENGE,
+            &parameters,
+        )
+        .expect("generation of initial accumulator is successful"),
+    }
     writable_map
         .flush()
         .expect("unable to flush memmap to disk");
@@ -86,7 +210,10 @@ fn main() {
         .expect("must make a map readonly");
     let contribution_hash = calculate_hash(&output_readonly);
 
-    println!("Empty contribution is formed with a hash:");
+    match existing {
+        Some(_) => println!("Initial challenge is formed with a hash:"),
+        None => println!("Empty contribution is formed with a hash:"),
+    }
 
     for line in contribution_hash.as_slice().chunks(16) {
         print!("\t");
@@ -99,5 +226,10 @@ fn main() {
         println!();
     }
 
-    println!("Wrote a fresh accumulator to challenge file");
+    challenge_file.publish().unwrap_or_else(|e| e.exit());
+
+    match existing {
+        Some(_) => println!("Wrote the existing accumulator to challenge file"),
+        None => println!("Wrote a fresh accumulator to challenge file"),
+    }
 }
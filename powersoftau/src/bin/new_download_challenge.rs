@@ -0,0 +1,44 @@
+use powersoftau::download_proof::{DownloadChallenge, DEFAULT_RANGE_COUNT, DEFAULT_RANGE_LENGTH};
+
+use rand::OsRng;
+use std::fs::{self, File};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 3 || args.len() > 5 {
+        println!(
+            "Usage: \n<challenge_file> <download_challenge_file> [range_count] [range_length]"
+        );
+        std::process::exit(exitcode::USAGE);
+    }
+    let challenge_filename = &args[1];
+    let download_challenge_filename = &args[2];
+    let count = args
+        .get(3)
+        .map(|count| count.parse().expect("could not parse range count"))
+        .unwrap_or(DEFAULT_RANGE_COUNT);
+    let range_length = args
+        .get(4)
+        .map(|length| length.parse().expect("could not parse range length"))
+        .unwrap_or(DEFAULT_RANGE_LENGTH);
+
+    let length = fs::metadata(challenge_filename)
+        .expect("unable to get filesystem metadata for challenge file")
+        .len();
+
+    let mut rng = OsRng::new().expect("unable to access the system randomness");
+    let download_challenge = DownloadChallenge::new(&mut rng, length, count, range_length);
+
+    let mut writer = File::create(download_challenge_filename)
+        .expect("unable to create download challenge file");
+    download_challenge
+        .write(&mut writer)
+        .expect("unable to write download challenge");
+
+    println!(
+        "Wrote a download challenge for {} ranges of {} to {}",
+        download_challenge.ranges.len(),
+        challenge_filename,
+        download_challenge_filename
+    );
+}
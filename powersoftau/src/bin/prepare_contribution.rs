@@ -0,0 +1,142 @@
+use powersoftau::{
+    errors::{self, Error, ErrorCategory},
+    hash_file::{check_expected_hash, take_expected_hash},
+    parameters::{
+        check_compression, expected_sizes, take_check_input, CeremonyParams, CheckForCorrectness,
+        UseCompression,
+    },
+    storage::LocalFile,
+    utils::calculate_hash,
+    workset::{compress_challenge, workset_size, WorksetHeader, HEADER_SIZE},
+};
+
+use bellman_ce::pairing::bn256::Bn256;
+use memmap::*;
+use std::fs::OpenOptions;
+
+const CHALLENGE_IS_COMPRESSED: UseCompression = UseCompression::No;
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    let expected_hash_filename = take_expected_hash(&mut args).unwrap_or_else(|e| e.exit());
+    // The offline machine only decompresses the points, so they are fully checked here
+    let check_input =
+        take_check_input(&mut args, CheckForCorrectness::Yes).unwrap_or_else(|e| e.exit());
+    if args.len() != 5 {
+        println!("Usage: \n<challenge_file> <workset_file> <circuit_power> <batch_size> [--expected-hash <challenge_hash_file>] [--check-input <full|in-group|non-zero|none>]");
+        std::process::exit(exitcode::USAGE);
+    }
+    let challenge_file = LocalFile::fetch(&args[1]).unwrap_or_else(|e| e.exit());
+    let challenge_filename = challenge_file.path();
+    let workset_filename = &args[2];
+    let circuit_power = args[3].parse().expect("could not parse circuit power");
+    let batch_size = args[4].parse().expect("could not parse batch size");
+
+    let parameters =
+        CeremonyParams::<Bn256>::validated(circuit_power, batch_size).unwrap_or_else(|e| e.exit());
+
+    println!(
+        "Will prepare a workset to contribute offline to accumulator for 2^{} powers of tau",
+        parameters.size
+    );
+
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(challenge_filename)
+        .expect("unable open challenge file");
+    check_compression(&reader, CHALLENGE_IS_COMPRESSED, &parameters).unwrap_or_else(|e| {
+        Error::from(e)
+            .context("unable to use the challenge file")
+            .exit()
+    });
+    {
+        let metadata = reader
+            .metadata()
+            .expect("unable to get filesystem metadata for challenge file");
+        let expected_challenge_length =
+            expected_sizes(&parameters, CHALLENGE_IS_COMPRESSED).challenge;
+        if metadata.len() != (expected_challenge_length as u64) {
+            Error::new(
+                errors::INVALID_FILE_SIZE,
+                ErrorCategory::InvalidInput,
+                format!(
+                    "The size of challenge file should be {}, but it's {}, so something isn't right.",
+                    expected_challenge_length,
+                    metadata.len()
+                ),
+            )
+            .with_hint("check that the circuit power matches the ceremony and the file was fully transferred")
+            .exit();
+        }
+    }
+
+    let readable_map = unsafe {
+        MmapOptions::new()
+            .map(&reader)
+            .expect("unable to create a memory map for input")
+    };
+
+    println!("Calculating the challenge hash...");
+    let challenge_hash = calculate_hash(&readable_map);
+    println!("`challenge` file contains decompressed points and has a hash:");
+    for line in challenge_hash.as_slice().chunks(16) {
+        print!("\t");
+        for section in line.chunks(4) {
+            for b in section {
+                print!("{:02x}", b);
+            }
+            print!(" ");
+        }
+        println!();
+    }
+    if let Some(expected_hash_filename) = &expected_hash_filename {
+        check_expected_hash(
+            expected_hash_filename,
+            challenge_filename,
+            challenge_hash.as_slice(),
+        )
+        .unwrap_or_else(|e| e.exit());
+        println!("The hash matches the one in {}", expected_hash_filename);
+    }
+
+    let mut writer = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(workset_filename)
+        .expect("unable to create workset file");
+    let mut header = WorksetHeader {
+        circuit_power,
+        batch_size,
+        challenge_hash: [0; 64],
+    };
+    header
+        .challenge_hash
+        .copy_from_slice(challenge_hash.as_slice());
+    header
+        .write(&mut writer)
+        .expect("unable to write the workset header");
+    writer
+        .set_len(workset_size(&parameters) as u64)
+        .expect("must make workset file large enough");
+
+    let mut writable_map = unsafe {
+        MmapOptions::new()
+            .offset(HEADER_SIZE as u64)
+            .map_mut(&writer)
+            .expect("unable to create a memory map for output")
+    };
+
+    println!("Checking and compressing the challenge...");
+    compress_challenge(&readable_map, &mut writable_map, check_input, &parameters)
+        .map_err(Error::from)
+        .unwrap_or_else(|e| e.context("unable to compress the challenge").exit());
+    writable_map.flush().expect("must flush a memory map");
+
+    println!(
+        "Done! Carry {} ({} bytes instead of {}) to the offline machine and run apply_contribution there.",
+        workset_filename,
+        workset_size(&parameters),
+        expected_sizes(&parameters, CHALLENGE_IS_COMPRESSED).challenge
+    );
+}
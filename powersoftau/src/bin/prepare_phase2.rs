@@ -2,6 +2,7 @@ use bellman_ce::pairing::bn256::Bn256;
 use bellman_ce::pairing::bn256::{G1, G2};
 use bellman_ce::pairing::{CurveAffine, CurveProjective};
 use powersoftau::batched_accumulator::*;
+use powersoftau::errors::Error;
 use powersoftau::parameters::CeremonyParams;
 use powersoftau::*;
 
@@ -34,39 +35,61 @@ fn main() {
     let circuit_power = args[2].parse().expect("could not parse circuit power");
     let batch_size = args[3].parse().expect("could not parse batch size");
 
-    let parameters = CeremonyParams::<Bn256>::new(circuit_power, batch_size);
+    let parameters =
+        CeremonyParams::<Bn256>::validated(circuit_power, batch_size).unwrap_or_else(|e| e.exit());
 
     // Try to load response file from disk.
     let reader = OpenOptions::new()
         .read(true)
         .open(response_filename)
         .expect("unable open response file in this directory");
+    check_compression(&reader, UseCompression::Yes, &parameters).unwrap_or_else(|e| {
+        Error::from(e)
+            .context("unable to use the response file")
+            .exit()
+    });
     let response_readable_map = unsafe {
         MmapOptions::new()
             .map(&reader)
             .expect("unable to create a memory map for input")
     };
 
-    let current_accumulator = BatchedAccumulator::deserialize(
+    // Each of the files only needs a prefix of the powers, so they are decoded as they are used
+    // rather than holding the whole accumulator in memory
+    let current_accumulator = AccumulatorView::new(
         &response_readable_map,
-        CheckForCorrectness::Yes,
         UseCompression::Yes,
+        CheckForCorrectness::Yes,
         &parameters,
     )
-    .expect("unable to read uncompressed accumulator");
+    .expect("unable to read compressed accumulator");
+    let alpha_g1 = current_accumulator
+        .alpha_tau_powers_g1(0..1)
+        .expect("unable to read alpha in G1")[0];
+    let beta_g1 = current_accumulator
+        .beta_tau_powers_g1(0..1)
+        .expect("unable to read beta in G1")[0];
+    let beta_g2 = current_accumulator
+        .beta_g2()
+        .expect("unable to read beta in G2");
 
     let worker = &Worker::new();
 
     // Create the parameters for various 2^m circuit depths.
-    let max_degree = log_2(current_accumulator.tau_powers_g2.len() as u64);
+    let max_degree = log_2(parameters.powers_length as u64);
     for m in 0..=max_degree {
         let paramname = format!("phase1radix2m{}", m);
         println!("Creating {}", paramname);
 
         let degree = 1 << m;
 
+        // The H query below needs the powers up to 2 * degree - 2
+        let tau_powers_g1 = current_accumulator
+            .tau_powers_g1(0..2 * degree - 1)
+            .expect("unable to read powers of tau in G1");
+
         let mut g1_coeffs = EvaluationDomain::from_coeffs(
-            current_accumulator.tau_powers_g1[0..degree]
+            tau_powers_g1[0..degree]
                 .iter()
                 .map(|e| Point(e.into_projective()))
                 .collect(),
@@ -74,7 +97,9 @@ fn main() {
         .unwrap();
 
         let mut g2_coeffs = EvaluationDomain::from_coeffs(
-            current_accumulator.tau_powers_g2[0..degree]
+            current_accumulator
+                .tau_powers_g2(0..degree)
+                .expect("unable to read powers of tau in G2")
                 .iter()
                 .map(|e| Point(e.into_projective()))
                 .collect(),
@@ -82,7 +107,9 @@ fn main() {
         .unwrap();
 
         let mut g1_alpha_coeffs = EvaluationDomain::from_coeffs(
-            current_accumulator.alpha_tau_powers_g1[0..degree]
+            current_accumulator
+                .alpha_tau_powers_g1(0..degree)
+                .expect("unable to read powers of tau in G1 with alpha")
                 .iter()
                 .map(|e| Point(e.into_projective()))
                 .collect(),
@@ -90,7 +117,9 @@ fn main() {
         .unwrap();
 
         let mut g1_beta_coeffs = EvaluationDomain::from_coeffs(
-            current_accumulator.beta_tau_powers_g1[0..degree]
+            current_accumulator
+                .beta_tau_powers_g1(0..degree)
+                .expect("unable to read powers of tau in G1 with beta")
                 .iter()
                 .map(|e| Point(e.into_projective()))
                 .collect(),
@@ -136,8 +165,8 @@ fn main() {
         // for radix2 evaluation domains
         let mut h = Vec::with_capacity(degree - 1);
         for i in 0..(degree - 1) {
-            let mut tmp = current_accumulator.tau_powers_g1[i + degree].into_projective();
-            let mut tmp2 = current_accumulator.tau_powers_g1[i].into_projective();
+            let mut tmp = tau_powers_g1[i + degree].into_projective();
+            let mut tmp2 = tau_powers_g1[i].into_projective();
             tmp2.negate();
             tmp.add_assign(&tmp2);
 
@@ -161,28 +190,20 @@ fn main() {
         // Needed by verifier for e(alpha, beta)
         // Needed by prover for A and C elements of proof
         writer
-            .write_all(
-                current_accumulator.alpha_tau_powers_g1[0]
-                    .into_uncompressed()
-                    .as_ref(),
-            )
+            .write_all(alpha_g1.into_uncompressed().as_ref())
             .unwrap();
 
         // Write beta (in g1)
         // Needed by prover for C element of proof
         writer
-            .write_all(
-                current_accumulator.beta_tau_powers_g1[0]
-                    .into_uncompressed()
-                    .as_ref(),
-            )
+            .write_all(beta_g1.into_uncompressed().as_ref())
             .unwrap();
 
         // Write beta (in g2)
         // Needed by verifier for e(alpha, beta)
         // Needed by prover for B element of proof
         writer
-            .write_all(current_accumulator.beta_g2.into_uncompressed().as_ref())
+            .write_all(beta_g2.into_uncompressed().as_ref())
             .unwrap();
 
         // Lagrange coefficients in G1 (for constructing
@@ -0,0 +1,47 @@
+use powersoftau::download_proof::DownloadChallenge;
+
+use memmap::*;
+use std::fs::{File, OpenOptions};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 4 {
+        println!("Usage: \n<challenge_file> <download_challenge_file> <download_proof_file>");
+        std::process::exit(exitcode::USAGE);
+    }
+    let challenge_filename = &args[1];
+    let download_challenge_filename = &args[2];
+    let download_proof_filename = &args[3];
+
+    let mut download_challenge_reader = OpenOptions::new()
+        .read(true)
+        .open(download_challenge_filename)
+        .expect("unable open download challenge file in this directory");
+    let download_challenge = DownloadChallenge::read(&mut download_challenge_reader)
+        .expect("unable to read download challenge");
+
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(challenge_filename)
+        .expect("unable open challenge file in this directory");
+    let challenge_readable_map = unsafe {
+        MmapOptions::new()
+            .map(&reader)
+            .expect("unable to create a memory map for input")
+    };
+
+    let proof = download_challenge
+        .prove(&challenge_readable_map)
+        .unwrap_or_else(|e| {
+            println!("Unable to prove the download: {}", e);
+            std::process::exit(exitcode::DATAERR);
+        });
+
+    let mut writer =
+        File::create(download_proof_filename).expect("unable to create download proof file");
+    proof
+        .write(&mut writer)
+        .expect("unable to write download proof");
+
+    println!("Wrote the download proof to {}", download_proof_filename);
+}
@@ -0,0 +1,60 @@
+use powersoftau::{
+    errors::{self, Error, ErrorCategory},
+    hash_file::{decode_hash, read_hash_file},
+    path_template::take_template,
+    watch::Queue,
+};
+
+use std::path::Path;
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut args: Vec<String> = std::env::args().collect();
+    let challenge_template = take_template(&mut args, "--challenge-template")?;
+    let response_template = take_template(&mut args, "--response-template")?;
+    if args.len() != 4 && args.len() != 5 {
+        println!("Usage: \n<response_directory> <challenge_directory> <response_hash|hash_file> [<statement_file>] [--challenge-template <template>] [--response-template <template>]");
+        std::process::exit(exitcode::USAGE);
+    }
+    let mut queue = Queue::new(&args[1], &args[2]);
+    if let Some(template) = challenge_template {
+        queue.challenge_template = template;
+    }
+    if let Some(template) = response_template {
+        queue.response_template = template;
+    }
+    let response_hash = if Path::new(&args[3]).is_file() {
+        read_hash_file(&args[3])?
+    } else {
+        decode_hash(args[3].as_bytes())?
+    };
+
+    println!(
+        "Looking for the response in the hash chain from {}",
+        queue.challenge_path(0).display()
+    );
+    let statement = queue.inclusion_statement(&response_hash)?.ok_or_else(|| {
+        Error::new(
+            errors::MISSING_CONTRIBUTION,
+            ErrorCategory::Verification,
+            "the response is not in the hash chain",
+        )
+        .with_hint("check that the hash is the one of the response, not of the challenge")
+    })?;
+    print!("{}", statement);
+    if let Some(statement_path) = args.get(4) {
+        std::fs::write(statement_path, statement.to_string())
+            .map_err(|e| Error::from(e).context("unable to write the statement"))?;
+    }
+    println!(
+        "The response is the contribution to round {}. No contribution was verified.",
+        statement.round
+    );
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use powersoftau::{
+    batched_accumulator::BatchedAccumulator,
+    errors::{self, Error, ErrorCategory},
+    merkle::{MerkleTree, DEFAULT_LEAF_SIZE},
+    parameters::{check_compression, expected_sizes, CeremonyParams, ElementType, UseCompression},
+};
+
+use bellman_ce::pairing::bn256::Bn256;
+use memmap::*;
+use std::fs::OpenOptions;
+
+const CHALLENGE_IS_COMPRESSED: UseCompression = UseCompression::No;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 8 && args.len() != 9 {
+        println!("Usage: \n<challenge_file> <circuit_power> <batch_size> <tau_g1|tau_g2|alpha_g1|beta_g1|beta_g2> <index> <count> <proof_file> [<leaf_size>]");
+        std::process::exit(exitcode::USAGE);
+    }
+    let challenge_filename = &args[1];
+    let circuit_power = args[2].parse().expect("could not parse circuit power");
+    let batch_size = args[3].parse().expect("could not parse batch size");
+    let element_type: ElementType = args[4].parse().unwrap_or_else(|e: Error| e.exit());
+    let index = args[5].parse().expect("could not parse element index");
+    let count = args[6].parse().expect("could not parse element count");
+    let proof_filename = &args[7];
+    let leaf_size = args
+        .get(8)
+        .map(|size| size.parse().expect("could not parse leaf size"))
+        .unwrap_or(DEFAULT_LEAF_SIZE);
+    if leaf_size == 0 {
+        println!("leaf size must be positive");
+        std::process::exit(exitcode::USAGE);
+    }
+
+    let parameters =
+        CeremonyParams::<Bn256>::validated(circuit_power, batch_size).unwrap_or_else(|e| e.exit());
+
+    let range = BatchedAccumulator::element_range(
+        element_type,
+        index,
+        count,
+        CHALLENGE_IS_COMPRESSED,
+        &parameters,
+    )
+    .unwrap_or_else(|| {
+        Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            format!(
+                "there are no {} {:?} elements from {} in an accumulator for 2^{} powers of tau",
+                count, element_type, index, parameters.size
+            ),
+        )
+        .exit()
+    });
+
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(challenge_filename)
+        .expect("unable open challenge file in this directory");
+    check_compression(&reader, UseCompression::No, &parameters).unwrap_or_else(|e| {
+        Error::from(e)
+            .context("unable to use the challenge file")
+            .exit()
+    });
+    {
+        let metadata = reader
+            .metadata()
+            .expect("unable to get filesystem metadata for challenge file");
+        let expected_challenge_length = expected_sizes(&parameters, UseCompression::No).challenge;
+        if metadata.len() != (expected_challenge_length as u64) {
+            Error::new(
+                errors::INVALID_FILE_SIZE,
+                ErrorCategory::InvalidInput,
+                format!(
+                    "The size of challenge file should be {}, but it's {}",
+                    expected_challenge_length,
+                    metadata.len()
+                ),
+            )
+            .exit();
+        }
+    }
+    let challenge_readable_map = unsafe {
+        MmapOptions::new()
+            .map(&reader)
+            .expect("unable to create a memory map for input")
+    };
+
+    let tree = MerkleTree::compute(&challenge_readable_map, leaf_size);
+    let proof = tree.prove(
+        &challenge_readable_map,
+        range.start as u64..range.end as u64,
+    );
+
+    let mut writer = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(proof_filename)
+        .expect("unable to create proof file in this directory");
+    proof.write(&mut writer).expect("unable to write proof");
+
+    println!(
+        "Wrote a proof of {} {:?} elements from {} (bytes {} to {}) to `{}`, against the Merkle root",
+        count, element_type, index, range.start, range.end, proof_filename
+    );
+    println!("{}", hex::encode(&tree.root()[..]));
+}
@@ -1,12 +1,12 @@
 use bellman_ce::pairing::bn256::Bn256;
 use powersoftau::{
     batched_accumulator::BatchedAccumulator,
-    parameters::{CeremonyParams, CheckForCorrectness, UseCompression},
+    errors::Error,
+    parameters::{check_compression, CeremonyParams, CheckForCorrectness, UseCompression},
     utils::{calculate_hash, reduced_hash},
 };
 
 use std::fs::OpenOptions;
-use std::io::Write;
 
 use memmap::MmapOptions;
 
@@ -27,42 +27,35 @@ fn main() {
     }
     let challenge_filename = &args[1];
     let reduced_challenge_filename = &args[2];
-    let original_circuit_power = args[3].parse().expect("could not parse original circuit power");
-    let reduced_circuit_power = args[4].parse().expect("could not parse reduced circuit power");
+    let original_circuit_power: usize = args[3]
+        .parse()
+        .expect("could not parse original circuit power");
+    let reduced_circuit_power: usize = args[4]
+        .parse()
+        .expect("could not parse reduced circuit power");
     let batch_size = args[5].parse().expect("could not parse batch size");
 
-    let parameters = CeremonyParams::<Bn256>::new(reduced_circuit_power, batch_size);
+    let parameters = CeremonyParams::<Bn256>::validated(original_circuit_power, batch_size)
+        .unwrap_or_else(|e| e.exit());
+    let reduced_parameters = CeremonyParams::<Bn256>::validated(reduced_circuit_power, batch_size)
+        .unwrap_or_else(|e| e.exit());
 
     // Try to load the challenge from disk.
     let reader = OpenOptions::new()
         .read(true)
         .open(challenge_filename)
         .expect("unable to open challenge in this directory");
+    check_compression(&reader, UseCompression::No, &parameters).unwrap_or_else(|e| {
+        Error::from(e)
+            .context("unable to use the challenge file")
+            .exit()
+    });
     let challenge_readable_map = unsafe {
         MmapOptions::new()
             .map(&reader)
             .expect("unable to create a memory map for input")
     };
 
-    let current_accumulator = BatchedAccumulator::deserialize(
-        &challenge_readable_map,
-        CheckForCorrectness::Yes,
-        UseCompression::No,
-        &parameters,
-    )
-    .expect("unable to read compressed accumulator");
-
-    let mut reduced_accumulator = BatchedAccumulator::empty(&parameters);
-    reduced_accumulator.tau_powers_g1 =
-        current_accumulator.tau_powers_g1[..parameters.powers_g1_length].to_vec();
-    reduced_accumulator.tau_powers_g2 =
-        current_accumulator.tau_powers_g2[..parameters.powers_length].to_vec();
-    reduced_accumulator.alpha_tau_powers_g1 =
-        current_accumulator.alpha_tau_powers_g1[..parameters.powers_length].to_vec();
-    reduced_accumulator.beta_tau_powers_g1 =
-        current_accumulator.beta_tau_powers_g1[..parameters.powers_length].to_vec();
-    reduced_accumulator.beta_g2 = current_accumulator.beta_g2;
-
     let writer = OpenOptions::new()
         .read(true)
         .write(true)
@@ -72,7 +65,7 @@ fn main() {
 
     // Recomputation stips the public key and uses hashing to link with the previous contibution after decompression
     writer
-        .set_len(parameters.accumulator_size as u64)
+        .set_len(reduced_parameters.accumulator_size as u64)
         .expect("must make output file large enough");
 
     let mut writable_map = unsafe {
@@ -81,19 +74,25 @@ fn main() {
             .expect("unable to create a memory map for output")
     };
 
-    let hash = reduced_hash(
-        original_circuit_power,
-        parameters.size as u8,
-    );
-    (&mut writable_map[0..])
-        .write_all(hash.as_slice())
-        .expect("unable to write a default hash to mmap");
+    BatchedAccumulator::extract_subset(
+        &challenge_readable_map,
+        &mut writable_map,
+        UseCompression::No,
+        UseCompression::No,
+        CheckForCorrectness::Yes,
+        &parameters,
+        &reduced_parameters,
+    )
+    .expect("unable to extract the reduced accumulator");
     writable_map
         .flush()
-        .expect("unable to write reduced hash to the reduced_challenge");
+        .expect("unable to flush the reduced challenge");
 
     println!("Reduced hash for a reduced challenge:");
-    for line in hash.as_slice().chunks(16) {
+    for line in reduced_hash(original_circuit_power as u8, reduced_circuit_power as u8)
+        .as_slice()
+        .chunks(16)
+    {
         print!("\t");
         for section in line.chunks(4) {
             for b in section {
@@ -104,10 +103,6 @@ fn main() {
         println!();
     }
 
-    reduced_accumulator
-        .serialize(&mut writable_map, UseCompression::No, &parameters)
-        .unwrap();
-
     // Get the hash of the contribution, so the user can compare later
     let output_readonly = writable_map
         .make_read_only()
@@ -0,0 +1,88 @@
+use powersoftau::{
+    errors::{self, Error, ErrorCategory},
+    path_template::take_template,
+    report::{read_attestations, CeremonyReport},
+    watch::Queue,
+};
+
+use std::path::Path;
+
+/// Removes `--attestations <directory>` from the arguments and returns the directory.
+fn take_attestations(args: &mut Vec<String>) -> Result<Option<String>, Error> {
+    let position = match args.iter().position(|arg| arg == "--attestations") {
+        Some(position) => position,
+        None => return Ok(None),
+    };
+    if position + 1 >= args.len() {
+        return Err(Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            "--attestations requires a directory",
+        ));
+    }
+    let directory = args[position + 1].clone();
+    args.drain(position..position + 2);
+
+    Ok(Some(directory))
+}
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut args: Vec<String> = std::env::args().collect();
+    let challenge_template = take_template(&mut args, "--challenge-template")?;
+    let response_template = take_template(&mut args, "--response-template")?;
+    let result_template = take_template(&mut args, "--result-template")?;
+    let attestations = take_attestations(&mut args)?;
+    if args.len() != 5 {
+        println!("Usage: \n<response_directory> <challenge_directory> <report.json> <report.md> [--attestations <directory>] [--challenge-template <template>] [--response-template <template>] [--result-template <template>]");
+        std::process::exit(exitcode::USAGE);
+    }
+    let mut queue = Queue::new(&args[1], &args[2]);
+    if let Some(template) = challenge_template {
+        queue.challenge_template = template;
+    }
+    if let Some(template) = response_template {
+        queue.response_template = template;
+    }
+    if let Some(template) = result_template {
+        queue.result_template = template;
+    }
+
+    let attestations = match attestations {
+        Some(directory) => read_attestations(Path::new(&directory))?,
+        None => vec![],
+    };
+    println!(
+        "Building the report from {} with {} attestation(s)",
+        queue.challenge_path(0).display(),
+        attestations.len()
+    );
+    let report = CeremonyReport::build(&queue, &attestations)?;
+
+    for (filename, contents) in [
+        (&args[3], report.to_json()),
+        (&args[4], report.to_markdown()),
+    ]
+    .iter()
+    {
+        std::fs::write(filename, contents)
+            .map_err(|e| Error::from(e).context(format!("unable to write {}", filename)))?;
+    }
+
+    let (attested_contributions, _) = report.attested();
+    println!(
+        "{} contribution(s), {} verified by the worker, {} attested. Wrote {} and {}.",
+        report.contributions(),
+        report.verified(),
+        attested_contributions,
+        args[3],
+        args[4]
+    );
+
+    Ok(())
+}
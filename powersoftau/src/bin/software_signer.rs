@@ -0,0 +1,27 @@
+use powersoftau::{errors::Error, parameters::CeremonyParams, signer::serve};
+
+use bellman_ce::pairing::bn256::Bn256;
+use rand::OsRng;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 3 {
+        println!("Usage: \n<circuit_power> <batch_size>");
+        println!("Answers the requests of compute_constrained --signer on the standard input and output, with a key sampled from the system randomness");
+        std::process::exit(exitcode::USAGE);
+    }
+    let circuit_power = args[1].parse().expect("could not parse circuit power");
+    let batch_size = args[2].parse().expect("could not parse batch size");
+
+    let parameters =
+        CeremonyParams::<Bn256>::validated(circuit_power, batch_size).unwrap_or_else(|e| e.exit());
+
+    // The standard output is for the answers, so messages go to the standard error
+    let mut rng = OsRng::new().expect("unable to access the system randomness");
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    serve(&parameters, &mut rng, stdin.lock(), stdout.lock())
+        .map_err(Error::from)
+        .unwrap_or_else(|e| e.context("the signer failed").exit());
+    eprintln!("The input was closed, the key of the signer is destroyed");
+}
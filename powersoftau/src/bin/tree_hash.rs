@@ -0,0 +1,54 @@
+use powersoftau::tree_hash::{TreeHash, DEFAULT_LEAF_SIZE};
+
+use memmap::*;
+use std::fs::OpenOptions;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 3 && args.len() != 4 {
+        println!("Usage: \n<challenge_or_response_file> <manifest_file> [<leaf_size>]");
+        std::process::exit(exitcode::USAGE);
+    }
+    let filename = &args[1];
+    let manifest_filename = &args[2];
+    let leaf_size = args
+        .get(3)
+        .map(|size| size.parse().expect("could not parse leaf size"))
+        .unwrap_or(DEFAULT_LEAF_SIZE);
+
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(filename)
+        .expect("unable open file in this directory");
+    let readable_map = unsafe {
+        MmapOptions::new()
+            .map(&reader)
+            .expect("unable to create a memory map for input")
+    };
+
+    let tree = TreeHash::compute(&readable_map, leaf_size);
+
+    let mut writer = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(manifest_filename)
+        .expect("unable to create manifest file in this directory");
+    tree.write(&mut writer).expect("unable to write manifest");
+
+    println!(
+        "Tree hash of `{}` over {} leaves of {} bytes:",
+        filename,
+        tree.leaves.len(),
+        leaf_size
+    );
+    for line in tree.root().chunks(16) {
+        print!("\t");
+        for section in line.chunks(4) {
+            for b in section {
+                print!("{:02x}", b);
+            }
+            print!(" ");
+        }
+        println!();
+    }
+}
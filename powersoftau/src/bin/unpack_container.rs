@@ -0,0 +1,52 @@
+use powersoftau::container::ContainerReader;
+
+use std::fs::OpenOptions;
+use std::io::{BufReader, BufWriter, Write};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 3 {
+        println!("Usage: \n<container_file> <output_file>");
+        std::process::exit(exitcode::USAGE);
+    }
+    let container_filename = &args[1];
+    let output_filename = &args[2];
+
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(container_filename)
+        .expect("unable open container file in this directory");
+    let mut container = match ContainerReader::new(BufReader::new(reader)) {
+        Ok(container) => container,
+        Err(e) => {
+            println!("Invalid container: {}", e);
+            std::process::exit(exitcode::DATAERR);
+        }
+    };
+    println!("Container has {} sections", container.sections().len());
+
+    let mut writer = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(output_filename)
+        .map(BufWriter::new)
+        .expect("unable to create output file in this directory");
+    loop {
+        match container.copy_next_section(&mut writer) {
+            Ok(Some(section)) => println!("\t{} ({} bytes) is valid", section.name, section.length),
+            Ok(None) => break,
+            Err(e) => {
+                drop(writer);
+                std::fs::remove_file(output_filename).expect("unable to remove output file");
+                println!("Invalid container: {}", e);
+                std::process::exit(exitcode::DATAERR);
+            }
+        }
+    }
+    writer.flush().expect("unable to write output file");
+
+    println!(
+        "All sections are valid, the file has been written to {}",
+        output_filename
+    );
+}
@@ -0,0 +1,71 @@
+use powersoftau::{
+    errors::{self, Error, ErrorCategory},
+    storage::storage_for,
+    transfer::{take_transfer_options, upload},
+};
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+const USAGE: &str =
+    "Usage: \nupload --file <file> --url <http[s]://...|s3://bucket/key|path> [--retries <n>]";
+
+/// Removes `<flag> <value>` from the arguments and returns the value
+fn take_required(args: &mut Vec<String>, flag: &str) -> Result<String, Error> {
+    let position = args
+        .iter()
+        .position(|arg| arg == flag)
+        .filter(|position| position + 1 < args.len())
+        .ok_or_else(|| {
+            Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                format!("{} is required", flag),
+            )
+        })?;
+    let value = args[position + 1].clone();
+    args.drain(position..position + 2);
+
+    Ok(value)
+}
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut args: Vec<String> = std::env::args().collect();
+    let options = take_transfer_options(&mut args)?;
+    let (file, url) = match (
+        take_required(&mut args, "--file"),
+        take_required(&mut args, "--url"),
+    ) {
+        (Ok(file), Ok(url)) if args.len() == 1 => (file, url),
+        _ => {
+            println!("{}", USAGE);
+            std::process::exit(exitcode::USAGE);
+        }
+    };
+
+    println!("Uploading {} to {}...", file, url);
+    if url.starts_with("http://") || url.starts_with("https://") {
+        upload(Path::new(&file), &url, &options)?;
+    } else {
+        // Object storages take large files in parts, see `storage::s3`
+        let (storage, path) = storage_for(&url)?;
+        let copy = || -> Result<(), Error> {
+            let mut writer = storage.open_write(&path)?;
+            io::copy(&mut File::open(&file)?, &mut writer)?;
+            writer.finish()?;
+            Ok(())
+        };
+        copy().map_err(|e| e.context(format!("unable to upload {}", file)))?;
+    }
+
+    println!("Done! {} has been uploaded.", file);
+
+    Ok(())
+}
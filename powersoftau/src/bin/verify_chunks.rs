@@ -0,0 +1,151 @@
+use powersoftau::{
+    batched_accumulator::BatchedAccumulator,
+    errors::{self, Error, ErrorCategory},
+    parameters::{
+        check_compression, expected_sizes, CeremonyParams, CheckForCorrectness, UseCompression,
+    },
+    spec::take_spec,
+};
+
+use bellman_ce::pairing::bn256::Bn256;
+use memmap::*;
+use std::fs::OpenOptions;
+
+const CHALLENGE_IS_COMPRESSED: UseCompression = UseCompression::No;
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    // Also check every chunk against the last power of the previous chunk
+    let check_boundaries = args.iter().any(|arg| arg == "--check-boundaries");
+    args.retain(|arg| arg != "--check-boundaries");
+    // Also audit the links between all consecutive chunks at once, see `audit_boundaries`
+    let audit_boundaries = args.iter().any(|arg| arg == "--audit-boundaries");
+    args.retain(|arg| arg != "--audit-boundaries");
+    let spec = take_spec::<Bn256>(&mut args, "bn256").unwrap_or_else(|e| e.exit());
+    // The spec takes the place of the circuit power and the batch size
+    let expected_args = if spec.is_some() { 3 } else { 5 };
+    if args.len() != expected_args {
+        println!("Usage: \n<challenge_file> (<circuit_power> <batch_size> | --spec <spec_file>) <chunk_indices|all> [--check-boundaries] [--audit-boundaries]");
+        println!("Chunk indices are comma separated, e.g. `0,3,4`");
+        std::process::exit(exitcode::USAGE);
+    }
+    let challenge_filename = &args[1];
+    let parameters = match spec {
+        Some(parameters) => parameters,
+        None => {
+            let circuit_power = args[2].parse().expect("could not parse circuit power");
+            let batch_size = args[3].parse().expect("could not parse batch size");
+            CeremonyParams::<Bn256>::validated(circuit_power, batch_size)
+                .unwrap_or_else(|e| e.exit())
+        }
+    };
+    let chunks = BatchedAccumulator::chunk_ranges(&parameters).len();
+
+    let chunk_argument = &args[expected_args - 1];
+    let chunk_indices: Vec<usize> = if chunk_argument == "all" {
+        (0..chunks).collect()
+    } else {
+        chunk_argument
+            .split(',')
+            .map(|index| index.trim().parse().expect("could not parse chunk index"))
+            .collect()
+    };
+
+    println!(
+        "Will verify {} of {} chunks of an accumulator for 2^{} powers of tau",
+        chunk_indices.len(),
+        chunks,
+        parameters.size
+    );
+
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(challenge_filename)
+        .expect("unable open challenge file in this directory");
+    check_compression(&reader, UseCompression::No, &parameters).unwrap_or_else(|e| {
+        Error::from(e)
+            .context("unable to use the challenge file")
+            .exit()
+    });
+
+    {
+        let metadata = reader
+            .metadata()
+            .expect("unable to get filesystem metadata for challenge file");
+        let expected_challenge_length = expected_sizes(&parameters, UseCompression::No).challenge;
+        if metadata.len() != (expected_challenge_length as u64) {
+            panic!(
+                "The size of challenge file should be {}, but it's {}, so something isn't right.",
+                expected_challenge_length,
+                metadata.len()
+            );
+        }
+    }
+
+    let challenge_readable_map = unsafe {
+        MmapOptions::new()
+            .map(&reader)
+            .expect("unable to create a memory map for input")
+    };
+
+    let verify_chunks = if check_boundaries {
+        BatchedAccumulator::verify_chunks_with_boundaries
+    } else {
+        BatchedAccumulator::verify_chunks
+    };
+    let valid = verify_chunks(
+        &challenge_readable_map,
+        CHALLENGE_IS_COMPRESSED,
+        CheckForCorrectness::Yes,
+        &chunk_indices,
+        &parameters,
+    )
+    .unwrap_or_else(|e| {
+        Error::from(e)
+            .context("unable to read the challenge")
+            .exit()
+    });
+
+    if valid && audit_boundaries {
+        let boundaries = BatchedAccumulator::audit_boundaries(
+            &challenge_readable_map,
+            CHALLENGE_IS_COMPRESSED,
+            CheckForCorrectness::Yes,
+            &parameters,
+        )
+        .unwrap_or_else(|e| {
+            Error::from(e)
+                .context("unable to read the challenge")
+                .exit()
+        });
+        println!("Chunk boundaries:");
+        for boundary in boundaries.iter() {
+            println!(
+                "\tchunk {} to {} at power {}: {}",
+                boundary.chunk,
+                boundary.chunk + 1,
+                boundary.index,
+                if boundary.valid { "ok" } else { "broken" }
+            );
+        }
+        if boundaries.iter().any(|boundary| !boundary.valid) {
+            Error::new(
+                errors::INVALID_CONTRIBUTION,
+                ErrorCategory::Verification,
+                "Verification failed, some of the chunks don't come from the same contribution.",
+            )
+            .exit();
+        }
+    }
+
+    if !valid {
+        Error::new(
+            errors::INVALID_CONTRIBUTION,
+            ErrorCategory::Verification,
+            "Verification failed, some of the chunks are invalid.",
+        )
+        .exit();
+    }
+
+    println!("Verification succeeded!");
+}
@@ -0,0 +1,90 @@
+use powersoftau::{
+    batched_accumulator::BatchedAccumulator,
+    errors::{self, Error, ErrorCategory},
+    parameters::{
+        check_compression, expected_sizes, CeremonyParams, CheckForCorrectness, ElementType,
+        UseCompression,
+    },
+};
+
+use bellman_ce::pairing::bn256::Bn256;
+use memmap::*;
+use std::fs::OpenOptions;
+
+const CHALLENGE_IS_COMPRESSED: UseCompression = UseCompression::No;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 6 {
+        println!("Usage: \n<challenge_file> <circuit_power> <batch_size> <tau_g1|tau_g2|alpha_g1|beta_g1|beta_g2> <index>");
+        std::process::exit(exitcode::USAGE);
+    }
+    let challenge_filename = &args[1];
+    let circuit_power = args[2].parse().expect("could not parse circuit power");
+    let batch_size = args[3].parse().expect("could not parse batch size");
+    let element_type: ElementType = args[4].parse().unwrap_or_else(|e: Error| e.exit());
+    let index = args[5].parse().expect("could not parse element index");
+
+    let parameters =
+        CeremonyParams::<Bn256>::validated(circuit_power, batch_size).unwrap_or_else(|e| e.exit());
+
+    println!(
+        "Will verify {:?} element {} of an accumulator for 2^{} powers of tau",
+        element_type, index, parameters.size
+    );
+
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(challenge_filename)
+        .expect("unable open challenge file in this directory");
+    check_compression(&reader, UseCompression::No, &parameters).unwrap_or_else(|e| {
+        Error::from(e)
+            .context("unable to use the challenge file")
+            .exit()
+    });
+
+    {
+        let metadata = reader
+            .metadata()
+            .expect("unable to get filesystem metadata for challenge file");
+        let expected_challenge_length = expected_sizes(&parameters, UseCompression::No).challenge;
+        if metadata.len() != (expected_challenge_length as u64) {
+            panic!(
+                "The size of challenge file should be {}, but it's {}, so something isn't right.",
+                expected_challenge_length,
+                metadata.len()
+            );
+        }
+    }
+
+    let challenge_readable_map = unsafe {
+        MmapOptions::new()
+            .map(&reader)
+            .expect("unable to create a memory map for input")
+    };
+
+    let valid = BatchedAccumulator::verify_element(
+        &challenge_readable_map,
+        CHALLENGE_IS_COMPRESSED,
+        CheckForCorrectness::Yes,
+        element_type,
+        index,
+        &parameters,
+    )
+    .unwrap_or_else(|e| {
+        Error::from(e)
+            .context("unable to read the challenge")
+            .exit()
+    });
+
+    if !valid {
+        Error::new(
+            errors::INVALID_CONTRIBUTION,
+            ErrorCategory::Verification,
+            "Verification failed, the element is invalid.",
+        )
+        .exit();
+    }
+
+    println!("Verification succeeded!");
+}
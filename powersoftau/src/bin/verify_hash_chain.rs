@@ -0,0 +1,54 @@
+use powersoftau::{errors::Error, path_template::take_template, watch::Queue};
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut args: Vec<String> = std::env::args().collect();
+    let challenge_template = take_template(&mut args, "--challenge-template")?;
+    let response_template = take_template(&mut args, "--response-template")?;
+    if args.len() != 3 {
+        println!("Usage: \n<response_directory> <challenge_directory> [--challenge-template <template>] [--response-template <template>]");
+        std::process::exit(exitcode::USAGE);
+    }
+    let mut queue = Queue::new(&args[1], &args[2]);
+    if let Some(template) = challenge_template {
+        queue.challenge_template = template;
+    }
+    if let Some(template) = response_template {
+        queue.response_template = template;
+    }
+
+    println!(
+        "Checking the hash chain from {}",
+        queue.challenge_path(0).display()
+    );
+    let audit = queue.audit_hash_chain(|round, challenge_hash, response_hash| {
+        println!("round {}", round);
+        println!("\tchallenge {}", hex::encode(&challenge_hash[..]));
+        if let Some(response_hash) = response_hash {
+            println!("\tresponse  {}", hex::encode(&response_hash[..]));
+        }
+    })?;
+
+    println!(
+        "The hash chain holds, {} response(s) checked. No contribution was verified.",
+        audit.responses
+    );
+    if audit.response_pending {
+        println!(
+            "The response to round {} has no next challenge yet.",
+            audit.round
+        );
+    } else {
+        println!(
+            "The challenge of round {} is waiting for a response.",
+            audit.round
+        );
+    }
+
+    Ok(())
+}
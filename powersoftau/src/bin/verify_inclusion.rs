@@ -0,0 +1,52 @@
+use powersoftau::{
+    errors::{self, Error, ErrorCategory},
+    inclusion::InclusionStatement,
+    path_template::take_template,
+    watch::Queue,
+};
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut args: Vec<String> = std::env::args().collect();
+    let challenge_template = take_template(&mut args, "--challenge-template")?;
+    let response_template = take_template(&mut args, "--response-template")?;
+    if args.len() != 4 {
+        println!("Usage: \n<response_directory> <challenge_directory> <statement_file> [--challenge-template <template>] [--response-template <template>]");
+        std::process::exit(exitcode::USAGE);
+    }
+    let mut queue = Queue::new(&args[1], &args[2]);
+    if let Some(template) = challenge_template {
+        queue.challenge_template = template;
+    }
+    if let Some(template) = response_template {
+        queue.response_template = template;
+    }
+    let statement_path = &args[3];
+    let text = std::fs::read_to_string(statement_path)
+        .map_err(|e| Error::from(e).context("unable to read the statement"))?;
+    let statement = InclusionStatement::parse(&text)
+        .map_err(|e| e.context(format!("invalid statement {}", statement_path)))?;
+
+    if queue.inclusion_statement(&statement.contribution)? != Some(statement.clone()) {
+        return Err(Error::new(
+            errors::MISSING_CONTRIBUTION,
+            ErrorCategory::Verification,
+            format!(
+                "the statement {} doesn't hold for the hash chain",
+                statement_path
+            ),
+        )
+        .with_hint("the response must be at the stated round, between the stated challenges"));
+    }
+    println!(
+        "The statement {} holds, the response is the contribution to round {}. No contribution was verified.",
+        statement_path, statement.round
+    );
+
+    Ok(())
+}
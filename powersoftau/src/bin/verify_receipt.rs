@@ -0,0 +1,63 @@
+use powersoftau::{
+    errors::{self, Error, ErrorCategory},
+    receipt::Receipt,
+};
+
+use bellman_ce::pairing::bn256::Bn256;
+use std::fs::OpenOptions;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 2 {
+        println!("Usage: \n<receipt_file>");
+        std::process::exit(exitcode::USAGE);
+    }
+    let receipt_filename = &args[1];
+
+    let mut reader = OpenOptions::new()
+        .read(true)
+        .open(receipt_filename)
+        .unwrap_or_else(|e| Error::from(e).context("unable to open receipt file").exit());
+    let receipt = Receipt::<Bn256>::deserialize(&mut reader)
+        .unwrap_or_else(|e| Error::from(e).context("unable to read receipt").exit());
+
+    for (name, hash) in [
+        ("challenge", &receipt.challenge_hash),
+        ("response", &receipt.response_hash),
+    ]
+    .iter()
+    {
+        println!("Hash of the `{}` file:", name);
+        for line in hash.chunks(16) {
+            print!("\t");
+            for section in line.chunks(4) {
+                for b in section {
+                    print!("{:02x}", b);
+                }
+                print!(" ");
+            }
+            println!();
+        }
+    }
+
+    if let Some(ref domain) = receipt.domain {
+        println!(
+            "The key is bound to round {} of {} {} with 2^{} powers",
+            domain.round, domain.curve, domain.proving_system, domain.power
+        );
+    }
+    if !receipt.trapdoors.is_full() {
+        println!("The contribution only updates {}", receipt.trapdoors);
+    }
+
+    if !receipt.verify() {
+        Error::new(
+            errors::INVALID_CONTRIBUTION,
+            ErrorCategory::Verification,
+            "The public key of the receipt does not prove knowledge of its secrets for this challenge.",
+        )
+        .exit();
+    }
+
+    println!("Receipt is valid. The response itself still needs to be verified in full.");
+}
@@ -0,0 +1,61 @@
+use powersoftau::round_state::RoundState;
+
+use memmap::*;
+use std::fs::OpenOptions;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 3 && args.len() != 4 {
+        println!("Usage: \n<round_state_file> <challenge_file> [<response_file>]");
+        std::process::exit(exitcode::USAGE);
+    }
+    let round_state_filename = &args[1];
+    let challenge_filename = &args[2];
+
+    let mut reader = OpenOptions::new()
+        .read(true)
+        .open(round_state_filename)
+        .expect("unable open round state file in this directory");
+    let round_state = RoundState::read(&mut reader).expect("unable to read round state");
+
+    println!(
+        "Round state is for 2^{} powers of tau with batch size {}, manifest signature of {} bytes",
+        round_state.size,
+        round_state.batch_size,
+        round_state.manifest_signature.len()
+    );
+
+    let challenge_reader = OpenOptions::new()
+        .read(true)
+        .open(challenge_filename)
+        .expect("unable open challenge file in this directory");
+    let challenge_readable_map = unsafe {
+        MmapOptions::new()
+            .map(&challenge_reader)
+            .expect("unable to create a memory map for input")
+    };
+
+    if !round_state.verify_challenge(&challenge_readable_map) {
+        println!("The challenge does not match the hash of the round state.");
+        panic!("CEREMONY IS NOT CONTINUOUS!!!");
+    }
+    println!("The challenge continues the ceremony from the round state.");
+
+    if let Some(response_filename) = args.get(3) {
+        let response_reader = OpenOptions::new()
+            .read(true)
+            .open(response_filename)
+            .expect("unable open response file in this directory");
+        let response_readable_map = unsafe {
+            MmapOptions::new()
+                .map(&response_reader)
+                .expect("unable to create a memory map for input")
+        };
+
+        if !round_state.verify_response(&response_readable_map) {
+            println!("The response is not based on the challenge of the round state.");
+            panic!("CEREMONY IS NOT CONTINUOUS!!!");
+        }
+        println!("The response hash-chains to the round state.");
+    }
+}
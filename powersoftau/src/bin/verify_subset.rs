@@ -0,0 +1,49 @@
+use powersoftau::errors::{self, Error, ErrorCategory};
+use powersoftau::merkle::SubsetProof;
+
+use std::fs::{self, OpenOptions};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 4 {
+        println!("Usage: \n<proof_file> <merkle_root> <subset_file>");
+        std::process::exit(exitcode::USAGE);
+    }
+    let proof_filename = &args[1];
+    let subset_filename = &args[3];
+    let mut root = [0; 64];
+    hex::decode_to_slice(&args[2], &mut root[..]).unwrap_or_else(|_| {
+        Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            format!("invalid Merkle root {}", args[2]),
+        )
+        .with_hint("the root is 128 hexadecimal digits, as printed by merkle_root")
+        .exit()
+    });
+
+    let mut proof_reader = OpenOptions::new()
+        .read(true)
+        .open(proof_filename)
+        .expect("unable open proof file in this directory");
+    let proof = SubsetProof::read(&mut proof_reader)
+        .unwrap_or_else(|e| Error::from(e).context("invalid subset proof").exit());
+
+    if !proof.verify(&root) {
+        Error::new(
+            errors::HASH_MISMATCH,
+            ErrorCategory::Verification,
+            "The subset proof does not match the Merkle root.",
+        )
+        .with_hint("check that the root was computed with the leaf size of the proof")
+        .exit();
+    }
+
+    fs::write(subset_filename, proof.subset())
+        .unwrap_or_else(|e| Error::from(e).context("unable to write the subset").exit());
+
+    println!(
+        "The subset proof is valid, wrote bytes {} to {} of the accumulator to `{}`.",
+        proof.range.start, proof.range.end, subset_filename
+    );
+}
@@ -1,33 +1,331 @@
 use powersoftau::{
+    backend::{take_report_backend, BackendReport},
     batched_accumulator::BatchedAccumulator,
-    keypair::PublicKey,
-    parameters::{CeremonyParams, CheckForCorrectness, UseCompression},
-    utils::calculate_hash,
+    beacon::{take_beacon, Beacon, BeaconOpts},
+    container::write_accumulator_container,
+    disk::take_read_options,
+    errors::{self, Error, ErrorCategory},
+    hash_file::{check_expected_hash, take_expected_hash},
+    hooks::{take_hooks, HookContext},
+    incremental::{follow_response, take_follow_options, IncrementalVerifier},
+    keypair::{take_round, KeyDomain, PublicKey},
+    metrics::take_metrics,
+    parameters::{
+        check_compression, expected_sizes, take_check_input, take_generators, take_subgroup_check,
+        take_trapdoors, CeremonyParams, CheckForCorrectness, UseCompression,
+    },
+    profiling,
+    spec::take_spec,
+    storage::{take_output_policy, LocalFile},
+    utils::{calculate_hash, read_hash_header, write_hash_header},
+    verification_cursor::VerificationCursor,
 };
 
 use bellman_ce::pairing::bn256::Bn256;
 use memmap::*;
 use std::fs::OpenOptions;
+use std::path::Path;
 
-use std::io::{Read, Write};
+use std::io::{BufWriter, Write};
 
 const PREVIOUS_CHALLENGE_IS_COMPRESSED: UseCompression = UseCompression::No;
 const CONTRIBUTION_IS_COMPRESSED: UseCompression = UseCompression::Yes;
 const COMPRESS_NEW_CHALLENGE: UseCompression = UseCompression::No;
 
+/// Removes `--cross-check-sample N` from the arguments and returns N
+fn take_cross_check_sample(args: &mut Vec<String>) -> Option<usize> {
+    let position = args.iter().position(|arg| arg == "--cross-check-sample")?;
+    if position + 1 >= args.len() {
+        println!("--cross-check-sample requires the number of samples");
+        std::process::exit(exitcode::USAGE);
+    }
+    let sample = args[position + 1].parse().unwrap_or_else(|_| {
+        Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            "could not parse cross-check sample size",
+        )
+        .exit()
+    });
+    args.drain(position..position + 2);
+
+    Some(sample)
+}
+
+/// Removes `--cross-check-seed <hex>` from the arguments and returns the seed
+fn take_cross_check_seed(args: &mut Vec<String>) -> Option<[u8; 32]> {
+    let position = args.iter().position(|arg| arg == "--cross-check-seed")?;
+    if position + 1 >= args.len() {
+        println!("--cross-check-seed requires a seed");
+        std::process::exit(exitcode::USAGE);
+    }
+    let mut seed = [0; 32];
+    hex::decode_to_slice(&args[position + 1], &mut seed[..]).unwrap_or_else(|_| {
+        Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            "could not parse cross-check seed",
+        )
+        .with_hint("the seed is 32 bytes in hex")
+        .exit()
+    });
+    args.drain(position..position + 2);
+
+    Some(seed)
+}
+
+/// Removes `--cross-check-report <file>` from the arguments and returns the file
+fn take_cross_check_report(args: &mut Vec<String>) -> Option<String> {
+    let position = args.iter().position(|arg| arg == "--cross-check-report")?;
+    if position + 1 >= args.len() {
+        println!("--cross-check-report requires a file");
+        std::process::exit(exitcode::USAGE);
+    }
+    let report_filename = args[position + 1].clone();
+    args.drain(position..position + 2);
+
+    Some(report_filename)
+}
+
+/// Removes `--container <file>` from the arguments and returns the file
+fn take_container(args: &mut Vec<String>) -> Option<String> {
+    let position = args.iter().position(|arg| arg == "--container")?;
+    if position + 1 >= args.len() {
+        println!("--container requires a file");
+        std::process::exit(exitcode::USAGE);
+    }
+    let container_filename = args[position + 1].clone();
+    args.drain(position..position + 2);
+
+    Some(container_filename)
+}
+
+/// Removes `--compressed-output <file>` from the arguments and returns the file
+fn take_compressed_output(args: &mut Vec<String>) -> Option<String> {
+    let position = args.iter().position(|arg| arg == "--compressed-output")?;
+    if position + 1 >= args.len() {
+        println!("--compressed-output requires a file");
+        std::process::exit(exitcode::USAGE);
+    }
+    let compressed_filename = args[position + 1].clone();
+    args.drain(position..position + 2);
+
+    Some(compressed_filename)
+}
+
+/// Creates an output file of `length` bytes that starts with `hash`, the hash of the response
+/// it continues, and maps it for writing
+fn create_new_challenge(filename: &str, length: usize, hash: &[u8]) -> Result<MmapMut, Error> {
+    let writer = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(filename)
+        .map_err(|e| Error::from(e).context(format!("unable to create {}", filename)))?;
+    writer
+        .set_len(length as u64)
+        .map_err(|e| Error::from(e).context("must make output file large enough"))?;
+
+    let mut map = unsafe {
+        MmapOptions::new()
+            .map_mut(&writer)
+            .map_err(|e| Error::from(e).context("unable to create a memory map for output"))?
+    };
+    write_hash_header(&mut map, hash)
+        .and_then(|_| map.flush())
+        .map_err(|e| Error::from(e).context("unable to write a default hash to mmap"))?;
+
+    Ok(map)
+}
+
+/// Opens the response, which must be complete, and maps it for reading
+fn open_response(filename: &str, parameters: &CeremonyParams<Bn256>) -> Result<Mmap, Error> {
+    let response_reader = OpenOptions::new()
+        .read(true)
+        .open(filename)
+        .map_err(|e| Error::from(e).context("unable open response file in this directory"))?;
+    check_compression(&response_reader, CONTRIBUTION_IS_COMPRESSED, parameters)
+        .map_err(|e| Error::from(e).context("unable to use the response file"))?;
+
+    {
+        let metadata = response_reader.metadata().map_err(|e| {
+            Error::from(e).context("unable to get filesystem metadata for response file")
+        })?;
+        let expected_response_length =
+            expected_sizes(parameters, CONTRIBUTION_IS_COMPRESSED).response;
+        if metadata.len() != (expected_response_length as u64) {
+            return Err(Error::new(
+                errors::INVALID_FILE_SIZE,
+                ErrorCategory::InvalidInput,
+                format!(
+                    "The size of response file should be {}, but it's {}, so something isn't right.",
+                    expected_response_length,
+                    metadata.len()
+                ),
+            )
+            .with_hint("check that the circuit power matches the ceremony and the file was fully transferred"));
+        }
+    }
+
+    unsafe {
+        MmapOptions::new()
+            .map(&response_reader)
+            .map_err(|e| Error::from(e).context("unable to create a memory map for input"))
+    }
+}
+
+/// Removes the files written while verifying in a single pass, unless the contribution was
+/// found valid
+#[derive(Default)]
+struct Rollback {
+    filenames: Vec<String>,
+    keep: bool,
+}
+
+impl Drop for Rollback {
+    fn drop(&mut self) {
+        if self.keep {
+            return;
+        }
+        for filename in &self.filenames {
+            if std::fs::remove_file(filename).is_ok() {
+                println!("Removed {}", filename);
+            }
+        }
+    }
+}
+
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() != 6 {
-        println!("Usage: \n<challenge_file> <response_file> <new_challenge_file> <circuit_power> <batch_size>");
+    let mut args: Vec<String> = std::env::args().collect();
+    let metrics = take_metrics(&mut args).unwrap_or_else(|e| e.exit());
+    // Failed verifications are exported too, as rejected contributions are what a fleet of
+    // verifiers is watched for
+    let result = run(args);
+    metrics.export("verify_transform_constrained", result.is_ok());
+    if let Err(e) = result {
+        e.exit();
+    }
+}
+
+fn run(mut args: Vec<String>) -> Result<(), Error> {
+    let cross_check_sample = take_cross_check_sample(&mut args);
+    let cross_check_seed = take_cross_check_seed(&mut args);
+    let cross_check_report = take_cross_check_report(&mut args);
+    let container_filename = take_container(&mut args);
+    let report_backend = take_report_backend(&mut args);
+    let compressed_filename = take_compressed_output(&mut args);
+    let read_options = take_read_options(&mut args)?;
+    // Verify the response while it is uploaded, see `IncrementalVerifier`
+    let follow_options = take_follow_options(&mut args)?;
+    let hooks = take_hooks(&mut args)?;
+    let expected_hash_filename = take_expected_hash(&mut args)?;
+    let check_challenge = take_check_input(&mut args, CheckForCorrectness::No)?;
+    let subgroup_check = take_subgroup_check(&mut args)?;
+    let generators = take_generators(&mut args)?;
+    let trapdoors = take_trapdoors(&mut args)?;
+    let spec = take_spec::<Bn256>(&mut args, "bn256")?;
+    let output_policy = take_output_policy(&mut args)?;
+    let beacon = take_beacon(&mut args)?;
+    let round = take_round(&mut args)?;
+    // Also accept keys without the domain of the round, while contributors migrate
+    let accept_legacy_keys = args.iter().any(|arg| arg == "--accept-legacy-keys");
+    args.retain(|arg| arg != "--accept-legacy-keys");
+    // Write the new challenge while the response is verified, instead of in a second pass
+    let single_pass = args.iter().any(|arg| arg == "--single-pass");
+    args.retain(|arg| arg != "--single-pass");
+    // Keep the progress of the verification next to the new challenge, see `VerificationCursor`
+    let resume = args.iter().any(|arg| arg == "--resume");
+    args.retain(|arg| arg != "--resume");
+    // The spec takes the place of the circuit power and the batch size
+    let expected_args = if spec.is_some() { 4 } else { 6 };
+    if args.len() != expected_args {
+        println!("Usage: \n<challenge_file> <response_file> <new_challenge_file> (<circuit_power> <batch_size> | --spec <spec_file>) [--cross-check-sample <N>] [--cross-check-seed <hex>] [--cross-check-report <file>] [--container <new_challenge_container>] [--compressed-output <compressed_new_challenge>] [--hash-file <file>] [--attestation <file>] [--webhook <http[s]://host/path>] [--hash-format <hex|b2sum|binary|json>] [--expected-hash <response_hash_file>] [--check-input <full|in-group|non-zero|none>] [--subgroup-check <full-order|cofactor-mul>] [--generators <file>] [--trapdoors <tau,alpha,beta>] [--no-clobber|--overwrite|--versioned] [--report-backend] [--beacon-hash <hex> --beacon-iterations <exp> [--beacon-height <n> --beacon-source <command>]] [--round <n> [--accept-legacy-keys]] [--read-ahead <chunks>] [--prefetch] [--max-read-rate <MiB/s>] [--single-pass | --resume | --follow [--poll-interval <ms>] [--idle-timeout <s>]] [--metrics-textfile <file>] [--metrics-push <http[s]://host/path>]");
         std::process::exit(exitcode::USAGE);
     }
-    let challenge_filename = &args[1];
-    let response_filename = &args[2];
-    let new_challenge_filename = &args[3];
-    let circuit_power = args[4].parse().expect("could not parse circuit power");
-    let batch_size = args[5].parse().expect("could not parse batch size");
+    if cross_check_sample.is_some() && !cfg!(feature = "cross-check") {
+        println!("--cross-check-sample requires building with the `cross-check` feature");
+        std::process::exit(exitcode::USAGE);
+    }
+    if (cross_check_seed.is_some() || cross_check_report.is_some()) && cross_check_sample.is_none()
+    {
+        println!("--cross-check-seed and --cross-check-report require --cross-check-sample");
+        std::process::exit(exitcode::USAGE);
+    }
+    if accept_legacy_keys && round.is_none() {
+        println!("--accept-legacy-keys requires --round");
+        std::process::exit(exitcode::USAGE);
+    }
+    if follow_options.is_some() && single_pass {
+        println!("--follow can't be used with --single-pass");
+        std::process::exit(exitcode::USAGE);
+    }
+    if resume && (single_pass || follow_options.is_some()) {
+        println!("--resume can't be used with --single-pass or --follow");
+        std::process::exit(exitcode::USAGE);
+    }
+    if resume && args[3].starts_with("s3://") {
+        println!("--resume requires a local new challenge file");
+        std::process::exit(exitcode::USAGE);
+    }
+    if follow_options.is_some() && args[2].starts_with("s3://") {
+        println!("--follow requires a local response file");
+        std::process::exit(exitcode::USAGE);
+    }
+    if spec.is_some() && (subgroup_check.is_some() || generators.is_some()) {
+        println!("--subgroup-check and --generators are taken from the spec");
+        std::process::exit(exitcode::USAGE);
+    }
+    // A beacon value that isn't the hash of its block is rejected before anything is read
+    if let Some(beacon) = &beacon {
+        beacon.check()?;
+    }
+    // Remote files are staged locally, as they are memory mapped
+    let challenge_file = LocalFile::fetch(&args[1])?;
+    let challenge_filename = challenge_file.path();
+    let response_file = LocalFile::fetch(&args[2])?;
+    let response_filename = response_file.path();
+    let new_challenge_file = LocalFile::output(&args[3], output_policy)?;
+    let new_challenge_filename = new_challenge_file.path();
+    let compressed_file = match compressed_filename {
+        Some(ref compressed_filename) => {
+            Some(LocalFile::output(compressed_filename, output_policy)?)
+        }
+        None => None,
+    };
+    let compressed_filename = compressed_file.as_ref().map(|file| file.path().to_string());
+    let mut parameters = match spec {
+        Some(parameters) => parameters,
+        None => {
+            let circuit_power = args[4].parse().map_err(|_| {
+                Error::new(
+                    errors::INVALID_ARGUMENT,
+                    ErrorCategory::InvalidInput,
+                    "could not parse circuit power",
+                )
+            })?;
+            let batch_size = args[5].parse().map_err(|_| {
+                Error::new(
+                    errors::INVALID_ARGUMENT,
+                    ErrorCategory::InvalidInput,
+                    "could not parse batch size",
+                )
+            })?;
+            CeremonyParams::<Bn256>::validated(circuit_power, batch_size)?
+        }
+    };
+    if let Some(subgroup_check) = subgroup_check {
+        parameters.curve.subgroup_check = subgroup_check;
+    }
+    if let Some(generators) = generators {
+        parameters.generators = generators;
+    }
+    if let Some(trapdoors) = trapdoors {
+        parameters.trapdoors = trapdoors;
+    }
 
-    let parameters = CeremonyParams::<Bn256>::new(circuit_power, batch_size);
+    if report_backend {
+        println!("{}", BackendReport::detect());
+    }
 
     println!(
         "Will verify and decompress a contribution to accumulator for 2^{} powers of tau",
@@ -38,58 +336,56 @@ fn main() {
     let challenge_reader = OpenOptions::new()
         .read(true)
         .open(challenge_filename)
-        .expect("unable open challenge file in this directory");
+        .map_err(|e| Error::from(e).context("unable open challenge file in this directory"))?;
+    check_compression(
+        &challenge_reader,
+        PREVIOUS_CHALLENGE_IS_COMPRESSED,
+        &parameters,
+    )
+    .map_err(|e| Error::from(e).context("unable to use the challenge file"))?;
 
     {
-        let metadata = challenge_reader
-            .metadata()
-            .expect("unable to get filesystem metadata for challenge file");
-        let expected_challenge_length = match PREVIOUS_CHALLENGE_IS_COMPRESSED {
-            UseCompression::Yes => parameters.contribution_size - parameters.public_key_size,
-            UseCompression::No => parameters.accumulator_size,
-        };
+        let metadata = challenge_reader.metadata().map_err(|e| {
+            Error::from(e).context("unable to get filesystem metadata for challenge file")
+        })?;
+        let expected_challenge_length =
+            expected_sizes(&parameters, PREVIOUS_CHALLENGE_IS_COMPRESSED).challenge;
         if metadata.len() != (expected_challenge_length as u64) {
-            panic!(
-                "The size of challenge file should be {}, but it's {}, so something isn't right.",
-                expected_challenge_length,
-                metadata.len()
-            );
+            return Err(Error::new(
+                errors::INVALID_FILE_SIZE,
+                ErrorCategory::InvalidInput,
+                format!(
+                    "The size of challenge file should be {}, but it's {}, so something isn't right.",
+                    expected_challenge_length,
+                    metadata.len()
+                ),
+            )
+            .with_hint("check that the circuit power matches the ceremony and the file was fully transferred"));
         }
     }
 
     let challenge_readable_map = unsafe {
         MmapOptions::new()
             .map(&challenge_reader)
-            .expect("unable to create a memory map for input")
+            .map_err(|e| Error::from(e).context("unable to create a memory map for input"))?
     };
 
-    // Try to load response file from disk.
-    let response_reader = OpenOptions::new()
-        .read(true)
-        .open(response_filename)
-        .expect("unable open response file in this directory");
-
-    {
-        let metadata = response_reader
-            .metadata()
-            .expect("unable to get filesystem metadata for response file");
-        let expected_response_length = match CONTRIBUTION_IS_COMPRESSED {
-            UseCompression::Yes => parameters.contribution_size,
-            UseCompression::No => parameters.accumulator_size + parameters.public_key_size,
-        };
-        if metadata.len() != (expected_response_length as u64) {
-            panic!(
-                "The size of response file should be {}, but it's {}, so something isn't right.",
-                expected_response_length,
-                metadata.len()
-            );
+    // A followed response is verified as it is received, and only mapped once complete
+    let mut incremental_verifier = None;
+    let response_readable_map = match follow_options {
+        Some(follow_options) => {
+            println!("Waiting for the response, verifying it as it is received...");
+            let verifier = incremental_verifier.get_or_insert(IncrementalVerifier::new(
+                &challenge_readable_map,
+                PREVIOUS_CHALLENGE_IS_COMPRESSED,
+                CONTRIBUTION_IS_COMPRESSED,
+                check_challenge,
+                CheckForCorrectness::Yes,
+                &parameters,
+            ));
+            follow_response(verifier, Path::new(response_filename), follow_options)?
         }
-    }
-
-    let response_readable_map = unsafe {
-        MmapOptions::new()
-            .map(&response_reader)
-            .expect("unable to create a memory map for input")
+        None => open_response(response_filename, &parameters)?,
     };
 
     println!("Calculating previous challenge hash...");
@@ -112,13 +408,9 @@ fn main() {
 
     // Check the hash chain - a new response must be based on the previous challenge!
     {
-        let mut response_challenge_hash = [0; 64];
-        let mut memory_slice = response_readable_map
-            .get(0..64)
-            .expect("must read point data from file");
-        memory_slice
-            .read_exact(&mut response_challenge_hash)
-            .expect("couldn't read hash of challenge file from response file");
+        let response_challenge_hash = read_hash_header(&response_readable_map).map_err(|e| {
+            Error::from(e).context("couldn't read hash of challenge file from response file")
+        })?;
 
         println!("`response` was based on the hash:");
         for line in response_challenge_hash.chunks(16) {
@@ -133,11 +425,20 @@ fn main() {
         }
 
         if &response_challenge_hash[..] != current_accumulator_hash.as_slice() {
-            panic!("Hash chain failure. This is not the right response.");
+            return Err(Error::new(
+                errors::HASH_CHAIN_BROKEN,
+                ErrorCategory::Verification,
+                "Hash chain failure. This is not the right response.",
+            )
+            .with_hint("the response must be computed from the current challenge file"));
         }
     }
 
-    let response_hash = calculate_hash(&response_readable_map);
+    // A followed response was hashed as it was received
+    let response_hash = incremental_verifier
+        .as_ref()
+        .and_then(|verifier| verifier.response_hash())
+        .unwrap_or_else(|| calculate_hash(&response_readable_map));
 
     println!("Hash of the response file for verification:");
     for line in response_hash.as_slice().chunks(16) {
@@ -151,88 +452,325 @@ fn main() {
         println!();
     }
 
+    if let Some(expected_hash_filename) = &expected_hash_filename {
+        check_expected_hash(
+            expected_hash_filename,
+            response_filename,
+            response_hash.as_slice(),
+        )?;
+        println!("The hash matches the one in {}", expected_hash_filename);
+    }
+
     // get the contributor's public key
     let public_key = PublicKey::read(
         &response_readable_map,
         CONTRIBUTION_IS_COMPRESSED,
         &parameters,
     )
-    .expect("wasn't able to deserialize the response file's public key");
+    .map_err(|e| {
+        Error::from(e).context("wasn't able to deserialize the response file's public key")
+    })?;
+
+    // Keys of a round are bound to the ceremony, see `KeyDomain`
+    // A contribution that only updates some trapdoors is also bound to them, see `TrapdoorMask`
+    let legacy_digest = parameters
+        .trapdoors
+        .digest(current_accumulator_hash.as_slice());
+    let key_digest = match round {
+        Some(round) => {
+            let domain_digest = parameters.trapdoors.digest(
+                &KeyDomain::bn256_groth16(parameters.size, round)
+                    .digest(current_accumulator_hash.as_slice()),
+            );
+            if public_key.proves_knowledge(&domain_digest) {
+                domain_digest
+            } else if accept_legacy_keys && public_key.proves_knowledge(&legacy_digest) {
+                println!(
+                    "The public key is a legacy key, it is not bound to round {} of this ceremony",
+                    round
+                );
+                legacy_digest
+            } else {
+                return Err(Error::new(
+                    errors::INVALID_CONTRIBUTION,
+                    ErrorCategory::Verification,
+                    format!(
+                        "the public key was not generated for round {} of this ceremony",
+                        round
+                    ),
+                ));
+            }
+        }
+        None => legacy_digest,
+    };
 
     // check that it follows the protocol
 
     println!(
         "Verifying a contribution to contain proper powers and correspond to the public key..."
     );
+    if !parameters.trapdoors.is_full() {
+        println!(
+            "The contribution must only update {}, and leave the other trapdoors as they are",
+            parameters.trapdoors
+        );
+    }
 
-    let valid = BatchedAccumulator::verify_transformation(
-        &challenge_readable_map,
-        &response_readable_map,
-        &public_key,
-        current_accumulator_hash.as_slice(),
-        PREVIOUS_CHALLENGE_IS_COMPRESSED,
-        CONTRIBUTION_IS_COMPRESSED,
-        CheckForCorrectness::No,
-        CheckForCorrectness::Yes,
-        &parameters,
-    );
+    // In a single pass, the new challenge is written as the response is verified, and
+    // removed again if the contribution turns out to be invalid
+    let mut rollback = Rollback::default();
+    let mut new_challenge_maps = None;
+    if single_pass && COMPRESS_NEW_CHALLENGE == UseCompression::No {
+        let writable_map = create_new_challenge(
+            new_challenge_filename,
+            expected_sizes(&parameters, UseCompression::No).new_challenge,
+            response_hash.as_slice(),
+        )?;
+        rollback.filenames.push(new_challenge_filename.to_string());
+        let compressed_writable_map = match compressed_filename {
+            Some(ref compressed_filename) => {
+                let map = create_new_challenge(
+                    compressed_filename,
+                    expected_sizes(&parameters, UseCompression::Yes).new_challenge,
+                    response_hash.as_slice(),
+                )?;
+                rollback.filenames.push(compressed_filename.clone());
+                Some(map)
+            }
+            None => None,
+        };
+        new_challenge_maps = Some((writable_map, compressed_writable_map));
+    }
+
+    let valid = match new_challenge_maps {
+        Some((ref mut writable_map, ref mut compressed_writable_map)) => {
+            BatchedAccumulator::verify_transformation_and_decompress(
+                &challenge_readable_map,
+                &response_readable_map,
+                &public_key,
+                &key_digest,
+                PREVIOUS_CHALLENGE_IS_COMPRESSED,
+                CONTRIBUTION_IS_COMPRESSED,
+                check_challenge,
+                CheckForCorrectness::Yes,
+                &read_options,
+                writable_map,
+                compressed_writable_map.as_mut(),
+                &parameters,
+            )
+        }
+        None => match incremental_verifier {
+            // Only the checks against the public key are left
+            Some(ref mut verifier) => {
+                verifier.finish(&response_readable_map, &public_key, &key_digest)?;
+                true
+            }
+            None if resume => {
+                // Named after the file asked for, which `--versioned` doesn't rename
+                let cursor_filename = format!("{}.progress", args[3]);
+                let cursor_path = Path::new(&cursor_filename);
+                let mut cursor = VerificationCursor::resume(
+                    cursor_path,
+                    current_accumulator_hash.as_slice(),
+                    response_hash.as_slice(),
+                    &parameters,
+                )
+                .map_err(|e| {
+                    Error::from(e).context(format!("unable to read {}", cursor_filename))
+                })?;
+                if cursor.has_progress(&parameters) {
+                    println!(
+                        "Resuming the verification from {}, at power {} of {} and extra power {} of {}",
+                        cursor_filename,
+                        cursor.next_power,
+                        parameters.powers_length,
+                        cursor.next_extra_power,
+                        parameters.powers_g1_length
+                    );
+                }
+                let valid = BatchedAccumulator::verify_transformation_resuming(
+                    &challenge_readable_map,
+                    &response_readable_map,
+                    &public_key,
+                    &key_digest,
+                    PREVIOUS_CHALLENGE_IS_COMPRESSED,
+                    CONTRIBUTION_IS_COMPRESSED,
+                    check_challenge,
+                    CheckForCorrectness::Yes,
+                    &read_options,
+                    &mut cursor,
+                    cursor_path,
+                    &parameters,
+                );
+                // Only an interrupted verification leaves its progress behind
+                let _ = std::fs::remove_file(cursor_path);
+                valid
+            }
+            None => BatchedAccumulator::verify_transformation_with(
+                &challenge_readable_map,
+                &response_readable_map,
+                &public_key,
+                &key_digest,
+                PREVIOUS_CHALLENGE_IS_COMPRESSED,
+                CONTRIBUTION_IS_COMPRESSED,
+                check_challenge,
+                CheckForCorrectness::Yes,
+                &read_options,
+                &parameters,
+            ),
+        },
+    };
 
     if !valid {
-        println!("Verification failed, contribution was invalid somehow.");
-        panic!("INVALID CONTRIBUTION!!!");
+        return Err(Error::new(
+            errors::INVALID_CONTRIBUTION,
+            ErrorCategory::Verification,
+            "Verification failed, contribution was invalid somehow.",
+        ));
     } else {
         println!("Verification succeeded!");
     }
 
-    if COMPRESS_NEW_CHALLENGE == UseCompression::Yes {
+    // The ratios hold for any key, so a contribution claimed to be the beacon is checked to use
+    // the key derived from the beacon
+    if let Some(BeaconOpts { beacon, .. }) = &beacon {
         println!(
-            "Don't need to recompress the contribution, please copy response file as new challenge"
+            "Recomputing the key of the beacon with 2^{} iterations, this could take a while...",
+            beacon.num_iterations_exp
         );
-    } else {
-        println!("Verification succeeded! Writing to new challenge file...");
-
-        // Create new challenge file in this directory
-        let writer = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create_new(true)
-            .open(new_challenge_filename)
-            .expect("unable to create new challenge file in this directory");
-
-        // Recomputation strips the public key and uses hashing to link with the previous contribution after decompression
-        writer
-            .set_len(parameters.accumulator_size as u64)
-            .expect("must make output file large enough");
-
-        let mut writable_map = unsafe {
-            MmapOptions::new()
-                .map_mut(&writer)
-                .expect("unable to create a memory map for output")
-        };
+        let final_hash = beacon.final_hash(|_, _| {});
+        let beacon_public_key = Beacon::public_key::<Bn256>(&final_hash, &key_digest);
+        if beacon_public_key != public_key {
+            return Err(Error::new(
+                errors::INVALID_CONTRIBUTION,
+                ErrorCategory::Verification,
+                format!(
+                    "the contribution was not made by the beacon {}",
+                    hex::encode(&beacon.hash)
+                ),
+            ));
+        }
+        println!(
+            "The contribution was made by the beacon, with the final hash {}",
+            hex::encode(&final_hash)
+        );
+    }
+
+    // The sample is recorded in the attestation, so that delegated verifiers can show which
+    // pairs they checked
+    #[cfg(feature = "cross-check")]
+    let details = match cross_check_sample {
+        Some(sample) => {
+            let seed = cross_check_seed.unwrap_or_else(|| {
+                use rand::{OsRng, Rng};
+
+                let mut seed = [0; 32];
+                OsRng::new()
+                    .expect("unable to access the system randomness")
+                    .fill_bytes(&mut seed);
+                seed
+            });
+            println!(
+                "Cross-checking {} random samples of the response with an independent implementation, with seed {}...",
+                sample,
+                hex::encode(&seed[..])
+            );
+
+            let report = powersoftau::cross_check::cross_check_report(
+                &response_readable_map,
+                CONTRIBUTION_IS_COMPRESSED,
+                &parameters,
+                sample,
+                &seed,
+                response_hash.as_slice(),
+            )
+            .map_err(|e| {
+                Error::new(
+                    errors::INVALID_CONTRIBUTION,
+                    ErrorCategory::Verification,
+                    format!("Cross-check failed: {}", e),
+                )
+            })?;
+            if let Some(ref report_filename) = cross_check_report {
+                report
+                    .write(report_filename)
+                    .map_err(|e| e.context("unable to write the cross-check report"))?;
+            }
 
-        {
-            (&mut writable_map[0..])
-                .write_all(response_hash.as_slice())
-                .expect("unable to write a default hash to mmap");
+            let failed = report.checks.iter().filter(|&&(_, passed)| !passed).count();
+            if failed > 0 {
+                return Err(Error::new(
+                    errors::INVALID_CONTRIBUTION,
+                    ErrorCategory::Verification,
+                    format!(
+                        "Cross-check failed for {} of {} samples",
+                        failed,
+                        report.checks.len()
+                    ),
+                ));
+            }
 
-            writable_map
-                .flush()
-                .expect("unable to write hash to new challenge file");
+            println!("Cross-check succeeded!");
+            report.lines()
         }
+        None => vec![],
+    };
+    #[cfg(not(feature = "cross-check"))]
+    let details = vec![];
 
-        BatchedAccumulator::decompress(
-            &response_readable_map,
-            &mut writable_map,
-            CheckForCorrectness::No,
-            &parameters,
-        )
-        .expect("must decompress a response for a new challenge");
+    // Every check passed, so a new challenge written in a single pass is kept
+    rollback.keep = true;
 
-        writable_map.flush().expect("must flush the memory map");
+    if COMPRESS_NEW_CHALLENGE == UseCompression::Yes {
+        println!(
+            "Don't need to recompress the contribution, please copy response file as new challenge"
+        );
+    } else {
+        let (writable_map, compressed_writable_map) = match new_challenge_maps {
+            Some(maps) => maps,
+            None => {
+                println!("Verification succeeded! Writing to new challenge file...");
+
+                // Recomputation strips the public key and uses hashing to link with the previous contribution after decompression
+                let mut writable_map = create_new_challenge(
+                    new_challenge_filename,
+                    expected_sizes(&parameters, UseCompression::No).new_challenge,
+                    response_hash.as_slice(),
+                )?;
+
+                // The compressed copy is the response without the public key, linked to it by
+                // hash like the new challenge
+                let mut compressed_writable_map = match compressed_filename {
+                    Some(ref compressed_filename) => Some(create_new_challenge(
+                        compressed_filename,
+                        expected_sizes(&parameters, UseCompression::Yes).new_challenge,
+                        response_hash.as_slice(),
+                    )?),
+                    None => None,
+                };
+
+                BatchedAccumulator::decompress_with_copy(
+                    &response_readable_map,
+                    &mut writable_map,
+                    compressed_writable_map.as_mut(),
+                    CheckForCorrectness::No,
+                    &parameters,
+                )
+                .map_err(|e| {
+                    Error::from(e).context("must decompress a response for a new challenge")
+                })?;
+
+                (writable_map, compressed_writable_map)
+            }
+        };
+
+        writable_map
+            .flush()
+            .map_err(|e| Error::from(e).context("must flush the memory map"))?;
 
         let new_challenge_readable_map = writable_map
             .make_read_only()
-            .expect("must make a map readonly");
+            .map_err(|e| Error::from(e).context("must make a map readonly"))?;
 
         let recompressed_hash = calculate_hash(&new_challenge_readable_map);
 
@@ -249,7 +787,63 @@ fn main() {
             println!();
         }
 
+        if let (Some(compressed_file), Some(map)) = (&compressed_file, compressed_writable_map) {
+            map.flush()
+                .map_err(|e| Error::from(e).context("must flush the memory map"))?;
+            let compressed_readable_map = map
+                .make_read_only()
+                .map_err(|e| Error::from(e).context("must make a map readonly"))?;
+            println!(
+                "The new challenge has also been written to {} in compressed form, with BLAKE2b hash {}",
+                compressed_file.uri(),
+                hex::encode(calculate_hash(&compressed_readable_map).as_slice())
+            );
+        }
+
+        new_challenge_file.publish()?;
+        if let Some(compressed_file) = compressed_file {
+            compressed_file.publish()?;
+        }
+
+        if let Some(container_filename) = container_filename {
+            let mut writer = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&container_filename)
+                .map(BufWriter::new)
+                .map_err(|e| {
+                    Error::from(e).context("unable to create new challenge container file")
+                })?;
+            write_accumulator_container(
+                &new_challenge_readable_map,
+                &parameters,
+                COMPRESS_NEW_CHALLENGE,
+                false,
+                &mut writer,
+            )
+            .and_then(|_| writer.flush())
+            .map_err(|e| Error::from(e).context("unable to write new challenge container"))?;
+            println!(
+                "The new challenge has also been written to {} as a container.",
+                container_filename
+            );
+        }
+
+        let mut context = HookContext::new(
+            "verify_transform_constrained",
+            new_challenge_file.uri(),
+            recompressed_hash.as_slice(),
+            response_hash.as_slice(),
+        );
+        context.details = details;
+        hooks.run(&context)?;
+
         println!("Done! new challenge file contains the new challenge file. The other files");
         println!("were left alone.");
     }
+
+    profiling::dump_report("verify_transform_constrained")
+        .map_err(|e| Error::from(e).context("unable to write the profiling report"))?;
+
+    Ok(())
 }
@@ -0,0 +1,135 @@
+use powersoftau::{
+    errors::{self, Error, ErrorCategory},
+    keypair::take_round,
+    parameters::{expected_sizes, take_trapdoors, CeremonyParams, UseCompression},
+    path_template::take_template,
+    watch::{Outcome, Queue, Status, WorkerState},
+};
+
+use bellman_ce::pairing::bn256::Bn256;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_POLL_INTERVAL_SECONDS: u64 = 10;
+
+/// Removes `--status-file <file>` from the arguments and returns the file
+fn take_status_file(args: &mut Vec<String>) -> Option<PathBuf> {
+    let position = args.iter().position(|arg| arg == "--status-file")?;
+    if position + 1 >= args.len() {
+        println!("--status-file requires a file");
+        std::process::exit(exitcode::USAGE);
+    }
+    let status_filename = PathBuf::from(&args[position + 1]);
+    args.drain(position..position + 2);
+
+    Some(status_filename)
+}
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut args: Vec<String> = std::env::args().collect();
+    let challenge_template = take_template(&mut args, "--challenge-template")?;
+    let response_template = take_template(&mut args, "--response-template")?;
+    let result_template = take_template(&mut args, "--result-template")?;
+    let status_filename = take_status_file(&mut args);
+    let domain_round = take_round(&mut args)?;
+    let trapdoors = take_trapdoors(&mut args)?;
+    if args.len() != 5 && args.len() != 6 {
+        println!("Usage: \n<queue_directory> <output_directory> <circuit_power> <batch_size> [<poll_interval_seconds>] [--challenge-template <template>] [--response-template <template>] [--result-template <template>] [--status-file <file>] [--round <round_of_the_first_challenge>] [--trapdoors <tau,alpha,beta>]");
+        std::process::exit(exitcode::USAGE);
+    }
+    let mut queue = Queue::new(&args[1], &args[2]);
+    if let Some(template) = challenge_template {
+        queue.challenge_template = template;
+    }
+    if let Some(template) = response_template {
+        queue.response_template = template;
+    }
+    if let Some(template) = result_template {
+        queue.result_template = template;
+    }
+    queue.domain_round = domain_round;
+    let parse_argument = |argument: &String, name: &str| {
+        argument.parse().map_err(|_| {
+            Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                format!("could not parse {}", name),
+            )
+        })
+    };
+    let circuit_power = parse_argument(&args[3], "circuit power")?;
+    let batch_size = parse_argument(&args[4], "batch size")?;
+    let poll_interval = match args.get(5) {
+        Some(seconds) => parse_argument(seconds, "poll interval")? as u64,
+        None => DEFAULT_POLL_INTERVAL_SECONDS,
+    };
+
+    let mut parameters = CeremonyParams::<Bn256>::validated(circuit_power, batch_size)?;
+    if let Some(trapdoors) = trapdoors {
+        parameters.trapdoors = trapdoors;
+    }
+
+    let mut round = queue
+        .next_round()
+        .map_err(|e| Error::from(e).context("unable to read the journal"))?;
+    if !queue.challenge_path(round).exists() {
+        return Err(Error::new(
+            errors::FILE_NOT_FOUND,
+            ErrorCategory::Io,
+            format!("{} does not exist", queue.challenge_path(round).display()),
+        )
+        .with_hint("put the initial challenge in the output directory under the name of round 0"));
+    }
+
+    println!(
+        "Watching {} for responses, starting at round {}",
+        queue.input_directory.display(),
+        round
+    );
+
+    let mut status = Status::new(round);
+    let response_length = expected_sizes(&parameters, UseCompression::Yes).response as u64;
+    let update_status = |status: &mut Status, state, round| -> Result<(), Error> {
+        if let Some(status_filename) = &status_filename {
+            status.state = state;
+            status.round = round;
+            status.queued = queue.queued_responses(round, &parameters)?;
+            status
+                .write(status_filename)
+                .map_err(|e| Error::from(e).context("unable to write the status file"))?;
+        }
+        Ok(())
+    };
+
+    loop {
+        if queue.response_ready(round, &parameters)? {
+            update_status(&mut status, WorkerState::Verifying, round)?;
+        }
+        let started = Instant::now();
+        match queue.process_round(round, &parameters)? {
+            Some(outcome) => {
+                status.record(round, &outcome, started.elapsed(), response_length);
+                match outcome {
+                    Outcome::Accepted { .. } => {
+                        println!("Response of round {} accepted", round);
+                        round += 1;
+                    }
+                    Outcome::Rejected(e) => {
+                        println!("Response of round {} rejected: {}", round, e);
+                    }
+                }
+            }
+            None => {
+                update_status(&mut status, WorkerState::Waiting, round)?;
+                thread::sleep(Duration::from_secs(poll_interval));
+            }
+        }
+    }
+}
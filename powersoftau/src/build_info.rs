@@ -0,0 +1,91 @@
+/// Provenance of the binary, so that the outputs of a ceremony can be linked to the source and
+/// configuration of the tools that produced them and to a reproducible build. The commit is the
+/// one checked out when building, or `POWERSOFTAU_GIT_COMMIT` for builds outside of a
+/// repository. Uncommitted changes are not detected.
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub commit: &'static str,
+    /// Enabled optional features of this crate
+    pub features: Vec<&'static str>,
+    pub target: &'static str,
+    /// Whether the binary was built with optimizations
+    pub release: bool,
+}
+
+impl BuildInfo {
+    pub fn current() -> Self {
+        let mut features = vec![];
+        if cfg!(feature = "cross-check") {
+            features.push("cross-check");
+        }
+        if cfg!(feature = "profiling") {
+            features.push("profiling");
+        }
+        if cfg!(feature = "s3") {
+            features.push("s3");
+        }
+
+        BuildInfo {
+            version: env!("CARGO_PKG_VERSION"),
+            commit: env!("POWERSOFTAU_GIT_COMMIT"),
+            features,
+            target: env!("POWERSOFTAU_TARGET"),
+            release: !cfg!(debug_assertions),
+        }
+    }
+
+    /// The build as a JSON object.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"version\":\"{}\",\"commit\":\"{}\",\"features\":[{}],\"target\":\"{}\",\"release\":{}}}",
+            self.version,
+            self.commit,
+            self.features
+                .iter()
+                .map(|feature| format!("\"{}\"", feature))
+                .collect::<Vec<_>>()
+                .join(","),
+            self.target,
+            self.release
+        )
+    }
+}
+
+/// e.g. `powersoftau 0.2.0 (commit <hash>, target x86_64-unknown-linux-gnu, features s3, release)`
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "powersoftau {} (commit {}, target {}, features {}, {})",
+            self.version,
+            self.commit,
+            self.target,
+            if self.features.is_empty() {
+                "none".to_string()
+            } else {
+                self.features.join(" ")
+            },
+            if self.release { "release" } else { "debug" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info() {
+        let build = BuildInfo::current();
+        assert_eq!(build.version, env!("CARGO_PKG_VERSION"));
+        assert!(!build.commit.is_empty());
+        assert!(build.to_string().starts_with("powersoftau "));
+        assert!(build.to_string().contains(build.target));
+        assert!(build
+            .to_json()
+            .contains(&format!("\"commit\":\"{}\"", build.commit)));
+    }
+}
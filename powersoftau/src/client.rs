@@ -0,0 +1,448 @@
+/// The workflow of a contributor as a library, for graphical clients that embed the crate
+/// instead of running `compute_constrained` and parsing what it prints. A contribution loads
+/// the spec of the ceremony, fetches the challenge, checks it, computes the response, writes
+/// it with a receipt, runs the hooks that attest it and submits it, see `ClientStep`.
+///
+/// How files are moved is up to a `ClientTransport`, and what the contributor sees is up to a
+/// `ClientUi`, which is told about every step as it starts. Entropy is the caller's: the
+/// contribution takes an RNG, so a client can mix in what the contributor typed or moved the
+/// mouse with the way it likes, instead of reading standard input like `entropy_rng` does.
+use bellman_ce::pairing::Engine;
+use generic_array::GenericArray;
+use memmap::MmapOptions;
+use rand::Rng;
+use typenum::consts::U64;
+
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+use super::errors::{self, Error, ErrorCategory};
+use super::hooks::{HookContext, Hooks};
+use super::keypair::{guarded_keypair_with_mask, KeyDomain};
+use super::parameters::{
+    check_compression, expected_sizes, CeremonyParams, CheckForCorrectness, UseCompression,
+};
+use super::receipt::Receipt;
+use super::round::Round;
+use super::spec::read_spec;
+use super::utils::read_hash_header;
+
+/// The steps of a contribution, in order.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ClientStep {
+    LoadManifest,
+    FetchChallenge,
+    Validate,
+    Contribute,
+    WriteResponse,
+    Attest,
+    Submit,
+}
+
+/// Moves the files of a contribution between the coordinator and the working directory.
+pub trait ClientTransport {
+    /// Writes the spec of the ceremony, as exported by `export_spec`, to `path`.
+    fn fetch_manifest(&self, path: &Path) -> Result<(), Error>;
+
+    /// Writes the challenge to `path`.
+    fn fetch_challenge(&self, path: &Path) -> Result<(), Error>;
+
+    /// Hands the response and its receipt over to the coordinator.
+    fn submit(&self, response: &Path, receipt: &Path) -> Result<(), Error>;
+}
+
+/// What the contributor is shown. Every method does nothing by default.
+pub trait ClientUi {
+    fn step(&mut self, _step: ClientStep) {}
+
+    /// The hash of the challenge, which the contributor may compare with the one the
+    /// coordinator announced, and the hash of the response it claims to be based on, which
+    /// must not be blindly trusted.
+    fn challenge(&mut self, _challenge_hash: &[u8], _previous_hash: &[u8]) {}
+}
+
+impl ClientUi for () {}
+
+/// A transport for a coordinator that shares a filesystem with the contributor, which copies
+/// the manifest and the challenge from their paths and the response and receipt to a
+/// directory.
+pub struct LocalTransport {
+    pub manifest: PathBuf,
+    pub challenge: PathBuf,
+    pub submissions: PathBuf,
+}
+
+impl LocalTransport {
+    fn copy(from: &Path, to: &Path) -> Result<(), Error> {
+        if to.exists() {
+            return Err(output_exists(to));
+        }
+        fs::copy(from, to).map(|_| ()).map_err(|e| {
+            Error::from(e).context(format!(
+                "unable to copy {} to {}",
+                from.display(),
+                to.display()
+            ))
+        })
+    }
+}
+
+impl ClientTransport for LocalTransport {
+    fn fetch_manifest(&self, path: &Path) -> Result<(), Error> {
+        Self::copy(&self.manifest, path)
+    }
+
+    fn fetch_challenge(&self, path: &Path) -> Result<(), Error> {
+        Self::copy(&self.challenge, path)
+    }
+
+    fn submit(&self, response: &Path, receipt: &Path) -> Result<(), Error> {
+        for file in &[response, receipt] {
+            let name = file.file_name().expect("files of the client have names");
+            Self::copy(file, &self.submissions.join(name))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A transport over HTTP with `fetch` and `upload`. Downloads resume where they stopped when a
+/// contribution is started again in the same working directory.
+#[cfg(feature = "http")]
+pub struct HttpTransport {
+    pub manifest_url: String,
+    pub challenge_url: String,
+    pub response_url: String,
+    /// Where the receipt is uploaded to, if anywhere
+    pub receipt_url: Option<String>,
+    /// The expected hash is the one of the challenge
+    pub options: super::transfer::TransferOptions,
+}
+
+#[cfg(feature = "http")]
+impl ClientTransport for HttpTransport {
+    fn fetch_manifest(&self, path: &Path) -> Result<(), Error> {
+        let options = super::transfer::TransferOptions {
+            expected_hash: None,
+            ..self.options.clone()
+        };
+        super::transfer::fetch(&self.manifest_url, path, &options).map(|_| ())
+    }
+
+    fn fetch_challenge(&self, path: &Path) -> Result<(), Error> {
+        super::transfer::fetch(&self.challenge_url, path, &self.options).map(|_| ())
+    }
+
+    fn submit(&self, response: &Path, receipt: &Path) -> Result<(), Error> {
+        super::transfer::upload(response, &self.response_url, &self.options)?;
+        match self.receipt_url {
+            Some(ref receipt_url) => super::transfer::upload(receipt, receipt_url, &self.options),
+            None => Ok(()),
+        }
+    }
+}
+
+/// What a contribution produced.
+pub struct Contribution<E: Engine> {
+    pub parameters: CeremonyParams<E>,
+    pub response: PathBuf,
+    pub response_hash: GenericArray<u8, U64>,
+    pub receipt: Receipt<E>,
+}
+
+fn output_exists(path: &Path) -> Error {
+    Error::new(
+        errors::OUTPUT_EXISTS,
+        ErrorCategory::Io,
+        format!("{} already exists", path.display()),
+    )
+    .with_hint("contribute in an empty working directory")
+}
+
+/// Contributes to a ceremony on `curve`, e.g. `bn256`, with the files in a working directory:
+/// `manifest.json`, `challenge`, `response` and `receipt`.
+pub struct CeremonyClient<T: ClientTransport, U: ClientUi> {
+    curve: String,
+    work_dir: PathBuf,
+    transport: T,
+    ui: U,
+    hooks: Hooks,
+    domain: Option<KeyDomain>,
+    expected_challenge_hash: Option<[u8; 64]>,
+    check_challenge: CheckForCorrectness,
+}
+
+impl<T: ClientTransport, U: ClientUi> CeremonyClient<T, U> {
+    /// A client that makes legacy keys and doesn't check the points of the challenge, like
+    /// `compute_constrained` by default.
+    pub fn new<P: Into<PathBuf>>(curve: &str, work_dir: P, transport: T, ui: U) -> Self {
+        CeremonyClient {
+            curve: curve.to_string(),
+            work_dir: work_dir.into(),
+            transport,
+            ui,
+            hooks: Hooks::new(),
+            domain: None,
+            expected_challenge_hash: None,
+            check_challenge: CheckForCorrectness::No,
+        }
+    }
+
+    /// Runs `hooks` once the response and the receipt are written, e.g. an `Attestation`.
+    pub fn with_hooks(mut self, hooks: Hooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Binds the key of the contribution to `domain`, see `KeyDomain`.
+    pub fn with_domain(mut self, domain: KeyDomain) -> Self {
+        self.domain = Some(domain);
+        self
+    }
+
+    /// Refuses a challenge without the hash that the coordinator announced.
+    pub fn with_expected_challenge_hash(mut self, hash: [u8; 64]) -> Self {
+        self.expected_challenge_hash = Some(hash);
+        self
+    }
+
+    pub fn with_check_challenge(mut self, check: CheckForCorrectness) -> Self {
+        self.check_challenge = check;
+        self
+    }
+
+    pub fn ui(&mut self) -> &mut U {
+        &mut self.ui
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.work_dir.join(name)
+    }
+
+    /// Makes a contribution with secrets drawn from `rng`, which are dropped once the response
+    /// is written. Nothing is submitted if a step fails.
+    pub fn contribute<E: Engine, R: Rng>(&mut self, rng: &mut R) -> Result<Contribution<E>, Error> {
+        self.ui.step(ClientStep::LoadManifest);
+        let manifest_path = self.path("manifest.json");
+        self.transport.fetch_manifest(&manifest_path)?;
+        let manifest = fs::read_to_string(&manifest_path)
+            .map_err(|e| Error::from(e).context("unable to read the manifest"))?;
+        let parameters = read_spec::<E>(&self.curve, &manifest)
+            .map_err(|e| e.context("unable to use the manifest"))?;
+
+        self.ui.step(ClientStep::FetchChallenge);
+        let challenge_path = self.path("challenge");
+        self.transport.fetch_challenge(&challenge_path)?;
+
+        self.ui.step(ClientStep::Validate);
+        let challenge = File::open(&challenge_path)
+            .map_err(|e| Error::from(e).context("unable to open the challenge"))?;
+        let expected_length = expected_sizes(&parameters, UseCompression::No).challenge;
+        let length = challenge.metadata()?.len();
+        if length != expected_length as u64 {
+            return Err(Error::new(
+                errors::INVALID_FILE_SIZE,
+                ErrorCategory::InvalidInput,
+                format!(
+                    "the challenge should be {} bytes long, but it's {}",
+                    expected_length, length
+                ),
+            )
+            .with_hint("check that the challenge is the one of the ceremony in the manifest"));
+        }
+        check_compression(&challenge, UseCompression::No, &parameters)
+            .map_err(|e| Error::from(e).context("unable to use the challenge"))?;
+        let challenge = unsafe { MmapOptions::new().map(&challenge)? };
+        let mut round = Round::new(&challenge, &parameters)
+            .with_checks(self.check_challenge, CheckForCorrectness::Yes);
+        if let Some(ref domain) = self.domain {
+            round = round.with_domain(domain);
+        }
+        if let Some(expected) = self.expected_challenge_hash {
+            if round.challenge_hash()[..] != expected[..] {
+                return Err(Error::new(
+                    errors::HASH_MISMATCH,
+                    ErrorCategory::Verification,
+                    format!(
+                        "the hash of the challenge is {}, not {}",
+                        hex::encode(round.challenge_hash()),
+                        hex::encode(&expected[..])
+                    ),
+                )
+                .with_hint("check that the challenge is the one the coordinator announced"));
+            }
+        }
+        let previous_hash = read_hash_header(&challenge)?;
+        self.ui.challenge(round.challenge_hash(), &previous_hash);
+
+        self.ui.step(ClientStep::Contribute);
+        let response_path = self.path("response");
+        let response = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&response_path)
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::AlreadyExists => output_exists(&response_path),
+                _ => Error::from(e).context("unable to create the response"),
+            })?;
+        response
+            .set_len(expected_sizes(&parameters, round.response_compression).response as u64)?;
+        let mut response = unsafe { MmapOptions::new().map_mut(&response)? };
+        let (public_key, private_key) =
+            guarded_keypair_with_mask(rng, round.key_digest(), parameters.trapdoors);
+        round.compute(&mut response, &*private_key)?;
+        drop(private_key);
+
+        self.ui.step(ClientStep::WriteResponse);
+        let response_hash = round.finalize(&mut response, &public_key)?;
+        let response = response.make_read_only()?;
+        let mut receipt = Receipt::new(&response, &response_hash, public_key)
+            .with_trapdoors(parameters.trapdoors);
+        if let Some(ref domain) = self.domain {
+            receipt = receipt.with_domain(domain.clone());
+        }
+        let receipt_path = self.path("receipt");
+        let mut writer = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&receipt_path)
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::AlreadyExists => output_exists(&receipt_path),
+                _ => Error::from(e).context("unable to create the receipt"),
+            })?;
+        receipt.serialize(&mut writer)?;
+
+        self.ui.step(ClientStep::Attest);
+        let context = HookContext::new(
+            "ceremony_client",
+            &response_path.to_string_lossy(),
+            &response_hash,
+            round.challenge_hash(),
+        );
+        self.hooks.run(&context)?;
+
+        self.ui.step(ClientStep::Submit);
+        self.transport
+            .submit(&response_path, &receipt_path)
+            .map_err(|e| e.context("unable to submit the response"))?;
+
+        Ok(Contribution {
+            parameters,
+            response: response_path,
+            response_hash,
+            receipt,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batched_accumulator::BatchedAccumulator;
+    use crate::spec::export_spec;
+    use crate::utils::{blank_hash, calculate_hash, write_hash_header};
+    use bellman_ce::pairing::bn256::Bn256;
+    use memmap::MmapMut;
+    use rand::thread_rng;
+
+    #[derive(Default)]
+    struct Steps(Vec<ClientStep>);
+
+    impl ClientUi for Steps {
+        fn step(&mut self, step: ClientStep) {
+            self.0.push(step);
+        }
+    }
+
+    /// A coordinator in `name` of the temporary directory with the first challenge of a
+    /// ceremony, and the working directory of a client.
+    fn ceremony(name: &str) -> (PathBuf, PathBuf, LocalTransport, [u8; 64]) {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        let (work_dir, coordinator) = (dir.join("work"), dir.join("coordinator"));
+        fs::create_dir_all(&work_dir).unwrap();
+        fs::create_dir_all(coordinator.join("submissions")).unwrap();
+
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        fs::write(
+            coordinator.join("spec.json"),
+            export_spec("bn256", &parameters),
+        )
+        .unwrap();
+        let mut challenge = MmapMut::map_anon(parameters.accumulator_size).unwrap();
+        write_hash_header(&mut challenge, &blank_hash()).unwrap();
+        BatchedAccumulator::generate_initial(&mut challenge, UseCompression::No, &parameters)
+            .unwrap();
+        fs::write(coordinator.join("challenge"), &challenge[..]).unwrap();
+        let mut challenge_hash = [0; 64];
+        challenge_hash.copy_from_slice(&calculate_hash(&challenge));
+
+        let transport = LocalTransport {
+            manifest: coordinator.join("spec.json"),
+            challenge: coordinator.join("challenge"),
+            submissions: coordinator.join("submissions"),
+        };
+
+        (dir, work_dir, transport, challenge_hash)
+    }
+
+    #[test]
+    fn test_ceremony_client() {
+        let (dir, work_dir, transport, challenge_hash) = ceremony("powersoftau_test_client");
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let coordinator = dir.join("coordinator");
+        let mut client = CeremonyClient::new("bn256", &work_dir, transport, Steps::default())
+            .with_expected_challenge_hash(challenge_hash);
+        let contribution = client.contribute::<Bn256, _>(&mut thread_rng()).unwrap();
+        assert_eq!(client.ui().0.len(), 7);
+        assert_eq!(client.ui().0[6], ClientStep::Submit);
+
+        let submitted = fs::read(coordinator.join("submissions/response")).unwrap();
+        assert_eq!(calculate_hash(&submitted), contribution.response_hash);
+        assert!(contribution.receipt.verify());
+        assert_eq!(contribution.receipt.challenge_hash, challenge_hash);
+        let receipt = fs::read(coordinator.join("submissions/receipt")).unwrap();
+        assert_eq!(receipt.len(), Receipt::size(&parameters));
+
+        // Nothing is overwritten, and a challenge with another hash is refused before anything
+        // is computed
+        let mut client = CeremonyClient::new("bn256", &work_dir, client.transport, ())
+            .with_expected_challenge_hash([0; 64]);
+        let e = client
+            .contribute::<Bn256, _>(&mut thread_rng())
+            .err()
+            .unwrap();
+        assert_eq!(e.code, errors::OUTPUT_EXISTS);
+        for file in &["manifest.json", "challenge", "response", "receipt"] {
+            fs::remove_file(work_dir.join(file)).unwrap();
+        }
+        let e = client
+            .contribute::<Bn256, _>(&mut thread_rng())
+            .err()
+            .unwrap();
+        assert_eq!(e.code, errors::HASH_MISMATCH);
+        assert!(!work_dir.join("response").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_ceremony_client_with_domain() {
+        let (dir, work_dir, transport, challenge_hash) = ceremony("powersoftau_test_client_domain");
+        let domain = KeyDomain::bn256_groth16(3, 2);
+        let mut client =
+            CeremonyClient::new("bn256", &work_dir, transport, ()).with_domain(domain.clone());
+        let contribution = client.contribute::<Bn256, _>(&mut thread_rng()).unwrap();
+
+        // The submitted receipt carries the domain that the key is bound to
+        let receipt = fs::read(dir.join("coordinator/submissions/receipt")).unwrap();
+        let receipt = Receipt::<Bn256>::deserialize(&mut &receipt[..]).unwrap();
+        assert_eq!(receipt.challenge_hash, challenge_hash);
+        assert_eq!(receipt.domain, Some(domain));
+        assert_eq!(receipt.key_digest(), contribution.receipt.key_digest());
+        assert!(receipt.verify());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,352 @@
+/// Container for files handed between participants and the coordinator. The header lists the
+/// sections of the payload with their length and BLAKE2b hash, and is itself hashed, so that
+/// transports and dashboards can check every section as soon as it has arrived without knowing
+/// anything about the curve or the layout of the file:
+///
+/// MAGIC | version (u32, BE) | section count (u32, BE) |
+/// for every section: name length (u8) | name | length (u64, BE) | hash |
+/// BLAKE2b of everything above | payload of every section, in order
+///
+/// The container is only a transport format; hashes of the ceremony are always the ones of the
+/// plain file inside it.
+use bellman_ce::pairing::Engine;
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use std::io::{self, Read, Write};
+use std::ops::Range;
+
+use super::parameters::{CeremonyParams, UseCompression};
+
+/// Identifies a container, followed by the format version.
+const MAGIC: &[u8; 8] = b"POTCNTNR";
+const VERSION: u32 = 1;
+
+/// Sections are hashed and copied in buffers of this size.
+const BUFFER_SIZE: usize = 1 << 20;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SectionEntry {
+    pub name: String,
+    pub length: u64,
+    /// BLAKE2b hash of the bytes of the section
+    pub hash: [u8; 64],
+}
+
+fn hash_bytes(bytes: &[u8]) -> [u8; 64] {
+    let mut hasher = Blake2b::default();
+    for chunk in bytes.chunks(BUFFER_SIZE) {
+        hasher.input(chunk);
+    }
+    let mut hash = [0; 64];
+    hash.copy_from_slice(hasher.result().as_slice());
+    hash
+}
+
+/// Writes a container from sections borrowed from e.g. the memory map of a file.
+#[derive(Default)]
+pub struct ContainerWriter<'a> {
+    sections: Vec<(String, &'a [u8])>,
+}
+
+impl<'a> ContainerWriter<'a> {
+    pub fn new() -> Self {
+        ContainerWriter { sections: vec![] }
+    }
+
+    /// Adds a section after the previous ones. Names are at most 255 bytes long.
+    pub fn add_section(&mut self, name: &str, bytes: &'a [u8]) -> &mut Self {
+        assert!(name.len() <= 255, "section name is too long");
+        self.sections.push((name.to_string(), bytes));
+        self
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut header = vec![];
+        header.extend_from_slice(&MAGIC[..]);
+        header.write_u32::<BigEndian>(VERSION)?;
+        header.write_u32::<BigEndian>(self.sections.len() as u32)?;
+        for (name, bytes) in &self.sections {
+            header.write_u8(name.len() as u8)?;
+            header.extend_from_slice(name.as_bytes());
+            header.write_u64::<BigEndian>(bytes.len() as u64)?;
+            header.extend_from_slice(&hash_bytes(bytes));
+        }
+        let header_hash = hash_bytes(&header);
+
+        writer.write_all(&header)?;
+        writer.write_all(&header_hash)?;
+        for (_, bytes) in &self.sections {
+            writer.write_all(bytes)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads a container, checking every section against its hash while it is streamed.
+pub struct ContainerReader<R: Read> {
+    reader: R,
+    sections: Vec<SectionEntry>,
+    next: usize,
+}
+
+impl<R: Read> ContainerReader<R> {
+    /// Reads and checks the header. The payload is not read yet.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        // Everything read is hashed, to be checked against the hash of the header
+        let mut header = vec![];
+        let mut read = |reader: &mut R, length: usize| -> io::Result<Vec<u8>> {
+            let mut bytes = vec![0; length];
+            reader.read_exact(&mut bytes)?;
+            header.extend_from_slice(&bytes);
+            Ok(bytes)
+        };
+
+        if read(&mut reader, MAGIC.len())? != MAGIC[..] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a container",
+            ));
+        }
+        let version = (&read(&mut reader, 4)?[..]).read_u32::<BigEndian>()?;
+        if version != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported container version {}", version),
+            ));
+        }
+
+        // The count is untrusted, so sections are only allocated as they are read
+        let count = (&read(&mut reader, 4)?[..]).read_u32::<BigEndian>()?;
+        let mut sections = vec![];
+        for _ in 0..count {
+            let name_length = read(&mut reader, 1)?[0] as usize;
+            let name = String::from_utf8(read(&mut reader, name_length)?).map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidData, "section name is not UTF-8")
+            })?;
+            let length = (&read(&mut reader, 8)?[..]).read_u64::<BigEndian>()?;
+            let mut hash = [0; 64];
+            hash.copy_from_slice(&read(&mut reader, 64)?);
+            sections.push(SectionEntry { name, length, hash });
+        }
+
+        let mut header_hash = [0; 64];
+        reader.read_exact(&mut header_hash)?;
+        if hash_bytes(&header)[..] != header_hash[..] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "container header does not match its hash",
+            ));
+        }
+
+        Ok(ContainerReader {
+            reader,
+            sections,
+            next: 0,
+        })
+    }
+
+    pub fn sections(&self) -> &[SectionEntry] {
+        &self.sections
+    }
+
+    /// Copies the next section to `writer`, and returns its entry once all of it has been
+    /// checked against its hash, or `None` after the last section. On an error, whatever was
+    /// written of the section must be discarded.
+    pub fn copy_next_section<W: Write>(
+        &mut self,
+        writer: &mut W,
+    ) -> io::Result<Option<&SectionEntry>> {
+        let section = match self.sections.get(self.next) {
+            Some(section) => section,
+            None => return Ok(None),
+        };
+
+        let mut hasher = Blake2b::default();
+        let mut buffer = vec![0; BUFFER_SIZE];
+        let mut remaining = section.length;
+        while remaining > 0 {
+            let length = std::cmp::min(remaining, BUFFER_SIZE as u64) as usize;
+            self.reader.read_exact(&mut buffer[..length])?;
+            hasher.input(&buffer[..length]);
+            writer.write_all(&buffer[..length])?;
+            remaining -= length as u64;
+        }
+        if hasher.result().as_slice() != &section.hash[..] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("section {} does not match its hash", section.name),
+            ));
+        }
+
+        self.next += 1;
+        Ok(Some(section))
+    }
+}
+
+/// Sections of a challenge or response file: the hash of the previous contribution, the
+/// compression policy of mixed files, the powers of tau in G1 and G2, the powers times alpha
+/// and beta, beta in G2 and, for responses, the public key of the contribution.
+pub fn accumulator_sections<E: Engine>(
+    parameters: &CeremonyParams<E>,
+    compression: UseCompression,
+    has_public_key: bool,
+) -> Vec<(&'static str, Range<usize>)> {
+    let (g1_size, g2_size) = (
+        parameters.curve.g1_size(compression),
+        parameters.curve.g2_size(compression),
+    );
+    let mut lengths = vec![("hash", parameters.hash_size)];
+    if !compression.header().is_empty() {
+        lengths.push(("policy", compression.header().len()));
+    }
+    lengths.extend(vec![
+        ("tau_g1", parameters.powers_g1_length * g1_size),
+        ("tau_g2", parameters.powers_length * g2_size),
+        ("alpha_g1", parameters.powers_length * g1_size),
+        ("beta_g1", parameters.powers_length * g1_size),
+        ("beta_g2", g2_size),
+    ]);
+    if has_public_key {
+        lengths.push(("public_key", parameters.public_key_size));
+    }
+
+    let mut position = 0;
+    lengths
+        .into_iter()
+        .map(|(name, length)| {
+            position += length;
+            (name, position - length..position)
+        })
+        .collect()
+}
+
+/// Writes `file` (a challenge or response) to `writer` as a container of its sections.
+pub fn write_accumulator_container<E: Engine, W: Write>(
+    file: &[u8],
+    parameters: &CeremonyParams<E>,
+    compression: UseCompression,
+    has_public_key: bool,
+    writer: &mut W,
+) -> io::Result<()> {
+    let sections = accumulator_sections(parameters, compression, has_public_key);
+    if sections.last().map(|(_, range)| range.end) != Some(file.len()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "file size does not match the parameters",
+        ));
+    }
+
+    let mut container = ContainerWriter::new();
+    for (name, range) in sections {
+        container.add_section(name, &file[range]);
+    }
+    container.write(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parameters::expected_sizes;
+    use bellman_ce::pairing::bn256::Bn256;
+    use rand::{thread_rng, Rng};
+
+    fn random_bytes(length: usize) -> Vec<u8> {
+        let rng = &mut thread_rng();
+        (0..length).map(|_| rng.gen()).collect()
+    }
+
+    #[test]
+    fn test_container_roundtrip() {
+        let first = random_bytes(100);
+        let second = random_bytes(3 * BUFFER_SIZE / 2);
+        let mut bytes = vec![];
+        ContainerWriter::new()
+            .add_section("first", &first)
+            .add_section("empty", &[])
+            .add_section("second", &second)
+            .write(&mut bytes)
+            .unwrap();
+
+        let mut reader = ContainerReader::new(&bytes[..]).unwrap();
+        let names = reader
+            .sections()
+            .iter()
+            .map(|section| section.name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["first", "empty", "second"]);
+
+        let mut payload = vec![];
+        while reader.copy_next_section(&mut payload).unwrap().is_some() {}
+        assert_eq!(payload, [&first[..], &second[..]].concat());
+    }
+
+    #[test]
+    fn test_container_rejects_corruption() {
+        let data = random_bytes(1000);
+        let mut bytes = vec![];
+        ContainerWriter::new()
+            .add_section("first", &data[..500])
+            .add_section("second", &data[500..])
+            .write(&mut bytes)
+            .unwrap();
+
+        // In the header
+        let mut corrupted = bytes.clone();
+        corrupted[20] ^= 1;
+        assert!(ContainerReader::new(&corrupted[..]).is_err());
+
+        // In the second section: the first one is still fine
+        let mut corrupted = bytes.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 1;
+        let mut reader = ContainerReader::new(&corrupted[..]).unwrap();
+        let mut payload = vec![];
+        assert!(reader.copy_next_section(&mut payload).is_ok());
+        assert!(reader.copy_next_section(&mut payload).is_err());
+    }
+
+    #[test]
+    fn test_accumulator_sections() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+
+        let sections = accumulator_sections(&parameters, UseCompression::No, false);
+        assert_eq!(sections[0], ("hash", 0..64));
+        assert_eq!(sections.last().unwrap().1.end, parameters.accumulator_size);
+
+        let sections = accumulator_sections(&parameters, UseCompression::Yes, true);
+        assert_eq!(sections.last().unwrap().1.end, parameters.contribution_size);
+
+        let sections = accumulator_sections(&parameters, UseCompression::Mixed, true);
+        assert_eq!(sections[1], ("policy", 64..72));
+        assert_eq!(
+            sections.last().unwrap().1.end,
+            expected_sizes(&parameters, UseCompression::Mixed).response
+        );
+
+        let response = random_bytes(parameters.contribution_size);
+        let mut bytes = vec![];
+        write_accumulator_container(
+            &response,
+            &parameters,
+            UseCompression::Yes,
+            true,
+            &mut bytes,
+        )
+        .unwrap();
+        let mut reader = ContainerReader::new(&bytes[..]).unwrap();
+        let mut payload = vec![];
+        while reader.copy_next_section(&mut payload).unwrap().is_some() {}
+        assert_eq!(payload, response);
+
+        assert!(write_accumulator_container(
+            &response[1..],
+            &parameters,
+            UseCompression::Yes,
+            true,
+            &mut vec![],
+        )
+        .is_err());
+    }
+}
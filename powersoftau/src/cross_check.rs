@@ -0,0 +1,363 @@
+/// Re-verification of a random sample of accumulator elements with an independent
+/// implementation of the BN256 arithmetic (the `substrate-bn` crate). A bug in the primary
+/// arithmetic could otherwise produce an accumulator that is wrong, but consistent with itself.
+use bellman_ce::pairing::bn256::{Bn256, G1Affine, G2Affine};
+use bellman_ce::pairing::CurveAffine;
+use bn::{pairing_batch, AffineG1, AffineG2, Fq, Fq2, Gt, G1, G2};
+use log::{error, info};
+use memmap::Mmap;
+use rand::Rng;
+
+use std::fmt;
+
+use super::batched_accumulator::BatchedAccumulator;
+use super::parameters::{
+    CeremonyParams, CheckForCorrectness, DeserializationError, ElementType, UseCompression,
+};
+use super::sampling::{sampled_indices, SampleReport};
+
+/// Errors that might occur during a cross-check.
+#[derive(Debug)]
+pub enum CrossCheckError {
+    DeserializationError(DeserializationError),
+    InvalidPoint(ElementType, usize),
+    RatioMismatch(ElementType, usize),
+}
+
+impl fmt::Display for CrossCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CrossCheckError::DeserializationError(ref e) => write!(f, "{}", e),
+            CrossCheckError::InvalidPoint(element_type, index) => write!(
+                f,
+                "{:?} element {} is not a valid point for the independent implementation",
+                element_type, index
+            ),
+            CrossCheckError::RatioMismatch(element_type, index) => write!(
+                f,
+                "{:?} element {} failed the independent pairing check",
+                element_type, index
+            ),
+        }
+    }
+}
+
+impl From<DeserializationError> for CrossCheckError {
+    fn from(err: DeserializationError) -> CrossCheckError {
+        CrossCheckError::DeserializationError(err)
+    }
+}
+
+fn fq_from_slice(slice: &[u8]) -> Option<Fq> {
+    Fq::from_slice(slice).ok()
+}
+
+fn convert_g1(point: &G1Affine) -> Option<G1> {
+    let encoded = point.into_uncompressed();
+    let bytes = encoded.as_ref();
+    let x = fq_from_slice(&bytes[0..32])?;
+    let y = fq_from_slice(&bytes[32..64])?;
+
+    AffineG1::new(x, y).ok().map(G1::from)
+}
+
+fn convert_g2(point: &G2Affine) -> Option<G2> {
+    // Coordinates are encoded as (x.c1, x.c0, y.c1, y.c0)
+    let encoded = point.into_uncompressed();
+    let bytes = encoded.as_ref();
+    let x = Fq2::new(
+        fq_from_slice(&bytes[32..64])?,
+        fq_from_slice(&bytes[0..32])?,
+    );
+    let y = Fq2::new(
+        fq_from_slice(&bytes[96..128])?,
+        fq_from_slice(&bytes[64..96])?,
+    );
+
+    AffineG2::new(x, y).ok().map(G2::from)
+}
+
+/// Checks that e(g1, g2_2) == e(g1_2, g2), i.e. that the ratio between `g1` and `g1_2`
+/// is the same as between `g2` and `g2_2`
+fn same_ratio(g1: (G1, G1), g2: (G2, G2)) -> bool {
+    pairing_batch(&[(g1.0, g2.1), (-g1.1, g2.0)]) == Gt::one()
+}
+
+/// Points used in every check.
+struct Base {
+    g1_one: G1,
+    g1_tau: G1,
+    g2_one: G2,
+    g2_tau: G2,
+    g2_beta: G2,
+}
+
+fn g1(element_type: ElementType, index: usize, point: &G1Affine) -> Result<G1, CrossCheckError> {
+    convert_g1(point).ok_or(CrossCheckError::InvalidPoint(element_type, index))
+}
+
+fn g2(element_type: ElementType, index: usize, point: &G2Affine) -> Result<G2, CrossCheckError> {
+    convert_g2(point).ok_or(CrossCheckError::InvalidPoint(element_type, index))
+}
+
+fn check(ratio: bool, element_type: ElementType, index: usize) -> Result<(), CrossCheckError> {
+    if ratio {
+        Ok(())
+    } else {
+        Err(CrossCheckError::RatioMismatch(element_type, index))
+    }
+}
+
+fn read_pair<'a>(
+    input_map: &Mmap,
+    compression: UseCompression,
+    parameters: &'a CeremonyParams<Bn256>,
+    index: usize,
+) -> Result<BatchedAccumulator<'a, Bn256>, CrossCheckError> {
+    let mut accumulator = BatchedAccumulator::empty(parameters);
+    accumulator.read_chunk(index, 2, compression, CheckForCorrectness::Yes, input_map)?;
+
+    Ok(accumulator)
+}
+
+fn read_base(
+    input_map: &Mmap,
+    compression: UseCompression,
+    parameters: &CeremonyParams<Bn256>,
+) -> Result<Base, CrossCheckError> {
+    // The generators and the first power of tau
+    let base = read_pair(input_map, compression, parameters, 0)?;
+
+    Ok(Base {
+        g1_one: g1(ElementType::TauG1, 0, &base.tau_powers_g1[0])?,
+        g1_tau: g1(ElementType::TauG1, 1, &base.tau_powers_g1[1])?,
+        g2_one: g2(ElementType::TauG2, 0, &base.tau_powers_g2[0])?,
+        g2_tau: g2(ElementType::TauG2, 1, &base.tau_powers_g2[1])?,
+        g2_beta: g2(ElementType::BetaG2, 0, &base.beta_g2)?,
+    })
+}
+
+/// Re-verifies the pair of consecutive powers at `index` of every power of tau.
+fn check_pair(
+    input_map: &Mmap,
+    compression: UseCompression,
+    parameters: &CeremonyParams<Bn256>,
+    base: &Base,
+    index: usize,
+) -> Result<(), CrossCheckError> {
+    let pair = read_pair(input_map, compression, parameters, index)?;
+
+    let tau_g1 = (
+        g1(ElementType::TauG1, index, &pair.tau_powers_g1[0])?,
+        g1(ElementType::TauG1, index + 1, &pair.tau_powers_g1[1])?,
+    );
+    check(
+        same_ratio(tau_g1, (base.g2_one, base.g2_tau)),
+        ElementType::TauG1,
+        index,
+    )?;
+
+    // Other powers are only available below `powers_length`
+    if index + 1 >= parameters.powers_length {
+        return Ok(());
+    }
+
+    let tau_g2 = (
+        g2(ElementType::TauG2, index, &pair.tau_powers_g2[0])?,
+        g2(ElementType::TauG2, index + 1, &pair.tau_powers_g2[1])?,
+    );
+    check(
+        same_ratio((base.g1_one, base.g1_tau), tau_g2),
+        ElementType::TauG2,
+        index,
+    )?;
+
+    let alpha_g1 = (
+        g1(ElementType::AlphaG1, index, &pair.alpha_tau_powers_g1[0])?,
+        g1(
+            ElementType::AlphaG1,
+            index + 1,
+            &pair.alpha_tau_powers_g1[1],
+        )?,
+    );
+    check(
+        same_ratio(alpha_g1, (base.g2_one, base.g2_tau)),
+        ElementType::AlphaG1,
+        index,
+    )?;
+
+    let beta_g1 = (
+        g1(ElementType::BetaG1, index, &pair.beta_tau_powers_g1[0])?,
+        g1(ElementType::BetaG1, index + 1, &pair.beta_tau_powers_g1[1])?,
+    );
+    check(
+        same_ratio(beta_g1, (base.g2_one, base.g2_tau)),
+        ElementType::BetaG1,
+        index,
+    )?;
+    check(
+        same_ratio((tau_g1.0, beta_g1.0), (base.g2_one, base.g2_beta)),
+        ElementType::BetaG2,
+        index,
+    )
+}
+
+/// Re-verifies `sample` randomly chosen consecutive pairs of every power of tau in the
+/// accumulator at `input_map`, together with the points needed to check them.
+pub fn cross_check_sample<R: Rng>(
+    input_map: &Mmap,
+    compression: UseCompression,
+    parameters: &CeremonyParams<Bn256>,
+    sample: usize,
+    rng: &mut R,
+) -> Result<(), CrossCheckError> {
+    let base = read_base(input_map, compression, parameters)?;
+    for _ in 0..sample {
+        let index = rng.gen_range(0, parameters.powers_g1_length - 1);
+        check_pair(input_map, compression, parameters, &base, index)?;
+    }
+
+    info!(
+        "Cross-checked {} samples with an independent implementation",
+        sample
+    );
+
+    Ok(())
+}
+
+/// Same as `cross_check_sample` with the pairs selected by `seed`, but checks all of them
+/// and reports which passed instead of stopping at the first failure. `file_hash` is the
+/// BLAKE2b hash of the accumulator, to tell reports of different files apart. Errors are
+/// only returned if the accumulator can't be read or its generators are invalid.
+pub fn cross_check_report(
+    input_map: &Mmap,
+    compression: UseCompression,
+    parameters: &CeremonyParams<Bn256>,
+    sample: usize,
+    seed: &[u8; 32],
+    file_hash: &[u8],
+) -> Result<SampleReport, CrossCheckError> {
+    let base = read_base(input_map, compression, parameters)?;
+    let mut checks = vec![];
+    for index in sampled_indices(seed, sample, parameters) {
+        let passed = match check_pair(input_map, compression, parameters, &base, index) {
+            Ok(()) => true,
+            Err(CrossCheckError::DeserializationError(e)) => return Err(e.into()),
+            Err(e) => {
+                error!("Cross-check of pair {} failed: {}", index, e);
+                false
+            }
+        };
+        checks.push((index, passed));
+    }
+
+    let mut report = SampleReport {
+        seed: *seed,
+        file_hash: [0; 64],
+        checks,
+    };
+    report.file_hash.copy_from_slice(file_hash);
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::keypair;
+    use memmap::MmapMut;
+    use rand::thread_rng;
+
+    fn transformed_map(parameters: &CeremonyParams<Bn256>) -> MmapMut {
+        let mut initial = MmapMut::map_anon(parameters.accumulator_size).unwrap();
+        BatchedAccumulator::generate_initial(&mut initial, UseCompression::No, parameters).unwrap();
+        let initial = initial.make_read_only().unwrap();
+
+        let rng = &mut thread_rng();
+        let digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+        let (_, private_key) = keypair::<_, Bn256>(rng, &digest);
+
+        let mut output = MmapMut::map_anon(parameters.accumulator_size).unwrap();
+        BatchedAccumulator::transform(
+            &initial,
+            &mut output,
+            UseCompression::No,
+            UseCompression::No,
+            CheckForCorrectness::Yes,
+            &private_key,
+            parameters,
+        )
+        .unwrap();
+
+        output
+    }
+
+    #[test]
+    fn test_cross_check_transformed_accumulator() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let map = transformed_map(&parameters).make_read_only().unwrap();
+
+        cross_check_sample(&map, UseCompression::No, &parameters, 8, &mut thread_rng()).unwrap();
+    }
+
+    #[test]
+    fn test_cross_check_detects_bad_power() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let mut map = transformed_map(&parameters);
+
+        // Replace every tau power in G1 with the generator
+        let g1_size = parameters.curve.g1;
+        let one = G1Affine::one().into_uncompressed();
+        for i in 1..parameters.powers_g1_length {
+            let position = parameters.hash_size + i * g1_size;
+            map[position..position + g1_size].copy_from_slice(one.as_ref());
+        }
+        let map = map.make_read_only().unwrap();
+
+        assert!(
+            cross_check_sample(&map, UseCompression::No, &parameters, 1, &mut thread_rng())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_cross_check_report() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let mut map = transformed_map(&parameters);
+
+        // Pairs 4 and 5 contain the replaced power
+        let g1_size = parameters.curve.g1;
+        let position = parameters.hash_size + 5 * g1_size;
+        map[position..position + g1_size]
+            .copy_from_slice(G1Affine::one().into_uncompressed().as_ref());
+        let map = map.make_read_only().unwrap();
+
+        let report = cross_check_report(
+            &map,
+            UseCompression::No,
+            &parameters,
+            64,
+            &[9; 32],
+            &[1; 64],
+        )
+        .unwrap();
+        assert_eq!(report.checks.len(), 64);
+        assert!(report.checks.iter().any(|&(_, passed)| !passed));
+        for &(index, passed) in &report.checks {
+            assert_eq!(passed, index != 4 && index != 5, "pair {}", index);
+        }
+        // The same seed checks the same pairs
+        assert_eq!(
+            cross_check_report(
+                &map,
+                UseCompression::No,
+                &parameters,
+                64,
+                &[9; 32],
+                &[1; 64]
+            )
+            .unwrap(),
+            report
+        );
+    }
+}
@@ -0,0 +1,185 @@
+/// Reading accumulator files from slow storage. The files are memory mapped, and every chunk is
+/// decoded on all cores at once, so its pages are read in no particular order from each section
+/// of the file. Spinning and network attached disks are much faster at reading the same bytes in
+/// order, so the pages of a chunk can be read ahead in file order before it is decoded, at a
+/// bounded rate that leaves bandwidth to other users of the disk.
+use std::ops::Range;
+use std::ptr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::errors::{self, Error, ErrorCategory};
+
+/// Every page in this many bytes is touched, which is the smallest page size in use.
+const PAGE_SIZE: usize = 4096;
+/// Reads are rate limited in blocks of this many bytes.
+const BLOCK_SIZE: usize = 1 << 20;
+
+/// How chunks of the files are read.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReadOptions {
+    /// Number of decoded chunks that are kept ready while another one is checked
+    pub read_ahead: usize,
+    /// Whether the pages of a chunk are read in file order before it is decoded
+    pub prefetch: bool,
+    /// Maximum rate of the reads ahead in bytes per second, if limited
+    pub max_rate: Option<u64>,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        ReadOptions {
+            read_ahead: 1,
+            prefetch: false,
+            max_rate: None,
+        }
+    }
+}
+
+/// Spreads reads over time so that they don't exceed a rate.
+pub struct RateLimiter {
+    rate: u64,
+    start: Instant,
+    bytes: u64,
+}
+
+impl RateLimiter {
+    /// Limits reads to `rate` bytes per second.
+    pub fn new(rate: u64) -> Self {
+        assert!(rate > 0, "the rate must be positive");
+        RateLimiter {
+            rate,
+            start: Instant::now(),
+            bytes: 0,
+        }
+    }
+
+    /// Waits until `bytes` more bytes can be read without exceeding the rate since the limiter
+    /// was created.
+    pub fn acquire(&mut self, bytes: u64) {
+        self.bytes += bytes;
+        let due = Duration::from_secs_f64(self.bytes as f64 / self.rate as f64);
+        let elapsed = self.start.elapsed();
+        if due > elapsed {
+            thread::sleep(due - elapsed);
+        }
+    }
+}
+
+/// Reads the pages of `ranges` of `data` in file order, so that they are in memory when they
+/// are decoded. Ranges are clipped to `data`. Returns the number of bytes read.
+pub fn prefetch(
+    data: &[u8],
+    ranges: &mut [Range<usize>],
+    mut limiter: Option<&mut RateLimiter>,
+) -> usize {
+    ranges.sort_by_key(|range| range.start);
+    let mut read = 0;
+    for range in ranges.iter() {
+        let range = range.start.min(data.len())..range.end.min(data.len());
+        for block in data[range].chunks(BLOCK_SIZE) {
+            if let Some(limiter) = limiter.as_mut() {
+                limiter.acquire(block.len() as u64);
+            }
+            for page in block.chunks(PAGE_SIZE) {
+                // The read must happen even though its value is unused
+                unsafe { ptr::read_volatile(&page[0]) };
+            }
+            read += block.len();
+        }
+    }
+
+    read
+}
+
+/// Removes `--read-ahead <chunks>`, `--prefetch` and `--max-read-rate <MiB/s>` from the
+/// arguments and returns the options they select. A rate also enables prefetching, as only
+/// the reads ahead are limited.
+pub fn take_read_options(args: &mut Vec<String>) -> Result<ReadOptions, Error> {
+    let mut take = |flag: &str| -> Result<Option<u64>, Error> {
+        let position = match args.iter().position(|arg| arg == flag) {
+            Some(position) => position,
+            None => return Ok(None),
+        };
+        let value = args
+            .get(position + 1)
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| {
+                Error::new(
+                    errors::INVALID_ARGUMENT,
+                    ErrorCategory::InvalidInput,
+                    format!("{} requires a number", flag),
+                )
+            })?;
+        args.drain(position..position + 2);
+
+        Ok(Some(value))
+    };
+
+    let mut options = ReadOptions::default();
+    if let Some(read_ahead) = take("--read-ahead")? {
+        options.read_ahead = read_ahead as usize;
+    }
+    if let Some(rate) = take("--max-read-rate")? {
+        if rate == 0 {
+            return Err(Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                "--max-read-rate must be positive",
+            ));
+        }
+        options.max_rate = Some(rate.saturating_mul(1 << 20));
+        options.prefetch = true;
+    }
+    if let Some(position) = args.iter().position(|arg| arg == "--prefetch") {
+        args.remove(position);
+        options.prefetch = true;
+    }
+
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefetch() {
+        let data = vec![1u8; 3 * BLOCK_SIZE];
+        let mut ranges = vec![2 * BLOCK_SIZE..4 * BLOCK_SIZE, 10..20, 0..0];
+        assert_eq!(prefetch(&data, &mut ranges, None), BLOCK_SIZE + 10);
+        assert_eq!(ranges[0], 0..0);
+
+        // Four blocks at eight blocks per second take half a second
+        let mut limiter = RateLimiter::new(8 * BLOCK_SIZE as u64);
+        let mut ranges = vec![BLOCK_SIZE..2 * BLOCK_SIZE, 0..BLOCK_SIZE];
+        let start = Instant::now();
+        prefetch(&data, &mut ranges, Some(&mut limiter));
+        prefetch(&data, &mut ranges, Some(&mut limiter));
+        assert!(start.elapsed() >= Duration::from_millis(450));
+    }
+
+    #[test]
+    fn test_take_read_options() {
+        let strings = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let mut args = strings(&["cmd", "--read-ahead", "4", "--max-read-rate", "100"]);
+        assert_eq!(
+            take_read_options(&mut args).unwrap(),
+            ReadOptions {
+                read_ahead: 4,
+                prefetch: true,
+                max_rate: Some(100 << 20),
+            }
+        );
+        assert_eq!(args, strings(&["cmd"]));
+        assert_eq!(
+            take_read_options(&mut args).unwrap(),
+            ReadOptions::default()
+        );
+
+        let mut args = strings(&["--prefetch"]);
+        assert!(take_read_options(&mut args).unwrap().prefetch);
+        assert!(take_read_options(&mut strings(&["--max-read-rate", "0"])).is_err());
+        assert!(take_read_options(&mut strings(&["--read-ahead"])).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+/// Proof that a participant has downloaded a challenge. Before handing out a contribution
+/// slot, the coordinator picks random byte ranges of the challenge and a fresh nonce, and the
+/// participant answers with
+///
+/// proof<sub>i</sub> = BLAKE2b(bytes of range i | nonce)
+///
+/// for every range. Without the nonce the answers cannot be precomputed or shared, and without
+/// the ranges of the file they cannot be computed, so slots can't be held by participants who
+/// never fetched the challenge. It proves possession of the sampled ranges only, not of the
+/// whole file.
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use rand::Rng;
+
+use std::io::{self, Read, Write};
+use std::ops::Range;
+
+/// Identify download challenge and proof files, followed by the format version.
+const CHALLENGE_MAGIC: &[u8; 8] = b"POTDLCHL";
+const PROOF_MAGIC: &[u8; 8] = b"POTDLPRF";
+const VERSION: u32 = 1;
+
+/// Number of ranges used by the command line tools unless another one is given.
+pub const DEFAULT_RANGE_COUNT: usize = 16;
+/// Length of the ranges used by the command line tools unless another one is given.
+pub const DEFAULT_RANGE_LENGTH: u64 = 1 << 20;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DownloadChallenge {
+    pub nonce: [u8; 32],
+    /// Length of the file the ranges were picked from
+    pub length: u64,
+    pub ranges: Vec<Range<u64>>,
+}
+
+/// Answer to a `DownloadChallenge`, one hash per range.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DownloadProof {
+    pub hashes: Vec<[u8; 64]>,
+}
+
+fn hash_range(range: &[u8], nonce: &[u8]) -> [u8; 64] {
+    let mut hasher = Blake2b::default();
+    hasher.input(range);
+    hasher.input(nonce);
+    let mut hash = [0; 64];
+    hash.copy_from_slice(hasher.result().as_slice());
+    hash
+}
+
+fn invalid_data<T>(message: &str) -> io::Result<T> {
+    Err(io::Error::new(io::ErrorKind::InvalidData, message))
+}
+
+fn read_header<R: Read>(reader: &mut R, magic: &[u8; 8], name: &str) -> io::Result<()> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    if &bytes != magic {
+        return invalid_data(&format!("not a {}", name));
+    }
+    let version = reader.read_u32::<BigEndian>()?;
+    if version != VERSION {
+        return invalid_data(&format!("unsupported {} version {}", name, version));
+    }
+
+    Ok(())
+}
+
+impl DownloadChallenge {
+    /// Picks `count` random ranges of `range_length` bytes of a file of `length` bytes. The
+    /// ranges are shortened to the length of the file if it is smaller.
+    pub fn new<R: Rng>(rng: &mut R, length: u64, count: usize, range_length: u64) -> Self {
+        let mut nonce = [0; 32];
+        rng.fill_bytes(&mut nonce);
+
+        let range_length = std::cmp::min(range_length, length);
+        let ranges = if length == 0 {
+            vec![]
+        } else {
+            (0..count)
+                .map(|_| {
+                    let start = rng.gen_range(0, length - range_length + 1);
+                    start..start + range_length
+                })
+                .collect()
+        };
+
+        DownloadChallenge {
+            nonce,
+            length,
+            ranges,
+        }
+    }
+
+    /// Answers the challenge with the downloaded file in `data`.
+    pub fn prove(&self, data: &[u8]) -> io::Result<DownloadProof> {
+        if data.len() as u64 != self.length {
+            return invalid_data(&format!(
+                "the challenge is for a file of {} bytes, but the file has {} bytes",
+                self.length,
+                data.len()
+            ));
+        }
+
+        let hashes = self
+            .ranges
+            .iter()
+            .map(|range| hash_range(&data[range.start as usize..range.end as usize], &self.nonce))
+            .collect();
+
+        Ok(DownloadProof { hashes })
+    }
+
+    /// Checks a proof against the original file in `data`.
+    pub fn check(&self, data: &[u8], proof: &DownloadProof) -> io::Result<bool> {
+        Ok(self.prove(data)? == *proof)
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&CHALLENGE_MAGIC[..])?;
+        writer.write_u32::<BigEndian>(VERSION)?;
+        writer.write_all(&self.nonce)?;
+        writer.write_u64::<BigEndian>(self.length)?;
+        writer.write_u32::<BigEndian>(self.ranges.len() as u32)?;
+        for range in &self.ranges {
+            writer.write_u64::<BigEndian>(range.start)?;
+            writer.write_u64::<BigEndian>(range.end)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        read_header(reader, CHALLENGE_MAGIC, "download challenge")?;
+
+        let mut nonce = [0; 32];
+        reader.read_exact(&mut nonce)?;
+        let length = reader.read_u64::<BigEndian>()?;
+        let count = reader.read_u32::<BigEndian>()?;
+        // The count is untrusted, so ranges are only allocated as they are read
+        let mut ranges = vec![];
+        for _ in 0..count {
+            let start = reader.read_u64::<BigEndian>()?;
+            let end = reader.read_u64::<BigEndian>()?;
+            if start > end || end > length {
+                return invalid_data("range outside of the file");
+            }
+            ranges.push(start..end);
+        }
+
+        Ok(DownloadChallenge {
+            nonce,
+            length,
+            ranges,
+        })
+    }
+}
+
+impl DownloadProof {
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&PROOF_MAGIC[..])?;
+        writer.write_u32::<BigEndian>(VERSION)?;
+        writer.write_u32::<BigEndian>(self.hashes.len() as u32)?;
+        for hash in &self.hashes {
+            writer.write_all(&hash[..])?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        read_header(reader, PROOF_MAGIC, "download proof")?;
+
+        let count = reader.read_u32::<BigEndian>()?;
+        let mut hashes = vec![];
+        for _ in 0..count {
+            let mut hash = [0; 64];
+            reader.read_exact(&mut hash)?;
+            hashes.push(hash);
+        }
+
+        Ok(DownloadProof { hashes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::thread_rng;
+
+    fn random_bytes(length: usize) -> Vec<u8> {
+        let rng = &mut thread_rng();
+        (0..length).map(|_| rng.gen()).collect()
+    }
+
+    #[test]
+    fn test_download_proof() {
+        let rng = &mut thread_rng();
+        let data = random_bytes(1000);
+        let challenge = DownloadChallenge::new(rng, 1000, 8, 100);
+        assert_eq!(challenge.ranges.len(), 8);
+        assert!(challenge
+            .ranges
+            .iter()
+            .all(|range| range.end - range.start == 100));
+
+        let proof = challenge.prove(&data).unwrap();
+        assert!(challenge.check(&data, &proof).unwrap());
+
+        // Another nonce needs another proof
+        let other = DownloadChallenge {
+            nonce: [0; 32],
+            ..challenge.clone()
+        };
+        assert!(!other.check(&data, &proof).unwrap());
+
+        // So does another file
+        let mut corrupted = data.clone();
+        let position = challenge.ranges[0].start as usize;
+        corrupted[position] ^= 1;
+        assert!(!challenge.check(&corrupted, &proof).unwrap());
+        assert!(challenge.prove(&data[..999]).is_err());
+
+        // Ranges are shortened to small files
+        let challenge = DownloadChallenge::new(rng, 10, 2, 100);
+        assert_eq!(challenge.ranges, vec![0..10, 0..10]);
+        assert!(DownloadChallenge::new(rng, 0, 2, 100).ranges.is_empty());
+    }
+
+    #[test]
+    fn test_download_proof_serialization() {
+        let rng = &mut thread_rng();
+        let data = random_bytes(300);
+        let challenge = DownloadChallenge::new(rng, 300, 3, 50);
+        let proof = challenge.prove(&data).unwrap();
+
+        let mut bytes = vec![];
+        challenge.write(&mut bytes).unwrap();
+        assert_eq!(DownloadChallenge::read(&mut &bytes[..]).unwrap(), challenge);
+        // A proof is not a challenge
+        let mut proof_bytes = vec![];
+        proof.write(&mut proof_bytes).unwrap();
+        assert!(DownloadChallenge::read(&mut &proof_bytes[..]).is_err());
+        assert_eq!(DownloadProof::read(&mut &proof_bytes[..]).unwrap(), proof);
+
+        // Ranges past the end of the file are rejected
+        let mut invalid = challenge.clone();
+        invalid.ranges[0] = 290..301;
+        bytes.clear();
+        invalid.write(&mut bytes).unwrap();
+        assert!(DownloadChallenge::read(&mut &bytes[..]).is_err());
+    }
+}
@@ -0,0 +1,198 @@
+/// Errors reported by the command line tools. Every error has a stable code and a category,
+/// and the category decides the exit code of the process, so that scripts driving a ceremony
+/// can tell a full disk from a bad contribution without parsing messages.
+use std::fmt;
+use std::io;
+
+use super::parameters::DeserializationError;
+
+pub const IO: u16 = 100;
+pub const FILE_NOT_FOUND: u16 = 101;
+pub const PERMISSION_DENIED: u16 = 102;
+pub const DISK_FULL: u16 = 103;
+pub const HOOK_FAILED: u16 = 104;
+pub const OUTPUT_EXISTS: u16 = 105;
+pub const INTERRUPTED: u16 = 106;
+pub const INVALID_FILE_SIZE: u16 = 200;
+pub const INVALID_POINT: u16 = 201;
+pub const INVALID_ARGUMENT: u16 = 202;
+pub const COMPRESSION_MISMATCH: u16 = 204;
+pub const HASH_CHAIN_BROKEN: u16 = 300;
+pub const INVALID_CONTRIBUTION: u16 = 301;
+pub const MISSING_CONTRIBUTION: u16 = 302;
+pub const HASH_MISMATCH: u16 = 303;
+pub const INTERNAL: u16 = 400;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ErrorCategory {
+    Io,
+    InvalidInput,
+    Verification,
+    Internal,
+}
+
+impl ErrorCategory {
+    /// Process exit code used for errors of this category.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorCategory::Io => exitcode::IOERR,
+            ErrorCategory::InvalidInput => exitcode::DATAERR,
+            ErrorCategory::Verification => exitcode::PROTOCOL,
+            ErrorCategory::Internal => exitcode::SOFTWARE,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorCategory::Io => write!(f, "IO"),
+            ErrorCategory::InvalidInput => write!(f, "InvalidInput"),
+            ErrorCategory::Verification => write!(f, "Verification"),
+            ErrorCategory::Internal => write!(f, "Internal"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub code: u16,
+    pub category: ErrorCategory,
+    pub message: String,
+    /// What the user can do about the error, if anything
+    pub hint: Option<&'static str>,
+}
+
+impl Error {
+    pub fn new<S: Into<String>>(code: u16, category: ErrorCategory, message: S) -> Self {
+        Error {
+            code,
+            category,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    pub fn with_hint(mut self, hint: &'static str) -> Self {
+        self.hint = Some(hint);
+        self
+    }
+
+    /// Prefixes the message with what was being done when the error happened.
+    pub fn context<S: AsRef<str>>(mut self, context: S) -> Self {
+        self.message = format!("{}: {}", context.as_ref(), self.message);
+        self
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.category.exit_code()
+    }
+
+    /// Reports the error and terminates the process with the exit code of its category.
+    pub fn exit(&self) -> ! {
+        eprintln!("{}", self);
+        std::process::exit(self.exit_code())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "error E{} ({}): {}",
+            self.code, self.category, self.message
+        )?;
+        if let Some(hint) = self.hint {
+            write!(f, "\nhint: {}", hint)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        let error = |code| Error::new(code, ErrorCategory::Io, err.to_string());
+        match err.kind() {
+            io::ErrorKind::NotFound => error(FILE_NOT_FOUND)
+                .with_hint("check that the file exists and the path is spelled correctly"),
+            io::ErrorKind::PermissionDenied => error(PERMISSION_DENIED)
+                .with_hint("check the permissions of the file and directory"),
+            io::ErrorKind::StorageFull => {
+                error(DISK_FULL).with_hint("free up disk space and run the command again")
+            }
+            io::ErrorKind::UnexpectedEof => Error::new(
+                INVALID_FILE_SIZE,
+                ErrorCategory::InvalidInput,
+                err.to_string(),
+            )
+            .with_hint("the file is truncated, check that it was transferred completely"),
+            _ => error(IO),
+        }
+    }
+}
+
+impl From<DeserializationError> for Error {
+    fn from(err: DeserializationError) -> Error {
+        match err {
+            DeserializationError::IoError(e) => e.into(),
+            e @ DeserializationError::CompressionMismatch { .. } => Error::new(
+                COMPRESSION_MISMATCH,
+                ErrorCategory::InvalidInput,
+                e.to_string(),
+            )
+            .with_hint("check that the file is the output of the previous step of the ceremony, responses are compressed and challenges are not"),
+            e => Error::new(INVALID_POINT, ErrorCategory::InvalidInput, e.to_string())
+                .with_hint("the file is corrupted or was not produced by this ceremony"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parameters::UseCompression;
+
+    #[test]
+    fn test_categories_have_distinct_exit_codes() {
+        let categories = [
+            ErrorCategory::Io,
+            ErrorCategory::InvalidInput,
+            ErrorCategory::Verification,
+            ErrorCategory::Internal,
+        ];
+        for (i, a) in categories.iter().enumerate() {
+            for b in categories.iter().skip(i + 1) {
+                assert_ne!(a.exit_code(), b.exit_code());
+            }
+        }
+    }
+
+    #[test]
+    fn test_io_error_conversion() {
+        let error: Error = io::Error::new(io::ErrorKind::NotFound, "challenge").into();
+        assert_eq!(error.code, FILE_NOT_FOUND);
+        assert_eq!(error.category, ErrorCategory::Io);
+        assert!(error.hint.is_some());
+
+        let error: Error = DeserializationError::PointAtInfinity.into();
+        assert_eq!(error.category, ErrorCategory::InvalidInput);
+
+        let error: Error = DeserializationError::CompressionMismatch {
+            expected: UseCompression::No,
+            detected: UseCompression::Yes,
+        }
+        .into();
+        assert_eq!(error.code, COMPRESSION_MISMATCH);
+        assert_eq!(
+            error.message,
+            "The file is compressed, but it was expected to be uncompressed"
+        );
+
+        let error: Error = io::Error::new(io::ErrorKind::UnexpectedEof, "response").into();
+        assert_eq!(error.code, INVALID_FILE_SIZE);
+        assert_eq!(error.category, ErrorCategory::InvalidInput);
+    }
+}
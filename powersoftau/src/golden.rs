@@ -0,0 +1,173 @@
+/// Golden tests of the wire format: points, public keys, hash headers and small accumulators of
+/// every curve, in every compression, are serialized and compared byte for byte with the
+/// fixtures in `fixtures/golden`. Transcripts must stay verifiable for as long as the setup is
+/// used, so an upgrade of the algebra that changes any encoding has to fail here rather than in
+/// a ceremony. Everything is computed from fixed scalars, without randomness.
+///
+/// `UPDATE_GOLDEN=1 cargo test golden` writes the fixtures. That's for new curves and new
+/// fixtures only: a fixture that no longer matches is a change of the format, not of the test.
+use bellman_ce::pairing::bls12_381::Bls12;
+use bellman_ce::pairing::bn256::Bn256;
+use bellman_ce::pairing::ff::PrimeField;
+use bellman_ce::pairing::{CurveAffine, CurveProjective, Engine};
+use memmap::MmapMut;
+
+use std::fs;
+use std::path::PathBuf;
+
+use super::batched_accumulator::BatchedAccumulator;
+use super::keypair::{PrivateKey, PublicKey};
+use super::parameters::{expected_sizes, CeremonyParams, CheckForCorrectness, UseCompression};
+use super::utils::{blank_hash, calculate_hash, write_hash_header, write_point};
+
+const COMPRESSIONS: [UseCompression; 3] = [
+    UseCompression::No,
+    UseCompression::Yes,
+    UseCompression::Mixed,
+];
+
+/// The secrets of the contribution of the fixtures, tau, alpha and beta.
+const SECRETS: [u64; 3] = [7, 11, 13];
+
+fn scalar<E: Engine>(n: u64) -> E::Fr {
+    E::Fr::from_str(&n.to_string()).unwrap()
+}
+
+fn g1<E: Engine>(n: u64) -> E::G1Affine {
+    E::G1Affine::one().mul(scalar::<E>(n)).into_affine()
+}
+
+fn g2<E: Engine>(n: u64) -> E::G2Affine {
+    E::G2Affine::one().mul(scalar::<E>(n)).into_affine()
+}
+
+/// Compares `bytes` with the fixture `name` of `curve`, or writes the fixture with
+/// `UPDATE_GOLDEN`. Fixtures are in hex, 32 bytes per line, so that a change shows up where it
+/// is in a diff.
+fn check_golden(curve: &str, name: &str, bytes: &[u8]) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures/golden")
+        .join(curve)
+        .join(format!("{}.hex", name));
+    let lines: Vec<String> = bytes.chunks(32).map(hex::encode).collect();
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, lines.join("\n") + "\n").unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("unable to read {}: {}", path.display(), e));
+    let expected: Vec<&str> = expected.lines().collect();
+    if let Some(line) = (0..expected.len().max(lines.len()))
+        .find(|&i| expected.get(i).copied() != lines.get(i).map(String::as_str))
+    {
+        panic!(
+            "the encoding of {} on {} changed at byte {}",
+            name,
+            curve,
+            line * 32
+        );
+    }
+}
+
+/// Points with the sign of y set and not, and the point at infinity.
+fn points<C: CurveAffine>(g: impl Fn(u64) -> C) -> Vec<C> {
+    let mut negated = g(5);
+    negated.negate();
+    vec![C::zero(), C::one(), g(5), negated, g(u64::MAX)]
+}
+
+fn encode<C: CurveAffine>(points: &[C], compression: UseCompression) -> Vec<u8> {
+    let mut bytes = vec![];
+    for point in points {
+        write_point(&mut bytes, point, compression).unwrap();
+    }
+    bytes
+}
+
+fn golden<E: Engine>(curve: &str) {
+    for &(compression, suffix) in [
+        (UseCompression::No, "uncompressed"),
+        (UseCompression::Yes, "compressed"),
+    ]
+    .iter()
+    {
+        check_golden(
+            curve,
+            &format!("g1_{}", suffix),
+            &encode(&points(g1::<E>), compression),
+        );
+        check_golden(
+            curve,
+            &format!("g2_{}", suffix),
+            &encode(&points(g2::<E>), compression),
+        );
+    }
+
+    // The key of a contribution with `SECRETS`, with fixed points in place of the random ones
+    let [tau, alpha, beta] = SECRETS;
+    let public_key = PublicKey::<E> {
+        tau_g1: (g1::<E>(2), g1::<E>(2 * tau)),
+        alpha_g1: (g1::<E>(3), g1::<E>(3 * alpha)),
+        beta_g1: (g1::<E>(5), g1::<E>(5 * beta)),
+        tau_g2: g2::<E>(tau),
+        alpha_g2: g2::<E>(alpha),
+        beta_g2: g2::<E>(beta),
+    };
+    let mut bytes = vec![];
+    public_key.serialize(&mut bytes).unwrap();
+    check_golden(curve, "public_key", &bytes);
+
+    // Accumulators of 7 powers of tau in G1, read and written in chunks of 3
+    let parameters = CeremonyParams::<E>::new(2, 3);
+    let private_key = PrivateKey::<E> {
+        tau: scalar::<E>(tau),
+        alpha: scalar::<E>(alpha),
+        beta: scalar::<E>(beta),
+    };
+    let mut challenge =
+        MmapMut::map_anon(expected_sizes(&parameters, UseCompression::No).challenge).unwrap();
+    for &compression in COMPRESSIONS.iter() {
+        let sizes = expected_sizes(&parameters, compression);
+        let mut initial = MmapMut::map_anon(sizes.challenge).unwrap();
+        write_hash_header(&mut initial, &blank_hash()).unwrap();
+        BatchedAccumulator::generate_initial(&mut initial, compression, &parameters).unwrap();
+        check_golden(curve, &format!("challenge_{}", compression), &initial);
+        if compression == UseCompression::No {
+            challenge.copy_from_slice(&initial);
+        }
+    }
+
+    // Responses to the uncompressed challenge, which start with its hash and end with the key
+    let challenge = challenge.make_read_only().unwrap();
+    for &compression in COMPRESSIONS.iter() {
+        let mut response =
+            MmapMut::map_anon(expected_sizes(&parameters, compression).response).unwrap();
+        write_hash_header(&mut response, &calculate_hash(&challenge)).unwrap();
+        BatchedAccumulator::transform(
+            &challenge,
+            &mut response,
+            UseCompression::No,
+            compression,
+            CheckForCorrectness::Yes,
+            &private_key,
+            &parameters,
+        )
+        .unwrap();
+        public_key
+            .write(&mut response, compression, &parameters)
+            .unwrap();
+        check_golden(curve, &format!("response_{}", compression), &response);
+    }
+}
+
+#[test]
+fn test_golden_bn256() {
+    golden::<Bn256>("bn256");
+}
+
+#[test]
+fn test_golden_bls12_381() {
+    golden::<Bls12>("bls12_381");
+}
@@ -0,0 +1,237 @@
+/// Files that the BLAKE2b hashes of challenges and responses are published in. They can be
+/// written as plain hex, in the format of `b2sum` so that `b2sum -c` checks them on any
+/// transfer host, as the raw 64 bytes, or as JSON with the context of the command. Reading
+/// accepts all of them, so a hash published in any format can be checked by the commands.
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::errors::{self, Error, ErrorCategory};
+use super::hooks::HookContext;
+use super::utils::json_string;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum HashFormat {
+    /// The hash in lowercase hex, followed by a newline
+    Hex,
+    /// `<hash>  <file>`, as written by `b2sum`
+    #[default]
+    B2sum,
+    /// The 64 bytes of the hash
+    Binary,
+    /// The hash with the command, the output file and the hash of the input
+    Json,
+}
+
+impl FromStr for HashFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "hex" => Ok(HashFormat::Hex),
+            "b2sum" => Ok(HashFormat::B2sum),
+            "binary" => Ok(HashFormat::Binary),
+            "json" => Ok(HashFormat::Json),
+            _ => Err(Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                format!("unknown hash format {}", s),
+            )
+            .with_hint("use one of hex, b2sum, binary or json")),
+        }
+    }
+}
+
+/// Encodes the hash of the output of a command.
+pub fn encode_hash(format: HashFormat, context: &HookContext) -> Vec<u8> {
+    let hash = hex::encode(&context.output_hash[..]);
+    match format {
+        HashFormat::Hex => format!("{}\n", hash).into_bytes(),
+        HashFormat::B2sum => format!("{}  {}\n", hash, context.output.display()).into_bytes(),
+        HashFormat::Binary => context.output_hash.to_vec(),
+        HashFormat::Json => {
+            format!(
+                "{{\"algorithm\":\"blake2b-512\",\"hash\":\"{}\",\"file\":{},\"command\":{},\"input_hash\":\"{}\"}}\n",
+                hash,
+                json_string(&context.output.display().to_string()),
+                json_string(&context.command),
+                hex::encode(&context.input_hash[..])
+            )
+            .into_bytes()
+        }
+    }
+}
+
+fn parse_hex_hash(hex_hash: &str) -> Option<[u8; 64]> {
+    let bytes = hex::decode(hex_hash).ok()?;
+    if bytes.len() != 64 {
+        return None;
+    }
+    let mut hash = [0; 64];
+    hash.copy_from_slice(&bytes);
+    Some(hash)
+}
+
+/// Decodes a hash written in any of the formats.
+pub fn decode_hash(bytes: &[u8]) -> Result<[u8; 64], Error> {
+    let invalid = || {
+        Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            "not a BLAKE2b hash in hex, b2sum, binary or JSON format",
+        )
+    };
+
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text.trim(),
+        // Raw hashes are rarely valid UTF-8
+        Err(_) if bytes.len() == 64 => {
+            let mut hash = [0; 64];
+            hash.copy_from_slice(bytes);
+            return Ok(hash);
+        }
+        Err(_) => return Err(invalid()),
+    };
+
+    let hex_hash = if text.starts_with('{') {
+        // The value of the `hash` key: `"hash"`, optional whitespace, `:`, and the string
+        let value = text
+            .find("\"hash\"")
+            .map(|position| text[position + 6..].trim_start())
+            .and_then(|rest| rest.strip_prefix(':'))
+            .and_then(|rest| rest.trim_start().strip_prefix('"'))
+            .and_then(|rest| rest.split('"').next());
+        value.ok_or_else(invalid)?
+    } else {
+        // Hex, or the first of the hashes in a b2sum file
+        text.split_whitespace().next().unwrap_or("")
+    };
+
+    match parse_hex_hash(hex_hash) {
+        Some(hash) => Ok(hash),
+        None if bytes.len() == 64 => {
+            let mut hash = [0; 64];
+            hash.copy_from_slice(bytes);
+            Ok(hash)
+        }
+        None => Err(invalid()),
+    }
+}
+
+/// Reads the hash published in `path`.
+pub fn read_hash_file<P: AsRef<Path>>(path: P) -> Result<[u8; 64], Error> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|e| {
+        Error::from(e).context(format!("unable to read hash file {}", path.display()))
+    })?;
+
+    decode_hash(&bytes).map_err(|e| e.context(format!("invalid hash file {}", path.display())))
+}
+
+/// Checks `hash`, the hash of `file_name`, against the one published in `path`.
+pub fn check_expected_hash<P: AsRef<Path>>(
+    path: P,
+    file_name: &str,
+    hash: &[u8],
+) -> Result<(), Error> {
+    let expected = read_hash_file(&path)?;
+    if expected[..] != *hash {
+        return Err(Error::new(
+            errors::HASH_MISMATCH,
+            ErrorCategory::Verification,
+            format!(
+                "the hash of {} is {}, but {} expects {}",
+                file_name,
+                hex::encode(hash),
+                path.as_ref().display(),
+                hex::encode(&expected[..])
+            ),
+        )
+        .with_hint("check that the file was fully transferred and is the one that was published"));
+    }
+
+    Ok(())
+}
+
+/// Removes `--expected-hash <path>` from the arguments of a command and returns the path.
+pub fn take_expected_hash(args: &mut Vec<String>) -> Result<Option<String>, Error> {
+    let position = match args.iter().position(|arg| arg == "--expected-hash") {
+        Some(position) => position,
+        None => return Ok(None),
+    };
+    if position + 1 >= args.len() {
+        return Err(Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            "--expected-hash requires a value",
+        ));
+    }
+    let path = args[position + 1].clone();
+    args.drain(position..position + 2);
+
+    Ok(Some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> HookContext {
+        let mut context = HookContext::new("compute_constrained", "response", &[7; 64], &[9; 64]);
+        context.output_hash[0] = 0xab;
+        context
+    }
+
+    #[test]
+    fn test_hash_formats_roundtrip() {
+        let context = context();
+        for format in &["hex", "b2sum", "binary", "json"] {
+            let format = format.parse().unwrap();
+            let encoded = encode_hash(format, &context);
+            assert_eq!(decode_hash(&encoded).unwrap()[..], context.output_hash[..]);
+        }
+        assert!("sha256".parse::<HashFormat>().is_err());
+
+        // Control characters in the file name are escaped, so the JSON stays on one line
+        let mut context = context;
+        context.output = "dir\tname\nresponse".into();
+        let encoded = String::from_utf8(encode_hash(HashFormat::Json, &context)).unwrap();
+        assert!(encoded.contains("\"file\":\"dir\\u0009name\\u000aresponse\""));
+        assert_eq!(encoded.lines().count(), 1);
+        assert_eq!(
+            decode_hash(encoded.as_bytes()).unwrap()[..],
+            context.output_hash[..]
+        );
+    }
+
+    #[test]
+    fn test_decode_hash() {
+        let hash = context().output_hash;
+        let hex_hash = hex::encode(&hash[..]);
+
+        // As written by other tools
+        let b2sum = format!("{}  ./response\n{}  challenge\n", hex_hash, "00".repeat(64));
+        assert_eq!(decode_hash(b2sum.as_bytes()).unwrap()[..], hash[..]);
+        let json = format!(
+            "{{\n  \"file\": \"response\",\n  \"hash\" : \"{}\"\n}}",
+            hex_hash
+        );
+        assert_eq!(decode_hash(json.as_bytes()).unwrap()[..], hash[..]);
+
+        assert!(decode_hash(b"").is_err());
+        assert!(decode_hash(&hex_hash.as_bytes()[..126]).is_err());
+        assert!(decode_hash(b"{\"file\": \"response\"}").is_err());
+    }
+
+    #[test]
+    fn test_check_expected_hash() {
+        let context = context();
+        let path = std::env::temp_dir().join(format!("expected_hash_{}", std::process::id()));
+        fs::write(&path, encode_hash(HashFormat::Json, &context)).unwrap();
+
+        assert!(check_expected_hash(&path, "response", &context.output_hash).is_ok());
+        let error = check_expected_hash(&path, "response", &context.input_hash).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error.code, errors::HASH_MISMATCH);
+    }
+}
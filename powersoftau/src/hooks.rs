@@ -0,0 +1,434 @@
+/// Steps that run after a command has successfully written its output, such as publishing the
+/// hash of the output. Hooks run in the order they were added, and the command fails if one of
+/// them fails, so that a contribution or verification is only reported as done once every step
+/// of the operator is done as well. Library users embedding the commands can add their own
+/// steps by implementing `Hook`.
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::build_info::BuildInfo;
+use super::errors::{self, Error, ErrorCategory};
+use super::hash_file::{encode_hash, HashFormat};
+#[cfg(feature = "http")]
+use super::http::{self, Url};
+#[cfg(feature = "http")]
+use super::utils::json_string;
+
+/// What a command produced, as passed to every hook.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HookContext {
+    /// Name of the command, e.g. `compute_constrained`
+    pub command: String,
+    /// File the command wrote
+    pub output: PathBuf,
+    /// BLAKE2b hash of the output
+    pub output_hash: [u8; 64],
+    /// BLAKE2b hash of the file the output was computed from
+    pub input_hash: [u8; 64],
+    /// Further lines for the attestation, such as the report of a sampled verification
+    pub details: Vec<String>,
+}
+
+impl HookContext {
+    /// Panics if a hash is not 64 bytes long.
+    pub fn new(command: &str, output: &str, output_hash: &[u8], input_hash: &[u8]) -> Self {
+        let mut context = HookContext {
+            command: command.to_string(),
+            output: PathBuf::from(output),
+            output_hash: [0; 64],
+            input_hash: [0; 64],
+            details: vec![],
+        };
+        context.output_hash.copy_from_slice(output_hash);
+        context.input_hash.copy_from_slice(input_hash);
+        context
+    }
+}
+
+pub trait Hook {
+    /// Short description used in messages, e.g. `webhook http://host/path`.
+    fn name(&self) -> String;
+
+    fn run(&self, context: &HookContext) -> Result<(), Error>;
+}
+
+/// Hooks of a command, run in order.
+#[derive(Default)]
+pub struct Hooks(Vec<Box<dyn Hook>>);
+
+impl Hooks {
+    pub fn new() -> Self {
+        Hooks(vec![])
+    }
+
+    pub fn add<H: Hook + 'static>(&mut self, hook: H) -> &mut Self {
+        self.0.push(Box::new(hook));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Runs every hook, stopping at the first one that fails.
+    pub fn run(&self, context: &HookContext) -> Result<(), Error> {
+        for hook in &self.0 {
+            println!("Running hook {}...", hook.name());
+            hook.run(context)
+                .map_err(|e| e.context(format!("hook {} failed", hook.name())))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes `path` atomically: the contents go to a temporary file next to it first.
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+/// Writes the hash of the output to a file, in one of the formats of `hash_file`.
+pub struct HashFile(pub PathBuf, pub HashFormat);
+
+impl Hook for HashFile {
+    fn name(&self) -> String {
+        format!("hash file {}", self.0.display())
+    }
+
+    fn run(&self, context: &HookContext) -> Result<(), Error> {
+        write_atomically(&self.0, &encode_hash(self.1, context)).map_err(Error::from)
+    }
+}
+
+/// Writes a statement of what the command did, for the operator to sign and publish.
+pub struct Attestation(pub PathBuf);
+
+impl Attestation {
+    pub fn statement(context: &HookContext) -> String {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+
+        let mut statement = format!(
+            "command: {}\n\
+             input blake2b: {}\n\
+             output: {}\n\
+             output blake2b: {}\n\
+             completed at: {} (seconds since the Unix epoch)\n\
+             built by: {}\n",
+            context.command,
+            hex::encode(&context.input_hash[..]),
+            context.output.display(),
+            hex::encode(&context.output_hash[..]),
+            timestamp,
+            BuildInfo::current()
+        );
+        for detail in &context.details {
+            statement.push_str(detail);
+            statement.push('\n');
+        }
+
+        statement
+    }
+}
+
+impl Hook for Attestation {
+    fn name(&self) -> String {
+        format!("attestation {}", self.0.display())
+    }
+
+    fn run(&self, context: &HookContext) -> Result<(), Error> {
+        write_atomically(&self.0, Self::statement(context).as_bytes()).map_err(Error::from)
+    }
+}
+
+/// POSTs the context as JSON to an `http://` or `https://` URL, and expects a 2xx status.
+/// Requests are sent with the `http` module, so webhooks need the `http` feature.
+#[cfg(feature = "http")]
+pub struct Webhook {
+    url: Url,
+}
+
+#[cfg(feature = "http")]
+impl Webhook {
+    /// Parses `http[s]://host[:port][/path]`.
+    pub fn parse(url: &str) -> Result<Self, Error> {
+        let url = Url::parse(url).map_err(|message| {
+            Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                format!("invalid webhook URL {}: {}", url, message),
+            )
+        })?;
+
+        Ok(Webhook { url })
+    }
+
+    pub fn body(context: &HookContext) -> String {
+        format!(
+            "{{\"command\":{},\"input_hash\":\"{}\",\"output\":{},\"output_hash\":\"{}\",\"build\":{}}}",
+            json_string(&context.command),
+            hex::encode(&context.input_hash[..]),
+            json_string(&context.output.display().to_string()),
+            hex::encode(&context.output_hash[..]),
+            BuildInfo::current().to_json()
+        )
+    }
+
+    pub fn url(&self) -> String {
+        self.url.to_string()
+    }
+
+    /// Sends `body` to the URL with `method`, and expects a 2xx status.
+    pub fn send(&self, method: &str, content_type: &str, body: &str) -> Result<(), Error> {
+        let headers = [("Content-Type", content_type)];
+        let length = body.len() as u64;
+        let response = http::send(
+            method,
+            &self.url,
+            &headers,
+            Some((&mut body.as_bytes(), length)),
+        )?;
+        if !(200..300).contains(&response.status) {
+            return Err(Error::new(
+                errors::HOOK_FAILED,
+                ErrorCategory::Io,
+                format!("unexpected response: status {}", response.status),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "http")]
+impl Hook for Webhook {
+    fn name(&self) -> String {
+        format!("webhook {}", self.url())
+    }
+
+    fn run(&self, context: &HookContext) -> Result<(), Error> {
+        self.send("POST", "application/json", &Self::body(context))
+    }
+}
+
+/// The error of an option that sends requests, such as `--webhook`, in a build without the
+/// `http` feature.
+#[cfg(not(feature = "http"))]
+pub(crate) fn requires_http(flag: &str) -> Error {
+    Error::new(
+        errors::INVALID_ARGUMENT,
+        ErrorCategory::InvalidInput,
+        format!("{} requires building with the `http` feature", flag),
+    )
+}
+
+/// Removes `--hash-file <path>`, `--attestation <path>` and `--webhook <url>` from the
+/// arguments of a command, in any number and order, and returns the hooks they describe.
+/// `--hash-format <format>` sets the format of all hash files, `b2sum` by default.
+pub fn take_hooks(args: &mut Vec<String>) -> Result<Hooks, Error> {
+    let mut flags = vec![];
+    let mut hash_format = HashFormat::default();
+    let mut position = 0;
+    while position < args.len() {
+        let flag = args[position].clone();
+        if !["--hash-file", "--hash-format", "--attestation", "--webhook"].contains(&flag.as_str())
+        {
+            position += 1;
+            continue;
+        }
+        let value = args.get(position + 1).cloned().ok_or_else(|| {
+            Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                format!("{} requires a value", flag),
+            )
+        })?;
+        if flag == "--hash-format" {
+            hash_format = value.parse()?;
+        } else {
+            flags.push((flag, value));
+        }
+        args.drain(position..position + 2);
+    }
+
+    let mut hooks = Hooks::new();
+    for (flag, value) in flags {
+        match flag.as_str() {
+            "--hash-file" => hooks.add(HashFile(value.into(), hash_format)),
+            "--attestation" => hooks.add(Attestation(value.into())),
+            #[cfg(feature = "http")]
+            _ => hooks.add(Webhook::parse(&value)?),
+            #[cfg(not(feature = "http"))]
+            _ => return Err(requires_http(&flag)),
+        };
+    }
+
+    Ok(hooks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn context() -> HookContext {
+        HookContext {
+            command: "compute_constrained".to_string(),
+            output: PathBuf::from("response"),
+            output_hash: [1; 64],
+            input_hash: [2; 64],
+            details: vec![],
+        }
+    }
+
+    struct Record(Rc<RefCell<Vec<String>>>, bool);
+
+    impl Hook for Record {
+        fn name(&self) -> String {
+            "record".to_string()
+        }
+
+        fn run(&self, context: &HookContext) -> Result<(), Error> {
+            self.0.borrow_mut().push(context.command.clone());
+            if self.1 {
+                Ok(())
+            } else {
+                Err(Error::new(
+                    errors::INTERNAL,
+                    ErrorCategory::Internal,
+                    "failed",
+                ))
+            }
+        }
+    }
+
+    #[test]
+    fn test_hooks_stop_at_first_failure() {
+        let calls = Rc::new(RefCell::new(vec![]));
+        let mut hooks = Hooks::new();
+        hooks
+            .add(Record(calls.clone(), true))
+            .add(Record(calls.clone(), false))
+            .add(Record(calls.clone(), true));
+
+        let error = hooks.run(&context()).unwrap_err();
+        assert_eq!(error.code, errors::INTERNAL);
+        assert!(error.message.starts_with("hook record failed"));
+        assert_eq!(calls.borrow().len(), 2);
+    }
+
+    #[test]
+    fn test_take_hooks() {
+        let to_args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert!(take_hooks(&mut to_args(&["compute", "challenge", "--webhook"])).is_err());
+
+        let mut args = to_args(&[
+            "compute",
+            "--hash-file",
+            "response.hash",
+            "challenge",
+            "--attestation",
+            "attestation.txt",
+            "--hash-format",
+            "json",
+        ]);
+        let hooks = take_hooks(&mut args).unwrap();
+        assert_eq!(args, vec!["compute", "challenge"]);
+        let names = hooks.0.iter().map(|hook| hook.name()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec!["hash file response.hash", "attestation attestation.txt"]
+        );
+
+        let mut args = to_args(&["compute", "--hash-format", "sha256"]);
+        assert!(take_hooks(&mut args).is_err());
+
+        // Webhooks send requests, which only builds with `http` can
+        let mut args = to_args(&["compute", "--webhook", "http://localhost:8080/done"]);
+        if cfg!(feature = "http") {
+            let hooks = take_hooks(&mut args).unwrap();
+            assert_eq!(hooks.0[0].name(), "webhook http://localhost:8080/done");
+        } else {
+            let error = take_hooks(&mut args).err().unwrap();
+            assert!(error.message.contains("`http` feature"));
+        }
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_webhook_url() {
+        assert!(Webhook::parse("ftp://localhost/done").is_err());
+        assert_eq!(
+            Webhook::parse("https://localhost/done").unwrap().url(),
+            "https://localhost/done"
+        );
+        assert!(Webhook::parse("http://:80/").is_err());
+    }
+
+    #[test]
+    fn test_hash_file() {
+        let path = std::env::temp_dir().join(format!("hash_file_{}", std::process::id()));
+        HashFile(path.clone(), HashFormat::B2sum)
+            .run(&context())
+            .unwrap();
+        let line = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(line, format!("{}  response\n", "01".repeat(64)));
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_webhook() {
+        use std::io::Read;
+        use std::net::TcpListener;
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = String::new();
+            let mut buffer = [0; 4096];
+            while !request.ends_with(&Webhook::body(&context())) {
+                let read = stream.read(&mut buffer).unwrap();
+                assert!(read > 0, "the request ended early");
+                request.push_str(&String::from_utf8_lossy(&buffer[..read]));
+            }
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .unwrap();
+            request
+        });
+
+        let webhook = Webhook::parse(&format!("http://127.0.0.1:{}/done", port)).unwrap();
+        webhook.run(&context()).unwrap();
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /done HTTP/1.1\r\n"));
+        assert!(request.ends_with(&Webhook::body(&context())));
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_webhook_body() {
+        let mut context = context();
+        context.output = PathBuf::from("new\n\"challenge\"");
+        let body = Webhook::body(&context);
+        assert!(body.starts_with("{\"command\":\"compute_constrained\","));
+        assert!(body.contains("\"output\":\"new\\u000a\\\"challenge\\\"\""));
+        assert!(!body.contains('\n'));
+    }
+}
@@ -0,0 +1,423 @@
+/// The HTTP client of the webhooks, the object storage and the transfers of the tools, a thin
+/// layer over ureq. `https://` URLs are reached over TLS, checked against the Mozilla root
+/// certificates. Redirects of `GET` and `HEAD` requests are followed, and bodies are decoded
+/// whatever their transfer encoding.
+use crypto::digest::Digest;
+use crypto::md5::Md5;
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// An `http://` or `https://` URL, `scheme://host[:port][/path]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Url {
+    pub tls: bool,
+    pub host: String,
+    pub port: u16,
+    /// The path and query, `/` if the URL has none
+    pub path: String,
+}
+
+impl Url {
+    pub fn parse(url: &str) -> Result<Self, &'static str> {
+        let (tls, rest) = match url.strip_prefix("https://") {
+            Some(rest) => (true, rest),
+            None => (
+                false,
+                url.strip_prefix("http://")
+                    .ok_or("only http:// and https:// URLs are supported")?,
+            ),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(position) => (&rest[..position], &rest[position..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rfind(':') {
+            Some(position) => (
+                &authority[..position],
+                authority[position + 1..].parse().map_err(|_| "bad port")?,
+            ),
+            None => (authority, if tls { 443 } else { 80 }),
+        };
+        if host.is_empty() {
+            return Err("expected http[s]://host[:port][/path]");
+        }
+
+        Ok(Url {
+            tls,
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+
+    /// The value of the `Host` header, without the port if it's the default of the scheme.
+    pub fn host_header(&self) -> String {
+        if self.port == if self.tls { 443 } else { 80 } {
+            self.host.clone()
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let scheme = if self.tls { "https" } else { "http" };
+        write!(f, "{}://{}{}", scheme, self.host_header(), self.path)
+    }
+}
+
+/// The status and headers of a response, and its body.
+pub struct Response {
+    pub status: u16,
+    response: ureq::Response,
+}
+
+impl Response {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.response.header(name)
+    }
+
+    pub fn content_length(&self) -> Option<u64> {
+        self.header("content-length")
+            .and_then(|length| length.parse().ok())
+    }
+
+    /// The body, which fails to read to the end if the connection closes before the
+    /// `Content-Length` announced by the server.
+    pub fn into_reader(self) -> Box<dyn Read + Send + Sync> {
+        self.response.into_reader()
+    }
+
+    /// Reads the whole body.
+    pub fn body(self) -> io::Result<Vec<u8>> {
+        let mut body = vec![];
+        self.into_reader().read_to_end(&mut body)?;
+
+        Ok(body)
+    }
+
+    /// Fails with the status and the message of the server if the request was not successful.
+    /// Object storages put the message in the `Message` element of an XML body.
+    pub fn success(self) -> io::Result<Self> {
+        if (200..300).contains(&self.status) {
+            return Ok(self);
+        }
+        let status = self.status;
+        let body = self.body().unwrap_or_default();
+        let message = xml_values(&String::from_utf8_lossy(&body), "Message")
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        let kind = match status {
+            404 => io::ErrorKind::NotFound,
+            403 => io::ErrorKind::PermissionDenied,
+            _ => io::ErrorKind::Other,
+        };
+        Err(io::Error::new(
+            kind,
+            format!("the server answered with status {} {}", status, message),
+        ))
+    }
+}
+
+/// The unescaped values of the elements `tag` of an XML document, which is enough for
+/// the listings and errors of S3.
+pub fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut values = vec![];
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let end = match rest.find(&close) {
+            Some(end) => end,
+            None => break,
+        };
+        values.push(
+            rest[..end]
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'")
+                .replace("&amp;", "&"),
+        );
+        rest = &rest[end + close.len()..];
+    }
+
+    values
+}
+
+/// The base64 of the MD5 digest of `data`, as the `Content-MD5` header expects it.
+pub fn content_md5(data: &mut dyn Read) -> io::Result<String> {
+    let mut md5 = Md5::new();
+    let mut buffer = vec![0; 1 << 20];
+    loop {
+        let read = data.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        md5.input(&buffer[..read]);
+    }
+    let mut digest = [0; 16];
+    md5.result(&mut digest);
+
+    Ok(base64(&digest))
+}
+
+pub fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// A body which fails if `reader` ends before `length` bytes.
+struct Body<'a> {
+    reader: &'a mut dyn Read,
+    length: u64,
+}
+
+impl<'a> Read for Body<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.length == 0 {
+            return Ok(0);
+        }
+        let limit = std::cmp::min(buf.len() as u64, self.length) as usize;
+        let read = self.reader.read(&mut buf[..limit])?;
+        if read == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the body is shorter than announced",
+            ));
+        }
+        self.length -= read as u64;
+        Ok(read)
+    }
+}
+
+/// The agents of the requests that follow redirects and of the ones that don't. Requests that
+/// change something must not be redirected, as ureq turns them into `GET` requests. Connections
+/// aren't reused, like with `Connection: close`.
+fn agent(follow_redirects: bool) -> &'static ureq::Agent {
+    static AGENTS: OnceLock<[ureq::Agent; 2]> = OnceLock::new();
+    let agents = AGENTS.get_or_init(|| {
+        let agent = |redirects| {
+            ureq::AgentBuilder::new()
+                .timeout_connect(Duration::from_secs(30))
+                .timeout_read(Duration::from_secs(300))
+                .max_idle_connections(0)
+                .redirects(redirects)
+                .build()
+        };
+        [agent(0), agent(5)]
+    });
+
+    &agents[follow_redirects as usize]
+}
+
+/// Sends a `method` request to `url` with `headers` and a body of `length` bytes, and returns
+/// the response whatever its status. Only `GET` and `HEAD` requests follow redirects.
+pub fn send(
+    method: &str,
+    url: &Url,
+    headers: &[(&str, &str)],
+    body: Option<(&mut dyn Read, u64)>,
+) -> io::Result<Response> {
+    let follow_redirects = method == "GET" || method == "HEAD";
+    let mut request = agent(follow_redirects).request(method, &url.to_string());
+    for (name, value) in headers {
+        request = request.set(name, value);
+    }
+    let result = match body {
+        Some((reader, length)) => request
+            .set("Content-Length", &length.to_string())
+            .send(Body { reader, length }),
+        None => request.call(),
+    };
+
+    match result {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => Ok(Response {
+            status: response.status(),
+            response,
+        }),
+        Err(ureq::Error::Transport(e)) => Err(io::Error::other(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    /// Answers the requests it receives with `responses`, in order, and sends the request
+    /// lines back.
+    fn serve(responses: Vec<String>) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for (stream, response) in listener.incoming().zip(responses) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                let mut line = String::new();
+                let mut length = 0;
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    if let Some(value) = line.to_lowercase().strip_prefix("content-length: ") {
+                        length = value.trim().parse().unwrap();
+                    }
+                    if request.is_empty() {
+                        request = line.trim().to_string();
+                    }
+                    line.clear();
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                stream.write_all(response.as_bytes()).unwrap();
+                sender.send(request).unwrap();
+            }
+        });
+
+        (url, receiver)
+    }
+
+    #[test]
+    fn test_url() {
+        assert_eq!(
+            Url::parse("http://localhost:9000/ceremony/challenge?round=2").unwrap(),
+            Url {
+                tls: false,
+                host: "localhost".to_string(),
+                port: 9000,
+                path: "/ceremony/challenge?round=2".to_string(),
+            }
+        );
+        let url = Url::parse("http://example.com").unwrap();
+        assert_eq!((url.port, url.path.as_str()), (80, "/"));
+        assert_eq!(url.host_header(), "example.com");
+        let url = Url::parse("https://example.com/challenge").unwrap();
+        assert_eq!((url.tls, url.port), (true, 443));
+        assert_eq!(url.to_string(), "https://example.com/challenge");
+        let url = Url::parse("https://example.com:8443").unwrap();
+        assert_eq!(url.to_string(), "https://example.com:8443/");
+        assert!(Url::parse("ftp://example.com/challenge").is_err());
+        assert!(Url::parse("http://example.com:port/challenge").is_err());
+        assert!(Url::parse("http:///challenge").is_err());
+    }
+
+    #[test]
+    fn test_content_md5() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        // `printf 'Check Integrity!' | openssl md5 -binary | base64`
+        assert_eq!(
+            content_md5(&mut &b"Check Integrity!"[..]).unwrap(),
+            "nwqq6b6ua/tTDk7B5M184w=="
+        );
+    }
+
+    #[test]
+    fn test_redirect() {
+        let (target, requests) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 9\r\n\r\nchallenge".to_string(),
+        ]);
+        let (url, redirects) = serve(vec![
+            format!(
+                "HTTP/1.1 302 Found\r\nLocation: {}/moved\r\nContent-Length: 0\r\n\r\n",
+                target
+            ),
+            format!(
+                "HTTP/1.1 307 Temporary Redirect\r\nLocation: {}/moved\r\nContent-Length: 0\r\n\r\n",
+                target
+            ),
+        ]);
+        let url = Url::parse(&format!("{}/challenge", url)).unwrap();
+        let response = send("GET", &url, &[], None).unwrap().success().unwrap();
+        assert_eq!(response.body().unwrap(), b"challenge");
+        assert_eq!(redirects.recv().unwrap(), "GET /challenge HTTP/1.1");
+        assert_eq!(requests.recv().unwrap(), "GET /moved HTTP/1.1");
+
+        // An upload isn't sent elsewhere, nor turned into a download
+        let response = send("PUT", &url, &[], Some((&mut &b"response"[..], 8))).unwrap();
+        assert_eq!(response.status, 307);
+        assert!(response.success().is_err());
+        assert_eq!(redirects.recv().unwrap(), "PUT /challenge HTTP/1.1");
+    }
+
+    #[test]
+    fn test_chunked() {
+        let (url, _) = serve(vec![concat!(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n",
+            "5\r\nchall\r\n4;name=value\r\nenge\r\n0\r\n\r\n"
+        )
+        .to_string()]);
+        let url = Url::parse(&url).unwrap();
+        let response = send("GET", &url, &[], None).unwrap().success().unwrap();
+        assert_eq!(response.content_length(), None);
+        assert_eq!(response.body().unwrap(), b"challenge");
+
+        // A body cut before its announced length is an error
+        let (url, _) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 9\r\n\r\nchall".to_string()
+        ]);
+        let url = Url::parse(&url).unwrap();
+        let response = send("GET", &url, &[], None).unwrap();
+        assert_eq!(response.content_length(), Some(9));
+        assert!(response.body().is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |status: &str, body: &str| {
+            let (url, _) = serve(vec![format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )]);
+            let url = Url::parse(&url).unwrap();
+            let response = send("GET", &url, &[], None).unwrap();
+            response.success().err().unwrap()
+        };
+        let e = error(
+            "404 Not Found",
+            "<Error><Code>NoSuchKey</Code><Message>no such key</Message></Error>",
+        );
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+        assert!(e.to_string().contains("404 no such key"));
+        let e = error("403 Forbidden", "");
+        assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+        let e = error("503 Service Unavailable", "busy");
+        assert_eq!(e.kind(), io::ErrorKind::Other);
+        assert!(e.to_string().contains("503"));
+
+        // Nothing listens on the port of a closed listener
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/challenge", listener.local_addr().unwrap());
+        drop(listener);
+        assert!(send("GET", &Url::parse(&url).unwrap(), &[], None).is_err());
+    }
+}
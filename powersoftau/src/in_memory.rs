@@ -0,0 +1,179 @@
+/// A whole powers of tau ceremony kept in memory, for tests and tiny trusted setups embedded in
+/// other projects, which would otherwise write a challenge and a response file per
+/// contribution. Challenges are uncompressed and responses compressed, like the command line
+/// tools write them, so a response of an in-memory ceremony can be verified by
+/// `verify_transform_constrained` and the other way around.
+///
+/// The accumulators live in anonymous memory maps, which is what `BatchedAccumulator` reads
+/// and writes, so nothing touches the filesystem.
+use bellman_ce::pairing::Engine;
+use generic_array::GenericArray;
+use memmap::{Mmap, MmapMut};
+use rand::Rng;
+use typenum::consts::U64;
+
+use std::io;
+
+use super::batched_accumulator::BatchedAccumulator;
+use super::keypair::guarded_keypair_with_mask;
+use super::parameters::{expected_sizes, CeremonyParams, DeserializationError, UseCompression};
+use super::round::Round;
+use super::utils::{blank_hash, calculate_hash, write_hash_header};
+
+pub struct InMemoryCeremony<E: Engine> {
+    pub parameters: CeremonyParams<E>,
+    /// Uncompressed challenge of the next contribution
+    challenge: Mmap,
+    /// Hashes of the responses that were combined, in order
+    contributions: Vec<GenericArray<u8, U64>>,
+}
+
+impl<E: Engine> InMemoryCeremony<E> {
+    /// A ceremony that starts from the initial accumulator, with a blank hash as the hash of
+    /// the previous contribution, like `new_constrained` writes it.
+    pub fn new(parameters: CeremonyParams<E>) -> io::Result<Self> {
+        let mut challenge = MmapMut::map_anon(parameters.accumulator_size)?;
+        write_hash_header(&mut challenge, &blank_hash())?;
+        BatchedAccumulator::generate_initial(&mut challenge, UseCompression::No, &parameters)?;
+
+        Ok(InMemoryCeremony {
+            challenge: challenge.make_read_only()?,
+            parameters,
+            contributions: vec![],
+        })
+    }
+
+    /// A ceremony that continues from the uncompressed `challenge`, which isn't checked.
+    pub fn from_challenge(parameters: CeremonyParams<E>, challenge: &[u8]) -> io::Result<Self> {
+        if challenge.len() != parameters.accumulator_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the challenge doesn't have the size of an uncompressed accumulator",
+            ));
+        }
+
+        Ok(InMemoryCeremony {
+            challenge: map(challenge)?,
+            parameters,
+            contributions: vec![],
+        })
+    }
+
+    /// The challenge of the next contribution, which is the final accumulator once the
+    /// ceremony is over.
+    pub fn challenge(&self) -> &[u8] {
+        &self.challenge
+    }
+
+    pub fn challenge_hash(&self) -> GenericArray<u8, U64> {
+        calculate_hash(&self.challenge)
+    }
+
+    /// The hashes of the responses that were combined, in order.
+    pub fn contributions(&self) -> &[GenericArray<u8, U64>] {
+        &self.contributions
+    }
+
+    /// Computes a response to the current challenge with secrets drawn from `rng`, which are
+    /// dropped once the response is written. The ceremony doesn't change until the response is
+    /// combined.
+    pub fn contribute<R: Rng>(&self, rng: &mut R) -> io::Result<Vec<u8>> {
+        let round = Round::new(&self.challenge, &self.parameters);
+        let (public_key, private_key) =
+            guarded_keypair_with_mask(rng, round.key_digest(), self.parameters.trapdoors);
+
+        let size = expected_sizes(&self.parameters, round.response_compression).response;
+        let mut response = MmapMut::map_anon(size)?;
+        round.compute(&mut response, &*private_key)?;
+        round.finalize(&mut response, &public_key)?;
+
+        Ok(response.to_vec())
+    }
+
+    /// Checks that `response` was computed from the current challenge, the way
+    /// `verify_transform_constrained` does.
+    pub fn verify(&self, response: &[u8]) -> Result<bool, DeserializationError> {
+        let round = Round::new(&self.challenge, &self.parameters);
+        check_response_size(&round, response)?;
+
+        round.verify(&map(response)?)
+    }
+
+    /// Verifies `response` and, if it's valid, makes the decompressed response the challenge
+    /// of the next contribution. An invalid response is `Ok(false)` and leaves the ceremony
+    /// unchanged. The new challenge is written as the response is verified, so the response is
+    /// read and decompressed only once.
+    pub fn combine(&mut self, response: &[u8]) -> Result<bool, DeserializationError> {
+        let round = Round::new(&self.challenge, &self.parameters);
+        check_response_size(&round, response)?;
+
+        let response = map(response)?;
+        let mut challenge = MmapMut::map_anon(self.parameters.accumulator_size)?;
+        if !round.verify_and_decompress(&response, &mut challenge)? {
+            return Ok(false);
+        }
+
+        self.challenge = challenge.make_read_only()?;
+        self.contributions.push(calculate_hash(&response));
+        Ok(true)
+    }
+}
+
+fn check_response_size<E: Engine>(round: &Round<E>, response: &[u8]) -> io::Result<()> {
+    if response.len() != expected_sizes(round.parameters, round.response_compression).response {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the response doesn't have the size of a response",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Copies `bytes` into an anonymous map, which the accumulator functions read from.
+fn map(bytes: &[u8]) -> io::Result<Mmap> {
+    let mut map = MmapMut::map_anon(bytes.len())?;
+    map.copy_from_slice(bytes);
+    map.make_read_only()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bellman_ce::pairing::bn256::Bn256;
+    use rand::thread_rng;
+
+    #[test]
+    fn test_in_memory_ceremony() {
+        let mut ceremony = InMemoryCeremony::new(CeremonyParams::<Bn256>::new(3, 4)).unwrap();
+        let initial = ceremony.challenge_hash();
+
+        for _ in 0..2 {
+            let response = ceremony.contribute(&mut thread_rng()).unwrap();
+            assert!(ceremony.verify(&response).unwrap());
+            assert!(ceremony.combine(&response).unwrap());
+            assert_eq!(
+                &ceremony.challenge()[..64],
+                calculate_hash(&response).as_slice()
+            );
+        }
+        assert_eq!(ceremony.contributions().len(), 2);
+        assert_ne!(ceremony.challenge_hash(), initial);
+
+        // A response to another challenge isn't combined
+        let mut other = InMemoryCeremony::from_challenge(
+            CeremonyParams::<Bn256>::new(3, 4),
+            ceremony.challenge(),
+        )
+        .unwrap();
+        let response = other.contribute(&mut thread_rng()).unwrap();
+        assert!(other.combine(&response).unwrap());
+        let stale = other.contribute(&mut thread_rng()).unwrap();
+        let challenge = ceremony.challenge_hash();
+        assert!(!ceremony.combine(&stale).unwrap());
+        assert_eq!(ceremony.challenge_hash(), challenge);
+        assert_eq!(ceremony.contributions().len(), 2);
+
+        assert!(ceremony.verify(&response[1..]).is_err());
+    }
+}
@@ -0,0 +1,831 @@
+/// Contributions that turn the challenge into the response within the same file, for
+/// contributors who can't hold both on disk at once. The hash of the challenge, which the
+/// response starts with and the keys are bound to, is computed by streaming the file before
+/// anything in it changes. The accumulator is then transformed chunk by chunk where it is, and
+/// compacted into the compressed layout of a response. Every compressed element lies at or
+/// before its uncompressed position, so compacting in file order never overwrites an element
+/// that hasn't been read yet.
+///
+/// Before a step rewrites a part of the file, the original bytes go to a small rollback journal
+/// next to the response. The secrets of the contribution only live in memory, so a contribution
+/// interrupted while transforming can't be finished: the journal puts the chunk back, and the
+/// challenge is usable again only if no chunk had been transformed yet. Compacting needs no
+/// secret, so an interrupted compaction is finished from the journal.
+use bellman_ce::pairing::{CurveAffine, EncodedPoint, Engine};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use generic_array::GenericArray;
+use log::info;
+use memmap::{MmapMut, MmapOptions};
+use typenum::consts::U64;
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use super::batched_accumulator::{BatchedAccumulator, ChunkTransformer};
+use super::errors::{self, Error, ErrorCategory};
+use super::hooks::write_atomically;
+use super::keypair::{KeyDomain, PublicKey};
+use super::parameters::{
+    check_compression, expected_sizes, CeremonyParams, CheckForCorrectness, ElementType,
+    UseCompression,
+};
+use super::utils::{calculate_hash_of_reader, iter_chunk, write_hash_header};
+
+/// Identifies a journal file, followed by the format version.
+const MAGIC: &[u8; 8] = b"POTJOURN";
+const VERSION: u32 = 1;
+
+/// The sections of an accumulator in file order, which is the order they are compacted in.
+const SECTIONS: [ElementType; 5] = [
+    ElementType::TauG1,
+    ElementType::TauG2,
+    ElementType::AlphaG1,
+    ElementType::BetaG1,
+    ElementType::BetaG2,
+];
+
+/// The journal of an in-place contribution to `response`, `<response>.journal`.
+pub fn journal_path(response: &Path) -> PathBuf {
+    let mut path = response.as_os_str().to_owned();
+    path.push(".journal");
+    PathBuf::from(path)
+}
+
+/// The step an in-place contribution is at.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Step {
+    /// Transforming the chunk of powers from `start`
+    Transform { start: usize },
+    /// Compacting the elements of `SECTIONS[section]` from `start`
+    Compact { section: usize, start: usize },
+}
+
+#[derive(Debug, PartialEq)]
+struct Journal {
+    challenge_hash: [u8; 64],
+    step: Step,
+    /// The serialized public key, once the accumulator is transformed
+    public_key: Vec<u8>,
+    /// The offsets and original bytes of the regions that the step rewrites
+    regions: Vec<(usize, Vec<u8>)>,
+}
+
+impl Journal {
+    /// Replaces the journal at `path`. The journal is replaced atomically and durably, so it
+    /// always describes a whole step, and the step only starts once it does.
+    fn write(&self, path: &Path) -> io::Result<()> {
+        let mut contents = vec![];
+        contents.write_all(&MAGIC[..])?;
+        contents.write_u32::<BigEndian>(VERSION)?;
+        contents.write_all(&self.challenge_hash)?;
+        match self.step {
+            Step::Transform { start } => {
+                contents.write_u8(0)?;
+                contents.write_u64::<BigEndian>(start as u64)?;
+            }
+            Step::Compact { section, start } => {
+                contents.write_u8(1)?;
+                contents.write_u64::<BigEndian>(section as u64)?;
+                contents.write_u64::<BigEndian>(start as u64)?;
+            }
+        }
+        contents.write_u64::<BigEndian>(self.public_key.len() as u64)?;
+        contents.write_all(&self.public_key)?;
+        contents.write_u32::<BigEndian>(self.regions.len() as u32)?;
+        for (offset, bytes) in &self.regions {
+            contents.write_u64::<BigEndian>(*offset as u64)?;
+            contents.write_u64::<BigEndian>(bytes.len() as u64)?;
+            contents.write_all(bytes)?;
+        }
+
+        write_atomically(path, &contents)?;
+        let directory = match path.parent() {
+            Some(directory) if !directory.as_os_str().is_empty() => directory,
+            _ => Path::new("."),
+        };
+        File::open(directory)?.sync_all()
+    }
+
+    fn read(path: &Path) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a journal"));
+        }
+        let version = reader.read_u32::<BigEndian>()?;
+        if version != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported journal version {}", version),
+            ));
+        }
+
+        let mut challenge_hash = [0; 64];
+        reader.read_exact(&mut challenge_hash)?;
+        let step = match reader.read_u8()? {
+            0 => Step::Transform {
+                start: reader.read_u64::<BigEndian>()? as usize,
+            },
+            1 => Step::Compact {
+                section: reader.read_u64::<BigEndian>()? as usize,
+                start: reader.read_u64::<BigEndian>()? as usize,
+            },
+            step => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown journal step {}", step),
+                ))
+            }
+        };
+        let public_key = read_bytes(&mut reader)?;
+        let mut regions = vec![];
+        for _ in 0..reader.read_u32::<BigEndian>()? {
+            let offset = reader.read_u64::<BigEndian>()? as usize;
+            regions.push((offset, read_bytes(&mut reader)?));
+        }
+
+        Ok(Journal {
+            challenge_hash,
+            step,
+            public_key,
+            regions,
+        })
+    }
+
+    /// Puts the original bytes of the regions of the step back into `map`.
+    fn restore(&self, map: &mut MmapMut) -> io::Result<()> {
+        for (offset, bytes) in &self.regions {
+            map.get_mut(*offset..*offset + bytes.len())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "the journal has a region beyond the end of the response",
+                    )
+                })?
+                .copy_from_slice(bytes);
+        }
+
+        map.flush()
+    }
+}
+
+/// Reads bytes prefixed with their length, which is untrusted, so only the bytes actually
+/// present are allocated.
+fn read_bytes<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let length = reader.read_u64::<BigEndian>()?;
+    let mut bytes = vec![];
+    reader.take(length).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != length {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "the journal is truncated",
+        ));
+    }
+
+    Ok(bytes)
+}
+
+fn remove_journal(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn map_response(response: &Path) -> Result<(File, MmapMut), Error> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(response)
+        .map_err(|e| Error::from(e).context("unable to open the response"))?;
+    let map = unsafe { MmapOptions::new().map_mut(&file) }
+        .map_err(|e| Error::from(e).context("unable to create a memory map for the response"))?;
+
+    Ok((file, map))
+}
+
+/// A contribution that turns the challenge into the response within the same file. Like the
+/// command line tools, it takes an uncompressed challenge and writes a compressed response.
+pub struct InPlace<'a, E: Engine> {
+    pub parameters: &'a CeremonyParams<E>,
+    challenge: PathBuf,
+    response: PathBuf,
+    /// BLAKE2b hash of the challenge, which the response starts with
+    challenge_hash: GenericArray<u8, U64>,
+    /// What the keys of the contribution prove knowledge of their secrets with
+    key_digest: Vec<u8>,
+    pub check_challenge: CheckForCorrectness,
+}
+
+impl<'a, E: Engine> InPlace<'a, E> {
+    /// Checks and hashes `challenge`, which is not changed until `contribute` moves it to
+    /// `response`. Keys are legacy keys, bound to the challenge only, and to the trapdoors that
+    /// the parameters update if not all of them.
+    pub fn open(
+        challenge: &Path,
+        response: &Path,
+        parameters: &'a CeremonyParams<E>,
+    ) -> Result<Self, Error> {
+        if response.exists() {
+            return Err(Error::new(
+                errors::OUTPUT_EXISTS,
+                ErrorCategory::Io,
+                format!("{} already exists", response.display()),
+            )
+            .with_hint("remove it or write the response to another file"));
+        }
+        let file = File::open(challenge)
+            .map_err(|e| Error::from(e).context("unable to open the challenge"))?;
+        check_compression(&file, UseCompression::No, parameters)
+            .map_err(|e| Error::from(e).context("unable to use the challenge file"))?;
+        let length = file.metadata()?.len();
+        let expected = expected_sizes(parameters, UseCompression::No).challenge;
+        if length != expected as u64 {
+            return Err(Error::new(
+                errors::INVALID_FILE_SIZE,
+                ErrorCategory::InvalidInput,
+                format!(
+                    "The challenge should be {} bytes, but it's {}.",
+                    expected, length
+                ),
+            )
+            .with_hint(
+                "check that the circuit power matches the ceremony and the file was fully transferred",
+            ));
+        }
+
+        // The response starts with this hash, so it must be taken before the file changes
+        let challenge_hash = calculate_hash_of_reader(&mut File::open(challenge)?)
+            .map_err(|e| Error::from(e).context("unable to hash the challenge"))?;
+
+        Ok(InPlace {
+            parameters,
+            challenge: challenge.to_path_buf(),
+            response: response.to_path_buf(),
+            challenge_hash,
+            key_digest: parameters.trapdoors.digest(&challenge_hash),
+            check_challenge: CheckForCorrectness::No,
+        })
+    }
+
+    /// Binds the keys of the contribution to `domain`, see `KeyDomain`.
+    pub fn with_domain(mut self, domain: &KeyDomain) -> Self {
+        self.key_digest = self
+            .parameters
+            .trapdoors
+            .digest(&domain.digest(&self.challenge_hash));
+        self
+    }
+
+    pub fn with_check(mut self, check_challenge: CheckForCorrectness) -> Self {
+        self.check_challenge = check_challenge;
+        self
+    }
+
+    pub fn challenge_hash(&self) -> &GenericArray<u8, U64> {
+        &self.challenge_hash
+    }
+
+    /// The digest to generate the keys of the contribution with.
+    pub fn key_digest(&self) -> &[u8] {
+        &self.key_digest
+    }
+
+    /// Moves the challenge to the response and turns it into the response of the contribution
+    /// that `transformer` applies, ending with `public_key`. Returns the hash of the response.
+    /// If this fails before any chunk is transformed, the challenge is moved back unchanged.
+    pub fn contribute<T: ChunkTransformer<E>>(
+        &self,
+        transformer: &T,
+        public_key: &PublicKey<E>,
+    ) -> Result<GenericArray<u8, U64>, Error> {
+        let mut serialized_key = vec![];
+        public_key.serialize(&mut serialized_key)?;
+        fs::rename(&self.challenge, &self.response)
+            .map_err(|e| Error::from(e).context("unable to move the challenge to the response"))?;
+        let (file, mut map) = map_response(&self.response)?;
+
+        let journal_path = journal_path(&self.response);
+        let mut journal = Journal {
+            challenge_hash: [0; 64],
+            step: Step::Transform { start: 0 },
+            public_key: vec![],
+            regions: vec![],
+        };
+        journal
+            .challenge_hash
+            .copy_from_slice(self.challenge_hash.as_slice());
+        if let Err(e) = transform(
+            &mut map,
+            &mut journal,
+            &journal_path,
+            transformer,
+            self.check_challenge,
+            self.parameters,
+        ) {
+            journal.restore(&mut map)?;
+            drop(map);
+            remove_journal(&journal_path)?;
+            return Err(match journal.step {
+                Step::Transform { start } if start > 0 => e
+                    .context(format!(
+                        "the challenge was transformed up to power {} and can't be used anymore",
+                        start
+                    ))
+                    .with_hint("delete the response, fetch the challenge again and start over"),
+                _ => {
+                    fs::rename(&self.response, &self.challenge)?;
+                    e.context("the challenge was left unchanged")
+                }
+            });
+        }
+
+        journal.public_key = serialized_key;
+        compact(
+            &mut map,
+            &mut journal,
+            &journal_path,
+            (0, 0),
+            self.parameters,
+        )
+        .map_err(|e| {
+            Error::from(e)
+                .context("unable to compact the response")
+                .with_hint("run the command again to finish the response from its journal")
+        })?;
+
+        finish(file, map, &journal, &self.response, self.parameters)
+    }
+}
+
+/// Transforms the accumulator in `map` chunk by chunk, journaling every chunk before it is
+/// rewritten. If this fails, `journal` holds the chunk that failed.
+fn transform<E: Engine, T: ChunkTransformer<E>>(
+    map: &mut MmapMut,
+    journal: &mut Journal,
+    journal_path: &Path,
+    transformer: &T,
+    check_challenge: CheckForCorrectness,
+    parameters: &CeremonyParams<E>,
+) -> Result<(), Error> {
+    let mut accumulator = BatchedAccumulator::empty(parameters);
+    // Every chunk of the first section rewrites beta_g2, so it is only read from the first one
+    let mut beta_g2 = None;
+
+    for section in [
+        0..parameters.powers_length,
+        parameters.powers_length..parameters.powers_g1_length,
+    ] {
+        iter_chunk(
+            section,
+            parameters.batch_size,
+            |start, end| -> Result<(), Error> {
+                let size = end - start + 1;
+                let regions = BatchedAccumulator::chunk_byte_ranges(
+                    start,
+                    size,
+                    UseCompression::No,
+                    parameters,
+                );
+                journal.step = Step::Transform { start };
+                journal.regions = regions
+                    .iter()
+                    .map(|region| (region.start, map[region.clone()].to_vec()))
+                    .collect();
+                journal
+                    .write(journal_path)
+                    .map_err(|e| Error::from(e).context("unable to write the journal"))?;
+
+                accumulator
+                    .read_chunk(start, size, UseCompression::No, check_challenge, &map[..])
+                    .map_err(|e| {
+                        Error::from(e).context(format!(
+                            "unable to read the chunk from {} to {} from the challenge",
+                            start, end
+                        ))
+                    })?;
+                match beta_g2 {
+                    Some(original) => accumulator.beta_g2 = original,
+                    None => beta_g2 = Some(accumulator.beta_g2),
+                }
+                transformer.transform_chunk(start, &mut accumulator)?;
+                accumulator.write_chunk(start, UseCompression::No, map)?;
+                for region in regions {
+                    map.flush_range(region.start, region.len())?;
+                }
+                info!("Done processing {} powers of tau", end);
+
+                Ok(())
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Compacts the transformed accumulator in `map` into the compressed layout of a response, in
+/// file order from the element `from.1` of `SECTIONS[from.0]`.
+fn compact<E: Engine>(
+    map: &mut MmapMut,
+    journal: &mut Journal,
+    journal_path: &Path,
+    from: (usize, usize),
+    parameters: &CeremonyParams<E>,
+) -> io::Result<()> {
+    let mut first = from.1;
+    for (section, element_type) in SECTIONS.iter().enumerate().skip(from.0) {
+        let length = match element_type {
+            ElementType::TauG1 => parameters.powers_g1_length,
+            ElementType::BetaG2 => 1,
+            _ => parameters.powers_length,
+        };
+        iter_chunk(first..length, parameters.batch_size, |start, end| {
+            compact_batch(map, journal, journal_path, section, start, end, parameters)
+        })?;
+        first = 0;
+    }
+
+    Ok(())
+}
+
+/// Compacts the elements from `start` to `end` of `SECTIONS[section]`.
+fn compact_batch<E: Engine>(
+    map: &mut MmapMut,
+    journal: &mut Journal,
+    journal_path: &Path,
+    section: usize,
+    start: usize,
+    end: usize,
+    parameters: &CeremonyParams<E>,
+) -> io::Result<()> {
+    let element_type = SECTIONS[section];
+    let range = |compression| {
+        BatchedAccumulator::element_range(
+            element_type,
+            start,
+            end - start + 1,
+            compression,
+            parameters,
+        )
+        .expect("batches are within their section")
+    };
+    let (source, destination) = (range(UseCompression::No), range(UseCompression::Yes));
+    journal.step = Step::Compact { section, start };
+    journal.regions = vec![(source.start, map[source].to_vec())];
+    journal.write(journal_path)?;
+
+    let uncompressed = &journal.regions[0].1;
+    let compressed = match element_type {
+        ElementType::TauG2 | ElementType::BetaG2 => recompress::<E::G2Affine>(uncompressed)?,
+        _ => recompress::<E::G1Affine>(uncompressed)?,
+    };
+    map[destination.clone()].copy_from_slice(&compressed);
+    map.flush_range(destination.start, destination.len())
+}
+
+/// Re-encodes consecutive uncompressed points compressed. The points were just written by the
+/// transformation, so they are not checked again.
+fn recompress<C: CurveAffine>(uncompressed: &[u8]) -> io::Result<Vec<u8>> {
+    let mut compressed = Vec::with_capacity(uncompressed.len() / 2);
+    let mut encoded = C::Uncompressed::empty();
+    for bytes in uncompressed.chunks(C::Uncompressed::size()) {
+        encoded.as_mut().copy_from_slice(bytes);
+        let point = encoded
+            .into_affine_unchecked()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        compressed.extend_from_slice(point.into_compressed().as_ref());
+    }
+
+    Ok(compressed)
+}
+
+/// Writes the hash of the challenge and the public key around the compacted accumulator, cuts
+/// the file to the size of a response and returns its hash.
+fn finish<E: Engine>(
+    file: File,
+    mut map: MmapMut,
+    journal: &Journal,
+    response: &Path,
+    parameters: &CeremonyParams<E>,
+) -> Result<GenericArray<u8, U64>, Error> {
+    let public_key = PublicKey::<E>::deserialize(&mut &journal.public_key[..])?;
+    write_hash_header(&mut map, &journal.challenge_hash)?;
+    public_key.write(&mut map, UseCompression::Yes, parameters)?;
+    map.flush()?;
+    drop(map);
+    file.set_len(expected_sizes(parameters, UseCompression::Yes).response as u64)?;
+    file.sync_all()?;
+    remove_journal(&journal_path(response))?;
+
+    Ok(calculate_hash_of_reader(&mut File::open(response)?)?)
+}
+
+/// What `recover` did with an interrupted in-place contribution.
+pub enum Recovered<E: Engine> {
+    /// The contribution was interrupted before it changed the challenge, which is back in place
+    Challenge,
+    /// The contribution was interrupted while compacting the response, which is now finished
+    Response {
+        challenge_hash: GenericArray<u8, U64>,
+        public_key: PublicKey<E>,
+        response_hash: GenericArray<u8, U64>,
+    },
+}
+
+/// Recovers from an in-place contribution from `challenge` to `response` that was interrupted,
+/// if the journal of `response` shows one. A contribution interrupted after it transformed a
+/// chunk of the challenge can't be finished, and fails with `errors::INTERRUPTED`.
+pub fn recover<E: Engine>(
+    challenge: &Path,
+    response: &Path,
+    parameters: &CeremonyParams<E>,
+) -> Result<Option<Recovered<E>>, Error> {
+    let journal_path = journal_path(response);
+    if !journal_path.exists() {
+        return Ok(None);
+    }
+    let mut journal = Journal::read(&journal_path)
+        .map_err(|e| Error::from(e).context("unable to read the journal"))?;
+    let (file, mut map) = map_response(response)?;
+
+    let response_hash = if map.len() == expected_sizes(parameters, UseCompression::Yes).response {
+        // Interrupted once the response was complete, before the journal was removed
+        drop(map);
+        remove_journal(&journal_path)?;
+        calculate_hash_of_reader(&mut File::open(response)?)?
+    } else {
+        journal.restore(&mut map)?;
+        match journal.step {
+            Step::Transform { start: 0 } => {
+                drop(map);
+                remove_journal(&journal_path)?;
+                fs::rename(response, challenge)?;
+                return Ok(Some(Recovered::Challenge));
+            }
+            Step::Transform { start } => {
+                remove_journal(&journal_path)?;
+                return Err(Error::new(
+                    errors::INTERRUPTED,
+                    ErrorCategory::Io,
+                    format!(
+                        "A contribution in place was interrupted after it transformed the challenge up to power {}, and its secrets are gone.",
+                        start
+                    ),
+                )
+                .with_hint("delete the response, fetch the challenge again and start over"));
+            }
+            Step::Compact { section, start } => {
+                info!("Finishing the compaction from the journal");
+                compact(
+                    &mut map,
+                    &mut journal,
+                    &journal_path,
+                    (section, start),
+                    parameters,
+                )?;
+                finish(file, map, &journal, response, parameters)?
+            }
+        }
+    };
+
+    let mut challenge_hash = GenericArray::default();
+    challenge_hash.copy_from_slice(&journal.challenge_hash);
+    Ok(Some(Recovered::Response {
+        challenge_hash,
+        public_key: PublicKey::deserialize(&mut &journal.public_key[..])?,
+        response_hash,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::{keypair, PrivateKey};
+    use crate::malicious::challenge;
+    use crate::round::Round;
+    use bellman_ce::pairing::bn256::Bn256;
+    use memmap::Mmap;
+    use rand::thread_rng;
+
+    fn scratch(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!(
+            "powersoftau-in-place-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    /// The response that `Round` computes from `challenge` with the key.
+    fn expected_response(
+        challenge: &Mmap,
+        public_key: &PublicKey<Bn256>,
+        private_key: &PrivateKey<Bn256>,
+        parameters: &CeremonyParams<Bn256>,
+    ) -> (Vec<u8>, GenericArray<u8, U64>) {
+        let round = Round::new(challenge, parameters);
+        let mut response =
+            MmapMut::map_anon(expected_sizes(parameters, UseCompression::Yes).response).unwrap();
+        round.compute(&mut response, private_key).unwrap();
+        let hash = round.finalize(&mut response, public_key).unwrap();
+        (response.to_vec(), hash)
+    }
+
+    /// Fails on the chunk from `start`, like a signer that went away.
+    struct FailAt<'a>(&'a PrivateKey<Bn256>, usize);
+
+    impl ChunkTransformer<Bn256> for FailAt<'_> {
+        fn transform_chunk(
+            &self,
+            start: usize,
+            accumulator: &mut BatchedAccumulator<Bn256>,
+        ) -> io::Result<()> {
+            if start == self.1 {
+                return Err(io::Error::new(
+                    io::ErrorKind::BrokenPipe,
+                    "the signer went away",
+                ));
+            }
+            self.0.transform_chunk(start, accumulator)
+        }
+    }
+
+    #[test]
+    fn test_in_place() {
+        // Two chunks in the first section, so that beta_g2 is written twice
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let directory = scratch("contribute");
+        let challenge_path = directory.join("challenge");
+        let response_path = directory.join("response");
+        let challenge = challenge(&parameters);
+        fs::write(&challenge_path, &challenge[..]).unwrap();
+
+        let in_place = InPlace::open(&challenge_path, &response_path, &parameters).unwrap();
+        let (public_key, private_key) = keypair(&mut thread_rng(), in_place.key_digest());
+        let (expected, expected_hash) =
+            expected_response(&challenge, &public_key, &private_key, &parameters);
+        assert_eq!(
+            in_place.challenge_hash(),
+            Round::new(&challenge, &parameters).challenge_hash()
+        );
+
+        // A failure before the first chunk is written leaves the challenge as it was
+        let error = in_place
+            .contribute(&FailAt(&private_key, 0), &public_key)
+            .unwrap_err();
+        assert!(error.message.contains("left unchanged"));
+        assert_eq!(fs::read(&challenge_path).unwrap(), &challenge[..]);
+        assert!(!response_path.exists());
+
+        let hash = in_place.contribute(&private_key, &public_key).unwrap();
+        assert_eq!(hash, expected_hash);
+        assert_eq!(fs::read(&response_path).unwrap(), expected);
+        assert!(!challenge_path.exists());
+        assert!(!journal_path(&response_path).exists());
+
+        // A failure after a chunk is transformed can't be undone
+        fs::write(&challenge_path, &challenge[..]).unwrap();
+        let error = InPlace::open(&challenge_path, &response_path, &parameters)
+            .err()
+            .unwrap();
+        assert_eq!(error.code, errors::OUTPUT_EXISTS);
+        fs::remove_file(&response_path).unwrap();
+        let error = InPlace::open(&challenge_path, &response_path, &parameters)
+            .unwrap()
+            .contribute(&FailAt(&private_key, 4), &public_key)
+            .unwrap_err();
+        assert!(error.message.contains("up to power 4"));
+        assert!(!journal_path(&response_path).exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_recover() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let directory = scratch("recover");
+        let challenge_path = directory.join("challenge");
+        let response_path = directory.join("response");
+        let journal_path = journal_path(&response_path);
+        let challenge = challenge(&parameters);
+        let (public_key, private_key) = keypair::<_, Bn256>(
+            &mut thread_rng(),
+            Round::new(&challenge, &parameters).key_digest(),
+        );
+        let (expected, expected_hash) =
+            expected_response(&challenge, &public_key, &private_key, &parameters);
+        let mut journal = Journal {
+            challenge_hash: [0; 64],
+            step: Step::Transform { start: 0 },
+            public_key: vec![],
+            regions: vec![],
+        };
+        journal.challenge_hash.copy_from_slice(&expected[0..64]);
+        public_key.serialize(&mut journal.public_key).unwrap();
+        // Scribbles over the regions of the step of the journal, like an interrupted write
+        let interrupt = |journal: &Journal| {
+            journal.write(&journal_path).unwrap();
+            let (_, mut map) = map_response(&response_path).unwrap();
+            for (offset, bytes) in &journal.regions {
+                for byte in &mut map[*offset..*offset + bytes.len()] {
+                    *byte = 0xaa;
+                }
+            }
+            map.flush().unwrap();
+        };
+        assert!(recover(&challenge_path, &response_path, &parameters)
+            .unwrap()
+            .is_none());
+
+        // Interrupted while transforming the first chunk
+        fs::write(&response_path, &challenge[..]).unwrap();
+        let regions = BatchedAccumulator::chunk_byte_ranges(0, 4, UseCompression::No, &parameters);
+        journal.regions = regions
+            .iter()
+            .map(|region| (region.start, challenge[region.clone()].to_vec()))
+            .collect();
+        interrupt(&journal);
+        match recover(&challenge_path, &response_path, &parameters).unwrap() {
+            Some(Recovered::Challenge) => {}
+            _ => panic!("the challenge should be back in place"),
+        }
+        assert_eq!(fs::read(&challenge_path).unwrap(), &challenge[..]);
+        assert!(!response_path.exists());
+        assert!(!journal_path.exists());
+
+        // Interrupted while transforming the second chunk
+        fs::rename(&challenge_path, &response_path).unwrap();
+        journal.step = Step::Transform { start: 4 };
+        journal.regions =
+            BatchedAccumulator::chunk_byte_ranges(4, 4, UseCompression::No, &parameters)
+                .into_iter()
+                .map(|region| (region.start, challenge[region].to_vec()))
+                .collect();
+        interrupt(&journal);
+        let error = recover(&challenge_path, &response_path, &parameters)
+            .err()
+            .unwrap();
+        assert_eq!(error.code, errors::INTERRUPTED);
+        assert!(!journal_path.exists());
+
+        // Interrupted while compacting the second batch of tau powers in G1
+        fs::write(&response_path, &challenge[..]).unwrap();
+        let (_, mut map) = map_response(&response_path).unwrap();
+        transform(
+            &mut map,
+            &mut journal,
+            &journal_path,
+            &private_key,
+            CheckForCorrectness::No,
+            &parameters,
+        )
+        .unwrap();
+        compact_batch(&mut map, &mut journal, &journal_path, 0, 0, 3, &parameters).unwrap();
+        compact_batch(&mut map, &mut journal, &journal_path, 0, 4, 7, &parameters).unwrap();
+        drop(map);
+        interrupt(&journal);
+        match recover(&challenge_path, &response_path, &parameters).unwrap() {
+            Some(Recovered::Response {
+                challenge_hash,
+                public_key: recovered_key,
+                response_hash,
+            }) => {
+                assert_eq!(challenge_hash.as_slice(), &expected[0..64]);
+                assert!(recovered_key == public_key);
+                assert_eq!(response_hash, expected_hash);
+            }
+            _ => panic!("the response should be finished"),
+        }
+        assert_eq!(fs::read(&response_path).unwrap(), expected);
+        assert!(!journal_path.exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_journal() {
+        let path = scratch("journal").join("response.journal");
+        let journal = Journal {
+            challenge_hash: [7; 64],
+            step: Step::Compact {
+                section: 2,
+                start: 256,
+            },
+            public_key: vec![1, 2, 3],
+            regions: vec![(64, vec![4; 100]), (1 << 33, vec![])],
+        };
+        journal.write(&path).unwrap();
+        assert_eq!(Journal::read(&path).unwrap(), journal);
+
+        // A truncated journal is rejected
+        let contents = fs::read(&path).unwrap();
+        fs::write(&path, &contents[..contents.len() - 1]).unwrap();
+        assert!(Journal::read(&path).is_err());
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
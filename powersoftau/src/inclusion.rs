@@ -0,0 +1,156 @@
+/// Statements that a response is in the hash chain of a ceremony, which a contributor can
+/// publish to show that their contribution is in the final accumulator. A statement gives
+/// the round of the response, the hash of the challenge it answers and the hash of the
+/// challenge computed from it, `none` if there is none yet:
+///
+/// ```text
+/// round: 2
+/// previous: 5e1c...
+/// contribution: 09ab...
+/// next: 77f0...
+/// ```
+///
+/// The statement is built and checked with `Queue::inclusion_statement`, which audits the
+/// hash chain of the whole ceremony, so it holds for the files as they are at the end of it.
+use std::fmt;
+
+use super::errors::{self, Error, ErrorCategory};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InclusionStatement {
+    pub round: u32,
+    /// Hash of the challenge of the round
+    pub previous: [u8; 64],
+    /// Hash of the response
+    pub contribution: [u8; 64],
+    /// Hash of the challenge of the next round
+    pub next: Option<[u8; 64]>,
+}
+
+impl InclusionStatement {
+    /// Parses a statement written by `Display`. Lines may be in any order.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let invalid = |message: String| {
+            Error::new(errors::INVALID_ARGUMENT, ErrorCategory::InvalidInput, message).with_hint(
+                "the statement must have the round, previous, contribution and next lines written by prove_inclusion",
+            )
+        };
+        let hash = |key: &str, value: &str| {
+            parse_hex_hash(value).ok_or_else(|| invalid(format!("bad {} hash `{}`", key, value)))
+        };
+
+        let (mut round, mut previous, mut contribution, mut next) = (None, None, None, None);
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (key, value) = match line.find(':') {
+                Some(position) => (line[..position].trim(), line[position + 1..].trim()),
+                None => return Err(invalid(format!("expected `key: value`, found `{}`", line))),
+            };
+            match key {
+                "round" => {
+                    round = Some(
+                        value
+                            .parse()
+                            .map_err(|_| invalid(format!("bad round `{}`", value)))?,
+                    )
+                }
+                "previous" => previous = Some(hash(key, value)?),
+                "contribution" => contribution = Some(hash(key, value)?),
+                "next" if value == "none" => next = Some(None),
+                "next" => next = Some(Some(hash(key, value)?)),
+                _ => return Err(invalid(format!("unknown line `{}`", line))),
+            }
+        }
+
+        match (round, previous, contribution, next) {
+            (Some(round), Some(previous), Some(contribution), Some(next)) => {
+                Ok(InclusionStatement {
+                    round,
+                    previous,
+                    contribution,
+                    next,
+                })
+            }
+            _ => Err(invalid("the statement is incomplete".to_string())),
+        }
+    }
+}
+
+impl fmt::Display for InclusionStatement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "round: {}", self.round)?;
+        writeln!(f, "previous: {}", hex::encode(&self.previous[..]))?;
+        writeln!(f, "contribution: {}", hex::encode(&self.contribution[..]))?;
+        match self.next {
+            Some(next) => writeln!(f, "next: {}", hex::encode(&next[..])),
+            None => writeln!(f, "next: none"),
+        }
+    }
+}
+
+pub(crate) fn parse_hex_hash(hex_hash: &str) -> Option<[u8; 64]> {
+    let bytes = hex::decode(hex_hash.strip_prefix("0x").unwrap_or(hex_hash)).ok()?;
+    if bytes.len() != 64 {
+        return None;
+    }
+    let mut hash = [0; 64];
+    hash.copy_from_slice(&bytes);
+
+    Some(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statement_roundtrip() {
+        let mut statement = InclusionStatement {
+            round: 3,
+            previous: [1; 64],
+            contribution: [2; 64],
+            next: Some([3; 64]),
+        };
+        assert_eq!(
+            InclusionStatement::parse(&statement.to_string()).unwrap(),
+            statement
+        );
+        statement.next = None;
+        let text = statement.to_string();
+        assert!(text.ends_with("next: none\n"), "{}", text);
+        assert_eq!(InclusionStatement::parse(&text).unwrap(), statement);
+
+        // Lines in any order, hashes with 0x
+        let text = format!(
+            "contribution: 0x{}\nnext: none\n\nround: 3\nprevious: {}\n",
+            hex::encode(&[2; 64][..]),
+            hex::encode(&[1; 64][..])
+        );
+        assert_eq!(InclusionStatement::parse(&text).unwrap(), statement);
+    }
+
+    #[test]
+    fn test_statement_rejects_malformed() {
+        let text = InclusionStatement {
+            round: 0,
+            previous: [1; 64],
+            contribution: [2; 64],
+            next: None,
+        }
+        .to_string();
+        for malformed in [
+            text.replace("round: 0", "round: -1"),
+            text.replace("round: 0\n", ""),
+            text.replace("previous: 01", "previous: 1"),
+            text.replace("next: none", "next: 0102"),
+            format!("{}signature: none\n", text),
+            format!("{}garbage\n", text),
+        ] {
+            assert_eq!(
+                InclusionStatement::parse(&malformed).unwrap_err().code,
+                errors::INVALID_ARGUMENT,
+                "{}",
+                malformed
+            );
+        }
+    }
+}
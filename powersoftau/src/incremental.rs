@@ -0,0 +1,758 @@
+/// Verification of a response while it is still being received. Uploads only ever append to
+/// the response, so every chunk of it can be verified as soon as all of its bytes are there,
+/// and verification finishes shortly after the upload instead of starting once it is done.
+///
+/// The chunks of the extra powers of tau in G1 only need the first section of the file, so
+/// they are verified early in the upload, and the chunks of the other powers follow as their
+/// powers times beta arrive in the last section. The public key is at the very end of a
+/// response, so the proofs of knowledge and the first powers, which are checked against the
+/// key, are only checked by `finish`. The response is hashed as it arrives, too.
+///
+/// Bytes are fed explicitly with `advance`, which takes everything received so far, so the
+/// response can come from a socket as well as from a file. `follow_response` polls a file
+/// that another process writes to.
+use bellman_ce::pairing::Engine;
+use generic_array::GenericArray;
+use log::info;
+use memmap::{Mmap, MmapOptions};
+use typenum::consts::U64;
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::batched_accumulator::{BatchedAccumulator, FirstPowers};
+use super::errors::{self, Error, ErrorCategory};
+use super::keypair::PublicKey;
+use super::parameters::{
+    expected_sizes, CeremonyParams, CheckForCorrectness, DeserializationError, ElementType,
+    UseCompression,
+};
+
+pub struct IncrementalVerifier<'a, E: Engine> {
+    challenge: &'a [u8],
+    challenge_compression: UseCompression,
+    response_compression: UseCompression,
+    check_challenge: CheckForCorrectness,
+    check_response: CheckForCorrectness,
+    parameters: &'a CeremonyParams<E>,
+    /// Bytes of the response received so far, which are all hashed
+    received: usize,
+    hasher: blake2b_simd::State,
+    first_powers: Option<FirstPowers<E>>,
+    /// Chunks, see `BatchedAccumulator::chunk_ranges`, that aren't verified yet
+    pending: Vec<(usize, usize)>,
+    /// The last power of tau in G1 of the first section and the first of the extra powers
+    sections_link: [Option<E::G1Affine>; 2],
+}
+
+impl<'a, E: Engine> IncrementalVerifier<'a, E> {
+    /// Verifies a response to `challenge`, which must be complete.
+    pub fn new(
+        challenge: &'a [u8],
+        challenge_compression: UseCompression,
+        response_compression: UseCompression,
+        check_challenge: CheckForCorrectness,
+        check_response: CheckForCorrectness,
+        parameters: &'a CeremonyParams<E>,
+    ) -> Self {
+        IncrementalVerifier {
+            challenge,
+            challenge_compression,
+            response_compression,
+            check_challenge,
+            check_response,
+            parameters,
+            received: 0,
+            hasher: blake2b_simd::State::new(),
+            first_powers: None,
+            pending: BatchedAccumulator::chunk_ranges(parameters),
+            sections_link: [None, None],
+        }
+    }
+
+    /// The size of the complete response.
+    pub fn response_size(&self) -> usize {
+        expected_sizes(self.parameters, self.response_compression).response
+    }
+
+    /// Bytes of the response received so far.
+    pub fn received(&self) -> usize {
+        self.received
+    }
+
+    /// Whether the whole response was received.
+    pub fn is_complete(&self) -> bool {
+        self.received == self.response_size()
+    }
+
+    /// Chunks that are still to be verified.
+    pub fn pending_chunks(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// The hash of the response, once it is complete.
+    pub fn response_hash(&self) -> Option<GenericArray<u8, U64>> {
+        if !self.is_complete() {
+            return None;
+        }
+        Some(GenericArray::clone_from_slice(
+            self.hasher.finalize().as_bytes(),
+        ))
+    }
+
+    /// Verifies the chunks whose bytes are all in `response`, the part of the response received
+    /// so far, which must start with the part given to the previous call. Returns the number of
+    /// chunks verified by this call, and fails as soon as a chunk is invalid.
+    pub fn advance(&mut self, response: &[u8]) -> Result<usize, Error> {
+        let response_size = self.response_size();
+        if response.len() > response_size || response.len() < self.received {
+            return Err(Error::new(
+                errors::INVALID_FILE_SIZE,
+                ErrorCategory::InvalidInput,
+                format!(
+                    "the response is {} bytes, after {} were received, but it should grow up to {}",
+                    response.len(),
+                    self.received,
+                    response_size
+                ),
+            )
+            .with_hint("the response must only be appended to while it is verified"));
+        }
+        self.hasher.update(&response[self.received..]);
+        self.received = response.len();
+
+        if self.first_powers.is_none() {
+            self.first_powers = self.read_first_powers(response)?;
+        }
+        let first_powers = match self.first_powers {
+            Some(first_powers) => first_powers,
+            None => return Ok(0),
+        };
+
+        let mut verified = 0;
+        let pending = std::mem::take(&mut self.pending);
+        for (i, &(start, end)) in pending.iter().enumerate() {
+            let size = self.read_size(start, end);
+            let available = self.chunk_elements(start).iter().all(|&element_type| {
+                BatchedAccumulator::element_range(
+                    element_type,
+                    start,
+                    size,
+                    self.response_compression,
+                    self.parameters,
+                )
+                .is_some_and(|range| range.end <= response.len())
+            });
+            if !available {
+                self.pending.push((start, end));
+                continue;
+            }
+            if let Err(e) = self.verify_chunk(start, end, size, first_powers, response) {
+                self.pending.extend_from_slice(&pending[i..]);
+                return Err(e);
+            }
+            verified += 1;
+        }
+
+        Ok(verified)
+    }
+
+    /// Checks what is left once the whole `response` has been received: the proofs of
+    /// knowledge of `key` for the transcript `digest`, the first powers and the link between
+    /// the sections of powers of tau in G1.
+    pub fn finish(
+        &mut self,
+        response: &[u8],
+        key: &PublicKey<E>,
+        digest: &[u8],
+    ) -> Result<(), Error> {
+        self.advance(response)?;
+        if !self.is_complete() {
+            return Err(Error::new(
+                errors::INVALID_FILE_SIZE,
+                ErrorCategory::InvalidInput,
+                format!(
+                    "the response is {} bytes, but should be {}",
+                    self.received,
+                    self.response_size()
+                ),
+            ));
+        }
+        assert!(
+            self.pending.is_empty(),
+            "every chunk is verified once the response is complete"
+        );
+
+        let invalid = || {
+            Error::new(
+                errors::INVALID_CONTRIBUTION,
+                ErrorCategory::Verification,
+                "Verification failed, contribution was invalid somehow.",
+            )
+        };
+        let g2_s = BatchedAccumulator::check_key(key, digest, self.parameters.trapdoors)
+            .ok_or_else(invalid)?;
+        let mut before = BatchedAccumulator::empty(self.parameters);
+        before
+            .read_chunk(
+                0,
+                2,
+                self.challenge_compression,
+                self.check_challenge,
+                self.challenge,
+            )
+            .map_err(|e| Error::from(e).context("unable to read the first chunk of `challenge`"))?;
+        let mut after = BatchedAccumulator::empty(self.parameters);
+        after
+            .read_chunk(
+                0,
+                2,
+                self.response_compression,
+                self.check_response,
+                response,
+            )
+            .map_err(|e| Error::from(e).context("unable to read the first chunk of `response`"))?;
+        if !BatchedAccumulator::check_first_powers(&before, &after, key, &g2_s) {
+            return Err(invalid());
+        }
+
+        let sections_link = [
+            self.sections_link[0].expect("the first section was verified"),
+            self.sections_link[1].expect("the extra powers were verified"),
+        ];
+        let first_powers = self.first_powers.expect("the first powers were read");
+        if !BatchedAccumulator::<E>::check_sections_link(&sections_link, first_powers) {
+            return Err(invalid());
+        }
+
+        Ok(())
+    }
+
+    /// The first two powers of tau in G1 and G2 of the response, once they were received.
+    fn read_first_powers(&self, response: &[u8]) -> Result<Option<FirstPowers<E>>, Error> {
+        let available = [ElementType::TauG1, ElementType::TauG2]
+            .iter()
+            .all(|&element_type| {
+                BatchedAccumulator::element_range(
+                    element_type,
+                    0,
+                    2,
+                    self.response_compression,
+                    self.parameters,
+                )
+                .is_some_and(|range| range.end <= response.len())
+            });
+        if !available {
+            return Ok(None);
+        }
+
+        let mut accumulator = BatchedAccumulator::empty(self.parameters);
+        let read_error =
+            |e| Error::from(e).context("unable to read the first powers of `response`");
+        let g1 = accumulator
+            .read_g1_points(
+                0,
+                2,
+                ElementType::TauG1,
+                self.response_compression,
+                self.check_response,
+                response,
+            )
+            .map_err(read_error)?;
+        let g2 = accumulator
+            .read_g2_points(
+                0,
+                2,
+                ElementType::TauG2,
+                self.response_compression,
+                self.check_response,
+                response,
+            )
+            .map_err(read_error)?;
+
+        Ok(Some(((g1[0], g1[1]), (g2[0], g2[1]))))
+    }
+
+    /// The number of powers read for the chunk from `start` to `end`, which is one more than
+    /// the chunk, to check the link with the next chunk, except at the end of a section.
+    fn read_size(&self, start: usize, end: usize) -> usize {
+        let section_end = if start < self.parameters.powers_length {
+            self.parameters.powers_length
+        } else {
+            self.parameters.powers_g1_length
+        };
+        end - start + 1 + if end == section_end - 1 { 0 } else { 1 }
+    }
+
+    /// The elements that the checks of a chunk from `start` use. Beta in G2 is at the end of
+    /// the accumulator and only checked with the first powers, so it isn't among them.
+    fn chunk_elements(&self, start: usize) -> &'static [ElementType] {
+        if start < self.parameters.powers_length {
+            &[
+                ElementType::TauG1,
+                ElementType::TauG2,
+                ElementType::AlphaG1,
+                ElementType::BetaG1,
+            ]
+        } else {
+            &[ElementType::TauG1]
+        }
+    }
+
+    /// Reads the `chunk_elements` of `size` powers from `start` of `input`.
+    fn read_chunk(
+        &self,
+        start: usize,
+        size: usize,
+        compression: UseCompression,
+        checked: CheckForCorrectness,
+        input: &[u8],
+    ) -> Result<BatchedAccumulator<'a, E>, DeserializationError> {
+        let mut accumulator = BatchedAccumulator::empty(self.parameters);
+        for &element_type in self.chunk_elements(start) {
+            match element_type {
+                ElementType::TauG1 => {
+                    accumulator.tau_powers_g1 = accumulator.read_g1_points(
+                        start,
+                        size,
+                        element_type,
+                        compression,
+                        checked,
+                        input,
+                    )?
+                }
+                ElementType::TauG2 => {
+                    accumulator.tau_powers_g2 = accumulator.read_g2_points(
+                        start,
+                        size,
+                        element_type,
+                        compression,
+                        checked,
+                        input,
+                    )?
+                }
+                ElementType::AlphaG1 => {
+                    accumulator.alpha_tau_powers_g1 = accumulator.read_g1_points(
+                        start,
+                        size,
+                        element_type,
+                        compression,
+                        checked,
+                        input,
+                    )?
+                }
+                ElementType::BetaG1 => {
+                    accumulator.beta_tau_powers_g1 = accumulator.read_g1_points(
+                        start,
+                        size,
+                        element_type,
+                        compression,
+                        checked,
+                        input,
+                    )?
+                }
+                ElementType::BetaG2 => unreachable!("beta in G2 isn't read with a chunk"),
+            }
+        }
+
+        Ok(accumulator)
+    }
+
+    fn verify_chunk(
+        &mut self,
+        start: usize,
+        end: usize,
+        size: usize,
+        first_powers: FirstPowers<E>,
+        response: &[u8],
+    ) -> Result<(), Error> {
+        // The challenge is read to check its points, like `verify_transformation` does
+        self.read_chunk(
+            start,
+            size,
+            self.challenge_compression,
+            self.check_challenge,
+            self.challenge,
+        )
+        .map_err(|e| {
+            Error::from(e).context(format!(
+                "unable to read a chunk from {} to {} from `challenge`",
+                start, end
+            ))
+        })?;
+        let after = self
+            .read_chunk(
+                start,
+                size,
+                self.response_compression,
+                self.check_response,
+                response,
+            )
+            .map_err(|e| {
+                Error::from(e).context(format!(
+                    "unable to read a chunk from {} to {} from `response`",
+                    start, end
+                ))
+            })?;
+
+        if end == self.parameters.powers_length - 1 {
+            self.sections_link[0] = after.tau_powers_g1.last().cloned();
+        }
+        if start == self.parameters.powers_length {
+            self.sections_link[1] = Some(after.tau_powers_g1[0]);
+        }
+        // A chunk of a single power has no pairs, and it was already checked as the overlap of
+        // the previous chunk, or against the last power of the first section
+        if after.tau_powers_g1.len() > 1 && !after.check_chunk_powers(first_powers) {
+            return Err(Error::new(
+                errors::INVALID_CONTRIBUTION,
+                ErrorCategory::Verification,
+                format!(
+                    "Verification failed, the chunk from {} to {} of the response is invalid.",
+                    start, end
+                ),
+            ));
+        }
+        info!("Done processing {} powers of tau", end);
+
+        Ok(())
+    }
+}
+
+/// How `follow_response` waits for the response.
+#[derive(Clone, Copy, Debug)]
+pub struct FollowOptions {
+    pub poll_interval: Duration,
+    pub idle_timeout: Duration,
+}
+
+impl Default for FollowOptions {
+    fn default() -> Self {
+        FollowOptions {
+            poll_interval: Duration::from_millis(500),
+            idle_timeout: Duration::from_secs(600),
+        }
+    }
+}
+
+/// Removes `--follow`, `--poll-interval <ms>` and `--idle-timeout <s>` from the arguments and
+/// returns the options they select, if the response is to be followed.
+pub fn take_follow_options(args: &mut Vec<String>) -> Result<Option<FollowOptions>, Error> {
+    let mut take = |flag: &str| -> Result<Option<u64>, Error> {
+        let position = match args.iter().position(|arg| arg == flag) {
+            Some(position) => position,
+            None => return Ok(None),
+        };
+        let value = args
+            .get(position + 1)
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| {
+                Error::new(
+                    errors::INVALID_ARGUMENT,
+                    ErrorCategory::InvalidInput,
+                    format!("{} requires a number", flag),
+                )
+            })?;
+        args.drain(position..position + 2);
+
+        Ok(Some(value))
+    };
+
+    let mut options = FollowOptions::default();
+    let poll_interval = take("--poll-interval")?;
+    let idle_timeout = take("--idle-timeout")?;
+    let follow = match args.iter().position(|arg| arg == "--follow") {
+        Some(position) => {
+            args.remove(position);
+            true
+        }
+        None => false,
+    };
+    if !follow {
+        if poll_interval.is_some() || idle_timeout.is_some() {
+            return Err(Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                "--poll-interval and --idle-timeout require --follow",
+            ));
+        }
+        return Ok(None);
+    }
+    if let Some(poll_interval) = poll_interval {
+        options.poll_interval = Duration::from_millis(poll_interval);
+    }
+    if let Some(idle_timeout) = idle_timeout {
+        options.idle_timeout = Duration::from_secs(idle_timeout);
+    }
+
+    Ok(Some(options))
+}
+
+/// Feeds the response at `path` to `verifier` as it grows, checking for new bytes every
+/// poll interval of `options`, until it is complete. The file doesn't have to exist yet. Fails
+/// as soon as a chunk is invalid, or if the file doesn't grow for the idle timeout. Returns the complete
+/// response, mapped into memory, for `IncrementalVerifier::finish`.
+pub fn follow_response<E: Engine>(
+    verifier: &mut IncrementalVerifier<E>,
+    path: &Path,
+    options: FollowOptions,
+) -> Result<Mmap, Error> {
+    let mut last_growth = Instant::now();
+    loop {
+        let file = match File::open(path) {
+            Ok(file) => Some(file),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(Error::from(e).context("unable to open the response")),
+        };
+        if let Some(file) = file {
+            let length = file
+                .metadata()
+                .map_err(|e| Error::from(e).context("unable to get the size of the response"))?
+                .len() as usize;
+            if length > verifier.received() {
+                let map = unsafe {
+                    MmapOptions::new()
+                        .len(length)
+                        .map(&file)
+                        .map_err(|e| Error::from(e).context("unable to map the response"))?
+                };
+                let verified = verifier.advance(&map)?;
+                info!(
+                    "Received {} of {} bytes of the response, verified {} more chunks, {} left",
+                    length,
+                    verifier.response_size(),
+                    verified,
+                    verifier.pending_chunks()
+                );
+                if verifier.is_complete() {
+                    return Ok(map);
+                }
+                last_growth = Instant::now();
+            }
+        }
+        if last_growth.elapsed() >= options.idle_timeout {
+            return Err(Error::new(
+                errors::INTERRUPTED,
+                ErrorCategory::Io,
+                format!(
+                    "the response has stayed at {} bytes for {} seconds",
+                    verifier.received(),
+                    options.idle_timeout.as_secs()
+                ),
+            )
+            .with_hint("check that the upload is still running"));
+        }
+        thread::sleep(options.poll_interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::keypair;
+    use crate::utils::calculate_hash;
+    use bellman_ce::pairing::bn256::Bn256;
+    use memmap::MmapMut;
+    use rand::thread_rng;
+
+    /// A challenge and a compressed response to it, with the public key of the response.
+    fn contribution(parameters: &CeremonyParams<Bn256>) -> (Mmap, Mmap, PublicKey<Bn256>) {
+        let mut challenge = MmapMut::map_anon(parameters.accumulator_size).unwrap();
+        BatchedAccumulator::generate_initial(&mut challenge, UseCompression::No, parameters)
+            .unwrap();
+        let challenge = challenge.make_read_only().unwrap();
+        let digest = calculate_hash(&challenge);
+        let (public_key, private_key) = keypair::<_, Bn256>(&mut thread_rng(), &digest);
+
+        let mut response =
+            MmapMut::map_anon(expected_sizes(parameters, UseCompression::Yes).response).unwrap();
+        BatchedAccumulator::transform(
+            &challenge,
+            &mut response,
+            UseCompression::No,
+            UseCompression::Yes,
+            CheckForCorrectness::Yes,
+            &private_key,
+            parameters,
+        )
+        .unwrap();
+        public_key
+            .write(&mut response, UseCompression::Yes, parameters)
+            .unwrap();
+
+        (challenge, response.make_read_only().unwrap(), public_key)
+    }
+
+    fn new_verifier<'a>(
+        challenge: &'a [u8],
+        parameters: &'a CeremonyParams<Bn256>,
+    ) -> IncrementalVerifier<'a, Bn256> {
+        IncrementalVerifier::new(
+            challenge,
+            UseCompression::No,
+            UseCompression::Yes,
+            CheckForCorrectness::No,
+            CheckForCorrectness::Yes,
+            parameters,
+        )
+    }
+
+    #[test]
+    fn test_incremental_verification() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 3);
+        let (challenge, response, public_key) = contribution(&parameters);
+        let digest = calculate_hash(&challenge);
+
+        let mut verifier = new_verifier(&challenge, &parameters);
+        let chunks = verifier.pending_chunks();
+        let mut verified = 0;
+        for length in (0..response.len()).step_by(97) {
+            verified += verifier.advance(&response[..length]).unwrap();
+            assert_eq!(verified + verifier.pending_chunks(), chunks);
+        }
+        // The extra powers of tau in G1 are verified before the last section arrives
+        assert!(verified > 0);
+        assert!(verifier.response_hash().is_none());
+
+        verifier.finish(&response, &public_key, &digest).unwrap();
+        assert_eq!(verifier.pending_chunks(), 0);
+        assert_eq!(verifier.response_hash().unwrap(), calculate_hash(&response));
+        assert!(BatchedAccumulator::verify_transformation(
+            &challenge,
+            &response,
+            &public_key,
+            &digest,
+            UseCompression::No,
+            UseCompression::Yes,
+            CheckForCorrectness::No,
+            CheckForCorrectness::Yes,
+            &parameters,
+        ));
+
+        // Another key doesn't prove the transformation
+        let (other_key, _) = keypair::<_, Bn256>(&mut thread_rng(), &digest);
+        let mut verifier = new_verifier(&challenge, &parameters);
+        assert_eq!(
+            verifier
+                .finish(&response, &other_key, &digest)
+                .unwrap_err()
+                .code,
+            errors::INVALID_CONTRIBUTION
+        );
+    }
+
+    #[test]
+    fn test_incremental_verification_rejects_invalid_chunks() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 3);
+        let (challenge, response, _) = contribution(&parameters);
+
+        // Swap two of the extra powers of tau in G1, which are in the first section of the file
+        let mut corrupted = MmapMut::map_anon(response.len()).unwrap();
+        corrupted.copy_from_slice(&response);
+        let first = BatchedAccumulator::element_range(
+            ElementType::TauG1,
+            parameters.powers_length + 1,
+            1,
+            UseCompression::Yes,
+            &parameters,
+        )
+        .unwrap();
+        let second = first.end..first.end + first.len();
+        let point = corrupted[first.clone()].to_vec();
+        corrupted.copy_within(second.clone(), first.start);
+        corrupted[second.clone()].copy_from_slice(&point);
+
+        // The chunk fails once the first two powers of tau in G2 are received, long before the
+        // response is complete
+        let tau_g2 = BatchedAccumulator::element_range(
+            ElementType::TauG2,
+            0,
+            2,
+            UseCompression::Yes,
+            &parameters,
+        )
+        .unwrap();
+        let mut verifier = new_verifier(&challenge, &parameters);
+        assert_eq!(verifier.advance(&corrupted[..tau_g2.end - 1]).unwrap(), 0);
+        let error = verifier.advance(&corrupted[..tau_g2.end]).unwrap_err();
+        assert_eq!(error.code, errors::INVALID_CONTRIBUTION);
+
+        let mut verifier = new_verifier(&challenge, &parameters);
+        assert!(verifier.advance(&response[..100]).is_ok());
+        assert!(verifier.advance(&response[..50]).is_err());
+    }
+
+    #[test]
+    fn test_take_follow_options() {
+        let mut args: Vec<String> = vec!["verify", "--follow", "--poll-interval", "20"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let options = take_follow_options(&mut args).unwrap().unwrap();
+        assert_eq!(args, vec!["verify".to_string()]);
+        assert_eq!(options.poll_interval, Duration::from_millis(20));
+        assert_eq!(options.idle_timeout, FollowOptions::default().idle_timeout);
+
+        assert!(take_follow_options(&mut args).unwrap().is_none());
+        let mut args = vec!["--idle-timeout".to_string(), "5".to_string()];
+        assert_eq!(
+            take_follow_options(&mut args).unwrap_err().code,
+            errors::INVALID_ARGUMENT
+        );
+    }
+
+    #[test]
+    fn test_follow_response() {
+        let parameters = CeremonyParams::<Bn256>::new(2, 2);
+        let (challenge, response, public_key) = contribution(&parameters);
+        let path = std::env::temp_dir().join(format!("follow_response_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let upload = {
+            let path = path.clone();
+            let response = response.to_vec();
+            thread::spawn(move || {
+                use std::io::Write;
+                thread::sleep(Duration::from_millis(20));
+                let mut file = File::create(&path).unwrap();
+                for part in response.chunks(response.len() / 3 + 1) {
+                    file.write_all(part).unwrap();
+                    file.flush().unwrap();
+                    thread::sleep(Duration::from_millis(20));
+                }
+            })
+        };
+        let mut verifier = new_verifier(&challenge, &parameters);
+        let map = follow_response(
+            &mut verifier,
+            &path,
+            FollowOptions {
+                poll_interval: Duration::from_millis(5),
+                idle_timeout: Duration::from_secs(10),
+            },
+        )
+        .unwrap();
+        upload.join().unwrap();
+        verifier
+            .finish(&map, &public_key, &calculate_hash(&challenge))
+            .unwrap();
+
+        // Nothing is uploaded any more
+        let mut verifier = new_verifier(&challenge, &parameters);
+        std::fs::write(&path, &response[..10]).unwrap();
+        let error = follow_response(
+            &mut verifier,
+            &path,
+            FollowOptions {
+                poll_interval: Duration::from_millis(5),
+                idle_timeout: Duration::from_millis(50),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(error.code, errors::INTERRUPTED);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
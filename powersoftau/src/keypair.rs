@@ -1,15 +1,28 @@
-use bellman_ce::pairing::{CurveAffine, CurveProjective, EncodedPoint, Engine};
+use bellman_ce::pairing::ff::Field;
+use bellman_ce::pairing::{CurveAffine, CurveProjective, EncodedPoint, Engine, GroupDecodingError};
 use blake2::{Blake2b, Digest};
 
 use memmap::{Mmap, MmapMut};
 
+use rand::chacha::ChaChaRng;
 use rand::{Rand, Rng};
 
+use std::fmt;
 use std::io::{self, Read, Write};
+use std::ops::Deref;
+use std::ptr;
+use std::sync::atomic::{self, Ordering};
 
 use typenum::consts::U64;
-
-use super::parameters::{CeremonyParams, DeserializationError, UseCompression};
+use verification_core::{self, ALPHA_PERSONALIZATION, BETA_PERSONALIZATION, TAU_PERSONALIZATION};
+use zeroize::Zeroize;
+
+use super::errors::{self, Error, ErrorCategory};
+use super::metrics::{self, Metric};
+use super::parameters::{
+    expected_sizes, CeremonyParams, DeserializationError, TrapdoorMask, UseCompression,
+};
+use super::profiling::{self, Operation};
 use super::utils::{hash_to_g2, write_point};
 
 /// Contains terms of the form (s<sub>1</sub>, s<sub>1</sub><sup>x</sup>, H(s<sub>1</sub><sup>x</sup>)<sub>2</sub>, H(s<sub>1</sub><sup>x</sup>)<sub>2</sub><sup>x</sup>)
@@ -44,28 +57,159 @@ impl<E: Engine> PartialEq for PublicKey<E> {
 }
 
 /// Contains the secrets τ, α and β that the participant of the ceremony must destroy.
+/// The secrets are overwritten with zeroes when the key is dropped.
 pub struct PrivateKey<E: Engine> {
     pub tau: E::Fr,
     pub alpha: E::Fr,
     pub beta: E::Fr,
 }
 
+impl<E: Engine> Zeroize for PrivateKey<E> {
+    fn zeroize(&mut self) {
+        clear_scalar(&mut self.tau);
+        clear_scalar(&mut self.alpha);
+        clear_scalar(&mut self.beta);
+    }
+}
+
+impl<E: Engine> Drop for PrivateKey<E> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// Overwrites a secret scalar with zero. The write is volatile so that the compiler
+/// does not remove it even if the scalar is never read again.
+pub fn clear_scalar<F: Field>(scalar: &mut F) {
+    unsafe { ptr::write_volatile(scalar, F::zero()) };
+    atomic::compiler_fence(Ordering::SeqCst);
+}
+
+/// Owns a secret and zeroizes it when dropped. The secret can only be borrowed, and the
+/// guard implements neither `Clone` nor a `Debug` that prints the secret, so that copies
+/// of it are not made by accident.
+pub struct SecretGuard<T: Zeroize>(T);
+
+impl<T: Zeroize> SecretGuard<T> {
+    pub fn new(secret: T) -> Self {
+        SecretGuard(secret)
+    }
+}
+
+impl<T: Zeroize> Deref for SecretGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Zeroize> fmt::Debug for SecretGuard<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecretGuard(..)")
+    }
+}
+
+impl<T: Zeroize> Drop for SecretGuard<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Same as `keypair`, but the private key can only be used through a `SecretGuard`.
+pub fn guarded_keypair<R: Rng, E: Engine>(
+    rng: &mut R,
+    digest: &[u8],
+) -> (PublicKey<E>, SecretGuard<PrivateKey<E>>) {
+    guarded_keypair_with_mask(rng, digest, TrapdoorMask::default())
+}
+
+/// Same as `keypair_with_mask`, but the private key can only be used through a `SecretGuard`.
+pub fn guarded_keypair_with_mask<R: Rng, E: Engine>(
+    rng: &mut R,
+    digest: &[u8],
+    trapdoors: TrapdoorMask,
+) -> (PublicKey<E>, SecretGuard<PrivateKey<E>>) {
+    let (public_key, private_key) = keypair_with_mask(rng, digest, trapdoors);
+    (public_key, SecretGuard::new(private_key))
+}
+
+/// Creates an RNG based on a mixture of system randomness and user provided randomness
+pub fn entropy_rng() -> ChaChaRng {
+    use byteorder::{BigEndian, ReadBytesExt};
+    use rand::{OsRng, SeedableRng};
+
+    let h = {
+        let mut system_rng = OsRng::new().unwrap();
+        let mut h = Blake2b::default();
+
+        // Gather 1024 bytes of entropy from the system
+        for _ in 0..1024 {
+            let r: u8 = system_rng.gen();
+            h.input([r]);
+        }
+
+        // Ask the user to provide some information for additional entropy
+        let mut user_input = String::new();
+        println!("Type some random text and press [ENTER] to provide additional entropy...");
+        std::io::stdin()
+            .read_line(&mut user_input)
+            .expect("expected to read some random text from the user");
+
+        // Hash it all up to make a seed
+        h.input(user_input.as_bytes());
+        h.result()
+    };
+
+    let mut digest = &h[..];
+
+    // Interpret the first 32 bytes of the digest as 8 32-bit words
+    let mut seed = [0u32; 8];
+    for s in &mut seed {
+        *s = digest
+            .read_u32::<BigEndian>()
+            .expect("digest is large enough for this to work");
+    }
+
+    ChaChaRng::from_seed(&seed)
+}
+
 /// Constructs a keypair given an RNG and a 64-byte transcript `digest`.
 pub fn keypair<R: Rng, E: Engine>(rng: &mut R, digest: &[u8]) -> (PublicKey<E>, PrivateKey<E>) {
+    keypair_with_mask(rng, digest, TrapdoorMask::default())
+}
+
+/// Constructs a keypair that only updates the trapdoors of `trapdoors`, the secrets of the
+/// others are one. `digest` is the transcript digest of the mask, see `TrapdoorMask::digest`.
+pub fn keypair_with_mask<R: Rng, E: Engine>(
+    rng: &mut R,
+    digest: &[u8],
+    trapdoors: TrapdoorMask,
+) -> (PublicKey<E>, PrivateKey<E>) {
     assert_eq!(digest.len(), 64);
 
-    // tau is a contribution to the "powers of tau", in a set of points of the form "tau^i * G"
-    let tau = E::Fr::rand(rng);
-    // alpha and beta are a set of contributions in a form "alpha * tau^i * G" and that are required
-    // for construction of the polynomials
-    let alpha = E::Fr::rand(rng);
-    let beta = E::Fr::rand(rng);
+    let mut secret = |updated: bool| {
+        if updated {
+            E::Fr::rand(rng)
+        } else {
+            E::Fr::one()
+        }
+    };
+    // The secrets are sampled directly into the key, so that no copies of them outlive it.
+    let private_key = PrivateKey::<E> {
+        // tau is a contribution to the "powers of tau", in a set of points of the form "tau^i * G"
+        tau: secret(trapdoors.tau),
+        // alpha and beta are a set of contributions in a form "alpha * tau^i * G" and that are
+        // required for construction of the polynomials
+        alpha: secret(trapdoors.alpha),
+        beta: secret(trapdoors.beta),
+    };
 
-    let mut op = |x: E::Fr, personalization: u8| {
+    let mut op = |x: &E::Fr, personalization: u8| {
         // Sample random g^s
         let g1_s = E::G1::rand(rng).into_affine();
         // Compute g^{s*x}
-        let g1_s_x = g1_s.mul(x).into_affine();
+        let g1_s_x = g1_s.mul(*x).into_affine();
         // Compute BLAKE2b(personalization | transcript | g^s | g^{s*x})
         let h: generic_array::GenericArray<u8, U64> = {
             let mut h = Blake2b::default();
@@ -78,16 +222,16 @@ pub fn keypair<R: Rng, E: Engine>(rng: &mut R, digest: &[u8]) -> (PublicKey<E>,
         // Hash into G2 as g^{s'}
         let g2_s: E::G2Affine = hash_to_g2::<E>(h.as_ref()).into_affine();
         // Compute g^{s'*x}
-        let g2_s_x = g2_s.mul(x).into_affine();
+        let g2_s_x = g2_s.mul(*x).into_affine();
 
         ((g1_s, g1_s_x), g2_s_x)
     };
 
     // these "public keys" are required for for next participants to check that points are in fact
     // sequential powers
-    let pk_tau = op(tau, 0);
-    let pk_alpha = op(alpha, 1);
-    let pk_beta = op(beta, 2);
+    let pk_tau = op(&private_key.tau, TAU_PERSONALIZATION);
+    let pk_alpha = op(&private_key.alpha, ALPHA_PERSONALIZATION);
+    let pk_beta = op(&private_key.beta, BETA_PERSONALIZATION);
 
     (
         PublicKey {
@@ -98,11 +242,115 @@ pub fn keypair<R: Rng, E: Engine>(rng: &mut R, digest: &[u8]) -> (PublicKey<E>,
             alpha_g2: pk_alpha.1,
             beta_g2: pk_beta.1,
         },
-        PrivateKey { tau, alpha, beta },
+        private_key,
     )
 }
 
+/// Personalization of domain separated transcripts. The version changes with the fields.
+const KEY_DOMAIN_PERSONALIZATION: &[u8] = b"powersoftau key domain v1";
+
+/// The ceremony a key is generated for. Keys are generated and verified with a digest of the
+/// domain and of the challenge hash, instead of the challenge hash alone, so that a public key
+/// can't be replayed in another ceremony, for another size or in another round, even on top
+/// of the same challenge. Keys generated without a domain are legacy keys.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyDomain {
+    /// Name of the curve, e.g. `bn256`
+    pub curve: String,
+    /// Name of the proving system the parameters are for, e.g. `groth16`
+    pub proving_system: String,
+    /// The circuit size exponent
+    pub power: usize,
+    /// Index of the contribution in the ceremony
+    pub round: u64,
+}
+
+impl KeyDomain {
+    /// The domain of the command line tools, which contribute on BN256 for Groth16.
+    pub fn bn256_groth16(power: usize, round: u64) -> Self {
+        KeyDomain {
+            curve: "bn256".to_string(),
+            proving_system: "groth16".to_string(),
+            power,
+            round,
+        }
+    }
+
+    /// The 64-byte transcript digest that keys of this domain use on top of the challenge
+    /// with the hash `challenge_hash`.
+    pub fn digest(&self, challenge_hash: &[u8]) -> [u8; 64] {
+        let mut h = Blake2b::default();
+        // Every field is prefixed with its length, so that fields can't run into each other
+        for field in &[
+            KEY_DOMAIN_PERSONALIZATION,
+            self.curve.as_bytes(),
+            self.proving_system.as_bytes(),
+            &(self.power as u64).to_be_bytes(),
+            &self.round.to_be_bytes(),
+            challenge_hash,
+        ] {
+            h.input((field.len() as u64).to_be_bytes());
+            h.input(field);
+        }
+        let mut digest = [0; 64];
+        digest.copy_from_slice(h.result().as_slice());
+        digest
+    }
+}
+
+/// Removes `--round <n>` from the arguments and returns the round, which selects the keys of
+/// `KeyDomain::bn256_groth16`. Without it, keys are legacy keys.
+pub fn take_round(args: &mut Vec<String>) -> Result<Option<u64>, Error> {
+    let position = match args.iter().position(|arg| arg == "--round") {
+        Some(position) => position,
+        None => return Ok(None),
+    };
+    let round = args
+        .get(position + 1)
+        .and_then(|round| round.parse().ok())
+        .ok_or_else(|| {
+            Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                "--round requires the number of the round",
+            )
+        })?;
+    args.drain(position..position + 2);
+
+    Ok(Some(round))
+}
+
+impl<E: Engine> PublicKey<E> {
+    /// Checks the proofs of knowledge of tau, alpha and beta against the transcript `digest`,
+    /// which is the first check of verifying a transformation.
+    pub fn proves_knowledge(&self, digest: &[u8]) -> bool {
+        let pairs = [
+            (self.tau_g1, self.tau_g2, TAU_PERSONALIZATION),
+            (self.alpha_g1, self.alpha_g2, ALPHA_PERSONALIZATION),
+            (self.beta_g1, self.beta_g2, BETA_PERSONALIZATION),
+        ];
+        pairs.iter().all(|&(g1, g2_x, personalization)| {
+            let _span = profiling::span(Operation::PairingCheck, 2);
+            metrics::count(Metric::Pairings, 2);
+            verification_core::proves_knowledge::<E>(g1, g2_x, digest, personalization)
+        })
+    }
+}
+
 impl<E: Engine> PublicKey<E> {
+    /// Checks that the key leaves the trapdoors outside `trapdoors` as they are: the ratios of
+    /// their pairs in G1 are one. With the proofs of knowledge, this also holds in G2.
+    pub fn respects_mask(&self, trapdoors: TrapdoorMask) -> bool {
+        let pairs = [
+            (self.tau_g1, trapdoors.tau),
+            (self.alpha_g1, trapdoors.alpha),
+            (self.beta_g1, trapdoors.beta),
+        ];
+        pairs
+            .iter()
+            .all(|&((g1_s, g1_s_x), updated)| updated || g1_s == g1_s_x)
+    }
+
     /// Serialize the public key. Points are always in uncompressed form.
     pub fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
         write_point(writer, &self.tau_g1.0, UseCompression::No)?;
@@ -122,8 +370,8 @@ impl<E: Engine> PublicKey<E> {
     }
 
     /// Deserialize the public key. Points are always in uncompressed form, and
-    /// always checked, since there aren't very many of them. Does not allow any
-    /// points at infinity.
+    /// always checked to be in the subgroup, since there aren't very many of them.
+    /// Does not allow any points at infinity.
     pub fn deserialize<R: Read>(reader: &mut R) -> Result<PublicKey<E>, DeserializationError> {
         fn read_uncompressed<EE: Engine, C: CurveAffine<Engine = EE, Scalar = EE::Fr>, R: Read>(
             reader: &mut R,
@@ -134,6 +382,8 @@ impl<E: Engine> PublicKey<E> {
 
             if v.is_zero() {
                 Err(DeserializationError::PointAtInfinity)
+            } else if !v.is_in_subgroup() {
+                Err(GroupDecodingError::NotInSubgroup.into())
             } else {
                 Ok(v)
             }
@@ -173,10 +423,7 @@ impl<E: Engine> PublicKey<E> {
         accumulator_was_compressed: UseCompression,
         parameters: &CeremonyParams<E>,
     ) -> io::Result<()> {
-        let mut position = match accumulator_was_compressed {
-            UseCompression::Yes => parameters.contribution_size - parameters.public_key_size,
-            UseCompression::No => parameters.accumulator_size,
-        };
+        let mut position = expected_sizes(parameters, accumulator_was_compressed).challenge;
 
         let g1_size = parameters.curve.g1;
         let g2_size = parameters.curve.g2;
@@ -213,8 +460,8 @@ impl<E: Engine> PublicKey<E> {
     }
 
     /// Deserialize the public key. Points are always in uncompressed form, and
-    /// always checked, since there aren't very many of them. Does not allow any
-    /// points at infinity.
+    /// always checked to be in the subgroup, since there aren't very many of them.
+    /// Does not allow any points at infinity.
     pub fn read(
         input_map: &Mmap,
         accumulator_was_compressed: UseCompression,
@@ -228,21 +475,22 @@ impl<E: Engine> PublicKey<E> {
             let element_size = C::Uncompressed::size();
             let mut memory_slice = input_map
                 .get(position..position + element_size)
-                .expect("must read point data from file");
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::UnexpectedEof, "public key is truncated")
+                })?;
             memory_slice.read_exact(repr.as_mut())?;
             let v = repr.into_affine()?;
 
             if v.is_zero() {
                 Err(DeserializationError::PointAtInfinity)
+            } else if !v.is_in_subgroup() {
+                Err(GroupDecodingError::NotInSubgroup.into())
             } else {
                 Ok(v)
             }
         }
 
-        let mut position = match accumulator_was_compressed {
-            UseCompression::Yes => parameters.contribution_size - parameters.public_key_size,
-            UseCompression::No => parameters.accumulator_size,
-        };
+        let mut position = expected_sizes(parameters, accumulator_was_compressed).challenge;
 
         let g1_size = parameters.curve.g1;
         let g2_size = parameters.curve.g2;
@@ -291,8 +539,10 @@ mod tests {
 
     mod bn256 {
         use super::*;
-        use crate::parameters::CurveParams;
-        use bellman_ce::pairing::bn256::Bn256;
+        use crate::parameters::{arbitrary, CurveParams};
+        use bellman_ce::pairing::bn256::{Bn256, Fr};
+        use proptest::prelude::{any, prop_assert, proptest, ProptestConfig};
+        use rand::SeedableRng;
 
         #[test]
         fn test_pubkey_serialization() {
@@ -313,5 +563,98 @@ mod tests {
             let deserialized = PublicKey::<Bn256>::deserialize(&mut &v[..]).unwrap();
             assert!(pk == deserialized);
         }
+
+        proptest! {
+            #![proptest_config(ProptestConfig::with_cases(32))]
+
+            #[test]
+            fn prop_public_key_roundtrip(
+                parameters in arbitrary::ceremony_params(),
+                compression in arbitrary::compression(),
+                seed in any::<[u32; 8]>(),
+            ) {
+                let rng = &mut ChaChaRng::from_seed(&seed);
+                let (pk, _) = keypair::<_, Bn256>(rng, &[3; 64]);
+                let sizes = expected_sizes(&parameters, compression);
+
+                // The key is written after the accumulator, up to the end of the response
+                let mut map = MmapMut::map_anon(sizes.response).unwrap();
+                pk.write(&mut map, compression, &parameters).unwrap();
+                prop_assert!(map[..sizes.challenge].iter().all(|&byte| byte == 0));
+                let map = map.make_read_only().unwrap();
+                let read = PublicKey::<Bn256>::read(&map, compression, &parameters).unwrap();
+                prop_assert!(read == pk);
+            }
+        }
+
+        #[test]
+        fn test_private_key_zeroize() {
+            let rng = &mut thread_rng();
+            let digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+            let (_, mut sk) = keypair::<_, Bn256>(rng, &digest);
+            assert!(!sk.tau.is_zero());
+
+            sk.zeroize();
+            assert!(sk.tau.is_zero());
+            assert!(sk.alpha.is_zero());
+            assert!(sk.beta.is_zero());
+        }
+
+        #[test]
+        fn test_key_domain() {
+            let challenge_hash = [5; 64];
+            let domain = KeyDomain::bn256_groth16(21, 3);
+            let digest = domain.digest(&challenge_hash);
+            assert_eq!(digest, domain.digest(&challenge_hash));
+            assert_ne!(&digest[..], &challenge_hash[..]);
+            for other in &[
+                KeyDomain::bn256_groth16(22, 3),
+                KeyDomain::bn256_groth16(21, 4),
+                KeyDomain {
+                    curve: "bls12_381".to_string(),
+                    ..domain.clone()
+                },
+                KeyDomain {
+                    proving_system: "marlin".to_string(),
+                    ..domain.clone()
+                },
+            ] {
+                assert_ne!(other.digest(&challenge_hash), digest);
+            }
+            assert_ne!(domain.digest(&[6; 64]), digest);
+
+            // A key of the domain only verifies with the digest of the domain
+            let (pk, _) = keypair::<_, Bn256>(&mut thread_rng(), &digest);
+            assert!(pk.proves_knowledge(&digest));
+            assert!(!pk.proves_knowledge(&challenge_hash));
+            assert!(!pk.proves_knowledge(&KeyDomain::bn256_groth16(21, 4).digest(&challenge_hash)));
+        }
+
+        #[test]
+        fn test_keypair_with_mask() {
+            let beta_only: TrapdoorMask = "beta".parse().unwrap();
+            let digest = beta_only.digest(&[5; 64]);
+            let (pk, sk) = keypair_with_mask::<_, Bn256>(&mut thread_rng(), &digest, beta_only);
+            assert_eq!(sk.tau, Fr::one());
+            assert_eq!(sk.alpha, Fr::one());
+            assert_ne!(sk.beta, Fr::one());
+            assert!(pk.proves_knowledge(&digest));
+            assert!(pk.respects_mask(beta_only));
+            assert!(pk.respects_mask(TrapdoorMask::default()));
+
+            // A key that updates every trapdoor doesn't leave tau and alpha as they are
+            let (pk, _) = keypair::<_, Bn256>(&mut thread_rng(), &digest);
+            assert!(!pk.respects_mask(beta_only));
+        }
+
+        #[test]
+        fn test_take_round() {
+            let mut args = vec!["cmd".to_string(), "--round".to_string(), "7".to_string()];
+            assert_eq!(take_round(&mut args).unwrap(), Some(7));
+            assert_eq!(args, vec!["cmd".to_string()]);
+            assert_eq!(take_round(&mut args).unwrap(), None);
+            let mut args = vec!["--round".to_string(), "x".to_string()];
+            assert!(take_round(&mut args).is_err());
+        }
     }
 }
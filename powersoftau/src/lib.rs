@@ -1,4 +1,47 @@
+pub mod backend;
 pub mod batched_accumulator;
+pub mod beacon;
+pub mod build_info;
+pub mod client;
+pub mod container;
+#[cfg(feature = "cross-check")]
+pub mod cross_check;
+pub mod disk;
+pub mod download_proof;
+pub mod errors;
+#[cfg(test)]
+mod golden;
+pub mod hash_file;
+pub mod hooks;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod in_memory;
+pub mod in_place;
+pub mod incremental;
+pub mod inclusion;
 pub mod keypair;
+#[cfg(test)]
+mod malicious;
+pub mod merkle;
+pub mod metrics;
 pub mod parameters;
+pub mod path_template;
+pub mod profiling;
+pub mod receipt;
+pub mod report;
+pub mod round;
+pub mod round_state;
+pub mod sampling;
+pub mod signer;
+pub mod spec;
+pub mod storage;
+#[cfg(feature = "http")]
+pub mod transfer;
+pub mod tree_hash;
 pub mod utils;
+pub mod verification_cursor;
+pub mod watch;
+pub mod workset;
+
+/// The checks of a contribution on decoded points, which external verifiers can use alone.
+pub use verification_core;
@@ -0,0 +1,307 @@
+//! Contributions that are well formed but must not verify, to test the verification with
+//! something closer to an attack than flipped bytes, which mostly fail to decode.
+use bellman_ce::pairing::bn256::{Bn256, Fr, G2Affine, G2Compressed};
+use bellman_ce::pairing::ff::PrimeField;
+use bellman_ce::pairing::{CurveAffine, CurveProjective, EncodedPoint};
+use generic_array::GenericArray;
+use memmap::{Mmap, MmapMut};
+use rand::thread_rng;
+use typenum::consts::U64;
+
+use super::batched_accumulator::{AccumulatorView, BatchedAccumulator};
+use super::keypair::{keypair, PublicKey};
+use super::parameters::{CeremonyParams, CheckForCorrectness, ElementType, UseCompression};
+use super::utils::calculate_hash;
+
+/// How a contribution goes wrong.
+#[derive(Clone, Copy, Debug)]
+pub enum Attack {
+    /// Nothing, the contribution is honest
+    None,
+    /// One element is doubled, so its ratio with its neighbours is wrong
+    WrongRatio(ElementType, usize),
+    /// One element in G2 is replaced with a point on the curve outside of the subgroup
+    NotInSubgroup(ElementType, usize),
+    /// The key was generated for another challenge, as if the contributor reused their secrets
+    ReusedKey,
+    /// The published key isn't the one the response was computed with
+    MismatchedKey,
+}
+
+/// A contribution and what it's verified with.
+pub struct Contribution {
+    /// Uncompressed accumulator, without the public key
+    pub response: Mmap,
+    pub public_key: PublicKey<Bn256>,
+    /// Hash of the challenge, which the key should be bound to
+    pub digest: GenericArray<u8, U64>,
+}
+
+impl Contribution {
+    pub fn verify(&self, challenge: &Mmap, parameters: &CeremonyParams<Bn256>) -> bool {
+        BatchedAccumulator::verify_transformation(
+            challenge,
+            &self.response,
+            &self.public_key,
+            &self.digest,
+            UseCompression::No,
+            UseCompression::No,
+            CheckForCorrectness::Yes,
+            CheckForCorrectness::Yes,
+            parameters,
+        )
+    }
+}
+
+pub struct MaliciousContributor {
+    attack: Attack,
+}
+
+impl MaliciousContributor {
+    pub fn new(attack: Attack) -> Self {
+        MaliciousContributor { attack }
+    }
+
+    /// Contributes to the uncompressed `challenge`.
+    pub fn contribute(&self, challenge: &Mmap, parameters: &CeremonyParams<Bn256>) -> Contribution {
+        let rng = &mut thread_rng();
+        let digest = calculate_hash(challenge);
+        let key_digest = match self.attack {
+            Attack::ReusedKey => calculate_hash(&random_map(64)),
+            _ => digest,
+        };
+        let (mut public_key, private_key) = keypair::<_, Bn256>(rng, &key_digest);
+        if let Attack::MismatchedKey = self.attack {
+            public_key = keypair::<_, Bn256>(rng, &digest).0;
+        }
+
+        let mut response = MmapMut::map_anon(parameters.accumulator_size).unwrap();
+        BatchedAccumulator::transform(
+            challenge,
+            &mut response,
+            UseCompression::No,
+            UseCompression::No,
+            CheckForCorrectness::Yes,
+            &private_key,
+            parameters,
+        )
+        .unwrap();
+
+        match self.attack {
+            Attack::WrongRatio(element_type, index) => {
+                let view = AccumulatorView::new(
+                    &response,
+                    UseCompression::No,
+                    CheckForCorrectness::Yes,
+                    parameters,
+                )
+                .unwrap();
+                let two = Fr::from_str("2").unwrap();
+                match element_type {
+                    ElementType::TauG2 | ElementType::BetaG2 => {
+                        let point = match element_type {
+                            ElementType::TauG2 => view.tau_powers_g2(index..index + 1).unwrap()[0],
+                            _ => view.beta_g2().unwrap(),
+                        };
+                        let doubled = point.mul(two).into_affine();
+                        write(&mut response, element_type, index, doubled, parameters);
+                    }
+                    _ => {
+                        let range = index..index + 1;
+                        let point = match element_type {
+                            ElementType::TauG1 => view.tau_powers_g1(range),
+                            ElementType::AlphaG1 => view.alpha_tau_powers_g1(range),
+                            _ => view.beta_tau_powers_g1(range),
+                        }
+                        .unwrap()[0];
+                        let doubled = point.mul(two).into_affine();
+                        write(&mut response, element_type, index, doubled, parameters);
+                    }
+                }
+            }
+            Attack::NotInSubgroup(element_type, index) => {
+                write(
+                    &mut response,
+                    element_type,
+                    index,
+                    point_not_in_subgroup(),
+                    parameters,
+                );
+            }
+            _ => {}
+        }
+
+        Contribution {
+            response: response.make_read_only().unwrap(),
+            public_key,
+            digest,
+        }
+    }
+}
+
+/// A challenge with `parameters` that has had a contribution, so that its elements differ.
+pub fn challenge(parameters: &CeremonyParams<Bn256>) -> Mmap {
+    let mut initial = MmapMut::map_anon(parameters.accumulator_size).unwrap();
+    BatchedAccumulator::generate_initial(&mut initial, UseCompression::No, parameters).unwrap();
+    let initial = initial.make_read_only().unwrap();
+
+    MaliciousContributor::new(Attack::None)
+        .contribute(&initial, parameters)
+        .response
+}
+
+/// A point on the curve in G2 that isn't in the subgroup of prime order. Almost any point on
+/// the twist is, since the cofactor is large.
+pub fn point_not_in_subgroup() -> G2Affine {
+    let mut encoded = G2Compressed::empty();
+    for x in 1..=u8::MAX {
+        encoded.as_mut()[63] = x;
+        if let Ok(point) = encoded.into_affine_unchecked() {
+            if !point.mul(Fr::char()).is_zero() {
+                return point;
+            }
+        }
+    }
+    unreachable!("half of the x coordinates are on the curve")
+}
+
+fn write<C: CurveAffine>(
+    map: &mut MmapMut,
+    element_type: ElementType,
+    index: usize,
+    point: C,
+    parameters: &CeremonyParams<Bn256>,
+) {
+    let range =
+        BatchedAccumulator::element_range(element_type, index, 1, UseCompression::No, parameters)
+            .unwrap();
+    map[range].copy_from_slice(point.into_uncompressed().as_ref());
+}
+
+fn random_map(size: usize) -> Mmap {
+    use rand::Rng;
+
+    let mut map = MmapMut::map_anon(size).unwrap();
+    thread_rng().fill_bytes(&mut map);
+    map.make_read_only().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parameters::DeserializationError;
+    use bellman_ce::pairing::GroupDecodingError;
+
+    #[test]
+    fn test_malicious_contributions() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let challenge = challenge(&parameters);
+        let verify = |attack| {
+            MaliciousContributor::new(attack)
+                .contribute(&challenge, &parameters)
+                .verify(&challenge, &parameters)
+        };
+
+        assert!(verify(Attack::None));
+        assert!(!verify(Attack::ReusedKey));
+        assert!(!verify(Attack::MismatchedKey));
+        for &(element_type, index) in &[
+            (ElementType::TauG1, 1),
+            (ElementType::TauG1, parameters.powers_g1_length - 1),
+            (ElementType::TauG2, 3),
+            (ElementType::AlphaG1, 0),
+            (ElementType::BetaG1, parameters.powers_length - 1),
+            (ElementType::BetaG2, 0),
+        ] {
+            assert!(
+                !verify(Attack::WrongRatio(element_type, index)),
+                "{:?} {}",
+                element_type,
+                index
+            );
+        }
+        for &(element_type, index) in &[(ElementType::TauG2, 2), (ElementType::BetaG2, 0)] {
+            assert!(
+                !verify(Attack::NotInSubgroup(element_type, index)),
+                "{:?} {}",
+                element_type,
+                index
+            );
+        }
+    }
+
+    #[test]
+    fn test_failures_are_located() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let challenge = challenge(&parameters);
+        let contribution = |attack| {
+            MaliciousContributor::new(attack)
+                .contribute(&challenge, &parameters)
+                .response
+        };
+        let verify = |response: &Mmap, check, element_type, index| {
+            BatchedAccumulator::verify_element(
+                response,
+                UseCompression::No,
+                check,
+                element_type,
+                index,
+                &parameters,
+            )
+            .unwrap()
+        };
+        let verify_element = |response: &Mmap, element_type, index| {
+            verify(response, CheckForCorrectness::Yes, element_type, index)
+        };
+        let verify_unchecked_element = |response: &Mmap, element_type, index| {
+            verify(response, CheckForCorrectness::No, element_type, index)
+        };
+
+        // A wrong ratio fails the element and its neighbours, and only the chunk they're in
+        let response = contribution(Attack::WrongRatio(ElementType::AlphaG1, 5));
+        for index in 0..parameters.powers_length {
+            let expected = !(4..=6).contains(&index);
+            assert_eq!(
+                verify_element(&response, ElementType::AlphaG1, index),
+                expected
+            );
+            assert!(verify_element(&response, ElementType::TauG1, index));
+        }
+        let chunks = BatchedAccumulator::chunk_ranges(&parameters);
+        for (chunk, &(start, end)) in chunks.iter().enumerate() {
+            let expected = end < 4 || start > 6;
+            let verified = BatchedAccumulator::verify_chunks(
+                &response,
+                UseCompression::No,
+                CheckForCorrectness::Yes,
+                &[chunk],
+                &parameters,
+            )
+            .unwrap();
+            assert_eq!(verified, expected, "chunk {}", chunk);
+        }
+
+        // Points outside of the subgroup don't decode, and the ratios catch them when the
+        // points aren't checked
+        let response = contribution(Attack::NotInSubgroup(ElementType::TauG2, 2));
+        let view = AccumulatorView::new(
+            &response,
+            UseCompression::No,
+            CheckForCorrectness::Yes,
+            &parameters,
+        )
+        .unwrap();
+        match view.tau_powers_g2(2..3) {
+            Err(DeserializationError::DecodingError(GroupDecodingError::NotInSubgroup)) => {}
+            _ => panic!("the point must not decode"),
+        }
+        assert!(view.tau_powers_g2(3..4).is_ok());
+        assert!(!verify_unchecked_element(&response, ElementType::TauG2, 2));
+        assert!(verify_unchecked_element(&response, ElementType::TauG2, 4));
+
+        let response = contribution(Attack::NotInSubgroup(ElementType::BetaG2, 0));
+        assert!(!verify_unchecked_element(&response, ElementType::BetaG2, 0));
+        assert!(!verify_unchecked_element(&response, ElementType::BetaG1, 0));
+        assert!(verify_unchecked_element(&response, ElementType::BetaG1, 1));
+    }
+}
@@ -0,0 +1,361 @@
+/// Merkle tree over a final accumulator, for applications that embed only a few of its powers.
+/// The file is split into leaves and the leaves are hashed as in the tree hash of `tree_hash`.
+/// The leaf hashes are then combined pairwise, level by level, up to a single node:
+///
+/// node = BLAKE2b(0x02 | left | right)
+///
+/// A node without a sibling, at the end of a level of odd length, moves up unchanged. The root
+/// binds the layout of the file to the top node:
+///
+/// root = BLAKE2b(0x03 | leaf_size (u64, BE) | length (u64, BE) | top node)
+///
+/// A subset proof carries the leaves that cover a byte range of the file and the sibling nodes
+/// needed to recompute the root, so that the range can be checked against a published root
+/// without downloading the rest of the file.
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use std::io::{self, Read, Write};
+use std::ops::Range;
+
+use super::tree_hash::{hash_leaf, hash_leaves, leaf_range, TreeHash};
+
+/// Identifies a subset proof file, followed by the format version.
+const MAGIC: &[u8; 8] = b"POTMRKPF";
+const VERSION: u32 = 1;
+
+const NODE_PREFIX: u8 = 2;
+const ROOT_PREFIX: u8 = 3;
+
+/// Leaf size used by the command line tools unless another one is given. Proofs carry whole
+/// leaves, so they are much smaller than the leaves of tree hashes.
+pub const DEFAULT_LEAF_SIZE: u64 = 1 << 16;
+
+fn hash_node(left: &[u8; 64], right: &[u8; 64]) -> [u8; 64] {
+    let mut hasher = Blake2b::default();
+    hasher.input([NODE_PREFIX]);
+    hasher.input(&left[..]);
+    hasher.input(&right[..]);
+    let mut hash = [0; 64];
+    hash.copy_from_slice(hasher.result().as_slice());
+    hash
+}
+
+fn hash_root(leaf_size: u64, length: u64, top: &[u8; 64]) -> [u8; 64] {
+    let mut hasher = Blake2b::default();
+    hasher.input([ROOT_PREFIX]);
+    hasher.input(leaf_size.to_be_bytes());
+    hasher.input(length.to_be_bytes());
+    hasher.input(&top[..]);
+    let mut root = [0; 64];
+    root.copy_from_slice(hasher.result().as_slice());
+    root
+}
+
+/// The level above `nodes`, which must start at an even index of their level.
+fn parents(nodes: &[[u8; 64]]) -> Vec<[u8; 64]> {
+    nodes
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => hash_node(left, right),
+            [single] => *single,
+            _ => unreachable!("chunks are of one or two nodes"),
+        })
+        .collect()
+}
+
+/// Indices of the leaves that cover `range`.
+fn covering_leaves(range: &Range<u64>, leaf_size: u64) -> Range<usize> {
+    let first = range.start / leaf_size;
+    let last = (range.end - 1) / leaf_size;
+    first as usize..last as usize + 1
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MerkleTree {
+    /// Size of every leaf but the last one
+    pub leaf_size: u64,
+    /// Length of the file
+    pub length: u64,
+    /// Nodes of every level, from the leaf hashes up to the top node
+    levels: Vec<Vec<[u8; 64]>>,
+}
+
+impl MerkleTree {
+    /// Computes the tree of `data` with leaves of `leaf_size` bytes, spreading the leaves over
+    /// all cores.
+    pub fn compute(data: &[u8], leaf_size: u64) -> Self {
+        assert!(leaf_size > 0, "leaf size must be positive");
+        assert!(!data.is_empty(), "the file must not be empty");
+        let leaves = hash_leaves(data, leaf_size, data.len() as u64)
+            .into_iter()
+            .map(|leaf| leaf.expect("all leaves are in the data"))
+            .collect::<Vec<_>>();
+
+        let mut levels = vec![leaves];
+        while let Some(level) = levels.last().filter(|level| level.len() > 1) {
+            let next = parents(level);
+            levels.push(next);
+        }
+
+        MerkleTree {
+            leaf_size,
+            length: data.len() as u64,
+            levels,
+        }
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Root of the tree, which is what gets published next to the plain hash of the file.
+    pub fn root(&self) -> [u8; 64] {
+        let top = &self.levels[self.levels.len() - 1][0];
+        hash_root(self.leaf_size, self.length, top)
+    }
+
+    /// Proves the bytes of `range` of `data`, which must be the file the tree was computed
+    /// from. The range must be a non-empty range of the file.
+    pub fn prove(&self, data: &[u8], range: Range<u64>) -> SubsetProof {
+        assert_eq!(data.len() as u64, self.length, "not the file of the tree");
+        assert!(
+            range.start < range.end && range.end <= self.length,
+            "range outside of the file"
+        );
+        let leaves = covering_leaves(&range, self.leaf_size);
+        let bytes = leaf_range(leaves.start, self.leaf_size, self.length).start
+            ..leaf_range(leaves.end - 1, self.leaf_size, self.length).end;
+
+        let mut siblings = vec![];
+        let (mut start, mut end) = (leaves.start, leaves.end);
+        for level in &self.levels[..self.levels.len() - 1] {
+            if start % 2 == 1 {
+                start -= 1;
+                siblings.push(level[start]);
+            }
+            if end % 2 == 1 && end < level.len() {
+                siblings.push(level[end]);
+                end += 1;
+            }
+            start /= 2;
+            end = end.div_ceil(2);
+        }
+
+        SubsetProof {
+            leaf_size: self.leaf_size,
+            length: self.length,
+            range,
+            leaves: data[bytes].to_vec(),
+            siblings,
+        }
+    }
+}
+
+/// Proof that a byte range is part of the file of a Merkle root.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubsetProof {
+    pub leaf_size: u64,
+    /// Length of the file
+    pub length: u64,
+    /// Proved byte range of the file
+    pub range: Range<u64>,
+    /// Bytes of the leaves that cover the range
+    pub leaves: Vec<u8>,
+    /// Siblings needed to recompute the root, from the leaves up
+    pub siblings: Vec<[u8; 64]>,
+}
+
+impl SubsetProof {
+    /// Byte range of the file covered by the leaves of the proof.
+    fn leaf_bytes(&self) -> Range<u64> {
+        let leaves = covering_leaves(&self.range, self.leaf_size);
+        let start = leaves.start as u64 * self.leaf_size;
+        let end = std::cmp::min(
+            (leaves.end as u64).saturating_mul(self.leaf_size),
+            self.length,
+        );
+        start..end
+    }
+
+    /// The bytes of the proved range. They are only authenticated once `verify` succeeds.
+    pub fn subset(&self) -> &[u8] {
+        let offset = (self.range.start - self.leaf_bytes().start) as usize;
+        let length = (self.range.end - self.range.start) as usize;
+        &self.leaves[offset..offset + length]
+    }
+
+    /// Checks the proof against a published root.
+    pub fn verify(&self, root: &[u8; 64]) -> bool {
+        if self.leaf_size == 0 || self.range.start >= self.range.end || self.range.end > self.length
+        {
+            return false;
+        }
+        let leaves = covering_leaves(&self.range, self.leaf_size);
+        let bytes = self.leaf_bytes();
+        let mut nodes = self
+            .leaves
+            .chunks(self.leaf_size as usize)
+            .map(hash_leaf)
+            .collect::<Vec<_>>();
+        if self.leaves.len() as u64 != bytes.end - bytes.start || nodes.len() != leaves.len() {
+            return false;
+        }
+
+        let mut siblings = self.siblings.iter();
+        let mut start = leaves.start;
+        let mut level_length = TreeHash::leaf_count_for(self.length, self.leaf_size);
+        while level_length > 1 {
+            if start % 2 == 1 {
+                match siblings.next() {
+                    Some(sibling) => nodes.insert(0, *sibling),
+                    None => return false,
+                }
+                start -= 1;
+            }
+            let end = start + nodes.len();
+            if end % 2 == 1 && end < level_length {
+                match siblings.next() {
+                    Some(sibling) => nodes.push(*sibling),
+                    None => return false,
+                }
+            }
+            nodes = parents(&nodes);
+            start /= 2;
+            level_length = level_length.div_ceil(2);
+        }
+
+        siblings.next().is_none()
+            && nodes.len() == 1
+            && hash_root(self.leaf_size, self.length, &nodes[0])[..] == root[..]
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&MAGIC[..])?;
+        writer.write_u32::<BigEndian>(VERSION)?;
+        writer.write_u64::<BigEndian>(self.leaf_size)?;
+        writer.write_u64::<BigEndian>(self.length)?;
+        writer.write_u64::<BigEndian>(self.range.start)?;
+        writer.write_u64::<BigEndian>(self.range.end)?;
+        writer.write_all(&self.leaves)?;
+        writer.write_u32::<BigEndian>(self.siblings.len() as u32)?;
+        for sibling in &self.siblings {
+            writer.write_all(&sibling[..])?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a subset proof".to_string()));
+        }
+        let version = reader.read_u32::<BigEndian>()?;
+        if version != VERSION {
+            return Err(invalid(format!(
+                "unsupported subset proof version {}",
+                version
+            )));
+        }
+
+        let leaf_size = reader.read_u64::<BigEndian>()?;
+        let length = reader.read_u64::<BigEndian>()?;
+        let range = reader.read_u64::<BigEndian>()?..reader.read_u64::<BigEndian>()?;
+        if leaf_size == 0 || leaf_size > usize::MAX as u64 {
+            return Err(invalid("invalid leaf size".to_string()));
+        }
+        if range.start >= range.end || range.end > length {
+            return Err(invalid("range outside of the file".to_string()));
+        }
+
+        let mut proof = SubsetProof {
+            leaf_size,
+            length,
+            range,
+            leaves: vec![],
+            siblings: vec![],
+        };
+        // The lengths are untrusted, so the leaves and siblings are only allocated as they
+        // are read
+        let bytes = proof.leaf_bytes();
+        let leaves_length = bytes.end - bytes.start;
+        reader.take(leaves_length).read_to_end(&mut proof.leaves)?;
+        if proof.leaves.len() as u64 != leaves_length {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "subset proof is truncated",
+            ));
+        }
+        let count = reader.read_u32::<BigEndian>()?;
+        for _ in 0..count {
+            let mut sibling = [0; 64];
+            reader.read_exact(&mut sibling)?;
+            proof.siblings.push(sibling);
+        }
+
+        Ok(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+
+    fn random_bytes(length: usize) -> Vec<u8> {
+        let rng = &mut thread_rng();
+        (0..length).map(|_| rng.gen()).collect()
+    }
+
+    #[test]
+    fn test_subset_proofs() {
+        let data = random_bytes(1000);
+        // 16 leaves, and levels of odd length with 13 leaves
+        for &leaf_size in &[64, 77, 1000, 2000] {
+            let tree = MerkleTree::compute(&data, leaf_size);
+            let root = tree.root();
+            for range in &[0..1, 0..1000, 63..65, 500..501, 999..1000, 130..900] {
+                let proof = tree.prove(&data, range.clone());
+                assert!(proof.verify(&root));
+                assert_eq!(
+                    proof.subset(),
+                    &data[range.start as usize..range.end as usize]
+                );
+
+                // Other bytes or leaves don't match the root
+                let mut other = proof.clone();
+                other.leaves[0] ^= 1;
+                assert!(!other.verify(&root));
+                if range.end + leaf_size <= 1000 {
+                    let mut other = proof.clone();
+                    other.range = range.start + leaf_size..range.end + leaf_size;
+                    assert!(!other.verify(&root));
+                }
+            }
+        }
+
+        let tree = MerkleTree::compute(&data, 64);
+        assert_eq!(tree.leaf_count(), 16);
+        // The layout is part of the root
+        assert!(tree.root()[..] != MerkleTree::compute(&data, 128).root()[..]);
+        let mut proof = tree.prove(&data, 100..200);
+        proof.siblings.pop();
+        assert!(!proof.verify(&tree.root()));
+    }
+
+    #[test]
+    fn test_subset_proof_serialization() {
+        let data = random_bytes(1000);
+        let tree = MerkleTree::compute(&data, 77);
+        let proof = tree.prove(&data, 100..300);
+        let mut bytes = vec![];
+        proof.write(&mut bytes).unwrap();
+        assert_eq!(SubsetProof::read(&mut &bytes[..]).unwrap(), proof);
+        assert!(SubsetProof::read(&mut &bytes[..bytes.len() - 1]).is_err());
+        assert!(SubsetProof::read(&mut &bytes[..bytes.len() / 2]).is_err());
+    }
+}
@@ -0,0 +1,325 @@
+/// Counters of the work a command did, exported in the Prometheus text format when it
+/// completes, so that coordinators running many verifiers get the same metrics from every one
+/// of them. Unlike the `profiling` counters, these are always collected: they are updated once
+/// per point written or chunk read, which costs nothing next to the curve arithmetic.
+///
+/// The metrics are written to a file for the textfile collector of the node exporter with
+/// `--metrics-textfile <file>`, or pushed to a Pushgateway with `--metrics-push <url>`.
+use std::fmt::Write as FmtWrite;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use super::errors::{self, Error, ErrorCategory};
+use super::hooks::write_atomically;
+#[cfg(feature = "http")]
+use super::hooks::Webhook;
+
+/// The counted quantities.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Metric {
+    BytesRead,
+    BytesWritten,
+    ChunksRead,
+    ChunksWritten,
+    Pairings,
+}
+
+const METRICS: [Metric; 5] = [
+    Metric::BytesRead,
+    Metric::BytesWritten,
+    Metric::ChunksRead,
+    Metric::ChunksWritten,
+    Metric::Pairings,
+];
+
+impl Metric {
+    fn name(self) -> &'static str {
+        match self {
+            Metric::BytesRead => "powersoftau_bytes_read_total",
+            Metric::BytesWritten => "powersoftau_bytes_written_total",
+            Metric::ChunksRead => "powersoftau_chunks_read_total",
+            Metric::ChunksWritten => "powersoftau_chunks_written_total",
+            Metric::Pairings => "powersoftau_pairings_total",
+        }
+    }
+
+    fn help(self) -> &'static str {
+        match self {
+            Metric::BytesRead => "Bytes of accumulator elements read",
+            Metric::BytesWritten => "Bytes of accumulator elements written",
+            Metric::ChunksRead => "Chunks of accumulators read",
+            Metric::ChunksWritten => "Chunks of accumulators written",
+            Metric::Pairings => "Pairings computed to check ratios and proofs of knowledge",
+        }
+    }
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicU64 = AtomicU64::new(0);
+
+static COUNTERS: [AtomicU64; 5] = [ZERO; 5];
+
+pub fn count(metric: Metric, amount: u64) {
+    COUNTERS[metric as usize].fetch_add(amount, Ordering::Relaxed);
+}
+
+pub fn value(metric: Metric) -> u64 {
+    COUNTERS[metric as usize].load(Ordering::Relaxed)
+}
+
+/// Escapes a label value of the Prometheus text format, where only backslashes, double quotes
+/// and line feeds are escaped.
+fn label_value(s: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Renders the counters and the duration of the command in the Prometheus text format, every
+/// sample labelled with the command.
+pub fn render(command: &str, duration: Duration, success: bool) -> String {
+    let label = format!("{{command={}}}", label_value(command));
+    let mut text = String::new();
+    let mut sample = |name: &str, kind: &str, help: &str, value: String| {
+        write!(
+            text,
+            "# HELP {} {}\n# TYPE {} {}\n{}{} {}\n",
+            name, help, name, kind, name, label, value
+        )
+        .expect("must write to a string");
+    };
+
+    for metric in METRICS.iter() {
+        sample(
+            metric.name(),
+            "counter",
+            metric.help(),
+            value(*metric).to_string(),
+        );
+    }
+    sample(
+        "powersoftau_command_duration_seconds",
+        "gauge",
+        "Wall time of the command",
+        format!("{:.3}", duration.as_secs_f64()),
+    );
+    sample(
+        "powersoftau_command_success",
+        "gauge",
+        "Whether the command succeeded",
+        (success as u8).to_string(),
+    );
+
+    text
+}
+
+/// Where the metrics of a command go once it completes.
+pub struct MetricsExport {
+    textfile: Option<PathBuf>,
+    #[cfg(feature = "http")]
+    push: Option<Webhook>,
+    started: Instant,
+}
+
+impl MetricsExport {
+    /// Writes or pushes the metrics of `command`. Metrics are not what the command is run for,
+    /// so failing to export them is reported without failing the command.
+    pub fn export(&self, command: &str, success: bool) {
+        #[cfg(feature = "http")]
+        let pushes = self.push.is_some();
+        #[cfg(not(feature = "http"))]
+        let pushes = false;
+        if self.textfile.is_none() && !pushes {
+            return;
+        }
+        let text = render(command, self.started.elapsed(), success);
+        if let Some(textfile) = &self.textfile {
+            match write_atomically(textfile, text.as_bytes()) {
+                Ok(()) => println!("Metrics written to {}", textfile.display()),
+                Err(e) => eprintln!(
+                    "{}",
+                    Error::from(e).context(format!(
+                        "unable to write the metrics to {}",
+                        textfile.display()
+                    ))
+                ),
+            }
+        }
+        #[cfg(feature = "http")]
+        if let Some(push) = &self.push {
+            // The text format of version 0.0.4 is the one the Pushgateway accepts
+            match push.send("PUT", "text/plain; version=0.0.4", &text) {
+                Ok(()) => println!("Metrics pushed to {}", push.url()),
+                Err(e) => eprintln!("{}", e.context("unable to push the metrics")),
+            }
+        }
+    }
+}
+
+/// Removes `--metrics-textfile <file>` and `--metrics-push <url>` from the arguments and
+/// returns where the metrics go. The duration of the command is counted from here.
+pub fn take_metrics(args: &mut Vec<String>) -> Result<MetricsExport, Error> {
+    let mut take = |flag: &str| -> Result<Option<String>, Error> {
+        let position = match args.iter().position(|arg| arg == flag) {
+            Some(position) => position,
+            None => return Ok(None),
+        };
+        if position + 1 >= args.len() {
+            return Err(Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                format!("{} requires a value", flag),
+            ));
+        }
+        let value = args[position + 1].clone();
+        args.drain(position..position + 2);
+
+        Ok(Some(value))
+    };
+
+    let textfile = take("--metrics-textfile")?.map(PathBuf::from);
+    #[cfg(feature = "http")]
+    let push = match take("--metrics-push")? {
+        Some(url) => Some(Webhook::parse(&url)?),
+        None => None,
+    };
+    #[cfg(not(feature = "http"))]
+    {
+        if take("--metrics-push")?.is_some() {
+            return Err(super::hooks::requires_http("--metrics-push"));
+        }
+    }
+
+    Ok(MetricsExport {
+        textfile,
+        #[cfg(feature = "http")]
+        push,
+        started: Instant::now(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        count(Metric::Pairings, 2);
+        let text = render(
+            "verify_transform_constrained",
+            Duration::from_millis(1500),
+            true,
+        );
+        assert!(text.contains("# TYPE powersoftau_pairings_total counter\n"));
+        assert!(text.contains("powersoftau_command_duration_seconds{command=\"verify_transform_constrained\"} 1.500\n"));
+        assert!(text
+            .contains("powersoftau_command_success{command=\"verify_transform_constrained\"} 1\n"));
+        // Every sample has its help and type
+        assert_eq!(text.lines().count(), 3 * (METRICS.len() + 2));
+    }
+
+    #[test]
+    fn test_label_value() {
+        assert_eq!(label_value("compute"), "\"compute\"");
+        assert_eq!(
+            label_value("a\\b \"c\"\nd\te"),
+            "\"a\\\\b \\\"c\\\"\\nd\te\""
+        );
+    }
+
+    #[test]
+    fn test_export_textfile() {
+        let textfile = std::env::temp_dir().join("powersoftau_test_metrics_textfile.prom");
+        let mut args: Vec<String> = vec!["cmd", "--metrics-textfile", textfile.to_str().unwrap()]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let metrics = take_metrics(&mut args).unwrap();
+        assert_eq!(args, vec!["cmd".to_string()]);
+        metrics.export("compute_constrained", true);
+
+        let written = std::fs::read_to_string(&textfile).unwrap();
+        assert!(
+            written.contains("powersoftau_command_success{command=\"compute_constrained\"} 1\n")
+        );
+        std::fs::remove_file(&textfile).unwrap();
+
+        assert!(take_metrics(&mut vec!["--metrics-textfile".to_string()]).is_err());
+    }
+
+    #[cfg(not(feature = "http"))]
+    #[test]
+    fn test_push_requires_http() {
+        let mut args = vec!["--metrics-push".to_string(), "http://host".to_string()];
+        let error = take_metrics(&mut args).err().unwrap();
+        assert!(error.message.contains("`http` feature"));
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_export() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+        use std::thread;
+
+        let textfile = std::env::temp_dir().join("powersoftau_test_metrics.prom");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://{}/metrics/job/verifier",
+            listener.local_addr().unwrap()
+        );
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = String::new();
+            let mut buffer = [0; 4096];
+            // The body ends with the success of the command
+            while !request
+                .contains("powersoftau_command_success{command=\"compute_constrained\"} 0\n")
+            {
+                let read = stream.read(&mut buffer).unwrap();
+                assert!(read > 0, "the request ended early");
+                request.push_str(&String::from_utf8_lossy(&buffer[..read]));
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            request
+        });
+
+        let mut args: Vec<String> = vec![
+            "cmd",
+            "--metrics-textfile",
+            textfile.to_str().unwrap(),
+            "--metrics-push",
+            &url,
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+        let metrics = take_metrics(&mut args).unwrap();
+        assert_eq!(args, vec!["cmd".to_string()]);
+        metrics.export("compute_constrained", false);
+
+        let written = std::fs::read_to_string(&textfile).unwrap();
+        assert!(
+            written.contains("powersoftau_command_success{command=\"compute_constrained\"} 0\n")
+        );
+        let request = server.join().unwrap();
+        assert!(request.starts_with("PUT /metrics/job/verifier HTTP/1.1\r\n"));
+        assert!(request.contains("powersoftau_bytes_read_total"));
+        std::fs::remove_file(&textfile).unwrap();
+
+        assert!(take_metrics(&mut vec!["--metrics-push".to_string()]).is_err());
+        let mut ftp = vec!["--metrics-push".to_string(), "ftp://host".to_string()];
+        assert!(take_metrics(&mut ftp).is_err());
+    }
+}
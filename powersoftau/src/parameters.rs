@@ -1,9 +1,17 @@
-use bellman_ce::pairing::{CurveAffine, EncodedPoint, Engine, GroupDecodingError};
+use bellman_ce::pairing::ff::PrimeField;
+use bellman_ce::pairing::{CurveAffine, CurveProjective, EncodedPoint, Engine, GroupDecodingError};
+use blake2::{Blake2b, Digest};
 use std::fmt;
-use std::io;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::marker::PhantomData;
+use std::str::FromStr;
+use verification_core::{hash_to_g1, hash_to_g2};
 
-/// The sizes of the group elements of a curev
+use super::errors::{self, Error, ErrorCategory};
+use super::utils::write_point;
+
+/// The sizes of the group elements of a curev, and how they are checked
 #[derive(Clone, PartialEq, Eq, Default)]
 pub struct CurveParams<E> {
     /// Size of a G1 Element
@@ -14,7 +22,10 @@ pub struct CurveParams<E> {
     pub g1_compressed: usize,
     /// Size of a compressed G2 Element
     pub g2_compressed: usize,
-    engine_type: PhantomData<E>,
+    /// How points are checked to be in the prime order subgroup
+    pub subgroup_check: SubgroupCheckMode,
+    // Only sizes are stored, so the parameters can be shared between threads whatever `E` is
+    engine_type: PhantomData<fn() -> E>,
 }
 
 impl<E: Engine> CurveParams<E> {
@@ -29,16 +40,35 @@ impl<E: Engine> CurveParams<E> {
             g2,
             g1_compressed,
             g2_compressed,
+            subgroup_check: SubgroupCheckMode::default(),
             engine_type: PhantomData,
         }
     }
+
+    /// Size of a G1 element encoded with `compression`.
+    pub fn g1_size(&self, compression: UseCompression) -> usize {
+        match compression.g1() {
+            UseCompression::Yes => self.g1_compressed,
+            _ => self.g1,
+        }
+    }
+
+    /// Size of a G2 element encoded with `compression`.
+    pub fn g2_size(&self, compression: UseCompression) -> usize {
+        match compression.g2() {
+            UseCompression::Yes => self.g2_compressed,
+            _ => self.g2,
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Eq)]
 /// The parameters used for the trusted setup ceremony
-pub struct CeremonyParams<E> {
+pub struct CeremonyParams<E: Engine> {
     /// The type of the curve being used (currently only supports BN256)
     pub curve: CurveParams<E>,
+    /// The points that the powers are taken of
+    pub generators: Generators<E>,
     /// The number of Powers of Tau G1 elements which will be accumulated
     pub powers_g1_length: usize,
     /// The number of Powers of Tau Alpha/Beta/G2 elements which will be accumulated
@@ -57,6 +87,9 @@ pub struct CeremonyParams<E> {
     pub contribution_size: usize,
     /// Size of the hash of the previous contribution
     pub hash_size: usize,
+    /// The trapdoors that contributions update, all of them unless later rounds only refresh
+    /// some of them
+    pub trapdoors: TrapdoorMask,
 }
 
 impl<E: Engine> CeremonyParams<E> {
@@ -67,6 +100,34 @@ impl<E: Engine> CeremonyParams<E> {
         Self::new_with_curve(curve, size, batch_size)
     }
 
+    /// Same as `new`, but returns a descriptive error for parameters the ceremony can't run with,
+    /// instead of panicking or failing somewhere in the middle of the computation. Any power
+    /// from 1 up to the two-adicity of the scalar field works, with any positive batch size, so
+    /// tests can use tiny ceremonies.
+    pub fn validated(size: usize, batch_size: usize) -> Result<Self, Error> {
+        let max_size = <E::Fr as PrimeField>::S as usize;
+        if size == 0 || size > max_size {
+            return Err(Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                format!(
+                    "circuit power must be between 1 and {}, but it's {}",
+                    max_size, size
+                ),
+            )
+            .with_hint("a power of 0 has no powers of tau in G2 to check the contributions against, and powers above the two-adicity of the scalar field can't be used for FFTs"));
+        }
+        if batch_size == 0 {
+            return Err(Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                "batch size must be positive",
+            ));
+        }
+
+        Ok(Self::new(size, batch_size))
+    }
+
     /// Constructs a new ceremony parameters object from the directly provided curve with parameters
     /// Consider using the `new` method if you want to use one of the pre-implemented curves
     pub fn new_with_curve(curve: CurveParams<E>, size: usize, batch_size: usize) -> Self {
@@ -108,6 +169,7 @@ impl<E: Engine> CeremonyParams<E> {
 
         Self {
             curve,
+            generators: Generators::default(),
             size,
             batch_size,
             accumulator_size,
@@ -116,34 +178,570 @@ impl<E: Engine> CeremonyParams<E> {
             hash_size,
             powers_length,
             powers_g1_length,
+            trapdoors: TrapdoorMask::default(),
         }
     }
+
+    /// Starts the ceremony from `generators` instead of the generators of the subgroups.
+    pub fn with_generators(mut self, generators: Generators<E>) -> Self {
+        self.generators = generators;
+        self
+    }
 }
 
-// TODO: Add tests!
+impl<E: Engine> fmt::Debug for CeremonyParams<E> {
+    /// Only the power and the batch size, every size follows from them.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CeremonyParams")
+            .field("size", &self.size)
+            .field("batch_size", &self.batch_size)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The points that the powers of a ceremony are taken of: the first powers of tau in G1 and
+/// G2, which every other power is a multiple of. These are the generators of the prime order
+/// subgroups by default, but a ceremony may start from other publicly specified points, for
+/// example so that its parameters are separated from those of other protocols.
+#[derive(Clone, Debug)]
+pub struct Generators<E: Engine> {
+    pub g1: E::G1Affine,
+    pub g2: E::G2Affine,
+}
+
+impl<E: Engine> PartialEq for Generators<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.g1 == other.g1 && self.g2 == other.g2
+    }
+}
+
+impl<E: Engine> Eq for Generators<E> {}
+
+impl<E: Engine> Default for Generators<E> {
+    fn default() -> Self {
+        Generators {
+            g1: E::G1Affine::one(),
+            g2: E::G2Affine::one(),
+        }
+    }
+}
+
+impl<E: Engine> Generators<E> {
+    /// Size of the encoding, which is both points uncompressed.
+    pub fn size() -> usize {
+        <E::G1Affine as CurveAffine>::Uncompressed::size()
+            + <E::G2Affine as CurveAffine>::Uncompressed::size()
+    }
+
+    /// Generators hashed to the curve from `domain`, so that anyone can derive them again and
+    /// no one knows their discrete logarithms.
+    pub fn derive(domain: &[u8]) -> Self {
+        let digest = |personalization: u8| {
+            let mut h = Blake2b::default();
+            h.input([personalization]);
+            h.input(domain);
+            h.result()
+        };
+
+        Generators {
+            g1: hash_to_g1::<E>(digest(1).as_ref()).into_affine(),
+            g2: hash_to_g2::<E>(digest(2).as_ref()).into_affine(),
+        }
+    }
+
+    /// Whether these are the generators of the subgroups.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(self.g1.into_uncompressed().as_ref())?;
+        writer.write_all(self.g2.into_uncompressed().as_ref())
+    }
+
+    /// Reads generators written with `write`. Both points must be in the subgroup and neither
+    /// can be the point at infinity, otherwise the powers taken of them prove nothing.
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, DeserializationError> {
+        let mut g1 = <E::G1Affine as CurveAffine>::Uncompressed::empty();
+        reader.read_exact(g1.as_mut())?;
+        let mut g2 = <E::G2Affine as CurveAffine>::Uncompressed::empty();
+        reader.read_exact(g2.as_mut())?;
+        let (g1, g2): (E::G1Affine, E::G2Affine) = (g1.into_affine()?, g2.into_affine()?);
+        if g1.is_zero() || g2.is_zero() {
+            return Err(DeserializationError::PointAtInfinity);
+        }
+        // Only two points are checked, so the check that works on any curve is cheap enough
+        let mode = SubgroupCheckMode::FullOrder;
+        if !mode.is_in_subgroup(&g1) || !mode.is_in_subgroup(&g2) {
+            return Err(GroupDecodingError::NotInSubgroup.into());
+        }
+
+        Ok(Generators { g1, g2 })
+    }
+}
+
+/// Removes `--generators <file>` from the arguments and returns the generators read from the
+/// file, or `None` if it isn't given.
+pub fn take_generators<E: Engine>(args: &mut Vec<String>) -> Result<Option<Generators<E>>, Error> {
+    let position = match args.iter().position(|arg| arg == "--generators") {
+        Some(position) => position,
+        None => return Ok(None),
+    };
+    if position + 1 >= args.len() {
+        return Err(Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            "--generators requires a file",
+        ));
+    }
+    let mut file = File::open(&args[position + 1])
+        .map_err(|e| Error::from(e).context("unable to open the generators file"))?;
+    let generators = Generators::read(&mut file).map_err(|e| {
+        Error::from(e)
+            .context("unable to read the generators")
+            .with_hint("the file must hold the generator of G1 and then of G2, uncompressed")
+    })?;
+    args.drain(position..position + 2);
+
+    Ok(Some(generators))
+}
 
 /// Determines if point compression should be used.
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum UseCompression {
     Yes,
     No,
+    /// G2 elements are compressed, which saves the most space, and G1 elements are not, so
+    /// that they are fast to read
+    Mixed,
+}
+
+impl UseCompression {
+    /// How G1 elements are encoded, which is either `Yes` or `No`.
+    pub fn g1(self) -> UseCompression {
+        match self {
+            UseCompression::Mixed => UseCompression::No,
+            compression => compression,
+        }
+    }
+
+    /// How G2 elements are encoded, which is either `Yes` or `No`.
+    pub fn g2(self) -> UseCompression {
+        match self {
+            UseCompression::Mixed => UseCompression::Yes,
+            compression => compression,
+        }
+    }
+
+    /// How elements of `element_type` are encoded, which is either `Yes` or `No`.
+    pub fn of(self, element_type: ElementType) -> UseCompression {
+        match element_type {
+            ElementType::TauG2 | ElementType::BetaG2 => self.g2(),
+            _ => self.g1(),
+        }
+    }
+
+    /// The declaration of the policy that follows the hash header of a file, `MIXED_HEADER` for
+    /// mixed files. Compressed and uncompressed files predate the declaration and have none,
+    /// the encoding of their first point tells them apart.
+    pub fn header(self) -> &'static [u8] {
+        match self {
+            UseCompression::Mixed => MIXED_HEADER,
+            _ => &[],
+        }
+    }
+}
+
+/// The policy header of mixed files: G1 uncompressed, G2 compressed.
+pub const MIXED_HEADER: &[u8] = b"g1u,g2c\n";
+
+impl fmt::Display for UseCompression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UseCompression::Yes => write!(f, "compressed"),
+            UseCompression::No => write!(f, "uncompressed"),
+            UseCompression::Mixed => write!(f, "mixed"),
+        }
+    }
+}
+
+impl FromStr for UseCompression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "compressed" => Ok(UseCompression::Yes),
+            "uncompressed" => Ok(UseCompression::No),
+            "mixed" => Ok(UseCompression::Mixed),
+            _ => Err(Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                format!("unknown encoding {}", s),
+            )
+            .with_hint("use one of compressed, uncompressed or mixed")),
+        }
+    }
+}
+
+/// Detects the compression of the accumulator read from `reader` from its policy header and the
+/// encodings of its first points in G1 and G2, which are the generators of the ceremony in every
+/// challenge and response. Mixed files declare their policy after the hash header, compressed
+/// and uncompressed files start with the generator of G1 in their encoding. Returns `None` if
+/// the first points aren't the generators encoded as the policy says, as for corrupted files.
+pub fn detect_compression<E: Engine, R: Read + Seek>(
+    mut reader: R,
+    parameters: &CeremonyParams<E>,
+) -> io::Result<Option<UseCompression>> {
+    let mut read_at = |position: usize, length: usize| -> io::Result<Vec<u8>> {
+        let mut bytes = vec![];
+        reader.seek(SeekFrom::Start(position as u64))?;
+        (&mut reader).take(length as u64).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    };
+    let generators = &parameters.generators;
+
+    // The compressed encoding is checked last, as it could be a prefix of the uncompressed one
+    let start = read_at(parameters.hash_size, parameters.curve.g1)?;
+    let compression = if start.starts_with(MIXED_HEADER) {
+        UseCompression::Mixed
+    } else if start.starts_with(generators.g1.into_uncompressed().as_ref()) {
+        UseCompression::No
+    } else if start.starts_with(generators.g1.into_compressed().as_ref()) {
+        UseCompression::Yes
+    } else {
+        return Ok(None);
+    };
+
+    // The first powers of tau in G1 and G2, after the policy header
+    let mut g1 = vec![];
+    write_point(&mut g1, &generators.g1, compression.g1())?;
+    let mut g2 = vec![];
+    write_point(&mut g2, &generators.g2, compression.g2())?;
+    let g1_position = parameters.hash_size + compression.header().len();
+    let g2_position =
+        g1_position + parameters.powers_g1_length * parameters.curve.g1_size(compression);
+    if read_at(g1_position, g1.len())? != g1 || read_at(g2_position, g2.len())? != g2 {
+        return Ok(None);
+    }
+
+    Ok(Some(compression))
+}
+
+/// Fails with `DeserializationError::CompressionMismatch` if the accumulator read from `reader`
+/// is detected to be written with another compression than `expected`, which would otherwise
+/// show up as a wrong file size or as invalid points.
+pub fn check_compression<E: Engine, R: Read + Seek>(
+    reader: R,
+    expected: UseCompression,
+    parameters: &CeremonyParams<E>,
+) -> Result<(), DeserializationError> {
+    match detect_compression(reader, parameters)? {
+        Some(detected) if detected != expected => {
+            Err(DeserializationError::CompressionMismatch { expected, detected })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The sizes in bytes of the files of a round, all derived from the ceremony parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArtifactSizes {
+    /// The challenge that a participant contributes to
+    pub challenge: usize,
+    /// The response of a participant, which is the transformed challenge and its public key
+    pub response: usize,
+    /// The challenge of the next round, recomputed from the response without its public key
+    pub new_challenge: usize,
+    /// The public key at the end of a response
+    pub public_key: usize,
+    /// The hash at the start of every file, and of the files themselves
+    pub hash: usize,
+}
+
+/// Returns the expected sizes of the files of a round written with `compression`, so that
+/// every tool checks and allocates them the same way.
+pub fn expected_sizes<E: Engine>(
+    parameters: &CeremonyParams<E>,
+    compression: UseCompression,
+) -> ArtifactSizes {
+    let (g1_size, g2_size) = (
+        parameters.curve.g1_size(compression),
+        parameters.curve.g2_size(compression),
+    );
+    let challenge = parameters.hash_size
+        + compression.header().len()
+        + parameters.powers_g1_length * g1_size
+        + parameters.powers_length * (g2_size + 2 * g1_size)
+        + g2_size;
+
+    ArtifactSizes {
+        challenge,
+        response: challenge + parameters.public_key_size,
+        new_challenge: challenge,
+        public_key: parameters.public_key_size,
+        hash: parameters.hash_size,
+    }
 }
 
 /// Determines if points should be checked for correctness during deserialization.
 /// This is not necessary for participants, because a transcript verifier can
-/// check this theirself.
-#[derive(Copy, Clone, PartialEq)]
+/// check this theirself. Points are always checked to be on the curve.
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum CheckForCorrectness {
+    /// Points must be in the prime order subgroup and not at infinity
     Yes,
+    /// Points must be in the prime order subgroup, which is the expensive part of the checks
+    OnlyInGroup,
+    /// Points must not be at infinity, which is cheap to check
+    OnlyNonZero,
     No,
 }
 
+impl CheckForCorrectness {
+    pub fn checks_subgroup(self) -> bool {
+        match self {
+            CheckForCorrectness::Yes | CheckForCorrectness::OnlyInGroup => true,
+            CheckForCorrectness::OnlyNonZero | CheckForCorrectness::No => false,
+        }
+    }
+
+    pub fn rejects_infinity(self) -> bool {
+        match self {
+            CheckForCorrectness::Yes | CheckForCorrectness::OnlyNonZero => true,
+            CheckForCorrectness::OnlyInGroup | CheckForCorrectness::No => false,
+        }
+    }
+}
+
+impl FromStr for CheckForCorrectness {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "full" => Ok(CheckForCorrectness::Yes),
+            "in-group" => Ok(CheckForCorrectness::OnlyInGroup),
+            "non-zero" => Ok(CheckForCorrectness::OnlyNonZero),
+            "none" => Ok(CheckForCorrectness::No),
+            _ => Err(Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                format!("unknown correctness check {}", s),
+            )
+            .with_hint("use one of full, in-group, non-zero or none")),
+        }
+    }
+}
+
+/// Removes `--check-input <full|in-group|non-zero|none>` from the arguments and returns the
+/// checks of the points of the input, or `default` if it isn't given.
+pub fn take_check_input(
+    args: &mut Vec<String>,
+    default: CheckForCorrectness,
+) -> Result<CheckForCorrectness, Error> {
+    let position = match args.iter().position(|arg| arg == "--check-input") {
+        Some(position) => position,
+        None => return Ok(default),
+    };
+    if position + 1 >= args.len() {
+        return Err(Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            "--check-input requires a value",
+        ));
+    }
+    let checks = args[position + 1].parse()?;
+    args.drain(position..position + 2);
+
+    Ok(checks)
+}
+
+/// How points are checked to be in the prime order subgroup, when they are checked at all.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SubgroupCheckMode {
+    /// Multiplying by the order of the subgroup, which works on any curve
+    FullOrder,
+    /// The cheapest test the curve has: nothing beyond being on the curve for a cofactor of 1,
+    /// as in G1 of BN256, and `psi(P) = [6u^2]P` in G2 of BN256, which only multiplies by a
+    /// scalar of half the size
+    #[default]
+    CofactorMul,
+}
+
+impl SubgroupCheckMode {
+    /// Whether `point`, which must be on the curve, is in the subgroup of prime order.
+    pub fn is_in_subgroup<C: CurveAffine>(self, point: &C) -> bool {
+        match self {
+            SubgroupCheckMode::FullOrder => point.mul(C::Scalar::char()).is_zero(),
+            SubgroupCheckMode::CofactorMul => point.is_in_subgroup(),
+        }
+    }
+}
+
+impl fmt::Display for SubgroupCheckMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SubgroupCheckMode::FullOrder => write!(f, "full-order"),
+            SubgroupCheckMode::CofactorMul => write!(f, "cofactor-mul"),
+        }
+    }
+}
+
+impl FromStr for SubgroupCheckMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "full-order" => Ok(SubgroupCheckMode::FullOrder),
+            "cofactor-mul" => Ok(SubgroupCheckMode::CofactorMul),
+            _ => Err(Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                format!("unknown subgroup check {}", s),
+            )
+            .with_hint("use one of full-order or cofactor-mul")),
+        }
+    }
+}
+
+/// Removes `--subgroup-check <full-order|cofactor-mul>` from the arguments and returns how
+/// points are checked to be in the subgroup, or `None` if it isn't given.
+pub fn take_subgroup_check(args: &mut Vec<String>) -> Result<Option<SubgroupCheckMode>, Error> {
+    let position = match args.iter().position(|arg| arg == "--subgroup-check") {
+        Some(position) => position,
+        None => return Ok(None),
+    };
+    if position + 1 >= args.len() {
+        return Err(Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            "--subgroup-check requires a value",
+        ));
+    }
+    let mode = args[position + 1].parse()?;
+    args.drain(position..position + 2);
+
+    Ok(Some(mode))
+}
+
+/// Personalization of the transcripts of contributions that only update some trapdoors.
+const TRAPDOOR_MASK_PERSONALIZATION: &[u8] = b"powersoftau trapdoor mask v1";
+
+/// The trapdoors that a contribution updates. The secrets of the others are one, so their
+/// powers are left as they are and their ratios in the public key are one, which verification
+/// checks. A partial update is recorded in the transcript that the keys prove knowledge with,
+/// see `digest`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TrapdoorMask {
+    pub tau: bool,
+    pub alpha: bool,
+    pub beta: bool,
+}
+
+impl Default for TrapdoorMask {
+    fn default() -> Self {
+        TrapdoorMask {
+            tau: true,
+            alpha: true,
+            beta: true,
+        }
+    }
+}
+
+impl TrapdoorMask {
+    pub fn is_full(&self) -> bool {
+        self.tau && self.alpha && self.beta
+    }
+
+    /// The transcript digest of the keys of a contribution with this mask, from the digest
+    /// that keys updating every trapdoor use, which is returned as it is for the full mask.
+    pub fn digest(&self, digest: &[u8]) -> Vec<u8> {
+        if self.is_full() {
+            return digest.to_vec();
+        }
+        let mut h = Blake2b::default();
+        h.input(TRAPDOOR_MASK_PERSONALIZATION);
+        h.input([self.tau as u8, self.alpha as u8, self.beta as u8]);
+        h.input(digest);
+        h.result().to_vec()
+    }
+}
+
+impl fmt::Display for TrapdoorMask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = [
+            ("tau", self.tau),
+            ("alpha", self.alpha),
+            ("beta", self.beta),
+        ];
+        let names: Vec<_> = names
+            .iter()
+            .filter(|(_, updated)| *updated)
+            .map(|(name, _)| *name)
+            .collect();
+        write!(f, "{}", names.join(","))
+    }
+}
+
+impl FromStr for TrapdoorMask {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let mut mask = TrapdoorMask {
+            tau: false,
+            alpha: false,
+            beta: false,
+        };
+        for name in s.split(',') {
+            let updated = match name {
+                "tau" => &mut mask.tau,
+                "alpha" => &mut mask.alpha,
+                "beta" => &mut mask.beta,
+                _ => {
+                    return Err(Error::new(
+                        errors::INVALID_ARGUMENT,
+                        ErrorCategory::InvalidInput,
+                        format!("unknown trapdoor {}", name),
+                    )
+                    .with_hint("use a comma separated list of tau, alpha and beta"))
+                }
+            };
+            *updated = true;
+        }
+
+        Ok(mask)
+    }
+}
+
+/// Removes `--trapdoors <tau,alpha,beta>` from the arguments and returns the trapdoors that
+/// the contribution updates, or `None` if it isn't given.
+pub fn take_trapdoors(args: &mut Vec<String>) -> Result<Option<TrapdoorMask>, Error> {
+    let position = match args.iter().position(|arg| arg == "--trapdoors") {
+        Some(position) => position,
+        None => return Ok(None),
+    };
+    if position + 1 >= args.len() {
+        return Err(Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            "--trapdoors requires a value",
+        ));
+    }
+    let mask = args[position + 1].parse()?;
+    args.drain(position..position + 2);
+
+    Ok(Some(mask))
+}
+
 /// Errors that might occur during deserialization.
 #[derive(Debug)]
 pub enum DeserializationError {
     IoError(io::Error),
     DecodingError(GroupDecodingError),
     PointAtInfinity,
+    CompressionMismatch {
+        expected: UseCompression,
+        detected: UseCompression,
+    },
 }
 
 impl fmt::Display for DeserializationError {
@@ -152,6 +750,13 @@ impl fmt::Display for DeserializationError {
             DeserializationError::IoError(ref e) => write!(f, "Disk IO error: {}", e),
             DeserializationError::DecodingError(ref e) => write!(f, "Decoding error: {}", e),
             DeserializationError::PointAtInfinity => write!(f, "Point at infinity found"),
+            DeserializationError::CompressionMismatch { expected, detected } => {
+                write!(
+                    f,
+                    "The file is {}, but it was expected to be {}",
+                    detected, expected
+                )
+            }
         }
     }
 }
@@ -176,3 +781,300 @@ pub enum ElementType {
     BetaG1,
     BetaG2,
 }
+
+impl FromStr for ElementType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "tau_g1" => Ok(ElementType::TauG1),
+            "tau_g2" => Ok(ElementType::TauG2),
+            "alpha_g1" => Ok(ElementType::AlphaG1),
+            "beta_g1" => Ok(ElementType::BetaG1),
+            "beta_g2" => Ok(ElementType::BetaG2),
+            _ => Err(Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                format!("unknown element type {}", s),
+            )
+            .with_hint("use one of tau_g1, tau_g2, alpha_g1, beta_g1 or beta_g2")),
+        }
+    }
+}
+
+/// Strategies for property tests. Ceremonies are tiny, so that a case runs in milliseconds, and
+/// batches go from a single element to more than the whole accumulator, so that chunks end
+/// anywhere in the sections.
+#[cfg(test)]
+pub(crate) mod arbitrary {
+    use super::*;
+    use bellman_ce::pairing::bn256::Bn256;
+    use proptest::prelude::*;
+
+    pub fn ceremony_params() -> impl Strategy<Value = CeremonyParams<Bn256>> {
+        (1usize..=4, prop_oneof![1usize..=17, Just(1 << 10)])
+            .prop_map(|(size, batch_size)| CeremonyParams::new(size, batch_size))
+    }
+
+    pub fn compression() -> impl Strategy<Value = UseCompression> {
+        prop_oneof![
+            Just(UseCompression::No),
+            Just(UseCompression::Yes),
+            Just(UseCompression::Mixed),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bellman_ce::pairing::bn256::Bn256;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_take_check_input() {
+        let mut args = vec!["compute".to_string()];
+        let checks = take_check_input(&mut args, CheckForCorrectness::No).unwrap();
+        assert_eq!(checks, CheckForCorrectness::No);
+
+        let mut args = vec![
+            "compute".to_string(),
+            "--check-input".to_string(),
+            "in-group".to_string(),
+        ];
+        let checks = take_check_input(&mut args, CheckForCorrectness::No).unwrap();
+        assert_eq!(checks, CheckForCorrectness::OnlyInGroup);
+        assert!(checks.checks_subgroup() && !checks.rejects_infinity());
+        assert_eq!(args, vec!["compute".to_string()]);
+
+        let mut args = vec!["compute".to_string(), "--check-input".to_string()];
+        assert!(take_check_input(&mut args, CheckForCorrectness::No).is_err());
+        assert!("some".parse::<CheckForCorrectness>().is_err());
+    }
+
+    #[test]
+    fn test_subgroup_check_modes() {
+        use bellman_ce::pairing::bn256::{G1, G2};
+        use rand::{thread_rng, Rand};
+
+        let mut args = vec![
+            "verify".to_string(),
+            "--subgroup-check".to_string(),
+            "full-order".to_string(),
+        ];
+        let mode = take_subgroup_check(&mut args).unwrap();
+        assert_eq!(mode, Some(SubgroupCheckMode::FullOrder));
+        assert_eq!(args, vec!["verify".to_string()]);
+        assert_eq!(take_subgroup_check(&mut args).unwrap(), None);
+        assert!("fast".parse::<SubgroupCheckMode>().is_err());
+
+        let rng = &mut thread_rng();
+        let g1 = G1::rand(rng).into_affine();
+        let g2 = G2::rand(rng).into_affine();
+        let outside = crate::malicious::point_not_in_subgroup();
+        for &mode in &[SubgroupCheckMode::FullOrder, SubgroupCheckMode::CofactorMul] {
+            assert!(mode.is_in_subgroup(&g1));
+            assert!(mode.is_in_subgroup(&g2));
+            assert!(!mode.is_in_subgroup(&outside));
+        }
+    }
+
+    #[test]
+    fn test_trapdoor_masks() {
+        let mut args = vec![
+            "compute".to_string(),
+            "--trapdoors".to_string(),
+            "beta".to_string(),
+        ];
+        let mask = take_trapdoors(&mut args).unwrap().unwrap();
+        assert_eq!(args, vec!["compute".to_string()]);
+        assert_eq!(
+            mask,
+            TrapdoorMask {
+                tau: false,
+                alpha: false,
+                beta: true
+            }
+        );
+        assert_eq!(mask.to_string(), "beta");
+        assert_eq!(TrapdoorMask::default().to_string(), "tau,alpha,beta");
+        assert_eq!(
+            "tau,alpha,beta".parse::<TrapdoorMask>().unwrap(),
+            TrapdoorMask::default()
+        );
+        assert!("gamma".parse::<TrapdoorMask>().is_err());
+        assert!("".parse::<TrapdoorMask>().is_err());
+
+        // Only partial updates change the transcript, and each in its own way
+        let digest = [7; 64];
+        assert_eq!(TrapdoorMask::default().digest(&digest), digest.to_vec());
+        assert_ne!(mask.digest(&digest), digest.to_vec());
+        assert_ne!(
+            mask.digest(&digest),
+            "alpha".parse::<TrapdoorMask>().unwrap().digest(&digest)
+        );
+    }
+
+    #[test]
+    fn test_generators() {
+        use std::io::Cursor;
+
+        assert!(Generators::<Bn256>::default().is_default());
+        let generators = Generators::<Bn256>::derive(b"domain");
+        assert!(!generators.is_default());
+        assert_eq!(generators, Generators::derive(b"domain"));
+        assert!(generators != Generators::derive(b"other domain"));
+
+        let mut encoded = vec![];
+        generators.write(&mut encoded).unwrap();
+        assert_eq!(encoded.len(), Generators::<Bn256>::size());
+        let read = Generators::<Bn256>::read(&mut Cursor::new(&encoded)).unwrap();
+        assert_eq!(read, generators);
+        assert!(Generators::<Bn256>::read(&mut &encoded[1..]).is_err());
+
+        // Neither point can be at infinity or outside of the subgroup
+        let mut infinity = vec![];
+        Generators::<Bn256> {
+            g1: generators.g1,
+            g2: <Bn256 as Engine>::G2Affine::zero(),
+        }
+        .write(&mut infinity)
+        .unwrap();
+        match Generators::<Bn256>::read(&mut &infinity[..]) {
+            Err(DeserializationError::PointAtInfinity) => {}
+            _ => panic!("the point at infinity must be rejected"),
+        }
+        let mut outside = vec![];
+        Generators::<Bn256> {
+            g1: generators.g1,
+            g2: crate::malicious::point_not_in_subgroup(),
+        }
+        .write(&mut outside)
+        .unwrap();
+        match Generators::<Bn256>::read(&mut &outside[..]) {
+            Err(DeserializationError::DecodingError(GroupDecodingError::NotInSubgroup)) => {}
+            _ => panic!("a point outside of the subgroup must be rejected"),
+        }
+
+        let mut args = vec!["new".to_string(), "--generators".to_string()];
+        assert!(take_generators::<Bn256>(&mut args).is_err());
+        assert!(take_generators::<Bn256>(&mut vec!["new".to_string()])
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_validated_parameters() {
+        for &(size, batch_size) in &[(1, 1), (2, 1000), (28, 1 << 21)] {
+            assert!(CeremonyParams::<Bn256>::validated(size, batch_size).is_ok());
+        }
+        for &(size, batch_size) in &[(0, 4), (29, 4), (3, 0)] {
+            let error = CeremonyParams::<Bn256>::validated(size, batch_size)
+                .err()
+                .unwrap();
+            assert_eq!(error.code, errors::INVALID_ARGUMENT);
+        }
+    }
+
+    #[test]
+    fn test_detect_compression() {
+        use crate::batched_accumulator::BatchedAccumulator;
+        use memmap::MmapMut;
+        use std::io::Cursor;
+
+        let parameters = CeremonyParams::<Bn256>::new(2, 4);
+        let compressions = [
+            UseCompression::No,
+            UseCompression::Yes,
+            UseCompression::Mixed,
+        ];
+        for &compression in compressions.iter() {
+            let sizes = expected_sizes(&parameters, compression);
+            let mut map = MmapMut::map_anon(sizes.challenge).unwrap();
+            BatchedAccumulator::generate_initial(&mut map, compression, &parameters).unwrap();
+            assert_eq!(
+                detect_compression(Cursor::new(&map[..]), &parameters).unwrap(),
+                Some(compression)
+            );
+            assert_eq!(
+                &map[64..64 + compression.header().len()],
+                compression.header()
+            );
+            assert!(check_compression(Cursor::new(&map[..]), compression, &parameters).is_ok());
+
+            for &other in compressions.iter().filter(|&&other| other != compression) {
+                match check_compression(Cursor::new(&map[..]), other, &parameters) {
+                    Err(DeserializationError::CompressionMismatch { expected, detected }) => {
+                        assert_eq!((expected, detected), (other, compression))
+                    }
+                    _ => panic!("the mismatch should be detected"),
+                }
+            }
+        }
+
+        // Without its policy header, a mixed file starts like an uncompressed one, but its
+        // powers in G2 are compressed
+        let sizes = expected_sizes(&parameters, UseCompression::Mixed);
+        let mut map = MmapMut::map_anon(sizes.challenge).unwrap();
+        BatchedAccumulator::generate_initial(&mut map, UseCompression::Mixed, &parameters).unwrap();
+        let mut headerless = map[..64].to_vec();
+        headerless.extend_from_slice(&map[64 + MIXED_HEADER.len()..]);
+        assert_eq!(
+            detect_compression(Cursor::new(&headerless[..]), &parameters).unwrap(),
+            None
+        );
+
+        // Files that are too short or corrupted are left to the other checks
+        assert_eq!(
+            detect_compression(Cursor::new(&[0u8; 10][..]), &parameters).unwrap(),
+            None
+        );
+        let truncated = Cursor::new(&map[..200]);
+        assert_eq!(detect_compression(truncated, &parameters).unwrap(), None);
+        let corrupted = Cursor::new(&[1u8; 200][..]);
+        assert!(check_compression(corrupted, UseCompression::No, &parameters).is_ok());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_expected_sizes(
+            parameters in arbitrary::ceremony_params(),
+            compression in arbitrary::compression(),
+        ) {
+            let sizes = expected_sizes(&parameters, compression);
+            prop_assert_eq!(sizes.response, sizes.challenge + parameters.public_key_size);
+            prop_assert_eq!(sizes.new_challenge, sizes.challenge);
+            // Compressing the G2 elements, then the G1 elements, only ever shrinks the files
+            let (no, mixed, yes) = (
+                expected_sizes(&parameters, UseCompression::No),
+                expected_sizes(&parameters, UseCompression::Mixed),
+                expected_sizes(&parameters, UseCompression::Yes),
+            );
+            prop_assert!(yes.challenge < mixed.challenge && mixed.challenge < no.challenge);
+            prop_assert_eq!(no.challenge, parameters.accumulator_size);
+            prop_assert_eq!(yes.response, parameters.contribution_size);
+        }
+    }
+
+    #[test]
+    fn test_expected_sizes() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let sizes = expected_sizes(&parameters, UseCompression::No);
+        assert_eq!(
+            sizes,
+            ArtifactSizes {
+                challenge: 3200,
+                response: 3968,
+                new_challenge: 3200,
+                public_key: 768,
+                hash: 64,
+            }
+        );
+        let sizes = expected_sizes(&parameters, UseCompression::Yes);
+        assert_eq!((sizes.challenge, sizes.response), (1632, 2400));
+        assert_eq!(sizes.response, parameters.contribution_size);
+        // Only the G2 elements are compressed, and the policy follows the hash
+        let sizes = expected_sizes(&parameters, UseCompression::Mixed);
+        assert_eq!((sizes.challenge, sizes.response), (2632, 3400));
+    }
+}
@@ -0,0 +1,169 @@
+/// Templates for the names of the files of a ceremony, e.g. `{round:04}/response.bin`.
+/// `{round}` is replaced with the round of the file, optionally zero-padded to the width after
+/// the colon; `{{` and `}}` stand for literal braces.
+use std::fmt;
+use std::path::PathBuf;
+
+use super::errors::{self, Error, ErrorCategory};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    Round(usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathTemplate {
+    template: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct TemplateError(String);
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid path template: {}", self.0)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl PathTemplate {
+    /// Parses a template. Every template must contain `{round}`, so that the files of
+    /// different rounds never share a name.
+    pub fn parse(template: &str) -> Result<Self, TemplateError> {
+        let mut segments = vec![];
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+                        placeholder.push(c);
+                    }
+                    if !closed {
+                        return Err(TemplateError(format!("unmatched {{ in {}", template)));
+                    }
+
+                    let mut parts = placeholder.splitn(2, ':');
+                    if parts.next() != Some("round") {
+                        return Err(TemplateError(format!(
+                            "unknown placeholder {{{}}}",
+                            placeholder
+                        )));
+                    }
+                    let width = match parts.next() {
+                        Some(width) => width.parse().map_err(|_| {
+                            TemplateError(format!("bad width in {{{}}}", placeholder))
+                        })?,
+                        None => 0,
+                    };
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Round(width));
+                }
+                '}' => return Err(TemplateError(format!("unmatched }} in {}", template))),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        if !segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Round(_)))
+        {
+            return Err(TemplateError(format!(
+                "{} does not use {{round}}",
+                template
+            )));
+        }
+
+        Ok(PathTemplate {
+            template: template.to_string(),
+            segments,
+        })
+    }
+
+    pub fn render(&self, round: u32) -> PathBuf {
+        let mut path = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(literal) => path.push_str(literal),
+                Segment::Round(width) => {
+                    path.push_str(&format!("{:0width$}", round, width = width))
+                }
+            }
+        }
+
+        PathBuf::from(path)
+    }
+}
+
+impl fmt::Display for PathTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.template)
+    }
+}
+
+/// Removes `<flag> <template>` from the arguments and returns the parsed template
+pub fn take_template(args: &mut Vec<String>, flag: &str) -> Result<Option<PathTemplate>, Error> {
+    let position = match args.iter().position(|arg| arg == flag) {
+        Some(position) => position,
+        None => return Ok(None),
+    };
+    if position + 1 >= args.len() {
+        println!("{} requires a path template", flag);
+        std::process::exit(exitcode::USAGE);
+    }
+    let template = PathTemplate::parse(&args[position + 1]).map_err(|e| {
+        Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            e.to_string(),
+        )
+        .with_hint("templates look like `{round:04}/response.bin`")
+    })?;
+    args.drain(position..position + 2);
+
+    Ok(Some(template))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let template = PathTemplate::parse("{round}/response.bin").unwrap();
+        assert_eq!(template.render(3), PathBuf::from("3/response.bin"));
+
+        let template = PathTemplate::parse("challenge_{round:04}{{x}}").unwrap();
+        assert_eq!(template.render(7), PathBuf::from("challenge_0007{x}"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(PathTemplate::parse("response").is_err());
+        assert!(PathTemplate::parse("{participant}").is_err());
+        assert!(PathTemplate::parse("{round:x}").is_err());
+        assert!(PathTemplate::parse("{round").is_err());
+        assert!(PathTemplate::parse("{round}}").is_err());
+    }
+}
@@ -0,0 +1,163 @@
+/// Lightweight counters for the expensive parts of the ceremony. They are only collected
+/// when the crate is built with the `profiling` feature, otherwise every call compiles
+/// down to nothing.
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "profiling")]
+use std::time::Instant;
+
+/// The instrumented operations.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Operation {
+    BatchExp,
+    Serialization,
+    SubgroupCheck,
+    PairingCheck,
+}
+
+const OPERATIONS: [Operation; 4] = [
+    Operation::BatchExp,
+    Operation::Serialization,
+    Operation::SubgroupCheck,
+    Operation::PairingCheck,
+];
+
+impl Operation {
+    fn name(self) -> &'static str {
+        match self {
+            Operation::BatchExp => "batch_exp",
+            Operation::Serialization => "serialization",
+            Operation::SubgroupCheck => "subgroup_check",
+            Operation::PairingCheck => "pairing_check",
+        }
+    }
+
+    fn counter(self) -> &'static Counter {
+        &COUNTERS[self as usize]
+    }
+}
+
+struct Counter {
+    invocations: AtomicU64,
+    elements: AtomicU64,
+    nanoseconds: AtomicU64,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_COUNTER: Counter = Counter {
+    invocations: AtomicU64::new(0),
+    elements: AtomicU64::new(0),
+    nanoseconds: AtomicU64::new(0),
+};
+
+static COUNTERS: [Counter; 4] = [EMPTY_COUNTER; 4];
+
+/// Records an invocation of `operation` over `elements` elements. The wall time is
+/// accounted for when the returned guard is dropped.
+#[must_use]
+pub struct Span {
+    #[cfg(feature = "profiling")]
+    operation: Operation,
+    #[cfg(feature = "profiling")]
+    started: Instant,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        #[cfg(feature = "profiling")]
+        {
+            let elapsed = self.started.elapsed().as_nanos() as u64;
+            self.operation
+                .counter()
+                .nanoseconds
+                .fetch_add(elapsed, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "profiling"), allow(unused_variables))]
+pub fn span(operation: Operation, elements: usize) -> Span {
+    #[cfg(feature = "profiling")]
+    {
+        let counter = operation.counter();
+        counter.invocations.fetch_add(1, Ordering::Relaxed);
+        counter
+            .elements
+            .fetch_add(elements as u64, Ordering::Relaxed);
+
+        Span {
+            operation,
+            started: Instant::now(),
+        }
+    }
+    #[cfg(not(feature = "profiling"))]
+    {
+        Span {}
+    }
+}
+
+/// Renders the counters collected so far as a JSON object, keyed by operation.
+pub fn report() -> String {
+    let mut json = String::from("{\n");
+    for (i, operation) in OPERATIONS.iter().enumerate() {
+        let counter = operation.counter();
+        let nanoseconds = counter.nanoseconds.load(Ordering::Relaxed);
+        write!(
+            json,
+            "  \"{}\": {{ \"invocations\": {}, \"elements\": {}, \"wall_time_ms\": {:.3} }}",
+            operation.name(),
+            counter.invocations.load(Ordering::Relaxed),
+            counter.elements.load(Ordering::Relaxed),
+            nanoseconds as f64 / 1_000_000.0
+        )
+        .expect("must write to a string");
+        json.push_str(if i + 1 == OPERATIONS.len() {
+            "\n"
+        } else {
+            ",\n"
+        });
+    }
+    json.push('}');
+
+    json
+}
+
+/// Writes the report of this run to `<command>.profile.json`. Does nothing unless the
+/// `profiling` feature is enabled.
+pub fn dump_report(command: &str) -> io::Result<()> {
+    if !cfg!(feature = "profiling") {
+        return Ok(());
+    }
+
+    let filename = format!("{}.profile.json", command);
+    let mut file = File::create(&filename)?;
+    file.write_all(report().as_bytes())?;
+    println!("Profiling report written to {}", filename);
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "profiling"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_span_is_counted() {
+        let before = Operation::PairingCheck
+            .counter()
+            .invocations
+            .load(Ordering::Relaxed);
+        {
+            let _span = span(Operation::PairingCheck, 2);
+        }
+        let after = Operation::PairingCheck
+            .counter()
+            .invocations
+            .load(Ordering::Relaxed);
+
+        assert!(after > before);
+        assert!(report().contains("\"pairing_check\""));
+    }
+}
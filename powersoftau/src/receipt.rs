@@ -0,0 +1,300 @@
+/// A receipt is a constant-size summary of a contribution: the hash of the challenge it was
+/// computed from, the hash of the resulting response and the contributor's public key.
+/// Checking it only needs the proofs of knowledge of the public key, so it can be done
+/// cheaply (e.g. by a dashboard) long before the full verification of the response finishes.
+/// It does NOT prove that the response itself is correct.
+///
+/// Keys bound to a `KeyDomain` or updating only some trapdoors prove knowledge with another
+/// digest than the challenge hash, so their receipts also carry the domain and the trapdoors,
+/// after the public key. Receipts of legacy keys updating every trapdoor end with the key.
+use bellman_ce::pairing::Engine;
+use memmap::Mmap;
+
+use std::io::{self, Read, Write};
+
+use super::keypair::{KeyDomain, PublicKey};
+use super::parameters::{CeremonyParams, DeserializationError, TrapdoorMask};
+use super::utils::{compute_g2_s, same_ratio};
+
+/// The longest name of a curve or proving system in a receipt.
+const MAX_NAME_LENGTH: u64 = 256;
+
+pub struct Receipt<E: Engine> {
+    /// Hash of the challenge the contribution was computed from
+    pub challenge_hash: [u8; 64],
+    /// Hash of the response, i.e. the new head of the hash chain
+    pub response_hash: [u8; 64],
+    pub public_key: PublicKey<E>,
+    /// The domain the key is bound to, none for legacy keys
+    pub domain: Option<KeyDomain>,
+    /// The trapdoors the contribution updates
+    pub trapdoors: TrapdoorMask,
+}
+
+impl<E: Engine> Receipt<E> {
+    /// Size of a serialized receipt of a legacy key updating every trapdoor: two hashes
+    /// followed by the uncompressed public key.
+    pub fn size(parameters: &CeremonyParams<E>) -> usize {
+        parameters.hash_size * 2 + parameters.public_key_size
+    }
+
+    /// Builds the receipt of a response with a legacy key updating every trapdoor. The
+    /// challenge hash is taken from the head of the response.
+    pub fn new(response_map: &Mmap, response_hash: &[u8], public_key: PublicKey<E>) -> Self {
+        let mut challenge_hash = [0; 64];
+        challenge_hash.copy_from_slice(&response_map[0..64]);
+        let mut response = [0; 64];
+        response.copy_from_slice(response_hash);
+
+        Receipt {
+            challenge_hash,
+            response_hash: response,
+            public_key,
+            domain: None,
+            trapdoors: TrapdoorMask::default(),
+        }
+    }
+
+    /// The key of the receipt is bound to `domain`, see `Round::with_domain`.
+    pub fn with_domain(mut self, domain: KeyDomain) -> Self {
+        self.domain = Some(domain);
+        self
+    }
+
+    /// The contribution only updates `trapdoors`.
+    pub fn with_trapdoors(mut self, trapdoors: TrapdoorMask) -> Self {
+        self.trapdoors = trapdoors;
+        self
+    }
+
+    /// The digest the key proves knowledge with, the same as `Round::key_digest` of the
+    /// contribution.
+    pub fn key_digest(&self) -> Vec<u8> {
+        match self.domain {
+            Some(ref domain) => self.trapdoors.digest(&domain.digest(&self.challenge_hash)),
+            None => self.trapdoors.digest(&self.challenge_hash),
+        }
+    }
+
+    /// Checks the proofs of knowledge of tau, alpha and beta of the public key
+    /// against the key digest of the challenge hash.
+    pub fn verify(&self) -> bool {
+        let key = &self.public_key;
+        let digest = &self.key_digest()[..];
+
+        let tau_g2_s = compute_g2_s::<E>(digest, &key.tau_g1.0, &key.tau_g1.1, 0);
+        let alpha_g2_s = compute_g2_s::<E>(digest, &key.alpha_g1.0, &key.alpha_g1.1, 1);
+        let beta_g2_s = compute_g2_s::<E>(digest, &key.beta_g1.0, &key.beta_g1.1, 2);
+
+        same_ratio(key.tau_g1, (tau_g2_s, key.tau_g2))
+            && same_ratio(key.alpha_g1, (alpha_g2_s, key.alpha_g2))
+            && same_ratio(key.beta_g1, (beta_g2_s, key.beta_g2))
+    }
+
+    pub fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.challenge_hash)?;
+        writer.write_all(&self.response_hash)?;
+        self.public_key.serialize(writer)?;
+        if self.domain.is_none() && self.trapdoors.is_full() {
+            return Ok(());
+        }
+
+        // The trapdoors as bits, then the domain if there is one
+        let trapdoors = [
+            self.trapdoors.tau as u8
+                | (self.trapdoors.alpha as u8) << 1
+                | (self.trapdoors.beta as u8) << 2,
+            self.domain.is_some() as u8,
+        ];
+        writer.write_all(&trapdoors)?;
+        if let Some(ref domain) = self.domain {
+            for name in &[&domain.curve, &domain.proving_system] {
+                writer.write_all(&(name.len() as u64).to_be_bytes())?;
+                writer.write_all(name.as_bytes())?;
+            }
+            writer.write_all(&(domain.power as u64).to_be_bytes())?;
+            writer.write_all(&domain.round.to_be_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn deserialize<R: Read>(reader: &mut R) -> Result<Self, DeserializationError> {
+        let mut challenge_hash = [0; 64];
+        reader.read_exact(&mut challenge_hash)?;
+        let mut response_hash = [0; 64];
+        reader.read_exact(&mut response_hash)?;
+        let public_key = PublicKey::deserialize(reader)?;
+
+        let mut trapdoors = [0; 1];
+        let legacy = loop {
+            match reader.read(&mut trapdoors) {
+                Ok(read) => break read == 0,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        };
+        if legacy {
+            return Ok(Receipt {
+                challenge_hash,
+                response_hash,
+                public_key,
+                domain: None,
+                trapdoors: TrapdoorMask::default(),
+            });
+        }
+        let bits = trapdoors[0];
+        if bits == 0 || bits > 0b111 {
+            return Err(invalid_data("the receipt updates no valid trapdoors"));
+        }
+        let trapdoors = TrapdoorMask {
+            tau: bits & 1 != 0,
+            alpha: bits & 0b10 != 0,
+            beta: bits & 0b100 != 0,
+        };
+        let mut has_domain = [0; 1];
+        reader.read_exact(&mut has_domain)?;
+        let domain = match has_domain[0] {
+            0 => None,
+            1 => {
+                let curve = read_name(reader)?;
+                let proving_system = read_name(reader)?;
+                Some(KeyDomain {
+                    curve,
+                    proving_system,
+                    power: read_u64(reader)? as usize,
+                    round: read_u64(reader)?,
+                })
+            }
+            _ => return Err(invalid_data("invalid domain marker in the receipt")),
+        };
+
+        Ok(Receipt {
+            challenge_hash,
+            response_hash,
+            public_key,
+            domain,
+            trapdoors,
+        })
+    }
+}
+
+fn invalid_data(message: &str) -> DeserializationError {
+    io::Error::new(io::ErrorKind::InvalidData, message).into()
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+/// A name of the domain, prefixed with its length.
+fn read_name<R: Read>(reader: &mut R) -> Result<String, DeserializationError> {
+    let length = read_u64(reader)?;
+    if length > MAX_NAME_LENGTH {
+        return Err(invalid_data(
+            "a name of the domain of the receipt is too long",
+        ));
+    }
+    let mut name = vec![0; length as usize];
+    reader.read_exact(&mut name)?;
+    String::from_utf8(name)
+        .map_err(|_| invalid_data("a name of the domain of the receipt is not UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::{keypair, keypair_with_mask};
+    use bellman_ce::pairing::bn256::Bn256;
+    use rand::{thread_rng, Rng};
+
+    fn receipt(digest: &[u8]) -> Receipt<Bn256> {
+        let rng = &mut thread_rng();
+        let (public_key, _) = keypair::<_, Bn256>(rng, digest);
+        let mut challenge_hash = [0; 64];
+        challenge_hash.copy_from_slice(digest);
+
+        Receipt {
+            challenge_hash,
+            response_hash: [1; 64],
+            public_key,
+            domain: None,
+            trapdoors: TrapdoorMask::default(),
+        }
+    }
+
+    #[test]
+    fn test_receipt_serialization() {
+        let rng = &mut thread_rng();
+        let digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+        let receipt = receipt(&digest);
+        let parameters = CeremonyParams::<Bn256>::new(2, 4);
+
+        let mut v = vec![];
+        receipt.serialize(&mut v).unwrap();
+        assert_eq!(v.len(), Receipt::size(&parameters));
+
+        let deserialized = Receipt::<Bn256>::deserialize(&mut &v[..]).unwrap();
+        assert_eq!(
+            &deserialized.challenge_hash[..],
+            &receipt.challenge_hash[..]
+        );
+        assert_eq!(&deserialized.response_hash[..], &receipt.response_hash[..]);
+        assert!(deserialized.public_key == receipt.public_key);
+        assert!(deserialized.verify());
+    }
+
+    #[test]
+    fn test_receipt_rejects_other_challenge() {
+        let rng = &mut thread_rng();
+        let digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+        let mut receipt = receipt(&digest);
+        assert!(receipt.verify());
+
+        receipt.challenge_hash[0] ^= 1;
+        assert!(!receipt.verify());
+    }
+
+    #[test]
+    fn test_receipt_with_domain() {
+        let rng = &mut thread_rng();
+        let challenge_hash = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+        let domain = KeyDomain::bn256_groth16(4, 3);
+        let beta_only: TrapdoorMask = "beta".parse().unwrap();
+        let digest = beta_only.digest(&domain.digest(&challenge_hash));
+        let (public_key, _) = keypair_with_mask::<_, Bn256>(rng, &digest, beta_only);
+        let mut receipt = receipt(&challenge_hash);
+        receipt.public_key = public_key;
+        // The key doesn't prove knowledge against the challenge hash alone
+        assert!(!receipt.verify());
+
+        let receipt = receipt
+            .with_domain(domain.clone())
+            .with_trapdoors(beta_only);
+        assert_eq!(receipt.key_digest(), digest);
+        assert!(receipt.verify());
+
+        let mut v = vec![];
+        receipt.serialize(&mut v).unwrap();
+        let parameters = CeremonyParams::<Bn256>::new(4, 4);
+        assert!(v.len() > Receipt::size(&parameters));
+        let deserialized = Receipt::<Bn256>::deserialize(&mut &v[..]).unwrap();
+        assert_eq!(deserialized.domain, Some(domain));
+        assert_eq!(deserialized.trapdoors, beta_only);
+        assert!(deserialized.verify());
+
+        // Another round doesn't verify
+        let other = Receipt::<Bn256>::deserialize(&mut &v[..])
+            .unwrap()
+            .with_domain(KeyDomain::bn256_groth16(4, 4));
+        assert!(!other.verify());
+
+        // A truncated domain or an invalid trapdoor mask is an error
+        assert!(Receipt::<Bn256>::deserialize(&mut &v[..v.len() - 1]).is_err());
+        let mut invalid = v.clone();
+        invalid[Receipt::size(&parameters)] = 0;
+        assert!(Receipt::<Bn256>::deserialize(&mut &invalid[..]).is_err());
+    }
+}
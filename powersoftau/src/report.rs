@@ -0,0 +1,503 @@
+/// Final report of a ceremony run with `watch_responses`, built from the files the ceremony
+/// left behind rather than assembled by hand: the hash chain of challenges and responses, the
+/// journal of the worker and the attestations written by `--attestation`. Every round is one
+/// contribution, and the report gives for each its hashes, whether the worker verified it,
+/// how many responses to the round were rejected, when the response was uploaded and
+/// verified, and the attestations of the contribution and of its verification. Attestations
+/// are matched to rounds by the hashes in them, so they can be named in any way.
+///
+/// Upload and verification times are the modification times of the response and of the
+/// result record, which only mean something on the machine of the worker.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use super::errors::Error;
+use super::inclusion::parse_hex_hash;
+use super::utils::{json_string, unix_time};
+use super::watch::{ChainAudit, Queue};
+
+/// An attestation written by `--attestation`, see `hooks::Attestation`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttestationRecord {
+    pub path: PathBuf,
+    pub command: String,
+    pub input_hash: [u8; 64],
+    pub output_hash: [u8; 64],
+    /// Seconds since the Unix epoch
+    pub completed_at: Option<u64>,
+    pub built_by: Option<String>,
+}
+
+impl AttestationRecord {
+    /// Parses the statement of `hooks::Attestation`, or returns `None` if `text` isn't one,
+    /// such as the signature of an attestation next to it.
+    pub fn parse(path: &Path, text: &str) -> Option<Self> {
+        let (mut command, mut input_hash, mut output_hash, mut completed_at, mut built_by) =
+            (None, None, None, None, None);
+        for line in text.lines() {
+            let (key, value) = match line.find(':') {
+                Some(position) => (line[..position].trim(), line[position + 1..].trim()),
+                None => continue,
+            };
+            match key {
+                "command" => command = Some(value.to_string()),
+                "input blake2b" => input_hash = Some(parse_hex_hash(value)?),
+                "output blake2b" => output_hash = Some(parse_hex_hash(value)?),
+                "completed at" => {
+                    completed_at = value.split_whitespace().next()?.parse().ok();
+                }
+                "built by" => built_by = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        Some(AttestationRecord {
+            path: path.to_path_buf(),
+            command: command?,
+            input_hash: input_hash?,
+            output_hash: output_hash?,
+            completed_at,
+            built_by,
+        })
+    }
+
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"path\":{},\"command\":{},\"completed_at\":{},\"built_by\":{}}}",
+            json_string(&self.path.to_string_lossy()),
+            json_string(&self.command),
+            optional(self.completed_at),
+            self.built_by
+                .as_ref()
+                .map_or("null".to_string(), |built_by| json_string(built_by))
+        )
+    }
+}
+
+/// Reads the attestations in `directory`, skipping the files that aren't attestations.
+pub fn read_attestations(directory: &Path) -> Result<Vec<AttestationRecord>, Error> {
+    let context = || format!("unable to read the attestations in {}", directory.display());
+    let mut paths = vec![];
+    for entry in fs::read_dir(directory).map_err(|e| Error::from(e).context(context()))? {
+        let path = entry.map_err(|e| Error::from(e).context(context()))?.path();
+        if path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut attestations = vec![];
+    for path in paths {
+        // Signatures and other binary files aren't attestations
+        if let Ok(text) = fs::read_to_string(&path) {
+            attestations.extend(AttestationRecord::parse(&path, &text));
+        }
+    }
+
+    Ok(attestations)
+}
+
+/// A round of the ceremony, i.e. one contribution.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoundReport {
+    pub round: u32,
+    pub challenge_hash: [u8; 64],
+    pub response_hash: Option<[u8; 64]>,
+    /// Hash of the challenge computed from the response
+    pub next_challenge_hash: Option<[u8; 64]>,
+    /// Whether the journal of the worker has the response as accepted
+    pub verified: bool,
+    /// Responses to the round that the worker rejected
+    pub rejected: u32,
+    /// Seconds since the Unix epoch at which the response was written
+    pub uploaded: Option<u64>,
+    /// Seconds since the Unix epoch at which the result of the round was recorded
+    pub recorded: Option<u64>,
+    /// The attestation of the contribution, from the challenge to the response
+    pub contribution: Option<AttestationRecord>,
+    /// The attestation of the verification, from the response to the next challenge
+    pub verification: Option<AttestationRecord>,
+}
+
+impl RoundReport {
+    /// Seconds between the upload of the response and the result of its verification.
+    pub fn verification_seconds(&self) -> Option<u64> {
+        match (self.uploaded, self.recorded) {
+            (Some(uploaded), Some(recorded)) => Some(recorded.saturating_sub(uploaded)),
+            _ => None,
+        }
+    }
+
+    fn to_json(&self) -> String {
+        let attestation = |attestation: &Option<AttestationRecord>| {
+            attestation
+                .as_ref()
+                .map_or("null".to_string(), AttestationRecord::to_json)
+        };
+        format!(
+            "{{\"round\":{},\"challenge_hash\":\"{}\",\"response_hash\":{},\"next_challenge_hash\":{},\"verified\":{},\"rejected\":{},\"uploaded\":{},\"recorded\":{},\"verification_seconds\":{},\"contribution_attestation\":{},\"verification_attestation\":{}}}",
+            self.round,
+            hex::encode(&self.challenge_hash[..]),
+            optional_hash(&self.response_hash),
+            optional_hash(&self.next_challenge_hash),
+            self.verified,
+            self.rejected,
+            optional(self.uploaded),
+            optional(self.recorded),
+            optional(self.verification_seconds()),
+            attestation(&self.contribution),
+            attestation(&self.verification)
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CeremonyReport {
+    pub rounds: Vec<RoundReport>,
+    pub chain: ChainAudit,
+    /// Seconds since the Unix epoch
+    pub generated: u64,
+}
+
+impl CeremonyReport {
+    /// Audits the hash chain of `queue` and gathers what is known about every round of it.
+    /// Fails if the hash chain is broken.
+    pub fn build(queue: &Queue, attestations: &[AttestationRecord]) -> Result<Self, Error> {
+        let mut rounds: Vec<RoundReport> = vec![];
+        let chain = queue.audit_hash_chain(|round, challenge_hash, response_hash| {
+            if let Some(previous) = rounds.last_mut() {
+                previous.next_challenge_hash = Some(*challenge_hash);
+            }
+            rounds.push(RoundReport {
+                round,
+                challenge_hash: *challenge_hash,
+                response_hash: response_hash.copied(),
+                next_challenge_hash: None,
+                verified: false,
+                rejected: 0,
+                uploaded: None,
+                recorded: None,
+                contribution: None,
+                verification: None,
+            });
+        })?;
+
+        let journal = queue
+            .read_journal()
+            .map_err(|e| Error::from(e).context("unable to read the journal"))?;
+        for round in rounds.iter_mut() {
+            let number = round.round;
+            for entry in journal.iter().filter(|entry| entry.round == number) {
+                if !entry.accepted {
+                    round.rejected += 1;
+                } else if Some(entry.response_hash) == round.response_hash {
+                    round.verified = true;
+                }
+            }
+
+            let response_hash = match round.response_hash {
+                Some(response_hash) => response_hash,
+                None => continue,
+            };
+            round.uploaded = modified(&queue.response_path(round.round));
+            if round.verified {
+                round.recorded = modified(&queue.result_path(round.round));
+            }
+            round.contribution = attestations
+                .iter()
+                .find(|attestation| {
+                    attestation.input_hash == round.challenge_hash
+                        && attestation.output_hash == response_hash
+                })
+                .cloned();
+            round.verification = attestations
+                .iter()
+                .find(|attestation| {
+                    attestation.input_hash == response_hash
+                        && Some(attestation.output_hash) == round.next_challenge_hash
+                })
+                .cloned();
+        }
+
+        Ok(CeremonyReport {
+            rounds,
+            chain,
+            generated: unix_time(),
+        })
+    }
+
+    /// Rounds with a response in the hash chain.
+    pub fn contributions(&self) -> usize {
+        self.contributed().count()
+    }
+
+    /// Contributions the worker accepted.
+    pub fn verified(&self) -> usize {
+        self.contributed().filter(|round| round.verified).count()
+    }
+
+    pub fn rejected(&self) -> u32 {
+        self.rounds.iter().map(|round| round.rejected).sum()
+    }
+
+    /// Contributions with an attestation of the contribution, and of the verification.
+    pub fn attested(&self) -> (usize, usize) {
+        (
+            self.contributed()
+                .filter(|round| round.contribution.is_some())
+                .count(),
+            self.contributed()
+                .filter(|round| round.verification.is_some())
+                .count(),
+        )
+    }
+
+    /// Hash of the last challenge of the chain, the final accumulator if no response is
+    /// pending.
+    pub fn final_challenge_hash(&self) -> [u8; 64] {
+        self.rounds
+            .last()
+            .expect("the chain has at least one challenge")
+            .challenge_hash
+    }
+
+    /// Hash of the last response of the chain.
+    pub fn final_response_hash(&self) -> Option<[u8; 64]> {
+        self.contributed()
+            .last()
+            .and_then(|round| round.response_hash)
+    }
+
+    fn contributed(&self) -> impl Iterator<Item = &RoundReport> {
+        self.rounds
+            .iter()
+            .filter(|round| round.response_hash.is_some())
+    }
+
+    pub fn to_json(&self) -> String {
+        let (attested_contributions, attested_verifications) = self.attested();
+        format!(
+            "{{\"contributions\":{},\"verified\":{},\"rejected\":{},\"attested_contributions\":{},\"attested_verifications\":{},\"final_challenge_hash\":\"{}\",\"final_response_hash\":{},\"response_pending\":{},\"rounds\":[{}],\"generated\":{}}}\n",
+            self.contributions(),
+            self.verified(),
+            self.rejected(),
+            attested_contributions,
+            attested_verifications,
+            hex::encode(&self.final_challenge_hash()[..]),
+            optional_hash(&self.final_response_hash()),
+            self.chain.response_pending,
+            self.rounds
+                .iter()
+                .map(RoundReport::to_json)
+                .collect::<Vec<_>>()
+                .join(","),
+            self.generated
+        )
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let (attested_contributions, attested_verifications) = self.attested();
+        let yes_no = |value: bool| if value { "yes" } else { "no" };
+        let time = |time: Option<u64>| time.map_or("-".to_string(), |time| time.to_string());
+
+        let mut markdown = String::from("# Ceremony report\n\n");
+        markdown.push_str(&format!(
+            "Generated at {} (seconds since the Unix epoch) from the hash chain, the journal of the worker and the attestations.\n\n",
+            self.generated
+        ));
+
+        markdown.push_str("## Summary\n\n");
+        markdown.push_str(&format!("- Contributions: {}\n", self.contributions()));
+        markdown.push_str(&format!(
+            "- Verified by the worker: {} of {}\n",
+            self.verified(),
+            self.contributions()
+        ));
+        markdown.push_str(&format!("- Rejected responses: {}\n", self.rejected()));
+        markdown.push_str(&format!(
+            "- Attested contributions: {} of {}\n",
+            attested_contributions,
+            self.contributions()
+        ));
+        markdown.push_str(&format!(
+            "- Attested verifications: {} of {}\n\n",
+            attested_verifications,
+            self.contributions()
+        ));
+
+        markdown.push_str("## Contributions\n\n");
+        markdown.push_str("| Round | Response | Verified | Rejected | Uploaded | Verification (s) | Contribution attestation | Verification attestation |\n");
+        markdown.push_str("|---|---|---|---|---|---|---|---|\n");
+        for round in self.contributed() {
+            let attestation = |attestation: &Option<AttestationRecord>| {
+                attestation.as_ref().map_or("-".to_string(), |attestation| {
+                    format!("`{}`", attestation.path.display())
+                })
+            };
+            markdown.push_str(&format!(
+                "| {} | `{}` | {} | {} | {} | {} | {} | {} |\n",
+                round.round,
+                hex::encode(&round.response_hash.expect("round has a response")[..8]),
+                yes_no(round.verified),
+                round.rejected,
+                time(round.uploaded),
+                time(round.verification_seconds()),
+                attestation(&round.contribution),
+                attestation(&round.verification)
+            ));
+        }
+
+        markdown.push_str("\n## Final artifacts\n\n");
+        markdown.push_str(&format!(
+            "- Challenge of round {}: `{}`\n",
+            self.chain.round,
+            hex::encode(&self.final_challenge_hash()[..])
+        ));
+        if let Some(response_hash) = self.final_response_hash() {
+            markdown.push_str(&format!(
+                "- Last response: `{}`\n",
+                hex::encode(&response_hash[..])
+            ));
+        }
+        if self.chain.response_pending {
+            markdown.push_str(&format!(
+                "\nThe response to round {} has no next challenge yet.\n",
+                self.chain.round
+            ));
+        }
+
+        markdown
+    }
+}
+
+fn optional(value: Option<u64>) -> String {
+    value.map_or("null".to_string(), |value| value.to_string())
+}
+
+fn optional_hash(hash: &Option<[u8; 64]>) -> String {
+    hash.map_or("null".to_string(), |hash| {
+        format!("\"{}\"", hex::encode(&hash[..]))
+    })
+}
+
+/// Modification time of `path` in seconds since the Unix epoch, if it has one.
+fn modified(path: &Path) -> Option<u64> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|modified| modified.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hooks::{Attestation, HookContext};
+
+    #[test]
+    fn test_parse_attestation() {
+        let mut context = HookContext::new("compute_constrained", "response", &[2; 64], &[1; 64]);
+        context.details.push("sampled 10 chunks".to_string());
+        let path = Path::new("attestation.txt");
+        let attestation =
+            AttestationRecord::parse(path, &Attestation::statement(&context)).unwrap();
+        assert_eq!(attestation.command, "compute_constrained");
+        assert_eq!(attestation.input_hash, [1; 64]);
+        assert_eq!(attestation.output_hash, [2; 64]);
+        assert!(attestation.completed_at.is_some());
+        assert!(attestation.built_by.is_some());
+
+        assert!(AttestationRecord::parse(path, "-----BEGIN PGP SIGNATURE-----").is_none());
+        assert!(AttestationRecord::parse(path, "command: compute_constrained\n").is_none());
+    }
+
+    #[test]
+    fn test_report() {
+        let directory =
+            std::env::temp_dir().join(format!("powersoftau_report_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("in")).unwrap();
+        fs::create_dir_all(directory.join("out")).unwrap();
+        let queue = Queue::new(directory.join("in"), directory.join("out"));
+
+        // The hash chain doesn't depend on the contents, only on the hashes at the start
+        let hash = |path: &Path| {
+            let mut hash = [0; 64];
+            hash.copy_from_slice(&crate::utils::calculate_hash(&fs::read(path).unwrap()));
+            hash
+        };
+        fs::write(queue.challenge_path(0), [0; 100]).unwrap();
+        let challenge_hash = hash(&queue.challenge_path(0));
+        fs::write(
+            queue.response_path(0),
+            [&challenge_hash[..], &[1; 36]].concat(),
+        )
+        .unwrap();
+        let response_hash = hash(&queue.response_path(0));
+        fs::write(
+            queue.challenge_path(1),
+            [&response_hash[..], &[2; 36]].concat(),
+        )
+        .unwrap();
+        let next_challenge_hash = hash(&queue.challenge_path(1));
+        fs::write(
+            directory.join("out").join("journal"),
+            format!(
+                "0 rejected {}\n0 accepted {}\n",
+                hex::encode(&[7; 64][..]),
+                hex::encode(&response_hash[..])
+            ),
+        )
+        .unwrap();
+        fs::write(queue.result_path(0), "round 0\n").unwrap();
+
+        let contribution = AttestationRecord {
+            path: PathBuf::from("contribution.txt"),
+            command: "compute_constrained".to_string(),
+            input_hash: challenge_hash,
+            output_hash: response_hash,
+            completed_at: Some(1),
+            built_by: None,
+        };
+        let unrelated = AttestationRecord {
+            input_hash: [3; 64],
+            ..contribution.clone()
+        };
+        let report = CeremonyReport::build(&queue, &[unrelated, contribution.clone()]).unwrap();
+
+        assert_eq!(report.contributions(), 1);
+        assert_eq!(report.verified(), 1);
+        assert_eq!(report.rejected(), 1);
+        assert_eq!(report.attested(), (1, 0));
+        assert_eq!(report.rounds[0].contribution, Some(contribution));
+        assert_eq!(
+            report.rounds[0].next_challenge_hash,
+            Some(next_challenge_hash)
+        );
+        assert_eq!(report.final_challenge_hash(), next_challenge_hash);
+        assert_eq!(report.final_response_hash(), Some(response_hash));
+        assert!(report.rounds[0].verification_seconds().is_some());
+
+        let json = report.to_json();
+        assert!(
+            json.contains("\"contributions\":1,\"verified\":1,\"rejected\":1"),
+            "{}",
+            json
+        );
+        assert!(
+            json.contains(&hex::encode(&next_challenge_hash[..])),
+            "{}",
+            json
+        );
+        let markdown = report.to_markdown();
+        assert!(
+            markdown.contains("- Verified by the worker: 1 of 1\n"),
+            "{}",
+            markdown
+        );
+        assert!(markdown.contains("`contribution.txt`"), "{}", markdown);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
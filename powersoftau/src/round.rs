@@ -0,0 +1,280 @@
+/// A contribution to an accumulator, from the challenge it's computed on to the response it
+/// is written to. The functions of `BatchedAccumulator` take the maps, how each of them is
+/// encoded and how much they are checked as positional arguments of the same types, which are
+/// easy to pass in the wrong order; a round is set up once and then computed, finalized and
+/// verified without them.
+use bellman_ce::pairing::Engine;
+use generic_array::GenericArray;
+use log::error;
+use memmap::{Mmap, MmapMut};
+use typenum::consts::U64;
+
+use std::io;
+
+use super::batched_accumulator::{BatchedAccumulator, ChunkTransformer};
+use super::disk::ReadOptions;
+use super::keypair::{KeyDomain, PublicKey};
+use super::parameters::{
+    CeremonyParams, CheckForCorrectness, DeserializationError, UseCompression,
+};
+use super::utils::{calculate_hash, calculate_hash_of_reader, read_hash_header, write_hash_header};
+
+pub struct Round<'a, E: Engine> {
+    pub parameters: &'a CeremonyParams<E>,
+    challenge: &'a Mmap,
+    /// BLAKE2b hash of the challenge, which the response starts with
+    challenge_hash: GenericArray<u8, U64>,
+    /// What the keys of the round prove knowledge of their secrets with
+    key_digest: Vec<u8>,
+    pub challenge_compression: UseCompression,
+    pub response_compression: UseCompression,
+    pub check_challenge: CheckForCorrectness,
+    pub check_response: CheckForCorrectness,
+}
+
+impl<'a, E: Engine> Round<'a, E> {
+    /// A round on top of `challenge`, which is uncompressed and not checked when computing the
+    /// response, and a compressed response that is fully checked, like the command line tools
+    /// do. Keys are legacy keys, bound to the challenge only, and to the trapdoors that the
+    /// parameters update if not all of them.
+    pub fn new(challenge: &'a Mmap, parameters: &'a CeremonyParams<E>) -> Self {
+        let challenge_hash = calculate_hash(challenge);
+        Round {
+            parameters,
+            challenge,
+            challenge_hash,
+            key_digest: parameters.trapdoors.digest(&challenge_hash),
+            challenge_compression: UseCompression::No,
+            response_compression: UseCompression::Yes,
+            check_challenge: CheckForCorrectness::No,
+            check_response: CheckForCorrectness::Yes,
+        }
+    }
+
+    /// Binds the keys of the round to `domain`, see `KeyDomain`.
+    pub fn with_domain(mut self, domain: &KeyDomain) -> Self {
+        self.key_digest = self
+            .parameters
+            .trapdoors
+            .digest(&domain.digest(&self.challenge_hash));
+        self
+    }
+
+    pub fn with_response_compression(mut self, compression: UseCompression) -> Self {
+        self.response_compression = compression;
+        self
+    }
+
+    pub fn with_checks(
+        mut self,
+        challenge: CheckForCorrectness,
+        response: CheckForCorrectness,
+    ) -> Self {
+        self.check_challenge = challenge;
+        self.check_response = response;
+        self
+    }
+
+    pub fn challenge_hash(&self) -> &GenericArray<u8, U64> {
+        &self.challenge_hash
+    }
+
+    /// The digest to generate the keys of the round with.
+    pub fn key_digest(&self) -> &[u8] {
+        &self.key_digest
+    }
+
+    /// Writes the hash of the challenge and the transformed accumulator to `response`, which is
+    /// the size of a response. `transformer` is usually the private key.
+    pub fn compute<T: ChunkTransformer<E>>(
+        &self,
+        response: &mut MmapMut,
+        transformer: &T,
+    ) -> io::Result<()> {
+        write_hash_header(response, &self.challenge_hash)?;
+        BatchedAccumulator::transform_with(
+            self.challenge,
+            response,
+            self.challenge_compression,
+            self.response_compression,
+            self.check_challenge,
+            transformer,
+            self.parameters,
+        )
+    }
+
+    /// Writes `public_key` after the accumulator in `response` and returns the hash of the
+    /// response, which is complete then.
+    pub fn finalize(
+        &self,
+        response: &mut MmapMut,
+        public_key: &PublicKey<E>,
+    ) -> io::Result<GenericArray<u8, U64>> {
+        public_key.write(response, self.response_compression, self.parameters)?;
+        response.flush()?;
+        calculate_hash_of_reader(&mut &response[..])
+    }
+
+    /// Checks that `response` continues the hash chain from the challenge and was computed from
+    /// it with the key that it ends with.
+    pub fn verify(&self, response: &Mmap) -> Result<bool, DeserializationError> {
+        if read_hash_header(response)?[..] != self.challenge_hash[..] {
+            error!("The response was not computed on top of the challenge");
+            return Ok(false);
+        }
+        let public_key = PublicKey::read(response, self.response_compression, self.parameters)?;
+
+        Ok(BatchedAccumulator::verify_transformation(
+            self.challenge,
+            response,
+            &public_key,
+            &self.key_digest,
+            self.challenge_compression,
+            self.response_compression,
+            self.check_challenge,
+            self.check_response,
+            self.parameters,
+        ))
+    }
+
+    /// Same as `verify`, but the points of the response are also written to `new_challenge`,
+    /// which is the size of an uncompressed accumulator, as they are verified. `new_challenge`
+    /// starts with the hash of the response, and is the challenge of the next round if the
+    /// response is valid; it's incomplete otherwise. The response is read and decompressed once.
+    pub fn verify_and_decompress(
+        &self,
+        response: &Mmap,
+        new_challenge: &mut MmapMut,
+    ) -> Result<bool, DeserializationError> {
+        if read_hash_header(response)?[..] != self.challenge_hash[..] {
+            error!("The response was not computed on top of the challenge");
+            return Ok(false);
+        }
+        let public_key = PublicKey::read(response, self.response_compression, self.parameters)?;
+        write_hash_header(new_challenge, &calculate_hash(response))?;
+
+        Ok(BatchedAccumulator::verify_transformation_and_decompress(
+            self.challenge,
+            response,
+            &public_key,
+            &self.key_digest,
+            self.challenge_compression,
+            self.response_compression,
+            self.check_challenge,
+            self.check_response,
+            &ReadOptions::default(),
+            new_challenge,
+            None,
+            self.parameters,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batched_accumulator::AccumulatorView;
+    use crate::keypair::{keypair, keypair_with_mask, PrivateKey};
+    use crate::malicious::challenge;
+    use crate::parameters::expected_sizes;
+    use bellman_ce::pairing::bn256::Bn256;
+    use rand::thread_rng;
+
+    #[test]
+    fn test_round() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let challenge = challenge(&parameters);
+        let domain = KeyDomain::bn256_groth16(parameters.size, 2);
+        let round = Round::new(&challenge, &parameters).with_domain(&domain);
+        let response_size = expected_sizes(&parameters, UseCompression::Yes).response;
+
+        let contribute = |key_digest: &[u8]| {
+            let (public_key, private_key) = keypair::<_, Bn256>(&mut thread_rng(), key_digest);
+            let mut response = MmapMut::map_anon(response_size).unwrap();
+            round.compute(&mut response, &private_key).unwrap();
+            let hash = round.finalize(&mut response, &public_key).unwrap();
+            let response = response.make_read_only().unwrap();
+            assert_eq!(hash, calculate_hash(&response));
+            response
+        };
+
+        let response = contribute(round.key_digest());
+        assert_eq!(&response[0..64], round.challenge_hash().as_slice());
+        assert!(round.verify(&response).unwrap());
+
+        // In a single pass, the new challenge is the decompressed response
+        let mut new_challenge = MmapMut::map_anon(parameters.accumulator_size).unwrap();
+        assert!(round
+            .verify_and_decompress(&response, &mut new_challenge)
+            .unwrap());
+        let mut expected = MmapMut::map_anon(parameters.accumulator_size).unwrap();
+        write_hash_header(&mut expected, &calculate_hash(&response)).unwrap();
+        BatchedAccumulator::decompress(
+            &response,
+            &mut expected,
+            CheckForCorrectness::No,
+            &parameters,
+        )
+        .unwrap();
+        assert_eq!(&new_challenge[..], &expected[..]);
+
+        // A legacy key doesn't prove knowledge in the domain of the round
+        let response = contribute(round.challenge_hash());
+        assert!(!round.verify(&response).unwrap());
+        assert!(!round
+            .verify_and_decompress(&response, &mut new_challenge)
+            .unwrap());
+        assert!(Round::new(&challenge, &parameters)
+            .verify(&response)
+            .unwrap());
+
+        // The response isn't on top of another challenge
+        let other = self::challenge(&parameters);
+        let other_round = Round::new(&other, &parameters).with_domain(&domain);
+        assert!(!other_round.verify(&contribute(round.key_digest())).unwrap());
+    }
+
+    #[test]
+    fn test_round_with_trapdoor_mask() {
+        let mut parameters = CeremonyParams::<Bn256>::new(3, 4);
+        parameters.trapdoors = "beta".parse().unwrap();
+        let challenge = challenge(&parameters);
+        let round = Round::new(&challenge, &parameters);
+        assert_ne!(round.key_digest(), round.challenge_hash().as_slice());
+        let response_size = expected_sizes(&parameters, UseCompression::Yes).response;
+
+        let contribute = |(public_key, private_key): (PublicKey<Bn256>, PrivateKey<Bn256>)| {
+            let mut response = MmapMut::map_anon(response_size).unwrap();
+            round.compute(&mut response, &private_key).unwrap();
+            round.finalize(&mut response, &public_key).unwrap();
+            response.make_read_only().unwrap()
+        };
+
+        // Only beta is refreshed
+        let key = keypair_with_mask(&mut thread_rng(), round.key_digest(), parameters.trapdoors);
+        let response = contribute(key);
+        assert!(round.verify(&response).unwrap());
+        let view = |input, compression| {
+            AccumulatorView::new(input, compression, CheckForCorrectness::No, &parameters).unwrap()
+        };
+        let before = view(&challenge[..], UseCompression::No);
+        let after = view(&response[..], UseCompression::Yes);
+        let all = 0..parameters.powers_length;
+        assert!(
+            before.tau_powers_g1(all.clone()).unwrap() == after.tau_powers_g1(all.clone()).unwrap()
+        );
+        assert!(
+            before.beta_tau_powers_g1(all.clone()).unwrap()
+                != after.beta_tau_powers_g1(all).unwrap()
+        );
+
+        // A key that updates tau as well is rejected, and so is a partial update where every
+        // trapdoor is expected
+        let response = contribute(keypair(&mut thread_rng(), round.key_digest()));
+        assert!(!round.verify(&response).unwrap());
+        let full = CeremonyParams::<Bn256>::new(3, 4);
+        let key = keypair_with_mask(&mut thread_rng(), round.key_digest(), parameters.trapdoors);
+        let response = contribute(key);
+        assert!(!Round::new(&challenge, &full).verify(&response).unwrap());
+    }
+}
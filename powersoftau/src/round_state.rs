@@ -0,0 +1,162 @@
+/// State of a running ceremony that a coordinator hands over to the next one. It pins the
+/// parameters of the ceremony and the hash of the last verified challenge, so that everyone
+/// can check that the ceremony continues from exactly where the previous coordinator stopped.
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use memmap::Mmap;
+
+use std::io::{self, Read, Write};
+
+use bellman_ce::pairing::Engine;
+
+use super::parameters::CeremonyParams;
+use super::utils::{calculate_hash, read_hash_header};
+
+/// Identifies a round state file, followed by the format version.
+const MAGIC: &[u8; 8] = b"POTROUND";
+const VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoundState {
+    /// Power of the ceremony
+    pub size: u32,
+    /// Batch size the ceremony is processed with
+    pub batch_size: u32,
+    /// BLAKE2b hash of the last verified challenge
+    pub challenge_hash: [u8; 64],
+    /// Signature of the ceremony manifest by the outgoing coordinator, kept as opaque bytes
+    pub manifest_signature: Vec<u8>,
+}
+
+impl RoundState {
+    /// Captures the state of a ceremony whose latest verified challenge is `challenge_map`.
+    pub fn new<E: Engine>(
+        challenge_map: &Mmap,
+        parameters: &CeremonyParams<E>,
+        manifest_signature: Vec<u8>,
+    ) -> Self {
+        let mut challenge_hash = [0; 64];
+        challenge_hash.copy_from_slice(calculate_hash(challenge_map).as_slice());
+
+        RoundState {
+            size: parameters.size as u32,
+            batch_size: parameters.batch_size as u32,
+            challenge_hash,
+            manifest_signature,
+        }
+    }
+
+    /// Parameters of the ceremony this state belongs to.
+    pub fn parameters<E: Engine>(&self) -> CeremonyParams<E> {
+        CeremonyParams::new(self.size as usize, self.batch_size as usize)
+    }
+
+    /// Checks that `challenge_map` is the challenge this state was exported for.
+    pub fn verify_challenge(&self, challenge_map: &Mmap) -> bool {
+        calculate_hash(challenge_map).as_slice() == &self.challenge_hash[..]
+    }
+
+    /// Checks that `response_map` hash-chains to the challenge this state was exported for.
+    pub fn verify_response(&self, response_map: &Mmap) -> bool {
+        read_hash_header(response_map)
+            .map(|hash| hash[..] == self.challenge_hash[..])
+            .unwrap_or(false)
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&MAGIC[..])?;
+        writer.write_u32::<BigEndian>(VERSION)?;
+        writer.write_u32::<BigEndian>(self.size)?;
+        writer.write_u32::<BigEndian>(self.batch_size)?;
+        writer.write_all(&self.challenge_hash)?;
+        writer.write_u32::<BigEndian>(self.manifest_signature.len() as u32)?;
+        writer.write_all(&self.manifest_signature)?;
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a round state file",
+            ));
+        }
+        let version = reader.read_u32::<BigEndian>()?;
+        if version != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported round state version {}", version),
+            ));
+        }
+
+        let size = reader.read_u32::<BigEndian>()?;
+        let batch_size = reader.read_u32::<BigEndian>()?;
+        let mut challenge_hash = [0; 64];
+        reader.read_exact(&mut challenge_hash)?;
+        let signature_length = reader.read_u32::<BigEndian>()?;
+        // The length is untrusted, so only allocate for the bytes actually present
+        let mut manifest_signature = vec![];
+        reader
+            .by_ref()
+            .take(u64::from(signature_length))
+            .read_to_end(&mut manifest_signature)?;
+        if manifest_signature.len() != signature_length as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "manifest signature is truncated",
+            ));
+        }
+
+        Ok(RoundState {
+            size,
+            batch_size,
+            challenge_hash,
+            manifest_signature,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batched_accumulator::BatchedAccumulator;
+    use crate::parameters::UseCompression;
+    use bellman_ce::pairing::bn256::Bn256;
+    use memmap::MmapMut;
+
+    #[test]
+    fn test_round_state_roundtrip() {
+        let parameters = CeremonyParams::<Bn256>::new(2, 4);
+        let mut map = MmapMut::map_anon(parameters.accumulator_size).unwrap();
+        BatchedAccumulator::generate_initial(&mut map, UseCompression::No, &parameters).unwrap();
+        let map = map.make_read_only().unwrap();
+
+        let state = RoundState::new(&map, &parameters, vec![1, 2, 3]);
+        assert!(state.verify_challenge(&map));
+        assert!(!state.verify_response(&map));
+
+        let mut serialized = vec![];
+        state.write(&mut serialized).unwrap();
+        let deserialized = RoundState::read(&mut &serialized[..]).unwrap();
+        assert_eq!(state, deserialized);
+        let deserialized_parameters = deserialized.parameters::<Bn256>();
+        assert_eq!(deserialized_parameters.size, parameters.size);
+        assert_eq!(deserialized_parameters.batch_size, parameters.batch_size);
+    }
+
+    #[test]
+    fn test_round_state_rejects_garbage() {
+        assert!(RoundState::read(&mut &[0u8; 100][..]).is_err());
+
+        // A signature length far beyond the end of the file
+        let mut truncated = vec![];
+        truncated.extend_from_slice(&MAGIC[..]);
+        truncated.write_u32::<BigEndian>(VERSION).unwrap();
+        truncated.extend_from_slice(&[0; 8 + 64]);
+        truncated.write_u32::<BigEndian>(u32::MAX).unwrap();
+        truncated.extend_from_slice(&[1, 2, 3]);
+        assert!(RoundState::read(&mut &truncated[..]).is_err());
+    }
+}
@@ -0,0 +1,287 @@
+/// Records of sampled verifications, so that verification can be split between several
+/// partially trusted parties. Every party samples the pairs of consecutive powers to check
+/// from a seed, which is recorded together with the results, so anyone can replay the sample
+/// and the coordinator can hand out seeds. Reports of the same file are merged into a coverage
+/// map of the pairs that were checked by at least one party.
+///
+/// Reports are plain text, one `key: value` per line, and other lines are ignored, so that a
+/// report can be read back from an attestation that includes it.
+use bellman_ce::pairing::Engine;
+use rand::chacha::ChaChaRng;
+use rand::{Rng, SeedableRng};
+
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use super::errors::{self, Error, ErrorCategory};
+use super::parameters::CeremonyParams;
+
+/// Seeds the generator of the sampled indices.
+pub fn sample_rng(seed: &[u8; 32]) -> ChaChaRng {
+    let mut words = [0u32; 8];
+    for (word, bytes) in words.iter_mut().zip(seed.chunks(4)) {
+        *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    ChaChaRng::from_seed(&words)
+}
+
+/// Indices of the `sample` pairs of consecutive powers that the seed selects. The pair at
+/// index `i` links the powers `i` and `i + 1`.
+pub fn sampled_indices<E: Engine>(
+    seed: &[u8; 32],
+    sample: usize,
+    parameters: &CeremonyParams<E>,
+) -> Vec<usize> {
+    let mut rng = sample_rng(seed);
+    (0..sample)
+        .map(|_| rng.gen_range(0, parameters.powers_g1_length - 1))
+        .collect()
+}
+
+/// What a party checked in a sampled verification.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SampleReport {
+    pub seed: [u8; 32],
+    /// BLAKE2b hash of the verified file
+    pub file_hash: [u8; 64],
+    /// Index of every checked pair, and whether it passed
+    pub checks: Vec<(usize, bool)>,
+}
+
+impl SampleReport {
+    /// The lines of the report, as written to report files and attestations.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("sample seed: {}", hex::encode(&self.seed[..])),
+            format!("sampled file blake2b: {}", hex::encode(&self.file_hash[..])),
+        ];
+        for &(index, passed) in &self.checks {
+            lines.push(format!(
+                "sampled pair: {} {}",
+                index,
+                if passed { "ok" } else { "failed" }
+            ));
+        }
+
+        lines
+    }
+
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let invalid = |message: String| {
+            Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                format!("invalid sample report: {}", message),
+            )
+        };
+
+        let mut seed = None;
+        let mut file_hash = None;
+        let mut checks = vec![];
+        for line in text.lines() {
+            let (key, value) = match line.find(": ") {
+                Some(position) => (&line[..position], line[position + 2..].trim()),
+                None => continue,
+            };
+            match key {
+                "sample seed" => {
+                    let mut bytes = [0; 32];
+                    hex::decode_to_slice(value, &mut bytes[..])
+                        .map_err(|_| invalid(line.to_string()))?;
+                    seed = Some(bytes);
+                }
+                "sampled file blake2b" => {
+                    let mut hash = [0; 64];
+                    hex::decode_to_slice(value, &mut hash[..])
+                        .map_err(|_| invalid(line.to_string()))?;
+                    file_hash = Some(hash);
+                }
+                "sampled pair" => {
+                    let mut parts = value.split_whitespace();
+                    let index = parts.next().and_then(|index| index.parse().ok());
+                    let passed = match parts.next() {
+                        Some("ok") => Some(true),
+                        Some("failed") => Some(false),
+                        _ => None,
+                    };
+                    match (index, passed) {
+                        (Some(index), Some(passed)) => checks.push((index, passed)),
+                        _ => return Err(invalid(line.to_string())),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(SampleReport {
+            seed: seed.ok_or_else(|| invalid("missing sample seed".to_string()))?,
+            file_hash: file_hash.ok_or_else(|| invalid("missing file hash".to_string()))?,
+            checks,
+        })
+    }
+
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| {
+            Error::from(e).context(format!("unable to read sample report {}", path.display()))
+        })?;
+
+        Self::parse(&text).map_err(|e| e.context(format!("in {}", path.display())))
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut text = self.lines().join("\n");
+        text.push('\n');
+        fs::write(path, text).map_err(Error::from)
+    }
+}
+
+/// Pairs of consecutive powers checked by at least one of several reports of the same file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Coverage {
+    /// Number of pairs, one less than the number of powers of tau in G1
+    pub length: usize,
+    /// Checked pairs, as sorted and disjoint ranges of indices
+    pub covered: Vec<Range<usize>>,
+    /// Indices of the pairs that failed in some report, sorted
+    pub failed: Vec<usize>,
+}
+
+impl Coverage {
+    /// Merges reports of the same file. Fails if the reports are of different files or check
+    /// pairs that do not exist.
+    pub fn merge<E: Engine>(
+        reports: &[SampleReport],
+        parameters: &CeremonyParams<E>,
+    ) -> Result<Self, Error> {
+        let length = parameters.powers_g1_length - 1;
+        let mut checked = vec![false; length];
+        let mut failed = vec![];
+        for report in reports {
+            if report.file_hash[..] != reports[0].file_hash[..] {
+                return Err(Error::new(
+                    errors::INVALID_ARGUMENT,
+                    ErrorCategory::InvalidInput,
+                    format!(
+                        "the reports are of different files, {} and {}",
+                        hex::encode(&reports[0].file_hash[..]),
+                        hex::encode(&report.file_hash[..])
+                    ),
+                ));
+            }
+            for &(index, passed) in &report.checks {
+                if index >= length {
+                    return Err(Error::new(
+                        errors::INVALID_ARGUMENT,
+                        ErrorCategory::InvalidInput,
+                        format!("pair {} is out of range, there are {}", index, length),
+                    ));
+                }
+                checked[index] = true;
+                if !passed {
+                    failed.push(index);
+                }
+            }
+        }
+        failed.sort_unstable();
+        failed.dedup();
+
+        let mut covered: Vec<Range<usize>> = vec![];
+        for index in (0..length).filter(|&index| checked[index]) {
+            match covered.last_mut() {
+                Some(range) if range.end == index => range.end += 1,
+                _ => covered.push(index..index + 1),
+            }
+        }
+
+        Ok(Coverage {
+            length,
+            covered,
+            failed,
+        })
+    }
+
+    /// Pairs that no report checked.
+    pub fn uncovered(&self) -> Vec<Range<usize>> {
+        let mut uncovered = vec![];
+        let mut start = 0;
+        for range in self.covered.iter().chain(Some(&(self.length..self.length))) {
+            if range.start > start {
+                uncovered.push(start..range.start);
+            }
+            start = range.end;
+        }
+
+        uncovered
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.uncovered().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bellman_ce::pairing::bn256::Bn256;
+
+    fn report(seed: u8, checks: Vec<(usize, bool)>) -> SampleReport {
+        SampleReport {
+            seed: [seed; 32],
+            file_hash: [7; 64],
+            checks,
+        }
+    }
+
+    #[test]
+    fn test_sampled_indices_are_replayable() {
+        let parameters = CeremonyParams::<Bn256>::new(4, 4);
+        let indices = sampled_indices(&[1; 32], 20, &parameters);
+        assert_eq!(indices, sampled_indices(&[1; 32], 20, &parameters));
+        assert_ne!(indices, sampled_indices(&[2; 32], 20, &parameters));
+        assert!(indices
+            .iter()
+            .all(|&index| index < parameters.powers_g1_length - 1));
+    }
+
+    #[test]
+    fn test_sample_report_roundtrip() {
+        let report = report(3, vec![(5, true), (0, false)]);
+        let text = format!(
+            "command: verify_transform_constrained\n{}\n",
+            report.lines().join("\n")
+        );
+        assert_eq!(SampleReport::parse(&text).unwrap(), report);
+
+        assert!(SampleReport::parse("sample seed: 00\n").is_err());
+        let broken = text.replace("5 ok", "5 maybe");
+        assert!(SampleReport::parse(&broken).is_err());
+    }
+
+    #[test]
+    fn test_coverage() {
+        // 15 pairs
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let reports = [
+            report(1, vec![(0, true), (1, true), (5, true)]),
+            report(2, vec![(2, true), (6, false), (13, true)]),
+        ];
+        let coverage = Coverage::merge(&reports, &parameters).unwrap();
+        assert_eq!(coverage.length, 14);
+        assert_eq!(coverage.covered, vec![0..3, 5..7, 13..14]);
+        assert_eq!(coverage.uncovered(), vec![3..5, 7..13]);
+        assert_eq!(coverage.failed, vec![6]);
+        assert!(!coverage.is_complete());
+
+        let all = report(3, (0..14).map(|index| (index, true)).collect());
+        let coverage = Coverage::merge(&[all], &parameters).unwrap();
+        assert!(coverage.is_complete());
+        assert!(coverage.failed.is_empty());
+
+        let mut other_file = report(4, vec![]);
+        other_file.file_hash[0] = 0;
+        assert!(Coverage::merge(&[reports[0].clone(), other_file], &parameters).is_err());
+        assert!(Coverage::merge(&[report(5, vec![(14, true)])], &parameters).is_err());
+    }
+}
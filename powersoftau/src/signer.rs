@@ -0,0 +1,574 @@
+/// Contributions whose secrets stay in another process, such as a signing device or a machine
+/// set apart for the ceremony. The signer samples the private key, proves knowledge of it with
+/// the public key, and multiplies the chunks of the challenge that it is sent, so that the
+/// contributing machine only ever handles points.
+///
+/// The signer is a command that reads requests on its standard input and answers every one of
+/// them on its standard output. Messages are JSON objects of string values on a single line:
+///
+/// * `{"command": "public_key", "digest": <hex>}` samples the key for the challenge with the
+///   given BLAKE2b hash, and is answered with `{"public_key": <hex>}`, the serialized public
+///   key. The key is sampled once, so this can only be sent once.
+/// * `{"command": "transform", "start": <decimal>, "tau_g1": <hex>, "tau_g2": <hex>,
+///   "alpha_g1": <hex>, "beta_g1": <hex>, "beta_g2": <hex>}` sends the chunk of the challenge
+///   that starts at the power `start` as concatenated uncompressed points, and is answered with
+///   the same fields for the transformed chunk. Chunks past the powers of tau in G2 only have
+///   points in `tau_g1`, the other fields are empty.
+///
+/// Failed requests are answered with `{"error": <message>}`. The signer destroys the key when
+/// its input is closed. `serve` is a signer in software, as run by `software_signer`.
+use bellman_ce::pairing::{CurveAffine, EncodedPoint, Engine};
+use rand::Rng;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use super::batched_accumulator::{BatchedAccumulator, ChunkTransformer};
+use super::errors::{self, Error, ErrorCategory};
+use super::keypair::{guarded_keypair, PrivateKey, PublicKey, SecretGuard};
+use super::parameters::CeremonyParams;
+use super::utils::{blank_hash, json_string};
+
+type Message = BTreeMap<String, String>;
+
+fn invalid_data<T, S: Into<String>>(message: S) -> io::Result<T> {
+    Err(io::Error::new(io::ErrorKind::InvalidData, message.into()))
+}
+
+fn encode(fields: &[(&str, String)]) -> String {
+    let fields = fields
+        .iter()
+        .map(|(key, value)| format!("{}:{}", json_string(key), json_string(value)))
+        .collect::<Vec<_>>();
+
+    format!("{{{}}}", fields.join(","))
+}
+
+fn decode(line: &str) -> io::Result<Message> {
+    fn skip_whitespace(chars: &mut std::iter::Peekable<std::str::Chars>) {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+    }
+
+    fn code_unit(chars: &mut std::iter::Peekable<std::str::Chars>) -> io::Result<u32> {
+        let digits = chars.take(4).collect::<String>();
+        match u32::from_str_radix(&digits, 16) {
+            Ok(unit) if digits.len() == 4 => Ok(unit),
+            _ => invalid_data("expected 4 hexadecimal digits in a \\u escape"),
+        }
+    }
+
+    // The character of a `\u` escape, from a surrogate pair outside of the basic plane
+    fn unicode_escape(chars: &mut std::iter::Peekable<std::str::Chars>) -> io::Result<char> {
+        let mut unit = code_unit(chars)?;
+        if (0xd800..0xdc00).contains(&unit) {
+            if chars.next() != Some('\\') || chars.next() != Some('u') {
+                return invalid_data("unpaired surrogate in a string");
+            }
+            let low = code_unit(chars)?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return invalid_data("unpaired surrogate in a string");
+            }
+            unit = 0x10000 + ((unit - 0xd800) << 10) + (low - 0xdc00);
+        }
+        match std::char::from_u32(unit) {
+            Some(c) => Ok(c),
+            None => invalid_data("unpaired surrogate in a string"),
+        }
+    }
+
+    fn string(chars: &mut std::iter::Peekable<std::str::Chars>) -> io::Result<String> {
+        if chars.next() != Some('"') {
+            return invalid_data("expected a string");
+        }
+        let mut string = String::new();
+        loop {
+            match chars.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => match chars.next() {
+                    Some(c @ '"') | Some(c @ '\\') | Some(c @ '/') => string.push(c),
+                    Some('n') => string.push('\n'),
+                    Some('r') => string.push('\r'),
+                    Some('t') => string.push('\t'),
+                    Some('b') => string.push('\u{8}'),
+                    Some('f') => string.push('\u{c}'),
+                    Some('u') => string.push(unicode_escape(chars)?),
+                    _ => return invalid_data("unsupported escape in a string"),
+                },
+                Some(c) => string.push(c),
+                None => return invalid_data("unterminated string"),
+            }
+        }
+    }
+
+    let mut chars = line.chars().peekable();
+    let mut message = Message::new();
+    skip_whitespace(&mut chars);
+    if chars.next() != Some('{') {
+        return invalid_data("expected an object");
+    }
+    skip_whitespace(&mut chars);
+    if chars.peek() == Some(&'}') {
+        chars.next();
+    } else {
+        loop {
+            skip_whitespace(&mut chars);
+            let key = string(&mut chars)?;
+            skip_whitespace(&mut chars);
+            if chars.next() != Some(':') {
+                return invalid_data("expected a colon");
+            }
+            skip_whitespace(&mut chars);
+            let value = string(&mut chars)?;
+            message.insert(key, value);
+            skip_whitespace(&mut chars);
+            match chars.next() {
+                Some(',') => continue,
+                Some('}') => break,
+                _ => return invalid_data("expected a comma or the end of the object"),
+            }
+        }
+    }
+    skip_whitespace(&mut chars);
+    if chars.next().is_some() {
+        return invalid_data("unexpected text after the object");
+    }
+
+    Ok(message)
+}
+
+fn field<'m>(message: &'m Message, key: &str) -> io::Result<&'m str> {
+    match message.get(key) {
+        Some(value) => Ok(value),
+        None => invalid_data(format!("missing field {}", key)),
+    }
+}
+
+fn encode_points<C: CurveAffine>(points: &[C]) -> String {
+    let mut bytes = vec![];
+    for point in points {
+        bytes.extend_from_slice(point.into_uncompressed().as_ref());
+    }
+
+    hex::encode(bytes)
+}
+
+/// Decodes the points of a field, which must be in the subgroup and not at infinity.
+fn decode_points<C: CurveAffine>(message: &Message, key: &str) -> io::Result<Vec<C>> {
+    let bytes = hex::decode(field(message, key)?)
+        .or_else(|e| invalid_data(format!("invalid hex in {}: {}", key, e)))?;
+    let size = C::Uncompressed::size();
+    if bytes.len() % size != 0 {
+        return invalid_data(format!("{} is not made of whole points", key));
+    }
+
+    bytes
+        .chunks(size)
+        .map(|bytes| {
+            let mut encoded = C::Uncompressed::empty();
+            encoded.as_mut().copy_from_slice(bytes);
+            match encoded.into_affine() {
+                Ok(point) if !point.is_zero() => Ok(point),
+                Ok(_) => invalid_data(format!("point at infinity in {}", key)),
+                Err(e) => invalid_data(format!("invalid point in {}: {}", key, e)),
+            }
+        })
+        .collect()
+}
+
+fn chunk_fields<E: Engine>(
+    start: usize,
+    accumulator: &BatchedAccumulator<E>,
+) -> Vec<(&'static str, String)> {
+    let beta_g2 = if accumulator.tau_powers_g2.is_empty() {
+        String::new()
+    } else {
+        encode_points(&[accumulator.beta_g2])
+    };
+
+    vec![
+        ("start", start.to_string()),
+        ("tau_g1", encode_points(&accumulator.tau_powers_g1)),
+        ("tau_g2", encode_points(&accumulator.tau_powers_g2)),
+        ("alpha_g1", encode_points(&accumulator.alpha_tau_powers_g1)),
+        ("beta_g1", encode_points(&accumulator.beta_tau_powers_g1)),
+        ("beta_g2", beta_g2),
+    ]
+}
+
+/// Reads the points of a chunk into `accumulator`, and checks that they have the shape of the
+/// chunk of `length` powers that starts at `start`.
+fn read_chunk_fields<E: Engine>(
+    message: &Message,
+    start: usize,
+    length: usize,
+    accumulator: &mut BatchedAccumulator<E>,
+) -> io::Result<()> {
+    let parameters = accumulator.parameters;
+    let tau_powers_g1 = decode_points(message, "tau_g1")?;
+    let tau_powers_g2 = decode_points(message, "tau_g2")?;
+    let alpha_tau_powers_g1 = decode_points(message, "alpha_g1")?;
+    let beta_tau_powers_g1 = decode_points(message, "beta_g1")?;
+    let beta_g2 = decode_points(message, "beta_g2")?;
+
+    let end = start
+        .checked_add(length)
+        .filter(|&end| length > 0 && end <= parameters.powers_g1_length);
+    let in_first_section = end.is_some_and(|end| end <= parameters.powers_length);
+    let g2_length = if in_first_section { length } else { 0 };
+    if end.is_none()
+        || (!in_first_section && start < parameters.powers_length)
+        || tau_powers_g1.len() != length
+        || tau_powers_g2.len() != g2_length
+        || alpha_tau_powers_g1.len() != g2_length
+        || beta_tau_powers_g1.len() != g2_length
+        || beta_g2.len() != if in_first_section { 1 } else { 0 }
+    {
+        return invalid_data(format!(
+            "not a chunk of {} powers starting at {}",
+            length, start
+        ));
+    }
+
+    accumulator.tau_powers_g1 = tau_powers_g1;
+    accumulator.tau_powers_g2 = tau_powers_g2;
+    accumulator.alpha_tau_powers_g1 = alpha_tau_powers_g1;
+    accumulator.beta_tau_powers_g1 = beta_tau_powers_g1;
+    if let Some(&beta_g2) = beta_g2.first() {
+        accumulator.beta_g2 = beta_g2;
+    }
+
+    Ok(())
+}
+
+/// A signer running in another process, which the contribution talks to through its standard
+/// input and output.
+pub struct ExternalSigner {
+    child: Child,
+    input: RefCell<Option<ChildStdin>>,
+    output: RefCell<BufReader<ChildStdout>>,
+}
+
+impl ExternalSigner {
+    /// Starts the signer. The command is split at whitespace into the program and its
+    /// arguments, it is not run by a shell.
+    pub fn spawn(command: &str) -> Result<Self, Error> {
+        let mut words = command.split_whitespace();
+        let program = words.next().ok_or_else(|| {
+            Error::new(
+                errors::INVALID_ARGUMENT,
+                ErrorCategory::InvalidInput,
+                "the signer command is empty",
+            )
+        })?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                Error::from(e).context(format!("unable to start the signer {}", program))
+            })?;
+        let input = child
+            .stdin
+            .take()
+            .expect("the input of the signer is piped");
+        let output = child
+            .stdout
+            .take()
+            .expect("the output of the signer is piped");
+
+        Ok(ExternalSigner {
+            child,
+            input: RefCell::new(Some(input)),
+            output: RefCell::new(BufReader::new(output)),
+        })
+    }
+
+    fn request(&self, fields: &[(&str, String)]) -> io::Result<Message> {
+        {
+            let mut input = self.input.borrow_mut();
+            let input = input
+                .as_mut()
+                .expect("the input is open until the signer is dropped");
+            writeln!(input, "{}", encode(fields))?;
+            input.flush()?;
+        }
+
+        let mut line = String::new();
+        if self.output.borrow_mut().read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the signer exited without answering",
+            ));
+        }
+        let response = decode(&line)?;
+        if let Some(error) = response.get("error") {
+            return Err(io::Error::other(format!("the signer failed: {}", error)));
+        }
+
+        Ok(response)
+    }
+
+    /// Has the signer sample its key for the challenge with the hash `digest`, and returns
+    /// the public key.
+    pub fn public_key<E: Engine>(&self, digest: &[u8]) -> Result<PublicKey<E>, Error> {
+        let response = self.request(&[
+            ("command", "public_key".to_string()),
+            ("digest", hex::encode(digest)),
+        ])?;
+        let bytes = hex::decode(field(&response, "public_key")?).map_err(|e| {
+            Error::new(
+                errors::INVALID_POINT,
+                ErrorCategory::InvalidInput,
+                format!("invalid public key from the signer: {}", e),
+            )
+        })?;
+
+        PublicKey::deserialize(&mut &bytes[..])
+            .map_err(|e| Error::from(e).context("invalid public key from the signer"))
+    }
+}
+
+impl<E: Engine> ChunkTransformer<E> for ExternalSigner {
+    fn transform_chunk(
+        &self,
+        start: usize,
+        accumulator: &mut BatchedAccumulator<E>,
+    ) -> io::Result<()> {
+        let length = accumulator.tau_powers_g1.len();
+        let mut request = vec![("command", "transform".to_string())];
+        request.extend(chunk_fields(start, accumulator));
+        let response = self.request(&request)?;
+        read_chunk_fields(&response, start, length, accumulator)
+    }
+}
+
+impl Drop for ExternalSigner {
+    fn drop(&mut self) {
+        // Closing the input tells the signer to destroy the key and exit
+        self.input.borrow_mut().take();
+        let _ = self.child.wait();
+    }
+}
+
+fn answer<E: Engine, R: Rng>(
+    parameters: &CeremonyParams<E>,
+    rng: &mut R,
+    key: &mut Option<SecretGuard<PrivateKey<E>>>,
+    line: &str,
+) -> io::Result<Vec<(&'static str, String)>> {
+    let request = decode(line)?;
+    match field(&request, "command")? {
+        "public_key" => {
+            if key.is_some() {
+                return invalid_data("the key was already sampled");
+            }
+            let digest = hex::decode(field(&request, "digest")?)
+                .or_else(|e| invalid_data(format!("invalid digest: {}", e)))?;
+            if digest.len() != 64 {
+                return invalid_data("the digest must be a BLAKE2b hash of 64 bytes");
+            }
+            let (public_key, private_key) = guarded_keypair::<_, E>(rng, &digest);
+            *key = Some(private_key);
+
+            let mut bytes = vec![];
+            public_key.serialize(&mut bytes)?;
+            Ok(vec![("public_key", hex::encode(bytes))])
+        }
+        "transform" => {
+            let key = match key {
+                Some(key) => key,
+                None => return invalid_data("the key must be sampled before transforming"),
+            };
+            let start = field(&request, "start")?
+                .parse()
+                .or_else(|e| invalid_data(format!("invalid start: {}", e)))?;
+            // Two hex digits per byte
+            let length = field(&request, "tau_g1")?.len()
+                / (2 * <E::G1Affine as CurveAffine>::Uncompressed::size());
+            let mut accumulator = BatchedAccumulator {
+                tau_powers_g1: vec![],
+                tau_powers_g2: vec![],
+                alpha_tau_powers_g1: vec![],
+                beta_tau_powers_g1: vec![],
+                beta_g2: E::G2Affine::one(),
+                hash: blank_hash(),
+                parameters,
+            };
+            read_chunk_fields(&request, start, length, &mut accumulator)?;
+            key.transform_chunk(start, &mut accumulator)?;
+
+            Ok(chunk_fields(start, &accumulator))
+        }
+        command => invalid_data(format!("unknown command {}", command)),
+    }
+}
+
+/// Answers the requests of the protocol read from `input` on `output` until the input is
+/// closed, with a key sampled from `rng`. Chunks that are not part of an accumulator with
+/// `parameters` are refused.
+pub fn serve<E: Engine, R: Rng, I: BufRead, O: Write>(
+    parameters: &CeremonyParams<E>,
+    rng: &mut R,
+    input: I,
+    mut output: O,
+) -> io::Result<()> {
+    let mut key = None;
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match answer(parameters, rng, &mut key, &line) {
+            Ok(fields) => encode(&fields),
+            Err(e) => encode(&[("error", e.to_string())]),
+        };
+        writeln!(output, "{}", response)?;
+        output.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Removes `--signer <command>` from the arguments and returns the command
+pub fn take_signer(args: &mut Vec<String>) -> Result<Option<String>, Error> {
+    let position = match args.iter().position(|arg| arg == "--signer") {
+        Some(position) => position,
+        None => return Ok(None),
+    };
+    if position + 1 >= args.len() {
+        return Err(Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            "--signer requires a command",
+        ));
+    }
+    let command = args[position + 1].clone();
+    args.drain(position..position + 2);
+
+    Ok(Some(command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::same_ratio;
+    use bellman_ce::pairing::bn256::Bn256;
+    use rand::thread_rng;
+
+    #[test]
+    fn test_message_encoding() {
+        let fields = [
+            ("command", "public_key".to_string()),
+            ("error", "a \"quoted\" \\ message\n".to_string()),
+            ("control", "\r\t\u{1}\u{7f}é".to_string()),
+        ];
+        let encoded = encode(&fields);
+        assert!(!encoded.contains(|c: char| c.is_control() && c != '\u{7f}'));
+        let message = decode(&encoded).unwrap();
+        assert_eq!(message["command"], "public_key");
+        assert_eq!(message["error"], fields[1].1);
+        assert_eq!(message["control"], fields[2].1);
+        let message = decode("{\"a\":\"\\u00e9\\ud83d\\ude00\\b\"}").unwrap();
+        assert_eq!(message["a"], "é😀\u{8}");
+
+        let message = decode(" { \"a\" : \"1\" , \"b\":\"\" } ").unwrap();
+        assert_eq!(message.len(), 2);
+        assert!(decode("{}").unwrap().is_empty());
+        for invalid in &[
+            "",
+            "{",
+            "{\"a\":1}",
+            "{\"a\":\"1\"} x",
+            "{\"a\" \"1\"}",
+            "{\"a\":\"\\u12\"}",
+            "{\"a\":\"\\ud83d\"}",
+            "{\"a\":\"\\ude00\"}",
+        ] {
+            assert!(decode(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_serve() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let digest = hex::encode(&[1u8; 64][..]);
+        let mut accumulator = BatchedAccumulator::empty(&parameters);
+        accumulator.tau_powers_g1 = vec![<Bn256 as Engine>::G1Affine::one(); 2];
+        accumulator.tau_powers_g2 = vec![<Bn256 as Engine>::G2Affine::one(); 2];
+        accumulator.alpha_tau_powers_g1 = accumulator.tau_powers_g1.clone();
+        accumulator.beta_tau_powers_g1 = accumulator.tau_powers_g1.clone();
+        accumulator.beta_g2 = <Bn256 as Engine>::G2Affine::one();
+        let transform = |start| {
+            let mut fields = vec![("command", "transform".to_string())];
+            fields.extend(chunk_fields(start, &accumulator));
+            encode(&fields)
+        };
+        let requests = [
+            transform(0),
+            encode(&[
+                ("command", "public_key".to_string()),
+                ("digest", digest.clone()),
+            ]),
+            encode(&[("command", "public_key".to_string()), ("digest", digest)]),
+            transform(0),
+            // Past the powers of tau in G2, chunks only have powers in G1
+            transform(parameters.powers_length),
+        ];
+
+        let mut output = vec![];
+        serve(
+            &parameters,
+            &mut thread_rng(),
+            requests.join("\n").as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        let responses = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| decode(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(responses.len(), 5);
+        assert!(responses[0].contains_key("error"));
+        let public_key = hex::decode(&responses[1]["public_key"]).unwrap();
+        let public_key = PublicKey::<Bn256>::deserialize(&mut &public_key[..]).unwrap();
+        assert!(responses[2].contains_key("error"));
+        assert!(responses[4].contains_key("error"));
+
+        // The first power is the generator, and the others use the secrets of the public key
+        let mut transformed = BatchedAccumulator::empty(&parameters);
+        read_chunk_fields(&responses[3], 0, 2, &mut transformed).unwrap();
+        let g2 = <Bn256 as Engine>::G2Affine::one();
+        assert_eq!(transformed.tau_powers_g1[0], accumulator.tau_powers_g1[0]);
+        assert!(same_ratio(
+            public_key.tau_g1,
+            (g2, transformed.tau_powers_g2[1])
+        ));
+        assert!(same_ratio(
+            (transformed.tau_powers_g1[0], transformed.tau_powers_g1[1]),
+            (g2, transformed.tau_powers_g2[1])
+        ));
+        assert!(same_ratio(public_key.beta_g1, (g2, transformed.beta_g2)));
+    }
+
+    #[test]
+    fn test_take_signer() {
+        let mut args = vec![
+            "cmd".to_string(),
+            "--signer".to_string(),
+            "signer -v".to_string(),
+        ];
+        assert_eq!(
+            take_signer(&mut args).unwrap(),
+            Some("signer -v".to_string())
+        );
+        assert_eq!(args, vec!["cmd".to_string()]);
+        assert_eq!(take_signer(&mut args).unwrap(), None);
+        assert!(take_signer(&mut vec!["--signer".to_string()]).is_err());
+    }
+}
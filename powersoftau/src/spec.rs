@@ -0,0 +1,367 @@
+/// Machine-readable specification of a ceremony: the curve and its encodings, the power, the
+/// byte layout of every file for every encoding, the hash, the chunk schedule and what the
+/// verification checks. Everything in it is derived from the parameters, so an independent
+/// implementation can check that it reads the files exactly as this crate does.
+///
+/// The document is a single line of JSON with the keys in a fixed order and no whitespace:
+///
+/// `{"format":"powersoftau-spec","version":1,"spec":{...},"hash":"<hex>"}`
+///
+/// where `hash` is the BLAKE2b hash of the `spec` object as written. Reading a spec checks the
+/// hash, takes the curve, power, batch size, generators and subgroup check from it and writes
+/// the spec of the resulting parameters again, which must be the same bytes. A spec exported by
+/// a version of the tools that lays out the files differently is rejected.
+use bellman_ce::pairing::Engine;
+use std::fs;
+use verification_core::{ALPHA_PERSONALIZATION, BETA_PERSONALIZATION, TAU_PERSONALIZATION};
+
+use super::batched_accumulator::BatchedAccumulator;
+use super::container::accumulator_sections;
+use super::errors::{self, Error, ErrorCategory};
+use super::parameters::{
+    expected_sizes, CeremonyParams, CheckForCorrectness, Generators, UseCompression,
+};
+use super::utils::calculate_hash;
+
+const FORMAT: &str = "powersoftau-spec";
+pub const VERSION: u32 = 1;
+
+/// The encodings of the files of a round with the command line tools.
+const CHALLENGE_ENCODING: UseCompression = UseCompression::No;
+const RESPONSE_ENCODING: UseCompression = UseCompression::Yes;
+const NEW_CHALLENGE_ENCODING: UseCompression = UseCompression::No;
+
+/// The points of a public key in the order they are written, all uncompressed.
+const PUBLIC_KEY_POINTS: &[&str] = &[
+    "tau_g1_s",
+    "tau_g1_s_tau",
+    "alpha_g1_s",
+    "alpha_g1_s_alpha",
+    "beta_g1_s",
+    "beta_g1_s_beta",
+    "tau_g2",
+    "alpha_g2",
+    "beta_g2",
+];
+
+/// What `verify_transformation` checks, in order.
+const RULES: &[&str] = &[
+    "the response starts with the hash of the challenge",
+    "every point of the response is in the prime order subgroup and not at infinity",
+    "each proof of knowledge in the public key has the same ratio as the point hashed to G2 from the transcript, the personalization and both G1 points",
+    "tau_g1[0] and tau_g2[0] of the response are the generators",
+    "tau_g1[1] of the challenge and of the response have the ratio of the tau proof of knowledge",
+    "alpha_g1[0] of the challenge and of the response have the ratio of the alpha proof of knowledge",
+    "beta_g1[0] of the challenge and of the response have the ratio of the beta proof of knowledge",
+    "beta_g1[0] and beta_g2 of the challenge and of the response have the same ratio",
+    "consecutive powers of tau_g1, alpha_g1 and beta_g1 have the ratio of tau_g2[0] and tau_g2[1]",
+    "consecutive powers of tau_g2 have the ratio of tau_g1[0] and tau_g1[1]",
+];
+
+fn check_name(check: CheckForCorrectness) -> &'static str {
+    match check {
+        CheckForCorrectness::Yes => "full",
+        CheckForCorrectness::OnlyInGroup => "in-group",
+        CheckForCorrectness::OnlyNonZero => "non-zero",
+        CheckForCorrectness::No => "none",
+    }
+}
+
+fn quoted(strings: &[&str]) -> String {
+    strings
+        .iter()
+        .map(|s| format!("\"{}\"", s))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The byte layout of a challenge and a response encoded with `compression`.
+fn layout_json<E: Engine>(parameters: &CeremonyParams<E>, compression: UseCompression) -> String {
+    let sizes = expected_sizes(parameters, compression);
+    let (g1_size, g2_size) = (
+        parameters.curve.g1_size(compression),
+        parameters.curve.g2_size(compression),
+    );
+    let sections = accumulator_sections(parameters, compression, true)
+        .into_iter()
+        .map(|(name, range)| {
+            let element_size = match name {
+                "tau_g1" | "alpha_g1" | "beta_g1" => g1_size,
+                "tau_g2" | "beta_g2" => g2_size,
+                _ => range.len(),
+            };
+            format!(
+                "{{\"name\":\"{}\",\"offset\":{},\"length\":{},\"element_size\":{}}}",
+                name,
+                range.start,
+                range.len(),
+                element_size
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+
+    format!(
+        "{{\"encoding\":\"{}\",\"g1_size\":{},\"g2_size\":{},\"challenge_size\":{},\"response_size\":{},\"sections\":[{}]}}",
+        compression, g1_size, g2_size, sizes.challenge, sizes.response, sections
+    )
+}
+
+/// The `spec` object of the document for a ceremony on `curve` with `parameters`.
+pub fn spec_json<E: Engine>(curve: &str, parameters: &CeremonyParams<E>) -> String {
+    let mut generators = vec![];
+    parameters
+        .generators
+        .write(&mut generators)
+        .expect("must write generators to memory");
+    let layouts = [
+        UseCompression::No,
+        UseCompression::Yes,
+        UseCompression::Mixed,
+    ]
+    .iter()
+    .map(|&compression| layout_json(parameters, compression))
+    .collect::<Vec<_>>()
+    .join(",");
+
+    format!(
+        concat!(
+            "{{\"curve\":{{\"name\":\"{}\",\"g1\":{},\"g2\":{},\"g1_compressed\":{},\"g2_compressed\":{},\"generators\":\"{}\"}},",
+            "\"power\":{},\"batch_size\":{},\"powers_length\":{},\"powers_g1_length\":{},",
+            "\"hash\":{{\"algorithm\":\"blake2b-512\",\"size\":{},\"header\":\"every file starts with the hash of the previous file, the first challenge with the hash of no input\"}},",
+            "\"layouts\":[{}],",
+            "\"public_key\":{{\"size\":{},\"encoding\":\"uncompressed\",\"points\":[{}]}},",
+            "\"chunks\":{{\"count\":{},\"batch_size\":{},\"sections\":[[0,{}],[{},{}]],\"overlap\":1}},",
+            "\"verification\":{{\"challenge\":\"{}\",\"response\":\"{}\",\"new_challenge\":\"{}\",\"check_challenge\":\"{}\",\"check_response\":\"{}\",\"subgroup_check\":\"{}\",",
+            "\"personalizations\":{{\"tau\":{},\"alpha\":{},\"beta\":{}}},\"rules\":[{}]}}}}"
+        ),
+        curve,
+        parameters.curve.g1,
+        parameters.curve.g2,
+        parameters.curve.g1_compressed,
+        parameters.curve.g2_compressed,
+        hex::encode(&generators),
+        parameters.size,
+        parameters.batch_size,
+        parameters.powers_length,
+        parameters.powers_g1_length,
+        parameters.hash_size,
+        layouts,
+        parameters.public_key_size,
+        quoted(PUBLIC_KEY_POINTS),
+        BatchedAccumulator::chunk_ranges(parameters).len(),
+        parameters.batch_size,
+        parameters.powers_length,
+        parameters.powers_length,
+        parameters.powers_g1_length,
+        CHALLENGE_ENCODING,
+        RESPONSE_ENCODING,
+        NEW_CHALLENGE_ENCODING,
+        check_name(CheckForCorrectness::No),
+        check_name(CheckForCorrectness::Yes),
+        parameters.curve.subgroup_check,
+        TAU_PERSONALIZATION,
+        ALPHA_PERSONALIZATION,
+        BETA_PERSONALIZATION,
+        quoted(RULES)
+    )
+}
+
+/// The whole document, ending with a newline.
+pub fn export_spec<E: Engine>(curve: &str, parameters: &CeremonyParams<E>) -> String {
+    let spec = spec_json(curve, parameters);
+    let hash = calculate_hash(spec.as_bytes());
+
+    format!(
+        "{{\"format\":\"{}\",\"version\":{},\"spec\":{},\"hash\":\"{}\"}}\n",
+        FORMAT,
+        VERSION,
+        spec,
+        hex::encode(hash.as_slice())
+    )
+}
+
+/// The raw value of `key` in the compact JSON `json`: the contents of a string, or a number.
+fn value<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let start = json.find(&format!("\"{}\":", key))? + key.len() + 3;
+    let rest = &json[start..];
+    match rest.strip_prefix('"') {
+        Some(string) => string.split('"').next(),
+        None => rest.split(&[',', '}'][..]).next(),
+    }
+}
+
+/// Reads the parameters of a ceremony on `curve` from the document `text`, see the module
+/// documentation for what is checked.
+pub fn read_spec<E: Engine>(curve: &str, text: &str) -> Result<CeremonyParams<E>, Error> {
+    let invalid = |message: String| {
+        Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            message,
+        )
+    };
+
+    let text = text.trim_end();
+    let prefix = format!("{{\"format\":\"{}\",\"version\":", FORMAT);
+    let rest = text
+        .strip_prefix(prefix.as_str())
+        .ok_or_else(|| invalid("not a ceremony spec".to_string()))?;
+    let version = rest
+        .split(',')
+        .next()
+        .and_then(|version| version.parse::<u32>().ok())
+        .ok_or_else(|| invalid("the spec has no version".to_string()))?;
+    if version != VERSION {
+        return Err(invalid(format!("unsupported spec version {}", version))
+            .with_hint("export the spec again with this version of the tools"));
+    }
+    let (spec, hash) = rest
+        .strip_prefix(&format!("{},\"spec\":", version))
+        .and_then(|rest| rest.strip_suffix("\"}"))
+        .and_then(|rest| {
+            let position = rest.rfind(",\"hash\":\"")?;
+            Some((&rest[..position], &rest[position + 9..]))
+        })
+        .ok_or_else(|| invalid("the spec is malformed".to_string()))?;
+    if hex::encode(calculate_hash(spec.as_bytes()).as_slice()) != hash {
+        return Err(Error::new(
+            errors::HASH_MISMATCH,
+            ErrorCategory::InvalidInput,
+            "the hash of the spec doesn't match its contents",
+        )
+        .with_hint("the spec was modified after it was exported"));
+    }
+
+    let spec_curve = value(spec, "name").unwrap_or("");
+    if spec_curve != curve {
+        return Err(invalid(format!(
+            "the spec is for the curve {}, not {}",
+            spec_curve, curve
+        )));
+    }
+    let number = |key: &str| {
+        value(spec, key)
+            .and_then(|number| number.parse::<usize>().ok())
+            .ok_or_else(|| invalid(format!("the spec has no {}", key)))
+    };
+    let mut parameters = CeremonyParams::<E>::validated(number("power")?, number("batch_size")?)?;
+    let generators = value(spec, "generators")
+        .and_then(|generators| hex::decode(generators).ok())
+        .ok_or_else(|| invalid("the spec has no generators".to_string()))?;
+    parameters.generators = Generators::read(&mut &generators[..])
+        .map_err(|e| Error::from(e).context("unable to read the generators of the spec"))?;
+    parameters.curve.subgroup_check = value(spec, "subgroup_check")
+        .ok_or_else(|| invalid("the spec has no subgroup check".to_string()))?
+        .parse()?;
+
+    if spec_json(curve, &parameters) != spec {
+        return Err(invalid(
+            "the spec doesn't describe the files as this version of the tools does".to_string(),
+        )
+        .with_hint("compare the spec with the one exported by this version of the tools"));
+    }
+
+    Ok(parameters)
+}
+
+/// Removes `--spec <file>` from the arguments and returns the parameters of the ceremony on
+/// `curve` read from the file, or `None` if it isn't given.
+pub fn take_spec<E: Engine>(
+    args: &mut Vec<String>,
+    curve: &str,
+) -> Result<Option<CeremonyParams<E>>, Error> {
+    let position = match args.iter().position(|arg| arg == "--spec") {
+        Some(position) => position,
+        None => return Ok(None),
+    };
+    if position + 1 >= args.len() {
+        return Err(Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            "--spec requires a file",
+        ));
+    }
+    let text = fs::read_to_string(&args[position + 1])
+        .map_err(|e| Error::from(e).context("unable to read the spec file"))?;
+    let parameters = read_spec(curve, &text)?;
+    args.drain(position..position + 2);
+
+    Ok(Some(parameters))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parameters::SubgroupCheckMode;
+    use bellman_ce::pairing::bn256::Bn256;
+
+    #[test]
+    fn test_spec_roundtrip() {
+        let mut parameters = CeremonyParams::<Bn256>::new(3, 4)
+            .with_generators(Generators::derive(b"test ceremony"));
+        parameters.curve.subgroup_check = SubgroupCheckMode::FullOrder;
+        let spec = export_spec("bn256", &parameters);
+        assert_eq!(spec.lines().count(), 1);
+        assert!(spec.contains("\"power\":3,\"batch_size\":4,"));
+        assert!(spec.contains("\"chunks\":{\"count\":4,"));
+        // The response ends with the public key
+        let response = expected_sizes(&parameters, UseCompression::Yes).response;
+        assert!(spec.contains(&format!(
+            "{{\"name\":\"public_key\",\"offset\":{},\"length\":{},",
+            response - parameters.public_key_size,
+            parameters.public_key_size
+        )));
+
+        let read = read_spec::<Bn256>("bn256", &spec).unwrap();
+        assert_eq!((read.size, read.batch_size), (3, 4));
+        assert!(read.generators == parameters.generators);
+        assert_eq!(read.curve.subgroup_check, SubgroupCheckMode::FullOrder);
+        assert!(read_spec::<Bn256>("bls12_381", &spec).is_err());
+    }
+
+    #[test]
+    fn test_spec_rejects_modifications() {
+        let parameters = CeremonyParams::<Bn256>::new(2, 3);
+        let spec = export_spec("bn256", &parameters);
+
+        let tampered = spec.replace("\"g1_size\":64,", "\"g1_size\":65,");
+        assert_ne!(tampered, spec);
+        let error = read_spec::<Bn256>("bn256", &tampered).unwrap_err();
+        assert_eq!(error.code, errors::HASH_MISMATCH);
+
+        // A consistent hash over a layout that this version doesn't write
+        let body = spec_json("bn256", &parameters).replace("\"overlap\":1", "\"overlap\":0");
+        let rehashed = format!(
+            "{{\"format\":\"{}\",\"version\":{},\"spec\":{},\"hash\":\"{}\"}}",
+            FORMAT,
+            VERSION,
+            body,
+            hex::encode(calculate_hash(body.as_bytes()).as_slice())
+        );
+        let error = read_spec::<Bn256>("bn256", &rehashed).unwrap_err();
+        assert_eq!(error.code, errors::INVALID_ARGUMENT);
+
+        let newer = spec.replacen("\"version\":1", "\"version\":2", 1);
+        assert!(read_spec::<Bn256>("bn256", &newer).is_err());
+    }
+
+    #[test]
+    fn test_take_spec() {
+        let parameters = CeremonyParams::<Bn256>::new(2, 3);
+        let path = std::env::temp_dir().join(format!("spec_{}.json", std::process::id()));
+        fs::write(&path, export_spec("bn256", &parameters)).unwrap();
+
+        let mut args = vec![
+            "verify".to_string(),
+            "--spec".to_string(),
+            path.display().to_string(),
+            "challenge".to_string(),
+        ];
+        let read = take_spec::<Bn256>(&mut args, "bn256").unwrap().unwrap();
+        assert_eq!(read.accumulator_size, parameters.accumulator_size);
+        assert_eq!(args, vec!["verify".to_string(), "challenge".to_string()]);
+        assert!(take_spec::<Bn256>(&mut args, "bn256").unwrap().is_none());
+        fs::remove_file(&path).unwrap();
+    }
+}
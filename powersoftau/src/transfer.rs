@@ -0,0 +1,661 @@
+/// Downloads and uploads of ceremony files over HTTP, so that contributors don't need other
+/// tools to move challenges and responses. Downloads are split in parts that are fetched in
+/// parallel with range requests. The parts that are complete are recorded next to the
+/// partial file, so an interrupted download resumes where it stopped, and the file is hashed
+/// while the parts arrive. Failed requests are retried with exponential backoff.
+use blake2::{Blake2b, Digest};
+use generic_array::GenericArray;
+use std::collections::{BTreeSet, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::Duration;
+use typenum::consts::U64;
+
+use super::errors::{self, Error, ErrorCategory};
+use super::hash_file::{read_hash_file, take_expected_hash};
+use super::http::{self, content_md5, Url};
+
+#[derive(Clone, Debug)]
+pub struct TransferOptions {
+    /// Parts downloaded at the same time
+    pub connections: usize,
+    /// Size in bytes of the parts of a download
+    pub part_size: u64,
+    /// Attempts of a request after the first one fails
+    pub retries: u32,
+    /// Delay before the first retry, doubled for each of the next ones
+    pub backoff: Duration,
+    /// The BLAKE2b hash that the file must have
+    pub expected_hash: Option<[u8; 64]>,
+}
+
+impl Default for TransferOptions {
+    fn default() -> Self {
+        TransferOptions {
+            connections: 4,
+            part_size: 64 << 20,
+            retries: 5,
+            backoff: Duration::from_secs(1),
+            expected_hash: None,
+        }
+    }
+}
+
+/// Removes `--connections <n>`, `--part-size <MiB>`, `--retries <n>` and
+/// `--expected-hash <file>` from the arguments and returns the options they select.
+pub fn take_transfer_options(args: &mut Vec<String>) -> Result<TransferOptions, Error> {
+    let mut options = TransferOptions::default();
+    if let Some(path) = take_expected_hash(args)? {
+        options.expected_hash = Some(read_hash_file(path)?);
+    }
+
+    let mut take = |flag: &str| -> Result<Option<u64>, Error> {
+        let position = match args.iter().position(|arg| arg == flag) {
+            Some(position) => position,
+            None => return Ok(None),
+        };
+        let value = args
+            .get(position + 1)
+            .and_then(|value| value.parse().ok())
+            .filter(|&value| value > 0)
+            .ok_or_else(|| {
+                Error::new(
+                    errors::INVALID_ARGUMENT,
+                    ErrorCategory::InvalidInput,
+                    format!("{} requires a positive number", flag),
+                )
+            })?;
+        args.drain(position..position + 2);
+
+        Ok(Some(value))
+    };
+    if let Some(connections) = take("--connections")? {
+        options.connections = connections as usize;
+    }
+    if let Some(part_size) = take("--part-size")? {
+        options.part_size = part_size << 20;
+    }
+    if let Some(retries) = take("--retries")? {
+        options.retries = retries as u32;
+    }
+
+    Ok(options)
+}
+
+/// The longest delay between two attempts of a request.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+fn parse_url(url: &str) -> Result<Url, Error> {
+    Url::parse(url).map_err(|message| {
+        Error::new(
+            errors::INVALID_ARGUMENT,
+            ErrorCategory::InvalidInput,
+            format!("invalid URL {}: {}", url, message),
+        )
+    })
+}
+
+/// Runs `request` until it succeeds or fails `options.retries` more times. Errors that
+/// another attempt wouldn't fix, like a missing file, are returned right away.
+fn with_retries<T, F>(options: &TransferOptions, what: &str, mut request: F) -> io::Result<T>
+where
+    F: FnMut() -> io::Result<T>,
+{
+    let mut delay = options.backoff;
+    let mut attempt = 0;
+    loop {
+        match request() {
+            Ok(result) => return Ok(result),
+            Err(ref e)
+                if attempt < options.retries
+                    && e.kind() != io::ErrorKind::NotFound
+                    && e.kind() != io::ErrorKind::PermissionDenied =>
+            {
+                attempt += 1;
+                println!(
+                    "{} failed: {}, retrying in {:?} ({}/{})",
+                    what, e, delay, attempt, options.retries
+                );
+                thread::sleep(delay);
+                delay = std::cmp::min(delay * 2, MAX_BACKOFF);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// The length of the file at `url`, and whether the server answers range requests.
+fn probe(url: &Url) -> io::Result<(Option<u64>, bool)> {
+    let response = http::send("HEAD", url, &[], None)?.success()?;
+    let ranges = response
+        .header("accept-ranges")
+        .map(|ranges| ranges.eq_ignore_ascii_case("bytes"))
+        .unwrap_or(false);
+
+    Ok((response.content_length(), ranges))
+}
+
+/// Downloads `range` of the file at `url` to the same offset of `file`.
+fn fetch_range(url: &Url, range: (u64, u64), file: &mut File) -> io::Result<()> {
+    let (start, end) = range;
+    let range = format!("bytes={}-{}", start, end - 1);
+    let response = http::send("GET", url, &[("Range", &range)], None)?.success()?;
+    if response.status != 206 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "expected a partial response, got status {}",
+                response.status
+            ),
+        ));
+    }
+    file.seek(SeekFrom::Start(start))?;
+    let copied = io::copy(&mut response.into_reader().take(end - start), file)?;
+    if copied != end - start {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!(
+                "the connection closed after {} of {} bytes",
+                copied,
+                end - start
+            ),
+        ));
+    }
+
+    file.sync_data()
+}
+
+/// Where the parts of a download are kept until it's complete: the partial file, and the
+/// list of the parts that are in it. The list starts with the length and the part size, so
+/// that a download of another file or with other parts starts over.
+struct Partial {
+    data: PathBuf,
+    progress: PathBuf,
+}
+
+impl Partial {
+    fn new(output: &Path) -> Self {
+        Partial {
+            data: PathBuf::from(format!("{}.part", output.display())),
+            progress: PathBuf::from(format!("{}.part.progress", output.display())),
+        }
+    }
+
+    /// Opens the progress of a download of `length` bytes, and returns the parts that are
+    /// already complete.
+    fn resume(&self, length: u64, part_size: u64) -> io::Result<(File, BTreeSet<u64>)> {
+        let header = format!("{} {}", length, part_size);
+        let mut done = BTreeSet::new();
+        if let Ok(progress) = fs::read_to_string(&self.progress) {
+            // A part is only recorded once its line is complete
+            let complete = &progress[..progress.rfind('\n').map_or(0, |end| end + 1)];
+            let mut lines = complete.lines();
+            if lines.next() == Some(header.as_str()) && self.data.exists() {
+                done = lines.filter_map(|part| part.parse().ok()).collect();
+            }
+        }
+        if done.is_empty() {
+            let _ = fs::remove_file(&self.data);
+            fs::write(&self.progress, format!("{}\n", header))?;
+        }
+        let data = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.data)?;
+        data.set_len(length)?;
+        let progress = OpenOptions::new().append(true).open(&self.progress)?;
+
+        Ok((progress, done))
+    }
+
+    fn remove(&self) {
+        let _ = fs::remove_file(&self.data);
+        let _ = fs::remove_file(&self.progress);
+    }
+}
+
+/// Downloads the file at `url` to `output` and returns its BLAKE2b hash. The download goes to
+/// `<output>.part` and is only renamed to `output` once it's complete and has the expected
+/// hash, if any. An interrupted download is resumed by fetching the same URL to the same
+/// output again. Servers that don't answer range requests are downloaded over a single
+/// connection, which starts over if it's interrupted.
+pub fn fetch(
+    url: &str,
+    output: &Path,
+    options: &TransferOptions,
+) -> Result<GenericArray<u8, U64>, Error> {
+    let endpoint = parse_url(url)?;
+    if output.exists() {
+        return Err(Error::new(
+            errors::OUTPUT_EXISTS,
+            ErrorCategory::Io,
+            format!("{} already exists", output.display()),
+        )
+        .with_hint("remove it or download to another file"));
+    }
+    let partial = Partial::new(output);
+
+    let (length, ranges) = with_retries(options, "checking the file", || probe(&endpoint))
+        .map_err(|e| Error::from(e).context(format!("unable to reach {}", url)))?;
+    let hash = match length {
+        Some(length) if ranges => fetch_parts(&endpoint, length, &partial, options)?,
+        _ => fetch_whole(&endpoint, &partial, options)?,
+    };
+
+    if let Some(expected) = options.expected_hash {
+        if expected[..] != hash[..] {
+            partial.remove();
+            return Err(Error::new(
+                errors::HASH_MISMATCH,
+                ErrorCategory::Verification,
+                format!(
+                    "the download has hash {}, but {} was expected",
+                    hex::encode(&hash[..]),
+                    hex::encode(&expected[..])
+                ),
+            )
+            .with_hint("check that the URL is the one of the published file"));
+        }
+    }
+    fs::rename(&partial.data, output).map_err(|e| {
+        Error::from(e).context(format!(
+            "unable to move the download to {}",
+            output.display()
+        ))
+    })?;
+    let _ = fs::remove_file(&partial.progress);
+
+    Ok(hash)
+}
+
+/// Downloads the file over a single connection, hashing it as it is written.
+fn fetch_whole(
+    url: &Url,
+    partial: &Partial,
+    options: &TransferOptions,
+) -> Result<GenericArray<u8, U64>, Error> {
+    println!("The server doesn't answer range requests, downloading over a single connection");
+    let download = || -> io::Result<GenericArray<u8, U64>> {
+        let response = http::send("GET", url, &[], None)?.success()?;
+        let expected_length = response.content_length();
+        let mut reader = response.into_reader();
+        let mut file = File::create(&partial.data)?;
+        let mut hasher = Blake2b::default();
+        let mut buffer = vec![0; 1 << 20];
+        let mut copied = 0;
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.input(&buffer[..read]);
+            file.write_all(&buffer[..read])?;
+            copied += read as u64;
+        }
+        if expected_length
+            .map(|length| length != copied)
+            .unwrap_or(false)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the connection closed before the end of the file",
+            ));
+        }
+        file.sync_all()?;
+
+        Ok(hasher.result())
+    };
+
+    with_retries(options, "the download", download)
+        .map_err(|e| Error::from(e).context("unable to download the file"))
+}
+
+/// Downloads the parts of the file that aren't already in `partial` on `options.connections`
+/// connections. The parts are hashed in order as soon as the ones before them are complete.
+fn fetch_parts(
+    url: &Url,
+    length: u64,
+    partial: &Partial,
+    options: &TransferOptions,
+) -> Result<GenericArray<u8, U64>, Error> {
+    let part_size = options.part_size;
+    let part_count = length.div_ceil(part_size);
+    let part_range = |part: u64| {
+        (
+            part * part_size,
+            std::cmp::min((part + 1) * part_size, length),
+        )
+    };
+    let (mut progress, mut done) = partial
+        .resume(length, part_size)
+        .map_err(|e| Error::from(e).context("unable to prepare the partial download"))?;
+    if !done.is_empty() {
+        println!(
+            "Resuming the download, {} of {} parts are complete",
+            done.len(),
+            part_count
+        );
+    }
+
+    let pending = Mutex::new(
+        (0..part_count)
+            .filter(|part| !done.contains(part))
+            .collect::<VecDeque<_>>(),
+    );
+    let failed = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    let mut hasher = Blake2b::default();
+    let mut hashed = 0;
+    // Hashes the parts that follow the ones already hashed and are complete
+    let mut hash_complete = |done: &BTreeSet<u64>| -> io::Result<()> {
+        let mut data = File::open(&partial.data)?;
+        while hashed < part_count && done.contains(&hashed) {
+            let (start, end) = part_range(hashed);
+            let mut part = vec![0; (end - start) as usize];
+            data.seek(SeekFrom::Start(start))?;
+            data.read_exact(&mut part)?;
+            hasher.input(&part);
+            hashed += 1;
+        }
+        Ok(())
+    };
+
+    let mut result = hash_complete(&done);
+    crossbeam::scope(|scope| {
+        for _ in 0..std::cmp::max(options.connections, 1) {
+            let sender = sender.clone();
+            let (pending, failed) = (&pending, &failed);
+            scope.spawn(move |_| {
+                let mut file = match OpenOptions::new().write(true).open(&partial.data) {
+                    Ok(file) => file,
+                    Err(e) => return sender.send(Err(e)).unwrap(),
+                };
+                while !failed.load(Ordering::SeqCst) {
+                    let part = match pending.lock().unwrap().pop_front() {
+                        Some(part) => part,
+                        None => return,
+                    };
+                    let what = format!("part {} of {}", part + 1, part_count);
+                    let fetched = with_retries(options, &what, || {
+                        fetch_range(url, part_range(part), &mut file)
+                    });
+                    sender.send(fetched.map(|_| part)).unwrap();
+                }
+            });
+        }
+        drop(sender);
+
+        for fetched in receiver.iter() {
+            let recorded = fetched.and_then(|part| {
+                writeln!(progress, "{}", part)?;
+                done.insert(part);
+                hash_complete(&done)
+            });
+            match recorded {
+                Ok(()) => println!("{} of {} parts downloaded", done.len(), part_count),
+                Err(e) => {
+                    // The parts that are being downloaded are still recorded
+                    failed.store(true, Ordering::SeqCst);
+                    if result.is_ok() {
+                        result = Err(e);
+                    }
+                }
+            }
+        }
+    })
+    .unwrap();
+    result.map_err(|e| {
+        Error::from(e).context("unable to download the file, run the same command to resume")
+    })?;
+    if hashed != part_count {
+        return Err(Error::new(
+            errors::INTERNAL,
+            ErrorCategory::Internal,
+            "the download finished with parts missing",
+        ));
+    }
+
+    Ok(hasher.result())
+}
+
+/// Uploads `file` to `url` with a `PUT` request. The request carries the MD5 digest of the
+/// file in `Content-MD5`, so that servers which check it reject a corrupted upload, and is
+/// retried like the parts of a download.
+pub fn upload(file: &Path, url: &str, options: &TransferOptions) -> Result<(), Error> {
+    let endpoint = parse_url(url)?;
+    let open = || {
+        File::open(file)
+            .map_err(|e| Error::from(e).context(format!("unable to open {}", file.display())))
+    };
+    let length = open()?
+        .metadata()
+        .map_err(|e| Error::from(e).context("unable to get filesystem metadata"))?
+        .len();
+    let md5 = content_md5(&mut open()?)
+        .map_err(|e| Error::from(e).context(format!("unable to read {}", file.display())))?;
+
+    let send = || -> io::Result<()> {
+        let mut body = File::open(file)?;
+        let headers = [("Content-MD5", md5.as_str())];
+        http::send("PUT", &endpoint, &headers, Some((&mut body, length)))?.success()?;
+        Ok(())
+    };
+
+    with_retries(options, "the upload", send).map_err(|e| {
+        Error::from(e).context(format!("unable to upload {} to {}", file.display(), url))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+
+    /// What the test server was asked for, and how it answers.
+    #[derive(Default)]
+    struct Server {
+        data: Vec<u8>,
+        ranges: bool,
+        /// GET requests that are cut in the middle of the body
+        failures: usize,
+        requests: Vec<String>,
+        uploads: Vec<(Vec<u8>, String)>,
+    }
+
+    fn serve(server: Server) -> (String, Arc<Mutex<Server>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/challenge", listener.local_addr().unwrap());
+        let server = Arc::new(Mutex::new(server));
+        let shared = server.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                let mut line = String::new();
+                let mut range = None;
+                let (mut length, mut md5) = (0, String::new());
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    let lower = line.to_lowercase();
+                    if let Some(value) = lower.strip_prefix("range: bytes=") {
+                        let mut bounds = value.trim().split('-').map(|b| b.parse().unwrap());
+                        range = Some((bounds.next().unwrap(), bounds.next().unwrap() + 1));
+                    } else if let Some(value) = lower.strip_prefix("content-length: ") {
+                        length = value.trim().parse().unwrap();
+                    } else if lower.starts_with("content-md5: ") {
+                        md5 = line[13..].trim().to_string();
+                    }
+                    if request.is_empty() {
+                        request = line.trim().to_string();
+                    }
+                    line.clear();
+                }
+
+                let mut server = shared.lock().unwrap();
+                server.requests.push(format!("{} {:?}", request, range));
+                if request.starts_with("PUT") {
+                    let mut body = vec![0; length];
+                    reader.read_exact(&mut body).unwrap();
+                    server.uploads.push((body, md5));
+                    stream
+                        .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                        .unwrap();
+                    continue;
+                }
+                let accept = if server.ranges {
+                    "Accept-Ranges: bytes\r\n"
+                } else {
+                    ""
+                };
+                let (status, (start, end)) = match range {
+                    Some(range) if server.ranges => ("206 Partial Content", range),
+                    _ => ("200 OK", (0, server.data.len())),
+                };
+                let head = format!(
+                    "HTTP/1.1 {}\r\n{}Content-Length: {}\r\n\r\n",
+                    status,
+                    accept,
+                    end - start
+                );
+                stream.write_all(head.as_bytes()).unwrap();
+                if request.starts_with("GET") {
+                    let cut = server.failures > 0;
+                    if cut {
+                        server.failures -= 1;
+                    }
+                    let body = &server.data[start..end];
+                    let body = if cut { &body[..body.len() / 2] } else { body };
+                    stream.write_all(body).unwrap();
+                }
+            }
+        });
+
+        (url, server)
+    }
+
+    fn data() -> Vec<u8> {
+        (0..1000u32).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    fn options() -> TransferOptions {
+        TransferOptions {
+            connections: 3,
+            part_size: 100,
+            backoff: Duration::from_millis(1),
+            ..TransferOptions::default()
+        }
+    }
+
+    fn output(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("powersoftau-transfer-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let output = directory.join(name);
+        let _ = fs::remove_file(&output);
+        output
+    }
+
+    #[test]
+    fn test_fetch() {
+        let expected = Blake2b::digest(&data());
+        let (url, server) = serve(Server {
+            data: data(),
+            ranges: true,
+            failures: 2,
+            ..Server::default()
+        });
+        let output = output("challenge");
+        let mut options = options();
+        let mut expected_hash = [0; 64];
+        expected_hash.copy_from_slice(&expected);
+        options.expected_hash = Some(expected_hash);
+
+        let hash = fetch(&url, &output, &options).unwrap();
+        assert_eq!(hash, expected);
+        assert_eq!(fs::read(&output).unwrap(), data());
+        assert!(!Partial::new(&output).data.exists());
+        assert!(!Partial::new(&output).progress.exists());
+        // A HEAD, 10 parts and the 2 retries
+        assert_eq!(server.lock().unwrap().requests.len(), 13);
+
+        let error = fetch(&url, &output, &options).unwrap_err();
+        assert_eq!(error.code, errors::OUTPUT_EXISTS);
+        fs::remove_file(&output).unwrap();
+
+        // The download is rejected, and not left for resuming
+        options.expected_hash = Some([1; 64]);
+        let error = fetch(&url, &output, &options).unwrap_err();
+        assert_eq!(error.code, errors::HASH_MISMATCH);
+        assert!(!output.exists());
+        assert!(!Partial::new(&output).data.exists());
+
+        // Without range requests, the file is downloaded at once
+        let (url, _) = serve(Server {
+            data: data(),
+            ..Server::default()
+        });
+        options.expected_hash = None;
+        assert_eq!(fetch(&url, &output, &options).unwrap(), expected);
+        assert_eq!(fs::read(&output).unwrap(), data());
+        fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn test_resume() {
+        let (url, server) = serve(Server {
+            data: data(),
+            ranges: true,
+            ..Server::default()
+        });
+        let output = output("response");
+        let partial = Partial::new(&output);
+        let mut previous = data();
+        previous[400..].iter_mut().for_each(|byte| *byte = 0);
+        fs::write(&partial.data, &previous).unwrap();
+        // The line of the fourth part was cut when the download was interrupted
+        fs::write(&partial.progress, "1000 100\n0\n2\n1\n3").unwrap();
+
+        let hash = fetch(&url, &output, &options()).unwrap();
+        assert_eq!(hash, Blake2b::digest(&data()));
+        assert_eq!(fs::read(&output).unwrap(), data());
+        let requests = server.lock().unwrap().requests.clone();
+        assert_eq!(requests.len(), 1 + 7);
+        assert!(requests
+            .iter()
+            .all(|request| !request.contains("(0, 100)") && !request.contains("(200, 300)")));
+        assert!(requests
+            .iter()
+            .any(|request| request.contains("(300, 400)")));
+        fs::remove_file(&output).unwrap();
+
+        // The progress of a download with other parts is discarded
+        fs::write(&partial.data, &previous).unwrap();
+        fs::write(&partial.progress, "1000 200\n0\n").unwrap();
+        server.lock().unwrap().requests.clear();
+        assert_eq!(fetch(&url, &output, &options()).unwrap(), hash);
+        assert_eq!(server.lock().unwrap().requests.len(), 1 + 10);
+        fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn test_upload() {
+        let (url, server) = serve(Server::default());
+        let file = output("upload");
+        fs::write(&file, data()).unwrap();
+
+        upload(&file, &url, &options()).unwrap();
+        let (body, md5) = server.lock().unwrap().uploads.pop().unwrap();
+        assert_eq!(body, data());
+        assert_eq!(md5, content_md5(&mut &data()[..]).unwrap());
+        fs::remove_file(&file).unwrap();
+
+        assert!(upload(&file, "ftp://example.com/response", &options()).is_err());
+    }
+}
@@ -0,0 +1,318 @@
+/// Tree hash of a challenge or response file. The file is split into leaves of `leaf_size`
+/// bytes (the last one may be shorter), every leaf is hashed on its own and the root hashes
+/// the layout together with all leaf hashes:
+///
+/// leaf<sub>i</sub> = BLAKE2b(0x00 | bytes of leaf i)
+///
+/// root = BLAKE2b(0x01 | leaf_size (u64, BE) | length (u64, BE) | leaf<sub>0</sub> | leaf<sub>1</sub> | ...)
+///
+/// Leaves are hashed in parallel, and a downloaded byte range can be checked against the
+/// manifest (the list of leaf hashes) without the rest of the file. The tree hash is only used
+/// for transport integrity; the hash chain of the ceremony keeps using the plain BLAKE2b hash.
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use std::io::{self, Read, Write};
+use std::ops::Range;
+
+/// Identifies a tree hash manifest file, followed by the format version.
+const MAGIC: &[u8; 8] = b"POTTREEH";
+const VERSION: u32 = 1;
+
+const LEAF_PREFIX: u8 = 0;
+const ROOT_PREFIX: u8 = 1;
+
+/// Leaf size used by the command line tools unless another one is given.
+pub const DEFAULT_LEAF_SIZE: u64 = 1 << 26;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeHash {
+    /// Size of every leaf but the last one
+    pub leaf_size: u64,
+    /// Length of the hashed file
+    pub length: u64,
+    /// Hashes of the leaves, in file order
+    pub leaves: Vec<[u8; 64]>,
+}
+
+pub(crate) fn hash_leaf(leaf: &[u8]) -> [u8; 64] {
+    let mut hasher = Blake2b::default();
+    hasher.input([LEAF_PREFIX]);
+    hasher.input(leaf);
+    let mut hash = [0; 64];
+    hash.copy_from_slice(hasher.result().as_slice());
+    hash
+}
+
+impl TreeHash {
+    /// Hashes `data` with leaves of `leaf_size` bytes, spreading the leaves over all cores.
+    pub fn compute(data: &[u8], leaf_size: u64) -> Self {
+        assert!(leaf_size > 0, "leaf size must be positive");
+        let leaves = hash_leaves(data, leaf_size, data.len() as u64)
+            .into_iter()
+            .map(|leaf| leaf.expect("all leaves are in the data"))
+            .collect();
+
+        TreeHash {
+            leaf_size,
+            length: data.len() as u64,
+            leaves,
+        }
+    }
+
+    pub(crate) fn leaf_count_for(length: u64, leaf_size: u64) -> usize {
+        length.div_ceil(leaf_size) as usize
+    }
+
+    /// Root of the tree, which is what gets published next to the plain hash of the file.
+    pub fn root(&self) -> [u8; 64] {
+        let mut hasher = Blake2b::default();
+        hasher.input([ROOT_PREFIX]);
+        hasher.input(self.leaf_size.to_be_bytes());
+        hasher.input(self.length.to_be_bytes());
+        for leaf in &self.leaves {
+            hasher.input(&leaf[..]);
+        }
+        let mut root = [0; 64];
+        root.copy_from_slice(hasher.result().as_slice());
+        root
+    }
+
+    /// Byte range of the file covered by leaf `index`.
+    pub fn leaf_range(&self, index: usize) -> Range<usize> {
+        assert!(index < self.leaves.len(), "leaf index out of range");
+        leaf_range(index, self.leaf_size, self.length)
+    }
+
+    /// Checks the bytes of leaf `index` against the manifest.
+    pub fn verify_leaf(&self, index: usize, leaf: &[u8]) -> bool {
+        index < self.leaves.len()
+            && leaf.len() == self.leaf_range(index).len()
+            && hash_leaf(leaf) == self.leaves[index]
+    }
+
+    /// Checks a downloaded byte range starting at `offset` against the manifest. The range
+    /// must start on a leaf boundary and may end anywhere; only the leaves it fully covers are
+    /// checked. Returns the indices of the checked leaves, or `None` if one of them is invalid.
+    pub fn verify_range(&self, offset: u64, bytes: &[u8]) -> Option<Range<usize>> {
+        if !offset.is_multiple_of(self.leaf_size) || offset > self.length {
+            return None;
+        }
+        let first = (offset / self.leaf_size) as usize;
+        let mut index = first;
+        let mut position = 0;
+        while index < self.leaves.len() {
+            let length = self.leaf_range(index).len();
+            if position + length > bytes.len() {
+                break;
+            }
+            if !self.verify_leaf(index, &bytes[position..position + length]) {
+                return None;
+            }
+            position += length;
+            index += 1;
+        }
+
+        Some(first..index)
+    }
+
+    /// Checks a partially downloaded file against the manifest. Leaves that are entirely
+    /// present in `data` and match their hash are valid, everything else has to be downloaded
+    /// (again). Bytes of `data` past the length in the manifest are ignored.
+    pub fn check_download(&self, data: &[u8]) -> DownloadReport {
+        let hashes = hash_leaves(data, self.leaf_size, self.length);
+        let mut report = DownloadReport {
+            valid_leaves: vec![],
+            missing: vec![],
+        };
+        for (index, hash) in hashes.iter().enumerate() {
+            if hash.as_ref() == Some(&self.leaves[index]) {
+                report.valid_leaves.push(index);
+                continue;
+            }
+            let range = self.leaf_range(index);
+            let range = range.start as u64..range.end as u64;
+            match report.missing.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => report.missing.push(range),
+            }
+        }
+
+        report
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&MAGIC[..])?;
+        writer.write_u32::<BigEndian>(VERSION)?;
+        writer.write_u64::<BigEndian>(self.leaf_size)?;
+        writer.write_u64::<BigEndian>(self.length)?;
+        for leaf in &self.leaves {
+            writer.write_all(&leaf[..])?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a tree hash manifest",
+            ));
+        }
+        let version = reader.read_u32::<BigEndian>()?;
+        if version != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported tree hash manifest version {}", version),
+            ));
+        }
+
+        let leaf_size = reader.read_u64::<BigEndian>()?;
+        if leaf_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "leaf size must be positive",
+            ));
+        }
+        let length = reader.read_u64::<BigEndian>()?;
+        // The length is untrusted, so leaves are only allocated as they are read
+        let mut leaves = vec![];
+        for _ in 0..Self::leaf_count_for(length, leaf_size) {
+            let mut leaf = [0; 64];
+            reader.read_exact(&mut leaf)?;
+            leaves.push(leaf);
+        }
+
+        Ok(TreeHash {
+            leaf_size,
+            length,
+            leaves,
+        })
+    }
+}
+
+/// Result of checking a partially downloaded file against a manifest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DownloadReport {
+    /// Indices of the leaves that were downloaded correctly
+    pub valid_leaves: Vec<usize>,
+    /// Byte ranges that still have to be downloaded, merged when adjacent
+    pub missing: Vec<Range<u64>>,
+}
+
+impl DownloadReport {
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    /// Number of bytes that still have to be downloaded.
+    pub fn missing_bytes(&self) -> u64 {
+        self.missing
+            .iter()
+            .map(|range| range.end - range.start)
+            .sum()
+    }
+}
+
+/// Hashes the leaves of a file of `length` bytes that are entirely present in `data`, spreading
+/// the leaves over all cores. Leaves that are not entirely present are `None`.
+pub(crate) fn hash_leaves(data: &[u8], leaf_size: u64, length: u64) -> Vec<Option<[u8; 64]>> {
+    let leaf_count = TreeHash::leaf_count_for(length, leaf_size);
+    let mut leaves = vec![None; leaf_count];
+    if leaf_count == 0 {
+        return leaves;
+    }
+    let leaves_per_thread = leaf_count.div_ceil(num_cpus::get());
+
+    crossbeam::scope(|scope| {
+        for (i, leaves) in leaves.chunks_mut(leaves_per_thread).enumerate() {
+            scope.spawn(move |_| {
+                let first = i * leaves_per_thread;
+                for (j, leaf) in leaves.iter_mut().enumerate() {
+                    let range = leaf_range(first + j, leaf_size, length);
+                    *leaf = data.get(range).map(hash_leaf);
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    leaves
+}
+
+pub(crate) fn leaf_range(index: usize, leaf_size: u64, length: u64) -> Range<usize> {
+    let start = index as u64 * leaf_size;
+    let end = std::cmp::min(start.saturating_add(leaf_size), length);
+    start as usize..end as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+
+    fn random_bytes(length: usize) -> Vec<u8> {
+        let rng = &mut thread_rng();
+        (0..length).map(|_| rng.gen()).collect()
+    }
+
+    #[test]
+    fn test_tree_hash_layout() {
+        let data = random_bytes(1000);
+        let tree = TreeHash::compute(&data, 64);
+        assert_eq!(tree.leaves.len(), 16);
+        assert_eq!(tree.leaf_range(15), 960..1000);
+
+        // Leaves hashed in parallel are the ones of a serial pass
+        for (i, leaf) in data.chunks(64).enumerate() {
+            assert_eq!(&tree.leaves[i][..], &hash_leaf(leaf)[..]);
+        }
+
+        // The layout is part of the root
+        assert!(tree.root()[..] != TreeHash::compute(&data, 128).root()[..]);
+    }
+
+    #[test]
+    fn test_tree_hash_verify_range() {
+        let mut data = random_bytes(1000);
+        let tree = TreeHash::compute(&data, 64);
+
+        assert_eq!(tree.verify_range(128, &data[128..500]), Some(2..7));
+        assert_eq!(tree.verify_range(960, &data[960..]), Some(15..16));
+        assert_eq!(tree.verify_range(100, &data[100..500]), None);
+
+        data[300] ^= 1;
+        assert_eq!(tree.verify_range(128, &data[128..500]), None);
+        assert_eq!(tree.verify_range(320, &data[320..]), Some(5..16));
+    }
+
+    #[test]
+    fn test_check_download() {
+        let data = random_bytes(1000);
+        let tree = TreeHash::compute(&data, 64);
+
+        let report = tree.check_download(&data);
+        assert!(report.is_complete());
+        assert_eq!(report.valid_leaves.len(), 16);
+
+        // Interrupted in the middle of leaf 7, with leaves 2 and 3 corrupted
+        let mut partial = data[..500].to_vec();
+        partial[130] ^= 1;
+        partial[200] ^= 1;
+        let report = tree.check_download(&partial);
+        assert_eq!(report.valid_leaves, vec![0, 1, 4, 5, 6]);
+        assert_eq!(report.missing, vec![128..256, 448..1000]);
+        assert_eq!(report.missing_bytes(), 128 + 552);
+    }
+
+    #[test]
+    fn test_tree_hash_serialization() {
+        let tree = TreeHash::compute(&random_bytes(1000), 64);
+        let mut v = vec![];
+        tree.write(&mut v).unwrap();
+        assert_eq!(TreeHash::read(&mut &v[..]).unwrap(), tree);
+    }
+}
@@ -1,78 +1,107 @@
 use bellman_ce::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
 use bellman_ce::pairing::*;
 use blake2::{Blake2b, Digest};
-use byteorder::{BigEndian, ReadBytesExt};
 use generic_array::GenericArray;
-use rand::chacha::ChaChaRng;
-use rand::{Rand, Rng, SeedableRng};
+use rand::Rand;
 
-use memmap::Mmap;
-use std::io::{self, Write};
-use std::sync::Arc;
+use std::io::{self, Read, Write};
+use std::ops::Range;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 use typenum::consts::U64;
 
+use super::metrics::{self, Metric};
 use super::parameters::UseCompression;
+use super::profiling::{self, Operation};
+pub use verification_core::{compute_g2_s, hash_to_g2, RatioBatch};
 
 /// Calculate the contribution hash from the resulting file. Original powers of tau implementation
 /// used a specially formed writer to write to the file and calculate a hash on the fly, but memory-constrained
 /// implementation now writes without a particular order, so plain recalculation at the end
 /// of the procedure is more efficient
-pub fn calculate_hash(input_map: &Mmap) -> GenericArray<u8, U64> {
+///
+/// BLAKE2b of a single stream can't be split across threads, so the hash of a file is computed
+/// with `blake2b_simd`, which compresses with AVX2 where the CPU has it. The hash is the same as
+/// the one of the `blake2` crate used elsewhere; `examples/hash_throughput.rs` compares both.
+pub fn calculate_hash(input_map: &[u8]) -> GenericArray<u8, U64> {
     let chunk_size = 1 << 30; // read by 1GB from map
-    let mut hasher = Blake2b::default();
+    let mut state = blake2b_simd::State::new();
     for chunk in input_map.chunks(chunk_size) {
-        hasher.input(&chunk);
+        state.update(chunk);
+    }
+    GenericArray::clone_from_slice(state.finalize().as_bytes())
+}
+
+/// Same as `calculate_hash`, for the contents of `reader`. It's read in blocks, so that any
+/// size of file can be hashed in constant memory.
+pub fn calculate_hash_of_reader<R: Read>(reader: &mut R) -> io::Result<GenericArray<u8, U64>> {
+    let mut state = blake2b_simd::State::new();
+    let mut buffer = vec![0; 1 << 20];
+    loop {
+        match reader.read(&mut buffer)? {
+            0 => return Ok(GenericArray::clone_from_slice(state.finalize().as_bytes())),
+            read => {
+                state.update(&buffer[..read]);
+            }
+        }
     }
-    hasher.result()
 }
 
-/// Hashes to G2 using the first 32 bytes of `digest`. Panics if `digest` is less
-/// than 32 bytes. The input must be random.
-pub fn hash_to_g2<E: Engine>(mut digest: &[u8]) -> E::G2 {
-    assert!(digest.len() >= 32);
+/// Length of the hash that challenges and responses start with.
+pub const HASH_HEADER_SIZE: usize = 64;
+
+/// Reads the hash that an accumulator file starts with: the hash of the file it was computed
+/// from, or `blank_hash` for the first challenge of a ceremony. No tool writes a header of
+/// zeros, so one means that the file was allocated and never written, and is an error.
+pub fn read_hash_header(map: &[u8]) -> io::Result<[u8; HASH_HEADER_SIZE]> {
+    if map.len() < HASH_HEADER_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("the file is {} bytes, too short for a hash header", map.len()),
+        ));
+    }
+    let mut hash = [0; HASH_HEADER_SIZE];
+    hash.copy_from_slice(&map[0..HASH_HEADER_SIZE]);
+    if hash.iter().all(|&byte| byte == 0) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the hash header is all zeros, the file was never written",
+        ));
+    }
 
-    let mut seed = Vec::with_capacity(8);
+    Ok(hash)
+}
 
-    for _ in 0..8 {
-        seed.push(
-            digest
-                .read_u32::<BigEndian>()
-                .expect("assertion above guarantees this to work"),
-        );
+/// Writes `hash` at the start of an accumulator file. The map isn't flushed.
+pub fn write_hash_header(map: &mut [u8], hash: &[u8]) -> io::Result<()> {
+    if hash.len() != HASH_HEADER_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("a hash header is {} bytes, not {}", HASH_HEADER_SIZE, hash.len()),
+        ));
+    }
+    if map.len() < HASH_HEADER_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("the file is {} bytes, too short for a hash header", map.len()),
+        ));
     }
+    map[0..HASH_HEADER_SIZE].copy_from_slice(hash);
 
-    ChaChaRng::from_seed(&seed).gen()
+    Ok(())
+}
+
+/// Whether `hash` is the header of the first challenge of a ceremony, see `read_hash_header`.
+pub fn is_fresh_hash_header(hash: &[u8]) -> bool {
+    hash == blank_hash().as_slice()
 }
 
 #[cfg(test)]
 mod bn256_tests {
     use super::*;
-    use bellman_ce::pairing::bn256::{Bn256, Fr, G1Affine, G2Affine};
+    use bellman_ce::pairing::bn256::{Fr, G1Affine, G2Affine};
     use rand::{thread_rng, Rand};
 
-    #[test]
-    fn test_hash_to_g2_bn256() {
-        assert!(
-            hash_to_g2::<Bn256>(&[
-                1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
-                24, 25, 26, 27, 28, 29, 30, 31, 32, 33
-            ]) == hash_to_g2::<Bn256>(&[
-                1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
-                24, 25, 26, 27, 28, 29, 30, 31, 32, 34
-            ])
-        );
-
-        assert!(
-            hash_to_g2::<Bn256>(&[
-                1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
-                24, 25, 26, 27, 28, 29, 30, 31, 32
-            ]) != hash_to_g2::<Bn256>(&[
-                1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
-                24, 25, 26, 27, 28, 29, 30, 31, 33
-            ])
-        );
-    }
-
     #[test]
     fn test_same_ratio_bn256() {
         let rng = &mut thread_rng();
@@ -109,7 +138,9 @@ mod bn256_tests {
     }
 }
 
-fn merge_pairs<E: Engine, G: CurveAffine<Engine = E, Scalar = E::Fr>>(
+/// Construct a single pair (s, s^x) out of pairs (v1[i], v2[i]) that are all expected to
+/// have the ratio x, with a random linear combination.
+pub fn merge_pairs<E: Engine, G: CurveAffine<Engine = E, Scalar = E::Fr>>(
     v1: &[G],
     v2: &[G],
 ) -> (G, G) {
@@ -155,7 +186,16 @@ pub fn same_ratio<E: Engine, G1: CurveAffine<Engine = E, Scalar = E::Fr>>(
     if g1.0.is_zero() || g1.1.is_zero() || g2.0.is_zero() || g2.1.is_zero() {
         return false;
     }
-    g1.0.pairing_with(&g2.1) == g1.1.pairing_with(&g2.0)
+    let _span = profiling::span(Operation::PairingCheck, 2);
+    metrics::count(Metric::Pairings, 2);
+    verification_core::same_ratio(g1, g2)
+}
+
+/// Checks the pairs of `batch` with one multi-pairing of four pairs, see `RatioBatch`.
+pub fn check_ratios<E: Engine>(batch: &mut RatioBatch<E>) -> bool {
+    let _span = profiling::span(Operation::PairingCheck, 4);
+    metrics::count(Metric::Pairings, 4);
+    batch.check(&mut rand::thread_rng())
 }
 
 pub fn write_point<W, G>(writer: &mut W, p: &G, compression: UseCompression) -> io::Result<()>
@@ -166,24 +206,13 @@ where
     match compression {
         UseCompression::Yes => writer.write_all(p.into_compressed().as_ref()),
         UseCompression::No => writer.write_all(p.into_uncompressed().as_ref()),
+        UseCompression::Mixed => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "a mixed compression must be resolved for the group of the point",
+        )),
     }
 }
 
-pub fn compute_g2_s<E: Engine>(
-    digest: &[u8],
-    g1_s: &E::G1Affine,
-    g1_s_x: &E::G1Affine,
-    personalization: u8,
-) -> E::G2Affine {
-    let mut h = Blake2b::default();
-    h.input(&[personalization]);
-    h.input(digest);
-    h.input(g1_s.into_uncompressed().as_ref());
-    h.input(g1_s_x.into_uncompressed().as_ref());
-
-    hash_to_g2::<E>(h.result().as_ref()).into_affine()
-}
-
 /// Perform multi-exponentiation. The caller is responsible for ensuring that
 /// the number of bases is the same as the number of exponents.
 #[allow(dead_code)]
@@ -290,3 +319,301 @@ fn dense_multiexp_inner<G: CurveAffine>(
         next_region
     }
 }
+
+/// Calls `action` with the first and last index (inclusive) of consecutive chunks of at most
+/// `batch_size` elements covering `range`, in order, and stops at the first error. This is how
+/// passes over an accumulator process it in batches.
+pub fn iter_chunk<F, Err>(range: Range<usize>, batch_size: usize, mut action: F) -> Result<(), Err>
+where
+    F: FnMut(usize, usize) -> Result<(), Err>,
+{
+    assert!(batch_size > 0, "batch size must be positive");
+    for start in range.clone().step_by(batch_size) {
+        let end = std::cmp::min(start + batch_size, range.end) - 1;
+        action(start, end)?;
+    }
+
+    Ok(())
+}
+
+/// Same as `iter_chunk`, but every chunk is first loaded by `load` on another thread, so that
+/// loading the next chunks overlaps with `action` on the current one. At most `depth` loaded
+/// chunks wait for `action`, which is called in order. Stops at the first error of either.
+pub fn pipeline_chunk<T, L, F, Err>(
+    range: Range<usize>,
+    batch_size: usize,
+    depth: usize,
+    mut load: L,
+    mut action: F,
+) -> Result<(), Err>
+where
+    T: Send,
+    Err: Send,
+    L: FnMut(usize, usize) -> Result<T, Err> + Send,
+    F: FnMut(usize, usize, T) -> Result<(), Err>,
+{
+    assert!(batch_size > 0, "batch size must be positive");
+    crossbeam::scope(|scope| {
+        let (sender, receiver) = mpsc::sync_channel(depth);
+        scope.spawn(move |_| {
+            for start in range.clone().step_by(batch_size) {
+                let end = std::cmp::min(start + batch_size, range.end) - 1;
+                let loaded = load(start, end);
+                let failed = loaded.is_err();
+                // The receiver is gone once `action` failed
+                if sender.send((start, end, loaded)).is_err() || failed {
+                    return;
+                }
+            }
+        });
+
+        for (start, end, loaded) in receiver {
+            action(start, end, loaded?)?;
+        }
+
+        Ok(())
+    })
+    .unwrap()
+}
+
+/// Same as `iter_chunk`, but the chunks are processed on all cores in no particular order.
+/// Returns one of the errors if any chunk fails, after all chunks have been processed.
+/// Fills `output` with copies of `pattern`, whose length must divide the length of `output`.
+/// Every thread fills its part, by copying what it has already filled, so that most of the
+/// copies are large.
+pub fn fill_repeated(output: &mut [u8], pattern: &[u8]) {
+    assert!(
+        !pattern.is_empty() && output.len().is_multiple_of(pattern.len()),
+        "the pattern must fit a whole number of times"
+    );
+    let copies_per_thread = (output.len() / pattern.len()).div_ceil(num_cpus::get());
+    if copies_per_thread == 0 {
+        return;
+    }
+
+    crossbeam::scope(|scope| {
+        for part in output.chunks_mut(copies_per_thread * pattern.len()) {
+            scope.spawn(move |_| {
+                part[..pattern.len()].copy_from_slice(pattern);
+                let mut filled = pattern.len();
+                while filled < part.len() {
+                    let size = std::cmp::min(filled, part.len() - filled);
+                    part.copy_within(0..size, filled);
+                    filled += size;
+                }
+            });
+        }
+    })
+    .unwrap();
+}
+
+/// Seconds since the Unix epoch, for the times recorded in status files and reports.
+pub(crate) fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0)
+}
+
+/// `s` as a JSON string, quoted, with quotes, backslashes and control characters escaped. The
+/// JSON that the crate writes, from status files to webhook bodies, is formatted by hand with it.
+pub(crate) fn json_string(s: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+pub fn par_iter_chunk<F, Err>(range: Range<usize>, batch_size: usize, action: F) -> Result<(), Err>
+where
+    F: Fn(usize, usize) -> Result<(), Err> + Sync,
+    Err: Send,
+{
+    assert!(batch_size > 0, "batch size must be positive");
+    let starts = range.clone().step_by(batch_size).collect::<Vec<_>>();
+    if starts.is_empty() {
+        return Ok(());
+    }
+    let starts_per_thread = starts.len().div_ceil(num_cpus::get());
+    let error = Mutex::new(None);
+
+    crossbeam::scope(|scope| {
+        for starts in starts.chunks(starts_per_thread) {
+            let action = &action;
+            let error = &error;
+            let end_of_range = range.end;
+            scope.spawn(move |_| {
+                for &start in starts {
+                    let end = std::cmp::min(start + batch_size, end_of_range) - 1;
+                    if let Err(e) = action(start, end) {
+                        *error.lock().expect("poisoned") = Some(e);
+                        return;
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    match error.into_inner().expect("poisoned") {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string("a \"b\"\n\\"), "\"a \\\"b\\\"\\u000a\\\\\"");
+        assert_eq!(json_string("\r\t\u{1f}é"), "\"\\u000d\\u0009\\u001fé\"");
+    }
+
+    #[test]
+    fn test_pipeline_chunk() {
+        let mut chunks = vec![];
+        pipeline_chunk::<_, _, _, ()>(
+            3..10,
+            3,
+            1,
+            |start, end| Ok(end - start),
+            |start, end, length| {
+                chunks.push((start, end, length));
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(chunks, vec![(3, 5, 2), (6, 8, 2), (9, 9, 0)]);
+
+        // Errors of either side stop the pipeline
+        let mut actions = 0;
+        let result = pipeline_chunk(
+            0..100,
+            10,
+            2,
+            |start, _| if start == 30 { Err(start) } else { Ok(start) },
+            |_, _, _| {
+                actions += 1;
+                Ok(())
+            },
+        );
+        assert_eq!(result, Err(30));
+        assert_eq!(actions, 3);
+        let mut actions = 0;
+        let result = pipeline_chunk(0..100, 10, 2, |start, _| Ok(start), |start, _, _| {
+            actions += 1;
+            if start == 20 {
+                Err(start)
+            } else {
+                Ok(())
+            }
+        });
+        assert_eq!(result, Err(20));
+        assert_eq!(actions, 3);
+    }
+
+    #[test]
+    fn test_iter_chunk() {
+        let mut chunks = vec![];
+        iter_chunk::<_, ()>(3..10, 3, |start, end| {
+            chunks.push((start, end));
+            Ok(())
+        })
+        .unwrap();
+        // The last chunk has a single element
+        assert_eq!(chunks, vec![(3, 5), (6, 8), (9, 9)]);
+
+        let mut visited = 0;
+        assert_eq!(
+            iter_chunk(0..10, 2, |start, _| {
+                visited += 1;
+                if start == 4 {
+                    Err(start)
+                } else {
+                    Ok(())
+                }
+            }),
+            Err(4)
+        );
+        assert_eq!(visited, 3);
+
+        assert!(iter_chunk::<_, ()>(5..5, 2, |_, _| panic!("empty range")).is_ok());
+    }
+
+    #[test]
+    fn test_par_iter_chunk() {
+        let chunks = Mutex::new(vec![]);
+        par_iter_chunk::<_, ()>(0..100, 7, |start, end| {
+            chunks.lock().unwrap().push((start, end));
+            Ok(())
+        })
+        .unwrap();
+        let mut chunks = chunks.into_inner().unwrap();
+        chunks.sort();
+        let mut expected = vec![];
+        iter_chunk::<_, ()>(0..100, 7, |start, end| {
+            expected.push((start, end));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(chunks, expected);
+
+        assert_eq!(
+            par_iter_chunk(0..100, 7, |start, _| if start == 49 {
+                Err(start)
+            } else {
+                Ok(())
+            }),
+            Err(49)
+        );
+    }
+
+    #[test]
+    fn test_calculate_hash() {
+        // The hash chain depends on this being the BLAKE2b of the blake2 crate
+        for &length in &[0, 1, 127, 128, 129, 1 << 20, (1 << 20) + 1] {
+            let data: Vec<u8> = (0..length).map(|i| (i * 7 + i / 255) as u8).collect();
+            let expected = Blake2b::digest(&data);
+            assert_eq!(calculate_hash(&data), expected);
+            assert_eq!(calculate_hash_of_reader(&mut &data[..]).unwrap(), expected);
+        }
+        assert_eq!(calculate_hash(&[]), blank_hash());
+    }
+
+    #[test]
+    fn test_hash_header() {
+        let mut map = vec![0; 100];
+        assert!(read_hash_header(&map).is_err());
+        assert!(read_hash_header(&map[..63]).is_err());
+
+        write_hash_header(&mut map, blank_hash().as_slice()).unwrap();
+        let hash = read_hash_header(&map).unwrap();
+        assert!(is_fresh_hash_header(&hash));
+        assert!(map[64..].iter().all(|&byte| byte == 0));
+
+        write_hash_header(&mut map, &[7; 64]).unwrap();
+        assert_eq!(&read_hash_header(&map).unwrap()[..], &[7; 64][..]);
+        assert!(!is_fresh_hash_header(&map[..64]));
+
+        assert!(write_hash_header(&mut map, &[7; 32]).is_err());
+        assert!(write_hash_header(&mut map[..32], &[7; 64]).is_err());
+    }
+
+    #[test]
+    fn test_fill_repeated() {
+        for &copies in &[0, 1, 3, 1000, 1001] {
+            let mut output = vec![0; copies * 3];
+            fill_repeated(&mut output, &[1, 2, 3]);
+            assert_eq!(output, [1, 2, 3].repeat(copies));
+        }
+    }
+}
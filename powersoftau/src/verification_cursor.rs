@@ -0,0 +1,126 @@
+/// Progress of the verification of a response, so that a verification that was interrupted
+/// continues from the last chunk it finished instead of checking every pairing again. The
+/// cursor holds, for each of the two sections of the accumulator, the start of the next chunk
+/// to verify; every pair of consecutive powers before it has been checked, as chunks overlap
+/// by one power. The key and the first powers are cheap to check, so they are checked again
+/// whenever the verification resumes.
+///
+/// A cursor only applies to the challenge and the response whose hashes it holds. It is kept
+/// in a small text file, rewritten atomically after every chunk.
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use bellman_ce::pairing::Engine;
+
+use super::hooks::write_atomically;
+use super::parameters::CeremonyParams;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VerificationCursor {
+    pub size: usize,
+    pub challenge_hash: [u8; 64],
+    pub response_hash: [u8; 64],
+    /// Start of the next chunk of the powers that exist for all element types
+    pub next_power: usize,
+    /// Start of the next chunk of the extra powers of tau in G1
+    pub next_extra_power: usize,
+}
+
+impl VerificationCursor {
+    /// Cursor of a verification that hasn't started yet.
+    pub fn new<E: Engine>(
+        challenge_hash: &[u8],
+        response_hash: &[u8],
+        parameters: &CeremonyParams<E>,
+    ) -> Self {
+        let mut cursor = VerificationCursor {
+            size: parameters.size,
+            challenge_hash: [0; 64],
+            response_hash: [0; 64],
+            next_power: 0,
+            next_extra_power: parameters.powers_length,
+        };
+        cursor.challenge_hash.copy_from_slice(challenge_hash);
+        cursor.response_hash.copy_from_slice(response_hash);
+        cursor
+    }
+
+    /// The cursor in `path` if it is one for the same challenge, response and parameters, and
+    /// a new cursor otherwise.
+    pub fn resume<E: Engine>(
+        path: &Path,
+        challenge_hash: &[u8],
+        response_hash: &[u8],
+        parameters: &CeremonyParams<E>,
+    ) -> io::Result<Self> {
+        let cursor = Self::new(challenge_hash, response_hash, parameters);
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(cursor),
+            Err(e) => return Err(e),
+        };
+
+        Ok(match Self::parse(&text) {
+            Some(saved)
+                if saved.size == cursor.size
+                    && saved.challenge_hash == cursor.challenge_hash
+                    && saved.response_hash == cursor.response_hash
+                    && saved.next_power <= parameters.powers_length
+                    && saved.next_extra_power >= parameters.powers_length
+                    && saved.next_extra_power <= parameters.powers_g1_length =>
+            {
+                saved
+            }
+            _ => cursor,
+        })
+    }
+
+    /// Whether any chunk was verified already.
+    pub fn has_progress<E: Engine>(&self, parameters: &CeremonyParams<E>) -> bool {
+        self.next_power > 0 || self.next_extra_power > parameters.powers_length
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        write_atomically(path, self.to_text().as_bytes())
+    }
+
+    fn to_text(&self) -> String {
+        format!(
+            "size {}\nchallenge_hash {}\nresponse_hash {}\nnext_power {}\nnext_extra_power {}\n",
+            self.size,
+            hex::encode(&self.challenge_hash[..]),
+            hex::encode(&self.response_hash[..]),
+            self.next_power,
+            self.next_extra_power
+        )
+    }
+
+    /// Parses a cursor. A malformed cursor is `None` and the verification starts over.
+    fn parse(text: &str) -> Option<Self> {
+        let mut fields = text.lines().map(|line| {
+            let mut parts = line.splitn(2, ' ');
+            (parts.next(), parts.next())
+        });
+        let mut field = |name: &str| match fields.next() {
+            Some((Some(key), Some(value))) if key == name => Some(value.to_string()),
+            _ => None,
+        };
+
+        let size = field("size")?.parse().ok()?;
+        let mut challenge_hash = [0; 64];
+        hex::decode_to_slice(field("challenge_hash")?, &mut challenge_hash[..]).ok()?;
+        let mut response_hash = [0; 64];
+        hex::decode_to_slice(field("response_hash")?, &mut response_hash[..]).ok()?;
+        let next_power = field("next_power")?.parse().ok()?;
+        let next_extra_power = field("next_extra_power")?.parse().ok()?;
+
+        Some(VerificationCursor {
+            size,
+            challenge_hash,
+            response_hash,
+            next_power,
+            next_extra_power,
+        })
+    }
+}
@@ -0,0 +1,1214 @@
+/// Unattended verification of a ceremony. Responses are dropped into a queue directory as
+/// `response_NNNN`, where NNNN is the round they contribute to. The worker verifies them in
+/// order against `challenge_NNNN` in the output directory, and for every valid response writes
+/// `challenge_NNNN+1` next to it. Every verified response gets a `result_NNNN` record, and a
+/// journal of the processed rounds lets the worker continue where it stopped after a crash.
+/// All of these names can be changed with path templates. The hash of the current challenge
+/// is kept in a snapshot, so that neither a retried round nor a restarted worker hashes the
+/// challenge again. For dashboards, the worker can keep a `Status` of the queue in a JSON file.
+/// Anyone with a copy of the files can audit their hash chain, see `Queue::audit_hash_chain`.
+/// Keys are checked against the trapdoors that the parameters update, and against the round
+/// they were generated for if the queue has a `domain_round`.
+use memmap::{Mmap, MmapOptions};
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use bellman_ce::pairing::Engine;
+
+use super::batched_accumulator::BatchedAccumulator;
+use super::errors::{self, Error, ErrorCategory};
+use super::hooks::write_atomically;
+use super::inclusion::{parse_hex_hash, InclusionStatement};
+use super::keypair::{KeyDomain, PublicKey};
+use super::parameters::{expected_sizes, CeremonyParams, CheckForCorrectness, UseCompression};
+use super::path_template::PathTemplate;
+use super::utils::{
+    calculate_hash, calculate_hash_of_reader, json_string, read_hash_header, unix_time,
+    write_hash_header,
+};
+
+const CHALLENGE_IS_COMPRESSED: UseCompression = UseCompression::No;
+const RESPONSE_IS_COMPRESSED: UseCompression = UseCompression::Yes;
+
+const JOURNAL_FILENAME: &str = "journal";
+const SNAPSHOT_FILENAME: &str = "snapshot";
+
+pub const DEFAULT_CHALLENGE_TEMPLATE: &str = "challenge_{round:04}";
+pub const DEFAULT_RESPONSE_TEMPLATE: &str = "response_{round:04}";
+pub const DEFAULT_RESULT_TEMPLATE: &str = "result_{round:04}";
+
+/// Number of rejected responses kept in the status.
+const RECENT_FAILURES: usize = 10;
+
+/// Outcome of the verification of a response.
+pub enum Outcome {
+    Accepted {
+        response_hash: [u8; 64],
+        new_challenge_hash: [u8; 64],
+    },
+    Rejected(Error),
+}
+
+/// Hash of the challenge of a round. It is only used for the same parameters and while the
+/// challenge has the length and modification time it had when it was hashed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub round: u32,
+    pub size: usize,
+    pub batch_size: usize,
+    pub challenge_length: u64,
+    /// Modification time of the challenge, in nanoseconds since the Unix epoch
+    pub challenge_modified: u128,
+    pub challenge_hash: [u8; 64],
+}
+
+impl Snapshot {
+    fn to_text(&self) -> String {
+        format!(
+            "round {}\nsize {}\nbatch_size {}\nchallenge_length {}\nchallenge_modified {}\nchallenge_hash {}\n",
+            self.round,
+            self.size,
+            self.batch_size,
+            self.challenge_length,
+            self.challenge_modified,
+            hex::encode(&self.challenge_hash[..])
+        )
+    }
+
+    /// Parses a snapshot. A malformed snapshot, e.g. one torn by a crash, is `None` and the
+    /// challenge is hashed again.
+    fn parse(text: &str) -> Option<Self> {
+        let mut fields = text.lines().map(|line| {
+            let mut parts = line.splitn(2, ' ');
+            (parts.next(), parts.next())
+        });
+        let mut field = |name: &str| match fields.next() {
+            Some((Some(key), Some(value))) if key == name => Some(value.to_string()),
+            _ => None,
+        };
+
+        let round = field("round")?.parse().ok()?;
+        let size = field("size")?.parse().ok()?;
+        let batch_size = field("batch_size")?.parse().ok()?;
+        let challenge_length = field("challenge_length")?.parse().ok()?;
+        let challenge_modified = field("challenge_modified")?.parse().ok()?;
+        let mut challenge_hash = [0; 64];
+        hex::decode_to_slice(field("challenge_hash")?, &mut challenge_hash[..]).ok()?;
+
+        Some(Snapshot {
+            round,
+            size,
+            batch_size,
+            challenge_length,
+            challenge_modified,
+            challenge_hash,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WorkerState {
+    /// Waiting for the response of the round
+    Waiting,
+    /// Verifying the response of the round
+    Verifying,
+}
+
+/// A rejected response.
+#[derive(Clone, Debug)]
+pub struct Failure {
+    pub round: u32,
+    /// Seconds since the Unix epoch
+    pub time: u64,
+    pub error: String,
+}
+
+/// What the worker is doing and has done since it started, for dashboards.
+#[derive(Clone, Debug)]
+pub struct Status {
+    pub state: WorkerState,
+    pub round: u32,
+    /// Complete responses in the queue, see `Queue::queued_responses`
+    pub queued: usize,
+    pub accepted: u32,
+    pub rejected: u32,
+    /// Seconds the last verification took
+    pub last_duration: Option<f64>,
+    /// Bytes of responses verified, and the seconds it took
+    pub verified_bytes: u64,
+    pub verifying_seconds: f64,
+    /// The last rejected responses, oldest first
+    pub recent_failures: VecDeque<Failure>,
+    /// Seconds since the Unix epoch
+    pub started: u64,
+    pub updated: u64,
+}
+
+impl Status {
+    pub fn new(round: u32) -> Self {
+        Status {
+            state: WorkerState::Waiting,
+            round,
+            queued: 0,
+            accepted: 0,
+            rejected: 0,
+            last_duration: None,
+            verified_bytes: 0,
+            verifying_seconds: 0.0,
+            recent_failures: VecDeque::new(),
+            started: unix_time(),
+            updated: unix_time(),
+        }
+    }
+
+    /// Records the outcome of a verification of the response of `round`, of `response_length`
+    /// bytes, that took `duration`.
+    pub fn record(
+        &mut self,
+        round: u32,
+        outcome: &Outcome,
+        duration: Duration,
+        response_length: u64,
+    ) {
+        let seconds = duration.as_secs_f64();
+        self.last_duration = Some(seconds);
+        self.verified_bytes += response_length;
+        self.verifying_seconds += seconds;
+        match outcome {
+            Outcome::Accepted { .. } => self.accepted += 1,
+            Outcome::Rejected(e) => {
+                self.rejected += 1;
+                if self.recent_failures.len() == RECENT_FAILURES {
+                    self.recent_failures.pop_front();
+                }
+                self.recent_failures.push_back(Failure {
+                    round,
+                    time: unix_time(),
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+
+    /// Bytes of responses verified per second, since the worker started.
+    pub fn throughput(&self) -> Option<f64> {
+        if self.verifying_seconds > 0.0 {
+            Some(self.verified_bytes as f64 / self.verifying_seconds)
+        } else {
+            None
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let optional = |value: Option<f64>| match value {
+            Some(value) => format!("{:.3}", value),
+            None => "null".to_string(),
+        };
+        let failures = self
+            .recent_failures
+            .iter()
+            .map(|failure| {
+                format!(
+                    "{{\"round\":{},\"time\":{},\"error\":{}}}",
+                    failure.round,
+                    failure.time,
+                    json_string(&failure.error)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!(
+            "{{\"state\":\"{}\",\"round\":{},\"queued\":{},\"accepted\":{},\"rejected\":{},\"last_verification_seconds\":{},\"throughput_bytes_per_second\":{},\"recent_failures\":[{}],\"started\":{},\"updated\":{}}}\n",
+            match self.state {
+                WorkerState::Waiting => "waiting",
+                WorkerState::Verifying => "verifying",
+            },
+            self.round,
+            self.queued,
+            self.accepted,
+            self.rejected,
+            optional(self.last_duration),
+            optional(self.throughput()),
+            failures,
+            self.started,
+            self.updated
+        )
+    }
+
+    /// Rewrites the status file, atomically so that readers never see a partial status.
+    pub fn write(&mut self, path: &Path) -> io::Result<()> {
+        self.updated = unix_time();
+        write_atomically(path, self.to_json().as_bytes())
+    }
+}
+
+/// Length and modification time of a file, or `None` if the platform has no modification times.
+fn file_stamp(path: &Path) -> io::Result<Option<(u64, u128)>> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok());
+
+    Ok(modified.map(|modified| (metadata.len(), modified.as_nanos())))
+}
+
+/// Where the hash chain of a ceremony ends.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChainAudit {
+    /// Number of responses that chain to their challenge
+    pub responses: u32,
+    /// Round of the last challenge
+    pub round: u32,
+    /// Whether the response of the last round is there, but hasn't been verified into the
+    /// challenge of the next round yet
+    pub response_pending: bool,
+}
+
+/// A verification recorded in the journal of the worker.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JournalEntry {
+    pub round: u32,
+    pub accepted: bool,
+    pub response_hash: [u8; 64],
+}
+
+pub struct Queue {
+    /// Directory the responses are dropped into
+    pub input_directory: PathBuf,
+    /// Directory holding the challenges, results and journal
+    pub output_directory: PathBuf,
+    /// Names of the challenges, relative to the output directory
+    pub challenge_template: PathTemplate,
+    /// Names of the responses, relative to the input directory
+    pub response_template: PathTemplate,
+    /// Names of the result records, relative to the output directory
+    pub result_template: PathTemplate,
+    /// The round of the ceremony of round 0 of the queue, if keys are bound to their round. The
+    /// keys of round `r` of the queue are then those of `KeyDomain::bn256_groth16` for round
+    /// `domain_round + r`. Keys are legacy keys otherwise.
+    pub domain_round: Option<u64>,
+}
+
+impl Queue {
+    /// Queue with the default names `challenge_NNNN`, `response_NNNN` and `result_NNNN`.
+    pub fn new<P: AsRef<Path>, Q: AsRef<Path>>(input_directory: P, output_directory: Q) -> Self {
+        let template = |template| PathTemplate::parse(template).expect("default template is valid");
+        Queue {
+            input_directory: input_directory.as_ref().to_path_buf(),
+            output_directory: output_directory.as_ref().to_path_buf(),
+            challenge_template: template(DEFAULT_CHALLENGE_TEMPLATE),
+            response_template: template(DEFAULT_RESPONSE_TEMPLATE),
+            result_template: template(DEFAULT_RESULT_TEMPLATE),
+            domain_round: None,
+        }
+    }
+
+    /// The domain that the keys of `round` are bound to, see `domain_round`.
+    pub fn key_domain<E: Engine>(
+        &self,
+        round: u32,
+        parameters: &CeremonyParams<E>,
+    ) -> Option<KeyDomain> {
+        self.domain_round
+            .map(|first| KeyDomain::bn256_groth16(parameters.size, first + round as u64))
+    }
+
+    pub fn challenge_path(&self, round: u32) -> PathBuf {
+        self.output_directory
+            .join(self.challenge_template.render(round))
+    }
+
+    pub fn response_path(&self, round: u32) -> PathBuf {
+        self.input_directory
+            .join(self.response_template.render(round))
+    }
+
+    pub fn result_path(&self, round: u32) -> PathBuf {
+        self.output_directory
+            .join(self.result_template.render(round))
+    }
+
+    fn journal_path(&self) -> PathBuf {
+        self.output_directory.join(JOURNAL_FILENAME)
+    }
+
+    fn snapshot_path(&self) -> PathBuf {
+        self.output_directory.join(SNAPSHOT_FILENAME)
+    }
+
+    pub fn read_snapshot(&self) -> Option<Snapshot> {
+        Snapshot::parse(&fs::read_to_string(self.snapshot_path()).ok()?)
+    }
+
+    /// Records the hash of the challenge of `round`. The snapshot is written under a temporary
+    /// name first, so that a crash leaves the previous one or none behind.
+    fn write_snapshot<E: Engine>(
+        &self,
+        round: u32,
+        challenge_hash: &[u8; 64],
+        parameters: &CeremonyParams<E>,
+    ) -> io::Result<()> {
+        let stamp = file_stamp(&self.challenge_path(round))?;
+        let (challenge_length, challenge_modified) = match stamp {
+            Some(stamp) => stamp,
+            None => return Ok(()),
+        };
+        let snapshot = Snapshot {
+            round,
+            size: parameters.size,
+            batch_size: parameters.batch_size,
+            challenge_length,
+            challenge_modified,
+            challenge_hash: *challenge_hash,
+        };
+
+        let temporary_path = with_suffix(&self.snapshot_path(), "tmp");
+        let mut file = File::create(&temporary_path)?;
+        file.write_all(snapshot.to_text().as_bytes())?;
+        file.sync_all()?;
+        fs::rename(temporary_path, self.snapshot_path())
+    }
+
+    /// Hash of the challenge of `round` from the snapshot, if the snapshot is of this round and
+    /// these parameters and the challenge hasn't changed since.
+    fn snapshot_challenge_hash<E: Engine>(
+        &self,
+        round: u32,
+        parameters: &CeremonyParams<E>,
+    ) -> Option<[u8; 64]> {
+        let snapshot = self.read_snapshot()?;
+        let stamp = file_stamp(&self.challenge_path(round)).ok()??;
+        if snapshot.round == round
+            && snapshot.size == parameters.size
+            && snapshot.batch_size == parameters.batch_size
+            && (snapshot.challenge_length, snapshot.challenge_modified) == stamp
+        {
+            Some(snapshot.challenge_hash)
+        } else {
+            None
+        }
+    }
+
+    /// Round whose response is expected next, i.e. the one after the last accepted round
+    /// in the journal.
+    pub fn next_round(&self) -> io::Result<u32> {
+        Ok(self
+            .read_journal()?
+            .iter()
+            .rev()
+            .find(|entry| entry.accepted)
+            .map_or(0, |entry| entry.round + 1))
+    }
+
+    /// The verifications recorded in the journal, in the order they were made. Empty if the
+    /// worker hasn't verified any response yet.
+    pub fn read_journal(&self) -> io::Result<Vec<JournalEntry>> {
+        let journal = match File::open(self.journal_path()) {
+            Ok(journal) => journal,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+
+        let mut entries = vec![];
+        for line in BufReader::new(journal).lines() {
+            let line = line?;
+            let mut fields = line.split_whitespace();
+            // A line torn by a crash is ignored, its round is processed again
+            if let (Some(round), Some(status), Some(response_hash)) =
+                (fields.next(), fields.next(), fields.next())
+            {
+                let accepted = match status {
+                    "accepted" => true,
+                    "rejected" => false,
+                    _ => continue,
+                };
+                if let (Ok(round), Some(response_hash)) =
+                    (round.parse::<u32>(), parse_hex_hash(response_hash))
+                {
+                    entries.push(JournalEntry {
+                        round,
+                        accepted,
+                        response_hash,
+                    });
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn record(&self, round: u32, outcome: &Outcome, response_hash: &[u8]) -> io::Result<()> {
+        let status = match outcome {
+            Outcome::Accepted { .. } => "accepted",
+            Outcome::Rejected(_) => "rejected",
+        };
+        let result_path = self.result_path(round);
+        if let Some(parent) = result_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut result = File::create(result_path)?;
+        writeln!(result, "round {}", round)?;
+        writeln!(result, "status {}", status)?;
+        writeln!(result, "response_hash {}", hex::encode(response_hash))?;
+        match outcome {
+            Outcome::Accepted {
+                new_challenge_hash, ..
+            } => writeln!(
+                result,
+                "new_challenge_hash {}",
+                hex::encode(&new_challenge_hash[..])
+            )?,
+            Outcome::Rejected(e) => writeln!(result, "error {}", e.to_string().replace('\n', " "))?,
+        }
+        result.sync_all()?;
+
+        let mut journal = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.journal_path())?;
+        writeln!(
+            journal,
+            "{} {} {}",
+            round,
+            status,
+            hex::encode(response_hash)
+        )?;
+        journal.sync_all()
+    }
+
+    /// Whether the response of `round` is in the queue and has been fully transferred.
+    pub fn response_ready<E: Engine>(
+        &self,
+        round: u32,
+        parameters: &CeremonyParams<E>,
+    ) -> Result<bool, Error> {
+        let expected_response_length = expected_sizes(parameters, RESPONSE_IS_COMPRESSED).response;
+        match fs::metadata(self.response_path(round)) {
+            Ok(ref metadata) => Ok(metadata.len() == expected_response_length as u64),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(Error::from(e).context("unable to read the queue")),
+        }
+    }
+
+    /// Number of complete responses in the queue, from the one of `round` up to the first one
+    /// that is missing.
+    pub fn queued_responses<E: Engine>(
+        &self,
+        round: u32,
+        parameters: &CeremonyParams<E>,
+    ) -> Result<usize, Error> {
+        let mut queued = 0;
+        while self.response_ready(round + queued as u32, parameters)? {
+            queued += 1;
+        }
+
+        Ok(queued)
+    }
+
+    /// Verifies the response of `round` if it is in the queue. Returns `None` if the response
+    /// is not there yet or has not been fully transferred. A rejected response is renamed to
+    /// `response_NNNN.rejected` so that the contributor can upload another one.
+    pub fn process_round<E: Engine>(
+        &self,
+        round: u32,
+        parameters: &CeremonyParams<E>,
+    ) -> Result<Option<Outcome>, Error> {
+        let response_path = self.response_path(round);
+        if !self.response_ready(round, parameters)? {
+            return Ok(None);
+        }
+
+        let challenge_map = map(&self.challenge_path(round))
+            .map_err(|e| Error::from(e).context("unable to open the challenge of the round"))?;
+        let response_map = map(&response_path)
+            .map_err(|e| Error::from(e).context("unable to open the response of the round"))?;
+        let response_hash = calculate_hash(&response_map);
+        let challenge_hash = match self.snapshot_challenge_hash(round, parameters) {
+            Some(challenge_hash) => challenge_hash,
+            None => {
+                let mut challenge_hash = [0; 64];
+                challenge_hash.copy_from_slice(calculate_hash(&challenge_map).as_slice());
+                self.write_snapshot(round, &challenge_hash, parameters)
+                    .map_err(|e| Error::from(e).context("unable to write the snapshot"))?;
+                challenge_hash
+            }
+        };
+
+        let outcome = match verify_response_to_hash(
+            &challenge_map,
+            &challenge_hash,
+            &response_map,
+            &self.challenge_path(round + 1),
+            self.key_domain(round, parameters).as_ref(),
+            parameters,
+        ) {
+            Ok(new_challenge_hash) => {
+                let mut accepted_response_hash = [0; 64];
+                accepted_response_hash.copy_from_slice(response_hash.as_slice());
+                Outcome::Accepted {
+                    response_hash: accepted_response_hash,
+                    new_challenge_hash,
+                }
+            }
+            Err(e) if e.category == ErrorCategory::Io || e.category == ErrorCategory::Internal => {
+                return Err(e)
+            }
+            Err(e) => Outcome::Rejected(e),
+        };
+
+        if let Outcome::Rejected(_) = outcome {
+            drop(response_map);
+            fs::rename(&response_path, with_suffix(&response_path, "rejected"))
+                .map_err(|e| Error::from(e).context("unable to set the rejected response aside"))?;
+        }
+        self.record(round, &outcome, response_hash.as_slice())
+            .map_err(|e| Error::from(e).context("unable to record the result of the round"))?;
+        if let Outcome::Accepted {
+            new_challenge_hash, ..
+        } = outcome
+        {
+            self.write_snapshot(round + 1, &new_challenge_hash, parameters)
+                .map_err(|e| Error::from(e).context("unable to write the snapshot"))?;
+        }
+
+        Ok(Some(outcome))
+    }
+
+    /// Checks that the files of the ceremony form a hash chain, from the challenge of round 0
+    /// up to the first round without a response: the response of every round starts with the
+    /// hash of the challenge of the round, and the challenge of the next round starts with the
+    /// hash of the response. No contribution is verified, and files are hashed as streams, so
+    /// this is a quick audit of a copy of the ceremony on any machine. `progress` is called
+    /// with every round that chains, the hash of its challenge and that of its response.
+    pub fn audit_hash_chain<F>(&self, mut progress: F) -> Result<ChainAudit, Error>
+    where
+        F: FnMut(u32, &[u8; 64], Option<&[u8; 64]>),
+    {
+        let challenge_path = self.challenge_path(0);
+        let mut challenge_hash = hash_file(&challenge_path)?;
+        let mut round = 0;
+        loop {
+            let response_path = self.response_path(round);
+            if !file_exists(&response_path)? {
+                progress(round, &challenge_hash, None);
+                return Ok(ChainAudit {
+                    responses: round,
+                    round,
+                    response_pending: false,
+                });
+            }
+            if read_hash_field(&response_path)? != challenge_hash {
+                return Err(Error::new(
+                    errors::HASH_CHAIN_BROKEN,
+                    ErrorCategory::Verification,
+                    format!(
+                        "{} doesn't start with the hash of {}",
+                        response_path.display(),
+                        self.challenge_path(round).display()
+                    ),
+                ));
+            }
+            let response_hash = hash_file(&response_path)?;
+            progress(round, &challenge_hash, Some(&response_hash));
+
+            let challenge_path = self.challenge_path(round + 1);
+            if !file_exists(&challenge_path)? {
+                return Ok(ChainAudit {
+                    responses: round + 1,
+                    round,
+                    response_pending: true,
+                });
+            }
+            if read_hash_field(&challenge_path)? != response_hash {
+                return Err(Error::new(
+                    errors::HASH_CHAIN_BROKEN,
+                    ErrorCategory::Verification,
+                    format!(
+                        "{} doesn't start with the hash of {}",
+                        challenge_path.display(),
+                        response_path.display()
+                    ),
+                ));
+            }
+            challenge_hash = hash_file(&challenge_path)?;
+            round += 1;
+        }
+    }
+
+    /// The round of the response with the hash `response_hash`, if it's in the hash chain.
+    pub fn contribution_index_of(&self, response_hash: &[u8; 64]) -> Result<Option<u32>, Error> {
+        Ok(self
+            .inclusion_statement(response_hash)?
+            .map(|statement| statement.round))
+    }
+
+    /// The statement that the response with the hash `response_hash` is in the hash chain,
+    /// if it is. Like `audit_hash_chain`, which it runs, this checks the whole chain but no
+    /// contribution.
+    pub fn inclusion_statement(
+        &self,
+        response_hash: &[u8; 64],
+    ) -> Result<Option<InclusionStatement>, Error> {
+        let mut statement: Option<InclusionStatement> = None;
+        self.audit_hash_chain(|round, challenge_hash, hash| {
+            if let Some(statement) = statement.as_mut() {
+                if statement.round + 1 == round {
+                    statement.next = Some(*challenge_hash);
+                }
+            }
+            if hash == Some(response_hash) {
+                statement = Some(InclusionStatement {
+                    round,
+                    previous: *challenge_hash,
+                    contribution: *response_hash,
+                    next: None,
+                });
+            }
+        })?;
+
+        Ok(statement)
+    }
+}
+
+fn file_exists(path: &Path) -> Result<bool, Error> {
+    match fs::metadata(path) {
+        Ok(_) => Ok(true),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(Error::from(e).context(format!("unable to read {}", path.display()))),
+    }
+}
+
+fn hash_file(path: &Path) -> Result<[u8; 64], Error> {
+    let mut hash = [0; 64];
+    File::open(path)
+        .and_then(|mut file| calculate_hash_of_reader(&mut file))
+        .map(|result| hash.copy_from_slice(result.as_slice()))
+        .map_err(|e| Error::from(e).context(format!("unable to hash {}", path.display())))?;
+
+    Ok(hash)
+}
+
+/// The hash of the previous file of the chain, which challenges and responses start with.
+fn read_hash_field(path: &Path) -> Result<[u8; 64], Error> {
+    let mut hash = [0; 64];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut hash))
+        .map_err(|e| {
+            Error::from(e).context(format!("unable to read the hash in {}", path.display()))
+        })?;
+
+    Ok(hash)
+}
+
+/// `path` with `.suffix` appended, keeping any extension it already has.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+fn map(path: &Path) -> io::Result<Mmap> {
+    let file = File::open(path)?;
+    unsafe { MmapOptions::new().map(&file) }
+}
+
+/// Checks that the response is a valid contribution to the challenge and writes the new
+/// challenge to `new_challenge_path`. The new challenge is written under a temporary name first,
+/// so that a crash never leaves a partial challenge behind. Returns the hash of the new challenge.
+/// Keys are legacy keys, bound to the challenge and to the trapdoors the parameters update.
+pub fn verify_response<E: Engine>(
+    challenge_map: &Mmap,
+    response_map: &Mmap,
+    new_challenge_path: &Path,
+    parameters: &CeremonyParams<E>,
+) -> Result<[u8; 64], Error> {
+    let mut challenge_hash = [0; 64];
+    challenge_hash.copy_from_slice(calculate_hash(challenge_map).as_slice());
+
+    verify_response_to_hash(
+        challenge_map,
+        &challenge_hash,
+        response_map,
+        new_challenge_path,
+        None,
+        parameters,
+    )
+}
+
+/// Same as `verify_response`, for a challenge whose hash is already known, and with keys bound
+/// to `domain` if there is one, like `Round::with_domain`.
+pub fn verify_response_to_hash<E: Engine>(
+    challenge_map: &Mmap,
+    challenge_hash: &[u8; 64],
+    response_map: &Mmap,
+    new_challenge_path: &Path,
+    domain: Option<&KeyDomain>,
+    parameters: &CeremonyParams<E>,
+) -> Result<[u8; 64], Error> {
+    // A response without a hash header doesn't continue the chain either
+    let response_challenge_hash = read_hash_header(response_map).map_err(|e| {
+        Error::new(
+            errors::HASH_CHAIN_BROKEN,
+            ErrorCategory::Verification,
+            format!("Hash chain failure, {}.", e),
+        )
+    })?;
+    if response_challenge_hash[..] != challenge_hash[..] {
+        return Err(Error::new(
+            errors::HASH_CHAIN_BROKEN,
+            ErrorCategory::Verification,
+            "Hash chain failure. This is not the right response.",
+        ));
+    }
+
+    let public_key = PublicKey::read(response_map, RESPONSE_IS_COMPRESSED, parameters)
+        .map_err(|e| Error::from(e).context("wasn't able to deserialize the public key"))?;
+
+    // Keys of a round are bound to the ceremony, see `KeyDomain`
+    // A contribution that only updates some trapdoors is also bound to them, see `TrapdoorMask`
+    let key_digest = match domain {
+        Some(domain) => parameters.trapdoors.digest(&domain.digest(challenge_hash)),
+        None => parameters.trapdoors.digest(challenge_hash),
+    };
+    let valid = BatchedAccumulator::verify_transformation(
+        challenge_map,
+        response_map,
+        &public_key,
+        &key_digest,
+        CHALLENGE_IS_COMPRESSED,
+        RESPONSE_IS_COMPRESSED,
+        CheckForCorrectness::No,
+        CheckForCorrectness::Yes,
+        parameters,
+    );
+    if !valid {
+        return Err(Error::new(
+            errors::INVALID_CONTRIBUTION,
+            ErrorCategory::Verification,
+            "Verification failed, contribution was invalid somehow.",
+        ));
+    }
+
+    let temporary_path = with_suffix(new_challenge_path, "tmp");
+    if let Some(parent) = new_challenge_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| {
+            Error::from(e).context("unable to create the directory of the new challenge")
+        })?;
+    }
+    let writer = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&temporary_path)
+        .map_err(|e| Error::from(e).context("unable to create the new challenge"))?;
+    writer
+        .set_len(parameters.accumulator_size as u64)
+        .map_err(|e| Error::from(e).context("must make output file large enough"))?;
+    let mut writable_map = unsafe {
+        MmapOptions::new()
+            .map_mut(&writer)
+            .map_err(|e| Error::from(e).context("unable to create a memory map for output"))?
+    };
+
+    write_hash_header(&mut writable_map, calculate_hash(response_map).as_slice())
+        .map_err(|e| Error::from(e).context("unable to write the response hash"))?;
+    BatchedAccumulator::decompress(
+        response_map,
+        &mut writable_map,
+        CheckForCorrectness::No,
+        parameters,
+    )
+    .map_err(|e| Error::from(e).context("must decompress a response for a new challenge"))?;
+    writable_map
+        .flush()
+        .map_err(|e| Error::from(e).context("must flush the memory map"))?;
+
+    let new_challenge_map = writable_map
+        .make_read_only()
+        .map_err(|e| Error::from(e).context("must make a map readonly"))?;
+    let mut new_challenge_hash = [0; 64];
+    new_challenge_hash.copy_from_slice(calculate_hash(&new_challenge_map).as_slice());
+
+    fs::rename(&temporary_path, new_challenge_path)
+        .map_err(|e| Error::from(e).context("unable to move the new challenge in place"))?;
+
+    Ok(new_challenge_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::keypair_with_mask;
+    use bellman_ce::pairing::bn256::Bn256;
+    use memmap::MmapMut;
+    use rand::thread_rng;
+
+    fn queue(name: &str) -> Queue {
+        let directory =
+            std::env::temp_dir().join(format!("powersoftau_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("in")).unwrap();
+        fs::create_dir_all(directory.join("out")).unwrap();
+
+        Queue::new(directory.join("in"), directory.join("out"))
+    }
+
+    fn write_challenge(queue: &Queue, parameters: &CeremonyParams<Bn256>) -> Mmap {
+        let mut map = MmapMut::map_anon(parameters.accumulator_size).unwrap();
+        BatchedAccumulator::generate_initial(&mut map, UseCompression::No, parameters).unwrap();
+        fs::write(queue.challenge_path(0), &map[..]).unwrap();
+        map.make_read_only().unwrap()
+    }
+
+    fn write_response(
+        queue: &Queue,
+        challenge: &Mmap,
+        digest: &[u8],
+        parameters: &CeremonyParams<Bn256>,
+    ) {
+        write_response_with_key(queue, challenge, digest, digest, parameters)
+    }
+
+    /// Writes a response that starts with `challenge_hash`, with a key that proves knowledge
+    /// with `key_digest` and updates the trapdoors of `parameters`.
+    fn write_response_with_key(
+        queue: &Queue,
+        challenge: &Mmap,
+        challenge_hash: &[u8],
+        key_digest: &[u8],
+        parameters: &CeremonyParams<Bn256>,
+    ) {
+        let (public_key, private_key) =
+            keypair_with_mask::<_, Bn256>(&mut thread_rng(), key_digest, parameters.trapdoors);
+        let mut response = MmapMut::map_anon(parameters.contribution_size).unwrap();
+        response[0..64].copy_from_slice(challenge_hash);
+        BatchedAccumulator::transform(
+            challenge,
+            &mut response,
+            UseCompression::No,
+            UseCompression::Yes,
+            CheckForCorrectness::No,
+            &private_key,
+            parameters,
+        )
+        .unwrap();
+        public_key
+            .write(&mut response, UseCompression::Yes, parameters)
+            .unwrap();
+        fs::write(queue.response_path(0), &response[..]).unwrap();
+    }
+
+    #[test]
+    fn test_process_round() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let queue = queue("process_round");
+        let challenge = write_challenge(&queue, &parameters);
+        assert!(queue.process_round(0, &parameters).unwrap().is_none());
+
+        let digest = calculate_hash(&challenge);
+        write_response(&queue, &challenge, digest.as_slice(), &parameters);
+        match queue.process_round(0, &parameters).unwrap() {
+            Some(Outcome::Accepted {
+                new_challenge_hash, ..
+            }) => {
+                let new_challenge = map(&queue.challenge_path(1)).unwrap();
+                assert_eq!(
+                    &new_challenge_hash[..],
+                    calculate_hash(&new_challenge).as_slice()
+                );
+            }
+            _ => panic!("response must be accepted"),
+        }
+        assert_eq!(queue.next_round().unwrap(), 1);
+        assert!(queue.result_path(0).exists());
+
+        fs::remove_dir_all(queue.output_directory.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_process_round_with_mask_and_domain() {
+        let mut parameters = CeremonyParams::<Bn256>::new(3, 4);
+        parameters.trapdoors = "alpha,beta".parse().unwrap();
+        let mut queue = queue("process_round_mask");
+        queue.domain_round = Some(5);
+        let challenge = write_challenge(&queue, &parameters);
+        let challenge_hash = calculate_hash(&challenge);
+        let domain = queue.key_domain(0, &parameters).unwrap();
+        assert_eq!(domain, KeyDomain::bn256_groth16(3, 5));
+
+        // A key bound to the challenge only is rejected, and set aside
+        let legacy_digest = parameters.trapdoors.digest(challenge_hash.as_slice());
+        write_response_with_key(
+            &queue,
+            &challenge,
+            challenge_hash.as_slice(),
+            &legacy_digest,
+            &parameters,
+        );
+        match queue.process_round(0, &parameters).unwrap() {
+            Some(Outcome::Rejected(e)) => assert_eq!(e.code, errors::INVALID_CONTRIBUTION),
+            _ => panic!("a key of another round must be rejected"),
+        }
+
+        // The key of round 5 of the ceremony that updates alpha and beta is accepted
+        let key_digest = parameters
+            .trapdoors
+            .digest(&domain.digest(challenge_hash.as_slice()));
+        write_response_with_key(
+            &queue,
+            &challenge,
+            challenge_hash.as_slice(),
+            &key_digest,
+            &parameters,
+        );
+        match queue.process_round(0, &parameters).unwrap() {
+            Some(Outcome::Accepted { .. }) => {}
+            _ => panic!("a masked response bound to its round must be accepted"),
+        }
+        assert_eq!(
+            queue.key_domain(1, &parameters),
+            Some(KeyDomain::bn256_groth16(3, 6))
+        );
+
+        fs::remove_dir_all(queue.output_directory.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_audit_hash_chain() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let queue = queue("audit_hash_chain");
+        let challenge = write_challenge(&queue, &parameters);
+        let audit = |queue: &Queue| {
+            let mut rounds = vec![];
+            queue
+                .audit_hash_chain(|round, _, response_hash| {
+                    rounds.push((round, response_hash.is_some()))
+                })
+                .map(|audit| (audit, rounds))
+        };
+        assert_eq!(
+            audit(&queue).unwrap(),
+            (
+                ChainAudit {
+                    responses: 0,
+                    round: 0,
+                    response_pending: false
+                },
+                vec![(0, false)]
+            )
+        );
+
+        let digest = calculate_hash(&challenge);
+        write_response(&queue, &challenge, digest.as_slice(), &parameters);
+        assert!(audit(&queue).unwrap().0.response_pending);
+        queue.process_round(0, &parameters).unwrap().unwrap();
+
+        // The chain only looks at the hashes, so any response to round 1 is taken for now
+        let mut response = calculate_hash(&map(&queue.challenge_path(1)).unwrap()).to_vec();
+        response.extend_from_slice(b"contribution");
+        fs::write(queue.response_path(1), &response).unwrap();
+        assert_eq!(
+            audit(&queue).unwrap(),
+            (
+                ChainAudit {
+                    responses: 2,
+                    round: 1,
+                    response_pending: true
+                },
+                vec![(0, true), (1, true)]
+            )
+        );
+
+        response[0] ^= 1;
+        fs::write(queue.response_path(1), &response).unwrap();
+        assert_eq!(audit(&queue).unwrap_err().code, errors::HASH_CHAIN_BROKEN);
+
+        let mut new_challenge = fs::read(queue.challenge_path(1)).unwrap();
+        new_challenge[0] ^= 1;
+        fs::write(queue.challenge_path(1), &new_challenge).unwrap();
+        let error = audit(&queue).unwrap_err();
+        assert!(
+            error.message.contains("challenge_0001"),
+            "{}",
+            error.message
+        );
+
+        fs::remove_dir_all(queue.output_directory.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_inclusion_statement() {
+        let queue = queue("inclusion_statement");
+        // The chain only looks at the hashes, so the files don't need to be accumulators
+        let hash = |data: &[u8]| {
+            let mut hash = [0; 64];
+            hash.copy_from_slice(calculate_hash_of_reader(&mut &data[..]).unwrap().as_slice());
+            hash
+        };
+        let chained = |hash: [u8; 64], data: &[u8]| [&hash[..], data].concat();
+        let challenge_0 = b"challenge".to_vec();
+        let response_0 = chained(hash(&challenge_0), b"response");
+        let challenge_1 = chained(hash(&response_0), b"challenge");
+        let response_1 = chained(hash(&challenge_1), b"response");
+        fs::write(queue.challenge_path(0), &challenge_0).unwrap();
+        fs::write(queue.response_path(0), &response_0).unwrap();
+        fs::write(queue.challenge_path(1), &challenge_1).unwrap();
+        fs::write(queue.response_path(1), &response_1).unwrap();
+
+        assert_eq!(
+            queue.inclusion_statement(&hash(&response_0)).unwrap(),
+            Some(InclusionStatement {
+                round: 0,
+                previous: hash(&challenge_0),
+                contribution: hash(&response_0),
+                next: Some(hash(&challenge_1)),
+            })
+        );
+        // The response of the last round has no next challenge yet
+        assert_eq!(
+            queue.inclusion_statement(&hash(&response_1)).unwrap(),
+            Some(InclusionStatement {
+                round: 1,
+                previous: hash(&challenge_1),
+                contribution: hash(&response_1),
+                next: None,
+            })
+        );
+        assert_eq!(
+            queue.contribution_index_of(&hash(&response_1)).unwrap(),
+            Some(1)
+        );
+        assert_eq!(queue.contribution_index_of(&[0; 64]).unwrap(), None);
+        // A challenge is not a contribution
+        assert_eq!(
+            queue.contribution_index_of(&hash(&challenge_1)).unwrap(),
+            None
+        );
+
+        fs::remove_dir_all(queue.output_directory.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_status() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let queue = queue("status");
+        let challenge = write_challenge(&queue, &parameters);
+        assert_eq!(queue.queued_responses(0, &parameters).unwrap(), 0);
+        write_response(&queue, &challenge, &[0; 64], &parameters);
+        assert_eq!(queue.queued_responses(0, &parameters).unwrap(), 1);
+
+        let mut status = Status::new(0);
+        assert!(status
+            .to_json()
+            .contains("\"throughput_bytes_per_second\":null"));
+        let outcome = queue.process_round(0, &parameters).unwrap().unwrap();
+        for _ in 0..RECENT_FAILURES + 1 {
+            status.record(0, &outcome, Duration::from_secs(2), 1000);
+        }
+        assert_eq!(status.rejected, RECENT_FAILURES as u32 + 1);
+        assert_eq!(status.recent_failures.len(), RECENT_FAILURES);
+        assert_eq!(status.throughput(), Some(500.0));
+
+        let path = queue.output_directory.join("status.json");
+        status.write(&path).unwrap();
+        let json = fs::read_to_string(&path).unwrap();
+        assert!(json.starts_with("{\"state\":\"waiting\",\"round\":0,\"queued\":0,"));
+        assert!(json.contains("\"throughput_bytes_per_second\":500.000"));
+        assert!(json.contains("\"recent_failures\":[{\"round\":0,"));
+
+        fs::remove_dir_all(queue.output_directory.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let queue = queue("snapshot");
+        let challenge = write_challenge(&queue, &parameters);
+        assert!(queue.read_snapshot().is_none());
+
+        // A rejected response leaves the hash of the challenge for the next attempt
+        write_response(&queue, &challenge, &[0; 64], &parameters);
+        queue.process_round(0, &parameters).unwrap();
+        let snapshot = queue.read_snapshot().unwrap();
+        assert_eq!(snapshot.round, 0);
+        assert_eq!(
+            &snapshot.challenge_hash[..],
+            calculate_hash(&challenge).as_slice()
+        );
+        assert_eq!(Snapshot::parse(&snapshot.to_text()), Some(snapshot));
+
+        let digest = calculate_hash(&challenge);
+        write_response(&queue, &challenge, digest.as_slice(), &parameters);
+        let new_challenge_hash = match queue.process_round(0, &parameters).unwrap() {
+            Some(Outcome::Accepted {
+                new_challenge_hash, ..
+            }) => new_challenge_hash,
+            _ => panic!("response must be accepted"),
+        };
+        assert_eq!(
+            queue.snapshot_challenge_hash(1, &parameters),
+            Some(new_challenge_hash)
+        );
+
+        // Other parameters or a changed challenge invalidate the snapshot
+        assert!(queue
+            .snapshot_challenge_hash(1, &CeremonyParams::<Bn256>::new(3, 2))
+            .is_none());
+        OpenOptions::new()
+            .append(true)
+            .open(queue.challenge_path(1))
+            .unwrap()
+            .write_all(&[0])
+            .unwrap();
+        assert!(queue.snapshot_challenge_hash(1, &parameters).is_none());
+
+        fs::remove_dir_all(queue.output_directory.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_process_round_with_templates() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let mut queue = queue("templates");
+        queue.challenge_template = PathTemplate::parse("{round}/challenge").unwrap();
+        queue.response_template = PathTemplate::parse("{round:02}/response.bin").unwrap();
+        fs::create_dir_all(queue.challenge_path(0).parent().unwrap()).unwrap();
+        fs::create_dir_all(queue.response_path(0).parent().unwrap()).unwrap();
+        let challenge = write_challenge(&queue, &parameters);
+
+        let digest = calculate_hash(&challenge);
+        write_response(&queue, &challenge, digest.as_slice(), &parameters);
+        match queue.process_round(0, &parameters).unwrap() {
+            Some(Outcome::Accepted { .. }) => {}
+            _ => panic!("response must be accepted"),
+        }
+        assert!(queue.output_directory.join("1/challenge").exists());
+
+        fs::remove_dir_all(queue.output_directory.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_process_round_rejects_broken_hash_chain() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let queue = queue("broken_hash_chain");
+        let challenge = write_challenge(&queue, &parameters);
+
+        write_response(&queue, &challenge, &[0; 64], &parameters);
+        match queue.process_round(0, &parameters).unwrap() {
+            Some(Outcome::Rejected(e)) => assert_eq!(e.code, errors::HASH_CHAIN_BROKEN),
+            _ => panic!("response must be rejected"),
+        }
+        assert_eq!(queue.next_round().unwrap(), 0);
+        assert!(!queue.response_path(0).exists());
+        assert!(!queue.challenge_path(1).exists());
+
+        fs::remove_dir_all(queue.output_directory.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+/// Workset for contributing on an air-gapped machine. A challenge is uncompressed so that it is
+/// fast to read, which doubles what has to be carried across the gap. The workset holds the
+/// challenge in compressed form instead, together with what the response needs from the
+/// uncompressed file, which is its hash:
+///
+/// MAGIC | version (u32, BE) | circuit power (u32, BE) | batch size (u32, BE) |
+/// BLAKE2b of the challenge | compressed challenge
+///
+/// The compressed challenge starts with the hash of the previous response, like the challenge.
+/// A workset is checked when it is prepared, on the online machine; the contribution computed
+/// from it offline is checked by the coordinator against the challenge as usual.
+use bellman_ce::pairing::Engine;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use memmap::{Mmap, MmapMut};
+
+use std::io::{self, Read, Write};
+
+use super::batched_accumulator::BatchedAccumulator;
+use super::parameters::{expected_sizes, CeremonyParams, CheckForCorrectness, UseCompression};
+
+/// Identifies a workset, followed by the format version.
+const MAGIC: &[u8; 8] = b"POTWRKST";
+const VERSION: u32 = 1;
+
+/// Length of the header, before the compressed challenge.
+pub const HEADER_SIZE: usize = 8 + 4 + 4 + 4 + 64;
+
+#[derive(Clone)]
+pub struct WorksetHeader {
+    pub circuit_power: usize,
+    pub batch_size: usize,
+    /// BLAKE2b hash of the uncompressed challenge, which the response starts with
+    pub challenge_hash: [u8; 64],
+}
+
+impl WorksetHeader {
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&MAGIC[..])?;
+        writer.write_u32::<BigEndian>(VERSION)?;
+        writer.write_u32::<BigEndian>(self.circuit_power as u32)?;
+        writer.write_u32::<BigEndian>(self.batch_size as u32)?;
+        writer.write_all(&self.challenge_hash)
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a workset"));
+        }
+        if reader.read_u32::<BigEndian>()? != VERSION {
+            return Err(invalid("unsupported workset version"));
+        }
+        let circuit_power = reader.read_u32::<BigEndian>()? as usize;
+        let batch_size = reader.read_u32::<BigEndian>()? as usize;
+        let mut challenge_hash = [0; 64];
+        reader.read_exact(&mut challenge_hash)?;
+
+        Ok(WorksetHeader {
+            circuit_power,
+            batch_size,
+            challenge_hash,
+        })
+    }
+}
+
+/// Size of the workset of a challenge.
+pub fn workset_size<E: Engine>(parameters: &CeremonyParams<E>) -> usize {
+    HEADER_SIZE + expected_sizes(parameters, UseCompression::Yes).challenge
+}
+
+/// Writes the uncompressed `challenge` in compressed form to `output_map`, which is the part of
+/// a workset after the header.
+pub fn compress_challenge<E: Engine>(
+    challenge: &Mmap,
+    output_map: &mut MmapMut,
+    check_input_for_correctness: CheckForCorrectness,
+    parameters: &CeremonyParams<E>,
+) -> io::Result<()> {
+    BatchedAccumulator::convert_compression(
+        challenge,
+        output_map,
+        UseCompression::No,
+        UseCompression::Yes,
+        check_input_for_correctness,
+        parameters,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bellman_ce::pairing::bn256::Bn256;
+
+    #[test]
+    fn test_workset_header() {
+        let header = WorksetHeader {
+            circuit_power: 21,
+            batch_size: 256,
+            challenge_hash: [7; 64],
+        };
+        let mut bytes = vec![];
+        header.write(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), HEADER_SIZE);
+
+        let read = WorksetHeader::read(&mut &bytes[..]).unwrap();
+        assert_eq!(
+            (read.circuit_power, read.batch_size),
+            (header.circuit_power, header.batch_size)
+        );
+        assert_eq!(&read.challenge_hash[..], &header.challenge_hash[..]);
+
+        bytes[0] ^= 1;
+        assert!(WorksetHeader::read(&mut &bytes[..]).is_err());
+    }
+
+    #[test]
+    fn test_compress_challenge() {
+        let parameters = CeremonyParams::<Bn256>::new(3, 4);
+        let sizes = expected_sizes(&parameters, UseCompression::No);
+        let mut challenge = MmapMut::map_anon(sizes.challenge).unwrap();
+        challenge[..parameters.hash_size].copy_from_slice(&[3; 64]);
+        BatchedAccumulator::generate_initial(&mut challenge, UseCompression::No, &parameters)
+            .unwrap();
+        let challenge = challenge.make_read_only().unwrap();
+
+        let compressed_size = workset_size(&parameters) - HEADER_SIZE;
+        let mut compressed = MmapMut::map_anon(compressed_size).unwrap();
+        compress_challenge(
+            &challenge,
+            &mut compressed,
+            CheckForCorrectness::Yes,
+            &parameters,
+        )
+        .unwrap();
+        let compressed = compressed.make_read_only().unwrap();
+        assert_eq!(&compressed[..64], &challenge[..64]);
+
+        // Decompressing gives the challenge back
+        let mut decompressed = MmapMut::map_anon(sizes.challenge).unwrap();
+        BatchedAccumulator::convert_compression(
+            &compressed,
+            &mut decompressed,
+            UseCompression::Yes,
+            UseCompression::No,
+            CheckForCorrectness::Yes,
+            &parameters,
+        )
+        .unwrap();
+        assert_eq!(&decompressed[..], &challenge[..]);
+    }
+}
@@ -8,8 +8,9 @@ rm tmp_*
 
 set -e
 
-SIZE=10
-BATCH=256
+# e.g. SIZE=2 BATCH=1 for a ceremony that runs in seconds
+SIZE=${SIZE:-10}
+BATCH=${BATCH:-256}
 
 cargo run --release --bin new_constrained challenge1 $SIZE $BATCH
 yes | cargo run --release --bin compute_constrained challenge1 response1 $SIZE $BATCH
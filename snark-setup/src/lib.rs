@@ -0,0 +1,21 @@
+//! The powers of tau (phase 1) and circuit-specific (phase 2) ceremonies behind one
+//! dependency, so that both use the same `bellman_ce` and the same feature flags:
+//!
+//! - `phase1` and `phase2` select the ceremonies, both are on by default
+//! - `parallel` uses all cores in phase 2, on by default
+//! - `wasm` builds phase 2 for the browser, with `--no-default-features --features wasm`
+//!
+//! Phase 2 needs one of `parallel` or `wasm`.
+//! - `cross-check` and `profiling` are the ones of the powers of tau
+//! - `expensive-tests` runs a whole ceremony with the command line tools of both crates
+//!
+//! Both ceremonies report errors with the same codes, categories and exit codes; phase 2 has
+//! its own copy of the error type so that it builds without the powers of tau for wasm.
+
+pub use bellman_ce;
+
+#[cfg(feature = "phase1")]
+pub use powersoftau as phase1;
+
+#[cfg(feature = "phase2")]
+pub use phase2;
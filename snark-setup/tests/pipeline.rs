@@ -0,0 +1,281 @@
+//! Runs a complete small ceremony with the command line tools of both phases, the way
+//! coordinators run them, so that regressions in argument parsing, file names or the
+//! constants shared by the tools are caught.
+//!
+//! It builds the release binaries of powersoftau and phase2 first, so it only runs with
+//! `cargo test --features expensive-tests`.
+#![cfg(feature = "expensive-tests")]
+
+use snark_setup::phase1::utils::calculate_hash;
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const CIRCUIT_POWER: &str = "3";
+const BATCH_SIZE: &str = "4";
+const BEACON_HASH: &str = "0000000000000000000a558a61ddc8ee4e488d647a747fe4dcc362fe2026c620";
+
+/// The challenge of `new_constrained` only depends on the circuit power, the batch size
+/// and the generators.
+const INITIAL_CHALLENGE_HASH: &str = "45f580c564b26059f533418e8c0cbdbcdd73543f221bfa741c1a5eeb71b0c2cd4ab44f2f0600f3d7ba93203f9b04e2de3f325a88d0cdfcd902b08b85e071a471";
+
+/// a * b = c, with c public
+const CIRCUIT: &str =
+    r#"{"constraints":[[{"2":"1"},{"3":"1"},{"1":"1"}]],"nPubInputs":0,"nOutputs":1,"nVars":4}"#;
+const WITNESS: &str = r#"["1","33","3","11"]"#;
+
+struct Ceremony {
+    directory: PathBuf,
+    phase1: PathBuf,
+    phase2: PathBuf,
+}
+
+/// Builds the binaries of the crate in `../<name>` and returns where they are.
+fn build(name: &str) -> PathBuf {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join(name);
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let status = Command::new(cargo)
+        .args(["build", "--release", "--bins"])
+        .current_dir(&manifest)
+        .status()
+        .expect("unable to run cargo");
+    assert!(status.success(), "unable to build {}", name);
+    manifest.join("target").join("release")
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl Ceremony {
+    fn new() -> Self {
+        let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join("pipeline");
+        if directory.exists() {
+            fs::remove_dir_all(&directory).unwrap();
+        }
+        fs::create_dir_all(&directory).unwrap();
+
+        Ceremony {
+            directory,
+            phase1: build("powersoftau"),
+            phase2: build("phase2"),
+        }
+    }
+
+    /// Runs `binary` in the directory of the ceremony, with `input` on its standard input,
+    /// and returns its standard output.
+    fn run(&self, binaries: &Path, binary: &str, args: &[&str], input: &str) -> String {
+        let mut child = Command::new(binaries.join(binary))
+            .args(args)
+            .current_dir(&self.directory)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap_or_else(|e| panic!("unable to start {}: {}", binary, e));
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(
+            output.status.success(),
+            "{} {:?} failed:\n{}{}",
+            binary,
+            args,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    fn phase1(&self, binary: &str, args: &[&str]) -> String {
+        self.run(&self.phase1, binary, args, "")
+    }
+
+    fn phase2(&self, binary: &str, args: &[&str]) -> String {
+        self.run(&self.phase2, binary, args, "")
+    }
+
+    fn read(&self, file: &str) -> Vec<u8> {
+        fs::read(self.directory.join(file))
+            .unwrap_or_else(|e| panic!("unable to read {}: {}", file, e))
+    }
+
+    fn write(&self, file: &str, contents: &str) {
+        fs::write(self.directory.join(file), contents).unwrap();
+    }
+
+    fn hash(&self, file: &str) -> String {
+        hex(&calculate_hash(&self.read(file)))
+    }
+
+    /// Checks that the file `next` starts with the hash of the file `previous`, as
+    /// responses do with their challenge and challenges with the response they
+    /// come from.
+    fn assert_chained(&self, previous: &str, next: &str) {
+        assert_eq!(
+            hex(&self.read(next)[..64]),
+            self.hash(previous),
+            "{} doesn't follow {}",
+            next,
+            previous
+        );
+    }
+
+    /// Contributes to the challenge `challenge` and verifies the response.
+    fn contribute_phase1(
+        &self,
+        challenge: &str,
+        response: &str,
+        new_challenge: &str,
+        entropy: &str,
+    ) {
+        let output = self.run(
+            &self.phase1,
+            "compute_constrained",
+            &[challenge, response, CIRCUIT_POWER, BATCH_SIZE],
+            entropy,
+        );
+        // The hash is printed in groups of 4 bytes
+        let printed: String = output.split_whitespace().collect();
+        assert!(
+            printed.contains(&self.hash(response)),
+            "compute_constrained printed another hash"
+        );
+        self.assert_chained(challenge, response);
+
+        self.verify_phase1(challenge, response, new_challenge);
+    }
+
+    fn verify_phase1(&self, challenge: &str, response: &str, new_challenge: &str) {
+        let output = self.phase1(
+            "verify_transform_constrained",
+            &[
+                challenge,
+                response,
+                new_challenge,
+                CIRCUIT_POWER,
+                BATCH_SIZE,
+            ],
+        );
+        let printed: String = output.split_whitespace().collect();
+        assert!(
+            printed.contains(&self.hash(new_challenge)),
+            "verify_transform_constrained printed another hash"
+        );
+        self.assert_chained(response, new_challenge);
+    }
+
+    /// Contributes to the params `old` and returns the hash of the contribution.
+    fn contribute_phase2(&self, old: &str, new: &str, entropy: &str) -> String {
+        let output = self.phase2("contribute", &[old, new, entropy]);
+        let hash = output
+            .lines()
+            .find_map(|line| line.strip_prefix("Contribution hash: 0x"))
+            .expect("contribute didn't print the hash of the contribution")
+            .to_string();
+        assert_eq!(hash.len(), 128);
+        hash
+    }
+}
+
+#[test]
+fn test_full_ceremony() {
+    let ceremony = Ceremony::new();
+
+    // Phase 1: two contributions and a beacon
+    ceremony.phase1(
+        "new_constrained",
+        &["challenge1", CIRCUIT_POWER, BATCH_SIZE],
+    );
+    assert_eq!(ceremony.hash("challenge1"), INITIAL_CHALLENGE_HASH);
+
+    ceremony.contribute_phase1(
+        "challenge1",
+        "response1",
+        "challenge2",
+        "first contributor\n",
+    );
+    ceremony.contribute_phase1(
+        "challenge2",
+        "response2",
+        "challenge3",
+        "second contributor\n",
+    );
+
+    ceremony.phase1(
+        "beacon_constrained",
+        &[
+            "challenge3",
+            "response3",
+            CIRCUIT_POWER,
+            BATCH_SIZE,
+            BEACON_HASH,
+            "10",
+        ],
+    );
+    ceremony.assert_chained("challenge3", "response3");
+    ceremony.verify_phase1("challenge3", "response3", "challenge4");
+
+    ceremony.phase1("prepare_phase2", &["response3", CIRCUIT_POWER, BATCH_SIZE]);
+    for power in 0..=3 {
+        ceremony.read(&format!("phase1radix2m{}", power));
+    }
+
+    // Phase 2: two contributions to the params of a circuit
+    ceremony.write("circuit.json", CIRCUIT);
+    ceremony.write("witness.json", WITNESS);
+
+    ceremony.phase2("new", &["circuit.json", "circuit1.params", "./"]);
+    ceremony.phase2("verify_initial", &["circuit.json", "circuit1.params", "./"]);
+
+    let first =
+        ceremony.contribute_phase2("circuit1.params", "circuit2.params", "first contributor");
+    ceremony.phase2(
+        "verify_contribution",
+        &["circuit.json", "circuit1.params", "circuit2.params", "./"],
+    );
+
+    let second =
+        ceremony.contribute_phase2("circuit2.params", "circuit3.params", "second contributor");
+    ceremony.phase2(
+        "verify_contribution",
+        &[
+            "circuit.json",
+            "circuit2.params",
+            "circuit3.params",
+            "./",
+            "--smoke-test",
+            "witness.json",
+        ],
+    );
+    ceremony.phase2("verify_h_query", &["circuit3.params", "./"]);
+
+    // Both contributors find their contribution in the final params
+    for (hash, statement) in [(first, "first.statement"), (second, "second.statement")].iter() {
+        ceremony.phase2("prove_inclusion", &["circuit3.params", hash, statement]);
+        ceremony.phase2("verify_inclusion", &["circuit3.params", statement]);
+    }
+
+    ceremony.phase2(
+        "prove",
+        &[
+            "circuit.json",
+            "witness.json",
+            "circuit3.params",
+            "proof.json",
+            "public.json",
+        ],
+    );
+    assert_eq!(
+        String::from_utf8(ceremony.read("public.json"))
+            .unwrap()
+            .trim(),
+        r#"["33"]"#
+    );
+}
@@ -0,0 +1,355 @@
+//! The checks of a contribution on points that are already decoded: ratios, pairs merged out
+//! of powers, and the proofs of knowledge of a public key. Nothing here reads files, spawns
+//! threads or records profiling, and nothing allocates, so another verifier, for example one
+//! compiled to WebAssembly, can embed the checks with only the curve, BLAKE2b and the RNG that
+//! hashes to G2. `powersoftau` builds on these functions, so both verify the same way.
+//!
+//! Unlike `powersoftau::utils::merge_pairs`, merging takes the randomness from the caller and computes the
+//! multiexponentiation serially.
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, ByteOrder};
+use pairing::ff::{Field, PrimeField};
+use pairing::{CurveAffine, CurveProjective, Engine};
+use rand::chacha::ChaChaRng;
+use rand::{Rand, Rng, SeedableRng};
+
+/// Personalization of the proof of knowledge of tau, see `compute_g2_s`.
+pub const TAU_PERSONALIZATION: u8 = 0;
+/// Personalization of the proof of knowledge of alpha.
+pub const ALPHA_PERSONALIZATION: u8 = 1;
+/// Personalization of the proof of knowledge of beta.
+pub const BETA_PERSONALIZATION: u8 = 2;
+
+/// Checks if pairs have the same ratio.
+/// Under the hood uses pairing to check
+/// x1/x2 = y1/y2 => x1*y2 = x2*y1
+pub fn same_ratio<E: Engine, G1: CurveAffine<Engine = E, Scalar = E::Fr>>(
+    g1: (G1, G1),
+    g2: (G1::Pair, G1::Pair),
+) -> bool {
+    if g1.0.is_zero() || g1.1.is_zero() || g2.0.is_zero() || g2.1.is_zero() {
+        return false;
+    }
+    g1.0.pairing_with(&g2.1) == g1.1.pairing_with(&g2.0)
+}
+
+/// Construct a single pair (s, s^x) out of pairs (v1[i], v2[i]) that are all expected to
+/// have the ratio x, with a linear combination drawn from `rng`.
+pub fn merge_pairs_with<R, E, G>(rng: &mut R, v1: &[G], v2: &[G]) -> (G, G)
+where
+    R: Rng,
+    E: Engine,
+    G: CurveAffine<Engine = E, Scalar = E::Fr>,
+{
+    assert_eq!(v1.len(), v2.len());
+
+    let mut s = G::Projective::zero();
+    let mut sx = G::Projective::zero();
+    for (a, b) in v1.iter().zip(v2.iter()) {
+        let r = G::Scalar::rand(rng).into_repr();
+        s.add_assign(&a.mul(r));
+        sx.add_assign(&b.mul(r));
+    }
+
+    (s.into_affine(), sx.into_affine())
+}
+
+/// Construct a single pair (s, s^x) for a vector of
+/// the form [1, x, x^2, x^3, ...], with randomness from `rng`.
+pub fn power_pairs_with<R, E, G>(rng: &mut R, v: &[G]) -> (G, G)
+where
+    R: Rng,
+    E: Engine,
+    G: CurveAffine<Engine = E, Scalar = E::Fr>,
+{
+    merge_pairs_with::<_, E, _>(rng, &v[0..(v.len() - 1)], &v[1..])
+}
+
+/// Hashes to G1 using the first 32 bytes of `digest`, like `hash_to_g2`.
+pub fn hash_to_g1<E: Engine>(digest: &[u8]) -> E::G1 {
+    assert!(digest.len() >= 32);
+
+    let mut seed = [0u32; 8];
+    BigEndian::read_u32_into(&digest[0..32], &mut seed);
+
+    ChaChaRng::from_seed(&seed).gen()
+}
+
+/// Hashes to G2 using the first 32 bytes of `digest`. Panics if `digest` is less
+/// than 32 bytes. The input must be random.
+pub fn hash_to_g2<E: Engine>(digest: &[u8]) -> E::G2 {
+    assert!(digest.len() >= 32);
+
+    let mut seed = [0u32; 8];
+    BigEndian::read_u32_into(&digest[0..32], &mut seed);
+
+    ChaChaRng::from_seed(&seed).gen()
+}
+
+/// The point in G2 that the proof of knowledge of a secret x, given as (s, s^x) in G1, is
+/// checked against, bound to the transcript `digest`.
+pub fn compute_g2_s<E: Engine>(
+    digest: &[u8],
+    g1_s: &E::G1Affine,
+    g1_s_x: &E::G1Affine,
+    personalization: u8,
+) -> E::G2Affine {
+    let mut h = Blake2b::default();
+    h.input([personalization]);
+    h.input(digest);
+    h.input(g1_s.into_uncompressed().as_ref());
+    h.input(g1_s_x.into_uncompressed().as_ref());
+
+    hash_to_g2::<E>(h.result().as_ref()).into_affine()
+}
+
+/// Checks the proof of knowledge of a secret x: `g1` is (s, s^x) and `g2_x` is H^x, where H
+/// is `compute_g2_s` of `g1` with `digest` and `personalization`.
+pub fn proves_knowledge<E: Engine>(
+    g1: (E::G1Affine, E::G1Affine),
+    g2_x: E::G2Affine,
+    digest: &[u8],
+    personalization: u8,
+) -> bool {
+    let g2_s = compute_g2_s::<E>(digest, &g1.0, &g1.1, personalization);
+    same_ratio(g1, (g2_s, g2_x))
+}
+
+/// Pairs in G1 and in G2 that must all have the ratio of tau, checked at once: each pair is
+/// multiplied by fresh randomness and summed into a single pair per group, and both sums are
+/// checked with one multi-pairing, which has a single final exponentiation. The powers of a
+/// whole response are checked with four Miller loops this way, instead of two pairings for
+/// every element type of every chunk.
+pub struct RatioBatch<E: Engine> {
+    /// (1, tau) in G1, which the pairs in G2 are checked against
+    tau_g1: (E::G1Affine, E::G1Affine),
+    /// (1, tau) in G2, which the pairs in G1 are checked against
+    tau_g2: (E::G2Affine, E::G2Affine),
+    g1: (E::G1, E::G1),
+    g2: (E::G2, E::G2),
+    pairs: usize,
+    /// Whether a pair had a point at infinity, which `same_ratio` rejects
+    invalid: bool,
+}
+
+impl<E: Engine> RatioBatch<E> {
+    pub fn new(tau_g1: (E::G1Affine, E::G1Affine), tau_g2: (E::G2Affine, E::G2Affine)) -> Self {
+        RatioBatch {
+            tau_g1,
+            tau_g2,
+            g1: (E::G1::zero(), E::G1::zero()),
+            g2: (E::G2::zero(), E::G2::zero()),
+            pairs: 0,
+            invalid: false,
+        }
+    }
+
+    /// Adds a pair in G1 that must have the ratio of tau.
+    pub fn add_g1<R: Rng>(&mut self, rng: &mut R, pair: (E::G1Affine, E::G1Affine)) {
+        self.invalid |= pair.0.is_zero() || pair.1.is_zero();
+        let r = E::Fr::rand(rng).into_repr();
+        self.g1.0.add_assign(&pair.0.mul(r));
+        self.g1.1.add_assign(&pair.1.mul(r));
+        self.pairs += 1;
+    }
+
+    /// Adds a pair in G2 that must have the ratio of tau.
+    pub fn add_g2<R: Rng>(&mut self, rng: &mut R, pair: (E::G2Affine, E::G2Affine)) {
+        self.invalid |= pair.0.is_zero() || pair.1.is_zero();
+        let r = E::Fr::rand(rng).into_repr();
+        self.g2.0.add_assign(&pair.0.mul(r));
+        self.g2.1.add_assign(&pair.1.mul(r));
+        self.pairs += 1;
+    }
+
+    /// Number of pairs added since the last check.
+    pub fn len(&self) -> usize {
+        self.pairs
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs == 0
+    }
+
+    /// Checks every pair added since the last check, and empties the batch.
+    pub fn check<R: Rng>(&mut self, rng: &mut R) -> bool {
+        let (g1, g2, invalid) = (self.g1, self.g2, self.invalid);
+        *self = Self::new(self.tau_g1, self.tau_g2);
+
+        if invalid {
+            return false;
+        }
+        let (tau_g1, tau_g2) = (self.tau_g1, self.tau_g2);
+        if tau_g1.0.is_zero() || tau_g1.1.is_zero() || tau_g2.0.is_zero() || tau_g2.1.is_zero() {
+            return false;
+        }
+
+        // e(s, tau) = e(s^tau, 1) in G1 and e(1, t^tau) = e(tau, t) in G2, the latter
+        // multiplied by r so that an error in one group can't make up for one in the other:
+        // e(s, tau) e(-s^tau, 1) e(r, t^tau) e(-r tau, t) = 1
+        let r = E::Fr::rand(rng).into_repr();
+        let mut s_tau = g1.1.into_affine();
+        s_tau.negate();
+        let mut r_tau = tau_g1.1.mul(r).into_affine();
+        r_tau.negate();
+        let g1_points = [
+            g1.0.into_affine().prepare(),
+            s_tau.prepare(),
+            tau_g1.0.mul(r).into_affine().prepare(),
+            r_tau.prepare(),
+        ];
+        let g2_points = [
+            tau_g2.1.prepare(),
+            tau_g2.0.prepare(),
+            g2.1.into_affine().prepare(),
+            g2.0.into_affine().prepare(),
+        ];
+        let terms = [
+            (&g1_points[0], &g2_points[0]),
+            (&g1_points[1], &g2_points[1]),
+            (&g1_points[2], &g2_points[2]),
+            (&g1_points[3], &g2_points[3]),
+        ];
+
+        E::final_exponentiation(&E::miller_loop(terms.iter())) == Some(E::Fqk::one())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pairing::bn256::{Bn256, Fr, G1Affine, G2Affine};
+    use pairing::ff::Field;
+    use rand::thread_rng;
+
+    #[test]
+    fn test_hash_to_g2_bn256() {
+        assert!(
+            hash_to_g2::<Bn256>(&[
+                1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
+                24, 25, 26, 27, 28, 29, 30, 31, 32, 33
+            ]) == hash_to_g2::<Bn256>(&[
+                1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
+                24, 25, 26, 27, 28, 29, 30, 31, 32, 34
+            ])
+        );
+
+        assert!(
+            hash_to_g2::<Bn256>(&[
+                1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
+                24, 25, 26, 27, 28, 29, 30, 31, 32
+            ]) != hash_to_g2::<Bn256>(&[
+                1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
+                24, 25, 26, 27, 28, 29, 30, 31, 33
+            ])
+        );
+    }
+
+    #[test]
+    fn test_power_pairs_with() {
+        let rng = &mut thread_rng();
+
+        let mut v = vec![];
+        let x = Fr::rand(rng);
+        let mut acc = Fr::one();
+        for _ in 0..20 {
+            v.push(G1Affine::one().mul(acc).into_affine());
+            acc.mul_assign(&x);
+        }
+
+        let gx = G2Affine::one().mul(x).into_affine();
+
+        assert!(same_ratio(power_pairs_with(rng, &v), (G2Affine::one(), gx)));
+
+        v[1] = v[1].mul(Fr::rand(rng)).into_affine();
+
+        assert!(!same_ratio(
+            power_pairs_with(rng, &v),
+            (G2Affine::one(), gx)
+        ));
+    }
+
+    #[test]
+    fn test_ratio_batch() {
+        let rng = &mut thread_rng();
+        let x = Fr::rand(rng);
+        let tau_g1 = (G1Affine::one(), G1Affine::one().mul(x).into_affine());
+        let tau_g2 = (G2Affine::one(), G2Affine::one().mul(x).into_affine());
+        let g1_pair = || {
+            let p = G1Affine::one()
+                .mul(Fr::rand(&mut thread_rng()))
+                .into_affine();
+            (p, p.mul(x).into_affine())
+        };
+        let g2_pair = || {
+            let p = G2Affine::one()
+                .mul(Fr::rand(&mut thread_rng()))
+                .into_affine();
+            (p, p.mul(x).into_affine())
+        };
+
+        let mut batch = RatioBatch::<Bn256>::new(tau_g1, tau_g2);
+        assert!(batch.check(rng));
+        for _ in 0..3 {
+            batch.add_g1(rng, g1_pair());
+            batch.add_g2(rng, g2_pair());
+        }
+        assert_eq!(batch.len(), 6);
+        assert!(batch.check(rng));
+        assert!(batch.is_empty());
+
+        // A single wrong pair in either group fails the whole batch
+        let (p, _) = g1_pair();
+        batch.add_g1(rng, g1_pair());
+        batch.add_g1(rng, (p, p));
+        assert!(!batch.check(rng));
+        let (q, _) = g2_pair();
+        batch.add_g1(rng, g1_pair());
+        batch.add_g2(rng, (q, q));
+        assert!(!batch.check(rng));
+        // As do points at infinity, like in `same_ratio`
+        batch.add_g1(rng, (G1Affine::zero(), G1Affine::zero()));
+        assert!(!batch.check(rng));
+        // and the batch starts over after a failed check
+        batch.add_g1(rng, g1_pair());
+        assert!(batch.check(rng));
+    }
+
+    #[test]
+    fn test_proves_knowledge() {
+        let rng = &mut thread_rng();
+        let digest = [7u8; 64];
+        // The proof of a secret x for `personalization`, as in a public key
+        let prove = |x: Fr, personalization: u8| {
+            let s = G1Affine::one()
+                .mul(Fr::rand(&mut thread_rng()))
+                .into_affine();
+            let s_x = s.mul(x).into_affine();
+            let g2_s = compute_g2_s::<Bn256>(&digest, &s, &s_x, personalization);
+            ((s, s_x), g2_s.mul(x).into_affine())
+        };
+        let (tau, alpha) = (Fr::rand(rng), Fr::rand(rng));
+
+        let (tau_g1, tau_g2) = prove(tau, TAU_PERSONALIZATION);
+        assert!(proves_knowledge::<Bn256>(
+            tau_g1,
+            tau_g2,
+            &digest,
+            TAU_PERSONALIZATION
+        ));
+        // The personalization separates the proofs of the secrets
+        let (alpha_g1, alpha_g2) = prove(alpha, ALPHA_PERSONALIZATION);
+        assert!(!proves_knowledge::<Bn256>(
+            alpha_g1,
+            alpha_g2,
+            &digest,
+            BETA_PERSONALIZATION
+        ));
+        assert!(!proves_knowledge::<Bn256>(
+            alpha_g1,
+            alpha_g2,
+            &[8u8; 64],
+            ALPHA_PERSONALIZATION
+        ));
+    }
+}